@@ -0,0 +1,4 @@
+-- Enrichment runs in the background after upload; existing rows were enriched synchronously
+ALTER TABLE books ADD COLUMN enrichment_status TEXT NOT NULL DEFAULT 'done';
+
+CREATE INDEX IF NOT EXISTS idx_books_enrichment_status ON books(enrichment_status);
@@ -0,0 +1,4 @@
+-- Size of the stored EPUB; NULL until backfilled for books imported before tracking
+ALTER TABLE books ADD COLUMN file_size_bytes INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_books_file_size_bytes ON books(file_size_bytes);
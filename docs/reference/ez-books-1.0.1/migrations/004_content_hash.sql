@@ -0,0 +1,4 @@
+-- SHA-256 of the stored EPUB bytes, used to skip re-importing identical files
+ALTER TABLE books ADD COLUMN content_hash TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_books_content_hash ON books(content_hash);
@@ -0,0 +1,9 @@
+-- One row per book the reader has opened; books without a row are unread
+CREATE TABLE IF NOT EXISTS reading_progress (
+    book_id TEXT PRIMARY KEY NOT NULL,
+    opened_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_reading_progress_updated_at ON reading_progress(updated_at);
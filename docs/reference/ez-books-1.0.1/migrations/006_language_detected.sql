@@ -0,0 +1,2 @@
+-- 1 when the language was guessed from the book's text rather than declared in the EPUB
+ALTER TABLE books ADD COLUMN language_detected INTEGER NOT NULL DEFAULT 0;
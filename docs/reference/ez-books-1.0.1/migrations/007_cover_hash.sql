@@ -0,0 +1,2 @@
+-- SHA-256 of the stored cover, used to version cover URLs so they can be cached forever
+ALTER TABLE books ADD COLUMN cover_hash TEXT;
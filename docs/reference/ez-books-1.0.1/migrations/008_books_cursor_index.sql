@@ -0,0 +1,2 @@
+-- Keyset pagination on /api/books walks books by (created_at, id)
+CREATE INDEX IF NOT EXISTS idx_books_created_at_id ON books(created_at, id);
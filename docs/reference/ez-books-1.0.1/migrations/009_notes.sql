@@ -0,0 +1,2 @@
+-- The reader's private plain-text notes, separate from the publisher description
+ALTER TABLE books ADD COLUMN notes TEXT;
@@ -0,0 +1,11 @@
+-- Upload Idempotency-Key values (stored as SHA-256) and the response they produced.
+-- A row without a response is an upload still in progress.
+CREATE TABLE IF NOT EXISTS upload_idempotency_keys (
+    key_hash TEXT PRIMARY KEY NOT NULL,
+    book_id TEXT,
+    status INTEGER,
+    response TEXT,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_upload_idempotency_keys_created_at ON upload_idempotency_keys(created_at);
@@ -0,0 +1,2 @@
+-- Content type of the stored cover; NULL for covers stored before it was recorded (JPEG)
+ALTER TABLE books ADD COLUMN cover_mime TEXT;
@@ -0,0 +1,12 @@
+-- When a book was last deleted, so a library's Last-Modified also moves on deletions
+CREATE TABLE IF NOT EXISTS library_changes (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    last_deleted_at INTEGER NOT NULL
+);
+
+CREATE TRIGGER IF NOT EXISTS books_record_deletion
+AFTER DELETE ON books
+BEGIN
+    INSERT OR REPLACE INTO library_changes (id, last_deleted_at)
+    VALUES (1, CAST(strftime('%s', 'now') AS INTEGER));
+END;
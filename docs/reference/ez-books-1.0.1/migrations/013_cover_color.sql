@@ -0,0 +1,2 @@
+-- Average cover color as `#rrggbb`, shown while the cover loads; NULL until computed
+ALTER TABLE books ADD COLUMN cover_color TEXT;
@@ -0,0 +1,2 @@
+-- Set when a stored cover fails to decode on read, so integrity tooling can list it
+ALTER TABLE books ADD COLUMN cover_corrupt INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- EPUB3 media overlays (SMIL read-aloud narration) found at import
+ALTER TABLE books ADD COLUMN has_audio_narration BOOLEAN NOT NULL DEFAULT 0;
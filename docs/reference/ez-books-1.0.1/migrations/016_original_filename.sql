@@ -0,0 +1,2 @@
+-- Name the EPUB had when it was uploaded or imported; NULL for books stored before it was kept
+ALTER TABLE books ADD COLUMN original_filename TEXT;
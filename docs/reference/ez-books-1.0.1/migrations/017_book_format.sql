@@ -0,0 +1,2 @@
+-- File format of the stored book; every book imported so far is an EPUB
+ALTER TABLE books ADD COLUMN format TEXT NOT NULL DEFAULT 'epub';
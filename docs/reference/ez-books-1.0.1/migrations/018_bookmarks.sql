@@ -0,0 +1,12 @@
+-- Named positions saved in a book; unlike reading_progress a book can have many
+CREATE TABLE IF NOT EXISTS bookmarks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    book_id TEXT NOT NULL,
+    chapter_index INTEGER NOT NULL,
+    scroll_fraction REAL NOT NULL DEFAULT 0,
+    label TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_bookmarks_book_id ON bookmarks(book_id);
@@ -0,0 +1,20 @@
+-- Named, ordered reading lists; a book can be in any number of them
+CREATE TABLE IF NOT EXISTS collections (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS collection_books (
+    collection_id INTEGER NOT NULL,
+    book_id TEXT NOT NULL,
+    -- 0-based position in the reading list
+    ordinal INTEGER NOT NULL,
+    added_at INTEGER NOT NULL,
+    PRIMARY KEY (collection_id, book_id),
+    FOREIGN KEY (collection_id) REFERENCES collections(id) ON DELETE CASCADE,
+    FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_collection_books_book_id ON collection_books(book_id);
@@ -0,0 +1,23 @@
+-- Full-text index of book content, one row per chapter with text; filled only when
+-- CONTENT_SEARCH is enabled
+CREATE VIRTUAL TABLE IF NOT EXISTS book_content USING fts5(
+    book_id UNINDEXED,
+    -- Spine index of the chapter
+    chapter_index UNINDEXED,
+    text,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+-- Books whose content is in book_content, so backfills can skip them
+CREATE TABLE IF NOT EXISTS book_content_indexed (
+    book_id TEXT PRIMARY KEY,
+    chapter_count INTEGER NOT NULL,
+    indexed_at INTEGER NOT NULL,
+    FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE
+);
+
+-- Virtual tables cannot have foreign keys
+CREATE TRIGGER IF NOT EXISTS book_content_delete_with_book AFTER DELETE ON books
+BEGIN
+    DELETE FROM book_content WHERE book_id = old.id;
+END;
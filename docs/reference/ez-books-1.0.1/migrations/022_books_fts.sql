@@ -0,0 +1,29 @@
+-- Ranked metadata search; kept in sync with books by the triggers below
+CREATE VIRTUAL TABLE IF NOT EXISTS books_fts USING fts5(
+    book_id UNINDEXED,
+    title,
+    author,
+    description,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO books_fts (book_id, title, author, description)
+SELECT id, title, author, description FROM books;
+
+CREATE TRIGGER IF NOT EXISTS books_fts_insert AFTER INSERT ON books
+BEGIN
+    INSERT INTO books_fts (book_id, title, author, description)
+    VALUES (new.id, new.title, new.author, new.description);
+END;
+
+CREATE TRIGGER IF NOT EXISTS books_fts_update AFTER UPDATE OF title, author, description ON books
+BEGIN
+    DELETE FROM books_fts WHERE book_id = old.id;
+    INSERT INTO books_fts (book_id, title, author, description)
+    VALUES (new.id, new.title, new.author, new.description);
+END;
+
+CREATE TRIGGER IF NOT EXISTS books_fts_delete AFTER DELETE ON books
+BEGIN
+    DELETE FROM books_fts WHERE book_id = old.id;
+END;
@@ -0,0 +1,2 @@
+-- 1 when the description was taken from the book's first pages because the EPUB declared none
+ALTER TABLE books ADD COLUMN description_from_content INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Package version declared by the OPF, e.g. "2.0" or "3.0"; NULL when it could not be read
+ALTER TABLE books ADD COLUMN epub_version TEXT;
@@ -0,0 +1,4 @@
+-- How the book can be read on Internet Archive, from the OpenLibrary Read API: the scan's
+-- status ("full access", "lendable" or "checked out") and its web page; NULL when unknown
+ALTER TABLE books ADD COLUMN read_status TEXT;
+ALTER TABLE books ADD COLUMN read_url TEXT;
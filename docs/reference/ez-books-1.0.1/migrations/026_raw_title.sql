@@ -0,0 +1,2 @@
+-- The title the EPUB declared, when it was a file name and the library stores a tidied one
+ALTER TABLE books ADD COLUMN raw_title TEXT;
@@ -0,0 +1,2 @@
+-- When the reader marked the book finished; NULL while it is still being read
+ALTER TABLE reading_progress ADD COLUMN finished_at INTEGER;
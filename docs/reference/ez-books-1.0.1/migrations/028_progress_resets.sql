@@ -0,0 +1,8 @@
+-- Clearing a book's reading progress moves the library's Last-Modified like a deletion,
+-- as the gallery's finished badges change with it
+CREATE TRIGGER IF NOT EXISTS reading_progress_record_reset
+AFTER DELETE ON reading_progress
+BEGIN
+    INSERT OR REPLACE INTO library_changes (id, last_deleted_at)
+    VALUES (1, CAST(strftime('%s', 'now') AS INTEGER));
+END;
@@ -0,0 +1,217 @@
+use crate::annotation_repository::Annotation;
+use crate::html_templates::escape_html;
+use crate::text_extraction::decode_entity;
+
+/// A non-whitespace character of the chapter text
+struct TextChar {
+    c: char,
+    /// Byte range in the HTML; a decoded entity covers the whole reference
+    start: usize,
+    end: usize,
+    /// Characters with a tag between them are in different runs
+    run: usize,
+}
+
+/// Wraps the text of the `annotations` on spine item `chapter` in `<mark>` elements, one
+/// per text run so the markup stays well-formed. Quotes no longer in `html` are skipped.
+pub fn highlight_annotations(html: &str, chapter: usize, annotations: &[Annotation]) -> String {
+    let text = text_chars(html);
+    // (position, closing tags first, tag)
+    let mut insertions: Vec<(usize, u8, String)> = Vec::new();
+
+    for annotation in annotations
+        .iter()
+        .filter(|annotation| annotation.chapter_index as usize == chapter)
+    {
+        let Some((first, last)) = locate(&text, annotation) else {
+            continue;
+        };
+        let open = mark_tag(annotation);
+        let mut run_start = first;
+        for i in first..last {
+            if i + 1 == last || text[i + 1].run != text[i].run {
+                insertions.push((text[run_start].start, 1, open.clone()));
+                insertions.push((text[i].end, 0, "</mark>".to_string()));
+                run_start = i + 1;
+            }
+        }
+    }
+    if insertions.is_empty() {
+        return html.to_string();
+    }
+    insertions.sort_by_key(|(position, order, _)| (*position, *order));
+
+    let mut highlighted = String::with_capacity(html.len() + insertions.len() * 32);
+    let mut copied = 0;
+    for (position, _, tag) in insertions {
+        highlighted.push_str(&html[copied..position]);
+        highlighted.push_str(&tag);
+        copied = position;
+    }
+    highlighted.push_str(&html[copied..]);
+    highlighted
+}
+
+fn text_chars(html: &str) -> Vec<TextChar> {
+    let mut text = Vec::new();
+    let mut run = 0;
+    let mut chars = html.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let (c, end) = match c {
+            '<' => {
+                for (_, c) in chars.by_ref() {
+                    if c == '>' {
+                        break;
+                    }
+                }
+                run += 1;
+                continue;
+            }
+            '&' => {
+                let rest = &html[start..];
+                let entity = rest
+                    .find(';')
+                    .filter(|end| *end <= 10)
+                    .and_then(|end| decode_entity(&rest[1..end]).map(|c| (c, start + end + 1)));
+                match entity {
+                    Some((decoded, end)) => {
+                        while chars.peek().map_or(false, |(i, _)| *i < end) {
+                            chars.next();
+                        }
+                        (decoded, end)
+                    }
+                    None => ('&', start + 1),
+                }
+            }
+            c => (c, start + c.len_utf8()),
+        };
+        if !c.is_whitespace() {
+            text.push(TextChar { c, start, end, run });
+        }
+    }
+
+    text
+}
+
+fn significant_chars(text: &str) -> Vec<char> {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Range of `text` holding the quote, preferring the occurrence whose surroundings match
+/// most of the prefix and suffix; the first one on ties
+fn locate(text: &[TextChar], annotation: &Annotation) -> Option<(usize, usize)> {
+    let quote = significant_chars(&annotation.quote);
+    if quote.is_empty() || quote.len() > text.len() {
+        return None;
+    }
+    let prefix = significant_chars(&annotation.prefix);
+    let suffix = significant_chars(&annotation.suffix);
+
+    let mut best: Option<(usize, usize)> = None;
+    for start in 0..=text.len() - quote.len() {
+        let end = start + quote.len();
+        if !quote.iter().zip(&text[start..end]).all(|(q, t)| *q == t.c) {
+            continue;
+        }
+        let before = prefix
+            .iter()
+            .rev()
+            .zip(text[..start].iter().rev())
+            .take_while(|(p, t)| **p == t.c)
+            .count();
+        let after = suffix
+            .iter()
+            .zip(&text[end..])
+            .take_while(|(s, t)| **s == t.c)
+            .count();
+        let score = before + after;
+        if best.map_or(true, |(best_score, _)| score > best_score) {
+            best = Some((score, start));
+        }
+    }
+
+    best.map(|(_, start)| (start, start + quote.len()))
+}
+
+fn mark_tag(annotation: &Annotation) -> String {
+    let title = annotation
+        .note
+        .as_deref()
+        .map(|note| format!(r#" title="{}""#, escape_html(note)))
+        .unwrap_or_default();
+    format!(
+        r#"<mark class="highlight highlight-{}" data-annotation-id="{}"{}>"#,
+        annotation.color.as_str(),
+        annotation.id,
+        title
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotation_repository::HighlightColor;
+
+    fn annotation(id: i64, quote: &str, prefix: &str, suffix: &str) -> Annotation {
+        Annotation {
+            id,
+            book_id: "book".to_string(),
+            chapter_index: 0,
+            quote: quote.to_string(),
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+            color: HighlightColor::Green,
+            note: None,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn should_match_quote_despite_whitespace_entities_and_markup() {
+        // Given: A quote selected as plain text, found reflowed and emphasised in the HTML
+        let html = "<p>It was a <em>dark</em>\n   and&nbsp;stormy night.</p>";
+        let quote = annotation(7, "a dark and stormy", "", "");
+
+        // When: Highlighting it
+        let highlighted = highlight_annotations(html, 0, &[quote]);
+
+        // Then: Each text run is wrapped on its own so the markup stays balanced
+        let mark = r#"<mark class="highlight highlight-green" data-annotation-id="7">"#;
+        assert_eq!(
+            highlighted,
+            format!(
+                "<p>It was {m}a</mark> <em>{m}dark</mark></em>\n   {m}and&nbsp;stormy</mark> night.</p>",
+                m = mark
+            )
+        );
+    }
+
+    #[test]
+    fn should_pick_repeated_quote_by_its_context() {
+        // Given: The same word twice, annotated with the text around the second one
+        let html = "<p>Never again. She said never again, and meant it.</p>";
+        let mut second = annotation(1, "again", "said never ", ", and meant");
+        second.note = Some("Her \"promise\"".to_string());
+
+        // When: Highlighting it
+        let highlighted = highlight_annotations(html, 0, &[second]);
+
+        // Then: Only the second occurrence is marked, with the escaped note as title
+        assert!(highlighted.starts_with("<p>Never again. She said never <mark"));
+        assert!(highlighted.contains(r#"title="Her &quot;promise&quot;">again</mark>, and"#));
+    }
+
+    #[test]
+    fn should_leave_html_alone_when_quote_is_gone() {
+        // Given: An annotation whose text no longer appears, and one on another chapter
+        let html = "<p>Revised text.</p>";
+        let stale = annotation(1, "original text", "", "");
+        let mut elsewhere = annotation(2, "Revised", "", "");
+        elsewhere.chapter_index = 3;
+
+        // When/Then: Nothing is highlighted
+        assert_eq!(highlight_annotations(html, 0, &[stale, elsewhere]), html);
+    }
+}
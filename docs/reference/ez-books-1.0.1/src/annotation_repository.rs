@@ -0,0 +1,244 @@
+use crate::book_model::current_timestamp;
+use crate::book_update::{AnnotationUpdate, NewAnnotation};
+use crate::database_connection::DatabasePool;
+use crate::error::{EzBooksError, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+
+/// A highlighted passage, found again in its chapter by `annotation_anchor`
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct Annotation {
+    pub id: i64,
+    pub book_id: String,
+    pub chapter_index: u32,
+    /// The highlighted text as the reader selected it
+    pub quote: String,
+    /// Text just before the quote, telling repeated quotes apart
+    pub prefix: String,
+    /// Text just after the quote
+    pub suffix: String,
+    pub color: HighlightColor,
+    /// The reader's plain-text note; never HTML
+    pub note: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum HighlightColor {
+    #[default]
+    Yellow,
+    Green,
+    Blue,
+    Pink,
+}
+
+impl HighlightColor {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Yellow => "yellow",
+            Self::Green => "green",
+            Self::Blue => "blue",
+            Self::Pink => "pink",
+        }
+    }
+}
+
+const ANNOTATION_COLUMNS: &str =
+    "id, book_id, chapter_index, quote, prefix, suffix, color, note, created_at, updated_at";
+
+/// Saves an annotation; call `NewAnnotation::validate` first
+#[instrument(skip(pool, new_annotation))]
+pub async fn add_annotation(
+    pool: &DatabasePool,
+    book_id: &str,
+    new_annotation: &NewAnnotation,
+) -> Result<Annotation> {
+    let now = current_timestamp();
+    let note = new_annotation
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|note| !note.is_empty());
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO annotations
+            (book_id, chapter_index, quote, prefix, suffix, color, note, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(book_id)
+    .bind(new_annotation.chapter_index)
+    .bind(&new_annotation.quote)
+    .bind(&new_annotation.prefix)
+    .bind(&new_annotation.suffix)
+    .bind(new_annotation.color)
+    .bind(note)
+    .bind(now)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    let id = result.last_insert_rowid();
+    info!(book_id = %book_id, annotation_id = id, "Annotation added");
+    find_annotation(pool, book_id, id).await
+}
+
+/// Annotations of one book in reading order
+#[instrument(skip(pool))]
+pub async fn list_annotations(pool: &DatabasePool, book_id: &str) -> Result<Vec<Annotation>> {
+    let annotations = sqlx::query_as::<_, Annotation>(&format!(
+        "SELECT {} FROM annotations WHERE book_id = ? ORDER BY chapter_index, id",
+        ANNOTATION_COLUMNS
+    ))
+    .bind(book_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(annotations)
+}
+
+/// Changes the color or note; fails with `AnnotationNotFound` unless it belongs to `book_id`
+#[instrument(skip(pool, update))]
+pub async fn update_annotation(
+    pool: &DatabasePool,
+    book_id: &str,
+    annotation_id: i64,
+    update: AnnotationUpdate,
+) -> Result<Annotation> {
+    let mut annotation = find_annotation(pool, book_id, annotation_id).await?;
+    update.apply_to(&mut annotation);
+    annotation.updated_at = current_timestamp();
+
+    sqlx::query("UPDATE annotations SET color = ?, note = ?, updated_at = ? WHERE id = ?")
+        .bind(annotation.color)
+        .bind(&annotation.note)
+        .bind(annotation.updated_at)
+        .bind(annotation_id)
+        .execute(pool)
+        .await?;
+
+    info!(book_id = %book_id, annotation_id, "Annotation updated");
+    Ok(annotation)
+}
+
+/// Removes an annotation; fails with `AnnotationNotFound` unless it belongs to `book_id`
+#[instrument(skip(pool))]
+pub async fn delete_annotation(
+    pool: &DatabasePool,
+    book_id: &str,
+    annotation_id: i64,
+) -> Result<()> {
+    let result = sqlx::query("DELETE FROM annotations WHERE id = ? AND book_id = ?")
+        .bind(annotation_id)
+        .bind(book_id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(not_found(book_id, annotation_id));
+    }
+
+    info!(book_id = %book_id, annotation_id, "Annotation deleted");
+    Ok(())
+}
+
+async fn find_annotation(
+    pool: &DatabasePool,
+    book_id: &str,
+    annotation_id: i64,
+) -> Result<Annotation> {
+    sqlx::query_as::<_, Annotation>(&format!(
+        "SELECT {} FROM annotations WHERE id = ? AND book_id = ?",
+        ANNOTATION_COLUMNS
+    ))
+    .bind(annotation_id)
+    .bind(book_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| not_found(book_id, annotation_id))
+}
+
+fn not_found(book_id: &str, annotation_id: i64) -> EzBooksError {
+    EzBooksError::AnnotationNotFound {
+        book_id: book_id.to_string(),
+        annotation_id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book_model::Book;
+    use crate::book_repository;
+    use crate::test_fixtures::setup_test_db;
+
+    async fn insert_book(pool: &DatabasePool) -> Book {
+        let book = Book::new("Annotated".to_string(), "/annotated.epub".to_string());
+        book_repository::insert(pool, &book).await.unwrap();
+        book
+    }
+
+    fn new_annotation(chapter_index: u32, quote: &str) -> NewAnnotation {
+        NewAnnotation {
+            chapter_index,
+            quote: quote.to_string(),
+            prefix: String::new(),
+            suffix: String::new(),
+            color: HighlightColor::default(),
+            note: Some("  ".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_add_update_and_delete_annotation() {
+        // Given: An annotation with a blank note
+        let (pool, _temp_dir) = setup_test_db().await;
+        let book = insert_book(&pool).await;
+        let added = add_annotation(&pool, &book.id, &new_annotation(2, "a passage"))
+            .await
+            .unwrap();
+
+        // When: Recoloring it with a note, then deleting it twice
+        let update = AnnotationUpdate {
+            color: Some(HighlightColor::Blue),
+            note: Some("Remember this".to_string()),
+        };
+        let updated = update_annotation(&pool, &book.id, added.id, update)
+            .await
+            .unwrap();
+        let listed = list_annotations(&pool, &book.id).await.unwrap();
+        let deleted = delete_annotation(&pool, &book.id, added.id).await;
+        let again = delete_annotation(&pool, &book.id, added.id).await;
+
+        // Then: The blank note was dropped, the update stored and the deletion happens once
+        assert_eq!(added.note, None);
+        assert_eq!(added.color, HighlightColor::Yellow);
+        assert_eq!(updated.color, HighlightColor::Blue);
+        assert_eq!(listed, vec![updated]);
+        assert!(deleted.is_ok());
+        assert!(matches!(
+            again,
+            Err(EzBooksError::AnnotationNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_delete_annotations_with_their_book() {
+        // Given: An annotated book
+        let (pool, _temp_dir) = setup_test_db().await;
+        let book = insert_book(&pool).await;
+        add_annotation(&pool, &book.id, &new_annotation(0, "quote"))
+            .await
+            .unwrap();
+
+        // When: Deleting the book
+        book_repository::delete(&pool, &book.id).await.unwrap();
+
+        // Then: Its annotations are gone too
+        assert!(list_annotations(&pool, &book.id).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::book_model::Book;
+use crate::error::{EzBooksError, Result};
+use serde::Serialize;
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const MAX_FILENAME_CHARS: usize = 100;
+
+/// Contents of `metadata.json` in a bundle: the book's fields plus its subjects
+#[derive(Serialize)]
+struct BundleMetadata<'a> {
+    #[serde(flatten)]
+    book: &'a Book,
+    subjects: &'a [String],
+}
+
+/// Builds a ZIP holding the EPUB, the cover (if any) and `metadata.json`.
+///
+/// Entries are stored uncompressed: EPUBs and JPEGs are already compressed.
+pub fn build_bundle(
+    book: &Book,
+    subjects: &[String],
+    epub: &[u8],
+    cover: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    let metadata = serde_json::to_vec_pretty(&BundleMetadata { book, subjects })?;
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+    let mut entries = vec![
+        (format!("{}.epub", file_stem(&book.title)), epub),
+        ("metadata.json".to_string(), metadata.as_slice()),
+    ];
+    if let Some(cover) = cover {
+        entries.push(("cover.jpg".to_string(), cover));
+    }
+
+    for (name, data) in entries {
+        zip.start_file(name, stored).map_err(zip_error)?;
+        zip.write_all(data)?;
+    }
+
+    let cursor = zip.finish().map_err(zip_error)?;
+    Ok(cursor.into_inner())
+}
+
+/// `Content-Disposition` value naming the download after the book's title.
+///
+/// The plain `filename` is an ASCII fallback; `filename*` keeps non-ASCII titles intact.
+pub fn bundle_content_disposition(title: &str) -> String {
+    attachment_content_disposition(title, "zip")
+}
+
+/// `Content-Disposition` for the EPUB download: the original filename when one was kept,
+/// otherwise the title
+pub fn epub_content_disposition(book: &Book) -> String {
+    let stem = book
+        .original_filename
+        .as_deref()
+        .map(|name| strip_suffix_ignore_case(name, ".gz"))
+        .map(|name| strip_suffix_ignore_case(name, ".epub"))
+        .filter(|stem| !stem.trim().is_empty());
+    attachment_content_disposition(stem.unwrap_or(&book.title), "epub")
+}
+
+fn strip_suffix_ignore_case<'a>(name: &'a str, suffix: &str) -> &'a str {
+    let split = name.len().saturating_sub(suffix.len());
+    match (name.get(..split), name.get(split..)) {
+        (Some(stem), Some(tail)) if tail.eq_ignore_ascii_case(suffix) => stem,
+        _ => name,
+    }
+}
+
+/// `Content-Disposition` for downloading a file named `{title}.{extension}`
+pub fn attachment_content_disposition(title: &str, extension: &str) -> String {
+    let name = format!("{}.{}", title_for_filename(title), extension);
+    format!(
+        "attachment; filename=\"{}.{}\"; filename*=UTF-8''{}",
+        file_stem(title),
+        extension,
+        percent_encode(&name)
+    )
+}
+
+/// ASCII-only name safe for any filesystem, e.g. "Dune: Messiah" -> "Dune Messiah"
+fn file_stem(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                ' '
+            }
+        })
+        .collect();
+    let stem = cleaned
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches(|c: char| c == '.' || c == ' ')
+        .chars()
+        .take(MAX_FILENAME_CHARS)
+        .collect::<String>();
+
+    if stem.is_empty() {
+        "book".to_string()
+    } else {
+        stem
+    }
+}
+
+/// The title without path separators, control characters or leading/trailing dots
+fn title_for_filename(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, '/' | '\\'))
+        .take(MAX_FILENAME_CHARS)
+        .collect();
+    match cleaned.trim_matches(|c: char| c == '.' || c.is_whitespace()) {
+        "" => "book".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// RFC 5987 encoding: everything but unreserved ASCII becomes `%XX`
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+                (byte as char).to_string()
+            } else {
+                format!("%{:02X}", byte)
+            }
+        })
+        .collect()
+}
+
+fn zip_error(error: zip::result::ZipError) -> EzBooksError {
+    EzBooksError::FileStorage(format!("Failed to build bundle: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    fn read_entry(bundle: &[u8], name: &str) -> Option<Vec<u8>> {
+        let mut archive = ZipArchive::new(Cursor::new(bundle)).unwrap();
+        let mut entry = archive.by_name(name).ok()?;
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        Some(data)
+    }
+
+    #[test]
+    fn should_bundle_epub_cover_and_metadata() {
+        // Given: A book with subjects, EPUB bytes and a cover
+        let book = Book::new("Dune: Messiah".to_string(), "/dune.epub".to_string());
+        let subjects = vec!["Science Fiction".to_string()];
+
+        // When: Building the bundle
+        let bundle = build_bundle(&book, &subjects, b"epub-bytes", Some(b"jpeg-bytes")).unwrap();
+
+        // Then: All three entries should be present
+        assert_eq!(
+            read_entry(&bundle, "Dune Messiah.epub").unwrap(),
+            b"epub-bytes"
+        );
+        assert_eq!(read_entry(&bundle, "cover.jpg").unwrap(), b"jpeg-bytes");
+        let metadata: serde_json::Value =
+            serde_json::from_slice(&read_entry(&bundle, "metadata.json").unwrap()).unwrap();
+        assert_eq!(metadata["id"], book.id.as_str());
+        assert_eq!(metadata["title"], "Dune: Messiah");
+        assert_eq!(metadata["subjects"][0], "Science Fiction");
+    }
+
+    #[test]
+    fn should_omit_missing_cover() {
+        // Given/When: Bundling a book without a cover
+        let book = Book::new("Plain".to_string(), "/plain.epub".to_string());
+        let bundle = build_bundle(&book, &[], b"epub", None).unwrap();
+
+        // Then: There should be no cover entry
+        assert!(read_entry(&bundle, "cover.jpg").is_none());
+        assert!(read_entry(&bundle, "Plain.epub").is_some());
+    }
+
+    #[test]
+    fn should_build_safe_download_filenames() {
+        // Given/When/Then: Unsafe characters are dropped; non-ASCII survives in filename*
+        assert_eq!(
+            bundle_content_disposition("../Dune: \"Messiah\""),
+            "attachment; filename=\"Dune Messiah.zip\"; filename*=UTF-8''Dune%3A%20%22Messiah%22.zip"
+        );
+        assert_eq!(
+            bundle_content_disposition("소설"),
+            "attachment; filename=\"book.zip\"; filename*=UTF-8''%EC%86%8C%EC%84%A4.zip"
+        );
+    }
+
+    #[test]
+    fn should_name_epub_download_after_original_filename() {
+        // Given: One book uploaded as a gzipped EPUB and one without a recorded filename
+        let mut uploaded = Book::new("Dune".to_string(), "/dune.epub".to_string());
+        uploaded.original_filename = Some("Herbert - Dune (1965).EPUB.gz".to_string());
+        let legacy = Book::new("Dune".to_string(), "/dune.epub".to_string());
+
+        // When
+        let uploaded_disposition = epub_content_disposition(&uploaded);
+        let legacy_disposition = epub_content_disposition(&legacy);
+
+        // Then: The original name is kept as an .epub; legacy rows fall back to the title
+        assert_eq!(
+            uploaded_disposition,
+            "attachment; filename=\"Herbert - Dune 1965.epub\"; filename*=UTF-8''Herbert%20-%20Dune%20%281965%29.epub"
+        );
+        assert_eq!(
+            legacy_disposition,
+            "attachment; filename=\"Dune.epub\"; filename*=UTF-8''Dune.epub"
+        );
+    }
+}
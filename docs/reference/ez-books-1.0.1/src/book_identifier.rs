@@ -1,121 +1,268 @@
-use crate::book_model::Book;
+use crate::book_model::{Book, EnrichmentStatus};
 use crate::epub_parser::EpubMetadata;
 use crate::error::Result;
 use crate::openlibrary_client::OpenLibraryClient;
-use crate::openlibrary_types::BooksApiResponse;
+use crate::openlibrary_types::{BookData, BooksApiResponse};
 use tracing::{info, instrument, warn};
 
-/// Identifies and enriches book metadata by combining EPUB metadata with OpenLibrary data
-#[instrument(skip(client, epub_metadata))]
-pub async fn identify_and_enrich(
+/// Book fields enrichment may fill in or replace, named as in `LOCKED_METADATA_FIELDS`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataField {
+    Title,
+    Author,
+    Description,
+    Publisher,
+    PublishDate,
+    PageCount,
+}
+
+impl MetadataField {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MetadataField::Title => "title",
+            MetadataField::Author => "author",
+            MetadataField::Description => "description",
+            MetadataField::Publisher => "publisher",
+            MetadataField::PublishDate => "publish_date",
+            MetadataField::PageCount => "page_count",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            MetadataField::Title,
+            MetadataField::Author,
+            MetadataField::Description,
+            MetadataField::Publisher,
+            MetadataField::PublishDate,
+            MetadataField::PageCount,
+        ]
+        .into_iter()
+        .find(|field| field.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Builds a book from the metadata found inside the EPUB.
+///
+/// Books with an ISBN start out as `Pending` so the enrichment queue can pick them up later.
+pub fn book_from_epub_metadata(epub_metadata: EpubMetadata, epub_path: String) -> Book {
+    let mut book = Book::new(epub_metadata.title, epub_path);
+
+    book.raw_title = epub_metadata.raw_title;
+    book.author = epub_metadata.author;
+    book.isbn_10 = epub_metadata.isbn_10;
+    book.isbn_13 = epub_metadata.isbn_13;
+    book.publisher = epub_metadata.publisher;
+    book.language = epub_metadata.language;
+    book.language_detected = epub_metadata.language_detected;
+    book.has_audio_narration = epub_metadata.has_audio_narration;
+    book.epub_version = epub_metadata.epub_version;
+    book.description = epub_metadata.description;
+    book.description_from_content = epub_metadata.description_from_content;
+
+    book.enrichment_status = if book.isbn_13.is_some() || book.isbn_10.is_some() {
+        EnrichmentStatus::Pending
+    } else {
+        EnrichmentStatus::Done
+    };
+
+    book
+}
+
+/// Puts back the metadata `epub_metadata` declares, undoing enrichment and edits.
+///
+/// Only the EPUB declares ISBNs and language; title, author, publisher and description
+/// come from the EPUB but may have been filled in or replaced by OpenLibrary or an edit,
+/// so they are taken as the EPUB has them, missing ones cleared. The publish date, page
+/// count and OpenLibrary keys only ever come from OpenLibrary or edits and are cleared.
+/// Notes, subjects, files and cover are the reader's or the file's and stay. The book is
+/// marked as enriched so the queue does not merge the same data in again.
+pub fn reset_to_epub_metadata(book: &mut Book, epub_metadata: EpubMetadata) {
+    let parsed = book_from_epub_metadata(epub_metadata, String::new());
+    book.title = parsed.title;
+    book.raw_title = parsed.raw_title;
+    book.author = parsed.author;
+    book.isbn_10 = parsed.isbn_10;
+    book.isbn_13 = parsed.isbn_13;
+    book.publisher = parsed.publisher;
+    book.description = parsed.description;
+    book.description_from_content = parsed.description_from_content;
+    book.language = parsed.language;
+    book.language_detected = parsed.language_detected;
+    book.publish_date = None;
+    book.page_count = None;
+    book.openlibrary_key = None;
+    book.openlibrary_work_key = None;
+    book.read_status = None;
+    book.read_url = None;
+    book.enrichment_status = EnrichmentStatus::Done;
+}
+
+/// Enriches book metadata in place with OpenLibrary data looked up by ISBN, leaving the
+/// `locked` fields as they are
+#[instrument(skip(client, book), fields(book_id = %book.id))]
+pub async fn enrich_book(
     client: &OpenLibraryClient,
-    epub_metadata: EpubMetadata,
-    epub_path: String,
-) -> Result<Book> {
+    book: &mut Book,
+    locked: &[MetadataField],
+) -> Result<()> {
     info!(
-        title = %epub_metadata.title,
-        has_isbn_13 = epub_metadata.isbn_13.is_some(),
-        has_isbn_10 = epub_metadata.isbn_10.is_some(),
-        "Identifying and enriching book metadata"
+        title = %book.title,
+        has_isbn_13 = book.isbn_13.is_some(),
+        has_isbn_10 = book.isbn_10.is_some(),
+        "Enriching book metadata"
     );
 
-    // Create base book from EPUB metadata
-    let mut book = Book::new(epub_metadata.title.clone(), epub_path);
-
-    // Copy EPUB metadata to book
-    book.author = epub_metadata.author.clone();
-    book.isbn_10 = epub_metadata.isbn_10.clone();
-    book.isbn_13 = epub_metadata.isbn_13.clone();
-    book.publisher = epub_metadata.publisher.clone();
-    book.language = epub_metadata.language.clone();
-    book.description = epub_metadata.description.clone();
-
-    // Try to enrich with OpenLibrary data if we have an ISBN
-    let openlibrary_data = if let Some(isbn) = epub_metadata
-        .isbn_13
-        .as_ref()
-        .or(epub_metadata.isbn_10.as_ref())
-    {
-        match client.lookup_by_isbn(isbn).await {
-            Ok(Some(data)) => {
-                info!(isbn = %isbn, "Successfully retrieved OpenLibrary data");
-                Some(data)
-            }
-            Ok(None) => {
-                info!(isbn = %isbn, "No data found on OpenLibrary");
-                None
-            }
-            Err(e) => {
-                warn!(isbn = %isbn, error = %e, "Failed to lookup book on OpenLibrary, continuing with EPUB data only");
-                None
-            }
+    let isbn = match lookup_isbn(book) {
+        Some(isbn) => isbn.to_string(),
+        None => {
+            info!("No ISBN available, skipping OpenLibrary lookup");
+            return Ok(());
         }
-    } else {
-        info!("No ISBN available, skipping OpenLibrary lookup");
-        None
     };
 
-    // Merge OpenLibrary data if available
-    if let Some(ol_data) = openlibrary_data {
-        merge_openlibrary_data(&mut book, ol_data);
+    match client.lookup_by_isbn(&isbn).await? {
+        Some(data) => {
+            info!(isbn = %isbn, "Successfully retrieved OpenLibrary data");
+            merge_openlibrary_data(book, data, locked);
+        }
+        None => {
+            info!(isbn = %isbn, "No data found on OpenLibrary");
+        }
     }
 
     info!(
-        book_id = %book.id,
         title = %book.title,
         has_author = book.author.is_some(),
         has_description = book.description.is_some(),
         has_openlibrary_key = book.openlibrary_key.is_some(),
-        "Book identification and enrichment completed"
+        "Book enrichment completed"
     );
 
-    Ok(book)
+    Ok(())
 }
 
-fn merge_openlibrary_data(book: &mut Book, ol_response: BooksApiResponse) {
-    // Get the first (and likely only) book data from the response
-    let book_data = match ol_response.books.values().next() {
-        Some(data) => data,
-        None => {
-            warn!("OpenLibrary response contains no book data");
-            return;
+/// Enriches several books with a single OpenLibrary request.
+///
+/// A lone book with an ISBN goes through the single-ISBN lookup; books
+/// OpenLibrary has no data for are left unchanged.
+#[instrument(skip(client, books), fields(count = books.len()))]
+pub async fn enrich_books(
+    client: &OpenLibraryClient,
+    books: &mut [Book],
+    locked: &[MetadataField],
+) -> Result<()> {
+    let isbns: Vec<String> = books
+        .iter()
+        .filter_map(|book| lookup_isbn(book).map(str::to_string))
+        .collect();
+
+    if isbns.len() <= 1 {
+        for book in books.iter_mut() {
+            enrich_book(client, book, locked).await?;
+        }
+        return Ok(());
+    }
+
+    let isbn_refs: Vec<&str> = isbns.iter().map(String::as_str).collect();
+    let found = client.lookup_by_isbns(&isbn_refs).await?;
+
+    for book in books.iter_mut() {
+        let data = lookup_isbn(book).and_then(|isbn| found.get(isbn));
+        match data {
+            Some(data) => merge_book_data(book, data, locked),
+            None => info!(book_id = %book.id, "No data found on OpenLibrary"),
         }
+    }
+
+    info!(
+        requested = isbns.len(),
+        found = found.len(),
+        "Batch enrichment completed"
+    );
+    Ok(())
+}
+
+/// Records how the book can be read on Internet Archive. Read API failures are only
+/// logged, keeping what an earlier enrichment found.
+#[instrument(skip(client, book), fields(book_id = %book.id))]
+pub async fn update_read_availability(client: &OpenLibraryClient, book: &mut Book) {
+    let Some(isbn) = lookup_isbn(book).map(str::to_string) else {
+        return;
     };
+    match client.read_availability(&isbn).await {
+        Ok(availability) => {
+            book.read_status = availability.as_ref().map(|a| a.status.clone());
+            book.read_url = availability.map(|a| a.url);
+        }
+        Err(e) => warn!(isbn = %isbn, error = %e, "Failed to look up read availability"),
+    }
+}
+
+/// ISBN used for OpenLibrary lookups, preferring ISBN-13
+pub fn lookup_isbn(book: &Book) -> Option<&str> {
+    book.isbn_13.as_deref().or(book.isbn_10.as_deref())
+}
+
+fn merge_openlibrary_data(
+    book: &mut Book,
+    ol_response: BooksApiResponse,
+    locked: &[MetadataField],
+) {
+    // Get the first (and likely only) book data from the response
+    match ol_response.books.values().next() {
+        Some(data) => merge_book_data(book, data, locked),
+        None => warn!("OpenLibrary response contains no book data"),
+    }
+}
+
+/// Fills in and replaces book fields from an OpenLibrary record. `locked` fields are
+/// never touched, even where the rules below would replace them.
+pub fn merge_book_data(book: &mut Book, book_data: &BookData, locked: &[MetadataField]) {
+    let unlocked = |field: MetadataField| !locked.contains(&field);
 
     // Prefer OpenLibrary title if book title was "Unknown"
-    if book.title == "Unknown" {
+    if unlocked(MetadataField::Title) && book.title == "Unknown" {
         if let Some(title) = &book_data.title {
             book.title = title.clone();
         }
     }
 
     // Prefer OpenLibrary author if EPUB doesn't have one
-    if book.author.is_none() && !book_data.authors.is_empty() {
+    if unlocked(MetadataField::Author) && book.author.is_none() && !book_data.authors.is_empty() {
         book.author = Some(book_data.authors[0].name.clone());
     }
 
     // Always prefer OpenLibrary description (usually more complete)
-    if let Some(subtitle) = &book_data.subtitle {
+    if let Some(subtitle) = book_data
+        .subtitle
+        .as_ref()
+        .filter(|_| unlocked(MetadataField::Description))
+    {
         let description = format!(
             "{}\n\n{}",
             book_data.title.as_deref().unwrap_or(""),
             subtitle
         );
         book.description = Some(description);
+        book.description_from_content = false;
     }
 
     // Prefer OpenLibrary publisher if EPUB doesn't have one
-    if book.publisher.is_none() && !book_data.publishers.is_empty() {
+    if unlocked(MetadataField::Publisher)
+        && book.publisher.is_none()
+        && !book_data.publishers.is_empty()
+    {
         book.publisher = Some(book_data.publishers[0].name.clone());
     }
 
     // Use OpenLibrary publish date if available
-    if book_data.publish_date.is_some() {
+    if unlocked(MetadataField::PublishDate) && book_data.publish_date.is_some() {
         book.publish_date = book_data.publish_date.clone();
     }
 
     // Use page count from OpenLibrary
-    if book_data.number_of_pages.is_some() {
+    if unlocked(MetadataField::PageCount) && book_data.number_of_pages.is_some() {
         book.page_count = book_data.number_of_pages;
     }
 
@@ -150,12 +297,17 @@ mod tests {
     fn create_test_epub_metadata() -> EpubMetadata {
         EpubMetadata {
             title: "Test Book".to_string(),
+            raw_title: None,
             author: Some("Test Author".to_string()),
             isbn_10: None,
             isbn_13: Some("9781234567890".to_string()),
             publisher: None,
             language: Some("en".to_string()),
+            language_detected: false,
+            has_audio_narration: false,
+            epub_version: None,
             description: None,
+            description_from_content: false,
             subjects: vec!["Fiction".to_string()],
         }
     }
@@ -188,6 +340,35 @@ mod tests {
         BooksApiResponse { books }
     }
 
+    #[test]
+    fn should_build_pending_book_from_epub_metadata_with_isbn() {
+        // Given: EPUB metadata with an ISBN
+        let metadata = create_test_epub_metadata();
+
+        // When: Building a book from it
+        let book = book_from_epub_metadata(metadata, "/path.epub".to_string());
+
+        // Then: Fields should be copied and enrichment should be pending
+        assert_eq!(book.title, "Test Book");
+        assert_eq!(book.author, Some("Test Author".to_string()));
+        assert_eq!(book.isbn_13, Some("9781234567890".to_string()));
+        assert_eq!(book.language, Some("en".to_string()));
+        assert_eq!(book.enrichment_status, EnrichmentStatus::Pending);
+    }
+
+    #[test]
+    fn should_build_done_book_from_epub_metadata_without_isbn() {
+        // Given: EPUB metadata without any ISBN
+        let mut metadata = create_test_epub_metadata();
+        metadata.isbn_13 = None;
+
+        // When: Building a book from it
+        let book = book_from_epub_metadata(metadata, "/path.epub".to_string());
+
+        // Then: There is nothing to enrich
+        assert_eq!(book.enrichment_status, EnrichmentStatus::Done);
+    }
+
     #[test]
     fn should_merge_openlibrary_publisher() {
         // Given: A book without publisher and OpenLibrary data with publisher
@@ -196,7 +377,7 @@ mod tests {
         let ol_response = create_test_openlibrary_response();
 
         // When: Merging OpenLibrary data
-        merge_openlibrary_data(&mut book, ol_response);
+        merge_openlibrary_data(&mut book, ol_response, &[]);
 
         // Then: Publisher should be set from OpenLibrary
         assert_eq!(book.publisher, Some("Test Publisher".to_string()));
@@ -210,7 +391,7 @@ mod tests {
         let ol_response = create_test_openlibrary_response();
 
         // When: Merging OpenLibrary data
-        merge_openlibrary_data(&mut book, ol_response);
+        merge_openlibrary_data(&mut book, ol_response, &[]);
 
         // Then: Page count should be set from OpenLibrary
         assert_eq!(book.page_count, Some(250));
@@ -223,7 +404,7 @@ mod tests {
         let ol_response = create_test_openlibrary_response();
 
         // When: Merging OpenLibrary data
-        merge_openlibrary_data(&mut book, ol_response);
+        merge_openlibrary_data(&mut book, ol_response, &[]);
 
         // Then: OpenLibrary keys should be stored
         assert_eq!(book.openlibrary_key, Some("/books/OL12345M".to_string()));
@@ -241,7 +422,7 @@ mod tests {
         let ol_response = create_test_openlibrary_response();
 
         // When: Merging OpenLibrary data
-        merge_openlibrary_data(&mut book, ol_response);
+        merge_openlibrary_data(&mut book, ol_response, &[]);
 
         // Then: EPUB author should be preserved
         assert_eq!(book.author, Some("EPUB Author".to_string()));
@@ -255,7 +436,7 @@ mod tests {
         let ol_response = create_test_openlibrary_response();
 
         // When: Merging OpenLibrary data
-        merge_openlibrary_data(&mut book, ol_response);
+        merge_openlibrary_data(&mut book, ol_response, &[]);
 
         // Then: OpenLibrary author should be used
         assert_eq!(book.author, Some("OpenLibrary Author".to_string()));
@@ -268,7 +449,7 @@ mod tests {
         let ol_response = create_test_openlibrary_response();
 
         // When: Merging OpenLibrary data
-        merge_openlibrary_data(&mut book, ol_response);
+        merge_openlibrary_data(&mut book, ol_response, &[]);
 
         // Then: Title should be replaced with OpenLibrary title
         assert_eq!(book.title, "Enhanced Test Book");
@@ -282,7 +463,7 @@ mod tests {
         let ol_response = create_test_openlibrary_response();
 
         // When: Merging OpenLibrary data
-        merge_openlibrary_data(&mut book, ol_response);
+        merge_openlibrary_data(&mut book, ol_response, &[]);
 
         // Then: Description should be set from title and subtitle
         assert!(book.description.is_some());
@@ -291,6 +472,44 @@ mod tests {
         assert!(description.contains("A Test Subtitle"));
     }
 
+    #[test]
+    fn should_leave_locked_fields_untouched() {
+        // Given: A placeholder-titled book without author, description or page count
+        let mut book = Book::new("Unknown".to_string(), "/path.epub".to_string());
+        book.author = None;
+        book.description = None;
+        let ol_response = create_test_openlibrary_response();
+        let locked = [
+            MetadataField::Title,
+            MetadataField::Author,
+            MetadataField::Description,
+        ];
+
+        // When: Merging OpenLibrary data with title, author and description locked
+        merge_openlibrary_data(&mut book, ol_response, &locked);
+
+        // Then: The locked fields keep their values while the others are still filled in
+        assert_eq!(book.title, "Unknown");
+        assert!(book.author.is_none());
+        assert!(book.description.is_none());
+        assert_eq!(book.page_count, Some(250));
+        assert_eq!(book.publisher, Some("Test Publisher".to_string()));
+    }
+
+    #[test]
+    fn should_parse_metadata_field_names() {
+        // Given/When/Then: Names match ignoring case and surrounding space
+        assert_eq!(
+            MetadataField::from_name(" Publish_Date "),
+            Some(MetadataField::PublishDate)
+        );
+        assert_eq!(
+            MetadataField::from_name("title"),
+            Some(MetadataField::Title)
+        );
+        assert_eq!(MetadataField::from_name("isbn"), None);
+    }
+
     #[test]
     fn should_handle_empty_openlibrary_response() {
         // Given: A book and empty OpenLibrary response
@@ -301,7 +520,7 @@ mod tests {
         };
 
         // When: Merging OpenLibrary data
-        merge_openlibrary_data(&mut book, ol_response);
+        merge_openlibrary_data(&mut book, ol_response, &[]);
 
         // Then: Book should remain unchanged
         assert_eq!(book.title, "Test");
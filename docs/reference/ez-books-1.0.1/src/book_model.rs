@@ -1,58 +1,251 @@
-use serde::{Deserialize, Serialize};
+use crate::book_query::BookSort;
+use crate::collection_repository::Collection;
+use crate::metadata_completeness::{completeness_score, MetadataPresence};
+use crate::progress_repository::ReadingProgress;
+use crate::ui_text::{Lang, UiText};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
+/// `format` of books read by the EPUB parser
+pub const EPUB_FORMAT: &str = "epub";
+
+/// In JSON, `created_at` and `updated_at` are accompanied by `created_at_iso` and
+/// `updated_at_iso`, the same instants as ISO-8601 UTC strings
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[serde(remote = "Self")]
 pub struct Book {
     pub id: String,
     pub title: String,
+    /// The title the EPUB declared, when it was a file name and `title` is tidied from it
+    pub raw_title: Option<String>,
     pub author: Option<String>,
     pub isbn_10: Option<String>,
     pub isbn_13: Option<String>,
     pub publisher: Option<String>,
     pub publish_date: Option<String>,
     pub description: Option<String>,
+    /// The description is the opening paragraph of the book, as the EPUB declared none
+    pub description_from_content: bool,
+    /// The reader's own plain-text notes; never HTML
+    pub notes: Option<String>,
     pub cover_image_path: Option<String>,
+    /// Content hash of the stored cover; changes whenever the cover is replaced
+    pub cover_hash: Option<String>,
+    /// Content type of the stored cover; `None` for covers stored before it was recorded
+    pub cover_mime: Option<String>,
+    /// Average cover color as `#rrggbb`, used as the card background while the cover loads
+    pub cover_color: Option<String>,
     pub epub_file_path: String,
+    /// Filename the EPUB was uploaded or imported with; files are stored under the book id
+    pub original_filename: Option<String>,
+    /// Lowercase file format, named after the parser that imported the book, e.g. "epub"
+    pub format: String,
     pub openlibrary_key: Option<String>,
     pub openlibrary_work_key: Option<String>,
+    /// Internet Archive scan status from the OpenLibrary Read API, e.g. "lendable"
+    pub read_status: Option<String>,
+    /// Page of the Internet Archive scan; `None` when no readable scan is known
+    pub read_url: Option<String>,
     pub page_count: Option<i32>,
     pub language: Option<String>,
+    /// The language was guessed from the text because the EPUB did not declare one
+    pub language_detected: bool,
+    /// The EPUB ships EPUB3 media overlays for synchronized narration
+    pub has_audio_narration: bool,
+    /// OPF package version, e.g. "2.0" or "3.0"; `None` when unknown
+    pub epub_version: Option<String>,
+    pub enrichment_status: EnrichmentStatus,
+    pub file_size_bytes: Option<i64>,
+    pub content_hash: Option<String>,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
+impl Serialize for Book {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        BookJson {
+            book: BookFields(self),
+            created_at_iso: iso_8601(self.created_at),
+            updated_at_iso: iso_8601(self.updated_at),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Book {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Book::deserialize(deserializer)
+    }
+}
+
+#[derive(Serialize)]
+struct BookJson<'a> {
+    #[serde(flatten)]
+    book: BookFields<'a>,
+    created_at_iso: String,
+    updated_at_iso: String,
+}
+
+/// The derived serialization of every `Book` field
+struct BookFields<'a>(&'a Book);
+
+impl Serialize for BookFields<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Book::serialize(self.0, serializer)
+    }
+}
+
+/// Progress of the background OpenLibrary enrichment for a book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum EnrichmentStatus {
+    Pending,
+    Done,
+    Failed,
+}
+
 impl Book {
     pub fn new(title: String, epub_path: String) -> Self {
         let now = current_timestamp();
         Self {
             id: Uuid::new_v4().to_string(),
             title,
+            raw_title: None,
             epub_file_path: epub_path,
+            original_filename: None,
+            format: EPUB_FORMAT.to_string(),
             author: None,
             isbn_10: None,
             isbn_13: None,
             publisher: None,
             publish_date: None,
             description: None,
+            description_from_content: false,
+            notes: None,
             cover_image_path: None,
+            cover_hash: None,
+            cover_mime: None,
+            cover_color: None,
             openlibrary_key: None,
             openlibrary_work_key: None,
+            read_status: None,
+            read_url: None,
             page_count: None,
             language: None,
+            language_detected: false,
+            has_audio_narration: false,
+            epub_version: None,
+            enrichment_status: EnrichmentStatus::Done,
+            file_size_bytes: None,
+            content_hash: None,
             created_at: now,
             updated_at: now,
         }
     }
+
+    /// 0-100 score of how well the book is documented; see `completeness_score`
+    pub fn metadata_completeness(&self) -> u8 {
+        completeness_score(&MetadataPresence::of_book(self))
+    }
+
+    /// Author for display; missing or blank authors become "Unknown Author" in `lang`
+    pub fn display_author(&self, lang: Lang) -> &str {
+        match self.author.as_deref().map(str::trim) {
+            Some(author) if !author.is_empty() => author,
+            _ => lang.text(UiText::UnknownAuthor),
+        }
+    }
+}
+
+/// Response of `GET /api/books/{id}`: the book plus derived fields and related records,
+/// so clients need no further round-trips
+#[derive(Debug, Serialize)]
+pub struct BookDetail<'a> {
+    #[serde(flatten)]
+    pub book: &'a Book,
+    pub metadata_completeness: u8,
+    pub subjects: Vec<String>,
+    /// `None` until the book is opened in the reader
+    pub reading_progress: Option<ReadingProgress>,
+    /// Collections the book is in, by name
+    pub collections: Vec<Collection>,
+}
+
+impl<'a> BookDetail<'a> {
+    pub fn new(
+        book: &'a Book,
+        subjects: Vec<String>,
+        reading_progress: Option<ReadingProgress>,
+        collections: Vec<Collection>,
+    ) -> Self {
+        Self {
+            book,
+            metadata_completeness: book.metadata_completeness(),
+            subjects,
+            reading_progress,
+            collections,
+        }
+    }
+}
+
+/// An author of `GET /api/authors` with the number of their books
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct AuthorSummary {
+    pub name: String,
+    pub book_count: i64,
 }
 
-fn current_timestamp() -> i64 {
+/// Ids of the books listed just before and after a book in the gallery's `sort` order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AdjacentBooks {
+    pub sort: BookSort,
+    /// `None` for the first book
+    pub previous: Option<String>,
+    /// `None` for the last book
+    pub next: Option<String>,
+}
+
+pub fn current_timestamp() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_secs() as i64)
         .unwrap_or(0)
 }
 
+/// `2024-03-01T12:00:00Z` for a unix timestamp in seconds
+pub fn iso_8601(timestamp: i64) -> String {
+    let days = timestamp.div_euclid(86_400);
+    let seconds = timestamp.rem_euclid(86_400);
+
+    // Civil date from days since 1970-01-01, in 400-year eras starting on March 1st
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -73,6 +266,73 @@ mod tests {
         assert!(book.created_at > 0);
         assert_eq!(book.created_at, book.updated_at);
         assert!(book.author.is_none());
+        assert_eq!(book.enrichment_status, EnrichmentStatus::Done);
+    }
+
+    #[test]
+    fn should_serialize_enrichment_status_as_lowercase() {
+        // Given: A book awaiting enrichment
+        let mut book = Book::new("Test".to_string(), "/path.epub".to_string());
+        book.enrichment_status = EnrichmentStatus::Pending;
+
+        // When: Serializing to JSON
+        let json = serde_json::to_string(&book).unwrap();
+
+        // Then: Status should appear as a lowercase string
+        assert!(json.contains("\"enrichment_status\":\"pending\""));
+    }
+
+    #[test]
+    fn should_format_timestamps_as_iso_8601() {
+        // Given/When/Then: Known instants, including a leap day and the epoch
+        assert_eq!(iso_8601(0), "1970-01-01T00:00:00Z");
+        assert_eq!(iso_8601(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(iso_8601(1_709_294_400), "2024-03-01T12:00:00Z");
+        assert_eq!(iso_8601(1_735_689_599), "2024-12-31T23:59:59Z");
+        assert_eq!(iso_8601(-1), "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn should_serialize_timestamps_as_numbers_and_iso_strings() {
+        // Given: A book created at a known time
+        let mut book = Book::new("Test".to_string(), "/path.epub".to_string());
+        book.created_at = 1_709_294_400;
+        book.updated_at = 1_709_294_461;
+
+        // When: Serializing it, and reading the JSON back
+        let json = serde_json::to_value(&book).unwrap();
+        let parsed: Book = serde_json::from_value(json.clone()).unwrap();
+
+        // Then: Both forms are present and the extra fields are ignored on input
+        assert_eq!(json["created_at"], 1_709_294_400);
+        assert_eq!(json["created_at_iso"], "2024-03-01T12:00:00Z");
+        assert_eq!(json["updated_at_iso"], "2024-03-01T12:01:01Z");
+        assert_eq!(json["title"], "Test");
+        assert_eq!(parsed.updated_at, book.updated_at);
+    }
+
+    #[test]
+    fn should_display_author_or_fallback() {
+        // Given: Books with, without and with a blank author
+        let mut book = Book::new("Test".to_string(), "/path.epub".to_string());
+        assert_eq!(book.display_author(Lang::En), "Unknown Author");
+
+        book.author = Some("   ".to_string());
+        assert_eq!(book.display_author(Lang::En), "Unknown Author");
+
+        // When/Then: A real author is shown as-is
+        book.author = Some("Jane Doe".to_string());
+        assert_eq!(book.display_author(Lang::En), "Jane Doe");
+    }
+
+    #[test]
+    fn should_keep_missing_author_null_in_json() {
+        // Given/When: Serializing a book without author
+        let book = Book::new("Test".to_string(), "/path.epub".to_string());
+        let json = serde_json::to_value(&book).unwrap();
+
+        // Then: The API still distinguishes a missing author
+        assert!(json["author"].is_null());
     }
 
     #[test]
@@ -0,0 +1,248 @@
+use crate::book_model::Book;
+use crate::gallery_renderer::{author_link, cover_url};
+use crate::html_templates::{escape_html, html_footer, html_header};
+use crate::ui_text::{Lang, UiText};
+use serde_json::{json, Map, Value};
+
+/// Renders the public page of one book with a Schema.org `Book` JSON-LD block for
+/// search engines and link previews; labels are in `lang` and links start with
+/// `base_path` (see `html_templates`). The reader's private notes are left out.
+pub fn render_book_page(book: &Book, subjects: &[String], lang: Lang, base_path: &str) -> String {
+    let mut html = html_header(&book.title, "gallery.css", lang, base_path);
+
+    html.push_str(&format!(
+        r#"<header><h1>{}</h1><p class="author">{}</p></header>"#,
+        escape_html(&book.title),
+        author_link(book, lang, base_path)
+    ));
+    html.push_str(r#"<main class="book-page">"#);
+    // Only covered books get an image, since the Content-Security-Policy blocks inline `onerror`
+    if book.cover_image_path.is_some() {
+        html.push_str(&format!(
+            r#"<img class="cover" src="{}" alt="{}">"#,
+            cover_url(book, base_path),
+            escape_html(&book.title)
+        ));
+    }
+    html.push_str(r#"<div class="details">"#);
+    html.push_str(&render_facts(book, lang));
+    if let Some(description) = non_blank(&book.description) {
+        html.push_str(&format!(
+            r#"<p class="description">{}</p>"#,
+            escape_html(description)
+        ));
+    }
+    html.push_str(&render_subjects(subjects));
+    html.push_str(&format!(
+        r#"<div class="actions"><a href="{base}/reader/{id}">{read}</a><a href="{base}/api/books/{id}/download">{download}</a>{archive}<a href="{base}/">{library}</a></div>"#,
+        base = base_path,
+        id = escape_html(&book.id),
+        read = lang.text(UiText::Read),
+        download = lang.text(UiText::Download),
+        archive = render_archive_link(book, lang),
+        library = lang.text(UiText::Library)
+    ));
+    html.push_str("</div></main>");
+    html.push_str(&format!(
+        r#"<script type="application/ld+json">{}</script>"#,
+        json_ld(book, subjects, base_path)
+    ));
+    html.push_str(&html_footer(None, base_path));
+
+    html
+}
+
+fn render_facts(book: &Book, lang: Lang) -> String {
+    let page_count = book.page_count.map(|count| count.to_string());
+    let format = book.format.to_uppercase();
+    let facts = [
+        (lang.text(UiText::Publisher), non_blank(&book.publisher)),
+        (lang.text(UiText::Published), non_blank(&book.publish_date)),
+        ("ISBN-13", non_blank(&book.isbn_13)),
+        ("ISBN-10", non_blank(&book.isbn_10)),
+        (lang.text(UiText::Language), non_blank(&book.language)),
+        (lang.text(UiText::Pages), page_count.as_deref()),
+        (lang.text(UiText::Format), Some(format.as_str())),
+    ];
+
+    let mut html = String::from(r#"<dl class="facts">"#);
+    for (label, value) in facts {
+        if let Some(value) = value {
+            html.push_str(&format!(
+                "<dt>{}</dt><dd>{}</dd>",
+                label,
+                escape_html(value)
+            ));
+        }
+    }
+    html.push_str("</dl>");
+    html
+}
+
+/// Link to the Internet Archive scan found by the OpenLibrary Read API, if any
+fn render_archive_link(book: &Book, lang: Lang) -> String {
+    match &book.read_url {
+        Some(url) => format!(
+            r#"<a href="{}" rel="external noopener">{}</a>"#,
+            escape_html(url),
+            lang.text(UiText::ReadOnArchive)
+        ),
+        None => String::new(),
+    }
+}
+
+fn render_subjects(subjects: &[String]) -> String {
+    if subjects.is_empty() {
+        return String::new();
+    }
+    let items: String = subjects
+        .iter()
+        .map(|subject| format!("<li>{}</li>", escape_html(subject)))
+        .collect();
+    format!(r#"<ul class="subjects">{}</ul>"#, items)
+}
+
+/// Schema.org `Book` description, safe to place inside a `<script>` element
+fn json_ld(book: &Book, subjects: &[String], base_path: &str) -> String {
+    let mut data = Map::new();
+    data.insert("@context".to_string(), json!("https://schema.org"));
+    data.insert("@type".to_string(), json!("Book"));
+    data.insert("name".to_string(), json!(book.title));
+    data.insert(
+        "url".to_string(),
+        json!(format!("{}/books/{}", base_path, book.id)),
+    );
+    data.insert("bookFormat".to_string(), json!("https://schema.org/EBook"));
+
+    let optional = [
+        (
+            "author",
+            non_blank(&book.author).map(|name| json!({ "@type": "Person", "name": name })),
+        ),
+        (
+            "publisher",
+            non_blank(&book.publisher).map(|name| json!({ "@type": "Organization", "name": name })),
+        ),
+        (
+            "isbn",
+            non_blank(&book.isbn_13)
+                .or_else(|| non_blank(&book.isbn_10))
+                .map(|isbn| json!(isbn)),
+        ),
+        (
+            "datePublished",
+            non_blank(&book.publish_date).map(|date| json!(date)),
+        ),
+        (
+            "description",
+            non_blank(&book.description).map(|description| json!(description)),
+        ),
+        (
+            "inLanguage",
+            non_blank(&book.language).map(|language| json!(language)),
+        ),
+        ("numberOfPages", book.page_count.map(|count| json!(count))),
+        (
+            "image",
+            book.cover_image_path
+                .as_ref()
+                .map(|_| json!(format!("{}/covers/{}", base_path, book.id))),
+        ),
+        (
+            "keywords",
+            (!subjects.is_empty()).then(|| json!(subjects.join(", "))),
+        ),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            data.insert(key.to_string(), value);
+        }
+    }
+
+    // `</script>` or `<!--` in a field must not end the element early
+    Value::Object(data)
+        .to_string()
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+}
+
+fn non_blank(value: &Option<String>) -> Option<&str> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_ld_block(html: &str) -> Value {
+        let start = html.find(r#"<script type="application/ld+json">"#).unwrap() + 35;
+        let end = start + html[start..].find("</script>").unwrap();
+        serde_json::from_str(&html[start..end]).unwrap()
+    }
+
+    #[test]
+    fn should_render_metadata_links_and_structured_data() {
+        // Given: A documented book with subjects, served under a subpath
+        let mut book = Book::new("Dune".to_string(), "/dune.epub".to_string());
+        book.author = Some("Frank Herbert".to_string());
+        book.isbn_13 = Some("9780441013593".to_string());
+        book.publisher = Some("Ace".to_string());
+        book.page_count = Some(612);
+        book.cover_image_path = Some("/covers/dune.jpg".to_string());
+        book.notes = Some("Private thoughts".to_string());
+        let subjects = vec!["Science fiction".to_string(), "Deserts".to_string()];
+
+        // When: Rendering its page
+        let html = render_book_page(&book, &subjects, Lang::En, "/ezbooks");
+
+        // Then: The page shows the metadata and links, and the JSON-LD describes the book
+        assert!(html.contains("<title>Dune</title>"));
+        assert!(html.contains("<dt>ISBN-13</dt><dd>9780441013593</dd>"));
+        assert!(html.contains("<li>Science fiction</li>"));
+        assert!(html.contains(&format!(r#"href="/ezbooks/reader/{}">Read</a>"#, book.id)));
+        assert!(html.contains(r#"<img class="cover""#));
+        assert!(!html.contains("onerror"));
+        assert!(!html.contains("Private thoughts"));
+        assert!(!html.contains("Internet Archive"));
+        let data = json_ld_block(&html);
+        assert_eq!(data["@type"], "Book");
+        assert_eq!(data["author"]["name"], "Frank Herbert");
+        assert_eq!(data["isbn"], "9780441013593");
+        assert_eq!(data["numberOfPages"], 612);
+        assert_eq!(data["keywords"], "Science fiction, Deserts");
+        assert_eq!(data["image"], format!("/ezbooks/covers/{}", book.id));
+        assert!(data.get("description").is_none());
+    }
+
+    #[test]
+    fn should_escape_fields_in_markup_and_json_ld() {
+        // Given: A book whose fields try to break out of the page and the script
+        let mut book = Book::new("<b>Bold</b>".to_string(), "/bold.epub".to_string());
+        book.description = Some("The end</script><script>alert(1)</script>".to_string());
+        book.read_url = Some(r#"https://archive.org/details/x?a=1&b="2"#.to_string());
+        let subjects = vec!["<i>tag</i>".to_string()];
+
+        // When: Rendering its page
+        let html = render_book_page(&book, &subjects, Lang::Ko, "");
+
+        // Then: No field becomes markup, and the JSON-LD still decodes to the original text
+        assert!(!html.contains("<b>Bold</b>"));
+        assert!(!html.contains("<script>alert"));
+        assert!(!html.contains("<i>tag"));
+        assert!(html.contains("&lt;b&gt;Bold&lt;/b&gt;"));
+        assert!(html.contains("<dt>형식</dt><dd>EPUB</dd>"));
+        assert!(html.contains(
+            r#"<a href="https://archive.org/details/x?a=1&amp;b=&quot;2" rel="external noopener">인터넷 아카이브에서 읽기</a>"#
+        ));
+        let data = json_ld_block(&html);
+        assert_eq!(data["name"], "<b>Bold</b>");
+        assert_eq!(
+            data["description"],
+            "The end</script><script>alert(1)</script>"
+        );
+    }
+}
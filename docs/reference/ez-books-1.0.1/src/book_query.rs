@@ -0,0 +1,569 @@
+use crate::book_model::Book;
+use crate::config::Config;
+use crate::error::{self, EzBooksError};
+use crate::ui_text::Lang;
+use serde::{Deserialize, Serialize};
+
+/// Page size for cursor paging when only `cursor` is given, unless configured otherwise
+pub const DEFAULT_PAGE_LIMIT: u32 = 100;
+pub const MAX_PAGE_LIMIT: u32 = 1000;
+
+/// Sort orders accepted by the book listing endpoints
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookSort {
+    /// Newest books first
+    #[default]
+    Created,
+    /// Largest stored EPUB first; books without a known size come last
+    Size,
+    /// Least documented first, so books needing metadata work float up
+    Completeness,
+}
+
+impl BookSort {
+    /// Whitelisted `ORDER BY` clause for this sort, safe to embed in SQL
+    pub fn order_by_clause(self) -> &'static str {
+        match self {
+            BookSort::Created => "created_at DESC",
+            BookSort::Size => "file_size_bytes IS NULL, file_size_bytes DESC, created_at DESC",
+            // The score is computed in Rust; `find_all_sorted` sorts by it after fetching
+            BookSort::Completeness => "created_at DESC",
+        }
+    }
+
+    /// Value of the `sort` query parameter
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BookSort::Created => "created",
+            BookSort::Size => "size",
+            BookSort::Completeness => "completeness",
+        }
+    }
+
+    /// The sort named by `as_str`, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        [BookSort::Created, BookSort::Size, BookSort::Completeness]
+            .into_iter()
+            .find(|sort| sort.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Listing settings from the configuration, used when a request leaves them out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListingDefaults {
+    pub sort: BookSort,
+    /// Page size when paging with `cursor` but without `limit`
+    pub page_size: u32,
+}
+
+impl Default for ListingDefaults {
+    fn default() -> Self {
+        Self {
+            sort: BookSort::default(),
+            page_size: DEFAULT_PAGE_LIMIT,
+        }
+    }
+}
+
+impl ListingDefaults {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            sort: config.default_sort,
+            page_size: config.default_page_size,
+        }
+    }
+}
+
+/// Query parameters for `GET /api/books`
+#[derive(Debug, Default, Deserialize)]
+pub struct BooksQuery {
+    /// `None` uses the configured default sort; cursor pages are always `created`
+    pub sort: Option<BookSort>,
+    /// Page size; giving `limit` or `cursor` switches the response to a `BooksPage`
+    pub limit: Option<u32>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    /// Only books created at or after this Unix timestamp
+    pub added_from: Option<i64>,
+    /// Only books created at or before this Unix timestamp
+    pub added_to: Option<i64>,
+    /// Only books of this format, e.g. `epub`; not available with paging or date ranges
+    pub format: Option<String>,
+}
+
+impl BooksQuery {
+    pub fn is_paged(&self) -> bool {
+        self.limit.is_some() || self.cursor.is_some()
+    }
+
+    /// The requested sort, or `default` when the request gives none
+    pub fn sort_or(&self, default: BookSort) -> BookSort {
+        self.sort.unwrap_or(default)
+    }
+
+    /// A sort other than `created` was asked for explicitly
+    pub fn has_custom_sort(&self) -> bool {
+        self.sort.map_or(false, |sort| sort != BookSort::Created)
+    }
+
+    /// Inclusive `created_at` bounds when filtering by date added, open ends filled in
+    pub fn added_range(&self) -> error::Result<Option<(i64, i64)>> {
+        if self.added_from.is_none() && self.added_to.is_none() {
+            return Ok(None);
+        }
+
+        let from = self.added_from.unwrap_or(i64::MIN);
+        let to = self.added_to.unwrap_or(i64::MAX);
+        if from > to {
+            return Err(EzBooksError::InvalidDateRange(format!(
+                "added_from ({}) is after added_to ({})",
+                from, to
+            )));
+        }
+        if self.has_custom_sort() || self.is_paged() {
+            return Err(EzBooksError::InvalidDateRange(
+                "added_from/added_to cannot be combined with sort or paging".to_string(),
+            ));
+        }
+        if self.format.is_some() {
+            return Err(EzBooksError::InvalidDateRange(
+                "added_from/added_to cannot be combined with format".to_string(),
+            ));
+        }
+        Ok(Some((from, to)))
+    }
+
+    /// The requested page size or `default_limit`, checked against `MAX_PAGE_LIMIT`
+    pub fn page_limit(&self, default_limit: u32) -> error::Result<u32> {
+        match self.limit.unwrap_or(default_limit) {
+            0 => Err(EzBooksError::InvalidPagination(
+                "limit must be at least 1".to_string(),
+            )),
+            limit if limit > MAX_PAGE_LIMIT => Err(EzBooksError::InvalidPagination(format!(
+                "limit must be at most {}",
+                MAX_PAGE_LIMIT
+            ))),
+            limit => Ok(limit),
+        }
+    }
+}
+
+/// Position after the last book of a page in `created_at DESC, id DESC` order.
+///
+/// Sent to clients as an opaque hex string so its format can change freely.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookCursor {
+    pub created_at: i64,
+    pub id: String,
+}
+
+impl BookCursor {
+    /// A cursor before every book, for the first page
+    pub fn start() -> Self {
+        Self {
+            created_at: i64::MAX,
+            id: String::new(),
+        }
+    }
+
+    pub fn after(book: &Book) -> Self {
+        Self {
+            created_at: book.created_at,
+            id: book.id.clone(),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        format!("{}:{}", self.created_at, self.id)
+            .bytes()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    pub fn decode(cursor: &str) -> error::Result<Self> {
+        let invalid = || EzBooksError::InvalidPagination("malformed cursor".to_string());
+
+        if cursor.len() % 2 != 0 || !cursor.is_ascii() {
+            return Err(invalid());
+        }
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid())?;
+        let decoded = String::from_utf8(bytes).map_err(|_| invalid())?;
+        let (created_at, id) = decoded.split_once(':').ok_or_else(invalid)?;
+
+        Ok(Self {
+            created_at: created_at.parse().map_err(|_| invalid())?,
+            id: id.to_string(),
+        })
+    }
+}
+
+/// Response of `GET /api/books` when paging with `limit`/`cursor`
+#[derive(Debug, Serialize)]
+pub struct BooksPage {
+    pub books: Vec<Book>,
+    /// Pass as `cursor` to get the next page; `null` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Query parameters for `GET /api/books/next`
+#[derive(Debug, Deserialize)]
+pub struct NextBookQuery {
+    pub subject: String,
+    pub lang: Option<String>,
+}
+
+/// Recommendations returned when `limit` is not given, and the most allowed
+pub const DEFAULT_RECOMMENDED_LIMIT: u32 = 10;
+pub const MAX_RECOMMENDED_LIMIT: u32 = 50;
+
+/// Query parameters for `GET /api/books/recommended`
+#[derive(Debug, Default, Deserialize)]
+pub struct RecommendedQuery {
+    pub limit: Option<u32>,
+}
+
+impl RecommendedQuery {
+    /// The requested size, capped at `MAX_RECOMMENDED_LIMIT`
+    pub fn limit(&self) -> u32 {
+        self.limit
+            .unwrap_or(DEFAULT_RECOMMENDED_LIMIT)
+            .min(MAX_RECOMMENDED_LIMIT)
+    }
+}
+
+/// Books returned by a search when `limit` is not given, and the most allowed
+pub const DEFAULT_SEARCH_LIMIT: u32 = 20;
+pub const MAX_SEARCH_LIMIT: u32 = 100;
+
+/// Query parameters for `GET /api/search` and `POST /api/search/content`
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// Words to find in book metadata, or the phrase to find in book text
+    pub q: String,
+    pub limit: Option<u32>,
+}
+
+impl SearchQuery {
+    /// The requested number of books, capped at `MAX_SEARCH_LIMIT`
+    pub fn limit(&self) -> u32 {
+        self.limit
+            .unwrap_or(DEFAULT_SEARCH_LIMIT)
+            .min(MAX_SEARCH_LIMIT)
+    }
+}
+
+/// Query parameters for `GET /reader/{id}/text`
+#[derive(Debug, Default, Deserialize)]
+pub struct TextQuery {
+    /// 1-based chapter to return instead of the whole book
+    pub chapter: Option<usize>,
+}
+
+/// Layouts the reader page can render
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReaderMode {
+    /// One long scrolling column
+    #[default]
+    Scroll,
+    /// Screen-sized CSS columns turned with page controls
+    Paged,
+}
+
+impl ReaderMode {
+    /// Value of the `data-reader-mode` attribute and the `mode` query parameter
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReaderMode::Scroll => "scroll",
+            ReaderMode::Paged => "paged",
+        }
+    }
+}
+
+/// Query parameters for `GET /reader/{id}`
+#[derive(Debug, Default, Deserialize)]
+pub struct ReaderQuery {
+    #[serde(default)]
+    pub mode: ReaderMode,
+    /// Gallery order used for the previous/next book links
+    #[serde(default)]
+    pub sort: BookSort,
+    /// Negotiated by the route from `?lang=` and `Accept-Language`
+    #[serde(skip)]
+    pub lang: Lang,
+}
+
+/// The `?lang=` override of the `Accept-Language` header on HTML pages
+#[derive(Debug, Default, Deserialize)]
+pub struct LangQuery {
+    pub lang: Option<String>,
+}
+
+/// Query parameters for `GET /api/admin/integrity`
+#[derive(Debug, Default, Deserialize)]
+pub struct IntegrityQuery {
+    /// Delete orphaned files and clear dangling cover paths instead of only reporting
+    #[serde(default)]
+    pub fix: bool,
+}
+
+/// Widths and heights covers can be resized to; any size would let clients fill the
+/// disk with cached variants
+pub const COVER_VARIANT_SIZES: [u32; 6] = [64, 100, 150, 200, 300, 450];
+
+/// Query parameters for `GET /covers/{id}`
+#[derive(Debug, Default, Deserialize)]
+pub struct CoverQuery {
+    /// Cover hash from the gallery; a versioned URL never changes content
+    pub v: Option<String>,
+    /// Largest width of a resized cover, one of `COVER_VARIANT_SIZES`
+    pub w: Option<u32>,
+    /// Largest height of a resized cover, one of `COVER_VARIANT_SIZES`
+    pub h: Option<u32>,
+}
+
+/// Box a resized cover must fit in; a side left out does not constrain it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoverSize {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl CoverSize {
+    /// Names the cached variant of this size, e.g. `w150` or `w150h225`
+    pub fn variant_key(&self) -> String {
+        let width = self.width.map(|width| format!("w{}", width));
+        let height = self.height.map(|height| format!("h{}", height));
+        format!(
+            "{}{}",
+            width.unwrap_or_default(),
+            height.unwrap_or_default()
+        )
+    }
+}
+
+impl CoverQuery {
+    /// The requested size, or `None` for the stored cover as is
+    pub fn size(&self) -> error::Result<Option<CoverSize>> {
+        for (name, value) in [("w", self.w), ("h", self.h)] {
+            if let Some(value) = value.filter(|value| !COVER_VARIANT_SIZES.contains(value)) {
+                return Err(EzBooksError::InvalidCoverSize(format!(
+                    "{} must be one of {:?}, got {}",
+                    name, COVER_VARIANT_SIZES, value
+                )));
+            }
+        }
+        if self.w.is_none() && self.h.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(CoverSize {
+            width: self.w,
+            height: self.h,
+        }))
+    }
+}
+
+/// Columns and rows of a montage when the request leaves them out
+pub const DEFAULT_MONTAGE_COLUMNS: u32 = 6;
+pub const DEFAULT_MONTAGE_ROWS: u32 = 4;
+/// Most columns or rows of a montage, which bounds the covers composited per request
+pub const MAX_MONTAGE_SIDE: u32 = 10;
+
+/// Query parameters for `GET /api/gallery/montage`
+#[derive(Debug, Default, Deserialize)]
+pub struct MontageQuery {
+    pub cols: Option<u32>,
+    pub rows: Option<u32>,
+}
+
+impl MontageQuery {
+    /// Requested columns and rows, each 1 to `MAX_MONTAGE_SIDE`
+    pub fn grid(&self) -> error::Result<(u32, u32)> {
+        let columns = self.cols.unwrap_or(DEFAULT_MONTAGE_COLUMNS);
+        let rows = self.rows.unwrap_or(DEFAULT_MONTAGE_ROWS);
+        for (name, value) in [("cols", columns), ("rows", rows)] {
+            if !(1..=MAX_MONTAGE_SIDE).contains(&value) {
+                return Err(EzBooksError::InvalidMontageGrid(format!(
+                    "{} must be between 1 and {}, got {}",
+                    name, MAX_MONTAGE_SIDE, value
+                )));
+            }
+        }
+        Ok((columns, rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_default_to_created_sort() {
+        // Given/When: Deserializing an empty query
+        let query: BooksQuery = serde_json::from_str("{}").unwrap();
+
+        // Then: Should sort by creation date unless configured otherwise
+        assert_eq!(query.sort, None);
+        let sort = query.sort_or(ListingDefaults::default().sort);
+        assert_eq!(sort, BookSort::Created);
+        assert_eq!(sort.order_by_clause(), "created_at DESC");
+        assert_eq!(query.sort_or(BookSort::Size), BookSort::Size);
+    }
+
+    #[test]
+    fn should_parse_size_sort() {
+        // Given/When: Deserializing a size sort
+        let query: BooksQuery = serde_json::from_str(r#"{"sort":"size"}"#).unwrap();
+
+        // Then: Should sort by file size with unknown sizes last
+        assert_eq!(query.sort, Some(BookSort::Size));
+        assert!(query
+            .sort_or(BookSort::Created)
+            .order_by_clause()
+            .starts_with("file_size_bytes IS NULL"));
+    }
+
+    #[test]
+    fn should_look_up_sort_by_name() {
+        // Given/When/Then: Query parameter names are accepted in any case
+        assert_eq!(BookSort::from_name(" Size "), Some(BookSort::Size));
+        assert_eq!(
+            BookSort::from_name("completeness"),
+            Some(BookSort::Completeness)
+        );
+        assert_eq!(BookSort::from_name("title"), None);
+    }
+
+    #[test]
+    fn should_reject_unknown_sort() {
+        // Given/When: Deserializing an unsupported sort
+        let result: Result<BooksQuery, _> = serde_json::from_str(r#"{"sort":"color"}"#);
+
+        // Then: Should fail
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn should_require_subject_for_next_book() {
+        // Given/When: Deserializing next-book queries with and without a subject
+        let query: NextBookQuery = serde_json::from_str(r#"{"subject":"Fantasy"}"#).unwrap();
+        let missing: Result<NextBookQuery, _> = serde_json::from_str(r#"{"lang":"en"}"#);
+
+        // Then: The subject is required, the language optional
+        assert_eq!(query.subject, "Fantasy");
+        assert!(query.lang.is_none());
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn should_round_trip_cursor() {
+        // Given: A cursor
+        let cursor = BookCursor {
+            created_at: 1_700_000_000,
+            id: "abc-123".to_string(),
+        };
+
+        // When: Encoding and decoding it
+        let decoded = BookCursor::decode(&cursor.encode()).unwrap();
+
+        // Then: It should be unchanged, and garbage should be rejected
+        assert_eq!(decoded, cursor);
+        assert!(BookCursor::decode("zz").is_err());
+        assert!(BookCursor::decode("616263").is_err());
+    }
+
+    #[test]
+    fn should_bound_page_limit() {
+        // Given/When/Then: Zero and oversized limits are rejected; cursor alone uses the default
+        let query = |limit, cursor: Option<&str>| BooksQuery {
+            limit,
+            cursor: cursor.map(str::to_string),
+            ..BooksQuery::default()
+        };
+        assert!(query(Some(0), None).page_limit(DEFAULT_PAGE_LIMIT).is_err());
+        assert!(query(Some(MAX_PAGE_LIMIT + 1), None)
+            .page_limit(DEFAULT_PAGE_LIMIT)
+            .is_err());
+        assert_eq!(
+            query(None, Some("00"))
+                .page_limit(DEFAULT_PAGE_LIMIT)
+                .unwrap(),
+            DEFAULT_PAGE_LIMIT
+        );
+        assert_eq!(query(None, Some("00")).page_limit(25).unwrap(), 25);
+        assert!(!query(None, None).is_paged());
+    }
+
+    #[test]
+    fn should_validate_added_range() {
+        // Given/When: Parsing date range queries
+        let parse = |query: &str| -> BooksQuery { serde_json::from_str(query).unwrap() };
+
+        // Then: Open ends are filled in and inverted ranges are rejected
+        assert_eq!(parse("{}").added_range().unwrap(), None);
+        assert_eq!(
+            parse(r#"{"added_from":100}"#).added_range().unwrap(),
+            Some((100, i64::MAX))
+        );
+        assert!(parse(r#"{"added_from":200,"added_to":100}"#)
+            .added_range()
+            .is_err());
+        assert!(parse(r#"{"added_to":100,"limit":5}"#)
+            .added_range()
+            .is_err());
+    }
+
+    #[test]
+    fn should_cap_recommended_limit() {
+        let limit = |limit| RecommendedQuery { limit }.limit();
+        assert_eq!(limit(None), DEFAULT_RECOMMENDED_LIMIT);
+        assert_eq!(limit(Some(3)), 3);
+        assert_eq!(limit(Some(10_000)), MAX_RECOMMENDED_LIMIT);
+    }
+
+    #[test]
+    fn should_only_accept_listed_cover_sizes() {
+        // Given/When: Cover queries with no size, allowed sizes and an arbitrary one
+        let query = |w, h| CoverQuery { v: None, w, h }.size();
+
+        // Then: Listed sizes give a box, other sizes are rejected
+        assert_eq!(query(None, None).unwrap(), None);
+        assert_eq!(
+            query(Some(150), None).unwrap(),
+            Some(CoverSize {
+                width: Some(150),
+                height: None
+            })
+        );
+        assert!(matches!(
+            query(Some(150), Some(451)),
+            Err(EzBooksError::InvalidCoverSize(_))
+        ));
+    }
+
+    #[test]
+    fn should_default_and_bound_montage_grid() {
+        // Given/When: Montage queries without, within and beyond the limits
+        let grid = |cols, rows| MontageQuery { cols, rows }.grid();
+
+        // Then: Missing sides get defaults, out-of-range ones are rejected
+        assert_eq!(
+            grid(None, None).unwrap(),
+            (DEFAULT_MONTAGE_COLUMNS, DEFAULT_MONTAGE_ROWS)
+        );
+        assert_eq!(grid(Some(10), Some(1)).unwrap(), (10, 1));
+        assert!(matches!(
+            grid(Some(0), None),
+            Err(EzBooksError::InvalidMontageGrid(_))
+        ));
+        assert!(matches!(
+            grid(None, Some(11)),
+            Err(EzBooksError::InvalidMontageGrid(_))
+        ));
+    }
+}
@@ -1,36 +1,64 @@
-use crate::book_model::Book;
+use crate::book_model::{current_timestamp, AdjacentBooks, AuthorSummary, Book, EnrichmentStatus};
+use crate::book_query::BookSort;
 use crate::database_connection::DatabasePool;
 use crate::error::{EzBooksError, Result};
+use crate::isbn::{isbn10_to_isbn13, isbn13_to_isbn10, normalize_isbn};
 use sqlx::Row;
+use std::collections::HashMap;
 use tracing::{info, instrument, warn};
 
 #[instrument(skip(pool, book))]
-pub async fn insert(pool: &DatabasePool, book: &Book) -> Result<()> {
+pub async fn insert<'e, E>(pool: E, book: &Book) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
     info!(book_id = %book.id, title = %book.title, "Inserting book into database");
 
     sqlx::query(
         r#"
         INSERT INTO books (
-            id, title, author, isbn_10, isbn_13, publisher, publish_date,
-            description, cover_image_path, epub_file_path, openlibrary_key,
-            openlibrary_work_key, page_count, language, created_at, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            id, title, raw_title, author, isbn_10, isbn_13, publisher, publish_date,
+            description, description_from_content, notes, cover_image_path, cover_hash, cover_mime,
+            cover_color, epub_file_path, original_filename, format, openlibrary_key,
+            openlibrary_work_key, read_status, read_url, page_count, language, language_detected,
+            has_audio_narration, epub_version, enrichment_status, file_size_bytes, content_hash,
+            created_at, updated_at
+        ) VALUES (
+            ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+            ?, ?, ?
+        )
         "#,
     )
     .bind(&book.id)
     .bind(&book.title)
+    .bind(&book.raw_title)
     .bind(&book.author)
     .bind(&book.isbn_10)
     .bind(&book.isbn_13)
     .bind(&book.publisher)
     .bind(&book.publish_date)
     .bind(&book.description)
+    .bind(book.description_from_content)
+    .bind(&book.notes)
     .bind(&book.cover_image_path)
+    .bind(&book.cover_hash)
+    .bind(&book.cover_mime)
+    .bind(&book.cover_color)
     .bind(&book.epub_file_path)
+    .bind(&book.original_filename)
+    .bind(&book.format)
     .bind(&book.openlibrary_key)
     .bind(&book.openlibrary_work_key)
+    .bind(&book.read_status)
+    .bind(&book.read_url)
     .bind(book.page_count)
     .bind(&book.language)
+    .bind(book.language_detected)
+    .bind(book.has_audio_narration)
+    .bind(&book.epub_version)
+    .bind(book.enrichment_status)
+    .bind(book.file_size_bytes)
+    .bind(&book.content_hash)
     .bind(book.created_at)
     .bind(book.updated_at)
     .execute(pool)
@@ -52,6 +80,170 @@ pub async fn find_all(pool: &DatabasePool) -> Result<Vec<Book>> {
     Ok(books)
 }
 
+/// Neighbours of `book` in the gallery listing for `sort`; ties on the sort keys
+/// are broken by id, as in cursor paging
+#[instrument(skip(pool, book), fields(book_id = %book.id))]
+pub async fn find_adjacent(
+    pool: &DatabasePool,
+    book: &Book,
+    sort: BookSort,
+) -> Result<AdjacentBooks> {
+    let (previous, next) = if sort == BookSort::Completeness {
+        // The score is computed in Rust, so the neighbours come from the sorted list
+        let books = find_all_sorted(pool, sort).await?;
+        match books.iter().position(|other| other.id == book.id) {
+            Some(index) => (
+                index
+                    .checked_sub(1)
+                    .and_then(|previous| books.get(previous))
+                    .map(|previous| previous.id.clone()),
+                books.get(index + 1).map(|next| next.id.clone()),
+            ),
+            None => (None, None),
+        }
+    } else {
+        let sql = format!(
+            "SELECT previous, next FROM (
+                SELECT id, LAG(id) OVER listing AS previous, LEAD(id) OVER listing AS next
+                FROM books
+                WINDOW listing AS (ORDER BY {}, id DESC)
+            ) WHERE id = ?",
+            sort.order_by_clause()
+        );
+        sqlx::query_as::<_, (Option<String>, Option<String>)>(&sql)
+            .bind(&book.id)
+            .fetch_optional(pool)
+            .await?
+            .unwrap_or_default()
+    };
+
+    Ok(AdjacentBooks {
+        sort,
+        previous,
+        next,
+    })
+}
+
+#[instrument(skip(pool))]
+pub async fn find_all_sorted(pool: &DatabasePool, sort: BookSort) -> Result<Vec<Book>> {
+    info!(sort = ?sort, "Fetching all books from database");
+
+    let sql = format!("SELECT * FROM books ORDER BY {}", sort.order_by_clause());
+    let books = sqlx::query_as::<_, Book>(&sql).fetch_all(pool).await?;
+
+    info!(count = books.len(), "Fetched all books");
+    Ok(sort_by_completeness_if_requested(books, sort))
+}
+
+/// Books of one `format` (compared case-insensitively) in `sort` order
+#[instrument(skip(pool))]
+pub async fn find_by_format(
+    pool: &DatabasePool,
+    format: &str,
+    sort: BookSort,
+) -> Result<Vec<Book>> {
+    info!(format = %format, sort = ?sort, "Fetching books by format");
+
+    let sql = format!(
+        "SELECT * FROM books WHERE format = lower(?) ORDER BY {}",
+        sort.order_by_clause()
+    );
+    let books = sqlx::query_as::<_, Book>(&sql)
+        .bind(format.trim())
+        .fetch_all(pool)
+        .await?;
+    info!(count = books.len(), "Fetched books by format");
+    Ok(sort_by_completeness_if_requested(books, sort))
+}
+
+/// Books whose author is `author`, ignoring case and surrounding space, newest first
+#[instrument(skip(pool))]
+pub async fn find_by_author(pool: &DatabasePool, author: &str) -> Result<Vec<Book>> {
+    info!(author = %author, "Fetching books by author");
+
+    let books = sqlx::query_as::<_, Book>(
+        "SELECT * FROM books WHERE trim(author) = ? COLLATE NOCASE ORDER BY created_at DESC, id",
+    )
+    .bind(author.trim())
+    .fetch_all(pool)
+    .await?;
+    info!(count = books.len(), "Fetched books by author");
+    Ok(books)
+}
+
+/// Distinct authors by name, with how many books each has; spellings differing only in
+/// case or surrounding space are counted as one
+#[instrument(skip(pool))]
+pub async fn find_authors(pool: &DatabasePool) -> Result<Vec<AuthorSummary>> {
+    let authors = sqlx::query_as::<_, AuthorSummary>(
+        r#"
+        SELECT MIN(trim(author)) AS name, COUNT(*) AS book_count FROM books
+        WHERE trim(author) != ''
+        GROUP BY trim(author) COLLATE NOCASE
+        ORDER BY name COLLATE NOCASE
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    info!(count = authors.len(), "Fetched authors");
+    Ok(authors)
+}
+
+fn sort_by_completeness_if_requested(mut books: Vec<Book>, sort: BookSort) -> Vec<Book> {
+    if sort == BookSort::Completeness {
+        // Stable, so equally complete books keep newest-first order
+        books.sort_by_key(Book::metadata_completeness);
+    }
+    books
+}
+
+/// Books created within `from_unix..=to_unix`, newest first
+#[instrument(skip(pool))]
+pub async fn find_created_between(
+    pool: &DatabasePool,
+    from_unix: i64,
+    to_unix: i64,
+) -> Result<Vec<Book>> {
+    let books = sqlx::query_as::<_, Book>(
+        "SELECT * FROM books WHERE created_at BETWEEN ? AND ? ORDER BY created_at DESC",
+    )
+    .bind(from_unix)
+    .bind(to_unix)
+    .fetch_all(pool)
+    .await?;
+
+    info!(count = books.len(), "Fetched books created in range");
+    Ok(books)
+}
+
+/// Up to `limit` books after the cursor in `created_at DESC, id DESC` order.
+///
+/// Keyset paging: cost does not grow with how far into the library the cursor is.
+#[instrument(skip(pool))]
+pub async fn find_after(
+    pool: &DatabasePool,
+    cursor_created_at: i64,
+    cursor_id: &str,
+    limit: u32,
+) -> Result<Vec<Book>> {
+    let books = sqlx::query_as::<_, Book>(
+        r#"
+        SELECT * FROM books
+        WHERE (created_at, id) < (?, ?)
+        ORDER BY created_at DESC, id DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(cursor_created_at)
+    .bind(cursor_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    info!(count = books.len(), "Fetched page of books");
+    Ok(books)
+}
+
 #[instrument(skip(pool))]
 pub async fn find_by_id(pool: &DatabasePool, id: &str) -> Result<Book> {
     info!(book_id = %id, "Fetching book by ID");
@@ -69,6 +261,318 @@ pub async fn find_by_id(pool: &DatabasePool, id: &str) -> Result<Book> {
     Ok(book)
 }
 
+/// Whether a book with `id` is stored, without loading its row
+#[instrument(skip(pool))]
+pub async fn exists(pool: &DatabasePool, id: &str) -> Result<bool> {
+    let found: Option<i64> = sqlx::query_scalar("SELECT 1 FROM books WHERE id = ? LIMIT 1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(found.is_some())
+}
+
+/// Ids bound per `find_by_ids` query, well below SQLite's limit on parameters
+const MAX_IDS_PER_QUERY: usize = 500;
+
+/// The books with any of `ids`, by id; ids of missing books are left out
+#[instrument(skip(pool, ids), fields(count = ids.len()))]
+pub async fn find_by_ids(pool: &DatabasePool, ids: &[String]) -> Result<HashMap<String, Book>> {
+    let mut books = HashMap::with_capacity(ids.len());
+    for chunk in ids.chunks(MAX_IDS_PER_QUERY) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let sql = format!("SELECT * FROM books WHERE id IN ({})", placeholders);
+        let mut query = sqlx::query_as::<_, Book>(&sql);
+        for id in chunk {
+            query = query.bind(id);
+        }
+        books.extend(
+            query
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .map(|book| (book.id.clone(), book)),
+        );
+    }
+
+    info!(
+        requested = ids.len(),
+        found = books.len(),
+        "Fetched books by ID"
+    );
+    Ok(books)
+}
+
+/// Finds a book by ISBN-10 or ISBN-13, ignoring hyphens, spaces and an "ISBN" prefix,
+/// and the book `except_id` if given
+#[instrument(skip(pool))]
+pub async fn find_by_isbn(
+    pool: &DatabasePool,
+    isbn: &str,
+    except_id: Option<&str>,
+) -> Result<Option<Book>> {
+    let normalized = normalize_isbn(isbn);
+    let (isbn_10, isbn_13) = match normalized.len() {
+        10 => (Some(normalized.clone()), isbn10_to_isbn13(&normalized)),
+        13 => (isbn13_to_isbn10(&normalized), Some(normalized)),
+        _ => return Ok(None),
+    };
+
+    let book = sqlx::query_as::<_, Book>(
+        r#"
+        SELECT * FROM books
+        WHERE (REPLACE(REPLACE(UPPER(isbn_13), '-', ''), ' ', '') = ?
+           OR REPLACE(REPLACE(UPPER(isbn_10), '-', ''), ' ', '') = ?)
+          AND id IS NOT ?
+        ORDER BY created_at ASC
+        LIMIT 1
+        "#,
+    )
+    .bind(isbn_13)
+    .bind(isbn_10)
+    .bind(except_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(book)
+}
+
+/// Persists the metadata enrichment changed between `loaded` and `book`, together with
+/// the final status. Fields it left alone keep what the row holds now, so edits made
+/// while OpenLibrary was being asked are not lost.
+#[instrument(skip(pool, loaded, book))]
+pub async fn update_enrichment(pool: &DatabasePool, loaded: &Book, book: &Book) -> Result<()> {
+    info!(book_id = %book.id, status = ?book.enrichment_status, "Updating enriched book metadata");
+
+    let description_changed = book.description != loaded.description
+        || book.description_from_content != loaded.description_from_content;
+    let result = sqlx::query(
+        r#"
+        UPDATE books SET
+            title = CASE WHEN ? THEN ? ELSE title END,
+            author = CASE WHEN ? THEN ? ELSE author END,
+            publisher = CASE WHEN ? THEN ? ELSE publisher END,
+            publish_date = CASE WHEN ? THEN ? ELSE publish_date END,
+            description = CASE WHEN ? THEN ? ELSE description END,
+            description_from_content = CASE WHEN ? THEN ? ELSE description_from_content END,
+            openlibrary_key = CASE WHEN ? THEN ? ELSE openlibrary_key END,
+            openlibrary_work_key = CASE WHEN ? THEN ? ELSE openlibrary_work_key END,
+            page_count = CASE WHEN ? THEN ? ELSE page_count END,
+            read_status = CASE WHEN ? THEN ? ELSE read_status END,
+            read_url = CASE WHEN ? THEN ? ELSE read_url END,
+            enrichment_status = ?, updated_at = ?
+        WHERE id = ?
+        "#,
+    )
+    .bind(book.title != loaded.title)
+    .bind(&book.title)
+    .bind(book.author != loaded.author)
+    .bind(&book.author)
+    .bind(book.publisher != loaded.publisher)
+    .bind(&book.publisher)
+    .bind(book.publish_date != loaded.publish_date)
+    .bind(&book.publish_date)
+    .bind(description_changed)
+    .bind(&book.description)
+    .bind(description_changed)
+    .bind(book.description_from_content)
+    .bind(book.openlibrary_key != loaded.openlibrary_key)
+    .bind(&book.openlibrary_key)
+    .bind(book.openlibrary_work_key != loaded.openlibrary_work_key)
+    .bind(&book.openlibrary_work_key)
+    .bind(book.page_count != loaded.page_count)
+    .bind(book.page_count)
+    .bind(book.read_status != loaded.read_status)
+    .bind(&book.read_status)
+    .bind(book.read_url != loaded.read_url)
+    .bind(&book.read_url)
+    .bind(book.enrichment_status)
+    .bind(current_timestamp())
+    .bind(&book.id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        warn!(book_id = %book.id, "Book not found for enrichment update");
+        return Err(EzBooksError::BookNotFound(book.id.clone()));
+    }
+
+    info!(book_id = %book.id, "Enriched metadata updated successfully");
+    Ok(())
+}
+
+/// Persists user-edited metadata fields
+#[instrument(skip(pool, book))]
+pub async fn update_metadata(pool: &DatabasePool, book: &Book) -> Result<()> {
+    info!(book_id = %book.id, "Updating book metadata");
+
+    let result = sqlx::query(
+        r#"
+        UPDATE books SET
+            title = ?, author = ?, isbn_10 = ?, isbn_13 = ?, publisher = ?,
+            publish_date = ?, description = ?, description_from_content = ?, notes = ?,
+            page_count = ?, language = ?, language_detected = ?, updated_at = ?
+        WHERE id = ?
+        "#,
+    )
+    .bind(&book.title)
+    .bind(&book.author)
+    .bind(&book.isbn_10)
+    .bind(&book.isbn_13)
+    .bind(&book.publisher)
+    .bind(&book.publish_date)
+    .bind(&book.description)
+    .bind(book.description_from_content)
+    .bind(&book.notes)
+    .bind(book.page_count)
+    .bind(&book.language)
+    .bind(book.language_detected)
+    .bind(book.updated_at)
+    .bind(&book.id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        warn!(book_id = %book.id, "Book not found for metadata update");
+        return Err(EzBooksError::BookNotFound(book.id.clone()));
+    }
+
+    info!(book_id = %book.id, "Book metadata updated successfully");
+    Ok(())
+}
+
+/// Persists every metadata field EPUB parsing or enrichment sets, as
+/// `reset_to_epub_metadata` leaves them
+#[instrument(skip(pool, book))]
+pub async fn update_reset_metadata(pool: &DatabasePool, book: &Book) -> Result<()> {
+    info!(book_id = %book.id, "Resetting book metadata");
+
+    let result = sqlx::query(
+        r#"
+        UPDATE books SET
+            title = ?, raw_title = ?, author = ?, isbn_10 = ?, isbn_13 = ?, publisher = ?,
+            publish_date = ?, description = ?, description_from_content = ?, page_count = ?, language = ?,
+            language_detected = ?, openlibrary_key = ?, openlibrary_work_key = ?, read_status = ?,
+            read_url = ?, enrichment_status = ?, updated_at = ?
+        WHERE id = ?
+        "#,
+    )
+    .bind(&book.title)
+    .bind(&book.raw_title)
+    .bind(&book.author)
+    .bind(&book.isbn_10)
+    .bind(&book.isbn_13)
+    .bind(&book.publisher)
+    .bind(&book.publish_date)
+    .bind(&book.description)
+    .bind(book.description_from_content)
+    .bind(book.page_count)
+    .bind(&book.language)
+    .bind(book.language_detected)
+    .bind(&book.openlibrary_key)
+    .bind(&book.openlibrary_work_key)
+    .bind(&book.read_status)
+    .bind(&book.read_url)
+    .bind(book.enrichment_status)
+    .bind(book.updated_at)
+    .bind(&book.id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        warn!(book_id = %book.id, "Book not found for metadata reset");
+        return Err(EzBooksError::BookNotFound(book.id.clone()));
+    }
+    Ok(())
+}
+
+/// Persists the file-derived fields of a book whose EPUB was replaced; its id, notes,
+/// enrichment and relations are left alone
+#[instrument(skip(pool, book))]
+pub async fn update_replaced_file<'e, E>(pool: E, book: &Book) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    info!(book_id = %book.id, "Updating book after file replacement");
+
+    let result = sqlx::query(
+        r#"
+        UPDATE books SET
+            title = ?, raw_title = ?, author = ?, isbn_10 = ?, isbn_13 = ?, publisher = ?,
+            description = ?, description_from_content = ?, language = ?, language_detected = ?,
+            has_audio_narration = ?,
+            epub_version = ?, cover_image_path = ?, cover_hash = ?, cover_mime = ?, cover_color = ?,
+            cover_corrupt = 0, epub_file_path = ?, original_filename = ?, format = ?,
+            file_size_bytes = ?, content_hash = ?, enrichment_status = ?, updated_at = ?
+        WHERE id = ?
+        "#,
+    )
+    .bind(&book.title)
+    .bind(&book.raw_title)
+    .bind(&book.author)
+    .bind(&book.isbn_10)
+    .bind(&book.isbn_13)
+    .bind(&book.publisher)
+    .bind(&book.description)
+    .bind(book.description_from_content)
+    .bind(&book.language)
+    .bind(book.language_detected)
+    .bind(book.has_audio_narration)
+    .bind(&book.epub_version)
+    .bind(&book.cover_image_path)
+    .bind(&book.cover_hash)
+    .bind(&book.cover_mime)
+    .bind(&book.cover_color)
+    .bind(&book.epub_file_path)
+    .bind(&book.original_filename)
+    .bind(&book.format)
+    .bind(book.file_size_bytes)
+    .bind(&book.content_hash)
+    .bind(book.enrichment_status)
+    .bind(book.updated_at)
+    .bind(&book.id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        warn!(book_id = %book.id, "Book not found for file replacement");
+        return Err(EzBooksError::BookNotFound(book.id.clone()));
+    }
+    Ok(())
+}
+
+#[instrument(skip(pool))]
+pub async fn update_enrichment_status(
+    pool: &DatabasePool,
+    id: &str,
+    status: EnrichmentStatus,
+) -> Result<()> {
+    info!(book_id = %id, status = ?status, "Updating enrichment status");
+
+    sqlx::query("UPDATE books SET enrichment_status = ?, updated_at = ? WHERE id = ?")
+        .bind(status)
+        .bind(current_timestamp())
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Ids of books with the given enrichment status, oldest first
+#[instrument(skip(pool))]
+pub async fn find_ids_by_enrichment_status(
+    pool: &DatabasePool,
+    status: EnrichmentStatus,
+) -> Result<Vec<String>> {
+    let ids = sqlx::query_scalar(
+        "SELECT id FROM books WHERE enrichment_status = ? ORDER BY created_at ASC",
+    )
+    .bind(status)
+    .fetch_all(pool)
+    .await?;
+    Ok(ids)
+}
+
 #[instrument(skip(pool))]
 pub async fn delete(pool: &DatabasePool, id: &str) -> Result<()> {
     info!(book_id = %id, "Deleting book from database");
@@ -87,52 +591,430 @@ pub async fn delete(pool: &DatabasePool, id: &str) -> Result<()> {
     Ok(())
 }
 
+/// Latest time any book was added, changed, deleted or had its reading progress changed
+/// or reset; `None` for a library that never had books
+#[instrument(skip(pool))]
+pub async fn max_updated_at(pool: &DatabasePool) -> Result<Option<i64>> {
+    let row = sqlx::query(
+        r#"
+        SELECT MAX(changed_at) FROM (
+            SELECT MAX(updated_at, created_at) AS changed_at FROM books
+            UNION ALL
+            SELECT updated_at FROM reading_progress
+            UNION ALL
+            SELECT last_deleted_at FROM library_changes
+        )
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.get::<Option<i64>, _>(0))
+}
+
+/// Trims a subject and collapses inner whitespace, so "  Science   Fiction " is stored as "Science Fiction"
+pub fn normalize_subject(subject: &str) -> String {
+    subject.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Stores the normalized subject; fails with `DuplicateSubject` if the book already
+/// has it in any letter case
 #[instrument(skip(pool))]
-pub async fn insert_subject(pool: &DatabasePool, book_id: &str, subject: &str) -> Result<()> {
+pub async fn insert_subject<'e, E>(pool: E, book_id: &str, subject: &str) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    let subject = normalize_subject(subject);
     info!(book_id = %book_id, subject = %subject, "Inserting book subject");
 
-    sqlx::query("INSERT INTO book_subjects (book_id, subject) VALUES (?, ?)")
-        .bind(book_id)
-        .bind(subject)
+    let result = sqlx::query(
+        r#"
+        INSERT INTO book_subjects (book_id, subject)
+        SELECT ?1, ?2 WHERE NOT EXISTS (
+            SELECT 1 FROM book_subjects WHERE book_id = ?1 AND subject = ?2 COLLATE NOCASE
+        )
+        "#,
+    )
+    .bind(book_id)
+    .bind(&subject)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(EzBooksError::DuplicateSubject {
+            book_id: book_id.to_string(),
+            subject,
+        });
+    }
+
+    info!(book_id = %book_id, subject = %subject, "Subject inserted successfully");
+    Ok(())
+}
+
+/// Stores every normalized subject the book does not have yet in one statement, so
+/// either all or none are added. Blank entries and repeats in any letter case are
+/// skipped rather than failing; returns how many were added.
+#[instrument(skip(pool, subjects), fields(count = subjects.len()))]
+pub async fn insert_subjects<'e, E>(pool: E, book_id: &str, subjects: &[String]) -> Result<u64>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    let mut unique: Vec<String> = Vec::new();
+    for subject in subjects.iter().map(|subject| normalize_subject(subject)) {
+        let seen = unique
+            .iter()
+            .any(|kept| kept.to_lowercase() == subject.to_lowercase());
+        if !subject.is_empty() && !seen {
+            unique.push(subject);
+        }
+    }
+    if unique.is_empty() {
+        return Ok(0);
+    }
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO book_subjects (book_id, subject)
+        SELECT ?1, value FROM json_each(?2) WHERE NOT EXISTS (
+            SELECT 1 FROM book_subjects WHERE book_id = ?1 AND subject = value COLLATE NOCASE
+        )
+        ORDER BY key
+        "#,
+    )
+    .bind(book_id)
+    .bind(serde_json::to_string(&unique)?)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    info!(book_id = %book_id, inserted, "Subjects inserted");
+    Ok(inserted)
+}
+
+/// Removes a subject matched case-insensitively after normalization
+#[instrument(skip(pool))]
+pub async fn delete_subject(pool: &DatabasePool, book_id: &str, subject: &str) -> Result<()> {
+    let subject = normalize_subject(subject);
+    info!(book_id = %book_id, subject = %subject, "Deleting book subject");
+
+    let result =
+        sqlx::query("DELETE FROM book_subjects WHERE book_id = ? AND subject = ? COLLATE NOCASE")
+            .bind(book_id)
+            .bind(&subject)
+            .execute(pool)
+            .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(EzBooksError::SubjectNotFound {
+            book_id: book_id.to_string(),
+            subject,
+        });
+    }
+    Ok(())
+}
+
+/// Bumps `updated_at` for changes stored outside the books row, such as subjects
+#[instrument(skip(pool))]
+pub async fn touch(pool: &DatabasePool, id: &str) -> Result<()> {
+    sqlx::query("UPDATE books SET updated_at = ? WHERE id = ?")
+        .bind(current_timestamp())
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[instrument(skip(pool))]
+pub async fn find_subjects_by_book_id(pool: &DatabasePool, book_id: &str) -> Result<Vec<String>> {
+    info!(book_id = %book_id, "Fetching subjects for book");
+
+    let subjects: Vec<String> = sqlx::query("SELECT subject FROM book_subjects WHERE book_id = ?")
+        .bind(book_id)
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| row.get("subject"))
+        .collect();
+
+    info!(book_id = %book_id, count = subjects.len(), "Fetched subjects");
+    Ok(subjects)
+}
+
+/// Books without an author, description or cover, oldest-added first
+#[instrument(skip(pool))]
+pub async fn find_missing_metadata(pool: &DatabasePool) -> Result<Vec<Book>> {
+    let books = sqlx::query_as::<_, Book>(
+        r#"
+        SELECT * FROM books
+        WHERE author IS NULL OR TRIM(author) = ''
+            OR description IS NULL OR TRIM(description) = '' OR description_from_content = 1
+            OR cover_image_path IS NULL
+        ORDER BY created_at ASC, rowid ASC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(books)
+}
+
+/// Books tagged with `subject` (case-insensitive), oldest-added first
+#[instrument(skip(pool))]
+pub async fn find_books_by_subject(pool: &DatabasePool, subject: &str) -> Result<Vec<Book>> {
+    info!(subject = %subject, "Fetching books by subject");
+
+    let books = sqlx::query_as::<_, Book>(
+        r#"
+        SELECT books.* FROM books
+        JOIN book_subjects ON book_subjects.book_id = books.id
+        WHERE book_subjects.subject = ? COLLATE NOCASE
+        ORDER BY books.created_at ASC, books.rowid ASC
+        "#,
+    )
+    .bind(subject)
+    .fetch_all(pool)
+    .await?;
+
+    info!(subject = %subject, count = books.len(), "Fetched books by subject");
+    Ok(books)
+}
+
+#[instrument(skip(pool))]
+pub async fn count_books(pool: &DatabasePool) -> Result<i64> {
+    let count = sqlx::query_scalar("SELECT COUNT(*) FROM books")
+        .fetch_one(pool)
+        .await?;
+    Ok(count)
+}
+
+#[instrument(skip(pool))]
+pub async fn count_missing_covers(pool: &DatabasePool) -> Result<i64> {
+    let count = sqlx::query_scalar("SELECT COUNT(*) FROM books WHERE cover_image_path IS NULL")
+        .fetch_one(pool)
+        .await?;
+    Ok(count)
+}
+
+#[instrument(skip(pool))]
+pub async fn sum_file_sizes(pool: &DatabasePool) -> Result<i64> {
+    let total = sqlx::query_scalar("SELECT COALESCE(SUM(file_size_bytes), 0) FROM books")
+        .fetch_one(pool)
+        .await?;
+    Ok(total)
+}
+
+/// Ids of books imported before file sizes were tracked
+#[instrument(skip(pool))]
+pub async fn find_ids_missing_file_size(pool: &DatabasePool) -> Result<Vec<String>> {
+    let ids = sqlx::query_scalar("SELECT id FROM books WHERE file_size_bytes IS NULL")
+        .fetch_all(pool)
+        .await?;
+    Ok(ids)
+}
+
+#[instrument(skip(pool))]
+pub async fn update_file_size(pool: &DatabasePool, id: &str, file_size_bytes: i64) -> Result<()> {
+    sqlx::query("UPDATE books SET file_size_bytes = ? WHERE id = ?")
+        .bind(file_size_bytes)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Points a book at relocated files; books without a cover keep a NULL cover path
+#[instrument(skip(pool))]
+pub async fn update_storage_paths(
+    pool: &DatabasePool,
+    id: &str,
+    epub_file_path: &str,
+    cover_image_path: &str,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE books SET
+            epub_file_path = ?,
+            cover_image_path = CASE WHEN cover_image_path IS NULL THEN NULL ELSE ? END
+        WHERE id = ?
+        "#,
+    )
+    .bind(epub_file_path)
+    .bind(cover_image_path)
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[instrument(skip(pool))]
+pub async fn find_by_content_hash(pool: &DatabasePool, content_hash: &str) -> Result<Option<Book>> {
+    let book = sqlx::query_as::<_, Book>("SELECT * FROM books WHERE content_hash = ? LIMIT 1")
+        .bind(content_hash)
+        .fetch_optional(pool)
+        .await?;
+    Ok(book)
+}
+
+#[instrument(skip(pool))]
+pub async fn find_ids_missing_content_hash(pool: &DatabasePool) -> Result<Vec<String>> {
+    let ids = sqlx::query_scalar("SELECT id FROM books WHERE content_hash IS NULL")
+        .fetch_all(pool)
+        .await?;
+    Ok(ids)
+}
+
+#[instrument(skip(pool))]
+pub async fn update_content_hash(pool: &DatabasePool, id: &str, content_hash: &str) -> Result<()> {
+    sqlx::query("UPDATE books SET content_hash = ? WHERE id = ?")
+        .bind(content_hash)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Ids of books with a cover but no recorded cover hash
+#[instrument(skip(pool))]
+pub async fn find_ids_missing_cover_hash(pool: &DatabasePool) -> Result<Vec<String>> {
+    let ids = sqlx::query_scalar(
+        "SELECT id FROM books WHERE cover_image_path IS NOT NULL AND cover_hash IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(ids)
+}
+
+#[instrument(skip(pool))]
+pub async fn update_cover_hash(pool: &DatabasePool, id: &str, cover_hash: &str) -> Result<()> {
+    sqlx::query("UPDATE books SET cover_hash = ? WHERE id = ?")
+        .bind(cover_hash)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[instrument(skip(pool))]
+pub async fn find_ids_missing_cover_color(pool: &DatabasePool) -> Result<Vec<String>> {
+    let ids = sqlx::query_scalar(
+        "SELECT id FROM books WHERE cover_image_path IS NOT NULL AND cover_color IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(ids)
+}
+
+#[instrument(skip(pool))]
+pub async fn update_cover_color(pool: &DatabasePool, id: &str, cover_color: &str) -> Result<()> {
+    sqlx::query("UPDATE books SET cover_color = ? WHERE id = ?")
+        .bind(cover_color)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Points the book at a newly stored cover; `updated_at` changes so cached pages pick it up
+#[instrument(skip(pool))]
+pub async fn update_cover(
+    pool: &DatabasePool,
+    id: &str,
+    cover_image_path: &str,
+    cover_hash: &str,
+    cover_mime: &str,
+    cover_color: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE books SET cover_image_path = ?, cover_hash = ?, cover_mime = ?, cover_color = ?, updated_at = ? WHERE id = ?",
+    )
+    .bind(cover_image_path)
+    .bind(cover_hash)
+    .bind(cover_mime)
+    .bind(cover_color)
+    .bind(current_timestamp())
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Ids and cover hashes of the newest books with a cover, at most `limit`
+#[instrument(skip(pool))]
+pub async fn find_newest_covers(
+    pool: &DatabasePool,
+    limit: u32,
+) -> Result<Vec<(String, Option<String>)>> {
+    let covers = sqlx::query_as(
+        "SELECT id, cover_hash FROM books WHERE cover_image_path IS NOT NULL ORDER BY created_at DESC, id LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(covers)
+}
+
+/// Stored content type of a book's cover; `None` for unknown books and legacy covers
+#[instrument(skip(pool))]
+pub async fn find_cover_mime(pool: &DatabasePool, id: &str) -> Result<Option<String>> {
+    let mime = sqlx::query_scalar("SELECT cover_mime FROM books WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(mime.flatten())
+}
+
+/// Records that a book's stored cover failed to decode
+#[instrument(skip(pool))]
+pub async fn mark_cover_corrupt(pool: &DatabasePool, id: &str) -> Result<()> {
+    sqlx::query("UPDATE books SET cover_corrupt = 1 WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Ids of books whose cover was flagged by `mark_cover_corrupt`
+#[instrument(skip(pool))]
+pub async fn find_ids_with_corrupt_cover(pool: &DatabasePool) -> Result<Vec<String>> {
+    let ids = sqlx::query_scalar("SELECT id FROM books WHERE cover_corrupt = 1 ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+    Ok(ids)
+}
+
+/// Forgets a cover whose file is gone or unreadable, so the book is shown without one
+#[instrument(skip(pool))]
+pub async fn clear_cover(pool: &DatabasePool, id: &str) -> Result<()> {
+    sqlx::query(
+        "UPDATE books SET cover_image_path = NULL, cover_hash = NULL, cover_mime = NULL, cover_color = NULL, cover_corrupt = 0 WHERE id = ?",
+    )
+        .bind(id)
         .execute(pool)
         .await?;
-
-    info!(book_id = %book_id, subject = %subject, "Subject inserted successfully");
     Ok(())
 }
 
 #[instrument(skip(pool))]
-pub async fn find_subjects_by_book_id(pool: &DatabasePool, book_id: &str) -> Result<Vec<String>> {
-    info!(book_id = %book_id, "Fetching subjects for book");
+pub async fn average_page_count(pool: &DatabasePool) -> Result<Option<f64>> {
+    let average = sqlx::query_scalar("SELECT AVG(page_count) FROM books")
+        .fetch_one(pool)
+        .await?;
+    Ok(average)
+}
 
-    let subjects: Vec<String> = sqlx::query("SELECT subject FROM book_subjects WHERE book_id = ?")
-        .bind(book_id)
+/// Returns the number of books per language; books without a language are grouped under `None`
+#[instrument(skip(pool))]
+pub async fn count_by_language(pool: &DatabasePool) -> Result<Vec<(Option<String>, i64)>> {
+    let counts = sqlx::query_as("SELECT language, COUNT(*) FROM books GROUP BY language")
         .fetch_all(pool)
-        .await?
-        .iter()
-        .map(|row| row.get("subject"))
-        .collect();
-
-    info!(book_id = %book_id, count = subjects.len(), "Fetched subjects");
-    Ok(subjects)
+        .await?;
+    Ok(counts)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::database_connection::{create_pool, run_migrations};
-    use tempfile::TempDir;
-
-    async fn setup_test_db() -> (DatabasePool, TempDir) {
-        let temp_dir = TempDir::new().unwrap();
-        let db_path = temp_dir.path().join("test.db");
-        let database_url = format!("sqlite://{}", db_path.display());
-
-        let pool = create_pool(&database_url).await.unwrap();
-        run_migrations(&pool).await.unwrap();
-
-        (pool, temp_dir)
-    }
+    use crate::book_model::EPUB_FORMAT;
+    use crate::test_fixtures::setup_test_db;
 
     fn create_test_book() -> Book {
         Book::new("Test Book".to_string(), "/path/to/book.epub".to_string())
@@ -181,6 +1063,49 @@ mod tests {
         assert!(matches!(result.unwrap_err(), EzBooksError::BookNotFound(_)));
     }
 
+    #[tokio::test]
+    async fn should_tell_whether_a_book_exists() {
+        // Given: A database with one book
+        let (pool, _temp_dir) = setup_test_db().await;
+        let book = create_test_book();
+        insert(&pool, &book).await.unwrap();
+
+        // When/Then: Its id exists and an unknown one does not
+        assert!(exists(&pool, &book.id).await.unwrap());
+        assert!(!exists(&pool, "non-existent-id").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_find_existing_books_by_ids_in_one_call() {
+        // Given: Two stored books
+        let (pool, _temp_dir) = setup_test_db().await;
+        let first = create_test_book();
+        let second = create_test_book();
+        insert(&pool, &first).await.unwrap();
+        insert(&pool, &second).await.unwrap();
+
+        // When: Fetching them with a missing id and a duplicate, and fetching nothing
+        let found = find_by_ids(
+            &pool,
+            &[
+                first.id.clone(),
+                "missing".to_string(),
+                second.id.clone(),
+                first.id.clone(),
+            ],
+        )
+        .await
+        .unwrap();
+        let none = find_by_ids(&pool, &[]).await.unwrap();
+
+        // Then: Only the stored books come back, once each
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[&first.id].title, first.title);
+        assert_eq!(found[&second.id].id, second.id);
+        assert!(!found.contains_key("missing"));
+        assert!(none.is_empty());
+    }
+
     #[tokio::test]
     async fn should_find_all_books() {
         // Given: Multiple books in the database
@@ -218,6 +1143,87 @@ mod tests {
         assert_eq!(books[1].id, book1.id);
     }
 
+    #[tokio::test]
+    async fn should_persist_enrichment_status_on_insert() {
+        // Given: A book awaiting enrichment
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = create_test_book();
+        book.enrichment_status = EnrichmentStatus::Pending;
+
+        // When: Inserting and reading it back
+        insert(&pool, &book).await.unwrap();
+        let found = find_by_id(&pool, &book.id).await.unwrap();
+
+        // Then: Status should round-trip
+        assert_eq!(found.enrichment_status, EnrichmentStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn should_update_enriched_metadata_and_status() {
+        // Given: A pending book in the database
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = create_test_book();
+        book.enrichment_status = EnrichmentStatus::Pending;
+        insert(&pool, &book).await.unwrap();
+
+        // When: Updating it with enriched metadata
+        let loaded = book.clone();
+        book.publisher = Some("Enriched Publisher".to_string());
+        book.page_count = Some(321);
+        book.enrichment_status = EnrichmentStatus::Done;
+        update_enrichment(&pool, &loaded, &book).await.unwrap();
+
+        // Then: The stored row should reflect the enrichment
+        let found = find_by_id(&pool, &book.id).await.unwrap();
+        assert_eq!(found.publisher, Some("Enriched Publisher".to_string()));
+        assert_eq!(found.page_count, Some(321));
+        assert_eq!(found.enrichment_status, EnrichmentStatus::Done);
+    }
+
+    #[tokio::test]
+    async fn should_keep_edits_made_while_enriching() {
+        // Given: A pending book loaded for enrichment, then edited by its reader
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = create_test_book();
+        book.enrichment_status = EnrichmentStatus::Pending;
+        insert(&pool, &book).await.unwrap();
+        let loaded = find_by_id(&pool, &book.id).await.unwrap();
+        book.title = "Edited Title".to_string();
+        book.author = Some("Edited Author".to_string());
+        update_metadata(&pool, &book).await.unwrap();
+
+        // When: Storing enrichment that only found a publisher
+        let mut enriched = loaded.clone();
+        enriched.publisher = Some("Enriched Publisher".to_string());
+        enriched.enrichment_status = EnrichmentStatus::Done;
+        update_enrichment(&pool, &loaded, &enriched).await.unwrap();
+
+        // Then: The edits and the enrichment are both kept
+        let found = find_by_id(&pool, &book.id).await.unwrap();
+        assert_eq!(found.title, "Edited Title");
+        assert_eq!(found.author, Some("Edited Author".to_string()));
+        assert_eq!(found.publisher, Some("Enriched Publisher".to_string()));
+        assert_eq!(found.enrichment_status, EnrichmentStatus::Done);
+    }
+
+    #[tokio::test]
+    async fn should_update_enrichment_status() {
+        // Given: A pending book in the database
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = create_test_book();
+        book.enrichment_status = EnrichmentStatus::Pending;
+        insert(&pool, &book).await.unwrap();
+
+        // When: Marking enrichment as failed
+        update_enrichment_status(&pool, &book.id, EnrichmentStatus::Failed)
+            .await
+            .unwrap();
+
+        // Then: The status should be failed
+        let found = find_by_id(&pool, &book.id).await.unwrap();
+        assert_eq!(found.enrichment_status, EnrichmentStatus::Failed);
+    }
+
     #[tokio::test]
     async fn should_delete_book_successfully() {
         // Given: A book in the database
@@ -285,6 +1291,60 @@ mod tests {
         assert!(subjects.contains(&"Science Fiction".to_string()));
     }
 
+    #[tokio::test]
+    async fn should_update_metadata_fields() {
+        // Given: A stored book with edited metadata
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = create_test_book();
+        insert(&pool, &book).await.unwrap();
+        book.title = "Edited".to_string();
+        book.isbn_13 = Some("9780306406157".to_string());
+        book.language = Some("de".to_string());
+
+        // When: Updating the metadata
+        update_metadata(&pool, &book).await.unwrap();
+
+        // Then: The stored row should reflect the edits
+        let stored = find_by_id(&pool, &book.id).await.unwrap();
+        assert_eq!(stored.title, "Edited");
+        assert_eq!(stored.isbn_13, Some("9780306406157".to_string()));
+        assert_eq!(stored.language, Some("de".to_string()));
+    }
+
+    #[tokio::test]
+    async fn should_fail_metadata_update_for_missing_book() {
+        // Given: A book that was never stored
+        let (pool, _temp_dir) = setup_test_db().await;
+
+        // When: Updating its metadata
+        let result = update_metadata(&pool, &create_test_book()).await;
+
+        // Then: Should report the book as missing
+        assert!(matches!(result, Err(EzBooksError::BookNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn should_find_books_by_subject_oldest_first() {
+        // Given: Two tagged books and one with another subject
+        let (pool, _temp_dir) = setup_test_db().await;
+        let older = create_test_book();
+        let newer = Book::new("Newer".to_string(), "/path/to/newer.epub".to_string());
+        let other = Book::new("Other".to_string(), "/path/to/other.epub".to_string());
+        for book in [&older, &newer, &other] {
+            insert(&pool, book).await.unwrap();
+        }
+        insert_subject(&pool, &newer.id, "Fantasy").await.unwrap();
+        insert_subject(&pool, &older.id, "Fantasy").await.unwrap();
+        insert_subject(&pool, &other.id, "History").await.unwrap();
+
+        // When: Finding books by subject with different casing
+        let books = find_books_by_subject(&pool, "fantasy").await.unwrap();
+
+        // Then: Only tagged books are returned, oldest first
+        let ids: Vec<&str> = books.iter().map(|book| book.id.as_str()).collect();
+        assert_eq!(ids, vec![older.id.as_str(), newer.id.as_str()]);
+    }
+
     #[tokio::test]
     async fn should_delete_subjects_when_book_deleted() {
         // Given: A book with subjects
@@ -301,6 +1361,429 @@ mod tests {
         assert_eq!(subjects.len(), 0);
     }
 
+    #[tokio::test]
+    async fn should_move_max_updated_at_on_changes_and_deletes() {
+        // Given: An empty library, then two books last changed long ago
+        let (pool, _temp_dir) = setup_test_db().await;
+        let empty = max_updated_at(&pool).await.unwrap();
+        let mut old = create_test_book();
+        old.created_at = 1_000;
+        old.updated_at = 2_000;
+        let mut older = create_test_book();
+        older.id = "older".to_string();
+        older.created_at = 500;
+        older.updated_at = 500;
+        insert(&pool, &old).await.unwrap();
+        insert(&pool, &older).await.unwrap();
+        let before = max_updated_at(&pool).await.unwrap();
+
+        // When: Deleting the book that is not the newest
+        delete(&pool, &older.id).await.unwrap();
+        let after = max_updated_at(&pool).await.unwrap();
+
+        // Then: The latest change is the newest book, then the deletion
+        assert_eq!(empty, None);
+        assert_eq!(before, Some(2_000));
+        assert!(after.unwrap() >= current_timestamp() - 5);
+    }
+
+    #[tokio::test]
+    async fn should_move_max_updated_at_on_progress_changes_and_resets() {
+        // Given: A book last changed long ago
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = create_test_book();
+        book.created_at = 1_000;
+        book.updated_at = 1_000;
+        insert(&pool, &book).await.unwrap();
+
+        // When: Finishing it, and separately resetting its progress
+        crate::progress_repository::mark_finished(&pool, &book.id)
+            .await
+            .unwrap();
+        let finished = max_updated_at(&pool).await.unwrap();
+        sqlx::query("UPDATE reading_progress SET updated_at = 1000")
+            .execute(&pool)
+            .await
+            .unwrap();
+        crate::progress_repository::reset(&pool, &book.id)
+            .await
+            .unwrap();
+        let reset = max_updated_at(&pool).await.unwrap();
+
+        // Then: Both move the latest change, while the book itself is untouched
+        assert!(finished.unwrap() >= current_timestamp() - 5);
+        assert!(reset.unwrap() >= current_timestamp() - 5);
+        assert_eq!(find_by_id(&pool, &book.id).await.unwrap().updated_at, 1_000);
+    }
+
+    #[tokio::test]
+    async fn should_aggregate_book_counts() {
+        // Given: Books with and without covers, languages and page counts
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book1 = create_test_book();
+        book1.language = Some("en".to_string());
+        book1.page_count = Some(100);
+        book1.cover_image_path = Some("/covers/1.jpg".to_string());
+        let mut book2 = create_test_book();
+        book2.language = Some("en".to_string());
+        book2.page_count = Some(300);
+        let book3 = create_test_book();
+        for book in [&book1, &book2, &book3] {
+            insert(&pool, book).await.unwrap();
+        }
+
+        // When: Running the aggregate queries
+        let total = count_books(&pool).await.unwrap();
+        let missing_covers = count_missing_covers(&pool).await.unwrap();
+        let average = average_page_count(&pool).await.unwrap();
+        let by_language = count_by_language(&pool).await.unwrap();
+
+        // Then: Aggregates should reflect the stored books
+        assert_eq!(total, 3);
+        assert_eq!(missing_covers, 2);
+        assert_eq!(average, Some(200.0));
+        assert!(by_language.contains(&(Some("en".to_string()), 2)));
+        assert!(by_language.contains(&(None, 1)));
+    }
+
+    #[tokio::test]
+    async fn should_sort_books_by_file_size_with_unknown_last() {
+        // Given: Books of different sizes and one without a size
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut small = create_test_book();
+        small.file_size_bytes = Some(10);
+        let mut large = create_test_book();
+        large.file_size_bytes = Some(5_000_000_000);
+        let unknown = create_test_book();
+        for book in [&unknown, &small, &large] {
+            insert(&pool, book).await.unwrap();
+        }
+
+        // When: Sorting by size
+        let books = find_all_sorted(&pool, BookSort::Size).await.unwrap();
+
+        // Then: Largest comes first and unknown sizes last
+        let ids: Vec<&str> = books.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![large.id.as_str(), small.id.as_str(), unknown.id.as_str()]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_sort_least_complete_books_first() {
+        // Given: A well documented book, a bare one and a partly documented one
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut documented = create_test_book();
+        documented.author = Some("Author".to_string());
+        documented.isbn_13 = Some("9780306406157".to_string());
+        let bare = Book::new("Unknown".to_string(), "/bare.epub".to_string());
+        let mut partial = create_test_book();
+        partial.author = Some("Author".to_string());
+        for book in [&documented, &bare, &partial] {
+            insert(&pool, book).await.unwrap();
+        }
+
+        // When: Sorting by completeness
+        let books = find_all_sorted(&pool, BookSort::Completeness)
+            .await
+            .unwrap();
+
+        // Then: The worst documented book comes first
+        let ids: Vec<&str> = books.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                bare.id.as_str(),
+                partial.id.as_str(),
+                documented.id.as_str()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_find_books_by_format() {
+        // Given: An EPUB and a book of another format
+        let (pool, _temp_dir) = setup_test_db().await;
+        let epub = create_test_book();
+        let mut pdf = create_test_book();
+        pdf.format = "pdf".to_string();
+        for book in [&epub, &pdf] {
+            insert(&pool, book).await.unwrap();
+        }
+
+        // When: Filtering by format, in any case
+        let pdfs = find_by_format(&pool, "PDF", BookSort::Created)
+            .await
+            .unwrap();
+        let epubs = find_by_format(&pool, "epub", BookSort::Size).await.unwrap();
+
+        // Then: Only books of that format come back
+        assert_eq!(pdfs.len(), 1);
+        assert_eq!(pdfs[0].id, pdf.id);
+        assert_eq!(epubs.len(), 1);
+        assert_eq!(epubs[0].format, EPUB_FORMAT);
+    }
+
+    #[tokio::test]
+    async fn should_find_books_by_author_and_count_authors() {
+        // Given: Two books by one author spelled differently, one by another and one without
+        let (pool, _temp_dir) = setup_test_db().await;
+        for author in [
+            Some("Ursula K. Le Guin"),
+            Some(" ursula k. le guin "),
+            Some("O'Brien & Sons"),
+            None,
+        ] {
+            let mut book = create_test_book();
+            book.author = author.map(str::to_string);
+            insert(&pool, &book).await.unwrap();
+        }
+
+        // When: Fetching an author's books and the author list
+        let le_guin = find_by_author(&pool, "URSULA K. LE GUIN").await.unwrap();
+        let unknown = find_by_author(&pool, "Nobody").await.unwrap();
+        let authors = find_authors(&pool).await.unwrap();
+
+        // Then: Spellings differing in case and space are one author
+        assert_eq!(le_guin.len(), 2);
+        assert!(unknown.is_empty());
+        let counts: Vec<(&str, i64)> = authors
+            .iter()
+            .map(|author| (author.name.as_str(), author.book_count))
+            .collect();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[0], ("O'Brien & Sons", 1));
+        assert_eq!(counts[1].1, 2);
+        assert!(counts[1].0.eq_ignore_ascii_case("Ursula K. Le Guin"));
+    }
+
+    #[tokio::test]
+    async fn should_find_adjacent_books_in_each_sort() {
+        // Given: Three books added in order, the newest being the smallest and barest
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut oldest = create_test_book();
+        oldest.created_at = 100;
+        oldest.file_size_bytes = Some(300);
+        oldest.author = Some("Author".to_string());
+        let mut middle = create_test_book();
+        middle.created_at = 200;
+        middle.file_size_bytes = Some(200);
+        middle.author = Some("Author".to_string());
+        middle.isbn_13 = Some("9780306406157".to_string());
+        let mut newest = Book::new("Unknown".to_string(), "/bare.epub".to_string());
+        newest.created_at = 300;
+        for book in [&oldest, &middle, &newest] {
+            insert(&pool, book).await.unwrap();
+        }
+
+        // When: Looking up neighbours in the newest-first, size and completeness orders
+        let by_created = find_adjacent(&pool, &middle, BookSort::Created)
+            .await
+            .unwrap();
+        let first_by_created = find_adjacent(&pool, &newest, BookSort::Created)
+            .await
+            .unwrap();
+        let by_size = find_adjacent(&pool, &oldest, BookSort::Size).await.unwrap();
+        let last_by_completeness = find_adjacent(&pool, &middle, BookSort::Completeness)
+            .await
+            .unwrap();
+
+        // Then: Each listing's neighbours come back, with none past the ends
+        assert_eq!(by_created.previous.as_deref(), Some(newest.id.as_str()));
+        assert_eq!(by_created.next.as_deref(), Some(oldest.id.as_str()));
+        assert_eq!(first_by_created.previous, None);
+        assert_eq!(by_size.previous, None);
+        assert_eq!(by_size.next.as_deref(), Some(middle.id.as_str()));
+        assert_eq!(
+            last_by_completeness.previous.as_deref(),
+            Some(oldest.id.as_str())
+        );
+        assert_eq!(last_by_completeness.next, None);
+    }
+
+    #[tokio::test]
+    async fn should_backfill_missing_file_sizes() {
+        // Given: A book without a recorded size
+        let (pool, _temp_dir) = setup_test_db().await;
+        let book = create_test_book();
+        insert(&pool, &book).await.unwrap();
+
+        // When: Finding and updating books missing a size
+        let missing = find_ids_missing_file_size(&pool).await.unwrap();
+        update_file_size(&pool, &book.id, 42).await.unwrap();
+
+        // Then: The book was reported and now has a size
+        assert_eq!(missing, vec![book.id.clone()]);
+        assert!(find_ids_missing_file_size(&pool).await.unwrap().is_empty());
+        assert_eq!(sum_file_sizes(&pool).await.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn should_update_storage_paths_keeping_missing_cover_null() {
+        // Given: One book with a cover and one without
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut with_cover = create_test_book();
+        with_cover.cover_image_path = Some("/covers/a.jpg".to_string());
+        let without_cover = create_test_book();
+        insert(&pool, &with_cover).await.unwrap();
+        insert(&pool, &without_cover).await.unwrap();
+
+        // When: Relocating both books
+        update_storage_paths(
+            &pool,
+            &with_cover.id,
+            "/books/ab/a.epub",
+            "/covers/ab/a.jpg",
+        )
+        .await
+        .unwrap();
+        update_storage_paths(
+            &pool,
+            &without_cover.id,
+            "/books/cd/b.epub",
+            "/covers/cd/b.jpg",
+        )
+        .await
+        .unwrap();
+
+        // Then: Only the existing cover path should change
+        let found = find_by_id(&pool, &with_cover.id).await.unwrap();
+        assert_eq!(found.epub_file_path, "/books/ab/a.epub");
+        assert_eq!(found.cover_image_path.as_deref(), Some("/covers/ab/a.jpg"));
+        let found = find_by_id(&pool, &without_cover.id).await.unwrap();
+        assert_eq!(found.epub_file_path, "/books/cd/b.epub");
+        assert!(found.cover_image_path.is_none());
+    }
+
+    #[tokio::test]
+    async fn should_report_missing_book_when_updating_storage_paths() {
+        // Given: An empty database
+        let (pool, _temp_dir) = setup_test_db().await;
+
+        // When/Then: Updating an unknown book reports no match
+        assert!(!update_storage_paths(&pool, "missing", "/a.epub", "/a.jpg")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_find_book_by_isbn_ignoring_hyphenation() {
+        // Given: A book with an ISBN-13
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = create_test_book();
+        book.isbn_13 = Some("9780306406157".to_string());
+        insert(&pool, &book).await.unwrap();
+
+        // When: Looking it up with hyphens, or by its ISBN-10
+        let hyphenated = find_by_isbn(&pool, "978-0-306-40615-7", None)
+            .await
+            .unwrap();
+        let isbn_10 = find_by_isbn(&pool, "0-306-40615-2", None).await.unwrap();
+
+        // Then: Both should find the book
+        assert_eq!(hyphenated.unwrap().id, book.id);
+        assert_eq!(isbn_10.unwrap().id, book.id);
+    }
+
+    #[tokio::test]
+    async fn should_find_book_storing_only_isbn10_by_its_isbn13() {
+        // Given: A book with only an ISBN-10
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = create_test_book();
+        book.isbn_10 = Some("0-306-40615-2".to_string());
+        insert(&pool, &book).await.unwrap();
+
+        // When: Looking it up by the matching ISBN-13
+        let found = find_by_isbn(&pool, "9780306406157", None).await.unwrap();
+
+        // Then: The book is found
+        assert_eq!(found.unwrap().id, book.id);
+    }
+
+    #[tokio::test]
+    async fn should_not_find_unknown_isbn() {
+        // Given: A book with a different ISBN
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = create_test_book();
+        book.isbn_10 = Some("0306406152".to_string());
+        insert(&pool, &book).await.unwrap();
+
+        // When/Then: Other or malformed ISBNs find nothing
+        assert!(find_by_isbn(&pool, "9781234567897", None)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(find_by_isbn(&pool, "not-an-isbn", None)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn should_skip_excepted_book_when_finding_by_isbn() {
+        // Given: Two books sharing an ISBN-13
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut first = create_test_book();
+        first.isbn_13 = Some("9780306406157".to_string());
+        first.created_at = 1;
+        let mut second = create_test_book();
+        second.isbn_13 = first.isbn_13.clone();
+        second.created_at = 2;
+        insert(&pool, &first).await.unwrap();
+        insert(&pool, &second).await.unwrap();
+
+        // When: Looking the ISBN up except for the first book
+        let other = find_by_isbn(&pool, "9780306406157", Some(&first.id))
+            .await
+            .unwrap();
+
+        // Then: The second book is found
+        assert_eq!(other.unwrap().id, second.id);
+    }
+
+    #[tokio::test]
+    async fn should_find_book_by_content_hash() {
+        // Given: A book with a content hash and one without
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut hashed = create_test_book();
+        hashed.content_hash = Some("abc123".to_string());
+        let unhashed = create_test_book();
+        insert(&pool, &hashed).await.unwrap();
+        insert(&pool, &unhashed).await.unwrap();
+
+        // When: Looking up by hash and listing books without one
+        let found = find_by_content_hash(&pool, "abc123").await.unwrap();
+        let missing = find_ids_missing_content_hash(&pool).await.unwrap();
+
+        // Then: Should match the hashed book and list only the other one
+        assert_eq!(found.unwrap().id, hashed.id);
+        assert!(find_by_content_hash(&pool, "other")
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(missing, vec![unhashed.id.clone()]);
+
+        // And: Backfilling the hash makes it findable
+        update_content_hash(&pool, &unhashed.id, "def456")
+            .await
+            .unwrap();
+        let found = find_by_content_hash(&pool, "def456").await.unwrap();
+        assert_eq!(found.unwrap().id, unhashed.id);
+    }
+
+    #[tokio::test]
+    async fn should_return_no_average_for_empty_library() {
+        // Given: An empty database
+        let (pool, _temp_dir) = setup_test_db().await;
+
+        // When: Computing the average page count
+        let average = average_page_count(&pool).await.unwrap();
+
+        // Then: There is no average
+        assert!(average.is_none());
+    }
+
     #[tokio::test]
     async fn should_prevent_duplicate_subjects() {
         // Given: A book with a subject
@@ -315,4 +1798,108 @@ mod tests {
         // Then: Should fail (unique constraint)
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn should_insert_subject_list_once_each_without_failing_on_duplicates() {
+        // Given: A book that already has one subject
+        let (pool, _temp_dir) = setup_test_db().await;
+        let book = create_test_book();
+        insert(&pool, &book).await.unwrap();
+        insert_subject(&pool, &book.id, "Fantasy").await.unwrap();
+        let subjects =
+            [" Dragons ", "fantasy", "Quests", "DRAGONS", "", "Quests"].map(String::from);
+
+        // When: Inserting a list that repeats it and itself, twice
+        let inserted = insert_subjects(&pool, &book.id, &subjects).await.unwrap();
+        let again = insert_subjects(&pool, &book.id, &subjects).await.unwrap();
+
+        // Then: Each subject is stored once, normalized, and nothing fails
+        assert_eq!(inserted, 2);
+        assert_eq!(again, 0);
+        let mut stored = find_subjects_by_book_id(&pool, &book.id).await.unwrap();
+        stored.sort();
+        assert_eq!(stored, vec!["Dragons", "Fantasy", "Quests"]);
+    }
+
+    #[tokio::test]
+    async fn should_normalize_subjects_and_dedupe_ignoring_case() {
+        // Given: A book with a subject
+        let (pool, _temp_dir) = setup_test_db().await;
+        let book = create_test_book();
+        insert(&pool, &book).await.unwrap();
+        insert_subject(&pool, &book.id, "  Science   Fiction ")
+            .await
+            .unwrap();
+
+        // When: Adding it again in another case, then removing it
+        let duplicate = insert_subject(&pool, &book.id, "science fiction").await;
+        let stored = find_subjects_by_book_id(&pool, &book.id).await.unwrap();
+        delete_subject(&pool, &book.id, "SCIENCE FICTION")
+            .await
+            .unwrap();
+        let removed_again = delete_subject(&pool, &book.id, "Science Fiction").await;
+
+        // Then: The subject is stored once, normalized, and only removable once
+        assert!(matches!(
+            duplicate,
+            Err(EzBooksError::DuplicateSubject { .. })
+        ));
+        assert_eq!(stored, vec!["Science Fiction".to_string()]);
+        assert!(matches!(
+            removed_again,
+            Err(EzBooksError::SubjectNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_find_books_created_between_inclusive_bounds() {
+        // Given: Books created at 100, 200 and 300
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut ids = Vec::new();
+        for created_at in [100, 200, 300] {
+            let mut book = create_test_book();
+            book.created_at = created_at;
+            insert(&pool, &book).await.unwrap();
+            ids.push(book.id);
+        }
+
+        // When: Asking for 200..=300
+        let books = find_created_between(&pool, 200, 300).await.unwrap();
+
+        // Then: Both bounds are included, newest first
+        let found: Vec<&str> = books.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(found, vec![ids[2].as_str(), ids[1].as_str()]);
+    }
+
+    #[tokio::test]
+    async fn should_yield_every_book_once_when_paging_by_cursor() {
+        // Given: Seven books, several sharing a creation timestamp
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut ids = Vec::new();
+        for i in 0..7 {
+            let mut book = Book::new(format!("Book {}", i), format!("/{}.epub", i));
+            book.created_at = 1_000 + i / 3;
+            insert(&pool, &book).await.unwrap();
+            ids.push(book.id);
+        }
+
+        // When: Walking all pages of three
+        let mut seen = Vec::new();
+        let (mut created_at, mut id) = (i64::MAX, String::new());
+        loop {
+            let page = find_after(&pool, created_at, &id, 3).await.unwrap();
+            let Some(last) = page.last() else { break };
+            (created_at, id) = (last.created_at, last.id.clone());
+            seen.extend(page.into_iter().map(|book| book.id));
+        }
+
+        // Then: Each book appears exactly once, newest first
+        assert_eq!(seen.len(), 7);
+        let mut unique = seen.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 7);
+        ids.sort();
+        assert_eq!(unique, ids);
+    }
 }
@@ -0,0 +1,464 @@
+use crate::annotation_repository::{Annotation, HighlightColor};
+use crate::book_model::Book;
+use crate::error::{EzBooksError, FieldErrors, Result};
+use crate::isbn::{has_valid_checksum, normalize_isbn};
+use serde::Deserialize;
+
+pub const MAX_TITLE_CHARS: usize = 500;
+/// Limit for author and publisher names
+pub const MAX_NAME_CHARS: usize = 300;
+const MAX_PUBLISH_DATE_CHARS: usize = 50;
+const MAX_DESCRIPTION_CHARS: usize = 10_000;
+const MAX_NOTES_CHARS: usize = 20_000;
+const MAX_QUOTE_CHARS: usize = 5_000;
+/// Limit for the text around an annotation's quote
+const MAX_QUOTE_CONTEXT_CHARS: usize = 200;
+/// Long enough for any BCP 47 tag in practice, e.g. "zh-Hant-TW"
+const MAX_LANGUAGE_CHARS: usize = 35;
+
+/// Body of `PUT /api/books/{id}`.
+///
+/// Absent fields are left unchanged. An empty string clears an optional text field.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BookUpdate {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub isbn_10: Option<String>,
+    pub isbn_13: Option<String>,
+    pub publisher: Option<String>,
+    pub publish_date: Option<String>,
+    pub description: Option<String>,
+    /// Private plain-text notes
+    pub notes: Option<String>,
+    /// Wider than `Book::page_count` so out-of-range values are reported, not rejected as malformed JSON
+    pub page_count: Option<i64>,
+    pub language: Option<String>,
+}
+
+impl BookUpdate {
+    /// Checks every field, collecting all problems instead of stopping at the first
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if let Some(title) = &self.title {
+            if title.trim().is_empty() {
+                errors.insert("title".to_string(), "must not be empty".to_string());
+            }
+        }
+        check_length(&mut errors, "title", &self.title, MAX_TITLE_CHARS);
+        check_length(&mut errors, "author", &self.author, MAX_NAME_CHARS);
+        check_length(&mut errors, "publisher", &self.publisher, MAX_NAME_CHARS);
+        check_length(
+            &mut errors,
+            "publish_date",
+            &self.publish_date,
+            MAX_PUBLISH_DATE_CHARS,
+        );
+        check_length(
+            &mut errors,
+            "description",
+            &self.description,
+            MAX_DESCRIPTION_CHARS,
+        );
+        check_length(&mut errors, "notes", &self.notes, MAX_NOTES_CHARS);
+        check_length(&mut errors, "language", &self.language, MAX_LANGUAGE_CHARS);
+        check_isbn(&mut errors, "isbn_10", &self.isbn_10, 10);
+        check_isbn(&mut errors, "isbn_13", &self.isbn_13, 13);
+
+        if let Some(page_count) = self.page_count {
+            if page_count < 0 {
+                errors.insert("page_count".to_string(), "must not be negative".to_string());
+            } else if page_count > i64::from(i32::MAX) {
+                errors.insert("page_count".to_string(), "is too large".to_string());
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(EzBooksError::Validation(errors))
+        }
+    }
+
+    /// Applies the provided fields to `book`; call `validate` first
+    pub fn apply_to(self, book: &mut Book) {
+        if let Some(title) = self.title {
+            book.title = title.trim().to_string();
+        }
+        apply_text(&mut book.author, self.author);
+        apply_text(&mut book.publisher, self.publisher);
+        apply_text(&mut book.publish_date, self.publish_date);
+        if self.description.is_some() {
+            book.description_from_content = false;
+        }
+        apply_text(&mut book.description, self.description);
+        apply_text(&mut book.notes, self.notes);
+        if self.language.is_some() {
+            book.language_detected = false;
+        }
+        apply_text(&mut book.language, self.language);
+        apply_text(
+            &mut book.isbn_10,
+            self.isbn_10.map(|isbn| normalize_isbn(&isbn)),
+        );
+        apply_text(
+            &mut book.isbn_13,
+            self.isbn_13.map(|isbn| normalize_isbn(&isbn)),
+        );
+        if let Some(page_count) = self.page_count.and_then(|count| i32::try_from(count).ok()) {
+            book.page_count = Some(page_count);
+        }
+    }
+}
+
+/// Body of `POST /api/books/{id}/subjects`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewSubject {
+    pub subject: String,
+}
+
+impl NewSubject {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+        if self.subject.trim().is_empty() {
+            errors.insert("subject".to_string(), "must not be empty".to_string());
+        } else if self.subject.chars().count() > MAX_NAME_CHARS {
+            errors.insert(
+                "subject".to_string(),
+                format!("must be at most {} characters", MAX_NAME_CHARS),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(EzBooksError::Validation(errors))
+        }
+    }
+}
+
+/// Body of `POST /api/books/{id}/bookmarks`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewBookmark {
+    /// Spine index of the bookmarked chapter
+    pub chapter_index: u32,
+    /// How far down the chapter, from 0.0 to 1.0; its start when absent
+    #[serde(default)]
+    pub scroll_fraction: f64,
+    pub label: String,
+}
+
+impl NewBookmark {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+        if self.label.trim().is_empty() {
+            errors.insert("label".to_string(), "must not be empty".to_string());
+        } else if self.label.chars().count() > MAX_NAME_CHARS {
+            errors.insert(
+                "label".to_string(),
+                format!("must be at most {} characters", MAX_NAME_CHARS),
+            );
+        }
+        if !(0.0..=1.0).contains(&self.scroll_fraction) {
+            errors.insert(
+                "scroll_fraction".to_string(),
+                "must be between 0 and 1".to_string(),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(EzBooksError::Validation(errors))
+        }
+    }
+}
+
+/// Body of `POST /api/books/{id}/annotations`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewAnnotation {
+    /// Spine index of the annotated chapter
+    pub chapter_index: u32,
+    pub quote: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub suffix: String,
+    #[serde(default)]
+    pub color: HighlightColor,
+    pub note: Option<String>,
+}
+
+impl NewAnnotation {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+        if self.quote.trim().is_empty() {
+            errors.insert("quote".to_string(), "must not be empty".to_string());
+        }
+        check_chars(&mut errors, "quote", &self.quote, MAX_QUOTE_CHARS);
+        check_chars(&mut errors, "prefix", &self.prefix, MAX_QUOTE_CONTEXT_CHARS);
+        check_chars(&mut errors, "suffix", &self.suffix, MAX_QUOTE_CONTEXT_CHARS);
+        check_length(&mut errors, "note", &self.note, MAX_NOTES_CHARS);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(EzBooksError::Validation(errors))
+        }
+    }
+}
+
+/// Body of `PUT /api/books/{id}/annotations/{annotation_id}`; the anchored quote is fixed.
+///
+/// Absent fields are left unchanged. An empty note clears it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnnotationUpdate {
+    pub color: Option<HighlightColor>,
+    pub note: Option<String>,
+}
+
+impl AnnotationUpdate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+        check_length(&mut errors, "note", &self.note, MAX_NOTES_CHARS);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(EzBooksError::Validation(errors))
+        }
+    }
+
+    /// Applies the provided fields to `annotation`; call `validate` first
+    pub fn apply_to(self, annotation: &mut Annotation) {
+        if let Some(color) = self.color {
+            annotation.color = color;
+        }
+        apply_text(&mut annotation.note, self.note);
+    }
+}
+
+/// Body of `POST /api/collections`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewCollection {
+    pub name: String,
+}
+
+impl NewCollection {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+        if self.name.trim().is_empty() {
+            errors.insert("name".to_string(), "must not be empty".to_string());
+        }
+        check_chars(&mut errors, "name", &self.name, MAX_NAME_CHARS);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(EzBooksError::Validation(errors))
+        }
+    }
+}
+
+/// Body of `POST /api/collections/{id}/books`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CollectionBook {
+    pub book_id: String,
+}
+
+/// Body of `PUT /api/collections/{id}/order`: every book of the collection, first to last
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CollectionOrder {
+    pub book_ids: Vec<String>,
+}
+
+fn check_length(errors: &mut FieldErrors, field: &str, value: &Option<String>, max_chars: usize) {
+    if let Some(value) = value {
+        check_chars(errors, field, value, max_chars);
+    }
+}
+
+fn check_chars(errors: &mut FieldErrors, field: &str, value: &str, max_chars: usize) {
+    if value.chars().count() > max_chars {
+        errors.insert(
+            field.to_string(),
+            format!("must be at most {} characters", max_chars),
+        );
+    }
+}
+
+fn check_isbn(errors: &mut FieldErrors, field: &str, value: &Option<String>, digits: usize) {
+    let normalized = match value.as_deref().map(normalize_isbn) {
+        Some(normalized) if !normalized.is_empty() => normalized,
+        _ => return,
+    };
+
+    if normalized.len() != digits {
+        errors.insert(field.to_string(), format!("must have {} digits", digits));
+    } else if !has_valid_checksum(&normalized) {
+        errors.insert(field.to_string(), "has an invalid check digit".to_string());
+    }
+}
+
+fn apply_text(target: &mut Option<String>, value: Option<String>) {
+    if let Some(value) = value {
+        let value = value.trim();
+        *target = (!value.is_empty()).then(|| value.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_errors(update: &BookUpdate) -> FieldErrors {
+        match update.validate() {
+            Err(EzBooksError::Validation(errors)) => errors,
+            other => panic!("expected validation errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn should_report_every_invalid_field() {
+        // Given: An update with several bad values
+        let update = BookUpdate {
+            title: Some("  ".to_string()),
+            page_count: Some(-5),
+            language: Some("x".repeat(200)),
+            isbn_13: Some("978-0-306-40615-8".to_string()),
+            isbn_10: Some("12345".to_string()),
+            ..BookUpdate::default()
+        };
+
+        // When: Validating it
+        let errors = field_errors(&update);
+
+        // Then: Each field should be named with its problem
+        assert_eq!(errors["title"], "must not be empty");
+        assert_eq!(errors["page_count"], "must not be negative");
+        assert_eq!(errors["language"], "must be at most 35 characters");
+        assert_eq!(errors["isbn_13"], "has an invalid check digit");
+        assert_eq!(errors["isbn_10"], "must have 10 digits");
+        assert_eq!(errors.len(), 5);
+    }
+
+    #[test]
+    fn should_apply_valid_partial_update() {
+        // Given: A book and an update touching some fields
+        let mut book = Book::new("Old".to_string(), "/old.epub".to_string());
+        book.language = Some("nl".to_string());
+        book.language_detected = true;
+        book.publisher = Some("Old Press".to_string());
+        book.author = Some("Someone".to_string());
+        let update = BookUpdate {
+            title: Some(" New ".to_string()),
+            isbn_13: Some("978-0-306-40615-7".to_string()),
+            publisher: Some(String::new()),
+            page_count: Some(320),
+            language: Some("af".to_string()),
+            ..BookUpdate::default()
+        };
+
+        // When: Validating and applying it
+        update.validate().unwrap();
+        update.apply_to(&mut book);
+
+        // Then: Provided fields change, empty ones clear, absent ones stay
+        assert_eq!(book.title, "New");
+        assert_eq!(book.isbn_13, Some("9780306406157".to_string()));
+        assert_eq!(book.publisher, None);
+        assert_eq!(book.page_count, Some(320));
+        assert_eq!(book.author, Some("Someone".to_string()));
+        assert_eq!(book.language, Some("af".to_string()));
+        assert!(!book.language_detected);
+    }
+
+    #[test]
+    fn should_reject_unknown_fields() {
+        // Given/When: Deserializing a body with a field that cannot be edited
+        let result: serde_json::Result<BookUpdate> =
+            serde_json::from_str(r#"{"epub_file_path":"/etc/passwd"}"#);
+
+        // Then: Should fail rather than silently ignore it
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn should_validate_bookmark_label_and_position() {
+        // Given: A bookmark without label past the end of its chapter
+        let bookmark: NewBookmark =
+            serde_json::from_str(r#"{"chapter_index":2,"scroll_fraction":1.5,"label":"  "}"#)
+                .unwrap();
+
+        // When: Validating it
+        let result = bookmark.validate();
+
+        // Then: Both fields are reported; a position defaults to the chapter start
+        match result {
+            Err(EzBooksError::Validation(errors)) => {
+                assert!(errors.contains_key("label"));
+                assert!(errors.contains_key("scroll_fraction"));
+            }
+            other => panic!("expected validation error, got {:?}", other),
+        }
+        let start: NewBookmark =
+            serde_json::from_str(r#"{"chapter_index":0,"label":"Start"}"#).unwrap();
+        assert_eq!(start.scroll_fraction, 0.0);
+        assert!(start.validate().is_ok());
+    }
+
+    #[test]
+    fn should_validate_annotation_quote_and_context() {
+        // Given: An annotation of blank text with an overlong prefix
+        let annotation: NewAnnotation = serde_json::from_value(serde_json::json!({
+            "chapter_index": 0,
+            "quote": " \n ",
+            "prefix": "x".repeat(MAX_QUOTE_CONTEXT_CHARS + 1)
+        }))
+        .unwrap();
+
+        // When: Validating it
+        let result = annotation.validate();
+
+        // Then: Both fields are reported; the color defaults to yellow
+        match result {
+            Err(EzBooksError::Validation(errors)) => {
+                assert!(errors.contains_key("quote"));
+                assert!(errors.contains_key("prefix"));
+            }
+            other => panic!("expected validation error, got {:?}", other),
+        }
+        assert_eq!(annotation.color, HighlightColor::Yellow);
+    }
+
+    #[test]
+    fn should_reject_blank_or_overlong_collection_names() {
+        // Given: A blank name, an overlong one and an ordinary one
+        let blank = NewCollection {
+            name: "  ".to_string(),
+        };
+        let overlong = NewCollection {
+            name: "x".repeat(MAX_NAME_CHARS + 1),
+        };
+        let ordinary = NewCollection {
+            name: "To read".to_string(),
+        };
+
+        // When/Then: Only the ordinary name passes
+        for collection in [blank, overlong] {
+            match collection.validate() {
+                Err(EzBooksError::Validation(errors)) => assert!(errors.contains_key("name")),
+                other => panic!("expected validation error, got {:?}", other),
+            }
+        }
+        assert!(ordinary.validate().is_ok());
+    }
+}
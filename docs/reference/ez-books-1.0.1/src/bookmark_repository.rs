@@ -0,0 +1,176 @@
+use crate::book_model::current_timestamp;
+use crate::book_update::NewBookmark;
+use crate::database_connection::DatabasePool;
+use crate::error::{EzBooksError, Result};
+use serde::Serialize;
+use tracing::{info, instrument};
+
+/// A named position in a book: a spine index and how far down that chapter it is
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct Bookmark {
+    pub id: i64,
+    pub book_id: String,
+    pub chapter_index: u32,
+    /// 0.0 at the start of the chapter, 1.0 at its end
+    pub scroll_fraction: f64,
+    pub label: String,
+    pub created_at: i64,
+}
+
+/// Saves a bookmark with a trimmed label; call `NewBookmark::validate` first
+#[instrument(skip(pool))]
+pub async fn add_bookmark(
+    pool: &DatabasePool,
+    book_id: &str,
+    new_bookmark: &NewBookmark,
+) -> Result<Bookmark> {
+    let label = new_bookmark.label.trim().to_string();
+    let now = current_timestamp();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO bookmarks (book_id, chapter_index, scroll_fraction, label, created_at)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(book_id)
+    .bind(new_bookmark.chapter_index)
+    .bind(new_bookmark.scroll_fraction)
+    .bind(&label)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    let bookmark = Bookmark {
+        id: result.last_insert_rowid(),
+        book_id: book_id.to_string(),
+        chapter_index: new_bookmark.chapter_index,
+        scroll_fraction: new_bookmark.scroll_fraction,
+        label,
+        created_at: now,
+    };
+    info!(book_id = %book_id, bookmark_id = bookmark.id, "Bookmark added");
+    Ok(bookmark)
+}
+
+/// Bookmarks of one book in reading order
+#[instrument(skip(pool))]
+pub async fn list_bookmarks(pool: &DatabasePool, book_id: &str) -> Result<Vec<Bookmark>> {
+    let bookmarks = sqlx::query_as::<_, Bookmark>(
+        r#"
+        SELECT id, book_id, chapter_index, scroll_fraction, label, created_at FROM bookmarks
+        WHERE book_id = ?
+        ORDER BY chapter_index, scroll_fraction, id
+        "#,
+    )
+    .bind(book_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(bookmarks)
+}
+
+/// Removes a bookmark; fails with `BookmarkNotFound` unless it belongs to `book_id`
+#[instrument(skip(pool))]
+pub async fn delete_bookmark(pool: &DatabasePool, book_id: &str, bookmark_id: i64) -> Result<()> {
+    let result = sqlx::query("DELETE FROM bookmarks WHERE id = ? AND book_id = ?")
+        .bind(bookmark_id)
+        .bind(book_id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(EzBooksError::BookmarkNotFound {
+            book_id: book_id.to_string(),
+            bookmark_id,
+        });
+    }
+
+    info!(book_id = %book_id, bookmark_id, "Bookmark deleted");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book_model::Book;
+    use crate::book_repository;
+    use crate::test_fixtures::setup_test_db;
+
+    async fn insert_book(pool: &DatabasePool) -> Book {
+        let book = Book::new("Bookmarked".to_string(), "/bookmarked.epub".to_string());
+        book_repository::insert(pool, &book).await.unwrap();
+        book
+    }
+
+    fn new_bookmark(chapter_index: u32, scroll_fraction: f64, label: &str) -> NewBookmark {
+        NewBookmark {
+            chapter_index,
+            scroll_fraction,
+            label: label.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_list_bookmarks_in_reading_order() {
+        // Given: Bookmarks added out of order
+        let (pool, _temp_dir) = setup_test_db().await;
+        let book = insert_book(&pool).await;
+        add_bookmark(&pool, &book.id, &new_bookmark(3, 0.2, "Late"))
+            .await
+            .unwrap();
+        add_bookmark(&pool, &book.id, &new_bookmark(1, 0.8, " Further "))
+            .await
+            .unwrap();
+        add_bookmark(&pool, &book.id, &new_bookmark(1, 0.1, "Early"))
+            .await
+            .unwrap();
+
+        // When: Listing them
+        let bookmarks = list_bookmarks(&pool, &book.id).await.unwrap();
+
+        // Then: They are sorted by chapter and position, labels trimmed
+        let labels: Vec<&str> = bookmarks.iter().map(|b| b.label.as_str()).collect();
+        assert_eq!(labels, vec!["Early", "Further", "Late"]);
+    }
+
+    #[tokio::test]
+    async fn should_delete_only_bookmarks_of_the_given_book() {
+        // Given: A bookmark in one book and another book
+        let (pool, _temp_dir) = setup_test_db().await;
+        let book = insert_book(&pool).await;
+        let other = insert_book(&pool).await;
+        let bookmark = add_bookmark(&pool, &book.id, &new_bookmark(0, 0.0, "Start"))
+            .await
+            .unwrap();
+
+        // When: Deleting it through the wrong book, then the right one, twice
+        let wrong_book = delete_bookmark(&pool, &other.id, bookmark.id).await;
+        let deleted = delete_bookmark(&pool, &book.id, bookmark.id).await;
+        let again = delete_bookmark(&pool, &book.id, bookmark.id).await;
+
+        // Then: Only the owning book can delete it, once
+        assert!(matches!(
+            wrong_book,
+            Err(EzBooksError::BookmarkNotFound { .. })
+        ));
+        assert!(deleted.is_ok());
+        assert!(matches!(again, Err(EzBooksError::BookmarkNotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn should_delete_bookmarks_with_their_book() {
+        // Given: A bookmarked book
+        let (pool, _temp_dir) = setup_test_db().await;
+        let book = insert_book(&pool).await;
+        add_bookmark(&pool, &book.id, &new_bookmark(2, 0.5, "Middle"))
+            .await
+            .unwrap();
+
+        // When: Deleting the book
+        book_repository::delete(&pool, &book.id).await.unwrap();
+
+        // Then: Its bookmarks are gone too
+        assert!(list_bookmarks(&pool, &book.id).await.unwrap().is_empty());
+    }
+}
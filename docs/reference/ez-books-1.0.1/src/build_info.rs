@@ -0,0 +1,70 @@
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_commit: Option<&'static str>,
+    pub build_timestamp: Option<&'static str>,
+}
+
+impl BuildInfo {
+    /// The build of this binary
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_commit: non_empty(option_env!("EZ_BOOKS_GIT_COMMIT")),
+            build_timestamp: non_empty(option_env!("EZ_BOOKS_BUILD_TIMESTAMP")),
+        }
+    }
+}
+
+/// `ez-books 0.1.0 (commit 1a2b3c4, built 2024-05-01T12:00:00Z)`
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ez-books {}", self.version)?;
+        let details: Vec<String> = [
+            self.git_commit.map(|commit| format!("commit {}", commit)),
+            self.build_timestamp.map(|built| format!("built {}", built)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn non_empty(value: Option<&'static str>) -> Option<&'static str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_describe_build_with_and_without_details() {
+        // Given: A build with commit and time, and one without either
+        let full = BuildInfo {
+            version: "1.2.3",
+            git_commit: Some("1a2b3c4"),
+            build_timestamp: Some("2024-05-01T12:00:00Z"),
+        };
+        let bare = BuildInfo {
+            git_commit: None,
+            build_timestamp: None,
+            ..full
+        };
+
+        // When/Then: The details are listed only when known
+        assert_eq!(
+            full.to_string(),
+            "ez-books 1.2.3 (commit 1a2b3c4, built 2024-05-01T12:00:00Z)"
+        );
+        assert_eq!(bare.to_string(), "ez-books 1.2.3");
+        assert_eq!(BuildInfo::current().version, env!("CARGO_PKG_VERSION"));
+    }
+}
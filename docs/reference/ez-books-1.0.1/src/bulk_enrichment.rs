@@ -0,0 +1,179 @@
+use crate::book_identifier::{lookup_isbn, merge_book_data, MetadataField};
+use crate::book_model::{Book, EnrichmentStatus};
+use crate::book_repository;
+use crate::database_connection::DatabasePool;
+use crate::enrichment_queue::MAX_BATCH_SIZE;
+use crate::error::Result;
+use crate::openlibrary_client::OpenLibraryClient;
+use serde::Serialize;
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+/// Pause between OpenLibrary requests of one run, to stay polite to the public API
+pub const OPENLIBRARY_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Outcome of re-enriching books that are missing metadata
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct BulkEnrichmentReport {
+    /// Books OpenLibrary had data for, now updated
+    pub enriched: usize,
+    /// Books whose lookup or update failed
+    pub failed: usize,
+    /// Books without an ISBN or unknown to OpenLibrary
+    pub skipped: usize,
+}
+
+/// Looks up every book missing an author, description or cover that has an ISBN,
+/// `MAX_BATCH_SIZE` ISBNs per request with `request_interval` between requests.
+/// Covers are not downloaded; books without one still get OpenLibrary's other data.
+/// The `locked` fields are left as they are.
+#[instrument(skip(pool, client))]
+pub async fn enrich_missing(
+    pool: &DatabasePool,
+    client: &OpenLibraryClient,
+    locked: &[MetadataField],
+    request_interval: Duration,
+) -> Result<BulkEnrichmentReport> {
+    let (mut candidates, without_isbn): (Vec<Book>, Vec<Book>) =
+        book_repository::find_missing_metadata(pool)
+            .await?
+            .into_iter()
+            .partition(|book| lookup_isbn(book).is_some());
+    let mut report = BulkEnrichmentReport {
+        skipped: without_isbn.len(),
+        ..BulkEnrichmentReport::default()
+    };
+    info!(
+        candidates = candidates.len(),
+        without_isbn = report.skipped,
+        "Starting bulk enrichment"
+    );
+
+    for (index, batch) in candidates.chunks_mut(MAX_BATCH_SIZE).enumerate() {
+        if index > 0 {
+            tokio::time::sleep(request_interval).await;
+        }
+
+        let isbns: Vec<&str> = batch.iter().filter_map(lookup_isbn).collect();
+        let found = match client.lookup_by_isbns(&isbns).await {
+            Ok(found) => found,
+            Err(e) => {
+                warn!(count = batch.len(), error = %e, "Bulk enrichment lookup failed");
+                report.failed += batch.len();
+                continue;
+            }
+        };
+
+        for book in batch.iter_mut() {
+            let Some(data) = lookup_isbn(book).and_then(|isbn| found.get(isbn)) else {
+                report.skipped += 1;
+                continue;
+            };
+            let loaded = book.clone();
+            merge_book_data(book, data, locked);
+            book.enrichment_status = EnrichmentStatus::Done;
+            match book_repository::update_enrichment(pool, &loaded, book).await {
+                Ok(()) => report.enriched += 1,
+                Err(e) => {
+                    warn!(book_id = %book.id, error = %e, "Failed to store enriched book");
+                    report.failed += 1;
+                }
+            }
+        }
+    }
+
+    info!(
+        enriched = report.enriched,
+        failed = report.failed,
+        skipped = report.skipped,
+        "Bulk enrichment completed"
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::setup_test_db;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A local OpenLibrary that only knows ISBN 9780140328721
+    async fn fake_openlibrary() -> OpenLibraryClient {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0; 4096];
+                let _ = stream.read(&mut buffer).await.unwrap();
+                let body = r#"{"ISBN:9780140328721":{"authors":[{"name":"Roald Dahl"}]}}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        OpenLibraryClient::with_base_url(&base_url).unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_count_enriched_skipped_and_complete_books() {
+        // Given: A known and an unknown ISBN, a book without ISBN and a complete book
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut ids = Vec::new();
+        for isbn in [Some("9780140328721"), Some("9780000000002"), None] {
+            let mut book = Book::new("Sparse".to_string(), "/sparse.epub".to_string());
+            book.isbn_13 = isbn.map(str::to_string);
+            book_repository::insert(&pool, &book).await.unwrap();
+            ids.push(book.id);
+        }
+        let mut complete = Book::new("Complete".to_string(), "/complete.epub".to_string());
+        complete.isbn_13 = Some("9780140328721".to_string());
+        complete.author = Some("Someone".to_string());
+        complete.description = Some("About it.".to_string());
+        complete.cover_image_path = Some("/covers/complete.jpg".to_string());
+        book_repository::insert(&pool, &complete).await.unwrap();
+
+        // When: Enriching books missing metadata
+        let report = enrich_missing(&pool, &fake_openlibrary().await, &[], Duration::ZERO)
+            .await
+            .unwrap();
+
+        // Then: Only the known ISBN is enriched; complete books are not looked at
+        assert_eq!(
+            report,
+            BulkEnrichmentReport {
+                enriched: 1,
+                failed: 0,
+                skipped: 2,
+            }
+        );
+        let enriched = book_repository::find_by_id(&pool, &ids[0]).await.unwrap();
+        assert_eq!(enriched.author, Some("Roald Dahl".to_string()));
+        let untouched = book_repository::find_by_id(&pool, &complete.id)
+            .await
+            .unwrap();
+        assert_eq!(untouched.author, Some("Someone".to_string()));
+    }
+
+    #[tokio::test]
+    async fn should_count_failures_when_openlibrary_is_unreachable() {
+        // Given: A sparse book with an ISBN and no OpenLibrary
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = Book::new("Sparse".to_string(), "/sparse.epub".to_string());
+        book.isbn_13 = Some("9780140328721".to_string());
+        book_repository::insert(&pool, &book).await.unwrap();
+        let client = OpenLibraryClient::with_base_url("http://127.0.0.1:9").unwrap();
+
+        // When: Enriching
+        let report = enrich_missing(&pool, &client, &[], Duration::ZERO)
+            .await
+            .unwrap();
+
+        // Then: The book is counted as failed
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.enriched, 0);
+    }
+}
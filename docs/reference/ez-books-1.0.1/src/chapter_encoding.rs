@@ -0,0 +1,94 @@
+use encoding_rs::{Encoding, UTF_8};
+use epub::doc::EpubDoc;
+use regex::bytes::Regex;
+use std::io::{Read, Seek};
+use tracing::{info, warn};
+
+/// How far into a chapter the charset declaration is looked for
+const DECLARATION_SCAN_BYTES: usize = 1024;
+
+/// The current spine item as UTF-8, or `None` when it cannot be read at all
+pub fn current_chapter_str<R: Read + Seek>(doc: &mut EpubDoc<R>) -> Option<String> {
+    let (bytes, _mime) = doc.get_current()?;
+    Some(decode_chapter(&bytes))
+}
+
+/// Decodes by byte order mark, then as UTF-8, then by the declared charset. Chapters
+/// that are neither valid UTF-8 nor declare a known charset are decoded lossily.
+pub fn decode_chapter(bytes: &[u8]) -> String {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        return text.into_owned();
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+
+    match declared_encoding(bytes).filter(|encoding| *encoding != UTF_8) {
+        Some(encoding) => {
+            info!(charset = encoding.name(), "Transcoding chapter to UTF-8");
+            let (text, _) = encoding.decode_without_bom_handling(bytes);
+            text.into_owned()
+        }
+        None => {
+            warn!("Chapter is not valid UTF-8 and declares no known charset");
+            String::from_utf8_lossy(bytes).into_owned()
+        }
+    }
+}
+
+/// Charset named by the XML declaration or a `<meta>` element near the start
+fn declared_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = &bytes[..bytes.len().min(DECLARATION_SCAN_BYTES)];
+    let pattern = Regex::new(
+        r#"(?i)(?:<\?xml[^>]*?\bencoding|<meta[^>]*?\bcharset)\s*=\s*["']?([A-Za-z0-9._:-]+)"#,
+    )
+    .ok()?;
+    let label = pattern.captures(head)?.get(1)?.as_bytes();
+    Encoding::for_label(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_transcode_declared_latin1_chapter() {
+        // Given: A chapter encoded as ISO-8859-1, as its XML declaration says
+        let mut chapter = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><p>Caf".to_vec();
+        chapter.extend_from_slice(&[0xE9, b' ', 0xE0, b' ', b'l', 0xE0, b'.']);
+        chapter.extend_from_slice(b"</p>");
+
+        // When: Decoding it
+        let text = decode_chapter(&chapter);
+
+        // Then: The accented letters come out as proper UTF-8
+        assert!(text.ends_with("<p>Café à là.</p>"));
+    }
+
+    #[test]
+    fn should_use_meta_charset_bom_or_utf8() {
+        // Given: A Windows-1252 chapter declared by <meta>, a UTF-16 one with BOM and a UTF-8 one
+        let mut windows = b"<html><head><meta charset=windows-1252></head><p>".to_vec();
+        windows.extend_from_slice(&[0x93, b'q', 0x94]);
+        let utf16: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain("<p>Ünïcode</p>".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let utf8 = "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><p>déjà</p>";
+
+        // When/Then: Each decodes to the same text it was written with
+        assert!(decode_chapter(&windows).ends_with("<p>\u{201c}q\u{201d}"));
+        assert_eq!(decode_chapter(&utf16), "<p>Ünïcode</p>");
+        assert_eq!(decode_chapter(utf8.as_bytes()), utf8);
+    }
+
+    #[test]
+    fn should_fall_back_to_lossy_utf8_without_declaration() {
+        // Given/When: Invalid UTF-8 with no charset declared
+        let text = decode_chapter(&[b'<', b'p', b'>', 0xE9, b'<']);
+
+        // Then: The undecodable byte is replaced rather than the chapter dropped
+        assert_eq!(text, "<p>\u{fffd}<");
+    }
+}
@@ -0,0 +1,196 @@
+use crate::config::Config;
+use crate::error::{EzBooksError, Result};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: ez-books [OPTIONS]
+
+Options:
+  --port <PORT>                Port to listen on (overrides SERVER_PORT)
+  --storage-path <PATH>        Directory for EPUB files and covers (overrides STORAGE_PATH)
+  --database-url <URL>         SQLite database URL (overrides DATABASE_URL)
+  --config <FILE>              KEY=VALUE file used for settings missing from the environment
+  -h, --help                   Print this help and exit
+  -V, --version                Print the version, commit and build time and exit
+
+Precedence: command line > environment > config file > defaults";
+
+/// Command line overrides applied on top of the environment/file configuration
+#[derive(Debug, Default, PartialEq)]
+pub struct CliArgs {
+    pub port: Option<u16>,
+    pub storage_path: Option<String>,
+    pub database_url: Option<String>,
+    pub config_file: Option<PathBuf>,
+    pub show_help: bool,
+    pub show_version: bool,
+}
+
+impl CliArgs {
+    /// Parses arguments, excluding the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => parsed.show_help = true,
+                "-V" | "--version" => parsed.show_version = true,
+                "--port" => {
+                    let value = required_value(&arg, args.next())?;
+                    let port = value.parse().map_err(|_| {
+                        EzBooksError::Config(format!("Invalid port for --port: {}", value))
+                    })?;
+                    parsed.port = Some(port);
+                }
+                "--storage-path" => parsed.storage_path = Some(required_value(&arg, args.next())?),
+                "--database-url" => parsed.database_url = Some(required_value(&arg, args.next())?),
+                "--config" => {
+                    parsed.config_file = Some(PathBuf::from(required_value(&arg, args.next())?))
+                }
+                unknown => {
+                    return Err(EzBooksError::Config(format!(
+                        "Unknown argument: {}\n\n{}",
+                        unknown, USAGE
+                    )))
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Loads the environment/file configuration and applies the command line overrides
+    pub fn load_config(&self) -> Result<Config> {
+        let mut config = match &self.config_file {
+            Some(path) => Config::from_env_and_file(path)?,
+            None => Config::from_env()?,
+        };
+        self.apply_to(&mut config);
+        Ok(config)
+    }
+
+    fn apply_to(&self, config: &mut Config) {
+        if let Some(port) = self.port {
+            config.server_port = port;
+        }
+        if let Some(storage_path) = &self.storage_path {
+            config.storage_path = storage_path.clone();
+        }
+        if let Some(database_url) = &self.database_url {
+            config.database_url = database_url.clone();
+        }
+    }
+}
+
+fn required_value(flag: &str, value: Option<String>) -> Result<String> {
+    value
+        .filter(|v| !v.starts_with("--"))
+        .ok_or_else(|| EzBooksError::Config(format!("Missing value for {}", flag)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn should_parse_all_supported_flags() {
+        // Given: A full set of arguments
+        let input = args(&[
+            "--port",
+            "3000",
+            "--storage-path",
+            "/srv/books",
+            "--database-url",
+            "sqlite://srv.db",
+            "--config",
+            "ez-books.env",
+        ]);
+
+        // When: Parsing
+        let parsed = CliArgs::parse(input).unwrap();
+
+        // Then: Every value should be captured
+        assert_eq!(parsed.port, Some(3000));
+        assert_eq!(parsed.storage_path, Some("/srv/books".to_string()));
+        assert_eq!(parsed.database_url, Some("sqlite://srv.db".to_string()));
+        assert_eq!(parsed.config_file, Some(PathBuf::from("ez-books.env")));
+        assert!(!parsed.show_help);
+        assert!(!parsed.show_version);
+    }
+
+    #[test]
+    fn should_return_defaults_without_arguments() {
+        // Given/When: Parsing no arguments
+        let parsed = CliArgs::parse(Vec::new()).unwrap();
+
+        // Then: Nothing should be overridden
+        assert_eq!(parsed, CliArgs::default());
+    }
+
+    #[test]
+    fn should_recognize_help_flag() {
+        // Given/When: Parsing the short help flag
+        let parsed = CliArgs::parse(args(&["-h"])).unwrap();
+
+        // Then: Help should be requested
+        assert!(parsed.show_help);
+    }
+
+    #[test]
+    fn should_recognize_version_flag() {
+        // Given/When: Parsing the long and short version flags
+        let long = CliArgs::parse(args(&["--version"])).unwrap();
+        let short = CliArgs::parse(args(&["-V"])).unwrap();
+
+        // Then: The version should be requested, not help
+        assert!(long.show_version && short.show_version);
+        assert!(!long.show_help);
+    }
+
+    #[test]
+    fn should_reject_invalid_port() {
+        // Given/When: Parsing a non-numeric port
+        let result = CliArgs::parse(args(&["--port", "eighty"]));
+
+        // Then: Should fail with a config error
+        assert!(matches!(result, Err(EzBooksError::Config(_))));
+    }
+
+    #[test]
+    fn should_reject_missing_value() {
+        // Given/When: Parsing a flag followed by another flag
+        let result = CliArgs::parse(args(&["--storage-path", "--port", "3000"]));
+
+        // Then: Should report the missing value
+        assert!(matches!(result, Err(EzBooksError::Config(msg)) if msg.contains("--storage-path")));
+    }
+
+    #[test]
+    fn should_reject_unknown_argument() {
+        // Given/When: Parsing an unsupported flag
+        let result = CliArgs::parse(args(&["--verbose"]));
+
+        // Then: Should fail and include usage
+        assert!(matches!(result, Err(EzBooksError::Config(msg)) if msg.contains("Usage")));
+    }
+
+    #[test]
+    fn should_override_config_values() {
+        // Given: A config and command line overrides
+        let mut config = Config::from_env().unwrap();
+        let parsed =
+            CliArgs::parse(args(&["--port", "4000", "--storage-path", "/tmp/books"])).unwrap();
+
+        // When: Applying the overrides
+        parsed.apply_to(&mut config);
+
+        // Then: Overridden values win, others are untouched
+        assert_eq!(config.server_port, 4000);
+        assert_eq!(config.storage_path, "/tmp/books");
+        assert_eq!(config.database_url, "sqlite://data/ez-books.db");
+    }
+}
@@ -0,0 +1,444 @@
+use crate::book_model::{current_timestamp, Book};
+use crate::book_repository::{self, normalize_subject};
+use crate::database_connection::DatabasePool;
+use crate::error::{EzBooksError, FieldErrors, Result};
+use serde::Serialize;
+use std::collections::HashSet;
+use tracing::{info, instrument};
+
+/// A named reading list; its books keep the order they were added or arranged in
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+    pub book_count: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Response of `GET /api/collections/{id}`: the collection and its books in reading order
+#[derive(Debug, Serialize)]
+pub struct CollectionDetail {
+    #[serde(flatten)]
+    pub collection: Collection,
+    pub books: Vec<Book>,
+}
+
+const COLLECTION_QUERY: &str = r#"
+    SELECT c.id, c.name, c.created_at, c.updated_at,
+        (SELECT COUNT(*) FROM collection_books cb WHERE cb.collection_id = c.id) AS book_count
+    FROM collections c
+"#;
+
+/// Creates a collection with its name's whitespace collapsed; fails with
+/// `DuplicateCollection` if one of that name exists in any letter case
+#[instrument(skip(pool))]
+pub async fn create_collection(pool: &DatabasePool, name: &str) -> Result<Collection> {
+    let name = normalize_subject(name);
+    let now = current_timestamp();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO collections (name, created_at, updated_at)
+        SELECT ?1, ?2, ?2 WHERE NOT EXISTS (SELECT 1 FROM collections WHERE name = ?1)
+        "#,
+    )
+    .bind(&name)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(EzBooksError::DuplicateCollection(name));
+    }
+
+    let id = result.last_insert_rowid();
+    info!(collection_id = id, name = %name, "Collection created");
+    find_collection(pool, id).await
+}
+
+/// All collections by name
+#[instrument(skip(pool))]
+pub async fn list_collections(pool: &DatabasePool) -> Result<Vec<Collection>> {
+    let collections =
+        sqlx::query_as::<_, Collection>(&format!("{} ORDER BY c.name, c.id", COLLECTION_QUERY))
+            .fetch_all(pool)
+            .await?;
+
+    Ok(collections)
+}
+
+/// Fails with `CollectionNotFound` if there is no such collection
+#[instrument(skip(pool))]
+pub async fn find_collection(pool: &DatabasePool, collection_id: i64) -> Result<Collection> {
+    sqlx::query_as::<_, Collection>(&format!("{} WHERE c.id = ?", COLLECTION_QUERY))
+        .bind(collection_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(EzBooksError::CollectionNotFound(collection_id))
+}
+
+/// Removes a collection; its books stay in the library and are touched, as their
+/// details no longer list it
+#[instrument(skip(pool))]
+pub async fn delete_collection(pool: &DatabasePool, collection_id: i64) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"
+        UPDATE books SET updated_at = ?
+        WHERE id IN (SELECT book_id FROM collection_books WHERE collection_id = ?)
+        "#,
+    )
+    .bind(current_timestamp())
+    .bind(collection_id)
+    .execute(&mut *tx)
+    .await?;
+    let result = sqlx::query("DELETE FROM collections WHERE id = ?")
+        .bind(collection_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(EzBooksError::CollectionNotFound(collection_id));
+    }
+
+    tx.commit().await?;
+    info!(collection_id, "Collection deleted");
+    Ok(())
+}
+
+/// Books of a collection in reading order
+#[instrument(skip(pool))]
+pub async fn find_books_in_collection(
+    pool: &DatabasePool,
+    collection_id: i64,
+) -> Result<Vec<Book>> {
+    let books = sqlx::query_as::<_, Book>(
+        r#"
+        SELECT b.* FROM books b
+        JOIN collection_books cb ON cb.book_id = b.id
+        WHERE cb.collection_id = ?
+        ORDER BY cb.ordinal, cb.added_at
+        "#,
+    )
+    .bind(collection_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(books)
+}
+
+/// Collections a book is in, by name
+#[instrument(skip(pool))]
+pub async fn find_collections_for_book(
+    pool: &DatabasePool,
+    book_id: &str,
+) -> Result<Vec<Collection>> {
+    let collections = sqlx::query_as::<_, Collection>(&format!(
+        "{} JOIN collection_books m ON m.collection_id = c.id WHERE m.book_id = ? ORDER BY c.name, c.id",
+        COLLECTION_QUERY
+    ))
+    .bind(book_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(collections)
+}
+
+/// Appends a book to the end of a collection; adding a book it already has changes nothing.
+/// Both must exist.
+#[instrument(skip(pool))]
+pub async fn add_book(pool: &DatabasePool, collection_id: i64, book_id: &str) -> Result<()> {
+    let now = current_timestamp();
+    let result = sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO collection_books (collection_id, book_id, ordinal, added_at)
+        SELECT ?1, ?2, COALESCE(MAX(ordinal) + 1, 0), ?3
+        FROM collection_books WHERE collection_id = ?1
+        "#,
+    )
+    .bind(collection_id)
+    .bind(book_id)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        touch(pool, collection_id, now).await?;
+        info!(collection_id, book_id = %book_id, "Book added to collection");
+    }
+    Ok(())
+}
+
+/// Takes a book out of a collection; fails with `CollectionBookNotFound` if it is not in it.
+/// The book is touched, as its detail no longer lists the collection.
+#[instrument(skip(pool))]
+pub async fn remove_book(pool: &DatabasePool, collection_id: i64, book_id: &str) -> Result<()> {
+    let result =
+        sqlx::query("DELETE FROM collection_books WHERE collection_id = ? AND book_id = ?")
+            .bind(collection_id)
+            .bind(book_id)
+            .execute(pool)
+            .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(EzBooksError::CollectionBookNotFound {
+            collection_id,
+            book_id: book_id.to_string(),
+        });
+    }
+
+    touch(pool, collection_id, current_timestamp()).await?;
+    book_repository::touch(pool, book_id).await?;
+    info!(collection_id, book_id = %book_id, "Book removed from collection");
+    Ok(())
+}
+
+/// Arranges a collection's books in the order of `book_ids`, which must name every book
+/// of the collection exactly once; fails with `Validation` otherwise
+#[instrument(skip(pool, book_ids))]
+pub async fn reorder_books(
+    pool: &DatabasePool,
+    collection_id: i64,
+    book_ids: &[String],
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let members: HashSet<String> =
+        sqlx::query_scalar("SELECT book_id FROM collection_books WHERE collection_id = ?")
+            .bind(collection_id)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect();
+    check_same_books(&members, book_ids)?;
+
+    for (ordinal, book_id) in book_ids.iter().enumerate() {
+        sqlx::query(
+            "UPDATE collection_books SET ordinal = ? WHERE collection_id = ? AND book_id = ?",
+        )
+        .bind(ordinal as i64)
+        .bind(collection_id)
+        .bind(book_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    sqlx::query("UPDATE collections SET updated_at = ? WHERE id = ?")
+        .bind(current_timestamp())
+        .bind(collection_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    info!(
+        collection_id,
+        count = book_ids.len(),
+        "Collection reordered"
+    );
+    Ok(())
+}
+
+fn check_same_books(members: &HashSet<String>, book_ids: &[String]) -> Result<()> {
+    let mut seen = HashSet::new();
+    let mut problems = Vec::new();
+
+    if let Some(repeated) = book_ids.iter().find(|id| !seen.insert(id.as_str())) {
+        problems.push(format!("lists {} more than once", repeated));
+    }
+    let extra: Vec<&str> = book_ids
+        .iter()
+        .map(String::as_str)
+        .filter(|id| !members.contains(*id))
+        .collect();
+    if !extra.is_empty() {
+        problems.push(format!("not in the collection: {}", extra.join(", ")));
+    }
+    let mut missing: Vec<&str> = members
+        .iter()
+        .map(String::as_str)
+        .filter(|id| !seen.contains(id))
+        .collect();
+    missing.sort_unstable();
+    if !missing.is_empty() {
+        problems.push(format!("missing: {}", missing.join(", ")));
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let mut errors = FieldErrors::new();
+    errors.insert("book_ids".to_string(), problems.join("; "));
+    Err(EzBooksError::Validation(errors))
+}
+
+async fn touch(pool: &DatabasePool, collection_id: i64, now: i64) -> Result<()> {
+    sqlx::query("UPDATE collections SET updated_at = ? WHERE id = ?")
+        .bind(now)
+        .bind(collection_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::setup_test_db;
+
+    async fn insert_books(pool: &DatabasePool, titles: &[&str]) -> Vec<Book> {
+        let mut books = Vec::new();
+        for title in titles {
+            let book = Book::new(title.to_string(), format!("/{}.epub", title));
+            book_repository::insert(pool, &book).await.unwrap();
+            books.push(book);
+        }
+        books
+    }
+
+    fn titles(books: &[Book]) -> Vec<&str> {
+        books.iter().map(|book| book.title.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn should_keep_books_in_added_then_arranged_order() {
+        // Given: A collection with three books added out of title order
+        let (pool, _temp_dir) = setup_test_db().await;
+        let books = insert_books(&pool, &["A", "B", "C"]).await;
+        let collection = create_collection(&pool, " Summer   reading ")
+            .await
+            .unwrap();
+        for index in [2, 0, 1, 0] {
+            add_book(&pool, collection.id, &books[index].id)
+                .await
+                .unwrap();
+        }
+        let added = find_books_in_collection(&pool, collection.id)
+            .await
+            .unwrap();
+
+        // When: Arranging them alphabetically
+        let order: Vec<String> = books.iter().map(|book| book.id.clone()).collect();
+        reorder_books(&pool, collection.id, &order).await.unwrap();
+
+        // Then: Books come back in insertion order first, then in the new order
+        assert_eq!(collection.name, "Summer reading");
+        assert_eq!(titles(&added), vec!["C", "A", "B"]);
+        let arranged = find_books_in_collection(&pool, collection.id)
+            .await
+            .unwrap();
+        assert_eq!(titles(&arranged), vec!["A", "B", "C"]);
+        let listed = list_collections(&pool).await.unwrap();
+        assert_eq!(listed[0].book_count, 3);
+    }
+
+    #[tokio::test]
+    async fn should_reject_order_that_does_not_match_membership() {
+        // Given: A collection of two books and a third book outside it
+        let (pool, _temp_dir) = setup_test_db().await;
+        let books = insert_books(&pool, &["A", "B", "Outside"]).await;
+        let collection = create_collection(&pool, "List").await.unwrap();
+        for book in &books[..2] {
+            add_book(&pool, collection.id, &book.id).await.unwrap();
+        }
+
+        // When: Reordering with a missing, an extra and a repeated id
+        let missing = reorder_books(&pool, collection.id, &[books[1].id.clone()]).await;
+        let extra = reorder_books(
+            &pool,
+            collection.id,
+            &[
+                books[1].id.clone(),
+                books[0].id.clone(),
+                books[2].id.clone(),
+            ],
+        )
+        .await;
+        let repeated = reorder_books(
+            &pool,
+            collection.id,
+            &[
+                books[1].id.clone(),
+                books[1].id.clone(),
+                books[0].id.clone(),
+            ],
+        )
+        .await;
+
+        // Then: Each is refused and the order is unchanged
+        for result in [missing, extra, repeated] {
+            match result {
+                Err(EzBooksError::Validation(errors)) => assert!(errors.contains_key("book_ids")),
+                other => panic!("expected validation error, got {:?}", other),
+            }
+        }
+        let unchanged = find_books_in_collection(&pool, collection.id)
+            .await
+            .unwrap();
+        assert_eq!(titles(&unchanged), vec!["A", "B"]);
+    }
+
+    #[tokio::test]
+    async fn should_refuse_duplicate_names_and_forget_deleted_books() {
+        // Given: A collection holding a book
+        let (pool, _temp_dir) = setup_test_db().await;
+        let books = insert_books(&pool, &["Gone"]).await;
+        let collection = create_collection(&pool, "Shelf").await.unwrap();
+        add_book(&pool, collection.id, &books[0].id).await.unwrap();
+
+        // When: Creating another of the same name and deleting the book
+        let duplicate = create_collection(&pool, "SHELF").await;
+        book_repository::delete(&pool, &books[0].id).await.unwrap();
+        let removed = remove_book(&pool, collection.id, &books[0].id).await;
+
+        // Then: The name is taken and the collection no longer lists the book
+        assert!(matches!(
+            duplicate,
+            Err(EzBooksError::DuplicateCollection(_))
+        ));
+        assert!(matches!(
+            removed,
+            Err(EzBooksError::CollectionBookNotFound { .. })
+        ));
+        assert_eq!(
+            find_collection(&pool, collection.id)
+                .await
+                .unwrap()
+                .book_count,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn should_list_collections_of_a_book_and_touch_it_when_it_leaves_one() {
+        // Given: A book last changed long ago, in two collections
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = Book::new("Kept".to_string(), "/kept.epub".to_string());
+        book.updated_at = 1_000;
+        book_repository::insert(&pool, &book).await.unwrap();
+        let later = create_collection(&pool, "To read").await.unwrap();
+        let favorites = create_collection(&pool, "Favorites").await.unwrap();
+        for collection in [&later, &favorites] {
+            add_book(&pool, collection.id, &book.id).await.unwrap();
+        }
+
+        // When: Listing its collections before and after it leaves both
+        let before = find_collections_for_book(&pool, &book.id).await.unwrap();
+        remove_book(&pool, later.id, &book.id).await.unwrap();
+        let removed = book_repository::find_by_id(&pool, &book.id).await.unwrap();
+        sqlx::query("UPDATE books SET updated_at = 1000 WHERE id = ?")
+            .bind(&book.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        delete_collection(&pool, favorites.id).await.unwrap();
+        let deleted = book_repository::find_by_id(&pool, &book.id).await.unwrap();
+        let after = find_collections_for_book(&pool, &book.id).await.unwrap();
+
+        // Then: Both are listed by name, and each departure moves the book's `updated_at`
+        let names: Vec<&str> = before.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Favorites", "To read"]);
+        assert!(removed.updated_at > 1_000);
+        assert!(deleted.updated_at > 1_000);
+        assert!(after.is_empty());
+    }
+}
@@ -1,28 +1,239 @@
-use crate::error::Result;
+use crate::book_identifier::MetadataField;
+use crate::book_query::{BookSort, DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT};
+use crate::epub_cover_extractor::{
+    parse_hex_color, CoverFit, DEFAULT_COVER_JPEG_QUALITY, DEFAULT_MAX_COVER_DIMENSION,
+    DEFAULT_MAX_COVER_PIXELS,
+};
+use crate::error::{EzBooksError, Result};
+use crate::openlibrary_client::{ApiHeader, DEFAULT_USER_AGENT};
+use crate::rate_limit::RateLimit;
+use crate::security_headers::DEFAULT_CONTENT_SECURITY_POLICY;
+use crate::upload_handler::DEFAULT_UPLOAD_EXTENSIONS;
+use std::collections::HashMap;
 use std::env;
+use std::fs;
+use std::path::Path;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub server_host: String,
     pub server_port: u16,
     pub database_url: String,
+    pub database_max_connections: u32,
+    pub database_acquire_timeout_secs: u64,
+    pub database_journal_mode: String,
+    pub database_synchronous: String,
+    pub database_busy_timeout_ms: u64,
     pub storage_path: String,
     pub openlibrary_api_url: String,
+    pub openlibrary_user_agent: String,
+    /// Header some OpenLibrary mirrors or caching proxies require, e.g. an API key
+    pub openlibrary_api_header: Option<ApiHeader>,
+    pub enrichment_queue_capacity: usize,
+    /// Fields OpenLibrary enrichment never changes; empty keeps the usual merge rules
+    pub locked_metadata_fields: Vec<MetadataField>,
+    pub upload_timeout_secs: u64,
+    /// How long a finished upload's Idempotency-Key is remembered
+    pub upload_idempotency_ttl_secs: u64,
+    pub storage_sharding: bool,
+    pub reject_duplicate_isbn: bool,
+    /// Lowercase upload extensions without the dot
+    pub upload_allowed_extensions: Vec<String>,
+    pub import_folder: Option<String>,
+    pub import_stability_secs: u64,
+    pub watch_import_folder: bool,
+    /// Extra filename regexes skipped during folder import, on top of the defaults
+    pub import_skip_patterns: Vec<String>,
+    pub reader_cache_max_bytes: usize,
+    /// Reader pages stop inlining chapters past this size; 0 inlines whole books
+    pub reader_max_inline_bytes: usize,
+    /// Rejoin words hyphenated across line breaks in reader and plain-text output
+    pub reader_rejoin_hyphenated_words: bool,
+    /// Bearer token required by `/api/admin` endpoints; they are disabled when unset
+    pub admin_api_token: Option<String>,
+    /// Path prefix when served under a reverse-proxy subpath, e.g. `/ezbooks`; empty at the root
+    pub base_path: String,
+    /// Decode covers before serving them, replacing corrupt ones with a placeholder
+    pub validate_covers_on_read: bool,
+    /// JPEG quality of stored covers, 1-100
+    pub cover_jpeg_quality: u8,
+    /// Covers larger than this many pixels on either side are not stored
+    pub cover_max_dimension: u32,
+    /// Covers with more pixels than this in total are not stored
+    pub cover_max_pixels: u64,
+    /// `contain` keeps covers' aspect ratio, `pad` letterboxes and `cover` crops them to
+    /// the exact gallery size
+    pub cover_fit: CoverFit,
+    /// Store covers with transparent pixels as PNG instead of JPEG
+    pub cover_keep_transparency: bool,
+    /// Gallery and `/api/books` order when the request has no `sort`
+    pub default_sort: BookSort,
+    /// `/api/books` page size when paging without `limit`
+    pub default_page_size: u32,
+    /// Index book text at import for `/api/search/content`
+    pub content_search: bool,
+    /// Turn underscores and dashes into spaces in titles that were file names
+    pub humanize_filename_titles: bool,
+    /// Reject uploads whose EPUB has no usable cover
+    pub require_cover: bool,
+    /// Per-client limit on uploads and enrichment; `None` when `RATE_LIMIT_PER_MINUTE` is unset or 0
+    pub rate_limit: Option<RateLimit>,
+    /// `Content-Security-Policy` of every response; `None` when `CONTENT_SECURITY_POLICY` is empty
+    pub content_security_policy: Option<String>,
+    /// Send `X-Response-Time-Ms` with every response
+    pub response_time_header: bool,
+    /// Requests taking at least this long are logged as warnings; 0 logs none
+    pub slow_request_threshold_ms: u64,
+    /// Indent JSON responses unless a request asks for `?pretty=0`
+    pub pretty_json: bool,
 }
 
 impl Config {
     pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+
+    /// Loads configuration from the environment, falling back to a `KEY=VALUE` file
+    pub fn from_env_and_file(path: impl AsRef<Path>) -> Result<Self> {
+        let file_values = read_config_file(path)?;
+        Self::from_lookup(|key| env::var(key).ok().or_else(|| file_values.get(key).cloned()))
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
         Ok(Self {
-            server_host: env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
-            server_port: env::var("SERVER_PORT")
-                .ok()
+            server_host: lookup("SERVER_HOST").unwrap_or_else(|| "127.0.0.1".to_string()),
+            server_port: lookup("SERVER_PORT")
                 .and_then(|p| p.parse().ok())
                 .unwrap_or(8080),
-            database_url: env::var("DATABASE_URL")
-                .unwrap_or_else(|_| "sqlite://data/ez-books.db".to_string()),
-            storage_path: env::var("STORAGE_PATH").unwrap_or_else(|_| "./data".to_string()),
-            openlibrary_api_url: env::var("OPENLIBRARY_API_URL")
-                .unwrap_or_else(|_| "https://openlibrary.org".to_string()),
+            database_url: lookup("DATABASE_URL")
+                .unwrap_or_else(|| "sqlite://data/ez-books.db".to_string()),
+            database_max_connections: lookup("DATABASE_MAX_CONNECTIONS")
+                .and_then(|n| n.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(5),
+            database_acquire_timeout_secs: lookup("DATABASE_ACQUIRE_TIMEOUT_SECS")
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(30),
+            database_journal_mode: lookup("DATABASE_JOURNAL_MODE")
+                .unwrap_or_else(|| "wal".to_string()),
+            database_synchronous: lookup("DATABASE_SYNCHRONOUS")
+                .unwrap_or_else(|| "normal".to_string()),
+            database_busy_timeout_ms: lookup("DATABASE_BUSY_TIMEOUT_MS")
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(5000),
+            storage_path: lookup("STORAGE_PATH").unwrap_or_else(|| "./data".to_string()),
+            openlibrary_api_url: parse_openlibrary_url(
+                &lookup("OPENLIBRARY_API_URL")
+                    .unwrap_or_else(|| "https://openlibrary.org".to_string()),
+            )?,
+            openlibrary_user_agent: lookup("OPENLIBRARY_USER_AGENT")
+                .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string()),
+            openlibrary_api_header: parse_api_header(
+                lookup("OPENLIBRARY_API_HEADER_NAME"),
+                lookup("OPENLIBRARY_API_HEADER_VALUE"),
+            )?,
+            enrichment_queue_capacity: lookup("ENRICHMENT_QUEUE_CAPACITY")
+                .and_then(|c| c.parse().ok())
+                .unwrap_or(100),
+            locked_metadata_fields: lookup("LOCKED_METADATA_FIELDS")
+                .map(|s| parse_metadata_fields(&s))
+                .transpose()?
+                .unwrap_or_default(),
+            upload_timeout_secs: lookup("UPLOAD_TIMEOUT_SECS")
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(120),
+            upload_idempotency_ttl_secs: lookup("UPLOAD_IDEMPOTENCY_TTL_SECS")
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(24 * 60 * 60),
+            storage_sharding: lookup("STORAGE_SHARDING")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            reject_duplicate_isbn: lookup("REJECT_DUPLICATE_ISBN")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            upload_allowed_extensions: lookup("UPLOAD_ALLOWED_EXTENSIONS")
+                .map(|s| parse_extensions(&s))
+                .filter(|extensions| !extensions.is_empty())
+                .unwrap_or_else(|| {
+                    DEFAULT_UPLOAD_EXTENSIONS
+                        .iter()
+                        .map(|extension| extension.to_string())
+                        .collect()
+                }),
+            import_folder: lookup("IMPORT_FOLDER").filter(|folder| !folder.is_empty()),
+            import_stability_secs: lookup("IMPORT_STABILITY_SECS")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(2),
+            watch_import_folder: lookup("WATCH_IMPORT_FOLDER")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            import_skip_patterns: lookup("IMPORT_SKIP_PATTERNS")
+                .map(|s| parse_list(&s))
+                .unwrap_or_default(),
+            reader_cache_max_bytes: lookup("READER_CACHE_MAX_BYTES")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(64 * 1024 * 1024),
+            reader_max_inline_bytes: lookup("READER_MAX_INLINE_BYTES")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(4 * 1024 * 1024),
+            reader_rejoin_hyphenated_words: lookup("READER_REJOIN_HYPHENATED_WORDS")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            admin_api_token: lookup("ADMIN_API_TOKEN").filter(|token| !token.trim().is_empty()),
+            base_path: parse_base_path(&lookup("BASE_PATH").unwrap_or_default())?,
+            validate_covers_on_read: lookup("VALIDATE_COVERS_ON_READ")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            cover_jpeg_quality: lookup("COVER_JPEG_QUALITY")
+                .map(|s| parse_jpeg_quality(&s))
+                .transpose()?
+                .unwrap_or(DEFAULT_COVER_JPEG_QUALITY),
+            cover_max_dimension: lookup("COVER_MAX_DIMENSION")
+                .and_then(|d| d.parse().ok())
+                .filter(|d| *d > 0)
+                .unwrap_or(DEFAULT_MAX_COVER_DIMENSION),
+            cover_max_pixels: lookup("COVER_MAX_PIXELS")
+                .and_then(|p| p.parse().ok())
+                .filter(|p| *p > 0)
+                .unwrap_or(DEFAULT_MAX_COVER_PIXELS),
+            cover_fit: parse_cover_fit(lookup("COVER_FIT"), lookup("COVER_PAD_COLOR"))?,
+            cover_keep_transparency: lookup("COVER_KEEP_TRANSPARENCY")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            default_sort: lookup("DEFAULT_SORT")
+                .map(|s| parse_default_sort(&s))
+                .transpose()?
+                .unwrap_or_default(),
+            default_page_size: lookup("DEFAULT_PAGE_SIZE")
+                .map(|s| parse_page_size(&s))
+                .transpose()?
+                .unwrap_or(DEFAULT_PAGE_LIMIT),
+            content_search: lookup("CONTENT_SEARCH")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            humanize_filename_titles: lookup("HUMANIZE_FILENAME_TITLES")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            require_cover: lookup("REQUIRE_COVER")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            rate_limit: parse_rate_limit(
+                lookup("RATE_LIMIT_PER_MINUTE"),
+                lookup("RATE_LIMIT_BURST"),
+            )?,
+            content_security_policy: match lookup("CONTENT_SECURITY_POLICY") {
+                Some(policy) => parse_content_security_policy(&policy)?,
+                None => Some(DEFAULT_CONTENT_SECURITY_POLICY.to_string()),
+            },
+            response_time_header: lookup("RESPONSE_TIME_HEADER")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(true),
+            slow_request_threshold_ms: lookup("SLOW_REQUEST_THRESHOLD_MS")
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(2000),
+            pretty_json: lookup("PRETTY_JSON")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
         })
     }
 
@@ -31,6 +242,219 @@ impl Config {
     }
 }
 
+fn parse_flag(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "1" | "true" | "yes")
+}
+
+/// Splits a `;`-separated list, dropping blank entries.
+/// Entries are not trimmed since spaces can be significant in patterns.
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .filter(|item| !item.trim().is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Normalises a base path to `/segment[/segment...]` without a trailing slash, or empty.
+/// Segments are limited to URL-safe characters since they are written into pages unescaped.
+fn parse_base_path(value: &str) -> Result<String> {
+    let segments: Vec<&str> = value
+        .trim()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let valid = segments.iter().all(|segment| {
+        segment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~'))
+    });
+    if !valid {
+        return Err(EzBooksError::Config(format!(
+            "Invalid BASE_PATH: {}",
+            value
+        )));
+    }
+
+    Ok(segments
+        .iter()
+        .map(|segment| format!("/{}", segment))
+        .collect())
+}
+
+/// An absolute http(s) URL with a host, without trailing slash
+fn parse_openlibrary_url(value: &str) -> Result<String> {
+    let invalid = || EzBooksError::Config(format!("Invalid OPENLIBRARY_API_URL: {}", value));
+    let url = reqwest::Url::parse(value.trim()).map_err(|_| invalid())?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(invalid());
+    }
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+/// Both the header name and value must be set, or neither
+fn parse_api_header(name: Option<String>, value: Option<String>) -> Result<Option<ApiHeader>> {
+    let name = name.filter(|name| !name.trim().is_empty());
+    let value = value.filter(|value| !value.trim().is_empty());
+    match (name, value) {
+        (None, None) => Ok(None),
+        (Some(name), Some(value)) => Ok(Some(ApiHeader { name, value })),
+        _ => Err(EzBooksError::Config(
+            "OPENLIBRARY_API_HEADER_NAME and OPENLIBRARY_API_HEADER_VALUE must be set together"
+                .to_string(),
+        )),
+    }
+}
+
+fn parse_jpeg_quality(value: &str) -> Result<u8> {
+    match value.trim().parse::<u8>() {
+        Ok(quality) if (1..=100).contains(&quality) => Ok(quality),
+        _ => Err(EzBooksError::Config(format!(
+            "COVER_JPEG_QUALITY must be between 1 and 100: {}",
+            value
+        ))),
+    }
+}
+
+/// Letterboxes are black unless `COVER_PAD_COLOR` says otherwise
+fn parse_cover_fit(fit: Option<String>, pad_color: Option<String>) -> Result<CoverFit> {
+    let pad_color = match pad_color {
+        Some(color) => parse_hex_color(&color).ok_or_else(|| {
+            EzBooksError::Config(format!("COVER_PAD_COLOR must be #rrggbb: {}", color))
+        })?,
+        None => [0, 0, 0],
+    };
+    match fit {
+        Some(fit) => CoverFit::from_name(&fit, pad_color).ok_or_else(|| {
+            EzBooksError::Config(format!("COVER_FIT must be contain, pad or cover: {}", fit))
+        }),
+        None => Ok(CoverFit::default()),
+    }
+}
+
+fn parse_metadata_fields(value: &str) -> Result<Vec<MetadataField>> {
+    parse_list(value)
+        .iter()
+        .map(|name| {
+            MetadataField::from_name(name).ok_or_else(|| {
+                EzBooksError::Config(format!(
+                    "LOCKED_METADATA_FIELDS may list title, author, description, publisher, publish_date and page_count: {}",
+                    name
+                ))
+            })
+        })
+        .collect()
+}
+
+fn parse_default_sort(value: &str) -> Result<BookSort> {
+    BookSort::from_name(value).ok_or_else(|| {
+        EzBooksError::Config(format!(
+            "DEFAULT_SORT must be created, size or completeness: {}",
+            value
+        ))
+    })
+}
+
+fn parse_page_size(value: &str) -> Result<u32> {
+    match value.trim().parse::<u32>() {
+        Ok(size) if (1..=MAX_PAGE_LIMIT).contains(&size) => Ok(size),
+        _ => Err(EzBooksError::Config(format!(
+            "DEFAULT_PAGE_SIZE must be between 1 and {}: {}",
+            MAX_PAGE_LIMIT, value
+        ))),
+    }
+}
+
+/// The burst defaults to the per-minute rate, so an idle client may spend a minute's worth at once
+fn parse_rate_limit(
+    per_minute: Option<String>,
+    burst: Option<String>,
+) -> Result<Option<RateLimit>> {
+    let per_minute = match per_minute.filter(|value| !value.trim().is_empty()) {
+        None => return Ok(None),
+        Some(value) => parse_count("RATE_LIMIT_PER_MINUTE", &value)?,
+    };
+    if per_minute == 0 {
+        return Ok(None);
+    }
+    let burst = match burst.filter(|value| !value.trim().is_empty()) {
+        None => per_minute,
+        Some(value) => match parse_count("RATE_LIMIT_BURST", &value)? {
+            0 => {
+                return Err(EzBooksError::Config(
+                    "RATE_LIMIT_BURST must be at least 1".to_string(),
+                ))
+            }
+            burst => burst,
+        },
+    };
+    Ok(Some(RateLimit { per_minute, burst }))
+}
+
+/// An empty policy turns the header off, for deployments that set it in a proxy
+fn parse_content_security_policy(value: &str) -> Result<Option<String>> {
+    let policy = value.trim();
+    if policy.is_empty() {
+        return Ok(None);
+    }
+    if !policy.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+        return Err(EzBooksError::Config(format!(
+            "CONTENT_SECURITY_POLICY must be printable ASCII: {}",
+            value
+        )));
+    }
+    Ok(Some(policy.to_string()))
+}
+
+fn parse_count(key: &str, value: &str) -> Result<u32> {
+    value
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| EzBooksError::Config(format!("{} must be a whole number: {}", key, value)))
+}
+
+/// `;`-separated extensions, normalised to lowercase without a leading dot
+fn parse_extensions(value: &str) -> Vec<String> {
+    parse_list(value)
+        .iter()
+        .map(|extension| extension.trim().trim_start_matches('.').to_lowercase())
+        .filter(|extension| !extension.is_empty())
+        .collect()
+}
+
+/// Reads a `.env`-style file: one `KEY=VALUE` per line, `#` starts a comment
+fn read_config_file(path: impl AsRef<Path>) -> Result<HashMap<String, String>> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).map_err(|e| {
+        EzBooksError::Config(format!(
+            "Failed to read config file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    let mut values = HashMap::new();
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = line.split_once('=').ok_or_else(|| {
+            EzBooksError::Config(format!(
+                "Invalid line {} in config file {}: expected KEY=VALUE",
+                line_number + 1,
+                path.display()
+            ))
+        })?;
+
+        let value = value.trim().trim_matches('"');
+        values.insert(key.trim().to_string(), value.to_string());
+    }
+
+    Ok(values)
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self::from_env().expect("Failed to load configuration")
@@ -52,6 +476,11 @@ mod tests {
         assert_eq!(config.database_url, "sqlite://data/ez-books.db");
         assert_eq!(config.storage_path, "./data");
         assert_eq!(config.openlibrary_api_url, "https://openlibrary.org");
+        assert_eq!(config.openlibrary_user_agent, DEFAULT_USER_AGENT);
+        assert_eq!(config.openlibrary_api_header, None);
+        assert_eq!(config.enrichment_queue_capacity, 100);
+        assert_eq!(config.default_sort, BookSort::Created);
+        assert_eq!(config.default_page_size, DEFAULT_PAGE_LIMIT);
     }
 
     #[test]
@@ -80,4 +509,285 @@ mod tests {
         // Cleanup
         env::remove_var("SERVER_PORT");
     }
+
+    #[test]
+    fn should_read_values_from_config_file() {
+        // Given: A config file with comments, blank lines and quoted values
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("ez-books.env");
+        fs::write(
+            &path,
+            "# Server\nSTORAGE_PATH=/srv/books\n\nDATABASE_URL=\"sqlite://srv.db\"\n",
+        )
+        .unwrap();
+
+        // When: Reading the file
+        let values = read_config_file(&path).unwrap();
+
+        // Then: Should contain the parsed keys
+        assert_eq!(values.get("STORAGE_PATH"), Some(&"/srv/books".to_string()));
+        assert_eq!(
+            values.get("DATABASE_URL"),
+            Some(&"sqlite://srv.db".to_string())
+        );
+        assert_eq!(values.len(), 2);
+    }
+
+    #[test]
+    fn should_reject_malformed_config_file_line() {
+        // Given: A config file with a line lacking '='
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("ez-books.env");
+        fs::write(&path, "STORAGE_PATH\n").unwrap();
+
+        // When: Reading the file
+        let result = read_config_file(&path);
+
+        // Then: Should return a config error naming the line
+        assert!(matches!(result, Err(EzBooksError::Config(msg)) if msg.contains("line 1")));
+    }
+
+    #[test]
+    fn should_prefer_lookup_values_over_defaults() {
+        // Given: A lookup providing only the storage path
+        let lookup = |key: &str| (key == "STORAGE_PATH").then(|| "/srv/books".to_string());
+
+        // When: Building config from the lookup
+        let config = Config::from_lookup(lookup).unwrap();
+
+        // Then: Provided key is used, others keep their defaults
+        assert_eq!(config.storage_path, "/srv/books");
+        assert_eq!(config.database_url, "sqlite://data/ez-books.db");
+    }
+
+    #[test]
+    fn should_parse_storage_sharding_flag() {
+        // Given/When: Building config with sharding enabled or left unset
+        let enabled =
+            Config::from_lookup(|key| (key == "STORAGE_SHARDING").then(|| "true".to_string()))
+                .unwrap();
+        let unset = Config::from_lookup(|_| None).unwrap();
+
+        // Then: Only the explicit flag turns it on
+        assert!(enabled.storage_sharding);
+        assert!(!unset.storage_sharding);
+    }
+
+    #[test]
+    fn should_split_import_skip_patterns() {
+        // Given/When: Building config with a semicolon-separated pattern list
+        let config = Config::from_lookup(|key| {
+            (key == "IMPORT_SKIP_PATTERNS").then(|| r" - Copy\.epub$; ;^draft-".to_string())
+        })
+        .unwrap();
+
+        // Then: Each non-blank pattern is kept as written
+        assert_eq!(
+            config.import_skip_patterns,
+            vec![r" - Copy\.epub$".to_string(), "^draft-".to_string()]
+        );
+    }
+
+    #[test]
+    fn should_normalise_upload_extensions() {
+        // Given/When: Building config with mixed-case, dotted extensions and without any
+        let config = Config::from_lookup(|key| {
+            (key == "UPLOAD_ALLOWED_EXTENSIONS").then(|| "EPUB; .pdf;".to_string())
+        })
+        .unwrap();
+        let defaults = Config::from_lookup(|_| None).unwrap();
+
+        // Then: Extensions are lowercase without dots, defaulting to EPUB only
+        assert_eq!(config.upload_allowed_extensions, vec!["epub", "pdf"]);
+        assert_eq!(defaults.upload_allowed_extensions, vec!["epub"]);
+    }
+
+    #[test]
+    fn should_normalise_base_path() {
+        // Given/When/Then: Slashes are normalised and an empty value keeps the root
+        assert_eq!(parse_base_path("").unwrap(), "");
+        assert_eq!(parse_base_path("/").unwrap(), "");
+        assert_eq!(parse_base_path("ezbooks").unwrap(), "/ezbooks");
+        assert_eq!(parse_base_path("/ezbooks/").unwrap(), "/ezbooks");
+        assert_eq!(
+            parse_base_path(" /apps//ez-books ").unwrap(),
+            "/apps/ez-books"
+        );
+    }
+
+    #[test]
+    fn should_reject_base_path_with_unsafe_characters() {
+        // Given/When/Then: Characters needing escaping in URLs are refused
+        assert!(parse_base_path("/ez books").is_err());
+        assert!(parse_base_path("/ez\"books").is_err());
+        assert!(parse_base_path("/ez?books").is_err());
+    }
+
+    #[test]
+    fn should_validate_cover_jpeg_quality_range() {
+        // Given/When/Then: 1-100 is accepted, anything else is a configuration error
+        assert_eq!(parse_jpeg_quality(" 80 ").unwrap(), 80);
+        assert_eq!(parse_jpeg_quality("1").unwrap(), 1);
+        assert_eq!(parse_jpeg_quality("100").unwrap(), 100);
+        assert!(parse_jpeg_quality("0").is_err());
+        assert!(parse_jpeg_quality("101").is_err());
+        assert!(parse_jpeg_quality("high").is_err());
+    }
+
+    #[test]
+    fn should_parse_cover_fit_with_pad_color() {
+        // Given/When/Then: Modes are matched ignoring case, padding defaults to black
+        assert_eq!(parse_cover_fit(None, None).unwrap(), CoverFit::Contain);
+        assert_eq!(
+            parse_cover_fit(Some(" Cover ".to_string()), None).unwrap(),
+            CoverFit::Cover
+        );
+        assert_eq!(
+            parse_cover_fit(Some("pad".to_string()), None).unwrap(),
+            CoverFit::Pad([0, 0, 0])
+        );
+        assert_eq!(
+            parse_cover_fit(Some("pad".to_string()), Some("#F5f0e6".to_string())).unwrap(),
+            CoverFit::Pad([0xf5, 0xf0, 0xe6])
+        );
+        assert!(parse_cover_fit(Some("stretch".to_string()), None).is_err());
+        assert!(parse_cover_fit(Some("pad".to_string()), Some("white".to_string())).is_err());
+    }
+
+    #[test]
+    fn should_parse_locked_metadata_fields() {
+        // Given/When/Then: Field names are ;-separated and checked
+        assert_eq!(
+            parse_metadata_fields("title; Author").unwrap(),
+            vec![MetadataField::Title, MetadataField::Author]
+        );
+        assert!(parse_metadata_fields("").unwrap().is_empty());
+        assert!(parse_metadata_fields("title;isbn").is_err());
+    }
+
+    #[test]
+    fn should_accept_http_and_https_openlibrary_urls() {
+        // Given/When/Then: Mirrors and proxies are accepted, trailing slashes dropped
+        assert_eq!(
+            parse_openlibrary_url("https://openlibrary.org").unwrap(),
+            "https://openlibrary.org"
+        );
+        assert_eq!(
+            parse_openlibrary_url(" http://ol-cache.lan:8081/openlibrary/ ").unwrap(),
+            "http://ol-cache.lan:8081/openlibrary"
+        );
+    }
+
+    #[test]
+    fn should_reject_malformed_openlibrary_urls() {
+        // Given/When/Then: Relative, non-http and hostless URLs are configuration errors
+        assert!(parse_openlibrary_url("openlibrary.org").is_err());
+        assert!(parse_openlibrary_url("ftp://openlibrary.org").is_err());
+        assert!(parse_openlibrary_url("file:///srv/openlibrary").is_err());
+        assert!(parse_openlibrary_url("").is_err());
+    }
+
+    #[test]
+    fn should_require_api_header_name_and_value_together() {
+        // Given/When/Then: Both or neither are accepted, blank counts as unset
+        assert_eq!(parse_api_header(None, Some(" ".to_string())).unwrap(), None);
+        assert_eq!(
+            parse_api_header(Some("X-Api-Key".to_string()), Some("s3cret".to_string())).unwrap(),
+            Some(ApiHeader {
+                name: "X-Api-Key".to_string(),
+                value: "s3cret".to_string(),
+            })
+        );
+        assert!(parse_api_header(Some("X-Api-Key".to_string()), None).is_err());
+        assert!(parse_api_header(None, Some("s3cret".to_string())).is_err());
+    }
+
+    #[test]
+    fn should_validate_default_sort_and_page_size() {
+        // Given/When/Then: Known sorts and in-range sizes are accepted, anything else fails
+        assert_eq!(
+            parse_default_sort("Completeness").unwrap(),
+            BookSort::Completeness
+        );
+        assert!(parse_default_sort("title").is_err());
+        assert_eq!(parse_page_size(" 25 ").unwrap(), 25);
+        assert!(parse_page_size("0").is_err());
+        assert!(parse_page_size(&(MAX_PAGE_LIMIT + 1).to_string()).is_err());
+        assert!(parse_page_size("many").is_err());
+    }
+
+    #[test]
+    fn should_parse_rate_limit_with_burst_defaulting_to_rate() {
+        // Given/When/Then: Unset or 0 disables the limit, the burst falls back to the rate
+        let value = |s: &str| Some(s.to_string());
+        assert_eq!(parse_rate_limit(None, value("5")).unwrap(), None);
+        assert_eq!(parse_rate_limit(value("0"), None).unwrap(), None);
+        assert_eq!(
+            parse_rate_limit(value(" 30 "), None).unwrap(),
+            Some(RateLimit {
+                per_minute: 30,
+                burst: 30
+            })
+        );
+        assert_eq!(
+            parse_rate_limit(value("30"), value("5")).unwrap(),
+            Some(RateLimit {
+                per_minute: 30,
+                burst: 5
+            })
+        );
+        assert!(parse_rate_limit(value("30"), value("0")).is_err());
+        assert!(parse_rate_limit(value("fast"), None).is_err());
+    }
+
+    #[test]
+    fn should_default_content_security_policy_and_allow_turning_it_off() {
+        // Given/When: Loading without the key and with a blank value
+        let default = Config::from_lookup(|_| None).unwrap();
+        let off =
+            Config::from_lookup(|key| (key == "CONTENT_SECURITY_POLICY").then(|| " ".to_string()))
+                .unwrap();
+
+        // Then: The built-in policy applies by default, blank disables it, control characters fail
+        assert_eq!(
+            default.content_security_policy.as_deref(),
+            Some(DEFAULT_CONTENT_SECURITY_POLICY)
+        );
+        assert_eq!(off.content_security_policy, None);
+        assert_eq!(
+            parse_content_security_policy(" default-src 'self' ").unwrap(),
+            Some("default-src 'self'".to_string())
+        );
+        assert!(parse_content_security_policy("default-src\n'self'").is_err());
+    }
+
+    #[test]
+    fn should_default_response_timing_and_allow_turning_it_off() {
+        // Given/When: Loading without the keys and with the header off and no threshold
+        let default = Config::from_lookup(|_| None).unwrap();
+        let off = Config::from_lookup(|key| match key {
+            "RESPONSE_TIME_HEADER" => Some("false".to_string()),
+            "SLOW_REQUEST_THRESHOLD_MS" => Some("0".to_string()),
+            _ => None,
+        })
+        .unwrap();
+
+        // Then: The header is on with a 2s threshold by default, and both can be disabled
+        assert!(default.response_time_header);
+        assert_eq!(default.slow_request_threshold_ms, 2000);
+        assert!(!off.response_time_header);
+        assert_eq!(off.slow_request_threshold_ms, 0);
+    }
+
+    #[test]
+    fn should_default_to_compact_json() {
+        // Given/When: Loading without the key and with it on
+        let default = Config::from_lookup(|_| None).unwrap();
+        let pretty =
+            Config::from_lookup(|key| (key == "PRETTY_JSON").then(|| "true".to_string())).unwrap();
+
+        // Then: Responses are compact unless asked otherwise
+        assert!(!default.pretty_json);
+        assert!(pretty.pretty_json);
+    }
 }
@@ -0,0 +1,182 @@
+use crate::reader_renderer::ReaderContent;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing::debug;
+
+/// Shared in-memory cache of sanitized reader content, bounded by total size.
+///
+/// Entries are keyed by book id and remember the book's `updated_at`, so content
+/// cached before an update is never served. The least recently used entries are
+/// evicted once `max_bytes` would be exceeded.
+#[derive(Clone, Debug)]
+pub struct ContentCache {
+    state: Arc<Mutex<CacheState>>,
+    max_bytes: usize,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    total_bytes: usize,
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    updated_at: i64,
+    content: Arc<ReaderContent>,
+    last_used: u64,
+}
+
+impl ContentCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CacheState::default())),
+            max_bytes,
+        }
+    }
+
+    /// Cached content for the book, if it was cached for the same `updated_at`
+    pub fn get(&self, book_id: &str, updated_at: i64) -> Option<Arc<ReaderContent>> {
+        let mut state = self.lock();
+        state.clock += 1;
+        let clock = state.clock;
+
+        match state.entries.get_mut(book_id) {
+            Some(entry) if entry.updated_at == updated_at => {
+                entry.last_used = clock;
+                Some(Arc::clone(&entry.content))
+            }
+            Some(_) => {
+                // Stale: the book changed since this was cached
+                state.remove(book_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, book_id: &str, updated_at: i64, content: Arc<ReaderContent>) {
+        let size = content.html.len();
+        if size > self.max_bytes {
+            debug!(book_id = %book_id, size, "Content too large to cache");
+            return;
+        }
+
+        let mut state = self.lock();
+        state.remove(book_id);
+
+        while state.total_bytes + size > self.max_bytes {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(id, _)| id.clone());
+            match oldest {
+                Some(id) => state.remove(&id),
+                None => break,
+            }
+        }
+
+        state.clock += 1;
+        let last_used = state.clock;
+        state.total_bytes += size;
+        state.entries.insert(
+            book_id.to_string(),
+            CacheEntry {
+                updated_at,
+                content,
+                last_used,
+            },
+        );
+    }
+
+    pub fn invalidate(&self, book_id: &str) {
+        self.lock().remove(book_id);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        // A panic while holding the lock cannot leave the map inconsistent
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl CacheState {
+    fn remove(&mut self, book_id: &str) {
+        if let Some(entry) = self.entries.remove(book_id) {
+            self.total_bytes -= entry.content.html.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(size: usize) -> Arc<ReaderContent> {
+        Arc::new(ReaderContent {
+            html: "x".repeat(size),
+            next_chapter: None,
+            chapter_count: 1,
+            chapter_starts: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn should_return_cached_content_for_same_version() {
+        // Given: A cache with an entry
+        let cache = ContentCache::new(100);
+        cache.insert("book", 1, content(10));
+
+        // When/Then: The same version is served, a newer one is not
+        assert_eq!(cache.get("book", 1).unwrap().html.len(), 10);
+        assert!(cache.get("book", 2).is_none());
+        assert!(cache.get("book", 1).is_none());
+    }
+
+    #[test]
+    fn should_evict_least_recently_used_when_full() {
+        // Given: A cache holding two entries at capacity
+        let cache = ContentCache::new(20);
+        cache.insert("a", 1, content(10));
+        cache.insert("b", 1, content(10));
+        cache.get("a", 1);
+
+        // When: Inserting a third entry
+        cache.insert("c", 1, content(10));
+
+        // Then: The least recently used entry should be gone
+        assert!(cache.get("a", 1).is_some());
+        assert!(cache.get("b", 1).is_none());
+        assert!(cache.get("c", 1).is_some());
+    }
+
+    #[test]
+    fn should_not_cache_content_larger_than_limit() {
+        // Given: A small cache
+        let cache = ContentCache::new(5);
+
+        // When: Inserting oversized content
+        cache.insert("big", 1, content(6));
+
+        // Then: It should not be stored
+        assert!(cache.get("big", 1).is_none());
+    }
+
+    #[test]
+    fn should_invalidate_entry() {
+        // Given: A cached entry
+        let cache = ContentCache::new(100);
+        cache.insert("book", 1, content(10));
+
+        // When: Invalidating it
+        cache.invalidate("book");
+
+        // Then: It should be gone and its space reclaimed
+        assert!(cache.get("book", 1).is_none());
+        cache.insert("other", 1, content(100));
+        assert!(cache.get("other", 1).is_some());
+    }
+}
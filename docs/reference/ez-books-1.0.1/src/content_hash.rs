@@ -0,0 +1,52 @@
+use crate::error::Result;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Hex-encoded SHA-256 of file contents, used to recognise files already in the library
+pub fn content_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// `content_hash` of a file, read in small blocks rather than all at once
+pub fn content_hash_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_hash_content_as_hex_sha256() {
+        // Given/When: Hashing known input
+        let hash = content_hash(b"abc");
+
+        // Then: Should match the SHA-256 test vector
+        assert_eq!(
+            hash,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn should_differ_for_different_content() {
+        // Given/When/Then: Different bytes produce different hashes
+        assert_ne!(content_hash(b"one"), content_hash(b"two"));
+    }
+
+    #[test]
+    fn should_hash_file_like_its_bytes() {
+        // Given: A file larger than one read block
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        let data = vec![7u8; 20_000];
+        std::fs::write(&path, &data).unwrap();
+
+        // When/Then: Hashing it streamed gives the in-memory hash
+        assert_eq!(content_hash_file(&path).unwrap(), content_hash(&data));
+    }
+}
@@ -0,0 +1,295 @@
+use crate::book_model::{current_timestamp, Book};
+use crate::book_repository;
+use crate::database_connection::DatabasePool;
+use crate::error::{EzBooksError, Result};
+use crate::fts_query::{self, highlight, MATCH_END, MATCH_START};
+use crate::text_extraction::for_each_chapter_text;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::{info, instrument, warn};
+
+/// Chapters extracted ahead of the database inserts
+const CHAPTER_BUFFER: usize = 2;
+/// Matching chapters read per search, across all books
+const MAX_MATCH_ROWS: i64 = 500;
+/// Snippets returned per book; further matching chapters are only counted
+pub const MAX_SNIPPETS_PER_BOOK: usize = 3;
+/// Words of context in a snippet
+const SNIPPET_TOKENS: i64 = 16;
+
+/// Outcome of indexing several books
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ContentIndexSummary {
+    pub indexed: usize,
+    pub failed: usize,
+}
+
+/// A book whose content matched a search, best match first
+#[derive(Debug, Serialize)]
+pub struct ContentMatch {
+    pub book: Book,
+    /// Matching chapters, including those without a snippet
+    pub match_count: usize,
+    pub snippets: Vec<ContentSnippet>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContentSnippet {
+    /// Spine index of the chapter, as in `/reader/{id}/chapters/{index}`
+    pub chapter_index: i64,
+    /// HTML-escaped text around the match, with the match in `<mark>`
+    pub snippet: String,
+}
+
+/// Replaces the indexed content of one book with the text of its EPUB
+#[instrument(skip(pool))]
+pub async fn index_book(pool: &DatabasePool, book_id: &str, epub_path: &str) -> Result<usize> {
+    let (sender, mut receiver) = mpsc::channel::<(usize, String)>(CHAPTER_BUFFER);
+    let path = epub_path.to_string();
+    let extraction = tokio::task::spawn_blocking(move || {
+        // A closed channel means indexing already failed; the rest is skipped quietly
+        for_each_chapter_text(&path, false, |index, text| {
+            let _ = sender.blocking_send((index, text));
+        })
+    });
+
+    sqlx::query("DELETE FROM book_content WHERE book_id = ?")
+        .bind(book_id)
+        .execute(pool)
+        .await?;
+    let mut chapters = 0;
+    while let Some((index, text)) = receiver.recv().await {
+        sqlx::query("INSERT INTO book_content (book_id, chapter_index, text) VALUES (?, ?, ?)")
+            .bind(book_id)
+            .bind(index as i64)
+            .bind(&text)
+            .execute(pool)
+            .await?;
+        chapters += 1;
+    }
+    extraction
+        .await
+        .map_err(|e| EzBooksError::EpubParse(format!("text extraction failed: {}", e)))??;
+
+    sqlx::query(
+        r#"
+        INSERT INTO book_content_indexed (book_id, chapter_count, indexed_at) VALUES (?, ?, ?)
+        ON CONFLICT (book_id) DO UPDATE SET
+            chapter_count = excluded.chapter_count, indexed_at = excluded.indexed_at
+        "#,
+    )
+    .bind(book_id)
+    .bind(chapters as i64)
+    .bind(current_timestamp())
+    .execute(pool)
+    .await?;
+
+    info!(book_id = %book_id, chapters, "Book content indexed");
+    Ok(chapters)
+}
+
+/// Indexes a newly stored book without delaying the response; failures are only logged
+pub fn index_in_background(pool: DatabasePool, book_id: String, epub_path: String) {
+    tokio::spawn(async move {
+        if let Err(e) = index_book(&pool, &book_id, &epub_path).await {
+            warn!(book_id = %book_id, error = %e, "Failed to index book content");
+        }
+    });
+}
+
+/// Indexes the books not indexed yet, such as those imported before content search was on
+#[instrument(skip(pool))]
+pub async fn index_unindexed(pool: &DatabasePool) -> Result<ContentIndexSummary> {
+    let books: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT id, epub_file_path FROM books
+        WHERE id NOT IN (SELECT book_id FROM book_content_indexed)
+        ORDER BY created_at
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    info!(count = books.len(), "Indexing book content");
+
+    let mut summary = ContentIndexSummary::default();
+    for (book_id, epub_path) in books {
+        match index_book(pool, &book_id, &epub_path).await {
+            Ok(_) => summary.indexed += 1,
+            Err(e) => {
+                warn!(book_id = %book_id, error = %e, "Failed to index book content");
+                summary.failed += 1;
+            }
+        }
+    }
+
+    info!(
+        indexed = summary.indexed,
+        failed = summary.failed,
+        "Content indexing completed"
+    );
+    Ok(summary)
+}
+
+/// Drops the whole index and indexes every book again
+#[instrument(skip(pool))]
+pub async fn rebuild_index(pool: &DatabasePool) -> Result<ContentIndexSummary> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM book_content_indexed")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM book_content")
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    index_unindexed(pool).await
+}
+
+/// Books containing `phrase`, ignoring case and diacritics, best match first
+#[instrument(skip(pool))]
+pub async fn search_content(
+    pool: &DatabasePool,
+    phrase: &str,
+    limit: u32,
+) -> Result<Vec<ContentMatch>> {
+    let rows: Vec<(String, i64, String)> = sqlx::query_as(
+        r#"
+        SELECT book_id, chapter_index, snippet(book_content, 2, ?, ?, '…', ?)
+        FROM book_content WHERE book_content MATCH ?
+        ORDER BY rank LIMIT ?
+        "#,
+    )
+    .bind(MATCH_START.to_string())
+    .bind(MATCH_END.to_string())
+    .bind(SNIPPET_TOKENS)
+    .bind(fts_query::phrase(phrase))
+    .bind(MAX_MATCH_ROWS)
+    .fetch_all(pool)
+    .await?;
+
+    let mut matches: Vec<(String, usize, Vec<ContentSnippet>)> = Vec::new();
+    for (book_id, chapter_index, snippet) in rows {
+        let snippet = ContentSnippet {
+            chapter_index,
+            snippet: highlight(&snippet),
+        };
+        if let Some((_, match_count, snippets)) =
+            matches.iter_mut().find(|(found, _, _)| *found == book_id)
+        {
+            *match_count += 1;
+            if snippets.len() < MAX_SNIPPETS_PER_BOOK {
+                snippets.push(snippet);
+            }
+        } else if matches.len() < limit as usize {
+            matches.push((book_id, 1, vec![snippet]));
+        }
+    }
+
+    let ids: Vec<String> = matches.iter().map(|(id, _, _)| id.clone()).collect();
+    let mut books = book_repository::find_by_ids(pool, &ids).await?;
+    // Books deleted while the search ran are left out
+    let matches: Vec<ContentMatch> = matches
+        .into_iter()
+        .filter_map(|(id, match_count, snippets)| {
+            books.remove(&id).map(|book| ContentMatch {
+                book,
+                match_count,
+                snippets,
+            })
+        })
+        .collect();
+
+    info!(books = matches.len(), "Content search completed");
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_storage::FileStorage;
+    use crate::test_epub::TestEpub;
+    use crate::test_fixtures::setup_test_db;
+    use tempfile::TempDir;
+
+    async fn setup_library() -> (DatabasePool, FileStorage, TempDir) {
+        let (pool, temp_dir) = setup_test_db().await;
+        let storage = FileStorage::new(temp_dir.path().join("data")).unwrap();
+        (pool, storage, temp_dir)
+    }
+
+    async fn store_book(
+        pool: &DatabasePool,
+        storage: &FileStorage,
+        title: &str,
+        chapters: &[&str],
+    ) -> Book {
+        let mut book = Book::new(title.to_string(), String::new());
+        let epub = TestEpub::new(title).chapters(chapters).build();
+        book.epub_file_path = storage.save_epub(&book.id, &epub).unwrap();
+        book_repository::insert(pool, &book).await.unwrap();
+        book
+    }
+
+    #[tokio::test]
+    async fn should_find_phrase_inside_chapters_with_highlighted_snippet() {
+        // Given: Two indexed books, one mentioning the phrase in its second chapter
+        let (pool, storage, _temp_dir) = setup_library().await;
+        let whale = store_book(
+            &pool,
+            &storage,
+            "Whale",
+            &[
+                "<p>Call me Ishmael.</p>",
+                "<p>The <b>white</b> whale & the sea.</p>",
+            ],
+        )
+        .await;
+        store_book(&pool, &storage, "Garden", &["<p>White roses only.</p>"]).await;
+        let summary = index_unindexed(&pool).await.unwrap();
+
+        // When: Searching for the phrase in another case and for words out of order
+        let found = search_content(&pool, "WHITE WHALE", 10).await.unwrap();
+        let reversed = search_content(&pool, "whale white", 10).await.unwrap();
+
+        // Then: Only the book with the exact phrase matches, with the match marked
+        assert_eq!(
+            summary,
+            ContentIndexSummary {
+                indexed: 2,
+                failed: 0
+            }
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].book.id, whale.id);
+        assert_eq!(found[0].snippets[0].chapter_index, 1);
+        assert_eq!(
+            found[0].snippets[0].snippet,
+            "The <mark>white whale</mark> &amp; the sea."
+        );
+        assert!(reversed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_skip_indexed_books_and_drop_index_of_deleted_ones() {
+        // Given: An indexed book
+        let (pool, storage, _temp_dir) = setup_library().await;
+        let book = store_book(&pool, &storage, "Indexed", &["<p>Unique marmalade</p>"]).await;
+        index_unindexed(&pool).await.unwrap();
+
+        // When: Backfilling again, then deleting the book
+        let again = index_unindexed(&pool).await.unwrap();
+        book_repository::delete(&pool, &book.id).await.unwrap();
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM book_content")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        // Then: Nothing is indexed twice and the deleted book leaves no content behind
+        assert_eq!(again, ContentIndexSummary::default());
+        assert_eq!(rows, 0);
+        assert!(search_content(&pool, "marmalade", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::content_hash::content_hash;
+use crate::epub_cover_extractor::encode_jpeg;
+use crate::error::Result;
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use tracing::warn;
+
+/// Size each cover is scaled into
+pub const TILE_WIDTH: u32 = 150;
+pub const TILE_HEIGHT: u32 = 225;
+/// Space between tiles and around the edge
+const GAP: u32 = 4;
+const BACKGROUND: Rgb<u8> = Rgb([0x22, 0x22, 0x22]);
+
+/// Identifies a montage of `grid`, e.g. `6x4`, by the books it shows and their covers, so
+/// a cached montage is used until a book is added or a cover changes
+pub fn montage_key(grid: &str, covers: &[(String, Option<String>)]) -> String {
+    let mut input = grid.to_string();
+    for (id, cover_hash) in covers {
+        input.push('\n');
+        input.push_str(id);
+        input.push(':');
+        input.push_str(cover_hash.as_deref().unwrap_or_default());
+    }
+    content_hash(input.as_bytes())
+}
+
+/// JPEG of `tiles` in rows of `columns`, as many rows as they fill and at least one.
+/// Tiles are expected at most `TILE_WIDTH` by `TILE_HEIGHT`; ones that do not decode
+/// leave their place empty.
+pub fn compose_montage(tiles: &[Vec<u8>], columns: u32, jpeg_quality: u8) -> Result<Vec<u8>> {
+    let columns = columns.max(1);
+    let rows = ((tiles.len() as u32 + columns - 1) / columns).max(1);
+    let mut canvas = RgbImage::from_pixel(
+        GAP + columns * (TILE_WIDTH + GAP),
+        GAP + rows * (TILE_HEIGHT + GAP),
+        BACKGROUND,
+    );
+
+    for (index, data) in tiles.iter().enumerate() {
+        let tile = match image::load_from_memory(data) {
+            Ok(tile) => tile,
+            Err(e) => {
+                warn!(index, error = %e, "Skipping montage tile that does not decode");
+                continue;
+            }
+        };
+        let (width, height) = tile.dimensions();
+        let tile = if width > TILE_WIDTH || height > TILE_HEIGHT {
+            tile.thumbnail(TILE_WIDTH, TILE_HEIGHT)
+        } else {
+            tile
+        };
+        let (width, height) = tile.dimensions();
+        let column = index as u32 % columns;
+        let row = index as u32 / columns;
+        image::imageops::overlay(
+            &mut canvas,
+            &tile.to_rgb8(),
+            i64::from(GAP + column * (TILE_WIDTH + GAP) + (TILE_WIDTH - width) / 2),
+            i64::from(GAP + row * (TILE_HEIGHT + GAP) + (TILE_HEIGHT - height) / 2),
+        );
+    }
+
+    encode_jpeg(&DynamicImage::ImageRgb8(canvas), jpeg_quality)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn png(width: u32, height: u32, color: [u8; 4]) -> Vec<u8> {
+        let mut data = Vec::new();
+        RgbaImage::from_pixel(width, height, Rgba(color))
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn should_lay_out_covers_in_rows_of_the_requested_columns() {
+        // Given: Three covers, one of them broken, for two columns
+        let tiles = vec![
+            png(TILE_WIDTH, TILE_HEIGHT, [255, 0, 0, 255]),
+            b"not an image".to_vec(),
+            png(TILE_WIDTH, TILE_HEIGHT, [0, 0, 255, 255]),
+        ];
+
+        // When: Composing the montage
+        let montage = compose_montage(&tiles, 2, 90).unwrap();
+
+        // Then: Two rows of two tiles, the broken one left empty
+        let image = image::load_from_memory(&montage).unwrap().to_rgb8();
+        assert_eq!(
+            image.dimensions(),
+            (GAP + 2 * (TILE_WIDTH + GAP), GAP + 2 * (TILE_HEIGHT + GAP))
+        );
+        let center = |column: u32, row: u32| {
+            *image.get_pixel(
+                GAP + column * (TILE_WIDTH + GAP) + TILE_WIDTH / 2,
+                GAP + row * (TILE_HEIGHT + GAP) + TILE_HEIGHT / 2,
+            )
+        };
+        assert!(center(0, 0).0[0] > 200);
+        assert!(center(1, 0).0.iter().all(|channel| *channel < 60));
+        assert!(center(0, 1).0[2] > 200);
+    }
+
+    #[test]
+    fn should_key_montages_by_books_and_covers() {
+        // Given: The same books, with one cover changed, and another grid
+        let covers = vec![
+            ("a".to_string(), Some("h1".to_string())),
+            ("b".to_string(), None),
+        ];
+        let mut changed = covers.clone();
+        changed[1].1 = Some("h2".to_string());
+
+        // When/Then: Only identical grids of identical covers share a key
+        assert_eq!(montage_key("2x1", &covers), montage_key("2x1", &covers));
+        assert_ne!(montage_key("2x1", &covers), montage_key("2x1", &changed));
+        assert_ne!(montage_key("2x1", &covers), montage_key("1x2", &covers));
+    }
+}
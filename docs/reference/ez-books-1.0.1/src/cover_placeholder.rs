@@ -0,0 +1,146 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Size of the generated placeholder, the aspect ratio of gallery covers
+const PLACEHOLDER_WIDTH: u32 = 250;
+const PLACEHOLDER_HEIGHT: u32 = 375;
+
+/// What to paint where a book's cover goes, from `GET /api/books/{id}/cover/color`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoverColor {
+    /// `#rrggbb` dominant color of the cover, or the top color of the placeholder gradient
+    pub color: String,
+    /// Black or white, whichever reads better on `color`
+    pub text_color: &'static str,
+    /// Top and bottom color of the placeholder; `None` for books with a cover
+    pub gradient: Option<[String; 2]>,
+}
+
+impl CoverColor {
+    pub fn from_cover(color: String) -> Self {
+        Self {
+            text_color: text_color_for(&color),
+            color,
+            gradient: None,
+        }
+    }
+
+    pub fn placeholder(title: &str) -> Self {
+        let gradient = gradient_colors(title);
+        Self {
+            color: gradient[0].clone(),
+            text_color: text_color_for(&gradient[0]),
+            gradient: Some(gradient),
+        }
+    }
+}
+
+/// Two colors of related hue, the second darker, both chosen by the title
+pub fn gradient_colors(title: &str) -> [String; 2] {
+    let digest = Sha256::digest(title.trim().as_bytes());
+    let hue = f64::from(u16::from_be_bytes([digest[0], digest[1]]) % 360);
+    // 30 to 90 degrees apart, so the two tones are distinct but never clash
+    let shift = 30.0 + f64::from(digest[2] % 61);
+    [
+        hsl_to_hex(hue, 0.55, 0.55),
+        hsl_to_hex((hue + shift) % 360.0, 0.6, 0.35),
+    ]
+}
+
+/// `#000000` on light backgrounds and `#ffffff` on dark ones, by WCAG relative luminance
+pub fn text_color_for(background: &str) -> &'static str {
+    let channel = |index: usize| {
+        let value = background
+            .trim_start_matches('#')
+            .get(index..index + 2)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .map_or(0.0, |value| f64::from(value) / 255.0);
+        if value <= 0.03928 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let luminance = 0.2126 * channel(0) + 0.7152 * channel(2) + 0.0722 * channel(4);
+    // Where black and white have the same contrast ratio
+    if luminance > 0.179 {
+        "#000000"
+    } else {
+        "#ffffff"
+    }
+}
+
+/// SVG cover of the title's gradient, served for books without a cover
+pub fn placeholder_svg(title: &str) -> String {
+    let [top, bottom] = gradient_colors(title);
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}"><defs><linearGradient id="g" x1="0" y1="0" x2="1" y2="1"><stop offset="0" stop-color="{top}"/><stop offset="1" stop-color="{bottom}"/></linearGradient></defs><rect width="{w}" height="{h}" fill="url(#g)"/></svg>"#,
+        w = PLACEHOLDER_WIDTH,
+        h = PLACEHOLDER_HEIGHT,
+        top = top,
+        bottom = bottom
+    )
+}
+
+fn hsl_to_hex(hue: f64, saturation: f64, lightness: f64) -> String {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let offset = lightness - chroma / 2.0;
+    let byte = |channel: f64| ((channel + offset) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(r), byte(g), byte(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_pick_the_same_distinct_gradient_for_a_title() {
+        // Given/When: The gradient of a title, computed twice, and of another title
+        let dune = gradient_colors("Dune");
+        let again = gradient_colors(" Dune ");
+        let other = gradient_colors("Neuromancer");
+
+        // Then: It is stable, two-tone and differs between titles
+        assert_eq!(dune, again);
+        assert_ne!(dune[0], dune[1]);
+        assert_ne!(dune, other);
+        assert!(dune
+            .iter()
+            .all(|color| color.len() == 7 && color.starts_with('#')));
+    }
+
+    #[test]
+    fn should_choose_readable_text_color() {
+        // Given/When/Then: Light backgrounds get black text, dark ones white
+        assert_eq!(text_color_for("#ffffff"), "#000000");
+        assert_eq!(text_color_for("#f1c40f"), "#000000");
+        assert_eq!(text_color_for("#2c3e50"), "#ffffff");
+        assert_eq!(text_color_for("#000000"), "#ffffff");
+        assert_eq!(hsl_to_hex(0.0, 1.0, 0.5), "#ff0000");
+        assert_eq!(hsl_to_hex(240.0, 1.0, 0.5), "#0000ff");
+    }
+
+    #[test]
+    fn should_describe_placeholder_with_its_gradient() {
+        // Given/When: The placeholder color and SVG of a title
+        let color = CoverColor::placeholder("Dune");
+        let svg = placeholder_svg("Dune");
+        let [top, bottom] = gradient_colors("Dune");
+
+        // Then: Both use the title's gradient
+        assert_eq!(color.color, top);
+        assert_eq!(color.gradient, Some([top.clone(), bottom.clone()]));
+        assert!(svg.contains(&format!(r#"stop-color="{}""#, top)));
+        assert!(svg.contains(&format!(r#"stop-color="{}""#, bottom)));
+    }
+}
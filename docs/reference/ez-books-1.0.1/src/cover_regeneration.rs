@@ -0,0 +1,285 @@
+use crate::book_model::{current_timestamp, Book};
+use crate::book_repository;
+use crate::content_hash::content_hash;
+use crate::database_connection::DatabasePool;
+use crate::epub_cover_extractor::{extract_cover, sniff_cover_mime, ExtractedCover};
+use crate::error::{EzBooksError, Result};
+use crate::file_storage::FileStorage;
+use crate::upload_handler::UploadSettings;
+use futures::StreamExt;
+use serde::Serialize;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing::{info, instrument, warn};
+
+/// Books whose covers are extracted at the same time
+pub const REGENERATION_CONCURRENCY: usize = 4;
+
+/// State of the current or last regeneration run
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RegenerationProgress {
+    pub running: bool,
+    /// Books in the library when the run started
+    pub total: usize,
+    pub regenerated: usize,
+    /// Books whose EPUB is missing or has no cover; their current cover is kept
+    pub skipped: usize,
+    pub failed: usize,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Regenerated,
+    Skipped,
+    Failed,
+}
+
+/// Handle on the background regeneration; clones share one run
+#[derive(Debug, Clone, Default)]
+pub struct CoverRegeneration {
+    progress: Arc<Mutex<RegenerationProgress>>,
+}
+
+impl CoverRegeneration {
+    pub fn progress(&self) -> RegenerationProgress {
+        self.lock().clone()
+    }
+
+    /// Starts a run with the cover settings of `settings`, unless one is running already
+    pub fn start(
+        &self,
+        pool: DatabasePool,
+        storage: FileStorage,
+        settings: UploadSettings,
+    ) -> Result<RegenerationProgress> {
+        let started = {
+            let mut progress = self.lock();
+            if progress.running {
+                return Err(EzBooksError::CoverRegenerationRunning);
+            }
+            *progress = RegenerationProgress {
+                running: true,
+                started_at: Some(current_timestamp()),
+                ..RegenerationProgress::default()
+            };
+            progress.clone()
+        };
+
+        let regeneration = self.clone();
+        tokio::spawn(async move { regeneration.run(&pool, &storage, &settings).await });
+        Ok(started)
+    }
+
+    #[instrument(skip_all)]
+    async fn run(&self, pool: &DatabasePool, storage: &FileStorage, settings: &UploadSettings) {
+        match book_repository::find_all(pool).await {
+            Ok(books) => {
+                info!(total = books.len(), "Starting cover regeneration");
+                self.lock().total = books.len();
+                futures::stream::iter(books)
+                    .map(|book| regenerate_cover(pool, storage, settings, book))
+                    .buffer_unordered(REGENERATION_CONCURRENCY)
+                    .for_each(|outcome| {
+                        self.record(outcome);
+                        futures::future::ready(())
+                    })
+                    .await;
+            }
+            Err(e) => warn!(error = %e, "Failed to list books for cover regeneration"),
+        }
+
+        let mut progress = self.lock();
+        progress.running = false;
+        progress.finished_at = Some(current_timestamp());
+        info!(
+            regenerated = progress.regenerated,
+            skipped = progress.skipped,
+            failed = progress.failed,
+            "Cover regeneration completed"
+        );
+    }
+
+    fn record(&self, outcome: Outcome) {
+        let mut progress = self.lock();
+        match outcome {
+            Outcome::Regenerated => progress.regenerated += 1,
+            Outcome::Skipped => progress.skipped += 1,
+            Outcome::Failed => progress.failed += 1,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RegenerationProgress> {
+        // Counters stay meaningful even if a holder of the lock panicked
+        self.progress
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Extracts the book's cover again and replaces the stored one
+async fn regenerate_cover(
+    pool: &DatabasePool,
+    storage: &FileStorage,
+    settings: &UploadSettings,
+    book: Book,
+) -> Outcome {
+    let epub_path = storage.epub_path(&book.id);
+    if !epub_path.is_file() {
+        warn!(book_id = %book.id, "Skipping cover regeneration, EPUB is missing");
+        return Outcome::Skipped;
+    }
+
+    let (quality, max_dimension, max_pixels, fit, keep_transparency) = (
+        settings.cover_jpeg_quality,
+        settings.cover_max_dimension,
+        settings.cover_max_pixels,
+        settings.cover_fit,
+        settings.cover_keep_transparency,
+    );
+    let extracted = tokio::task::spawn_blocking(move || {
+        extract_cover(
+            epub_path,
+            quality,
+            max_dimension,
+            max_pixels,
+            fit,
+            keep_transparency,
+        )
+    })
+    .await
+    .map_err(|e| EzBooksError::ImageProcessing(format!("cover extraction failed: {}", e)))
+    .and_then(|result| result);
+
+    match extracted {
+        Ok(Some(cover)) => match store_cover(pool, storage, &book.id, &cover).await {
+            Ok(()) => Outcome::Regenerated,
+            Err(e) => {
+                warn!(book_id = %book.id, error = %e, "Failed to store regenerated cover");
+                Outcome::Failed
+            }
+        },
+        // Covers found by enrichment are kept for EPUBs without one
+        Ok(None) => Outcome::Skipped,
+        Err(e) => {
+            warn!(book_id = %book.id, error = %e, "Failed to regenerate cover");
+            Outcome::Failed
+        }
+    }
+}
+
+async fn store_cover(
+    pool: &DatabasePool,
+    storage: &FileStorage,
+    book_id: &str,
+    cover: &ExtractedCover,
+) -> Result<()> {
+    let path = storage.save_cover(book_id, &cover.data)?;
+    book_repository::update_cover(
+        pool,
+        book_id,
+        &path,
+        &content_hash(&cover.data),
+        sniff_cover_mime(&cover.data),
+        cover.color.as_deref(),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_epub::TestEpub;
+    use crate::test_fixtures::setup_test_db;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    async fn setup() -> (DatabasePool, FileStorage, TempDir) {
+        let (pool, temp_dir) = setup_test_db().await;
+        let storage = FileStorage::new(temp_dir.path().join("data")).unwrap();
+        (pool, storage, temp_dir)
+    }
+
+    fn png_cover() -> Vec<u8> {
+        let mut data = Vec::new();
+        image::RgbImage::from_pixel(60, 90, image::Rgb([40, 90, 160]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut data),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        data
+    }
+
+    async fn wait_until_finished(regeneration: &CoverRegeneration) -> RegenerationProgress {
+        for _ in 0..200 {
+            let progress = regeneration.progress();
+            if !progress.running {
+                return progress;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        panic!("cover regeneration did not finish");
+    }
+
+    #[tokio::test]
+    async fn should_replace_covers_and_skip_books_without_epub() {
+        // Given: A book with a stale stored cover and one whose EPUB is missing
+        let (pool, storage, _temp_dir) = setup().await;
+        let mut covered = Book::new("Covered".to_string(), String::new());
+        let epub = TestEpub::new("Covered").image("cover.png", png_cover());
+        covered.epub_file_path = storage.save_epub(&covered.id, &epub.build()).unwrap();
+        covered.cover_image_path = Some(storage.save_cover(&covered.id, b"stale").unwrap());
+        covered.cover_hash = Some(content_hash(b"stale"));
+        book_repository::insert(&pool, &covered).await.unwrap();
+        let missing = Book::new("Missing".to_string(), "/missing.epub".to_string());
+        book_repository::insert(&pool, &missing).await.unwrap();
+        let settings = UploadSettings::from_config(&Config::default());
+
+        // When: Regenerating the covers and waiting for the run to end
+        let regeneration = CoverRegeneration::default();
+        let started = regeneration
+            .start(pool.clone(), storage.clone(), settings)
+            .unwrap();
+        let progress = wait_until_finished(&regeneration).await;
+
+        // Then: The stale cover is replaced by one extracted from the EPUB
+        assert!(started.running);
+        assert_eq!(progress.total, 2);
+        assert_eq!(progress.regenerated, 1);
+        assert_eq!(progress.skipped, 1);
+        assert_eq!(progress.failed, 0);
+        assert!(progress.finished_at.is_some());
+        let found = book_repository::find_by_id(&pool, &covered.id)
+            .await
+            .unwrap();
+        let cover = storage.read_cover(&covered.id).unwrap();
+        assert_ne!(cover, b"stale");
+        assert_eq!(found.cover_hash, Some(content_hash(&cover)));
+        assert_eq!(found.cover_mime.as_deref(), Some("image/jpeg"));
+    }
+
+    #[tokio::test]
+    async fn should_refuse_second_run_while_one_is_running() {
+        // Given: A run that has started
+        let (pool, storage, _temp_dir) = setup().await;
+        let settings = UploadSettings::from_config(&Config::default());
+        let regeneration = CoverRegeneration::default();
+        regeneration
+            .start(pool.clone(), storage.clone(), settings.clone())
+            .unwrap();
+
+        // When: Starting another before it ends, then once it has ended
+        let second = regeneration.start(pool.clone(), storage.clone(), settings.clone());
+        wait_until_finished(&regeneration).await;
+        let third = regeneration.start(pool, storage, settings);
+
+        // Then: Only the overlapping run is refused
+        assert!(matches!(
+            second,
+            Err(EzBooksError::CoverRegenerationRunning)
+        ));
+        assert!(third.is_ok());
+    }
+}
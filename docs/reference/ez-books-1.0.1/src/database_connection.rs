@@ -1,20 +1,78 @@
-use crate::error::Result;
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use crate::config::Config;
+use crate::error::{EzBooksError, Result};
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteSynchronous,
+};
 use std::str::FromStr;
+use std::time::Duration;
 use tracing::{info, instrument};
 
 pub type DatabasePool = SqlitePool;
 
+/// Connection pool limits and SQLite pragmas
+#[derive(Debug, Clone, Copy)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    /// How long a request waits for a free connection before failing with 503
+    pub acquire_timeout: Duration,
+    pub journal_mode: SqliteJournalMode,
+    pub synchronous: SqliteSynchronous,
+    /// How long a statement waits for a competing writer's lock before failing
+    pub busy_timeout: Duration,
+}
+
+impl PoolSettings {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(Self {
+            max_connections: config.database_max_connections,
+            acquire_timeout: Duration::from_secs(config.database_acquire_timeout_secs),
+            journal_mode: parse_pragma("DATABASE_JOURNAL_MODE", &config.database_journal_mode)?,
+            synchronous: parse_pragma("DATABASE_SYNCHRONOUS", &config.database_synchronous)?,
+            busy_timeout: Duration::from_millis(config.database_busy_timeout_ms),
+        })
+    }
+}
+
+/// Tuned for a read-heavy library: WAL lets readers proceed while an upload or
+/// enrichment is writing, and `NORMAL` sync is safe with WAL (a power loss can only
+/// drop the last transactions, never corrupt the database)
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            acquire_timeout: Duration::from_secs(30),
+            journal_mode: SqliteJournalMode::Wal,
+            synchronous: SqliteSynchronous::Normal,
+            busy_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+fn parse_pragma<T: FromStr>(key: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| EzBooksError::Config(format!("Invalid {}: {}", key, value)))
+}
+
 #[instrument]
-pub async fn create_pool(database_url: &str) -> Result<DatabasePool> {
-    info!("Creating database connection pool");
+pub async fn create_pool(database_url: &str, settings: PoolSettings) -> Result<DatabasePool> {
+    info!(
+        max_connections = settings.max_connections,
+        journal_mode = ?settings.journal_mode,
+        synchronous = ?settings.synchronous,
+        "Creating database connection pool"
+    );
 
     let options = SqliteConnectOptions::from_str(database_url)?
         .create_if_missing(true)
-        .foreign_keys(true);
+        .foreign_keys(true)
+        .journal_mode(settings.journal_mode)
+        .synchronous(settings.synchronous)
+        .busy_timeout(settings.busy_timeout);
 
     let pool = SqlitePoolOptions::new()
-        .max_connections(5)
+        .max_connections(settings.max_connections)
+        .acquire_timeout(settings.acquire_timeout)
         .connect_with(options)
         .await?;
 
@@ -26,10 +84,8 @@ pub async fn create_pool(database_url: &str) -> Result<DatabasePool> {
 pub async fn run_migrations(pool: &DatabasePool) -> Result<()> {
     info!("Running database migrations");
 
-    // Read and execute migration file
-    let migration_sql = include_str!("../migrations/001_initial_schema.sql");
-
-    sqlx::query(migration_sql).execute(pool).await?;
+    // Apply versioned migrations; already-applied versions are skipped
+    sqlx::migrate!("./migrations").run(pool).await?;
 
     info!("Database migrations completed successfully");
     Ok(())
@@ -45,7 +101,7 @@ mod tests {
         let db_path = temp_dir.path().join("test.db");
         let database_url = format!("sqlite://{}", db_path.display());
 
-        let pool = create_pool(&database_url)
+        let pool = create_pool(&database_url, PoolSettings::default())
             .await
             .expect("Failed to create pool");
 
@@ -60,7 +116,7 @@ mod tests {
         let database_url = format!("sqlite://{}", db_path.display());
 
         // When: Creating a connection pool
-        let result = create_pool(&database_url).await;
+        let result = create_pool(&database_url, PoolSettings::default()).await;
 
         // Then: Pool should be created successfully
         assert!(result.is_ok());
@@ -76,12 +132,96 @@ mod tests {
         let database_url = format!("sqlite://{}", db_path.display());
 
         // When: Creating a pool
-        let _ = create_pool(&database_url).await.unwrap();
+        let _ = create_pool(&database_url, PoolSettings::default())
+            .await
+            .unwrap();
 
         // Then: Database file should be created
         assert!(db_path.exists());
     }
 
+    #[tokio::test]
+    async fn should_use_wal_journal_mode() {
+        // Given: A fresh database pool
+        let (pool, _temp_dir) = create_test_pool().await;
+
+        // When: Reading the journal mode
+        let mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        // Then: Should be WAL with NORMAL sync (1)
+        let synchronous: i64 = sqlx::query_scalar("PRAGMA synchronous")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(mode, "wal");
+        assert_eq!(synchronous, 1);
+    }
+
+    #[tokio::test]
+    async fn should_apply_overridden_pragmas() {
+        // Given: Settings preferring durability over throughput
+        let temp_dir = TempDir::new().unwrap();
+        let database_url = format!("sqlite://{}", temp_dir.path().join("test.db").display());
+        let settings = PoolSettings {
+            journal_mode: SqliteJournalMode::Delete,
+            synchronous: SqliteSynchronous::Full,
+            ..PoolSettings::default()
+        };
+
+        // When: Creating the pool and reading the pragmas
+        let pool = create_pool(&database_url, settings).await.unwrap();
+        let mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let synchronous: i64 = sqlx::query_scalar("PRAGMA synchronous")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        // Then: The overrides should be in effect (FULL = 2)
+        assert_eq!(mode, "delete");
+        assert_eq!(synchronous, 2);
+    }
+
+    #[test]
+    fn should_reject_unknown_pragma_values() {
+        // Given: A config with a misspelled journal mode
+        let mut config = Config::from_env().unwrap();
+        config.database_journal_mode = "wall".to_string();
+
+        // When: Building pool settings
+        let result = PoolSettings::from_config(&config);
+
+        // Then: Should be a configuration error naming the setting
+        assert!(
+            matches!(result, Err(EzBooksError::Config(msg)) if msg.contains("DATABASE_JOURNAL_MODE"))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_time_out_when_pool_is_exhausted() {
+        // Given: A single-connection pool whose connection is in use
+        let temp_dir = TempDir::new().unwrap();
+        let database_url = format!("sqlite://{}", temp_dir.path().join("test.db").display());
+        let settings = PoolSettings {
+            max_connections: 1,
+            acquire_timeout: Duration::from_millis(100),
+            ..PoolSettings::default()
+        };
+        let pool = create_pool(&database_url, settings).await.unwrap();
+        let _held = pool.acquire().await.unwrap();
+
+        // When: Acquiring another connection
+        let result = pool.acquire().await;
+
+        // Then: Should fail quickly with a pool timeout instead of hanging
+        assert!(matches!(result, Err(sqlx::Error::PoolTimedOut)));
+    }
+
     #[tokio::test]
     async fn should_run_migrations_successfully() {
         // Given: A fresh database pool
@@ -150,7 +290,30 @@ mod tests {
         // When: Running migrations again
         let result = run_migrations(&pool).await;
 
-        // Then: Should succeed (using IF NOT EXISTS)
+        // Then: Should succeed (applied migrations are skipped)
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn should_default_enrichment_status_to_done() {
+        // Given: A database with migrations run
+        let (pool, _temp_dir) = create_test_pool().await;
+        run_migrations(&pool).await.unwrap();
+
+        // When: Inserting a row without an enrichment status
+        sqlx::query(
+            "INSERT INTO books (id, title, epub_file_path, created_at, updated_at) VALUES ('id', 'Title', '/path', 0, 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Then: The status should default to done
+        let status: String =
+            sqlx::query_scalar("SELECT enrichment_status FROM books WHERE id = 'id'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(status, "done");
+    }
 }
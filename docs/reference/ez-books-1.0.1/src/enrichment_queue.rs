@@ -0,0 +1,392 @@
+use crate::book_identifier::{enrich_books, update_read_availability, MetadataField};
+use crate::book_model::EnrichmentStatus;
+use crate::book_repository;
+use crate::database_connection::DatabasePool;
+use crate::error::{EzBooksError, Result};
+use crate::openlibrary_client::OpenLibraryClient;
+use tokio::sync::mpsc::{self, error::TrySendError, Receiver, Sender};
+use tracing::{info, info_span, instrument, warn, Instrument, Span};
+
+/// Most books enriched with one OpenLibrary request
+pub const MAX_BATCH_SIZE: usize = 20;
+
+#[derive(Debug)]
+struct EnrichmentJob {
+    book_id: String,
+    /// Span of the request that queued the book, so its request id reaches the worker's logs
+    span: Span,
+}
+
+/// Bounded queue feeding a background task that enriches books with OpenLibrary data
+#[derive(Clone, Debug)]
+pub struct EnrichmentQueue {
+    sender: Sender<EnrichmentJob>,
+}
+
+impl EnrichmentQueue {
+    /// Spawns the background worker and returns a handle for enqueueing books; the worker
+    /// never changes the `locked` fields
+    pub fn start(
+        pool: DatabasePool,
+        client: OpenLibraryClient,
+        capacity: usize,
+        locked: Vec<MetadataField>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        tokio::spawn(run_worker(receiver, pool, client, locked));
+        info!(capacity = capacity, "Enrichment queue started");
+        Self { sender }
+    }
+
+    #[instrument(skip(self))]
+    pub fn enqueue(&self, book_id: &str) -> Result<()> {
+        let job = EnrichmentJob {
+            book_id: book_id.to_string(),
+            span: Span::current(),
+        };
+
+        self.sender.try_send(job).map_err(|e| {
+            let reason = match e {
+                TrySendError::Full(_) => "queue is full",
+                TrySendError::Closed(_) => "worker has stopped",
+            };
+            warn!(book_id = %book_id, reason = reason, "Failed to enqueue enrichment job");
+            EzBooksError::EnrichmentQueue(format!("Failed to enqueue book: {}", reason))
+        })?;
+
+        info!(book_id = %book_id, "Enrichment job enqueued");
+        Ok(())
+    }
+
+    /// Queues the books a previous run left pending, as their jobs were lost with it.
+    /// They are fed in as the worker makes room, so a long backlog never fills the
+    /// queue against new uploads.
+    #[instrument(skip(self, pool))]
+    pub async fn resume_pending(&self, pool: &DatabasePool) -> Result<usize> {
+        let book_ids =
+            book_repository::find_ids_by_enrichment_status(pool, EnrichmentStatus::Pending).await?;
+        let count = book_ids.len();
+        let sender = self.sender.clone();
+        let span = Span::current();
+        tokio::spawn(async move {
+            for book_id in book_ids {
+                let job = EnrichmentJob {
+                    book_id,
+                    span: span.clone(),
+                };
+                if sender.send(job).await.is_err() {
+                    warn!("Enrichment worker stopped before pending books were queued");
+                    break;
+                }
+            }
+        });
+
+        info!(count, "Resuming enrichment of pending books");
+        Ok(count)
+    }
+}
+
+async fn run_worker(
+    mut receiver: Receiver<EnrichmentJob>,
+    pool: DatabasePool,
+    client: OpenLibraryClient,
+    locked: Vec<MetadataField>,
+) {
+    while let Some(job) = receiver.recv().await {
+        // Jobs queued while the last batch ran share one OpenLibrary request
+        let mut jobs = vec![job];
+        while jobs.len() < MAX_BATCH_SIZE {
+            match receiver.try_recv() {
+                Ok(job) => jobs.push(job),
+                Err(_) => break,
+            }
+        }
+
+        let result = process_batch(&pool, &client, &jobs, &locked)
+            .instrument(batch_span(&jobs))
+            .await;
+        if let Err(e) = result {
+            warn!(count = jobs.len(), error = %e, "Enrichment failed");
+            for job in &jobs {
+                mark_failed(&pool, &job.book_id).await;
+            }
+        }
+    }
+
+    info!("Enrichment queue closed, worker stopping");
+}
+
+#[instrument(skip(pool, client, jobs), fields(count = jobs.len()))]
+async fn process_batch(
+    pool: &DatabasePool,
+    client: &OpenLibraryClient,
+    jobs: &[EnrichmentJob],
+    locked: &[MetadataField],
+) -> Result<()> {
+    let mut books = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        match book_repository::find_by_id(pool, &job.book_id).await {
+            Ok(book) => books.push(book),
+            Err(e) => {
+                warn!(book_id = %job.book_id, error = %e, "Failed to load book for enrichment");
+                mark_failed(pool, &job.book_id).await;
+            }
+        }
+    }
+
+    // Edits made while OpenLibrary answers are kept: only what enrichment changed is written
+    let loaded = books.clone();
+    enrich_books(client, &mut books, locked).await?;
+
+    // A book deleted during the lookup fails alone; the rest of the batch is still saved
+    let mut enriched = 0;
+    for (book, loaded) in books.iter_mut().zip(&loaded) {
+        update_read_availability(client, book).await;
+        book.enrichment_status = EnrichmentStatus::Done;
+        match book_repository::update_enrichment(pool, loaded, book).await {
+            Ok(()) => enriched += 1,
+            Err(e) => {
+                warn!(book_id = %book.id, error = %e, "Failed to save enriched book");
+                mark_failed(pool, &book.id).await;
+            }
+        }
+    }
+
+    info!(enriched, "Enrichment batch completed");
+    Ok(())
+}
+
+/// A single job runs inside its request's span; larger batches follow from each request
+fn batch_span(jobs: &[EnrichmentJob]) -> Span {
+    match jobs {
+        [job] => job.span.clone(),
+        _ => {
+            let span = info_span!("enrichment_batch");
+            for job in jobs {
+                span.follows_from(&job.span);
+            }
+            span
+        }
+    }
+}
+
+async fn mark_failed(pool: &DatabasePool, book_id: &str) {
+    if let Err(e) =
+        book_repository::update_enrichment_status(pool, book_id, EnrichmentStatus::Failed).await
+    {
+        warn!(book_id = %book_id, error = %e, "Failed to record enrichment failure");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book_model::Book;
+    use crate::test_fixtures::setup_test_db;
+    use std::time::Duration;
+
+    fn unreachable_client() -> OpenLibraryClient {
+        OpenLibraryClient::with_base_url("http://127.0.0.1:9").unwrap()
+    }
+
+    async fn wait_for_status(pool: &DatabasePool, id: &str) -> EnrichmentStatus {
+        for _ in 0..100 {
+            let book = book_repository::find_by_id(pool, id).await.unwrap();
+            if book.enrichment_status != EnrichmentStatus::Pending {
+                return book.enrichment_status;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        EnrichmentStatus::Pending
+    }
+
+    #[tokio::test]
+    async fn should_mark_book_done_when_nothing_to_enrich() {
+        // Given: A pending book without an ISBN
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = Book::new("Test".to_string(), "/path.epub".to_string());
+        book.enrichment_status = EnrichmentStatus::Pending;
+        book_repository::insert(&pool, &book).await.unwrap();
+        let queue = EnrichmentQueue::start(pool.clone(), unreachable_client(), 4, Vec::new());
+
+        // When: Enqueueing the book
+        queue.enqueue(&book.id).unwrap();
+
+        // Then: The worker should mark it done
+        assert_eq!(
+            wait_for_status(&pool, &book.id).await,
+            EnrichmentStatus::Done
+        );
+    }
+
+    #[tokio::test]
+    async fn should_mark_book_failed_when_lookup_fails() {
+        // Given: A pending book with an ISBN and an unreachable OpenLibrary
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book = Book::new("Test".to_string(), "/path.epub".to_string());
+        book.isbn_13 = Some("9780140328721".to_string());
+        book.enrichment_status = EnrichmentStatus::Pending;
+        book_repository::insert(&pool, &book).await.unwrap();
+        let queue = EnrichmentQueue::start(pool.clone(), unreachable_client(), 4, Vec::new());
+
+        // When: Enqueueing the book
+        queue.enqueue(&book.id).unwrap();
+
+        // Then: The worker should record the failure
+        assert_eq!(
+            wait_for_status(&pool, &book.id).await,
+            EnrichmentStatus::Failed
+        );
+    }
+
+    #[tokio::test]
+    async fn should_resume_books_left_pending_by_a_previous_run() {
+        // Given: More pending books than the queue holds, and an enriched one
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut pending = Vec::new();
+        for title in ["One", "Two", "Three"] {
+            let mut book = Book::new(title.to_string(), "/path.epub".to_string());
+            book.enrichment_status = EnrichmentStatus::Pending;
+            book_repository::insert(&pool, &book).await.unwrap();
+            pending.push(book.id);
+        }
+        let done = Book::new("Done".to_string(), "/done.epub".to_string());
+        book_repository::insert(&pool, &done).await.unwrap();
+        let queue = EnrichmentQueue::start(pool.clone(), unreachable_client(), 1, Vec::new());
+
+        // When: Resuming pending enrichment
+        let resumed = queue.resume_pending(&pool).await.unwrap();
+
+        // Then: Every pending book is enriched
+        assert_eq!(resumed, 3);
+        for id in &pending {
+            assert_eq!(wait_for_status(&pool, id).await, EnrichmentStatus::Done);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_enrich_queued_books_with_one_request() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Given: A local OpenLibrary that knows one of two queued ISBNs and counts Books API
+        // requests, leaving the Read API lookups of each book out
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0; 4096];
+                let read = stream.read(&mut buffer).await.unwrap();
+                let request = &buffer[..read];
+                let body = if request.starts_with(b"GET /api/books?") {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    r#"{"ISBN:9780140328721":{"publishers":[{"name":"Puffin"}]}}"#
+                } else if request.starts_with(b"GET /api/volumes/brief/isbn/9780140328721.json") {
+                    r#"{"items":[{"match":"exact","status":"full access","itemURL":"https://archive.org/details/fox"}]}"#
+                } else {
+                    "[]"
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut ids = Vec::new();
+        for isbn in ["9780140328721", "9780000000002"] {
+            let mut book = Book::new(isbn.to_string(), "/path.epub".to_string());
+            book.isbn_13 = Some(isbn.to_string());
+            book.enrichment_status = EnrichmentStatus::Pending;
+            book_repository::insert(&pool, &book).await.unwrap();
+            ids.push(book.id);
+        }
+        let client = OpenLibraryClient::with_base_url(&base_url).unwrap();
+        let queue = EnrichmentQueue::start(pool.clone(), client, 4, Vec::new());
+
+        // When: Enqueueing both before the worker runs
+        for id in &ids {
+            queue.enqueue(id).unwrap();
+        }
+
+        // Then: Both finish after a single Books API request, and only the known one is merged
+        for id in &ids {
+            assert_eq!(wait_for_status(&pool, id).await, EnrichmentStatus::Done);
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        let known = book_repository::find_by_id(&pool, &ids[0]).await.unwrap();
+        let unknown = book_repository::find_by_id(&pool, &ids[1]).await.unwrap();
+        assert_eq!(known.publisher, Some("Puffin".to_string()));
+        assert_eq!(unknown.publisher, None);
+        assert_eq!(known.read_status.as_deref(), Some("full access"));
+        assert_eq!(
+            known.read_url.as_deref(),
+            Some("https://archive.org/details/fox")
+        );
+        assert_eq!(unknown.read_url, None);
+    }
+
+    #[tokio::test]
+    async fn should_save_rest_of_batch_when_a_book_is_deleted_during_lookup() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Given: Two queued books, and a local OpenLibrary that deletes the first one
+        // before answering the batch's Books API request
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut ids = Vec::new();
+        for isbn in ["9780140328721", "9780000000002"] {
+            let mut book = Book::new(isbn.to_string(), "/path.epub".to_string());
+            book.isbn_13 = Some(isbn.to_string());
+            book.enrichment_status = EnrichmentStatus::Pending;
+            book_repository::insert(&pool, &book).await.unwrap();
+            ids.push(book.id);
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server_pool = pool.clone();
+        let deleted = ids[0].clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0; 4096];
+                let read = stream.read(&mut buffer).await.unwrap();
+                let body = if buffer[..read].starts_with(b"GET /api/books?") {
+                    book_repository::delete(&server_pool, &deleted)
+                        .await
+                        .unwrap();
+                    r#"{"ISBN:9780140328721":{"publishers":[{"name":"Puffin"}]},"ISBN:9780000000002":{"publishers":[{"name":"Penguin"}]}}"#
+                } else {
+                    "[]"
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        let client = OpenLibraryClient::with_base_url(&base_url).unwrap();
+        let queue = EnrichmentQueue::start(pool.clone(), client, 4, Vec::new());
+
+        // When: Enqueueing both before the worker runs
+        for id in &ids {
+            queue.enqueue(id).unwrap();
+        }
+
+        // Then: The remaining book is saved as enriched rather than failed
+        assert_eq!(
+            wait_for_status(&pool, &ids[1]).await,
+            EnrichmentStatus::Done
+        );
+        let kept = book_repository::find_by_id(&pool, &ids[1]).await.unwrap();
+        assert_eq!(kept.publisher, Some("Penguin".to_string()));
+        assert!(!book_repository::exists(&pool, &ids[0]).await.unwrap());
+    }
+}
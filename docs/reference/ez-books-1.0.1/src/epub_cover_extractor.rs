@@ -1,16 +1,121 @@
 use crate::error::{EzBooksError, Result};
 use epub::doc::EpubDoc;
+use image::codecs::jpeg::JpegEncoder;
 use image::imageops::FilterType;
-use image::{GenericImageView, ImageFormat};
-use std::io::Cursor;
-use std::path::Path;
+use image::metadata::Orientation;
+use image::{
+    DynamicImage, GenericImageView, ImageDecoder, ImageFormat, ImageReader, Rgb, RgbImage,
+};
+use regex::Regex;
+use std::io::{Cursor, Read, Seek};
+use std::path::{Component, Path, PathBuf};
 use tracing::{info, instrument, warn};
 
 const COVER_WIDTH: u32 = 300;
 const COVER_HEIGHT: u32 = 450;
+/// Default encoding quality of stored covers, 1-100
+pub const DEFAULT_COVER_JPEG_QUALITY: u8 = 80;
+/// Default limit on either side of a cover image, in pixels
+pub const DEFAULT_MAX_COVER_DIMENSION: u32 = 6000;
+/// Default limit on width times height of a cover image, about 100MB of RGBA pixels
+pub const DEFAULT_MAX_COVER_PIXELS: u64 = 25_000_000;
 
+/// Covers are served as one of these; anything else is treated as JPEG
+const COVER_MIME_TYPES: [&str; 4] = ["image/jpeg", "image/png", "image/webp", "image/gif"];
+const DEFAULT_COVER_MIME: &str = "image/jpeg";
+
+/// Content type of cover bytes judged by their signature, JPEG when unrecognised
+pub fn sniff_cover_mime(data: &[u8]) -> &'static str {
+    let sniffed = image::guess_format(data)
+        .map(|format| format.to_mime_type())
+        .unwrap_or(DEFAULT_COVER_MIME);
+    cover_content_type(Some(sniffed))
+}
+
+/// Content type to serve for a stored `cover_mime`; legacy covers without one are JPEG
+pub fn cover_content_type(cover_mime: Option<&str>) -> &'static str {
+    cover_mime
+        .and_then(|mime| COVER_MIME_TYPES.iter().find(|known| **known == mime))
+        .copied()
+        .unwrap_or(DEFAULT_COVER_MIME)
+}
+
+/// 1x1 transparent PNG served in place of a stored cover that fails to decode
+pub const TRANSPARENT_PIXEL_PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+    0x89, 0x00, 0x00, 0x00, 0x0b, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x60, 0x00, 0x02, 0x00,
+    0x00, 0x05, 0x00, 0x01, 0x7a, 0x5e, 0xab, 0x3f, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+    0xae, 0x42, 0x60, 0x82,
+];
+
+/// Whether stored cover bytes decode completely; slow, since the whole image is decoded
+pub fn is_decodable_cover(data: &[u8]) -> bool {
+    image::load_from_memory(data).is_ok()
+}
+
+/// How a stored cover is brought to the gallery's cover size
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoverFit {
+    /// Scaled to fit inside, keeping its aspect ratio, so one side may come out shorter
+    #[default]
+    Contain,
+    /// Scaled to fit inside, then centered on a background of this RGB color
+    Pad([u8; 3]),
+    /// Scaled to fill the whole size, cropping the overflow evenly from both sides
+    Cover,
+}
+
+impl CoverFit {
+    /// The mode named `contain`, `pad` or `cover`, ignoring case; `pad` uses `pad_color`
+    pub fn from_name(name: &str, pad_color: [u8; 3]) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "contain" => Some(CoverFit::Contain),
+            "pad" => Some(CoverFit::Pad(pad_color)),
+            "cover" => Some(CoverFit::Cover),
+            _ => None,
+        }
+    }
+}
+
+/// RGB of a `#rrggbb` color
+pub fn parse_hex_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.trim().strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Cover ready for storage, with its average color when the image could be decoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractedCover {
+    pub data: Vec<u8>,
+    /// `#rrggbb`
+    pub color: Option<String>,
+}
+
+/// Average color of encoded cover bytes as `#rrggbb`, for covers stored without one
+pub fn dominant_color(data: &[u8]) -> Result<String> {
+    let img = image::load_from_memory(data)
+        .map_err(|e| EzBooksError::ImageProcessing(format!("Failed to load image: {}", e)))?;
+    Ok(average_color(&img))
+}
+
+/// The EPUB's cover, resized as `fit` says and re-encoded, as PNG when
+/// `keep_transparency` is set and it has transparent pixels. Images declaring more than
+/// `max_dimension` pixels on a side or `max_pixels` in total are dropped before their
+/// pixels are decoded.
 #[instrument(skip_all, fields(path = %path.as_ref().display()))]
-pub fn extract_cover(path: impl AsRef<Path>) -> Result<Option<Vec<u8>>> {
+pub fn extract_cover(
+    path: impl AsRef<Path>,
+    jpeg_quality: u8,
+    max_dimension: u32,
+    max_pixels: u64,
+    fit: CoverFit,
+    keep_transparency: bool,
+) -> Result<Option<ExtractedCover>> {
     let path = path.as_ref();
     info!(path = %path.display(), "Extracting cover from EPUB");
 
@@ -21,31 +126,63 @@ pub fn extract_cover(path: impl AsRef<Path>) -> Result<Option<Vec<u8>>> {
 
     // Try to get cover from EPUB
     let cover_data = match doc.get_cover() {
-        Some((data, _mime_type)) => {
+        Some((data, mime)) => {
             info!(size = data.len(), "Cover image found in EPUB");
-            Some(data)
+            Some((data, mime))
         }
         None => {
-            warn!("No cover found in EPUB");
-            None
+            let fallback = find_fallback_cover(&mut doc);
+            if fallback.is_none() {
+                warn!("No cover found in EPUB");
+            }
+            fallback
         }
     };
 
-    if let Some(data) = cover_data {
+    let format = cover_data
+        .as_ref()
+        .and_then(|(data, mime)| cover_format(data, mime));
+    if let Some((width, height)) = cover_data
+        .as_ref()
+        .and_then(|(data, _)| declared_dimensions(data, format))
+        .filter(|(width, height)| {
+            (*width).max(*height) > max_dimension || pixel_count(*width, *height) > max_pixels
+        })
+    {
+        // Storing the original instead would only move the cost to the next decode
+        warn!(
+            width,
+            height, max_dimension, max_pixels, "Cover image too large, not storing it"
+        );
+        return Ok(None);
+    }
+
+    if let Some((data, _)) = cover_data {
         // Process the cover image
-        match process_cover_image(&data) {
-            Ok(processed) => {
+        match process_cover_image(
+            &data,
+            format,
+            jpeg_quality,
+            max_pixels,
+            fit,
+            keep_transparency,
+        ) {
+            Ok((processed, color)) => {
                 info!(
                     original_size = data.len(),
                     processed_size = processed.len(),
+                    color = %color,
                     "Cover processed successfully"
                 );
-                Ok(Some(processed))
+                Ok(Some(ExtractedCover {
+                    data: processed,
+                    color: Some(color),
+                }))
             }
             Err(e) => {
                 warn!(error = %e, "Failed to process cover image, using original");
                 // If processing fails, return the original data
-                Ok(Some(data))
+                Ok(Some(ExtractedCover { data, color: None }))
             }
         }
     } else {
@@ -54,41 +191,281 @@ pub fn extract_cover(path: impl AsRef<Path>) -> Result<Option<Vec<u8>>> {
     }
 }
 
-fn process_cover_image(data: &[u8]) -> Result<Vec<u8>> {
-    // Load the image
+/// For EPUBs without a declared cover: the first image in the first spine item,
+/// otherwise the largest raster image in the manifest, each with its manifest mime type
+fn find_fallback_cover<R: Read + Seek>(doc: &mut EpubDoc<R>) -> Option<(Vec<u8>, String)> {
+    if let Some((data, mime)) = first_spine_image(doc) {
+        info!(size = data.len(), "Using first spine image as cover");
+        return Some((data, mime));
+    }
+
+    let (data, mime) = largest_manifest_image(doc)?;
+    info!(size = data.len(), "Using largest manifest image as cover");
+    Some((data, mime))
+}
+
+fn first_spine_image<R: Read + Seek>(doc: &mut EpubDoc<R>) -> Option<(Vec<u8>, String)> {
+    let chapter_path = doc
+        .spine
+        .first()
+        .and_then(|item| doc.resources.get(&item.idref))
+        .map(|resource| resource.path.clone())?;
+    let html = doc.get_resource_str_by_path(&chapter_path)?;
+    let image_path = resolve_href(&chapter_path, &first_image_href(&html)?)?;
+
+    let mime = doc.get_resource_mime_by_path(&image_path)?;
+    if !is_raster_image(&mime) {
+        return None;
+    }
+    doc.get_resource_by_path(&image_path)
+        .map(|data| (data, mime))
+}
+
+fn largest_manifest_image<R: Read + Seek>(doc: &mut EpubDoc<R>) -> Option<(Vec<u8>, String)> {
+    let images: Vec<(PathBuf, String)> = doc
+        .resources
+        .values()
+        .filter(|resource| is_raster_image(&resource.mime))
+        .map(|resource| (resource.path.clone(), resource.mime.clone()))
+        .collect();
+
+    images
+        .into_iter()
+        .filter_map(|(path, mime)| doc.get_resource_by_path(&path).map(|data| (data, mime)))
+        .max_by_key(|(data, _)| data.len())
+}
+
+/// `src` of the first `<img>` or `href` of the first SVG `<image>` in an XHTML document
+fn first_image_href(html: &str) -> Option<String> {
+    let pattern = Regex::new(
+        r#"(?is)<(?:img|image)\b[^>]*?\b(?:src|xlink:href|href)\s*=\s*["']([^"']+)["']"#,
+    )
+    .ok()?;
+    let href = pattern.captures(html)?.get(1)?.as_str();
+    let href = href.split(['#', '?']).next()?;
+    (!href.is_empty() && !href.contains(':')).then(|| href.to_string())
+}
+
+/// Resolves an href relative to the archive path of the document containing it
+fn resolve_href(document_path: &Path, href: &str) -> Option<PathBuf> {
+    let mut resolved = document_path.parent()?.to_path_buf();
+    for component in Path::new(href).components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(part) => resolved.push(part),
+            _ => {}
+        }
+    }
+    Some(resolved)
+}
+
+/// Images the `image` crate can decode; SVG covers are left to the placeholder
+fn is_raster_image(mime: &str) -> bool {
+    mime.starts_with("image/") && mime != "image/svg+xml"
+}
+
+/// Format of cover bytes by their signature, since EPUBs sometimes mislabel covers; the
+/// declared `mime` only decides for formats without a signature
+fn cover_format(data: &[u8], mime: &str) -> Option<ImageFormat> {
+    let declared = ImageFormat::from_mime_type(mime);
+    match image::guess_format(data) {
+        Ok(sniffed) => {
+            if declared != Some(sniffed) {
+                warn!(
+                    declared = %mime,
+                    actual = sniffed.to_mime_type(),
+                    "Cover mime type does not match its content"
+                );
+            }
+            Some(sniffed)
+        }
+        Err(_) => declared,
+    }
+}
+
+/// Reader for `data` as `format`, or as the format its signature suggests when `None`
+fn image_reader(data: &[u8], format: Option<ImageFormat>) -> Result<ImageReader<Cursor<&[u8]>>> {
+    let cursor = Cursor::new(data);
+    match format {
+        Some(format) => Ok(ImageReader::with_format(cursor, format)),
+        None => Ok(ImageReader::new(cursor).with_guessed_format()?),
+    }
+}
+
+/// Width and height from the image header, without decoding any pixels
+fn declared_dimensions(data: &[u8], format: Option<ImageFormat>) -> Option<(u32, u32)> {
+    image_reader(data, format).ok()?.into_dimensions().ok()
+}
+
+fn pixel_count(width: u32, height: u32) -> u64 {
+    u64::from(width) * u64::from(height)
+}
+
+/// JPEG cover at `jpeg_quality` (1-100), resized as `fit` says, and the average color of
+/// the result. Covers with transparent pixels become PNG instead when `keep_transparency`
+/// is set, since JPEG would fill their transparent parts with black.
+///
+/// EXIF orientation is applied first, so sideways phone photos end up upright. Images
+/// whose header declares more than `max_pixels` fail before any pixel is allocated, since
+/// a few compressed bytes can claim gigabytes of decoded image.
+fn process_cover_image(
+    data: &[u8],
+    format: Option<ImageFormat>,
+    jpeg_quality: u8,
+    max_pixels: u64,
+    fit: CoverFit,
+    keep_transparency: bool,
+) -> Result<(Vec<u8>, String)> {
+    let load_error = |e: image::ImageError| {
+        EzBooksError::ImageProcessing(format!("Failed to load image: {}", e))
+    };
+    if let Some((width, height)) = declared_dimensions(data, format) {
+        if pixel_count(width, height) > max_pixels {
+            return Err(EzBooksError::ImageProcessing(format!(
+                "Image of {}x{} pixels exceeds the limit of {} pixels",
+                width, height, max_pixels
+            )));
+        }
+    }
+    let mut decoder = image_reader(data, format)?
+        .into_decoder()
+        .map_err(load_error)?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder).map_err(load_error)?;
+    img.apply_orientation(orientation);
+
+    let resized = fit_cover(&img, fit);
+    let color = average_color(&resized);
+
+    let encoded = if keep_transparency && has_transparency(&resized) {
+        encode_png(&resized)?
+    } else {
+        encode_jpeg(&resized, jpeg_quality)?
+    };
+    Ok((encoded, color))
+}
+
+fn fit_cover(img: &DynamicImage, fit: CoverFit) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    match fit {
+        CoverFit::Contain => {
+            let (new_width, new_height) = fit_within(width, height, COVER_WIDTH, COVER_HEIGHT);
+            img.resize(new_width, new_height, FilterType::Lanczos3)
+        }
+        CoverFit::Pad(background) => {
+            let (new_width, new_height) = fit_within(width, height, COVER_WIDTH, COVER_HEIGHT);
+            let resized = img
+                .resize_exact(new_width, new_height, FilterType::Lanczos3)
+                .to_rgb8();
+            let mut canvas = RgbImage::from_pixel(COVER_WIDTH, COVER_HEIGHT, Rgb(background));
+            image::imageops::overlay(
+                &mut canvas,
+                &resized,
+                i64::from((COVER_WIDTH - new_width.min(COVER_WIDTH)) / 2),
+                i64::from((COVER_HEIGHT - new_height.min(COVER_HEIGHT)) / 2),
+            );
+            DynamicImage::ImageRgb8(canvas)
+        }
+        CoverFit::Cover => img.resize_to_fill(COVER_WIDTH, COVER_HEIGHT, FilterType::Lanczos3),
+    }
+}
+
+/// A stored cover scaled down to fit `max_width` by `max_height` (`None` leaves a side
+/// unconstrained) and encoded as JPEG, or as PNG for transparent PNG covers. Covers
+/// already small enough come back unchanged.
+pub fn resize_cover(
+    data: &[u8],
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+    jpeg_quality: u8,
+) -> Result<Vec<u8>> {
     let img = image::load_from_memory(data)
         .map_err(|e| EzBooksError::ImageProcessing(format!("Failed to load image: {}", e)))?;
-
-    // Calculate aspect ratio preserving dimensions
     let (width, height) = img.dimensions();
+    let (new_width, new_height) = fit_within(
+        width,
+        height,
+        max_width.unwrap_or(u32::MAX),
+        max_height.unwrap_or(u32::MAX),
+    );
+    if new_width >= width && new_height >= height {
+        return Ok(data.to_vec());
+    }
+
+    let resized = img.resize(new_width, new_height, FilterType::Lanczos3);
+    if image::guess_format(data).ok() == Some(ImageFormat::Png) && has_transparency(&resized) {
+        encode_png(&resized)
+    } else {
+        encode_jpeg(&resized, jpeg_quality)
+    }
+}
+
+/// Largest size with the aspect ratio of `width` by `height` that fits the box
+fn fit_within(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
     let aspect_ratio = width as f32 / height as f32;
-    let target_aspect_ratio = COVER_WIDTH as f32 / COVER_HEIGHT as f32;
+    let target_aspect_ratio = max_width as f32 / max_height as f32;
 
     let (new_width, new_height) = if aspect_ratio > target_aspect_ratio {
         // Image is wider than target, constrain by width
-        (COVER_WIDTH, (COVER_WIDTH as f32 / aspect_ratio) as u32)
+        (max_width, (max_width as f32 / aspect_ratio) as u32)
     } else {
         // Image is taller than target, constrain by height
-        ((COVER_HEIGHT as f32 * aspect_ratio) as u32, COVER_HEIGHT)
+        ((max_height as f32 * aspect_ratio) as u32, max_height)
     };
+    (new_width.max(1), new_height.max(1))
+}
 
-    // Resize the image
-    let resized = img.resize(new_width, new_height, FilterType::Lanczos3);
-
-    // Convert to JPEG
+/// JPEG has no alpha channel, so transparency is dropped
+pub fn encode_jpeg(img: &DynamicImage, jpeg_quality: u8) -> Result<Vec<u8>> {
     let mut output = Vec::new();
-    let mut cursor = Cursor::new(&mut output);
-
-    resized
-        .write_to(&mut cursor, ImageFormat::Jpeg)
+    JpegEncoder::new_with_quality(&mut output, jpeg_quality)
+        .encode_image(&img.to_rgb8())
         .map_err(|e| EzBooksError::ImageProcessing(format!("Failed to encode JPEG: {}", e)))?;
+    Ok(output)
+}
 
+fn encode_png(img: &DynamicImage) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    img.to_rgba8()
+        .write_to(&mut Cursor::new(&mut output), ImageFormat::Png)
+        .map_err(|e| EzBooksError::ImageProcessing(format!("Failed to encode PNG: {}", e)))?;
     Ok(output)
 }
 
+/// Whether any pixel is less than fully opaque; images without an alpha channel never are
+fn has_transparency(img: &DynamicImage) -> bool {
+    img.color().has_alpha() && img.to_rgba8().pixels().any(|pixel| pixel.0[3] < u8::MAX)
+}
+
+/// Mean RGB over all pixels, ignoring transparency
+fn average_color(img: &DynamicImage) -> String {
+    let rgb = img.to_rgb8();
+    let count = u64::from(rgb.width()) * u64::from(rgb.height());
+    if count == 0 {
+        return "#000000".to_string();
+    }
+
+    let mut sums = [0u64; 3];
+    for pixel in rgb.pixels() {
+        for (sum, channel) in sums.iter_mut().zip(pixel.0) {
+            *sum += u64::from(channel);
+        }
+    }
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        sums[0] / count,
+        sums[1] / count,
+        sums[2] / count
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_epub::TestEpub;
+    use image::{ImageEncoder, ImageFormat};
 
     #[test]
     fn should_calculate_resize_dimensions_for_wide_image() {
@@ -139,11 +516,18 @@ mod tests {
             .unwrap();
 
         // When: Processing the image
-        let result = process_cover_image(&png_data);
+        let result = process_cover_image(
+            &png_data,
+            None,
+            DEFAULT_COVER_JPEG_QUALITY,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        );
 
         // Then: Should succeed and return JPEG data
         assert!(result.is_ok());
-        let jpeg_data = result.unwrap();
+        let (jpeg_data, _color) = result.unwrap();
         assert!(!jpeg_data.is_empty());
     }
 
@@ -153,7 +537,14 @@ mod tests {
         let invalid_data = b"Not an image";
 
         // When: Processing the invalid data
-        let result = process_cover_image(invalid_data);
+        let result = process_cover_image(
+            invalid_data,
+            None,
+            DEFAULT_COVER_JPEG_QUALITY,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        );
 
         // Then: Should return error
         assert!(result.is_err());
@@ -173,11 +564,18 @@ mod tests {
             .unwrap();
 
         // When: Processing the image
-        let result = process_cover_image(&png_data);
+        let result = process_cover_image(
+            &png_data,
+            None,
+            DEFAULT_COVER_JPEG_QUALITY,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        );
 
         // Then: Should succeed
         assert!(result.is_ok());
-        let jpeg_data = result.unwrap();
+        let (jpeg_data, _color) = result.unwrap();
 
         // And: Should be able to load the processed image
         let processed_img = image::load_from_memory(&jpeg_data).unwrap();
@@ -189,6 +587,463 @@ mod tests {
         assert!(h <= COVER_HEIGHT);
     }
 
-    // Note: Full integration tests with actual EPUB files will be added
-    // in the tests directory once we have test fixtures
+    #[test]
+    fn should_pad_or_crop_covers_to_exact_size() {
+        // Given: A wide red image
+        let img = image::RgbImage::from_pixel(600, 300, image::Rgb([255, 0, 0]));
+        let mut png_data = Vec::new();
+        img.write_to(&mut Cursor::new(&mut png_data), ImageFormat::Png)
+            .unwrap();
+        let process = |fit| {
+            let (jpeg, _) = process_cover_image(
+                &png_data,
+                None,
+                DEFAULT_COVER_JPEG_QUALITY,
+                DEFAULT_MAX_COVER_PIXELS,
+                fit,
+                false,
+            )
+            .unwrap();
+            image::load_from_memory(&jpeg).unwrap().to_rgb8()
+        };
+
+        // When: Letterboxing on white and cropping it
+        let padded = process(CoverFit::Pad([255, 255, 255]));
+        let cropped = process(CoverFit::Cover);
+
+        // Then: Both fill the cover size; only the padded one has bars above and below
+        assert_eq!(padded.dimensions(), (COVER_WIDTH, COVER_HEIGHT));
+        assert_eq!(cropped.dimensions(), (COVER_WIDTH, COVER_HEIGHT));
+        let is_white = |pixel: &Rgb<u8>| pixel.0.iter().all(|channel| *channel > 240);
+        let is_red = |pixel: &Rgb<u8>| pixel.0[0] > 200 && pixel.0[1] < 40;
+        assert!(is_white(padded.get_pixel(COVER_WIDTH / 2, 10)));
+        assert!(is_red(padded.get_pixel(COVER_WIDTH / 2, COVER_HEIGHT / 2)));
+        assert!(is_red(cropped.get_pixel(COVER_WIDTH / 2, 10)));
+    }
+
+    #[test]
+    fn should_store_transparent_covers_as_png_when_asked() {
+        // Given: An RGBA cover with a transparent half and a fully opaque RGBA one
+        let encode = |img: image::RgbaImage| {
+            let mut data = Vec::new();
+            img.write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+                .unwrap();
+            data
+        };
+        let transparent = encode(image::RgbaImage::from_fn(60, 90, |_, y| {
+            image::Rgba([200, 40, 40, if y < 45 { 0 } else { 255 }])
+        }));
+        let opaque = encode(image::RgbaImage::from_pixel(
+            60,
+            90,
+            image::Rgba([200, 40, 40, 255]),
+        ));
+        let process = |data: &[u8], keep_transparency| {
+            let (cover, _) = process_cover_image(
+                data,
+                None,
+                DEFAULT_COVER_JPEG_QUALITY,
+                DEFAULT_MAX_COVER_PIXELS,
+                CoverFit::Contain,
+                keep_transparency,
+            )
+            .unwrap();
+            sniff_cover_mime(&cover)
+        };
+
+        // When/Then: Only the transparent one becomes PNG, and only with the option on
+        assert_eq!(process(&transparent, true), "image/png");
+        assert_eq!(process(&opaque, true), "image/jpeg");
+        assert_eq!(process(&transparent, false), "image/jpeg");
+        assert_eq!(
+            sniff_cover_mime(&resize_cover(&transparent, Some(30), None, 80).unwrap()),
+            "image/png"
+        );
+    }
+
+    #[test]
+    fn should_parse_cover_fit_names_and_hex_colors() {
+        // Given/When/Then: Known modes and #rrggbb colors parse, anything else does not
+        assert_eq!(
+            CoverFit::from_name("PAD", [1, 2, 3]),
+            Some(CoverFit::Pad([1, 2, 3]))
+        );
+        assert_eq!(
+            CoverFit::from_name("contain", [0; 3]),
+            Some(CoverFit::Contain)
+        );
+        assert_eq!(CoverFit::from_name("fill", [0; 3]), None);
+        assert_eq!(parse_hex_color("#ff8000"), Some([255, 128, 0]));
+        assert_eq!(parse_hex_color("ff8000"), None);
+        assert_eq!(parse_hex_color("#ff80"), None);
+        assert_eq!(parse_hex_color("#gg8000"), None);
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        image::RgbaImage::from_pixel(width, height, image::Rgba([10, 20, 30, 255]))
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    fn write_epub(temp_dir: &tempfile::TempDir, epub: TestEpub) -> PathBuf {
+        let path = temp_dir.path().join("book.epub");
+        std::fs::write(&path, epub.build()).unwrap();
+        path
+    }
+
+    fn cover_dimensions(cover: &[u8]) -> (u32, u32) {
+        image::load_from_memory(cover).unwrap().dimensions()
+    }
+
+    #[test]
+    fn should_use_first_spine_image_when_no_cover_is_declared() {
+        // Given: An EPUB whose first chapter shows a tall image, with a larger wide one elsewhere
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let epub = TestEpub::new("Undeclared")
+            .chapters(&[
+                r#"<div><img alt="" src="../OEBPS/images/front.png#top"/></div>"#,
+                "<p>Chapter two</p>",
+            ])
+            .image("images/front.png", png(100, 200))
+            .image("images/map.png", png(800, 200));
+        let path = write_epub(&temp_dir, epub);
+
+        // When: Extracting the cover
+        let cover = extract_cover(
+            &path,
+            DEFAULT_COVER_JPEG_QUALITY,
+            DEFAULT_MAX_COVER_DIMENSION,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+
+        // Then: The first chapter's image should be used
+        let (width, height) = cover_dimensions(&cover.data);
+        assert!(height > width);
+    }
+
+    #[test]
+    fn should_fall_back_to_largest_manifest_image() {
+        // Given: An EPUB with images that no chapter references first
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let epub = TestEpub::new("Loose images")
+            .image("small.png", png(10, 10))
+            .image("large.png", png(600, 150));
+        let path = write_epub(&temp_dir, epub);
+
+        // When: Extracting the cover
+        let cover = extract_cover(
+            &path,
+            DEFAULT_COVER_JPEG_QUALITY,
+            DEFAULT_MAX_COVER_DIMENSION,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+
+        // Then: The largest image should be used
+        let (width, height) = cover_dimensions(&cover.data);
+        assert!(width > height);
+    }
+
+    #[test]
+    fn should_decode_cover_by_content_when_mime_is_wrong() {
+        // Given: An EPUB whose only image is a PNG declared as JPEG
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let epub = TestEpub::new("Mislabelled").resource("cover.jpg", "image/jpeg", png(40, 80));
+        let path = write_epub(&temp_dir, epub);
+
+        // When: Extracting the cover
+        let cover = extract_cover(
+            &path,
+            DEFAULT_COVER_JPEG_QUALITY,
+            DEFAULT_MAX_COVER_DIMENSION,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+
+        // Then: The bytes decide the format, and the declared mime only helps without a signature
+        let (width, height) = cover_dimensions(&cover.data);
+        assert_eq!(height, width * 2);
+        assert_eq!(
+            cover_format(&png(4, 4), "image/jpeg"),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(
+            cover_format(b"no signature", "image/png"),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(cover_format(b"no signature", "image/unknown"), None);
+    }
+
+    #[test]
+    fn should_return_none_without_any_images() {
+        // Given: An EPUB with text only
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = write_epub(&temp_dir, TestEpub::new("Text only"));
+
+        // When/Then: No cover should be found
+        assert!(extract_cover(
+            &path,
+            DEFAULT_COVER_JPEG_QUALITY,
+            DEFAULT_MAX_COVER_DIMENSION,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        )
+        .unwrap()
+        .is_none());
+    }
+
+    #[test]
+    fn should_resolve_image_hrefs_relative_to_document() {
+        // Given/When/Then: Relative references resolve against the chapter's folder
+        assert_eq!(
+            resolve_href(Path::new("OEBPS/text/ch1.xhtml"), "../images/a.jpg"),
+            Some(PathBuf::from("OEBPS/images/a.jpg"))
+        );
+        assert_eq!(
+            first_image_href(r#"<svg><image width="1" xlink:href="cover.jpg"/></svg>"#),
+            Some("cover.jpg".to_string())
+        );
+        assert_eq!(
+            first_image_href(r#"<img src="data:image/png;base64,AA"/>"#),
+            None
+        );
+    }
+
+    #[test]
+    fn should_compute_average_color_while_processing_cover() {
+        // Given: An EPUB whose only image is a solid color
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let epub = TestEpub::new("Colored").image("cover.png", png(60, 90));
+        let path = write_epub(&temp_dir, epub);
+
+        // When: Extracting the cover
+        let cover = extract_cover(
+            &path,
+            DEFAULT_COVER_JPEG_QUALITY,
+            DEFAULT_MAX_COVER_DIMENSION,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+
+        // Then: The color matches the image, as does the color of the stored bytes
+        assert_eq!(cover.color.as_deref(), Some("#0a141e"));
+        assert_eq!(dominant_color(&png(2, 2)).unwrap(), "#0a141e");
+        assert!(dominant_color(b"not an image").is_err());
+    }
+
+    #[test]
+    fn should_not_store_cover_larger_than_max_dimension() {
+        // Given: An EPUB whose cover is wider than the limit
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let epub = TestEpub::new("Poster").image("cover.png", png(120, 40));
+        let path = write_epub(&temp_dir, epub);
+
+        // When: Extracting with limits below and above its width
+        let rejected = extract_cover(
+            &path,
+            DEFAULT_COVER_JPEG_QUALITY,
+            100,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        )
+        .unwrap();
+        let accepted = extract_cover(
+            &path,
+            DEFAULT_COVER_JPEG_QUALITY,
+            120,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        )
+        .unwrap();
+
+        // Then: The oversized cover is dropped rather than stored as is
+        assert!(rejected.is_none());
+        assert!(accepted.is_some());
+    }
+
+    /// A valid 8x8 JPEG whose frame header claims `width` x `height`
+    fn jpeg_declaring(width: u16, height: u16) -> Vec<u8> {
+        let mut jpeg = Vec::new();
+        JpegEncoder::new(&mut jpeg)
+            .encode_image(&image::RgbImage::from_pixel(8, 8, image::Rgb([9, 9, 9])))
+            .unwrap();
+        let sof = jpeg
+            .windows(2)
+            .position(|marker| marker == [0xFF, 0xC0])
+            .unwrap();
+        jpeg[sof + 5..sof + 7].copy_from_slice(&height.to_be_bytes());
+        jpeg[sof + 7..sof + 9].copy_from_slice(&width.to_be_bytes());
+        jpeg
+    }
+
+    #[test]
+    fn should_refuse_images_declaring_huge_dimensions_before_decoding() {
+        // Given: A few hundred bytes claiming to be a 60000x60000 image, in and out of an EPUB
+        let bomb = jpeg_declaring(60_000, 60_000);
+        assert_eq!(declared_dimensions(&bomb, None), Some((60_000, 60_000)));
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = write_epub(
+            &temp_dir,
+            TestEpub::new("Bomb").image("cover.jpg", bomb.clone()),
+        );
+
+        // When: Processing it directly, and extracting it with no limit on either side
+        let processed = process_cover_image(
+            &bomb,
+            None,
+            DEFAULT_COVER_JPEG_QUALITY,
+            1_000_000,
+            CoverFit::Contain,
+            false,
+        );
+        let extracted = extract_cover(
+            &path,
+            DEFAULT_COVER_JPEG_QUALITY,
+            u32::MAX,
+            1_000_000,
+            CoverFit::Contain,
+            false,
+        )
+        .unwrap();
+
+        // Then: Both stop at the header instead of allocating the pixels
+        assert!(matches!(processed, Err(EzBooksError::ImageProcessing(_))));
+        assert!(extracted.is_none());
+    }
+
+    #[test]
+    fn should_apply_exif_orientation() {
+        // Given: A landscape JPEG tagged to be rotated 90 degrees clockwise
+        let exif = vec![
+            b'M', b'M', 0, 42, 0, 0, 0, 8, // big-endian TIFF header, IFD at offset 8
+            0, 1, // one entry
+            0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0, // Orientation (SHORT) = 6
+            0, 0, 0, 0, // no next IFD
+        ];
+        let mut jpeg = Vec::new();
+        let mut encoder = JpegEncoder::new(&mut jpeg);
+        encoder.set_exif_metadata(exif).unwrap();
+        encoder
+            .encode_image(&image::RgbImage::from_pixel(
+                200,
+                100,
+                image::Rgb([9, 9, 9]),
+            ))
+            .unwrap();
+
+        // When: Processing the photo
+        let (cover, _color) = process_cover_image(
+            &jpeg,
+            None,
+            DEFAULT_COVER_JPEG_QUALITY,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        )
+        .unwrap();
+
+        // Then: The stored cover is upright, i.e. portrait
+        let (width, height) = cover_dimensions(&cover);
+        assert!(height > width);
+    }
+
+    #[test]
+    fn should_sniff_cover_mime_from_signature() {
+        // Given/When/Then: Known signatures are recognised, anything else is JPEG
+        assert_eq!(sniff_cover_mime(b"\x89PNG\r\n\x1a\nrest"), "image/png");
+        assert_eq!(sniff_cover_mime(b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+        assert_eq!(sniff_cover_mime(&[0xFF, 0xD8, 0xFF, 0xE0]), "image/jpeg");
+        assert_eq!(sniff_cover_mime(b"not an image"), "image/jpeg");
+    }
+
+    #[test]
+    fn should_only_serve_known_cover_types() {
+        assert_eq!(cover_content_type(Some("image/png")), "image/png");
+        assert_eq!(cover_content_type(Some("text/html")), "image/jpeg");
+        assert_eq!(cover_content_type(None), "image/jpeg");
+    }
+
+    #[test]
+    fn should_detect_undecodable_covers() {
+        // Given/When/Then: A complete image and the placeholder decode, truncated bytes don't
+        let complete = png(4, 4);
+        assert!(is_decodable_cover(&complete));
+        assert!(is_decodable_cover(TRANSPARENT_PIXEL_PNG));
+        assert!(!is_decodable_cover(&complete[..complete.len() / 2]));
+    }
+
+    #[test]
+    fn should_shrink_covers_at_lower_jpeg_quality() {
+        // Given: A detailed image
+        let img = image::RgbImage::from_fn(300, 450, |x, y| {
+            image::Rgb([
+                (x * 7 % 256) as u8,
+                (y * 13 % 256) as u8,
+                ((x ^ y) % 256) as u8,
+            ])
+        });
+        let mut png_data = Vec::new();
+        img.write_to(&mut Cursor::new(&mut png_data), ImageFormat::Png)
+            .unwrap();
+
+        // When: Encoding it at low and high quality
+        let (low, _) = process_cover_image(
+            &png_data,
+            None,
+            30,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        )
+        .unwrap();
+        let (high, _) = process_cover_image(
+            &png_data,
+            None,
+            95,
+            DEFAULT_MAX_COVER_PIXELS,
+            CoverFit::Contain,
+            false,
+        )
+        .unwrap();
+
+        // Then: The lower quality cover is smaller
+        assert!(low.len() < high.len());
+    }
+
+    #[test]
+    fn should_resize_cover_to_fit_requested_box_without_enlarging() {
+        // Given: A 300x450 stored cover
+        let cover = png(300, 450);
+
+        // When: Asking for one width, one height, a box, and a size larger than the cover
+        let by_width = resize_cover(&cover, Some(100), None, DEFAULT_COVER_JPEG_QUALITY).unwrap();
+        let by_height = resize_cover(&cover, None, Some(150), DEFAULT_COVER_JPEG_QUALITY).unwrap();
+        let boxed = resize_cover(&cover, Some(200), Some(200), DEFAULT_COVER_JPEG_QUALITY).unwrap();
+        let larger = resize_cover(&cover, Some(450), None, DEFAULT_COVER_JPEG_QUALITY).unwrap();
+
+        // Then: The aspect ratio is kept, and small covers are served unchanged
+        assert_eq!(cover_dimensions(&by_width), (100, 150));
+        assert_eq!(cover_dimensions(&by_height), (100, 150));
+        assert_eq!(cover_dimensions(&boxed), (133, 200));
+        assert_eq!(image::guess_format(&by_width).unwrap(), ImageFormat::Jpeg);
+        assert_eq!(larger, cover);
+    }
 }
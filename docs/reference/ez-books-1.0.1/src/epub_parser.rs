@@ -1,32 +1,109 @@
+use crate::chapter_encoding::current_chapter_str;
 use crate::error::{EzBooksError, Result};
-use epub::doc::EpubDoc;
+use crate::language_detection::detect_language;
+use crate::metadata_completeness::{has_real_title, has_text};
+use crate::opf_salvage::salvage_metadata;
+use crate::text_extraction::strip_tags;
+use epub::doc::{EpubDoc, EpubVersion};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read};
 use std::path::Path;
 use tracing::{info, instrument, warn};
+use zip::ZipArchive;
+
+/// Enough text for a confident language guess without reading the whole book
+const LANGUAGE_SAMPLE_CHARS: usize = 2000;
+/// Spine items to look through for sample text (front matter is often nearly empty)
+const LANGUAGE_SAMPLE_MAX_CHAPTERS: usize = 5;
+
+/// `META-INF/encryption.xml` algorithms that only obfuscate embedded fonts; the text
+/// stays readable, so these are not DRM
+const FONT_OBFUSCATION_ALGORITHMS: [&str; 2] = [
+    "http://www.idpf.org/2008/embedding",
+    "http://ns.adobe.com/pdf/enc#RC",
+];
+
+/// Shortest paragraph taken as a description when the EPUB declares none
+const CONTENT_DESCRIPTION_MIN_CHARS: usize = 120;
+/// Longest description taken from the text; longer paragraphs are cut at a word
+const CONTENT_DESCRIPTION_MAX_CHARS: usize = 500;
+
+/// Extensions of book files whose names EPUB tools are known to copy into the title
+const FILENAME_TITLE_EXTENSIONS: [&str; 8] =
+    ["epub", "kepub", "mobi", "azw", "azw3", "pdf", "txt", "zip"];
+
+/// Manifest media type of EPUB3 media overlay documents
+const SMIL_MIME: &str = "application/smil+xml";
+
+/// A media overlay document or audio file listed in an EPUB's manifest
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MediaOverlayResource {
+    /// Path inside the EPUB archive
+    pub path: String,
+    pub media_type: String,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct EpubMetadata {
     pub title: String,
+    /// The declared title, when `tidy_filename_title` replaced it
+    pub raw_title: Option<String>,
     pub author: Option<String>,
     pub isbn_10: Option<String>,
     pub isbn_13: Option<String>,
     pub publisher: Option<String>,
     pub language: Option<String>,
+    /// Whether `language` was guessed from the text rather than declared in the EPUB
+    pub language_detected: bool,
     pub description: Option<String>,
+    /// Whether `description` is the opening paragraph of the text rather than declared
+    pub description_from_content: bool,
     pub subjects: Vec<String>,
+    /// Whether the manifest declares SMIL media overlays
+    pub has_audio_narration: bool,
+    /// `version` of the OPF package, e.g. "2.0" or "3.0"
+    pub epub_version: Option<String>,
+}
+
+impl EpubMetadata {
+    /// Whether the EPUB alone documents the book well enough to need no manual work
+    pub fn is_complete(&self) -> bool {
+        has_real_title(&self.title)
+            && has_text(&self.author)
+            && (has_text(&self.isbn_13) || has_text(&self.isbn_10))
+            && has_text(&self.description)
+            && !self.description_from_content
+    }
+
+    /// Turns a title that is really a file name, such as `my_book.epub`, into one fit for
+    /// the gallery: the extension is dropped and, with `humanize`, underscores and dashes
+    /// become spaces. The title declared is kept in `raw_title`.
+    pub fn tidy_filename_title(&mut self, source_filename: Option<&str>, humanize: bool) {
+        if let Some(title) = filename_title(&self.title, source_filename, humanize) {
+            info!(raw_title = %self.title, title = %title, "Tidied title that was a file name");
+            self.raw_title = Some(std::mem::replace(&mut self.title, title));
+        }
+    }
 }
 
 impl Default for EpubMetadata {
     fn default() -> Self {
         Self {
             title: "Unknown".to_string(),
+            raw_title: None,
             author: None,
             isbn_10: None,
             isbn_13: None,
             publisher: None,
             language: None,
+            language_detected: false,
             description: None,
+            description_from_content: false,
             subjects: Vec::new(),
+            has_audio_narration: false,
+            epub_version: None,
         }
     }
 }
@@ -36,12 +113,20 @@ pub fn parse_epub(path: impl AsRef<Path>) -> Result<EpubMetadata> {
     let path = path.as_ref();
     info!(path = %path.display(), "Parsing EPUB file");
 
-    let doc = EpubDoc::new(path).map_err(|e| {
+    if is_drm_protected(path)? {
+        warn!(path = %path.display(), "Rejecting DRM-protected EPUB");
+        return Err(EzBooksError::DrmProtected);
+    }
+
+    let mut doc = EpubDoc::new(path).map_err(|e| {
         warn!(path = %path.display(), error = %e, "Failed to open EPUB file");
         EzBooksError::EpubParse(format!("Failed to open EPUB: {}", e))
     })?;
 
-    let mut metadata = EpubMetadata::default();
+    let mut metadata = EpubMetadata {
+        epub_version: package_version(&doc.version),
+        ..EpubMetadata::default()
+    };
 
     // Extract title
     if let Some(title) = doc.mdata("title") {
@@ -58,14 +143,35 @@ pub fn parse_epub(path: impl AsRef<Path>) -> Result<EpubMetadata> {
         metadata.publisher = Some(publisher.value.clone());
     }
 
-    // Extract language
-    if let Some(language) = doc.mdata("language") {
-        metadata.language = Some(language.value.clone());
+    // Extract language, guessing it from the text when undeclared
+    match doc
+        .mdata("language")
+        .map(|language| language.value.trim().to_string())
+    {
+        Some(language) if !language.is_empty() => metadata.language = Some(language),
+        _ => {
+            metadata.language = detect_language(&sample_text(&mut doc)).map(str::to_string);
+            metadata.language_detected = metadata.language.is_some();
+            info!(language = ?metadata.language, "No language declared, detected from text");
+        }
     }
 
-    // Extract description
-    if let Some(description) = doc.mdata("description") {
-        metadata.description = Some(description.value.clone());
+    // Extract description, falling back to the opening paragraph of the text
+    match doc
+        .mdata("description")
+        .map(|description| description.value.clone())
+    {
+        Some(description) if !description.trim().is_empty() => {
+            metadata.description = Some(description)
+        }
+        _ => {
+            metadata.description = opening_paragraph(&mut doc);
+            metadata.description_from_content = metadata.description.is_some();
+            info!(
+                found = metadata.description_from_content,
+                "No description declared, looked for one in the text"
+            );
+        }
     }
 
     // Extract subjects from metadata
@@ -76,10 +182,29 @@ pub fn parse_epub(path: impl AsRef<Path>) -> Result<EpubMetadata> {
     }
 
     // Extract ISBN from identifiers
-    extract_isbns(&doc, &mut metadata);
+    let identifiers: Vec<String> = doc
+        .metadata
+        .iter()
+        .filter(|item| item.property == "identifier")
+        .map(|item| item.value.clone())
+        .collect();
+    extract_isbns(&identifiers, &mut metadata);
+
+    if !has_real_title(&metadata.title)
+        || !has_text(&metadata.author)
+        || (metadata.isbn_13.is_none() && metadata.isbn_10.is_none())
+    {
+        fill_gaps_from_package_document(path, &mut metadata);
+    }
+
+    metadata.has_audio_narration = doc
+        .resources
+        .values()
+        .any(|resource| resource.mime == SMIL_MIME);
 
     info!(
         title = %metadata.title,
+        epub_version = ?metadata.epub_version,
         has_author = metadata.author.is_some(),
         has_isbn = metadata.isbn_13.is_some() || metadata.isbn_10.is_some(),
         "EPUB metadata extracted successfully"
@@ -88,16 +213,196 @@ pub fn parse_epub(path: impl AsRef<Path>) -> Result<EpubMetadata> {
     Ok(metadata)
 }
 
-fn extract_isbns(doc: &EpubDoc<std::io::BufReader<std::fs::File>>, metadata: &mut EpubMetadata) {
-    // Get all identifiers from metadata
-    let identifiers: Vec<String> = doc
-        .metadata
-        .iter()
-        .filter(|item| item.property == "identifier")
-        .map(|item| item.value.clone())
+/// The readable form of `title` if it is a file name: one ending in a book extension, or
+/// the name the file was uploaded or imported with, with or without its extension
+fn filename_title(title: &str, source_filename: Option<&str>, humanize: bool) -> Option<String> {
+    let title = title.trim();
+    let source = source_filename.map(str::trim).unwrap_or_default();
+    let stem = match title.rsplit_once('.') {
+        Some((stem, extension))
+            if title.eq_ignore_ascii_case(source)
+                || FILENAME_TITLE_EXTENSIONS
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(extension)) =>
+        {
+            stem
+        }
+        _ if source.rsplit_once('.').map_or(false, |(source_stem, _)| {
+            title.eq_ignore_ascii_case(source_stem)
+        }) =>
+        {
+            title
+        }
+        _ => return None,
+    };
+
+    let tidied = if humanize {
+        stem.split(|c: char| c == '_' || c == '-' || c.is_whitespace())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    } else {
+        stem.trim().to_string()
+    };
+    (!tidied.is_empty() && tidied != title).then_some(tidied)
+}
+
+/// SMIL overlays and audio files in the manifest, sorted by path; empty for books without narration
+#[instrument(skip_all, fields(path = %path.as_ref().display()))]
+pub fn media_overlay_resources(path: impl AsRef<Path>) -> Result<Vec<MediaOverlayResource>> {
+    let path = path.as_ref();
+    let doc = EpubDoc::new(path).map_err(|e| {
+        warn!(path = %path.display(), error = %e, "Failed to open EPUB file");
+        EzBooksError::EpubParse(format!("Failed to open EPUB: {}", e))
+    })?;
+
+    let mut resources: Vec<MediaOverlayResource> = doc
+        .resources
+        .values()
+        .filter(|resource| resource.mime == SMIL_MIME || resource.mime.starts_with("audio/"))
+        .map(|resource| MediaOverlayResource {
+            path: resource.path.to_string_lossy().to_string(),
+            media_type: resource.mime.clone(),
+        })
+        .collect();
+    resources.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(resources)
+}
+
+/// Whether the EPUB declares encrypted content other than obfuscated fonts
+fn is_drm_protected(path: &Path) -> Result<bool> {
+    let file = File::open(path)?;
+    let mut archive = ZipArchive::new(BufReader::new(file))
+        .map_err(|e| EzBooksError::EpubParse(format!("Failed to open EPUB: {}", e)))?;
+
+    let mut encryption = String::new();
+    match archive.by_name("META-INF/encryption.xml") {
+        Ok(mut entry) => {
+            entry.read_to_string(&mut encryption)?;
+        }
+        Err(_) => return Ok(false),
+    }
+
+    let algorithm =
+        Regex::new(r#"(?i)<(?:\w+:)?EncryptionMethod\b[^>]*\bAlgorithm\s*=\s*["']([^"']+)["']"#)
+            .map_err(|e| EzBooksError::EpubParse(e.to_string()))?;
+    let algorithms: Vec<&str> = algorithm
+        .captures_iter(&encryption)
+        .filter_map(|captures| captures.get(1))
+        .map(|algorithm| algorithm.as_str().trim())
         .collect();
 
-    for identifier in &identifiers {
+    // An encryption manifest naming no algorithm is treated as DRM, to be safe
+    let only_fonts = !algorithms.is_empty()
+        && algorithms
+            .iter()
+            .all(|algorithm| FONT_OBFUSCATION_ALGORITHMS.contains(algorithm));
+    Ok(!only_fonts)
+}
+
+/// Plain text from the first chapters, for language detection
+fn sample_text(doc: &mut EpubDoc<BufReader<File>>) -> String {
+    let mut sample = String::new();
+
+    for index in 0..doc.spine.len().min(LANGUAGE_SAMPLE_MAX_CHAPTERS) {
+        doc.set_current_chapter(index);
+        if let Some(content) = current_chapter_str(doc) {
+            sample.push_str(&strip_tags(&content));
+            sample.push_str("\n\n");
+        }
+        if sample.chars().count() >= LANGUAGE_SAMPLE_CHARS {
+            break;
+        }
+    }
+
+    sample.chars().take(LANGUAGE_SAMPLE_CHARS).collect()
+}
+
+/// First paragraph of the first chapters long enough to read as a blurb, shortened to
+/// `CONTENT_DESCRIPTION_MAX_CHARS` at a word boundary
+fn opening_paragraph(doc: &mut EpubDoc<BufReader<File>>) -> Option<String> {
+    for index in 0..doc.spine.len().min(LANGUAGE_SAMPLE_MAX_CHAPTERS) {
+        doc.set_current_chapter(index);
+        let Some(content) = current_chapter_str(doc) else {
+            continue;
+        };
+        let text = strip_tags(&content);
+        let paragraph = text
+            .split("\n\n")
+            .map(|paragraph| paragraph.split_whitespace().collect::<Vec<_>>().join(" "))
+            .find(|paragraph| is_blurb(paragraph));
+        if let Some(paragraph) = paragraph {
+            return Some(shorten(&paragraph, CONTENT_DESCRIPTION_MAX_CHARS));
+        }
+    }
+    None
+}
+
+/// Prose rather than a title, a dedication or the copyright notice
+fn is_blurb(paragraph: &str) -> bool {
+    let lowercase = paragraph.to_lowercase();
+    paragraph.chars().count() >= CONTENT_DESCRIPTION_MIN_CHARS
+        && !lowercase.contains("copyright")
+        && !lowercase.contains("all rights reserved")
+        && !paragraph.contains('\u{a9}')
+}
+
+fn shorten(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    let cut = match cut.rfind(' ') {
+        Some(end) => &cut[..end],
+        None => cut.as_str(),
+    };
+    format!(
+        "{}\u{2026}",
+        cut.trim_end_matches(|c: char| c.is_ascii_punctuation())
+    )
+}
+
+/// Fills fields the `epub` crate missed from a lenient read of the OPF; fields it did
+/// read are never replaced
+/// The version the package declares, or `None` when it declares none
+fn package_version(version: &EpubVersion) -> Option<String> {
+    match version {
+        EpubVersion::Version2_0 => Some("2.0".to_string()),
+        EpubVersion::Version3_0 => Some("3.0".to_string()),
+        // The epub crate reports a missing attribute as "Unknown"
+        EpubVersion::Unknown(version) if version == "Unknown" || version.trim().is_empty() => None,
+        EpubVersion::Unknown(version) => Some(version.trim().to_string()),
+    }
+}
+
+fn fill_gaps_from_package_document(path: &Path, metadata: &mut EpubMetadata) {
+    let salvaged = match salvage_metadata(path) {
+        Ok(salvaged) => salvaged,
+        Err(e) => {
+            warn!(error = %e, "Failed to salvage metadata from package document");
+            return;
+        }
+    };
+
+    if !has_real_title(&metadata.title) {
+        if let Some(title) = salvaged.title {
+            info!(title = %title, "Title salvaged from package document");
+            metadata.title = title;
+        }
+    }
+    if !has_text(&metadata.author) {
+        if let Some(creator) = salvaged.creator {
+            info!(author = %creator, "Author salvaged from package document");
+            metadata.author = Some(creator);
+        }
+    }
+    if metadata.isbn_13.is_none() && metadata.isbn_10.is_none() {
+        extract_isbns(&salvaged.identifiers, metadata);
+    }
+}
+
+fn extract_isbns(identifiers: &[String], metadata: &mut EpubMetadata) {
+    for identifier in identifiers {
         // Clean the identifier (remove hyphens, spaces, etc.)
         let cleaned = identifier.replace(['-', ' '], "");
 
@@ -190,6 +495,298 @@ mod tests {
         assert_eq!(isbn_part.len(), 13);
     }
 
-    // Note: Full integration tests with actual EPUB files will be added
-    // in the tests/epub_parser_test.rs file once we have test fixtures
+    #[test]
+    fn should_tidy_titles_that_are_file_names() {
+        // Given: A title copied from the file name, and real titles with dots
+        let tidy = |title: &str, source: Option<&str>, humanize: bool| {
+            let mut metadata = EpubMetadata {
+                title: title.to_string(),
+                ..EpubMetadata::default()
+            };
+            metadata.tidy_filename_title(source, humanize);
+            (metadata.title, metadata.raw_title)
+        };
+
+        // When/Then: Extensions are dropped, and separators become spaces when humanizing
+        assert_eq!(
+            tidy("my_book.epub", None, true),
+            ("my book".to_string(), Some("my_book.epub".to_string()))
+        );
+        assert_eq!(tidy("my_book.EPUB", None, false).0, "my_book");
+        assert_eq!(
+            tidy("the-long_walk", Some("the-long_walk.epub"), true).0,
+            "the long walk"
+        );
+        assert_eq!(tidy("Dune.v2", Some("Dune.v2"), false).0, "Dune");
+        assert_eq!(
+            tidy("Node.js in Action", None, true),
+            ("Node.js in Action".to_string(), None)
+        );
+        assert_eq!(tidy("Mr. Fox", Some("fox.epub"), true).1, None);
+        assert_eq!(tidy("my_book", None, true).1, None);
+    }
+
+    #[test]
+    fn should_parse_metadata_from_epub_file() {
+        // Given: A generated EPUB with title, author and ISBN
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        let epub = crate::test_epub::TestEpub::new("Fixture Title")
+            .author("Fixture Author")
+            .isbn("978-0-306-40615-7")
+            .build();
+        std::fs::write(&path, epub).unwrap();
+
+        // When: Parsing the EPUB
+        let metadata = parse_epub(&path).unwrap();
+
+        // Then: Should read the metadata and clean the ISBN
+        assert_eq!(metadata.title, "Fixture Title");
+        assert_eq!(metadata.author, Some("Fixture Author".to_string()));
+        assert_eq!(metadata.isbn_13, Some("9780306406157".to_string()));
+        assert_eq!(metadata.language, Some("en".to_string()));
+        assert!(!metadata.language_detected);
+        assert_eq!(metadata.epub_version, Some("2.0".to_string()));
+    }
+
+    #[test]
+    fn should_detect_epub3_package_version() {
+        // Given: An EPUB whose package declares version 3.0
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("v3.epub");
+        let epub = crate::test_epub::TestEpub::new("Third Edition")
+            .package_version("3.0")
+            .build();
+        std::fs::write(&path, epub).unwrap();
+
+        // When: Parsing the EPUB
+        let metadata = parse_epub(&path).unwrap();
+
+        // Then: The version is read from the OPF, and unknown versions are left out
+        assert_eq!(metadata.epub_version, Some("3.0".to_string()));
+        assert_eq!(
+            package_version(&EpubVersion::Unknown("3.1".to_string())),
+            Some("3.1".to_string())
+        );
+        assert_eq!(
+            package_version(&EpubVersion::Unknown("Unknown".to_string())),
+            None
+        );
+    }
+
+    #[test]
+    fn should_salvage_metadata_from_malformed_package_document() {
+        // Given: An OEB-style OPF that nests capitalised DC elements in <dc-metadata>
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("awkward.epub");
+        let epub = crate::test_epub::TestEpub::new("unused")
+            .raw_package_document(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0" unique-identifier="bookid">
+  <metadata>
+    <dc-metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+      <dc:Title>The Awkward Book</dc:Title>
+      <dc:Creator>Olde Press</dc:Creator>
+      <dc:Identifier id="bookid">ISBN 978-0-306-40615-7</dc:Identifier>
+      <dc:Language>en</dc:Language>
+    </dc-metadata>
+  </metadata>
+  <manifest><item id="chapter1" href="chapter1.xhtml" media-type="application/xhtml+xml"/></manifest>
+  <spine><itemref idref="chapter1"/></spine>
+</package>"#,
+            )
+            .build();
+        std::fs::write(&path, epub).unwrap();
+
+        // When: Parsing the EPUB
+        let metadata = parse_epub(&path).unwrap();
+
+        // Then: The core fields come from the lenient fallback
+        assert_eq!(metadata.title, "The Awkward Book");
+        assert_eq!(metadata.author, Some("Olde Press".to_string()));
+        assert_eq!(metadata.isbn_13, Some("9780306406157".to_string()));
+    }
+
+    #[test]
+    fn should_not_override_fields_read_by_the_parser() {
+        // Given: A well-formed EPUB with a title and author but no ISBN
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        let epub = crate::test_epub::TestEpub::new("Proper Title")
+            .author("Proper Author")
+            .build();
+        std::fs::write(&path, epub).unwrap();
+
+        // When: Parsing the EPUB, which triggers the fallback for the missing ISBN
+        let metadata = parse_epub(&path).unwrap();
+
+        // Then: The parsed values are kept and the UUID identifier is no ISBN
+        assert_eq!(metadata.title, "Proper Title");
+        assert_eq!(metadata.author, Some("Proper Author".to_string()));
+        assert!(metadata.isbn_13.is_none());
+        assert!(metadata.isbn_10.is_none());
+    }
+
+    #[test]
+    fn should_detect_language_when_not_declared() {
+        // Given: An EPUB without dc:language whose text is French
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        let epub = crate::test_epub::TestEpub::new("Sans langue")
+            .without_language()
+            .chapters(&[
+                "<p>Cover</p>",
+                "<p>Longtemps, je me suis couché de bonne heure. Parfois, à peine ma bougie éteinte, mes yeux se fermaient si vite que je n'avais pas le temps de me dire : je m'endors.</p>",
+            ])
+            .build();
+        std::fs::write(&path, epub).unwrap();
+
+        // When: Parsing the EPUB
+        let metadata = parse_epub(&path).unwrap();
+
+        // Then: The language should be guessed and flagged as detected
+        assert_eq!(metadata.language, Some("fr".to_string()));
+        assert!(metadata.language_detected);
+    }
+
+    #[test]
+    fn should_take_description_from_opening_paragraph_when_not_declared() {
+        // Given: An EPUB without dc:description, a copyright page and a long first paragraph
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        let blurb = format!(
+            "<p>It was a dark and <i>stormy</i> night &amp; {}</p>",
+            "rain fell. ".repeat(60)
+        );
+        let epub = crate::test_epub::TestEpub::new("Blurb")
+            .chapters(&[
+                "<h1>Blurb</h1><p>Copyright 2001 by the author. All rights reserved. No part of this book may be reproduced in any form without permission.</p>",
+                &blurb,
+            ])
+            .build();
+        std::fs::write(&path, epub).unwrap();
+
+        // When: Parsing the EPUB
+        let metadata = parse_epub(&path).unwrap();
+
+        // Then: The paragraph becomes a plain-text description, cut at a word and flagged
+        let description = metadata.description.clone().unwrap();
+        assert!(description.starts_with("It was a dark and stormy night & rain fell. rain"));
+        assert!(description.ends_with(" rain\u{2026}") || description.ends_with(" fell\u{2026}"));
+        assert!(description.chars().count() <= CONTENT_DESCRIPTION_MAX_CHARS + 1);
+        assert!(metadata.description_from_content);
+        assert!(!metadata.is_complete());
+    }
+
+    #[test]
+    fn should_leave_language_unset_when_detection_is_inconclusive() {
+        // Given: An EPUB without dc:language and almost no text
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        let epub = crate::test_epub::TestEpub::new("Blank")
+            .without_language()
+            .chapters(&["<p>42</p>"])
+            .build();
+        std::fs::write(&path, epub).unwrap();
+
+        // When: Parsing the EPUB
+        let metadata = parse_epub(&path).unwrap();
+
+        // Then: The import should still succeed without a language
+        assert_eq!(metadata.language, None);
+        assert!(!metadata.language_detected);
+    }
+
+    #[test]
+    fn should_only_call_metadata_complete_with_author_isbn_and_description() {
+        // Given: Metadata missing a description
+        let mut metadata = EpubMetadata {
+            title: "Complete?".to_string(),
+            author: Some("Someone".to_string()),
+            isbn_13: Some("9780306406157".to_string()),
+            ..EpubMetadata::default()
+        };
+
+        // When/Then: It is incomplete until the description is added
+        assert!(!metadata.is_complete());
+        metadata.description = Some("About it.".to_string());
+        assert!(metadata.is_complete());
+        assert!(!EpubMetadata::default().is_complete());
+    }
+
+    #[test]
+    fn should_reject_epub_with_encrypted_content() {
+        // Given: An EPUB whose chapter is encrypted with AES
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("drm.epub");
+        let epub = crate::test_epub::TestEpub::new("Locked")
+            .encryption(
+                "http://www.w3.org/2001/04/xmlenc#aes128-cbc",
+                "OEBPS/chapter1.xhtml",
+            )
+            .build();
+        std::fs::write(&path, epub).unwrap();
+
+        // When: Parsing the EPUB
+        let result = parse_epub(&path);
+
+        // Then: Should fail as DRM-protected instead of storing an unreadable book
+        assert!(matches!(result, Err(EzBooksError::DrmProtected)));
+    }
+
+    #[test]
+    fn should_accept_epub_with_only_obfuscated_fonts() {
+        // Given: An EPUB whose encryption.xml only covers an obfuscated font
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("fonts.epub");
+        let epub = crate::test_epub::TestEpub::new("Fonts")
+            .encryption(
+                "http://www.idpf.org/2008/embedding",
+                "OEBPS/fonts/serif.otf",
+            )
+            .build();
+        std::fs::write(&path, epub).unwrap();
+
+        // When/Then: Parsing should succeed
+        assert_eq!(parse_epub(&path).unwrap().title, "Fonts");
+    }
+
+    #[test]
+    fn should_detect_and_list_media_overlays() {
+        // Given: An EPUB with a SMIL overlay and its audio, and a plain one
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let narrated = temp_dir.path().join("narrated.epub");
+        let plain = temp_dir.path().join("plain.epub");
+        let epub = crate::test_epub::TestEpub::new("Narrated")
+            .resource("audio/chapter1.mp3", "audio/mpeg", b"ID3".to_vec())
+            .resource(
+                "overlays/chapter1.smil",
+                "application/smil+xml",
+                b"<smil/>".to_vec(),
+            )
+            .build();
+        std::fs::write(&narrated, epub).unwrap();
+        std::fs::write(&plain, crate::test_epub::TestEpub::new("Plain").build()).unwrap();
+
+        // When: Parsing both and listing their overlay resources
+        let resources = media_overlay_resources(&narrated).unwrap();
+
+        // Then: Only the narrated book is flagged and its resources are listed by path
+        assert!(parse_epub(&narrated).unwrap().has_audio_narration);
+        assert!(!parse_epub(&plain).unwrap().has_audio_narration);
+        assert_eq!(
+            resources,
+            vec![
+                MediaOverlayResource {
+                    path: "OEBPS/audio/chapter1.mp3".to_string(),
+                    media_type: "audio/mpeg".to_string(),
+                },
+                MediaOverlayResource {
+                    path: "OEBPS/overlays/chapter1.smil".to_string(),
+                    media_type: "application/smil+xml".to_string(),
+                },
+            ]
+        );
+        assert!(media_overlay_resources(&plain).unwrap().is_empty());
+    }
 }
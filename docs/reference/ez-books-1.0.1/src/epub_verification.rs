@@ -0,0 +1,142 @@
+use crate::book_model::Book;
+use crate::book_repository;
+use crate::database_connection::DatabasePool;
+use crate::error::Result;
+use epub::doc::EpubDoc;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::path::Path;
+use tracing::{info, instrument, warn};
+
+/// EPUBs opened at the same time by `verify_epubs`, so a large library does not thrash the disk
+pub const VERIFY_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EpubStatus {
+    Ok,
+    /// The stored file does not exist
+    Missing,
+    /// The file exists but `EpubDoc` cannot open it
+    Unreadable,
+}
+
+/// Outcome of opening one book's stored EPUB
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EpubVerification {
+    pub id: String,
+    pub title: String,
+    pub status: EpubStatus,
+}
+
+/// Opens every stored EPUB, at most `concurrency` at once, and reports each book in
+/// gallery order. Read-only: unlike `check_integrity` nothing is ever repaired.
+#[instrument(skip(pool))]
+pub async fn verify_epubs(
+    pool: &DatabasePool,
+    concurrency: usize,
+) -> Result<Vec<EpubVerification>> {
+    let books = book_repository::find_all(pool).await?;
+    info!(count = books.len(), "Verifying stored EPUBs");
+
+    let results: Vec<EpubVerification> = stream::iter(books)
+        .map(verify_book)
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+
+    info!(
+        missing = results
+            .iter()
+            .filter(|result| result.status == EpubStatus::Missing)
+            .count(),
+        unreadable = results
+            .iter()
+            .filter(|result| result.status == EpubStatus::Unreadable)
+            .count(),
+        "EPUB verification completed"
+    );
+    Ok(results)
+}
+
+async fn verify_book(book: Book) -> EpubVerification {
+    let path = book.epub_file_path.clone();
+    let status = tokio::task::spawn_blocking(move || epub_status(Path::new(&path)))
+        .await
+        .unwrap_or_else(|e| {
+            warn!(book_id = %book.id, error = %e, "EPUB verification task failed");
+            EpubStatus::Unreadable
+        });
+
+    EpubVerification {
+        id: book.id,
+        title: book.title,
+        status,
+    }
+}
+
+fn epub_status(path: &Path) -> EpubStatus {
+    if !path.is_file() {
+        return EpubStatus::Missing;
+    }
+    match EpubDoc::new(path) {
+        Ok(_) => EpubStatus::Ok,
+        Err(e) => {
+            warn!(path = %path.display(), error = %e, "Stored EPUB cannot be opened");
+            EpubStatus::Unreadable
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_storage::FileStorage;
+    use crate::test_epub::TestEpub;
+    use crate::test_fixtures::setup_test_db;
+
+    #[tokio::test]
+    async fn should_report_missing_and_unreadable_epubs_without_changes() {
+        // Given: A readable book, a truncated one and one whose file is gone
+        let (pool, temp_dir) = setup_test_db().await;
+        let storage = FileStorage::new(temp_dir.path().join("data")).unwrap();
+
+        let mut healthy = Book::new("Healthy".to_string(), String::new());
+        healthy.created_at = 3;
+        healthy.epub_file_path = storage
+            .save_epub(&healthy.id, &TestEpub::new("Healthy").build())
+            .unwrap();
+        let mut truncated = Book::new("Truncated".to_string(), String::new());
+        truncated.created_at = 2;
+        let epub = TestEpub::new("Truncated").build();
+        truncated.epub_file_path = storage
+            .save_epub(&truncated.id, &epub[..epub.len() / 2])
+            .unwrap();
+        let mut lost = Book::new("Lost".to_string(), "/nowhere/lost.epub".to_string());
+        lost.created_at = 1;
+        for book in [&healthy, &truncated, &lost] {
+            book_repository::insert(&pool, book).await.unwrap();
+        }
+
+        // When: Verifying the library one file at a time
+        let results = verify_epubs(&pool, 1).await.unwrap();
+
+        // Then: Each book is reported with its status and the files are left alone
+        let statuses: Vec<(&str, EpubStatus)> = results
+            .iter()
+            .map(|result| (result.title.as_str(), result.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("Healthy", EpubStatus::Ok),
+                ("Truncated", EpubStatus::Unreadable),
+                ("Lost", EpubStatus::Missing),
+            ]
+        );
+        assert_eq!(
+            storage.read_epub(&truncated.id).unwrap().len(),
+            epub.len() / 2
+        );
+    }
+}
@@ -1,13 +1,26 @@
+use std::collections::BTreeMap;
 use thiserror::Error;
 
+/// Field name -> what is wrong with its value
+pub type FieldErrors = BTreeMap<String, String>;
+
 #[derive(Error, Debug)]
 pub enum EzBooksError {
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
+    #[error("Database migration error: {0}")]
+    Migration(#[from] sqlx::migrate::MigrateError),
+
     #[error("EPUB parsing error: {0}")]
     EpubParse(String),
 
+    #[error("EPUB is DRM-protected and cannot be read; only DRM-free books can be imported")]
+    DrmProtected,
+
+    #[error("EPUB has no usable cover image; this library only imports books with covers")]
+    MissingCover,
+
     #[error("OpenLibrary API error: {0}")]
     OpenLibraryApi(String),
 
@@ -17,8 +30,47 @@ pub enum EzBooksError {
     #[error("Book not found: {0}")]
     BookNotFound(String),
 
-    #[error("Invalid file format")]
-    InvalidFormat,
+    #[error("Cover not found for book {0}")]
+    CoverNotFound(String),
+
+    #[error("Chapter {chapter} not found in book {book_id}")]
+    ChapterNotFound { book_id: String, chapter: usize },
+
+    #[error("Book {book_id} has no subject {subject}")]
+    SubjectNotFound { book_id: String, subject: String },
+
+    #[error("Book {book_id} already has subject {subject}")]
+    DuplicateSubject { book_id: String, subject: String },
+
+    #[error("Book {book_id} has no bookmark {bookmark_id}")]
+    BookmarkNotFound { book_id: String, bookmark_id: i64 },
+
+    #[error("Book {book_id} has no annotation {annotation_id}")]
+    AnnotationNotFound { book_id: String, annotation_id: i64 },
+
+    #[error("Collection not found: {0}")]
+    CollectionNotFound(i64),
+
+    #[error("Collection {collection_id} does not contain book {book_id}")]
+    CollectionBookNotFound { collection_id: i64, book_id: String },
+
+    #[error("A collection named {0} already exists")]
+    DuplicateCollection(String),
+
+    #[error("Invalid file format: {0}")]
+    InvalidFormat(String),
+
+    #[error("Invalid pagination: {0}")]
+    InvalidPagination(String),
+
+    #[error("Invalid date range: {0}")]
+    InvalidDateRange(String),
+
+    #[error("Invalid cover size: {0}")]
+    InvalidCoverSize(String),
+
+    #[error("Invalid montage grid: {0}")]
+    InvalidMontageGrid(String),
 
     #[error("File storage error: {0}")]
     FileStorage(String),
@@ -31,6 +83,47 @@ pub enum EzBooksError {
 
     #[error("JSON serialization error: {0}")]
     JsonSerialization(#[from] serde_json::Error),
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("Enrichment queue error: {0}")]
+    EnrichmentQueue(String),
+
+    #[error("A book with ISBN {isbn} already exists: {existing_id}")]
+    DuplicateIsbn { isbn: String, existing_id: String },
+
+    #[error("Missing or invalid API token")]
+    Unauthorized,
+
+    #[error("An upload with this Idempotency-Key is still in progress")]
+    IdempotencyKeyInUse,
+
+    #[error("Cover regeneration is already running")]
+    CoverRegenerationRunning,
+
+    #[error("Book {0} has changed since the If-Unmodified-Since date")]
+    BookModified(String),
+
+    #[error("Content search is disabled; set CONTENT_SEARCH=true to enable it")]
+    ContentSearchDisabled,
+
+    #[error("Upload not received within {0} seconds")]
+    UploadTimeout(u64),
+
+    #[error("Too many requests; retry in {0} seconds")]
+    RateLimited(u64),
+
+    #[error("Invalid fields: {}", field_names(.0))]
+    Validation(FieldErrors),
+}
+
+fn field_names(errors: &FieldErrors) -> String {
+    errors
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 pub type Result<T> = std::result::Result<T, EzBooksError>;
@@ -0,0 +1,396 @@
+use crate::error::EzBooksError;
+use crate::error_renderer::render_error_page;
+use crate::upload_handler::MAX_UPLOAD_BYTES;
+use std::convert::Infallible;
+use tracing::warn;
+use warp::http::header::{HeaderMap, ACCEPT, CONTENT_TYPE, RETRY_AFTER};
+use warp::http::{HeaderValue, StatusCode};
+use warp::path::FullPath;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// Attached to recovered responses so they can be re-rendered for the requesting client
+#[derive(Debug, Clone)]
+struct ErrorDetails {
+    status: StatusCode,
+    message: String,
+}
+
+/// Turns rejections into JSON error responses, or HTML error pages for browser requests.
+/// `base_path` is the prefix routes are served under (see `Config::base_path`).
+pub fn with_error_recovery<F, R>(
+    routes: F,
+    base_path: String,
+) -> impl Filter<Extract = (Response,), Error = Infallible> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    warp::path::full()
+        .and(warp::header::headers_cloned())
+        .and(
+            routes
+                .map(|reply: R| reply.into_response())
+                .recover(handle_rejection)
+                .unify(),
+        )
+        .map(
+            move |path: FullPath, headers: HeaderMap, response: Response| {
+                render_for_client(&base_path, path, headers, response)
+            },
+        )
+}
+
+async fn handle_rejection(rejection: Rejection) -> Result<Response, Infallible> {
+    let (status, message) = classify_rejection(&rejection);
+
+    if let Some(EzBooksError::Validation(errors)) = rejection.find::<EzBooksError>() {
+        return Ok(json_error_response_with_body(
+            status,
+            message.clone(),
+            serde_json::json!({ "error": message, "errors": errors }),
+        ));
+    }
+
+    if let Some(EzBooksError::RateLimited(retry_after)) = rejection.find::<EzBooksError>() {
+        let mut response = json_error_response(status, message);
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(*retry_after));
+        return Ok(response);
+    }
+
+    if status.is_server_error() {
+        warn!(status = %status, rejection = ?rejection, "Request failed");
+    }
+
+    Ok(json_error_response(status, message))
+}
+
+fn classify_rejection(rejection: &Rejection) -> (StatusCode, String) {
+    if rejection.is_not_found() {
+        return (StatusCode::NOT_FOUND, "not found".to_string());
+    }
+
+    // A bad query string means a route matched the path, so it outranks errors from
+    // overlapping routes tried afterwards (e.g. `/api/books/{id}` for `/api/books/next`)
+    if rejection.find::<warp::reject::InvalidQuery>().is_some() {
+        return (StatusCode::BAD_REQUEST, "invalid query string".to_string());
+    }
+
+    if let Some(error) = rejection.find::<EzBooksError>() {
+        return classify_error(error);
+    }
+
+    if rejection.find::<warp::reject::PayloadTooLarge>().is_some() {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "payload too large".to_string(),
+        );
+    }
+
+    if rejection.find::<warp::reject::MethodNotAllowed>().is_some() {
+        return (
+            StatusCode::METHOD_NOT_ALLOWED,
+            "method not allowed".to_string(),
+        );
+    }
+
+    if rejection
+        .find::<warp::reject::UnsupportedMediaType>()
+        .is_some()
+    {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported media type".to_string(),
+        );
+    }
+
+    if rejection.find::<warp::reject::MissingHeader>().is_some()
+        || rejection.find::<warp::reject::InvalidHeader>().is_some()
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            "invalid request headers".to_string(),
+        );
+    }
+
+    if rejection
+        .find::<warp::filters::body::BodyDeserializeError>()
+        .is_some()
+    {
+        return (StatusCode::BAD_REQUEST, "invalid request body".to_string());
+    }
+
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "internal server error".to_string(),
+    )
+}
+
+/// Maps domain errors to HTTP statuses; internal details are never exposed to clients
+fn classify_error(error: &EzBooksError) -> (StatusCode, String) {
+    match error {
+        EzBooksError::BookNotFound(_)
+        | EzBooksError::CoverNotFound(_)
+        | EzBooksError::ChapterNotFound { .. }
+        | EzBooksError::SubjectNotFound { .. }
+        | EzBooksError::BookmarkNotFound { .. }
+        | EzBooksError::AnnotationNotFound { .. }
+        | EzBooksError::CollectionNotFound(_)
+        | EzBooksError::CollectionBookNotFound { .. }
+        | EzBooksError::ContentSearchDisabled => (StatusCode::NOT_FOUND, error.to_string()),
+        EzBooksError::InvalidFormat(_)
+        | EzBooksError::InvalidPagination(_)
+        | EzBooksError::InvalidDateRange(_)
+        | EzBooksError::InvalidCoverSize(_)
+        | EzBooksError::InvalidMontageGrid(_) => (StatusCode::BAD_REQUEST, error.to_string()),
+        EzBooksError::EpubParse(_) | EzBooksError::DrmProtected | EzBooksError::MissingCover => {
+            (StatusCode::UNPROCESSABLE_ENTITY, error.to_string())
+        }
+        EzBooksError::DuplicateIsbn { .. }
+        | EzBooksError::DuplicateSubject { .. }
+        | EzBooksError::DuplicateCollection(_)
+        | EzBooksError::IdempotencyKeyInUse
+        | EzBooksError::CoverRegenerationRunning => (StatusCode::CONFLICT, error.to_string()),
+        EzBooksError::Unauthorized => (StatusCode::UNAUTHORIZED, error.to_string()),
+        EzBooksError::UploadTimeout(_) => (StatusCode::REQUEST_TIMEOUT, error.to_string()),
+        EzBooksError::RateLimited(_) => (StatusCode::TOO_MANY_REQUESTS, error.to_string()),
+        EzBooksError::BookModified(_) => (StatusCode::PRECONDITION_FAILED, error.to_string()),
+        EzBooksError::Validation(_) => (StatusCode::UNPROCESSABLE_ENTITY, error.to_string()),
+        EzBooksError::EnrichmentQueue(_) | EzBooksError::Database(sqlx::Error::PoolTimedOut) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "service temporarily unavailable".to_string(),
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal server error".to_string(),
+        ),
+    }
+}
+
+fn json_error_response(status: StatusCode, message: String) -> Response {
+    let body = serde_json::json!({ "error": message });
+    json_error_response_with_body(status, message, body)
+}
+
+fn json_error_response_with_body(
+    status: StatusCode,
+    message: String,
+    body: serde_json::Value,
+) -> Response {
+    let mut response = warp::reply::with_status(warp::reply::json(&body), status).into_response();
+    response
+        .extensions_mut()
+        .insert(ErrorDetails { status, message });
+    response
+}
+
+fn render_for_client(
+    base_path: &str,
+    path: FullPath,
+    headers: HeaderMap,
+    response: Response,
+) -> Response {
+    let details = match response.extensions().get::<ErrorDetails>() {
+        Some(details) => details.clone(),
+        None => return response,
+    };
+
+    let app_path = path
+        .as_str()
+        .strip_prefix(base_path)
+        .unwrap_or(path.as_str());
+    if !wants_html(app_path, &headers) {
+        return response;
+    }
+
+    // API paths never get here, so a browser's oversized body can only be an upload
+    let message = if details.status == StatusCode::PAYLOAD_TOO_LARGE {
+        upload_too_large_message(MAX_UPLOAD_BYTES)
+    } else {
+        capitalize(&details.message)
+    };
+    let html = render_error_page(details.status, &message, base_path);
+    let mut html_response =
+        warp::reply::with_status(warp::reply::html(html), details.status).into_response();
+    html_response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+    if let Some(retry_after) = response.headers().get(RETRY_AFTER) {
+        html_response
+            .headers_mut()
+            .insert(RETRY_AFTER, retry_after.clone());
+    }
+    html_response
+}
+
+/// API paths always get JSON; other paths get HTML unless the client asked for something else
+fn wants_html(path: &str, headers: &HeaderMap) -> bool {
+    if path == "/api" || path.starts_with("/api/") {
+        return false;
+    }
+
+    match headers.get(ACCEPT).and_then(|value| value.to_str().ok()) {
+        Some(accept) => accept.contains("text/html"),
+        None => true,
+    }
+}
+
+fn upload_too_large_message(max_bytes: u64) -> String {
+    format!(
+        "The file is too large to upload. EPUBs can be at most {} MB; try a copy with smaller images.",
+        max_bytes / (1024 * 1024)
+    )
+}
+
+fn capitalize(message: &str) -> String {
+    let mut chars = message.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers_with_accept(accept: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_str(accept).unwrap());
+        headers
+    }
+
+    #[test]
+    fn should_map_book_not_found_to_404() {
+        // Given/When: Classifying a missing book
+        let (status, _) = classify_error(&EzBooksError::BookNotFound("id".to_string()));
+
+        // Then: Should be 404
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn should_map_pool_exhaustion_to_503() {
+        // Given/When: Classifying a timed-out connection acquire
+        let (status, message) = classify_error(&EzBooksError::Database(sqlx::Error::PoolTimedOut));
+
+        // Then: Should be a retryable 503 without internal details
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(message, "service temporarily unavailable");
+    }
+
+    #[test]
+    fn should_map_duplicate_isbn_to_409_with_existing_id() {
+        // Given/When: Classifying a duplicate ISBN
+        let (status, message) = classify_error(&EzBooksError::DuplicateIsbn {
+            isbn: "9780306406157".to_string(),
+            existing_id: "book-1".to_string(),
+        });
+
+        // Then: Should be 409 and name the existing book
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(message.contains("book-1"));
+    }
+
+    #[test]
+    fn should_map_drm_protected_to_422_with_clear_message() {
+        // Given/When: Classifying a DRM-protected EPUB
+        let (status, message) = classify_error(&EzBooksError::DrmProtected);
+
+        // Then: Should be 422 and say why
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(message.contains("DRM"));
+    }
+
+    #[test]
+    fn should_map_upload_timeout_to_408() {
+        // Given/When: Classifying an upload timeout
+        let (status, _) = classify_error(&EzBooksError::UploadTimeout(30));
+
+        // Then: Should be 408
+        assert_eq!(status, StatusCode::REQUEST_TIMEOUT);
+    }
+
+    #[test]
+    fn should_hide_internal_error_details() {
+        // Given/When: Classifying a storage error
+        let (status, message) =
+            classify_error(&EzBooksError::FileStorage("/secret/path".to_string()));
+
+        // Then: Should be a generic 500
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!message.contains("/secret/path"));
+    }
+
+    #[test]
+    fn should_prefer_json_for_api_paths() {
+        // Given/When/Then: API paths never get HTML, even from browsers
+        assert!(!wants_html(
+            "/api/nonsense",
+            &headers_with_accept("text/html")
+        ));
+        assert!(!wants_html("/api", &HeaderMap::new()));
+    }
+
+    #[test]
+    fn should_prefer_html_for_browser_paths() {
+        // Given/When/Then: Non-API paths get HTML for browsers or unspecified Accept
+        assert!(wants_html("/nonsense", &HeaderMap::new()));
+        assert!(wants_html(
+            "/nonsense",
+            &headers_with_accept("text/html,application/xhtml+xml")
+        ));
+        assert!(!wants_html("/upload", &headers_with_accept("*/*")));
+        assert!(!wants_html(
+            "/upload",
+            &headers_with_accept("application/json")
+        ));
+    }
+
+    #[test]
+    fn should_explain_upload_limit_to_browsers_only() {
+        // Given: An oversized upload rejected with 413
+        let too_large = || {
+            json_error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "payload too large".to_string(),
+            )
+        };
+        let full_path = || {
+            futures::executor::block_on(
+                warp::test::request()
+                    .path("/upload")
+                    .filter(&warp::path::full()),
+            )
+            .unwrap()
+        };
+
+        // When: Rendering it for a browser and for a script
+        let browser = render_for_client(
+            "",
+            full_path(),
+            headers_with_accept("text/html"),
+            too_large(),
+        );
+        let script = render_for_client("", full_path(), headers_with_accept("*/*"), too_large());
+
+        // Then: The browser gets a page naming the limit and linking back, the script JSON
+        assert_eq!(browser.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let page =
+            futures::executor::block_on(warp::hyper::body::to_bytes(browser.into_body())).unwrap();
+        let page = String::from_utf8_lossy(&page);
+        assert!(page.contains("413 Payload Too Large"));
+        assert!(page.contains("at most 50 MB"));
+        assert!(page.contains(r#"<a href="/">&larr; Back to Library</a>"#));
+        assert_eq!(script.headers()[CONTENT_TYPE], "application/json");
+    }
+
+    #[test]
+    fn should_capitalize_messages() {
+        assert_eq!(capitalize("not found"), "Not found");
+        assert_eq!(capitalize(""), "");
+    }
+}
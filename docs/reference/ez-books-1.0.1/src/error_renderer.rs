@@ -0,0 +1,86 @@
+use crate::html_templates::{escape_html, html_footer, html_header};
+use crate::ui_text::Lang;
+use warp::http::StatusCode;
+
+pub fn render_error_page(status: StatusCode, message: &str, base_path: &str) -> String {
+    let heading = format!(
+        "{} {}",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("Error")
+    );
+
+    // Rejections carry no request headers, so error pages stay in English
+    let mut html = html_header(
+        &format!("{} - EZ-Books", heading),
+        "gallery.css",
+        Lang::En,
+        base_path,
+    );
+
+    html.push_str(
+        r#"<header>
+    <h1>EZ-Books Library</h1>
+</header>"#,
+    );
+    html.push_str(&format!(
+        r#"<main><div class="empty-state">
+    <h2>{}</h2>
+    <p>{}</p>
+    <p><a href="{}/">&larr; Back to Library</a></p>
+</div></main>"#,
+        escape_html(&heading),
+        escape_html(message),
+        base_path
+    ));
+    html.push_str(&html_footer(None, base_path));
+
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_render_error_page_with_status_and_message() {
+        // Given/When: Rendering a not found page
+        let html = render_error_page(StatusCode::NOT_FOUND, "The page does not exist", "");
+
+        // Then: Should reuse the page shell and show the status
+        assert!(html.contains("<!DOCTYPE html>"));
+        assert!(html.contains("gallery.css"));
+        assert!(html.contains("<h2>404 Not Found</h2>"));
+        assert!(html.contains("The page does not exist"));
+        assert!(html.contains(r#"<a href="/">&larr; Back to Library</a>"#));
+        assert!(html.contains("</html>"));
+    }
+
+    #[test]
+    fn should_escape_error_message() {
+        // Given/When: Rendering a message with HTML characters
+        let html = render_error_page(StatusCode::BAD_REQUEST, "<script>alert(1)</script>", "");
+
+        // Then: Should escape it
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script>alert"));
+    }
+
+    #[test]
+    fn should_not_include_javascript() {
+        // Given/When: Rendering an error page
+        let html = render_error_page(StatusCode::INTERNAL_SERVER_ERROR, "Oops", "");
+
+        // Then: Should not include any script tags
+        assert!(!html.contains("<script"));
+    }
+
+    #[test]
+    fn should_link_back_under_base_path() {
+        // Given/When: Rendering an error page for an app served under a subpath
+        let html = render_error_page(StatusCode::NOT_FOUND, "Missing", "/ezbooks");
+
+        // Then: The library link and stylesheet carry the prefix
+        assert!(html.contains(r#"<a href="/ezbooks/">&larr; Back to Library</a>"#));
+        assert!(html.contains("/ezbooks/static/css/gallery.css"));
+    }
+}
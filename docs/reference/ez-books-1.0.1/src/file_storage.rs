@@ -1,11 +1,21 @@
 use crate::error::{EzBooksError, Result};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 use tracing::{info, instrument, warn};
 
+/// Size and validator of a stored file, known without reading it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredFile {
+    pub len: u64,
+    /// Quoted ETag derived from size and modification time
+    pub etag: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct FileStorage {
     base_path: PathBuf,
+    sharded: bool,
 }
 
 impl FileStorage {
@@ -25,13 +35,25 @@ impl FileStorage {
         })?;
 
         info!(path = %base_path.display(), "File storage initialized");
-        Ok(Self { base_path })
+        Ok(Self {
+            base_path,
+            sharded: false,
+        })
     }
 
+    /// Stores files under a subfolder named after the first two characters of the book id
+    pub fn with_sharding(mut self, sharded: bool) -> Self {
+        self.sharded = sharded;
+        self
+    }
+
+    /// Writes an EPUB held in memory; uploads go through `save_epub_file` instead
+    #[cfg(test)]
     #[instrument(skip(self, data))]
     pub fn save_epub(&self, book_id: &str, data: &[u8]) -> Result<String> {
         let file_path = self.epub_path(book_id);
         info!(book_id = %book_id, path = %file_path.display(), "Saving EPUB file");
+        ensure_parent_dir(&file_path)?;
 
         fs::write(&file_path, data).map_err(|e| {
             warn!(book_id = %book_id, error = %e, "Failed to save EPUB file");
@@ -42,6 +64,22 @@ impl FileStorage {
         Ok(file_path.to_string_lossy().to_string())
     }
 
+    /// Copies an EPUB received to a file, so it is never held in memory whole
+    #[instrument(skip(self))]
+    pub fn save_epub_file(&self, book_id: &str, source: &Path) -> Result<String> {
+        let file_path = self.epub_path(book_id);
+        info!(book_id = %book_id, path = %file_path.display(), "Saving EPUB file");
+        ensure_parent_dir(&file_path)?;
+
+        let size = fs::copy(source, &file_path).map_err(|e| {
+            warn!(book_id = %book_id, error = %e, "Failed to save EPUB file");
+            EzBooksError::FileStorage(format!("Failed to save EPUB file: {}", e))
+        })?;
+
+        info!(book_id = %book_id, size, "EPUB file saved successfully");
+        Ok(file_path.to_string_lossy().to_string())
+    }
+
     #[instrument(skip(self))]
     pub fn read_epub(&self, book_id: &str) -> Result<Vec<u8>> {
         let file_path = self.epub_path(book_id);
@@ -60,6 +98,8 @@ impl FileStorage {
     pub fn save_cover(&self, book_id: &str, data: &[u8]) -> Result<String> {
         let file_path = self.cover_path(book_id);
         info!(book_id = %book_id, path = %file_path.display(), "Saving cover image");
+        ensure_parent_dir(&file_path)?;
+        self.delete_cover_variants(book_id)?;
 
         fs::write(&file_path, data).map_err(|e| {
             warn!(book_id = %book_id, error = %e, "Failed to save cover image");
@@ -106,6 +146,7 @@ impl FileStorage {
     pub fn delete_cover(&self, book_id: &str) -> Result<()> {
         let file_path = self.cover_path(book_id);
         info!(book_id = %book_id, path = %file_path.display(), "Deleting cover image");
+        self.delete_cover_variants(book_id)?;
 
         if file_path.exists() {
             fs::remove_file(&file_path).map_err(|e| {
@@ -120,17 +161,287 @@ impl FileStorage {
         Ok(())
     }
 
-    fn epub_path(&self, book_id: &str) -> PathBuf {
-        self.base_path
-            .join("books")
-            .join(format!("{}.epub", book_id))
+    /// Moves the stored EPUB and cover aside, so `restore_backup` can put them back
+    /// without them being held in memory
+    #[instrument(skip(self))]
+    pub fn back_up_files(&self, book_id: &str) -> Result<()> {
+        for path in [self.epub_path(book_id), self.cover_path(book_id)] {
+            if path.exists() {
+                fs::rename(&path, backup_path(&path)).map_err(|e| {
+                    warn!(book_id = %book_id, error = %e, "Failed to back up file");
+                    EzBooksError::FileStorage(format!(
+                        "Failed to back up {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts back the files moved aside by `back_up_files`, dropping those saved since.
+    /// Best effort: the change they were moved aside for has already failed.
+    #[instrument(skip(self))]
+    pub fn restore_backup(&self, book_id: &str) {
+        if let Err(e) = self.delete_cover_variants(book_id) {
+            warn!(book_id = %book_id, error = %e, "Failed to drop cover variants");
+        }
+        for path in [self.epub_path(book_id), self.cover_path(book_id)] {
+            let backup = backup_path(&path);
+            let restored = if backup.exists() {
+                fs::rename(&backup, &path)
+            } else if path.exists() {
+                fs::remove_file(&path)
+            } else {
+                Ok(())
+            };
+            if let Err(e) = restored {
+                warn!(book_id = %book_id, path = %path.display(), error = %e, "Failed to restore file");
+            }
+        }
+    }
+
+    /// Deletes the files moved aside by `back_up_files` once the change is kept
+    #[instrument(skip(self))]
+    pub fn discard_backup(&self, book_id: &str) -> Result<()> {
+        for path in [self.epub_path(book_id), self.cover_path(book_id)] {
+            let backup = backup_path(&path);
+            if backup.exists() {
+                fs::remove_file(&backup).map_err(|e| {
+                    EzBooksError::FileStorage(format!(
+                        "Failed to delete {}: {}",
+                        backup.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A resized copy of the cover saved by `save_cover_variant`, or `None` if there is none
+    pub fn read_cover_variant(&self, book_id: &str, variant: &str) -> Option<Vec<u8>> {
+        fs::read(self.cover_variant_path(book_id, variant)).ok()
+    }
+
+    /// Caches a resized copy of the cover; replacing or deleting the cover drops it
+    #[instrument(skip(self, data))]
+    pub fn save_cover_variant(&self, book_id: &str, variant: &str, data: &[u8]) -> Result<()> {
+        let file_path = self.cover_variant_path(book_id, variant);
+        ensure_parent_dir(&file_path)?;
+        fs::write(&file_path, data).map_err(|e| {
+            warn!(book_id = %book_id, error = %e, "Failed to save cover variant");
+            EzBooksError::FileStorage(format!("Failed to save cover variant: {}", e))
+        })
+    }
+
+    /// A montage saved by `save_montage`, or `None` if there is none
+    pub fn read_montage(&self, grid: &str, key: &str) -> Option<Vec<u8>> {
+        fs::read(self.montage_path(grid, key)).ok()
+    }
+
+    /// Caches a gallery montage of `grid`, e.g. `6x4`, dropping the older montages of that
+    /// grid: their books or covers have changed since, so they will not be asked for again
+    #[instrument(skip(self, data))]
+    pub fn save_montage(&self, grid: &str, key: &str, data: &[u8]) -> Result<()> {
+        let file_path = self.montage_path(grid, key);
+        ensure_parent_dir(&file_path)?;
+        let prefix = format!("{}-", grid);
+        for entry in fs::read_dir(self.base_path.join("montages"))? {
+            let path = entry?.path();
+            let is_older = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, |name| name.starts_with(&prefix));
+            if is_older {
+                fs::remove_file(&path)?;
+            }
+        }
+        fs::write(&file_path, data).map_err(|e| {
+            warn!(grid = %grid, error = %e, "Failed to save montage");
+            EzBooksError::FileStorage(format!("Failed to save montage: {}", e))
+        })
+    }
+
+    fn delete_cover_variants(&self, book_id: &str) -> Result<()> {
+        let dir = self.cover_variant_dir(book_id);
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        let prefix = format!("{}-", book_id);
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let is_variant = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, |name| name.starts_with(&prefix));
+            if is_variant {
+                fs::remove_file(&path).map_err(|e| {
+                    warn!(book_id = %book_id, error = %e, "Failed to delete cover variant");
+                    EzBooksError::FileStorage(format!("Failed to delete cover variant: {}", e))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Size of the stored EPUB, read from file metadata without loading it
+    pub fn epub_size(&self, book_id: &str) -> Result<u64> {
+        let file_path = self.epub_path(book_id);
+        let metadata = fs::metadata(&file_path)
+            .map_err(|e| EzBooksError::FileStorage(format!("Failed to stat EPUB file: {}", e)))?;
+        Ok(metadata.len())
+    }
+
+    /// Metadata of the stored EPUB, or `None` if there is no file
+    pub fn stat_epub(&self, book_id: &str) -> Option<StoredFile> {
+        stat_file(&self.epub_path(book_id))
+    }
+
+    /// Metadata of the stored cover, or `None` if there is no file
+    pub fn stat_cover(&self, book_id: &str) -> Option<StoredFile> {
+        stat_file(&self.cover_path(book_id))
+    }
+
+    /// Moves files saved in the flat layout into their shard folders, returning the affected book ids
+    #[instrument(skip(self))]
+    pub fn shard_flat_files(&self) -> Result<Vec<String>> {
+        if !self.sharded {
+            return Ok(Vec::new());
+        }
+
+        let mut moved = Vec::new();
+        for (dir, extension) in [("books", "epub"), ("covers", "jpg")] {
+            for (book_id, flat_path) in flat_files(&self.base_path.join(dir), extension)? {
+                let target = self.path_for(dir, &book_id, extension);
+                ensure_parent_dir(&target)?;
+                fs::rename(&flat_path, &target).map_err(|e| {
+                    warn!(book_id = %book_id, error = %e, "Failed to move file into shard");
+                    EzBooksError::FileStorage(format!(
+                        "Failed to move {}: {}",
+                        flat_path.display(),
+                        e
+                    ))
+                })?;
+
+                if !moved.contains(&book_id) {
+                    moved.push(book_id);
+                }
+            }
+        }
+
+        info!(count = moved.len(), "Moved flat files into sharded layout");
+        Ok(moved)
+    }
+
+    /// Every stored EPUB and cover in either layout, with the book id its name refers to
+    pub fn stored_files(&self) -> Result<Vec<(String, PathBuf)>> {
+        let mut files = Vec::new();
+        for (dir, extension) in [("books", "epub"), ("covers", "jpg")] {
+            let dir = self.base_path.join(dir);
+            files.extend(flat_files(&dir, extension)?);
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    files.extend(flat_files(&path, extension)?);
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    pub fn epub_path(&self, book_id: &str) -> PathBuf {
+        self.path_for("books", book_id, "epub")
+    }
+
+    pub fn cover_path(&self, book_id: &str) -> PathBuf {
+        self.path_for("covers", book_id, "jpg")
     }
 
-    fn cover_path(&self, book_id: &str) -> PathBuf {
+    pub fn cover_variant_path(&self, book_id: &str, variant: &str) -> PathBuf {
+        self.cover_variant_dir(book_id)
+            .join(format!("{}-{}.jpg", book_id, variant))
+    }
+
+    fn montage_path(&self, grid: &str, key: &str) -> PathBuf {
         self.base_path
-            .join("covers")
-            .join(format!("{}.jpg", book_id))
+            .join("montages")
+            .join(format!("{}-{}.jpg", grid, key))
+    }
+
+    fn cover_variant_dir(&self, book_id: &str) -> PathBuf {
+        let mut path = self.base_path.join("cover_variants");
+        if self.sharded {
+            path.push(shard_name(book_id));
+        }
+        path
+    }
+
+    fn path_for(&self, dir: &str, book_id: &str, extension: &str) -> PathBuf {
+        let mut path = self.base_path.join(dir);
+        if self.sharded {
+            path.push(shard_name(book_id));
+        }
+        path.join(format!("{}.{}", book_id, extension))
+    }
+}
+
+fn stat_file(path: &Path) -> Option<StoredFile> {
+    let metadata = fs::metadata(path).ok().filter(|m| m.is_file())?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_nanos());
+
+    Some(StoredFile {
+        len: metadata.len(),
+        etag: format!("\"{:x}-{:x}\"", metadata.len(), modified),
+    })
+}
+
+/// Next to the file, so moving it there is a rename within one directory
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+fn shard_name(book_id: &str) -> String {
+    book_id.chars().take(2).collect::<String>().to_lowercase()
+}
+
+fn ensure_parent_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| {
+            EzBooksError::FileStorage(format!(
+                "Failed to create directory {}: {}",
+                parent.display(),
+                e
+            ))
+        })?;
     }
+    Ok(())
+}
+
+/// Files directly inside `dir` with the given extension, keyed by their book id
+fn flat_files(dir: &Path, extension: &str) -> Result<Vec<(String, PathBuf)>> {
+    let entries = fs::read_dir(dir).map_err(|e| {
+        EzBooksError::FileStorage(format!("Failed to list {}: {}", dir.display(), e))
+    })?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if !path.is_file() || path.extension().and_then(|e| e.to_str()) != Some(extension) {
+            continue;
+        }
+        if let Some(book_id) = path.file_stem().and_then(|s| s.to_str()) {
+            files.push((book_id.to_string(), path.clone()));
+        }
+    }
+    Ok(files)
 }
 
 #[cfg(test)]
@@ -250,6 +561,67 @@ mod tests {
         assert!(!storage.cover_path(book_id).exists());
     }
 
+    #[test]
+    fn should_restore_backed_up_files() {
+        // Given: A book with an EPUB but no cover, backed up and then given new files
+        let (storage, _temp_dir) = create_test_storage();
+        storage.save_epub("book-1", b"old epub").unwrap();
+        storage.back_up_files("book-1").unwrap();
+        let moved_aside = !storage.epub_path("book-1").exists();
+        storage.save_epub("book-1", b"new epub").unwrap();
+        storage.save_cover("book-1", b"new cover").unwrap();
+
+        // When: Restoring the backup
+        storage.restore_backup("book-1");
+
+        // Then: The old EPUB is back and the new cover is gone
+        assert!(moved_aside);
+        assert_eq!(storage.read_epub("book-1").unwrap(), b"old epub");
+        assert!(!storage.cover_path("book-1").exists());
+        assert!(!backup_path(&storage.epub_path("book-1")).exists());
+    }
+
+    #[test]
+    fn should_discard_backup_once_change_is_kept() {
+        // Given: A book whose files were backed up and replaced
+        let (storage, _temp_dir) = create_test_storage();
+        storage.save_epub("book-1", b"old epub").unwrap();
+        storage.save_cover("book-1", b"old cover").unwrap();
+        storage.back_up_files("book-1").unwrap();
+        storage.save_epub("book-1", b"new epub").unwrap();
+
+        // When: Discarding the backup
+        storage.discard_backup("book-1").unwrap();
+
+        // Then: Only the new EPUB is left
+        assert_eq!(storage.read_epub("book-1").unwrap(), b"new epub");
+        assert!(!backup_path(&storage.epub_path("book-1")).exists());
+        assert!(!backup_path(&storage.cover_path("book-1")).exists());
+        assert_eq!(storage.stored_files().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn should_drop_cover_variants_with_their_cover() {
+        // Given: A cover with a cached variant, and another book's variant
+        let (storage, _temp_dir) = create_test_storage();
+        storage.save_cover("book-1", b"cover").unwrap();
+        storage
+            .save_cover_variant("book-1", "w100", b"small")
+            .unwrap();
+        storage
+            .save_cover_variant("book-2", "w100", b"other")
+            .unwrap();
+        let cached = storage.read_cover_variant("book-1", "w100");
+
+        // When: Replacing the cover
+        storage.save_cover("book-1", b"new cover").unwrap();
+
+        // Then: Only that book's variants are gone
+        assert_eq!(cached, Some(b"small".to_vec()));
+        assert_eq!(storage.read_cover_variant("book-1", "w100"), None);
+        assert!(storage.read_cover_variant("book-2", "w100").is_some());
+    }
+
     #[test]
     fn should_handle_deleting_non_existent_epub_gracefully() {
         // Given: A file storage without any EPUBs
@@ -262,6 +634,31 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn should_report_stored_epub_size() {
+        // Given: A stored EPUB
+        let (storage, _temp_dir) = create_test_storage();
+        storage.save_epub("book-1", b"12345").unwrap();
+
+        // When: Asking for its size
+        let size = storage.epub_size("book-1").unwrap();
+
+        // Then: Should match the written bytes
+        assert_eq!(size, 5);
+    }
+
+    #[test]
+    fn should_return_error_for_size_of_missing_epub() {
+        // Given: A storage without the EPUB
+        let (storage, _temp_dir) = create_test_storage();
+
+        // When/Then: Asking for its size fails
+        assert!(matches!(
+            storage.epub_size("missing"),
+            Err(EzBooksError::FileStorage(_))
+        ));
+    }
+
     #[test]
     fn should_generate_correct_file_paths() {
         // Given: A file storage
@@ -278,4 +675,96 @@ mod tests {
         assert!(cover_path.contains("covers"));
         assert!(cover_path.ends_with(".jpg"));
     }
+
+    #[test]
+    fn should_store_files_in_shard_folders() {
+        // Given: A sharded file storage
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::new(temp_dir.path())
+            .unwrap()
+            .with_sharding(true);
+
+        // When: Saving files for a book
+        storage.save_epub("abcd1234", b"epub").unwrap();
+        storage.save_cover("abcd1234", b"cover").unwrap();
+
+        // Then: They should live under the first two id characters
+        assert!(temp_dir.path().join("books/ab/abcd1234.epub").exists());
+        assert!(temp_dir.path().join("covers/ab/abcd1234.jpg").exists());
+        assert_eq!(storage.read_epub("abcd1234").unwrap(), b"epub");
+    }
+
+    #[test]
+    fn should_move_flat_files_into_shards() {
+        // Given: Files written with the flat layout
+        let temp_dir = TempDir::new().unwrap();
+        let flat = FileStorage::new(temp_dir.path()).unwrap();
+        flat.save_epub("ef012345", b"epub").unwrap();
+        flat.save_cover("ef012345", b"cover").unwrap();
+        flat.save_epub("9a876543", b"other").unwrap();
+
+        // When: Switching to sharding and migrating
+        let sharded = flat.with_sharding(true);
+        let mut moved = sharded.shard_flat_files().unwrap();
+        moved.sort();
+
+        // Then: Every book should be readable from its shard
+        assert_eq!(moved, vec!["9a876543", "ef012345"]);
+        assert!(!temp_dir.path().join("books/ef012345.epub").exists());
+        assert_eq!(sharded.read_epub("ef012345").unwrap(), b"epub");
+        assert_eq!(sharded.read_cover("ef012345").unwrap(), b"cover");
+        assert_eq!(sharded.read_epub("9a876543").unwrap(), b"other");
+    }
+
+    #[test]
+    fn should_leave_flat_layout_alone_when_not_sharded() {
+        // Given: A flat storage with a file
+        let (storage, temp_dir) = create_test_storage();
+        storage.save_epub("abcd1234", b"epub").unwrap();
+
+        // When: Running the shard migration
+        let moved = storage.shard_flat_files().unwrap();
+
+        // Then: Nothing should move
+        assert!(moved.is_empty());
+        assert!(temp_dir.path().join("books/abcd1234.epub").exists());
+    }
+
+    #[test]
+    fn should_stat_stored_files_without_reading_them() {
+        // Given: A stored EPUB and no cover
+        let (storage, _temp_dir) = create_test_storage();
+        storage.save_epub("stat1234", b"epub-bytes").unwrap();
+
+        // When: Stating both
+        let epub = storage.stat_epub("stat1234").unwrap();
+        let cover = storage.stat_cover("stat1234");
+
+        // Then: The EPUB size and a quoted ETag are known; the cover is absent
+        assert_eq!(epub.len, 10);
+        assert!(epub.etag.starts_with("\"a-"));
+        assert!(cover.is_none());
+    }
+
+    #[test]
+    fn should_list_stored_files_in_both_layouts() {
+        // Given: A flat EPUB and a sharded EPUB and cover
+        let (flat, _temp_dir) = create_test_storage();
+        flat.save_epub("flat-book", b"epub").unwrap();
+        let sharded = flat.clone().with_sharding(true);
+        sharded.save_epub("shard-book", b"epub").unwrap();
+        sharded.save_cover("shard-book", b"cover").unwrap();
+
+        // When: Listing stored files
+        let mut ids: Vec<String> = flat
+            .stored_files()
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        ids.sort();
+
+        // Then: Every file is found with its book id
+        assert_eq!(ids, vec!["flat-book", "shard-book", "shard-book"]);
+    }
 }
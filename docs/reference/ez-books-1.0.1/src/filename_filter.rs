@@ -0,0 +1,113 @@
+use crate::error::{EzBooksError, Result};
+use regex::Regex;
+use std::path::Path;
+
+/// Filename patterns that usually mark a duplicate or backup copy of a book
+pub const DEFAULT_SKIP_PATTERNS: &[&str] = &[
+    // "book (1).epub", "book (2).epub", ... but not a year such as "1984 (1949).epub"
+    r"(?i) \(\d{1,2}\)\.epub$",
+    // "book.bak.epub"
+    r"(?i)\.bak\.epub$",
+];
+
+/// Matches import filenames that should be skipped as duplicates or backups.
+///
+/// Unlike content-hash dedup this works on names alone, so it also catches copies
+/// whose bytes differ slightly from the original.
+#[derive(Debug, Clone)]
+pub struct FilenameFilter {
+    patterns: Vec<Regex>,
+}
+
+impl FilenameFilter {
+    /// The default patterns extended with `extra_patterns`
+    pub fn new(extra_patterns: &[String]) -> Result<Self> {
+        let patterns = DEFAULT_SKIP_PATTERNS
+            .iter()
+            .copied()
+            .chain(extra_patterns.iter().map(String::as_str))
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| {
+                    EzBooksError::Config(format!("Invalid skip pattern {:?}: {}", pattern, e))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { patterns })
+    }
+
+    /// The first pattern matching the file's name, if any
+    pub fn matching_pattern(&self, path: &Path) -> Option<&str> {
+        let name = path.file_name()?.to_str()?;
+        self.patterns
+            .iter()
+            .find(|pattern| pattern.is_match(name))
+            .map(Regex::as_str)
+    }
+}
+
+impl Default for FilenameFilter {
+    fn default() -> Self {
+        Self {
+            patterns: DEFAULT_SKIP_PATTERNS
+                .iter()
+                .filter_map(|pattern| Regex::new(pattern).ok())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_match_default_duplicate_and_backup_names() {
+        // Given: The default filter
+        let filter = FilenameFilter::default();
+
+        // When/Then: Numbered copies and backups match, originals do not
+        assert!(filter
+            .matching_pattern(Path::new("/in/book (1).epub"))
+            .is_some());
+        assert!(filter
+            .matching_pattern(Path::new("/in/book (12).EPUB"))
+            .is_some());
+        assert!(filter
+            .matching_pattern(Path::new("/in/book.bak.epub"))
+            .is_some());
+        assert!(filter
+            .matching_pattern(Path::new("/in/book.epub"))
+            .is_none());
+        assert!(filter
+            .matching_pattern(Path::new("/in/1984 (1949).epub"))
+            .is_none());
+        assert!(filter
+            .matching_pattern(Path::new("/in/(1) intro.epub"))
+            .is_none());
+    }
+
+    #[test]
+    fn should_extend_defaults_with_configured_patterns() {
+        // Given: A filter with an extra pattern
+        let filter = FilenameFilter::new(&["(?i)-copy\\.epub$".to_string()]).unwrap();
+
+        // When/Then: Both the extra and the default patterns apply
+        assert_eq!(
+            filter.matching_pattern(Path::new("book-copy.epub")),
+            Some("(?i)-copy\\.epub$")
+        );
+        assert!(filter
+            .matching_pattern(Path::new("book (1).epub"))
+            .is_some());
+    }
+
+    #[test]
+    fn should_reject_invalid_pattern() {
+        // Given/When: Building a filter with a malformed regex
+        let result = FilenameFilter::new(&["(unclosed".to_string()]);
+
+        // Then: Should report a configuration error
+        assert!(matches!(result, Err(EzBooksError::Config(msg)) if msg.contains("(unclosed")));
+    }
+}
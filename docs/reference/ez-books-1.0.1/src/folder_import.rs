@@ -0,0 +1,454 @@
+use crate::book_repository;
+use crate::content_hash::content_hash_file;
+use crate::database_connection::DatabasePool;
+use crate::enrichment_queue::EnrichmentQueue;
+use crate::error::{EzBooksError, Result};
+use crate::file_storage::FileStorage;
+use crate::filename_filter::FilenameFilter;
+use crate::upload_handler::{process_upload, UploadOverrides, UploadSettings};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// A book with identical content is already in the library
+    AlreadyInLibrary,
+    /// The file was still growing when it was checked
+    StillBeingWritten,
+    /// The filename matches a duplicate/backup skip pattern
+    FilenamePattern,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// A file with the same content as another one of the same scan, imported only once
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileAlias {
+    pub path: PathBuf,
+    /// The file imported in its place
+    pub original: PathBuf,
+}
+
+/// Summary of an import folder scan
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub skipped: Vec<SkippedFile>,
+    pub failed: usize,
+    /// Copies of imported files; copies of skipped or failed files share their outcome
+    pub aliases: Vec<FileAlias>,
+}
+
+/// Files of one scan with the same content hash, in scan order
+struct ContentGroup {
+    hash: String,
+    original: PathBuf,
+    aliases: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportOutcome {
+    Imported,
+    Skipped(SkipReason),
+}
+
+/// Imports EPUB files from a local folder through the same pipeline as HTTP uploads
+#[derive(Clone)]
+pub struct FolderImporter {
+    pool: DatabasePool,
+    storage: FileStorage,
+    enrichment_queue: EnrichmentQueue,
+    settings: UploadSettings,
+    /// How long a file's size must stay unchanged before it is considered fully written
+    stability_delay: Duration,
+    filename_filter: FilenameFilter,
+}
+
+impl FolderImporter {
+    pub fn new(
+        pool: DatabasePool,
+        storage: FileStorage,
+        enrichment_queue: EnrichmentQueue,
+        settings: UploadSettings,
+        stability_delay: Duration,
+    ) -> Self {
+        Self {
+            pool,
+            storage,
+            enrichment_queue,
+            settings,
+            stability_delay,
+            filename_filter: FilenameFilter::default(),
+        }
+    }
+
+    pub fn with_filename_filter(mut self, filename_filter: FilenameFilter) -> Self {
+        self.filename_filter = filename_filter;
+        self
+    }
+
+    /// Recursively imports every `.epub` below `folder` that is not already in the library
+    #[instrument(skip(self))]
+    pub async fn import_folder(&self, folder: &Path) -> Result<ImportSummary> {
+        let mut summary = ImportSummary::default();
+        let (files, excluded): (Vec<PathBuf>, Vec<PathBuf>) = find_epub_files(folder)?
+            .into_iter()
+            .partition(|path| !self.is_excluded_by_name(path));
+        info!(folder = %folder.display(), count = files.len(), excluded = excluded.len(), "Scanning import folder");
+        summary
+            .skipped
+            .extend(excluded.into_iter().map(|path| SkippedFile {
+                path,
+                reason: SkipReason::FilenamePattern,
+            }));
+
+        // Sample all sizes once, then wait a single delay for the whole batch
+        let sizes: Vec<Option<u64>> = files.iter().map(|path| file_size(path)).collect();
+        tokio::time::sleep(self.stability_delay).await;
+
+        let mut stable = Vec::new();
+        for (path, size_before) in files.into_iter().zip(sizes) {
+            if size_before.is_none() || file_size(&path) != size_before {
+                summary.skipped.push(SkippedFile {
+                    path,
+                    reason: SkipReason::StillBeingWritten,
+                });
+            } else {
+                stable.push(path);
+            }
+        }
+
+        for group in group_by_content(stable, &mut summary).await {
+            match self.ingest_hashed(&group.original, &group.hash).await {
+                Ok(ImportOutcome::Imported) => {
+                    summary.imported += 1;
+                    summary
+                        .aliases
+                        .extend(group.aliases.into_iter().map(|path| FileAlias {
+                            path,
+                            original: group.original.clone(),
+                        }));
+                }
+                Ok(ImportOutcome::Skipped(reason)) => {
+                    summary.skipped.extend(
+                        std::iter::once(group.original)
+                            .chain(group.aliases)
+                            .map(|path| SkippedFile { path, reason }),
+                    );
+                }
+                Err(e) => {
+                    warn!(path = %group.original.display(), copies = group.aliases.len(), error = %e, "Failed to import EPUB");
+                    summary.failed += 1 + group.aliases.len();
+                }
+            }
+        }
+
+        info!(
+            imported = summary.imported,
+            skipped = summary.skipped.len(),
+            failed = summary.failed,
+            aliases = summary.aliases.len(),
+            "Import folder scan completed"
+        );
+        Ok(summary)
+    }
+
+    /// Imports a single file once its size has stopped changing
+    #[instrument(skip(self))]
+    pub async fn import_file(&self, path: &Path) -> Result<ImportOutcome> {
+        if self.is_excluded_by_name(path) {
+            return Ok(ImportOutcome::Skipped(SkipReason::FilenamePattern));
+        }
+
+        let size_before = file_size(path);
+        tokio::time::sleep(self.stability_delay).await;
+        if size_before.is_none() || file_size(path) != size_before {
+            return Ok(ImportOutcome::Skipped(SkipReason::StillBeingWritten));
+        }
+
+        self.ingest(path).await
+    }
+
+    fn is_excluded_by_name(&self, path: &Path) -> bool {
+        match self.filename_filter.matching_pattern(path) {
+            Some(pattern) => {
+                info!(path = %path.display(), pattern = %pattern, "Skipping EPUB matching skip pattern");
+                true
+            }
+            None => false,
+        }
+    }
+
+    async fn ingest(&self, path: &Path) -> Result<ImportOutcome> {
+        let hash = hash_file(path).await?;
+        self.ingest_hashed(path, &hash).await
+    }
+
+    async fn ingest_hashed(&self, path: &Path, hash: &str) -> Result<ImportOutcome> {
+        if let Some(existing) = book_repository::find_by_content_hash(&self.pool, hash).await? {
+            info!(path = %path.display(), existing_id = %existing.id, "Skipping EPUB already in library");
+            return Ok(ImportOutcome::Skipped(SkipReason::AlreadyInLibrary));
+        }
+
+        let filename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| EzBooksError::InvalidFormat("file name is not valid UTF-8".to_string()))?
+            .to_string();
+
+        let response = process_upload(
+            filename,
+            path,
+            UploadOverrides::default(),
+            self.pool.clone(),
+            self.storage.clone(),
+            self.enrichment_queue.clone(),
+            self.settings.clone(),
+        )
+        .await?;
+
+        info!(path = %path.display(), book_id = %response.id, "Imported EPUB from folder");
+        Ok(ImportOutcome::Imported)
+    }
+}
+
+/// Hashes every file up front so copies under other names are parsed and enriched once.
+/// Unreadable files are counted as failed.
+async fn group_by_content(paths: Vec<PathBuf>, summary: &mut ImportSummary) -> Vec<ContentGroup> {
+    let mut groups: Vec<ContentGroup> = Vec::new();
+    let mut by_hash: HashMap<String, usize> = HashMap::new();
+    for path in paths {
+        let hash = match hash_file(&path).await {
+            Ok(hash) => hash,
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to read EPUB");
+                summary.failed += 1;
+                continue;
+            }
+        };
+        match by_hash.get(&hash) {
+            Some(&index) => {
+                info!(path = %path.display(), original = %groups[index].original.display(), "Same content as another file in this scan");
+                groups[index].aliases.push(path);
+            }
+            None => {
+                by_hash.insert(hash.clone(), groups.len());
+                groups.push(ContentGroup {
+                    hash,
+                    original: path,
+                    aliases: Vec::new(),
+                });
+            }
+        }
+    }
+    groups
+}
+
+/// `content_hash_file` on a blocking thread, so reading large EPUBs never stalls the runtime
+async fn hash_file(path: &Path) -> Result<String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || content_hash_file(&path))
+        .await
+        .map_err(|e| EzBooksError::FileStorage(format!("content hashing failed: {}", e)))?
+}
+
+/// All `.epub` files below `folder`, in a stable order
+pub fn find_epub_files(folder: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![folder.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_epub(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+pub fn is_epub(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("epub"))
+}
+
+fn file_size(path: &Path) -> Option<u64> {
+    fs::metadata(path).ok().map(|metadata| metadata.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_epub::TestEpub;
+    use crate::test_fixtures::setup_folder_importer;
+    use tempfile::TempDir;
+
+    #[test]
+    fn should_find_epubs_recursively() {
+        // Given: A folder tree with EPUBs and other files
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("nested/deeper")).unwrap();
+        fs::write(temp_dir.path().join("a.epub"), b"a").unwrap();
+        fs::write(temp_dir.path().join("nested/deeper/b.EPUB"), b"b").unwrap();
+        fs::write(temp_dir.path().join("nested/notes.txt"), b"c").unwrap();
+
+        // When: Finding EPUB files
+        let files = find_epub_files(temp_dir.path()).unwrap();
+
+        // Then: Only EPUBs should be listed, including nested ones
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().all(|path| is_epub(path)));
+    }
+
+    #[tokio::test]
+    async fn should_import_new_epubs_and_skip_known_content() {
+        // Given: An import folder with two copies of one book and another book
+        let (importer, pool, temp_dir) = setup_folder_importer().await;
+        let folder = temp_dir.path().join("import");
+        fs::create_dir_all(folder.join("sub")).unwrap();
+        let first = TestEpub::new("First").author("Ann").build();
+        fs::write(folder.join("first.epub"), &first).unwrap();
+        fs::write(folder.join("sub/first-copy.epub"), &first).unwrap();
+        fs::write(folder.join("second.epub"), TestEpub::new("Second").build()).unwrap();
+
+        // When: Scanning the folder twice
+        let summary = importer.import_folder(&folder).await.unwrap();
+        let rescan = importer.import_folder(&folder).await.unwrap();
+
+        // Then: Each distinct book is imported once, the copy reported as its alias, and
+        // re-scans are idempotent
+        assert_eq!(summary.imported, 2);
+        assert_eq!(summary.failed, 0);
+        assert!(summary.skipped.is_empty());
+        assert_eq!(
+            summary.aliases,
+            vec![FileAlias {
+                path: folder.join("sub/first-copy.epub"),
+                original: folder.join("first.epub"),
+            }]
+        );
+        assert_eq!(rescan.imported, 0);
+        assert_eq!(rescan.skipped.len(), 3);
+        assert_eq!(book_repository::count_books(&pool).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn should_count_invalid_epubs_as_failures() {
+        // Given: A file with an .epub extension that is not an EPUB
+        let (importer, pool, temp_dir) = setup_folder_importer().await;
+        let folder = temp_dir.path().join("import");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("broken.epub"), b"not a zip").unwrap();
+
+        // When: Scanning the folder
+        let summary = importer.import_folder(&folder).await.unwrap();
+
+        // Then: It should be reported as failed and nothing stored
+        assert_eq!(summary.failed, 1);
+        assert_eq!(book_repository::count_books(&pool).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_try_identical_broken_files_once_and_fail_each() {
+        // Given: Two names for the same invalid EPUB
+        let (importer, pool, temp_dir) = setup_folder_importer().await;
+        let folder = temp_dir.path().join("import");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("broken.epub"), b"not a zip").unwrap();
+        fs::write(folder.join("broken-again.epub"), b"not a zip").unwrap();
+
+        // When: Scanning the folder
+        let summary = importer.import_folder(&folder).await.unwrap();
+
+        // Then: Both count as failed, and neither is reported as an alias of a stored book
+        assert_eq!(summary.failed, 2);
+        assert!(summary.aliases.is_empty());
+        assert_eq!(book_repository::count_books(&pool).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_import_single_file_once() {
+        // Given: An EPUB outside the library
+        let (importer, pool, temp_dir) = setup_folder_importer().await;
+        let path = temp_dir.path().join("single.epub");
+        fs::write(&path, TestEpub::new("Single").build()).unwrap();
+
+        // When: Importing it twice
+        let first = importer.import_file(&path).await.unwrap();
+        let second = importer.import_file(&path).await.unwrap();
+
+        // Then: The second import should be recognised by content
+        assert_eq!(first, ImportOutcome::Imported);
+        assert_eq!(second, ImportOutcome::Skipped(SkipReason::AlreadyInLibrary));
+        assert_eq!(book_repository::count_books(&pool).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_skip_missing_file_as_still_being_written() {
+        // Given: A path that has no stable size
+        let (importer, _pool, temp_dir) = setup_folder_importer().await;
+
+        // When: Importing it
+        let outcome = importer
+            .import_file(&temp_dir.path().join("gone.epub"))
+            .await
+            .unwrap();
+
+        // Then: It should be skipped rather than failing
+        assert_eq!(
+            outcome,
+            ImportOutcome::Skipped(SkipReason::StillBeingWritten)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_skip_files_matching_filename_patterns() {
+        // Given: A folder with an original, a numbered copy with different bytes and a custom-pattern match
+        let (importer, pool, temp_dir) = setup_folder_importer().await;
+        let importer =
+            importer.with_filename_filter(FilenameFilter::new(&["^draft-".to_string()]).unwrap());
+        let folder = temp_dir.path().join("import");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("book.epub"), TestEpub::new("Book").build()).unwrap();
+        fs::write(
+            folder.join("book (1).epub"),
+            TestEpub::new("Book").author("Ann").build(),
+        )
+        .unwrap();
+        fs::write(
+            folder.join("draft-book.epub"),
+            TestEpub::new("Draft").build(),
+        )
+        .unwrap();
+
+        // When: Scanning the folder and importing the copy directly
+        let summary = importer.import_folder(&folder).await.unwrap();
+        let single = importer
+            .import_file(&folder.join("book (1).epub"))
+            .await
+            .unwrap();
+
+        // Then: Only the original is imported, the others reported as pattern skips
+        assert_eq!(summary.imported, 1);
+        assert_eq!(summary.skipped.len(), 2);
+        assert!(summary
+            .skipped
+            .iter()
+            .all(|skipped| skipped.reason == SkipReason::FilenamePattern));
+        assert_eq!(single, ImportOutcome::Skipped(SkipReason::FilenamePattern));
+        assert_eq!(book_repository::count_books(&pool).await.unwrap(), 1);
+    }
+}
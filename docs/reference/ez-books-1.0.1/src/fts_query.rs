@@ -0,0 +1,63 @@
+use crate::html_templates::escape_html;
+
+// Control characters cannot occur in book text or metadata, so they safely mark
+// matches until the text is escaped
+pub const MATCH_START: char = '\u{2}';
+pub const MATCH_END: char = '\u{3}';
+
+/// The user's text as one FTS5 phrase, so quotes and operators in it match literally
+pub fn phrase(text: &str) -> String {
+    quote(text)
+}
+
+/// Every word of the user's text as a prefix that must match, or `None` when the text
+/// has no words. `dun herb` finds "Dune" by Frank Herbert.
+pub fn prefix_terms(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .filter(|term| term.chars().any(char::is_alphanumeric))
+        .map(|term| format!("{}*", quote(term)))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// HTML-escapes FTS5 output and wraps the spans between the match markers in `<mark>`
+pub fn highlight(text: &str) -> String {
+    escape_html(text)
+        .replace(MATCH_START, "<mark>")
+        .replace(MATCH_END, "</mark>")
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_quote_user_text_as_a_single_phrase() {
+        // Given/When/Then: Quotes and FTS operators are taken literally
+        assert_eq!(phrase(r#"say "hi" OR"#), r#""say ""hi"" OR""#);
+    }
+
+    #[test]
+    fn should_turn_words_into_prefix_terms_and_skip_punctuation() {
+        // Given/When/Then: Each word becomes a quoted prefix; text without words gives none
+        assert_eq!(
+            prefix_terms("  dun  NOT  - "),
+            Some(r#""dun"* "NOT"*"#.to_string())
+        );
+        assert_eq!(prefix_terms(" - * "), None);
+    }
+
+    #[test]
+    fn should_escape_text_around_highlighted_matches() {
+        // Given/When/Then: Markup in the text is escaped, the markers become <mark>
+        assert_eq!(
+            highlight("<b>\u{2}Dune\u{3}</b>"),
+            "&lt;b&gt;<mark>Dune</mark>&lt;/b&gt;"
+        );
+    }
+}
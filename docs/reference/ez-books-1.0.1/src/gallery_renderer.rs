@@ -1,38 +1,96 @@
 use crate::book_model::Book;
 use crate::html_templates::{escape_html, html_footer, html_header};
+use crate::ui_text::{Lang, UiText};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::collections::HashSet;
+
+/// Renders the library page in `lang`, badging the books in `finished`; links start with
+/// `base_path` (see `html_templates`)
+pub fn render_gallery(
+    books: Vec<Book>,
+    finished: &HashSet<String>,
+    lang: Lang,
+    base_path: &str,
+) -> String {
+    let mut html = html_header(
+        lang.text(UiText::LibraryTitle),
+        "gallery.css",
+        lang,
+        base_path,
+    );
+
+    html.push_str(&render_header(lang));
+    html.push_str(&render_main(
+        books,
+        finished,
+        lang,
+        base_path,
+        &render_empty_state(lang),
+    ));
+    html.push_str(&html_footer(Some("upload.js"), base_path));
 
-pub fn render_gallery(books: Vec<Book>) -> String {
-    let mut html = html_header("EZ-Books Library", "gallery.css");
+    html
+}
 
-    html.push_str(&render_header());
-    html.push_str(&render_main(books));
-    html.push_str(&html_footer(Some("upload.js")));
+/// The gallery of one author's books, linked from the author names on cards and book pages
+pub fn render_author_gallery(
+    author: &str,
+    books: Vec<Book>,
+    finished: &HashSet<String>,
+    lang: Lang,
+    base_path: &str,
+) -> String {
+    let mut html = html_header(author, "gallery.css", lang, base_path);
+    let author = escape_html(author);
+
+    html.push_str(&format!(
+        r#"<header><h1>{}</h1><p><a href="{}/">{}</a></p></header>"#,
+        author,
+        base_path,
+        lang.text(UiText::Library)
+    ));
+    let empty_state = format!(
+        r#"<div class="empty-state"><h2>{}</h2></div>"#,
+        lang.text(UiText::NoBooksBy).replace("{author}", &author)
+    );
+    html.push_str(&render_main(books, finished, lang, base_path, &empty_state));
+    html.push_str(&html_footer(None, base_path));
 
     html
 }
 
-fn render_header() -> String {
-    r#"<header>
-    <h1>EZ-Books Library</h1>
+fn render_header(lang: Lang) -> String {
+    format!(
+        r#"<header>
+    <h1>{}</h1>
     <div id="upload-section">
         <form id="upload-form" enctype="multipart/form-data">
             <input type="file" name="file" accept=".epub" required>
-            <button type="submit">Upload EPUB</button>
+            <button type="submit">{}</button>
         </form>
         <div id="upload-status"></div>
     </div>
-</header>"#
-        .to_string()
+</header>"#,
+        lang.text(UiText::LibraryTitle),
+        lang.text(UiText::UploadEpub)
+    )
 }
 
-fn render_main(books: Vec<Book>) -> String {
+fn render_main(
+    books: Vec<Book>,
+    finished: &HashSet<String>,
+    lang: Lang,
+    base_path: &str,
+    empty_state: &str,
+) -> String {
     let mut html = String::from(r#"<main><div id="gallery">"#);
 
     if books.is_empty() {
-        html.push_str(&render_empty_state());
+        html.push_str(empty_state);
     } else {
         for book in books {
-            html.push_str(&render_book_card(&book));
+            let is_finished = finished.contains(&book.id);
+            html.push_str(&render_book_card(&book, is_finished, lang, base_path));
         }
     }
 
@@ -40,44 +98,95 @@ fn render_main(books: Vec<Book>) -> String {
     html
 }
 
-fn render_empty_state() -> String {
-    r#"<div class="empty-state">
-    <h2>No books yet</h2>
-    <p>Upload your first EPUB to get started!</p>
-</div>"#
-        .to_string()
+fn render_empty_state(lang: Lang) -> String {
+    format!(
+        r#"<div class="empty-state">
+    <h2>{}</h2>
+    <p>{}</p>
+</div>"#,
+        lang.text(UiText::NoBooksYet),
+        lang.text(UiText::UploadFirstEpub)
+    )
 }
 
-fn render_book_card(book: &Book) -> String {
+fn render_book_card(book: &Book, finished: bool, lang: Lang, base_path: &str) -> String {
     let title = escape_html(&book.title);
-    let author = book
-        .author
+    let author = author_link(book, lang, base_path);
+    let cover_url = cover_url(book, base_path);
+    let reader_url = format!("{}/reader/{}", base_path, escape_html(&book.id));
+    let page_url = format!("{}/books/{}", base_path, escape_html(&book.id));
+    // Shown until the cover loads, instead of the stylesheet's gray
+    let cover_style = book
+        .cover_color
         .as_ref()
-        .map(|a| escape_html(a))
-        .unwrap_or_else(|| "Unknown Author".to_string());
-    let cover_url = format!("/covers/{}", escape_html(&book.id));
-    let reader_url = format!("/reader/{}", escape_html(&book.id));
+        .map(|color| format!(r#" style="background-color: {}""#, escape_html(color)))
+        .unwrap_or_default();
+    let finished_badge = if finished {
+        format!(
+            r#"<span class="finished-badge">{}</span>"#,
+            lang.text(UiText::Finished)
+        )
+    } else {
+        String::new()
+    };
 
     format!(
         r#"<div class="book-card" data-book-id="{}">
-    <img src="{}" alt="{}" onerror="this.style.backgroundColor='#bdc3c7'">
-    <h3>{}</h3>
+    <img src="{}" alt="{}"{}>
+    <h3><a href="{}">{}</a></h3>
     <p class="author">{}</p>
+    <span class="format-badge">{}</span>{}
     <div class="actions">
-        <a href="{}">Read</a>
-        <button class="delete" data-id="{}">Delete</button>
+        <a href="{}">{}</a>
+        <button class="delete" data-id="{}">{}</button>
     </div>
 </div>"#,
         escape_html(&book.id),
         cover_url,
         title,
+        cover_style,
+        page_url,
         title,
         author,
+        escape_html(&book.format.to_uppercase()),
+        finished_badge,
         reader_url,
-        escape_html(&book.id)
+        lang.text(UiText::Read),
+        escape_html(&book.id),
+        lang.text(UiText::Delete)
     )
 }
 
+/// The book's author linked to their gallery, or the unknown-author placeholder unlinked
+pub fn author_link(book: &Book, lang: Lang, base_path: &str) -> String {
+    match book.author.as_deref().map(str::trim) {
+        Some(author) if !author.is_empty() => format!(
+            r#"<a href="{}/authors/{}">{}</a>"#,
+            base_path,
+            utf8_percent_encode(author, NON_ALPHANUMERIC),
+            escape_html(author)
+        ),
+        _ => escape_html(book.display_author(lang)),
+    }
+}
+
+/// Cover URL versioned by the cover hash, so browsers may cache it indefinitely.
+/// Books without a cover get their generated gradient placeholder.
+pub fn cover_url(book: &Book, base_path: &str) -> String {
+    if book.cover_image_path.is_none() {
+        return format!("{}/covers/{}/placeholder", base_path, escape_html(&book.id));
+    }
+    match &book.cover_hash {
+        Some(hash) => format!(
+            "{}/covers/{}?v={}",
+            base_path,
+            escape_html(&book.id),
+            escape_html(hash)
+        ),
+        None => format!("{}/covers/{}", base_path, escape_html(&book.id)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,7 +203,7 @@ mod tests {
         let books = vec![create_test_book()];
 
         // When: Rendering gallery
-        let html = render_gallery(books);
+        let html = render_gallery(books, &HashSet::new(), Lang::En, "");
 
         // Then: Should contain all necessary elements
         assert!(html.contains("<!DOCTYPE html>"));
@@ -112,7 +221,7 @@ mod tests {
         let books = vec![];
 
         // When: Rendering gallery
-        let html = render_gallery(books);
+        let html = render_gallery(books, &HashSet::new(), Lang::En, "");
 
         // Then: Should include upload form
         assert!(html.contains(r#"<form id="upload-form""#));
@@ -127,7 +236,7 @@ mod tests {
         let books = vec![];
 
         // When: Rendering gallery
-        let html = render_gallery(books);
+        let html = render_gallery(books, &HashSet::new(), Lang::En, "");
 
         // Then: Should show empty state
         assert!(html.contains("No books yet"));
@@ -142,13 +251,17 @@ mod tests {
         let books = vec![book];
 
         // When: Rendering gallery
-        let html = render_gallery(books);
+        let html = render_gallery(books, &HashSet::new(), Lang::En, "");
 
         // Then: Should render book card with all elements
         assert!(html.contains("Test Book"));
         assert!(html.contains("Test Author"));
-        assert!(html.contains(&format!("/covers/{}", book_id)));
+        assert!(html.contains(&format!("/covers/{}/placeholder", book_id)));
         assert!(html.contains(&format!("/reader/{}", book_id)));
+        assert!(html.contains(&format!(
+            r#"<h3><a href="/books/{}">Test Book</a></h3>"#,
+            book_id
+        )));
         assert!(html.contains(r#"class="delete""#));
     }
 
@@ -163,7 +276,7 @@ mod tests {
         let books = vec![book];
 
         // When: Rendering gallery
-        let html = render_gallery(books);
+        let html = render_gallery(books, &HashSet::new(), Lang::En, "");
 
         // Then: Should escape HTML entities
         assert!(html.contains("&lt;script&gt;"));
@@ -178,7 +291,7 @@ mod tests {
         let books = vec![book];
 
         // When: Rendering gallery
-        let html = render_gallery(books);
+        let html = render_gallery(books, &HashSet::new(), Lang::En, "");
 
         // Then: Should show "Unknown Author"
         assert!(html.contains("Unknown Author"));
@@ -193,7 +306,7 @@ mod tests {
         let books = vec![book1, book2];
 
         // When: Rendering gallery
-        let html = render_gallery(books);
+        let html = render_gallery(books, &HashSet::new(), Lang::En, "");
 
         // Then: Should render all books
         assert!(html.contains("Test Book"));
@@ -201,4 +314,113 @@ mod tests {
         assert!(html.contains("Test Author"));
         assert!(html.contains("Second Author"));
     }
+
+    #[test]
+    fn should_version_cover_url_by_hash() {
+        // Given: A book whose cover has a hash
+        let mut book = create_test_book();
+        book.cover_image_path = Some("/covers/cover.jpg".to_string());
+        book.cover_hash = Some("abc123".to_string());
+        let expected = format!("/covers/{}?v=abc123", book.id);
+
+        // When: Rendering gallery
+        let html = render_gallery(vec![book], &HashSet::new(), Lang::En, "");
+
+        // Then: The cover link should carry the version
+        assert!(html.contains(&expected));
+    }
+
+    #[test]
+    fn should_prefix_gallery_urls_with_base_path() {
+        // Given: A book with a cover hash, served under a subpath
+        let mut book = create_test_book();
+        book.cover_image_path = Some("/covers/cover.jpg".to_string());
+        book.cover_hash = Some("abc123".to_string());
+
+        // When: Rendering gallery
+        let html = render_gallery(vec![book.clone()], &HashSet::new(), Lang::En, "/ezbooks");
+
+        // Then: Reader, cover and asset URLs carry the prefix
+        assert!(html.contains(&format!(r#"href="/ezbooks/reader/{}""#, book.id)));
+        assert!(html.contains(&format!(r#"src="/ezbooks/covers/{}?v=abc123""#, book.id)));
+        assert!(html.contains("/ezbooks/static/js/upload.js"));
+    }
+
+    #[test]
+    fn should_use_cover_color_as_image_background() {
+        // Given: One book with a cover color and one without
+        let mut colored = create_test_book();
+        colored.cover_color = Some("#336699".to_string());
+        let plain = create_test_book();
+
+        // When: Rendering each card
+        let colored_html = render_gallery(vec![colored], &HashSet::new(), Lang::En, "");
+        let plain_html = render_gallery(vec![plain], &HashSet::new(), Lang::En, "");
+
+        // Then: Only the colored card overrides the background
+        assert!(colored_html.contains(r#"style="background-color: #336699""#));
+        assert!(!plain_html.contains("style=\"background-color"));
+    }
+
+    #[test]
+    fn should_show_format_badge_on_card() {
+        // Given: An EPUB book
+        let book = create_test_book();
+
+        // When: Rendering gallery
+        let html = render_gallery(vec![book], &HashSet::new(), Lang::En, "");
+
+        // Then: The card names its format
+        assert!(html.contains(r#"<span class="format-badge">EPUB</span>"#));
+    }
+
+    #[test]
+    fn should_badge_only_finished_books() {
+        // Given: A finished and an unfinished book
+        let finished = create_test_book();
+        let reading = Book::new("Reading".to_string(), "/r.epub".to_string());
+        let finished_ids = HashSet::from([finished.id.clone()]);
+
+        // When: Rendering the gallery
+        let html = render_gallery(vec![finished, reading], &finished_ids, Lang::Ko, "");
+
+        // Then: One card carries the badge, in the page language
+        assert_eq!(
+            html.matches(r#"<span class="finished-badge">완독</span>"#)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn should_link_authors_to_their_gallery_encoded() {
+        // Given: A book whose author has characters special in URLs and HTML
+        let mut book = create_test_book();
+        book.author = Some("O'Brien & Sons/Co".to_string());
+        let anonymous = Book::new("Anonymous".to_string(), "/a.epub".to_string());
+
+        // When: Rendering the gallery and that author's gallery
+        let html = render_gallery(
+            vec![book.clone(), anonymous],
+            &HashSet::new(),
+            Lang::En,
+            "/ezbooks",
+        );
+        let author_html = render_author_gallery(
+            "O'Brien & Sons/Co",
+            Vec::new(),
+            &HashSet::new(),
+            Lang::En,
+            "",
+        );
+
+        // Then: The name is percent-encoded in the link and escaped in the text; books
+        // without an author are not linked
+        assert!(html.contains(
+            r#"<a href="/ezbooks/authors/O%27Brien%20%26%20Sons%2FCo">O&#x27;Brien &amp; Sons/Co</a>"#
+        ));
+        assert!(html.contains(r#"<p class="author">Unknown Author</p>"#));
+        assert!(author_html.contains("<h1>O&#x27;Brien &amp; Sons/Co</h1>"));
+        assert!(author_html.contains("No books by O&#x27;Brien &amp; Sons/Co"));
+    }
 }
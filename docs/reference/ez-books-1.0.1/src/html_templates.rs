@@ -1,23 +1,54 @@
+use crate::ui_text::Lang;
+
 /// Reusable HTML template functions
-pub fn html_header(title: &str, css_file: &str) -> String {
+///
+/// `base_path` is the prefix the app is served under (`Config::base_path`); asset URLs
+/// start with it, and scripts read it from the `data-base-path` body attribute. `lang`
+/// is the language of the page's interface text.
+pub fn html_header(title: &str, css_file: &str, lang: Lang, base_path: &str) -> String {
+    html_header_with_body_attributes(title, css_file, "", lang, base_path)
+}
+
+/// Like `html_header`, with `body_attributes` (already escaped, leading space included)
+/// added to the `<body>` tag
+pub fn html_header_with_body_attributes(
+    title: &str,
+    css_file: &str,
+    body_attributes: &str,
+    lang: Lang,
+    base_path: &str,
+) -> String {
+    let base_path_attribute = if base_path.is_empty() {
+        String::new()
+    } else {
+        format!(r#" data-base-path="{}""#, escape_html(base_path))
+    };
+
     format!(
         r#"<!DOCTYPE html>
-<html lang="en">
+<html lang="{}">
 <head>
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
     <title>{}</title>
-    <link rel="stylesheet" href="/static/css/{}">
+    <link rel="stylesheet" href="{}/static/css/{}">
 </head>
-<body>"#,
+<body{}{}>"#,
+        lang.code(),
         escape_html(title),
-        css_file
+        base_path,
+        css_file,
+        base_path_attribute,
+        body_attributes
     )
 }
 
-pub fn html_footer(include_js: Option<&str>) -> String {
+pub fn html_footer(include_js: Option<&str>, base_path: &str) -> String {
     let js_tag = if let Some(js_file) = include_js {
-        format!(r#"<script src="/static/js/{}"></script>"#, js_file)
+        format!(
+            r#"<script src="{}/static/js/{}"></script>"#,
+            base_path, js_file
+        )
     } else {
         String::new()
     };
@@ -49,7 +80,7 @@ mod tests {
         let css = "test.css";
 
         // When: Generating header
-        let header = html_header(title, css);
+        let header = html_header(title, css, Lang::En, "");
 
         // Then: Should contain proper HTML structure
         assert!(header.contains("<!DOCTYPE html>"));
@@ -65,7 +96,7 @@ mod tests {
         let js_file = "script.js";
 
         // When: Generating footer with JS
-        let footer = html_footer(Some(js_file));
+        let footer = html_footer(Some(js_file), "");
 
         // Then: Should include script tag
         assert!(footer.contains(r#"<script src="/static/js/script.js"></script>"#));
@@ -76,7 +107,7 @@ mod tests {
     #[test]
     fn should_generate_html_footer_without_js() {
         // Given/When: Generating footer without JS
-        let footer = html_footer(None);
+        let footer = html_footer(None, "");
 
         // Then: Should not include script tag
         assert!(!footer.contains("<script"));
@@ -84,6 +115,22 @@ mod tests {
         assert!(footer.contains("</html>"));
     }
 
+    #[test]
+    fn should_prefix_asset_urls_with_base_path() {
+        // Given: An app served under a subpath
+        let base_path = "/ezbooks";
+
+        // When: Generating header and footer
+        let header = html_header("Test Page", "test.css", Lang::Ko, base_path);
+        let footer = html_footer(Some("script.js"), base_path);
+
+        // Then: Asset URLs and the body attribute carry the prefix
+        assert!(header.contains(r#"href="/ezbooks/static/css/test.css""#));
+        assert!(header.contains(r#"<body data-base-path="/ezbooks">"#));
+        assert!(header.contains(r#"<html lang="ko">"#));
+        assert!(footer.contains(r#"<script src="/ezbooks/static/js/script.js"></script>"#));
+    }
+
     #[test]
     fn should_escape_html_entities() {
         // Given: Text with special characters
@@ -0,0 +1,153 @@
+use crate::book_model::current_timestamp;
+use crate::content_hash::content_hash;
+use crate::database_connection::DatabasePool;
+use crate::error::Result;
+use sqlx::Row;
+use std::time::Duration;
+use tracing::{info, instrument};
+
+/// What an `Idempotency-Key` has been used for so far
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyClaim {
+    /// First use: the caller now owns the key and must complete or release it
+    New,
+    /// Another request with the key has not finished yet
+    InProgress,
+    /// The key already produced this response
+    Completed {
+        status: u16,
+        response: serde_json::Value,
+    },
+}
+
+/// Claims `key` for a new upload unless it was used within `ttl`.
+///
+/// Keys are stored hashed, so client-chosen values of any length fit the table.
+#[instrument(skip(pool, key))]
+pub async fn claim_key(pool: &DatabasePool, key: &str, ttl: Duration) -> Result<KeyClaim> {
+    let key_hash = content_hash(key.as_bytes());
+    let now = current_timestamp();
+    let expired_before = now.saturating_sub(ttl.as_secs() as i64);
+
+    sqlx::query("DELETE FROM upload_idempotency_keys WHERE created_at < ?")
+        .bind(expired_before)
+        .execute(pool)
+        .await?;
+
+    let inserted = sqlx::query(
+        "INSERT INTO upload_idempotency_keys (key_hash, created_at) VALUES (?, ?) ON CONFLICT(key_hash) DO NOTHING",
+    )
+    .bind(&key_hash)
+    .bind(now)
+    .execute(pool)
+    .await?;
+    if inserted.rows_affected() == 1 {
+        return Ok(KeyClaim::New);
+    }
+
+    let row =
+        sqlx::query("SELECT status, response FROM upload_idempotency_keys WHERE key_hash = ?")
+            .bind(&key_hash)
+            .fetch_optional(pool)
+            .await?;
+    let completed = row.and_then(|row| {
+        let status: Option<i64> = row.get("status");
+        let response: Option<String> = row.get("response");
+        Some((status?, serde_json::from_str(&response?).ok()?))
+    });
+
+    Ok(match completed {
+        Some((status, response)) => {
+            info!("Replaying response for repeated idempotency key");
+            KeyClaim::Completed {
+                status: status as u16,
+                response,
+            }
+        }
+        None => KeyClaim::InProgress,
+    })
+}
+
+/// Records the response of a claimed key so retries get it back
+#[instrument(skip(pool, key, response))]
+pub async fn complete_key(
+    pool: &DatabasePool,
+    key: &str,
+    book_id: &str,
+    status: u16,
+    response: &serde_json::Value,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE upload_idempotency_keys SET book_id = ?, status = ?, response = ? WHERE key_hash = ?",
+    )
+    .bind(book_id)
+    .bind(i64::from(status))
+    .bind(response.to_string())
+    .bind(content_hash(key.as_bytes()))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Frees a claimed key after a failed upload so the client can retry with it
+#[instrument(skip(pool, key))]
+pub async fn release_key(pool: &DatabasePool, key: &str) -> Result<()> {
+    sqlx::query("DELETE FROM upload_idempotency_keys WHERE key_hash = ? AND response IS NULL")
+        .bind(content_hash(key.as_bytes()))
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::setup_test_db;
+
+    const TTL: Duration = Duration::from_secs(3600);
+
+    #[tokio::test]
+    async fn should_replay_completed_key_and_block_in_progress_one() {
+        // Given: A claimed key
+        let (pool, _temp_dir) = setup_test_db().await;
+        assert_eq!(claim_key(&pool, "abc", TTL).await.unwrap(), KeyClaim::New);
+
+        // When: Retrying before and after the upload completes
+        let during = claim_key(&pool, "abc", TTL).await.unwrap();
+        let response = serde_json::json!({ "id": "book-1" });
+        complete_key(&pool, "abc", "book-1", 200, &response)
+            .await
+            .unwrap();
+        let after = claim_key(&pool, "abc", TTL).await.unwrap();
+
+        // Then: The retry waits, then gets the original response
+        assert_eq!(during, KeyClaim::InProgress);
+        assert_eq!(
+            after,
+            KeyClaim::Completed {
+                status: 200,
+                response
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn should_free_released_and_expired_keys() {
+        // Given: A key whose upload failed and a key claimed long ago
+        let (pool, _temp_dir) = setup_test_db().await;
+        claim_key(&pool, "failed", TTL).await.unwrap();
+        release_key(&pool, "failed").await.unwrap();
+        claim_key(&pool, "old", TTL).await.unwrap();
+        sqlx::query("UPDATE upload_idempotency_keys SET created_at = 0")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // When/Then: Both can be claimed again
+        assert_eq!(
+            claim_key(&pool, "failed", TTL).await.unwrap(),
+            KeyClaim::New
+        );
+        assert_eq!(claim_key(&pool, "old", TTL).await.unwrap(), KeyClaim::New);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::error::{EzBooksError, Result};
+use crate::folder_import::{is_epub, FolderImporter, ImportOutcome, SkipReason};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{debug, info, instrument, warn};
+
+/// Quiet period after the last filesystem event before a file is picked up
+pub const WATCH_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Watches the import folder and imports EPUBs as they appear.
+///
+/// Filesystem events for a path are debounced: a file is only imported once no new
+/// event has arrived for `debounce`. Files still being written are retried later.
+/// The watcher stops when `shutdown` becomes `true`.
+#[instrument(skip(importer, shutdown))]
+pub fn start_import_watcher(
+    importer: FolderImporter,
+    folder: PathBuf,
+    debounce: Duration,
+    shutdown: watch::Receiver<bool>,
+) -> Result<JoinHandle<()>> {
+    let (event_tx, event_rx) = mpsc::unbounded_channel();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        match event {
+            Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                for path in event.paths.into_iter().filter(|path| is_epub(path)) {
+                    // The receiver is gone only once the watcher task has stopped
+                    let _ = event_tx.send(path);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!(error = %e, "Import folder watch error"),
+        }
+    })
+    .map_err(|e| EzBooksError::FileStorage(format!("Failed to create folder watcher: {}", e)))?;
+
+    watcher
+        .watch(&folder, RecursiveMode::Recursive)
+        .map_err(|e| {
+            EzBooksError::FileStorage(format!("Failed to watch {}: {}", folder.display(), e))
+        })?;
+
+    info!(folder = %folder.display(), "Watching import folder");
+    Ok(tokio::spawn(run_watcher(
+        watcher, importer, event_rx, debounce, shutdown,
+    )))
+}
+
+async fn run_watcher(
+    watcher: RecommendedWatcher,
+    importer: FolderImporter,
+    mut events: mpsc::UnboundedReceiver<PathBuf>,
+    debounce: Duration,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    // Path -> time after which it is considered settled
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+
+    loop {
+        let next_due = pending.values().min().copied();
+
+        tokio::select! {
+            changed = shutdown.changed() => {
+                if changed.is_err() || *shutdown.borrow() {
+                    break;
+                }
+            }
+            event = events.recv() => match event {
+                Some(path) => {
+                    debug!(path = %path.display(), "Import folder change detected");
+                    pending.insert(path, Instant::now() + debounce);
+                }
+                None => break,
+            },
+            _ = sleep_until_due(next_due) => {
+                let now = Instant::now();
+                let due: Vec<PathBuf> = pending
+                    .iter()
+                    .filter(|(_, deadline)| **deadline <= now)
+                    .map(|(path, _)| path.clone())
+                    .collect();
+
+                for path in due {
+                    pending.remove(&path);
+                    match importer.import_file(&path).await {
+                        Ok(ImportOutcome::Skipped(SkipReason::StillBeingWritten)) if path.exists() => {
+                            pending.insert(path, Instant::now() + debounce);
+                        }
+                        Ok(outcome) => {
+                            info!(path = %path.display(), outcome = ?outcome, "Processed watched file");
+                        }
+                        Err(e) => {
+                            warn!(path = %path.display(), error = %e, "Failed to import watched file");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    drop(watcher);
+    info!("Import folder watcher stopped");
+}
+
+async fn sleep_until_due(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book_repository;
+    use crate::database_connection::DatabasePool;
+    use crate::test_epub::TestEpub;
+    use crate::test_fixtures::setup_folder_importer;
+
+    async fn wait_for_books(pool: &DatabasePool, expected: i64) -> i64 {
+        for _ in 0..100 {
+            let count = book_repository::count_books(pool).await.unwrap();
+            if count >= expected {
+                return count;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        book_repository::count_books(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_import_epubs_added_while_running() {
+        // Given: A watcher on an empty import folder
+        let (importer, pool, temp_dir) = setup_folder_importer().await;
+        let folder = temp_dir.path().join("import");
+        std::fs::create_dir_all(folder.join("nested")).unwrap();
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let handle = start_import_watcher(
+            importer,
+            folder.clone(),
+            Duration::from_millis(50),
+            shutdown_rx,
+        )
+        .unwrap();
+
+        // When: Dropping a book and a copy of it into the folder
+        let epub = TestEpub::new("Watched").build();
+        std::fs::write(folder.join("watched.epub"), &epub).unwrap();
+        std::fs::write(folder.join("nested/watched-copy.epub"), &epub).unwrap();
+        std::fs::write(folder.join("notes.txt"), b"ignored").unwrap();
+
+        // Then: The book should be imported exactly once
+        assert_eq!(wait_for_books(&pool, 1).await, 1);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(book_repository::count_books(&pool).await.unwrap(), 1);
+
+        // And: The watcher should stop on shutdown
+        shutdown_tx.send(true).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_fail_to_watch_missing_folder() {
+        // Given: A folder that does not exist
+        let (importer, _pool, temp_dir) = setup_folder_importer().await;
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+
+        // When: Starting the watcher
+        let result = start_import_watcher(
+            importer,
+            temp_dir.path().join("missing"),
+            Duration::from_millis(50),
+            shutdown_rx,
+        );
+
+        // Then: Should report a storage error
+        assert!(matches!(result, Err(EzBooksError::FileStorage(_))));
+    }
+}
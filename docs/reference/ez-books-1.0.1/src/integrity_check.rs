@@ -0,0 +1,189 @@
+use crate::book_repository;
+use crate::database_connection::DatabasePool;
+use crate::error::Result;
+use crate::file_storage::FileStorage;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use tracing::{info, instrument, warn};
+
+/// Drift between the books table and the files in storage
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct IntegrityReport {
+    /// Books whose `epub_file_path` does not exist; these can only be reported
+    pub missing_epubs: Vec<String>,
+    /// Books whose `cover_image_path` does not exist
+    pub missing_covers: Vec<String>,
+    /// Books whose cover failed to decode when served with `VALIDATE_COVERS_ON_READ`
+    pub corrupt_covers: Vec<String>,
+    /// Stored files named after no book in the database
+    pub orphaned_files: Vec<String>,
+    /// Whether orphaned files were deleted and missing or corrupt covers cleared
+    pub fixed: bool,
+}
+
+/// Compares book rows with stored files. With `fix`, orphaned files are deleted and
+/// dangling or corrupt covers are cleared so the gallery falls back to no cover.
+#[instrument(skip(pool, storage))]
+pub async fn check_integrity(
+    pool: &DatabasePool,
+    storage: &FileStorage,
+    fix: bool,
+) -> Result<IntegrityReport> {
+    let books = book_repository::find_all(pool).await?;
+    let book_ids: HashSet<&str> = books.iter().map(|book| book.id.as_str()).collect();
+    let mut report = IntegrityReport::default();
+
+    for book in &books {
+        if !Path::new(&book.epub_file_path).is_file() {
+            report.missing_epubs.push(book.id.clone());
+        }
+        if let Some(cover_path) = &book.cover_image_path {
+            if !Path::new(cover_path).is_file() {
+                report.missing_covers.push(book.id.clone());
+            }
+        }
+    }
+
+    report.corrupt_covers = book_repository::find_ids_with_corrupt_cover(pool).await?;
+
+    let stored = storage.stored_files()?;
+    let orphaned: Vec<_> = stored
+        .into_iter()
+        .filter(|(book_id, _)| !book_ids.contains(book_id.as_str()))
+        .map(|(_, path)| path)
+        .collect();
+    report.orphaned_files = orphaned
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+
+    if fix {
+        for path in &orphaned {
+            if let Err(e) = std::fs::remove_file(path) {
+                warn!(path = %path.display(), error = %e, "Failed to delete orphaned file");
+            }
+        }
+        for book_id in report.missing_covers.iter().chain(&report.corrupt_covers) {
+            book_repository::clear_cover(pool, book_id).await?;
+        }
+        report.fixed = true;
+    }
+
+    info!(
+        missing_epubs = report.missing_epubs.len(),
+        missing_covers = report.missing_covers.len(),
+        corrupt_covers = report.corrupt_covers.len(),
+        orphaned_files = report.orphaned_files.len(),
+        fixed = report.fixed,
+        "Integrity check completed"
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book_model::Book;
+    use crate::test_fixtures::setup_test_db;
+    use tempfile::TempDir;
+
+    async fn setup() -> (DatabasePool, FileStorage, TempDir) {
+        let (pool, temp_dir) = setup_test_db().await;
+        let storage = FileStorage::new(temp_dir.path().join("data")).unwrap();
+        (pool, storage, temp_dir)
+    }
+
+    /// A healthy book, a book whose cover file vanished and a file without a book
+    async fn drifted_library(pool: &DatabasePool, storage: &FileStorage) -> (Book, Book, String) {
+        let mut healthy = Book::new("Healthy".to_string(), String::new());
+        healthy.epub_file_path = storage.save_epub(&healthy.id, b"epub").unwrap();
+        book_repository::insert(pool, &healthy).await.unwrap();
+
+        let mut coverless = Book::new("Coverless".to_string(), String::new());
+        coverless.epub_file_path = storage.save_epub(&coverless.id, b"epub").unwrap();
+        coverless.cover_image_path = Some(
+            storage
+                .cover_path(&coverless.id)
+                .to_string_lossy()
+                .to_string(),
+        );
+        coverless.cover_hash = Some("abc".to_string());
+        book_repository::insert(pool, &coverless).await.unwrap();
+
+        let orphan = storage.save_epub("no-such-book", b"epub").unwrap();
+        (healthy, coverless, orphan)
+    }
+
+    #[tokio::test]
+    async fn should_report_drift_without_changing_anything() {
+        // Given: A library with a dangling cover path and an orphaned file
+        let (pool, storage, _temp_dir) = setup().await;
+        let (_, coverless, orphan) = drifted_library(&pool, &storage).await;
+
+        // When: Checking integrity without fixing
+        let report = check_integrity(&pool, &storage, false).await.unwrap();
+
+        // Then: Both problems are listed and left in place
+        assert_eq!(
+            report,
+            IntegrityReport {
+                missing_epubs: Vec::new(),
+                missing_covers: vec![coverless.id.clone()],
+                corrupt_covers: Vec::new(),
+                orphaned_files: vec![orphan.clone()],
+                fixed: false,
+            }
+        );
+        assert!(Path::new(&orphan).exists());
+    }
+
+    #[tokio::test]
+    async fn should_delete_orphans_and_clear_missing_covers_when_fixing() {
+        // Given: The same drifted library
+        let (pool, storage, _temp_dir) = setup().await;
+        let (healthy, coverless, orphan) = drifted_library(&pool, &storage).await;
+
+        // When: Fixing it and checking again
+        let report = check_integrity(&pool, &storage, true).await.unwrap();
+        let recheck = check_integrity(&pool, &storage, false).await.unwrap();
+
+        // Then: The orphan is gone, the cover cleared, and the healthy book untouched
+        assert!(report.fixed);
+        assert!(!Path::new(&orphan).exists());
+        let cleared = book_repository::find_by_id(&pool, &coverless.id)
+            .await
+            .unwrap();
+        assert!(cleared.cover_image_path.is_none());
+        assert!(cleared.cover_hash.is_none());
+        assert!(storage.stat_epub(&healthy.id).is_some());
+        assert_eq!(recheck, IntegrityReport::default());
+    }
+
+    #[tokio::test]
+    async fn should_report_and_clear_flagged_corrupt_covers() {
+        // Given: A book whose cover was flagged as corrupt when served
+        let (pool, storage, _temp_dir) = setup().await;
+        let mut book = Book::new("Garbled".to_string(), String::new());
+        book.epub_file_path = storage.save_epub(&book.id, b"epub").unwrap();
+        book.cover_image_path = Some(storage.save_cover(&book.id, b"garbage").unwrap());
+        book_repository::insert(&pool, &book).await.unwrap();
+        book_repository::mark_cover_corrupt(&pool, &book.id)
+            .await
+            .unwrap();
+
+        // When: Checking, then fixing
+        let report = check_integrity(&pool, &storage, false).await.unwrap();
+        check_integrity(&pool, &storage, true).await.unwrap();
+
+        // Then: The cover is listed, then cleared along with its flag
+        assert_eq!(report.corrupt_covers, vec![book.id.clone()]);
+        assert!(report.missing_covers.is_empty());
+        let cleared = book_repository::find_by_id(&pool, &book.id).await.unwrap();
+        assert!(cleared.cover_image_path.is_none());
+        assert!(book_repository::find_ids_with_corrupt_cover(&pool)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+/// Strips separators and prefixes so ISBNs can be compared, e.g. "ISBN 0-306-40615-2" -> "0306406152"
+pub fn normalize_isbn(isbn: &str) -> String {
+    let upper = isbn.to_uppercase();
+    let without_prefix = upper
+        .trim()
+        .trim_start_matches("ISBN")
+        .trim_start_matches(':');
+
+    without_prefix
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == 'X')
+        .collect()
+}
+
+/// Converts a normalized ISBN-10 to its ISBN-13 form, if it has the right shape
+pub fn isbn10_to_isbn13(isbn_10: &str) -> Option<String> {
+    if isbn_10.len() != 10 || !isbn_10[..9].chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let body = format!("978{}", &isbn_10[..9]);
+    let check_digit = isbn13_check_digit(&body);
+
+    Some(format!("{}{}", body, check_digit))
+}
+
+/// Converts a normalized ISBN-13 to its ISBN-10 form; only 978-prefixed ones have one
+pub fn isbn13_to_isbn10(isbn_13: &str) -> Option<String> {
+    if isbn_13.len() != 13
+        || !isbn_13.starts_with("978")
+        || !isbn_13.chars().all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let body = &isbn_13[3..12];
+    let sum: u32 = body
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .zip((2..=10).rev())
+        .map(|(digit, weight)| digit * weight)
+        .sum();
+    let check_digit = match (11 - sum % 11) % 11 {
+        10 => "X".to_string(),
+        digit => digit.to_string(),
+    };
+
+    Some(format!("{}{}", body, check_digit))
+}
+
+/// Whether a normalized ISBN-10 or ISBN-13 has the right shape and check digit
+pub fn has_valid_checksum(isbn: &str) -> bool {
+    match isbn.len() {
+        10 => isbn10_checksum_ok(isbn),
+        13 => {
+            isbn.chars().all(|c| c.is_ascii_digit())
+                && isbn[12..].parse::<u32>().ok() == Some(isbn13_check_digit(&isbn[..12]))
+        }
+        _ => false,
+    }
+}
+
+/// ISBN-10 digits weighted 10..1 must sum to a multiple of 11; only the last may be 'X'
+fn isbn10_checksum_ok(isbn_10: &str) -> bool {
+    let mut sum = 0;
+    for (i, c) in isbn_10.chars().enumerate() {
+        let value = match c {
+            'X' if i == 9 => 10,
+            _ => match c.to_digit(10) {
+                Some(digit) => digit,
+                None => return false,
+            },
+        };
+        sum += value * (10 - i as u32);
+    }
+    sum % 11 == 0
+}
+
+/// Check digit for the first twelve digits of an ISBN-13
+fn isbn13_check_digit(first_twelve: &str) -> u32 {
+    let sum: u32 = first_twelve
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, digit)| if i % 2 == 0 { digit } else { digit * 3 })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_strip_hyphens_spaces_and_prefix() {
+        // Given/When/Then: Differently formatted ISBNs normalize to the same value
+        assert_eq!(normalize_isbn("978-0-306-40615-7"), "9780306406157");
+        assert_eq!(normalize_isbn("ISBN: 978 0 306 40615 7"), "9780306406157");
+        assert_eq!(normalize_isbn("0-8044-2957-x"), "080442957X");
+    }
+
+    #[test]
+    fn should_convert_isbn10_to_isbn13() {
+        // Given/When/Then: Known ISBN pairs convert with the right check digit
+        assert_eq!(
+            isbn10_to_isbn13("0306406152"),
+            Some("9780306406157".to_string())
+        );
+        assert_eq!(
+            isbn10_to_isbn13("080442957X"),
+            Some("9780804429573".to_string())
+        );
+    }
+
+    #[test]
+    fn should_not_convert_malformed_isbn10() {
+        // Given/When/Then: Wrong lengths or characters are rejected
+        assert_eq!(isbn10_to_isbn13("12345"), None);
+        assert_eq!(isbn10_to_isbn13("ABCDEFGHIJ"), None);
+    }
+
+    #[test]
+    fn should_convert_isbn13_to_isbn10() {
+        // Given/When/Then: 978 ISBNs convert with the right check digit, others have none
+        assert_eq!(
+            isbn13_to_isbn10("9780306406157"),
+            Some("0306406152".to_string())
+        );
+        assert_eq!(
+            isbn13_to_isbn10("9780804429573"),
+            Some("080442957X".to_string())
+        );
+        assert_eq!(isbn13_to_isbn10("9791034304822"), None);
+        assert_eq!(isbn13_to_isbn10("12345"), None);
+    }
+
+    #[test]
+    fn should_validate_isbn_checksums() {
+        // Given/When/Then: Correct check digits pass, altered ones fail
+        assert!(has_valid_checksum("0306406152"));
+        assert!(has_valid_checksum("080442957X"));
+        assert!(has_valid_checksum("9780306406157"));
+        assert!(!has_valid_checksum("0306406153"));
+        assert!(!has_valid_checksum("9780306406158"));
+        assert!(!has_valid_checksum("X306406152"));
+        assert!(!has_valid_checksum("12345"));
+    }
+}
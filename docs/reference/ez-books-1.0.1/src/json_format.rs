@@ -0,0 +1,121 @@
+use std::convert::Infallible;
+use tracing::warn;
+use warp::http::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use warp::hyper::body::{self, Body};
+use warp::reply::Response;
+use warp::Filter;
+
+/// Indents the JSON responses of `filter` when the request's `pretty` parameter asks
+/// for it, or when `pretty_by_default` is set and the request does not say otherwise
+pub fn with_json_format<F>(
+    filter: F,
+    pretty_by_default: bool,
+) -> impl Filter<Extract = (Response,), Error = Infallible> + Clone
+where
+    F: Filter<Extract = (Response,), Error = Infallible> + Clone + Send + Sync + 'static,
+{
+    warp::query::raw()
+        .or(warp::any().map(String::new))
+        .unify()
+        .map(move |query: String| pretty_param(&query).unwrap_or(pretty_by_default))
+        .and(filter)
+        .then(|pretty: bool, response: Response| async move {
+            if pretty && is_json(&response) {
+                indent(response).await
+            } else {
+                response
+            }
+        })
+}
+
+/// The last `pretty` in `query`; a bare `pretty` counts as on
+fn pretty_param(query: &str) -> Option<bool> {
+    query.split('&').rev().find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, "1"));
+        let on = matches!(value.to_lowercase().as_str(), "1" | "true" | "yes");
+        (key == "pretty").then_some(on)
+    })
+}
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |value| value.starts_with("application/json"))
+}
+
+async fn indent(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let compact = match body::to_bytes(body).await {
+        Ok(compact) => compact,
+        Err(e) => {
+            warn!(error = %e, "Failed to read JSON response for pretty-printing");
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let pretty = serde_json::from_slice::<serde_json::Value>(&compact)
+        .and_then(|value| serde_json::to_vec_pretty(&value));
+    match pretty {
+        Ok(pretty) => {
+            parts
+                .headers
+                .insert(CONTENT_LENGTH, HeaderValue::from(pretty.len()));
+            Response::from_parts(parts, Body::from(pretty))
+        }
+        Err(_) => Response::from_parts(parts, Body::from(compact)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Reply;
+
+    fn book() -> impl Filter<Extract = (Response,), Error = Infallible> + Clone {
+        warp::any().map(|| {
+            warp::reply::json(&serde_json::json!({ "title": "Dune", "id": 1 })).into_response()
+        })
+    }
+
+    #[tokio::test]
+    async fn should_keep_json_compact_unless_asked() {
+        // Given: A JSON route wrapped with pretty output off by default
+        let filter = with_json_format(book(), false);
+
+        // When: Requesting it plainly and with `?pretty=1`
+        let compact = warp::test::request().path("/").reply(&filter).await;
+        let pretty = warp::test::request()
+            .path("/?pretty=1")
+            .reply(&filter)
+            .await;
+
+        // Then: Only the request that asked is indented, keeping the field order
+        assert_eq!(compact.body(), r#"{"title":"Dune","id":1}"#);
+        assert_eq!(pretty.body(), "{\n  \"title\": \"Dune\",\n  \"id\": 1\n}");
+        assert_eq!(pretty.headers()["content-type"], "application/json");
+    }
+
+    #[tokio::test]
+    async fn should_let_requests_opt_out_of_pretty_default() {
+        // Given: A JSON and an HTML route wrapped with pretty output on by default
+        let json = with_json_format(book(), true);
+        let html = with_json_format(
+            warp::any().map(|| warp::reply::html("<p>{ }</p>").into_response()),
+            true,
+        );
+
+        // When: Requesting JSON with `?pretty=0`, and the HTML page
+        let compact = warp::test::request()
+            .path("/?lang=en&pretty=0")
+            .reply(&json)
+            .await;
+        let page = warp::test::request().path("/").reply(&html).await;
+
+        // Then: Both are left as the handler wrote them
+        assert_eq!(compact.body(), r#"{"title":"Dune","id":1}"#);
+        assert_eq!(page.body(), "<p>{ }</p>");
+        assert_eq!(pretty_param("pretty"), Some(true));
+        assert_eq!(pretty_param("lang=ko"), None);
+    }
+}
@@ -0,0 +1,111 @@
+use std::time::{Duration, UNIX_EPOCH};
+use warp::http::header::{CACHE_CONTROL, LAST_MODIFIED};
+use warp::http::{HeaderValue, StatusCode};
+use warp::reply::Response;
+use warp::Reply;
+
+/// HTTP date for a unix timestamp in seconds
+pub fn http_date(timestamp: i64) -> String {
+    httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(timestamp.max(0) as u64))
+}
+
+/// Whether an `If-Modified-Since` date shows the client has the version last changed at `timestamp`.
+///
+/// Unparseable dates never match, so the full response is sent.
+pub fn is_not_modified(if_modified_since: Option<&str>, timestamp: i64) -> bool {
+    let since = match if_modified_since.map(httpdate::parse_http_date) {
+        Some(Ok(since)) => since,
+        _ => return false,
+    };
+    match since.duration_since(UNIX_EPOCH) {
+        Ok(since) => timestamp <= since.as_secs() as i64,
+        Err(_) => false,
+    }
+}
+
+/// Whether an `If-Unmodified-Since` date is older than the change at `timestamp`, so a
+/// conditional write must be refused.
+///
+/// Unparseable dates are ignored, as the header would be.
+pub fn is_modified_since(if_unmodified_since: Option<&str>, timestamp: i64) -> bool {
+    match if_unmodified_since.map(httpdate::parse_http_date) {
+        Some(Ok(_)) => !is_not_modified(if_unmodified_since, timestamp),
+        _ => false,
+    }
+}
+
+/// Empty 304 for a client whose copy is still current
+pub fn not_modified(timestamp: i64) -> Response {
+    with_last_modified(StatusCode::NOT_MODIFIED, Some(timestamp))
+}
+
+/// Adds `Last-Modified` when known; clients are asked to revalidate rather than guess freshness
+pub fn with_last_modified(reply: impl Reply, timestamp: Option<i64>) -> Response {
+    let mut response = reply.into_response();
+    let headers = response.headers_mut();
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    if let Some(timestamp) = timestamp {
+        if let Ok(value) = HeaderValue::from_str(&http_date(timestamp)) {
+            headers.insert(LAST_MODIFIED, value);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_format_timestamps_as_http_dates() {
+        // Given/When: Formatting a known timestamp
+        let date = http_date(784_111_777);
+
+        // Then: Should use the IMF-fixdate form
+        assert_eq!(date, "Sun, 06 Nov 1994 08:49:37 GMT");
+    }
+
+    #[test]
+    fn should_compare_if_modified_since_by_second() {
+        // Given: The date of a response last changed at 784111777
+        let since = Some("Sun, 06 Nov 1994 08:49:37 GMT");
+
+        // When/Then: Same or older changes are unmodified, newer ones are not
+        assert!(is_not_modified(since, 784_111_777));
+        assert!(is_not_modified(since, 784_111_000));
+        assert!(!is_not_modified(since, 784_111_778));
+    }
+
+    #[test]
+    fn should_treat_missing_or_malformed_dates_as_modified() {
+        // Given/When/Then: Nothing to compare means the full response
+        assert!(!is_not_modified(None, 0));
+        assert!(!is_not_modified(Some("yesterday"), 0));
+    }
+
+    #[test]
+    fn should_refuse_writes_only_after_a_later_change() {
+        // Given: The date of a copy last changed at 784111777
+        let since = Some("Sun, 06 Nov 1994 08:49:37 GMT");
+
+        // When/Then: Only a newer change fails the precondition; bad dates are ignored
+        assert!(!is_modified_since(since, 784_111_777));
+        assert!(is_modified_since(since, 784_111_778));
+        assert!(!is_modified_since(Some("yesterday"), 784_111_778));
+        assert!(!is_modified_since(None, 784_111_778));
+    }
+
+    #[test]
+    fn should_send_empty_304_with_last_modified() {
+        // Given/When: Building a not-modified response
+        let response = not_modified(784_111_777);
+
+        // Then: Status and validator headers are set
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            response.headers()[LAST_MODIFIED],
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        assert_eq!(response.headers()[CACHE_CONTROL], "no-cache");
+    }
+}
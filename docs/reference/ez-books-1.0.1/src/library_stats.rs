@@ -0,0 +1,92 @@
+use crate::book_repository;
+use crate::database_connection::DatabasePool;
+use crate::error::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::{info, instrument};
+
+const UNKNOWN_LANGUAGE: &str = "unknown";
+
+/// Aggregate numbers describing the whole library
+#[derive(Debug, Serialize, PartialEq)]
+pub struct LibraryStats {
+    pub total_books: i64,
+    /// Sum of the recorded EPUB file sizes
+    pub total_storage_bytes: i64,
+    pub books_by_language: BTreeMap<String, i64>,
+    pub books_missing_cover: i64,
+    pub average_page_count: Option<f64>,
+}
+
+#[instrument(skip(pool))]
+pub async fn collect_library_stats(pool: &DatabasePool) -> Result<LibraryStats> {
+    info!("Collecting library statistics");
+
+    let books_by_language = book_repository::count_by_language(pool)
+        .await?
+        .into_iter()
+        .fold(BTreeMap::new(), |mut map, (language, count)| {
+            let key = language.unwrap_or_else(|| UNKNOWN_LANGUAGE.to_string());
+            *map.entry(key).or_insert(0) += count;
+            map
+        });
+
+    let stats = LibraryStats {
+        total_books: book_repository::count_books(pool).await?,
+        total_storage_bytes: book_repository::sum_file_sizes(pool).await?,
+        books_by_language,
+        books_missing_cover: book_repository::count_missing_covers(pool).await?,
+        average_page_count: book_repository::average_page_count(pool).await?,
+    };
+
+    info!(
+        total_books = stats.total_books,
+        "Library statistics collected"
+    );
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book_model::Book;
+    use crate::test_fixtures::setup_test_db;
+
+    #[tokio::test]
+    async fn should_collect_stats_for_empty_library() {
+        // Given: An empty library
+        let (pool, _temp_dir) = setup_test_db().await;
+
+        // When: Collecting stats
+        let stats = collect_library_stats(&pool).await.unwrap();
+
+        // Then: Everything should be zero or absent
+        assert_eq!(stats.total_books, 0);
+        assert_eq!(stats.total_storage_bytes, 0);
+        assert!(stats.books_by_language.is_empty());
+        assert_eq!(stats.books_missing_cover, 0);
+        assert!(stats.average_page_count.is_none());
+    }
+
+    #[tokio::test]
+    async fn should_group_books_without_language_as_unknown() {
+        // Given: One sized book with a language and one unsized book without
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut book1 = Book::new("One".to_string(), "/one.epub".to_string());
+        book1.language = Some("de".to_string());
+        book1.file_size_bytes = Some(4);
+        let book2 = Book::new("Two".to_string(), "/two.epub".to_string());
+        book_repository::insert(&pool, &book1).await.unwrap();
+        book_repository::insert(&pool, &book2).await.unwrap();
+
+        // When: Collecting stats
+        let stats = collect_library_stats(&pool).await.unwrap();
+
+        // Then: Languages, covers and storage should be aggregated
+        assert_eq!(stats.total_books, 2);
+        assert_eq!(stats.books_by_language.get("de"), Some(&1));
+        assert_eq!(stats.books_by_language.get(UNKNOWN_LANGUAGE), Some(&1));
+        assert_eq!(stats.books_missing_cover, 2);
+        assert_eq!(stats.total_storage_bytes, 4);
+    }
+}
@@ -1,31 +1,105 @@
+mod annotation_anchor;
+mod annotation_repository;
+mod book_bundle;
 mod book_identifier;
 mod book_model;
+mod book_page_renderer;
+mod book_query;
 mod book_repository;
+mod book_update;
+mod bookmark_repository;
+mod build_info;
+mod bulk_enrichment;
+mod chapter_encoding;
+mod cli_args;
+mod collection_repository;
 mod config;
+mod content_cache;
+mod content_hash;
+mod content_index;
+mod cover_montage;
+mod cover_placeholder;
+mod cover_regeneration;
 mod database_connection;
+mod enrichment_queue;
 mod epub_cover_extractor;
 mod epub_parser;
+mod epub_verification;
 mod error;
+mod error_recovery;
+mod error_renderer;
 mod file_storage;
+mod filename_filter;
+mod folder_import;
+mod fts_query;
 mod gallery_renderer;
 mod html_templates;
+mod idempotency_repository;
+mod import_watcher;
+mod integrity_check;
+mod isbn;
+mod json_format;
+mod language_detection;
+mod last_modified;
+mod library_stats;
+mod metadata_completeness;
+mod metadata_search;
+mod openapi_spec;
 mod openlibrary_client;
 mod openlibrary_types;
+mod opf_salvage;
+mod progress_repository;
+mod rate_limit;
 mod reader_renderer;
+mod reindex_job;
+mod request_id;
+mod response_timing;
 mod route_filters;
 mod route_handlers;
+mod security_headers;
 mod static_assets;
+mod storage_migration;
+#[cfg(test)]
+mod test_epub;
+#[cfg(test)]
+mod test_fixtures;
+mod text_extraction;
+mod ui_text;
 mod upload_handler;
 
-use config::Config;
-use database_connection::{create_pool, run_migrations};
+use book_query::ListingDefaults;
+use build_info::BuildInfo;
+use cli_args::{CliArgs, USAGE};
+use content_cache::ContentCache;
+use database_connection::{create_pool, run_migrations, PoolSettings};
+use enrichment_queue::EnrichmentQueue;
 use file_storage::FileStorage;
+use filename_filter::FilenameFilter;
+use folder_import::FolderImporter;
+use import_watcher::{start_import_watcher, WATCH_DEBOUNCE};
 use openlibrary_client::OpenLibraryClient;
-use route_filters::routes;
+use rate_limit::RateLimiter;
+use reader_renderer::ReaderSettings;
+use response_timing::ResponseTiming;
+use route_filters::{routes, RouteSettings};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::watch;
 use tracing_subscriber::fmt::format::FmtSpan;
+use upload_handler::UploadSettings;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli_args = CliArgs::parse(std::env::args().skip(1))?;
+    if cli_args.show_help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    if cli_args.show_version {
+        println!("{}", BuildInfo::current());
+        return Ok(());
+    }
+
     // Initialize tracing
     tracing_subscriber::fmt()
         .with_env_filter("ez_books=info,warn,error")
@@ -35,7 +109,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("EZ-Books starting...");
 
     // Load configuration
-    let config = Config::from_env()?;
+    let config = cli_args.load_config()?;
     tracing::info!(
         host = %config.server_host,
         port = config.server_port,
@@ -44,29 +118,142 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Initialize database
     tracing::info!("Initializing database...");
-    let pool = create_pool(&config.database_url).await?;
+    let pool = create_pool(&config.database_url, PoolSettings::from_config(&config)?).await?;
     run_migrations(&pool).await?;
     tracing::info!("Database initialized successfully");
 
     // Initialize file storage
     tracing::info!(path = %config.storage_path, "Initializing file storage...");
-    let storage = FileStorage::new(&config.storage_path)?;
+    let storage = FileStorage::new(&config.storage_path)?.with_sharding(config.storage_sharding);
+    if config.storage_sharding {
+        storage_migration::migrate_to_sharded_layout(&pool, &storage).await?;
+    }
     tracing::info!("File storage initialized successfully");
 
+    // Backfill derived data for existing books without delaying startup
+    {
+        let pool = pool.clone();
+        let storage = storage.clone();
+        tokio::spawn(async move {
+            if let Err(e) = reindex_job::run_reindex(&pool, &storage).await {
+                tracing::warn!(error = %e, "Reindex job failed");
+            }
+        });
+    }
+
+    // Index the content of books imported before content search was enabled
+    if config.content_search {
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            if let Err(e) = content_index::index_unindexed(&pool).await {
+                tracing::warn!(error = %e, "Content indexing failed");
+            }
+        });
+    }
+
     // Initialize OpenLibrary client
     tracing::info!("Initializing OpenLibrary client...");
-    let ol_client = OpenLibraryClient::with_base_url(&config.openlibrary_api_url)?;
+    let ol_client = OpenLibraryClient::with_api_header(
+        &config.openlibrary_api_url,
+        &config.openlibrary_user_agent,
+        config.openlibrary_api_header.as_ref(),
+    )?;
     tracing::info!("OpenLibrary client initialized successfully");
 
+    // Start background enrichment
+    let enrichment_queue = EnrichmentQueue::start(
+        pool.clone(),
+        ol_client.clone(),
+        config.enrichment_queue_capacity,
+        config.locked_metadata_fields.clone(),
+    );
+    if let Err(e) = enrichment_queue.resume_pending(&pool).await {
+        tracing::warn!(error = %e, "Failed to resume pending enrichment");
+    }
+
+    let upload_settings = UploadSettings::from_config(&config);
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let mut import_watcher = None;
+
+    // Import EPUBs dropped into the import folder without delaying startup
+    if let Some(import_folder) = config.import_folder.clone() {
+        let importer = FolderImporter::new(
+            pool.clone(),
+            storage.clone(),
+            enrichment_queue.clone(),
+            upload_settings.clone(),
+            Duration::from_secs(config.import_stability_secs),
+        )
+        .with_filename_filter(FilenameFilter::new(&config.import_skip_patterns)?);
+
+        if config.watch_import_folder {
+            import_watcher = Some(start_import_watcher(
+                importer.clone(),
+                PathBuf::from(&import_folder),
+                WATCH_DEBOUNCE,
+                shutdown_rx.clone(),
+            )?);
+        }
+
+        tokio::spawn(async move {
+            if let Err(e) = importer.import_folder(Path::new(&import_folder)).await {
+                tracing::warn!(folder = %import_folder, error = %e, "Import folder scan failed");
+            }
+        });
+    }
+
     // Build routes
-    let routes = routes(pool, storage, ol_client);
+    let content_cache = ContentCache::new(config.reader_cache_max_bytes);
+    let rate_limiter = config.rate_limit.map(RateLimiter::new);
+    if let Some(limiter) = &rate_limiter {
+        limiter.spawn_cleanup();
+    }
+    let routes = routes(
+        pool,
+        storage,
+        enrichment_queue,
+        ol_client,
+        content_cache,
+        RouteSettings {
+            upload: upload_settings,
+            reader: ReaderSettings::from_config(&config),
+            listing: ListingDefaults::from_config(&config),
+            admin_token: config.admin_api_token.clone(),
+            base_path: config.base_path.clone(),
+            validate_covers: config.validate_covers_on_read,
+            content_search: config.content_search,
+            rate_limiter,
+            content_security_policy: config.content_security_policy.clone(),
+            locked_metadata_fields: config.locked_metadata_fields.clone(),
+            response_timing: ResponseTiming::from_config(&config),
+            pretty_json: config.pretty_json,
+        },
+    );
 
     // Start server
     let addr: std::net::SocketAddr = config.server_address().parse()?;
     tracing::info!(address = %addr, "Starting web server...");
-    tracing::info!("EZ-Books is ready! Open http://{} in your browser", addr);
+    tracing::info!(
+        "EZ-Books is ready! Open http://{}{}/ in your browser",
+        addr,
+        config.base_path
+    );
+
+    let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(addr, async move {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!(error = %e, "Failed to listen for shutdown signal");
+        }
+        tracing::info!("Shutdown signal received");
+        let _ = shutdown_tx.send(true);
+    });
+    server.await;
 
-    warp::serve(routes).run(addr).await;
+    if let Some(watcher) = import_watcher {
+        if let Err(e) = watcher.await {
+            tracing::warn!(error = %e, "Import folder watcher did not stop cleanly");
+        }
+    }
 
+    tracing::info!("EZ-Books stopped");
     Ok(())
 }
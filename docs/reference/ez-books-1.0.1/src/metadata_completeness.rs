@@ -0,0 +1,105 @@
+use crate::book_model::Book;
+use regex::Regex;
+
+/// Which metadata elements a book has, as input to `completeness_score`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetadataPresence {
+    pub title: bool,
+    pub author: bool,
+    pub cover: bool,
+    pub isbn: bool,
+    pub description: bool,
+    pub publish_year: bool,
+}
+
+impl MetadataPresence {
+    pub fn of_book(book: &Book) -> Self {
+        Self {
+            title: has_real_title(&book.title),
+            author: has_text(&book.author),
+            cover: book.cover_image_path.is_some(),
+            isbn: has_text(&book.isbn_13) || has_text(&book.isbn_10),
+            description: has_text(&book.description),
+            publish_year: book.publish_date.as_deref().map_or(false, has_year),
+        }
+    }
+}
+
+/// Weighted 0-100 score of how well a book is documented.
+///
+/// The weights are the single place to tune the score; they must add up to 100.
+/// Author, cover and ISBN count most because they are what the gallery shows and
+/// what enrichment needs; a publish year is nice to have.
+pub fn completeness_score(presence: &MetadataPresence) -> u8 {
+    const TITLE: u8 = 15;
+    const AUTHOR: u8 = 20;
+    const COVER: u8 = 20;
+    const ISBN: u8 = 20;
+    const DESCRIPTION: u8 = 15;
+    const PUBLISH_YEAR: u8 = 10;
+
+    [
+        (presence.title, TITLE),
+        (presence.author, AUTHOR),
+        (presence.cover, COVER),
+        (presence.isbn, ISBN),
+        (presence.description, DESCRIPTION),
+        (presence.publish_year, PUBLISH_YEAR),
+    ]
+    .iter()
+    .filter(|(present, _)| *present)
+    .map(|(_, weight)| weight)
+    .sum()
+}
+
+/// Titles the parser falls back to when the EPUB has none do not count
+pub fn has_real_title(title: &str) -> bool {
+    let title = title.trim();
+    !title.is_empty() && !title.eq_ignore_ascii_case("unknown")
+}
+
+pub fn has_text(value: &Option<String>) -> bool {
+    value
+        .as_deref()
+        .map_or(false, |value| !value.trim().is_empty())
+}
+
+fn has_year(publish_date: &str) -> bool {
+    Regex::new(r"\b\d{4}\b").map_or(false, |year| year.is_match(publish_date))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_score_fully_documented_book_100() {
+        // Given: A book with every scored element
+        let mut book = Book::new("Dune".to_string(), "/dune.epub".to_string());
+        book.author = Some("Frank Herbert".to_string());
+        book.cover_image_path = Some("/covers/dune.jpg".to_string());
+        book.isbn_10 = Some("0441013597".to_string());
+        book.description = Some("Desert planet.".to_string());
+        book.publish_date = Some("August 1965".to_string());
+
+        // When/Then: It should score the maximum
+        assert_eq!(book.metadata_completeness(), 100);
+    }
+
+    #[test]
+    fn should_not_credit_placeholders_or_blank_values() {
+        // Given: A book with only a placeholder title, a blank author and a year-less date
+        let mut book = Book::new("Unknown".to_string(), "/x.epub".to_string());
+        book.author = Some("  ".to_string());
+        book.publish_date = Some("n.d.".to_string());
+
+        // When/Then: Nothing should count
+        assert_eq!(book.metadata_completeness(), 0);
+        let presence = MetadataPresence {
+            author: true,
+            isbn: true,
+            ..MetadataPresence::default()
+        };
+        assert_eq!(completeness_score(&presence), 40);
+    }
+}
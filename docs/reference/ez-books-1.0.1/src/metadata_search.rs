@@ -0,0 +1,156 @@
+use crate::book_model::Book;
+use crate::database_connection::DatabasePool;
+use crate::error::Result;
+use crate::fts_query::{highlight, MATCH_END, MATCH_START};
+use serde::Serialize;
+use sqlx::{FromRow, Row};
+use tracing::{info, instrument};
+
+/// BM25 weights of the `books_fts` columns: book id, title, author, description.
+/// A word in the title counts for more than the same word in a long description.
+const COLUMN_WEIGHTS: &str = "0.0, 10.0, 5.0, 1.0";
+
+/// A book whose metadata matched a search, best match first
+#[derive(Debug, Serialize)]
+pub struct MetadataMatch {
+    pub book: Book,
+    /// HTML-escaped title with the matching words in `<mark>`
+    pub title: String,
+    /// HTML-escaped author with the matching words in `<mark>`
+    pub author: Option<String>,
+}
+
+/// Books matching every term of an FTS5 query, best BM25 rank first
+#[instrument(skip(pool))]
+pub async fn search_books(
+    pool: &DatabasePool,
+    terms: &str,
+    limit: u32,
+) -> Result<Vec<MetadataMatch>> {
+    let sql = format!(
+        r#"
+        SELECT books.*,
+            highlight(books_fts, 1, ?1, ?2) AS title_highlight,
+            highlight(books_fts, 2, ?1, ?2) AS author_highlight
+        FROM books_fts JOIN books ON books.id = books_fts.book_id
+        WHERE books_fts MATCH ?3
+        ORDER BY bm25(books_fts, {}), books.created_at DESC
+        LIMIT ?4
+        "#,
+        COLUMN_WEIGHTS
+    );
+    let rows = sqlx::query(&sql)
+        .bind(MATCH_START.to_string())
+        .bind(MATCH_END.to_string())
+        .bind(terms)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    let mut matches = Vec::with_capacity(rows.len());
+    for row in rows {
+        let title: String = row.try_get("title_highlight")?;
+        let author: Option<String> = row.try_get("author_highlight")?;
+        matches.push(MetadataMatch {
+            book: Book::from_row(&row)?,
+            title: highlight(&title),
+            author: author.as_deref().map(highlight),
+        });
+    }
+
+    info!(books = matches.len(), "Metadata search completed");
+    Ok(matches)
+}
+
+/// Fills `books_fts` again from every book, returning the number of books indexed
+#[instrument(skip(pool))]
+pub async fn rebuild_fts(pool: &DatabasePool) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM books_fts")
+        .execute(&mut *tx)
+        .await?;
+    let indexed = sqlx::query(
+        "INSERT INTO books_fts (book_id, title, author, description) SELECT id, title, author, description FROM books",
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    tx.commit().await?;
+
+    info!(indexed, "Metadata search index rebuilt");
+    Ok(indexed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book_repository;
+    use crate::fts_query::prefix_terms;
+    use crate::test_fixtures::setup_test_db;
+
+    async fn insert_book(
+        pool: &DatabasePool,
+        title: &str,
+        author: &str,
+        description: &str,
+    ) -> Book {
+        let mut book = Book::new(title.to_string(), format!("/{}.epub", title));
+        book.author = Some(author.to_string());
+        book.description = Some(description.to_string());
+        book_repository::insert(pool, &book).await.unwrap();
+        book
+    }
+
+    async fn search(pool: &DatabasePool, text: &str) -> Vec<MetadataMatch> {
+        search_books(pool, &prefix_terms(text).unwrap(), 10)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_rank_title_matches_first_and_highlight_them() {
+        // Given: One book with the word in its title, one with it only in the description
+        let (pool, _temp_dir) = setup_test_db().await;
+        let described = insert_book(&pool, "Sand", "Anon", "A story about a desert planet").await;
+        let titled = insert_book(&pool, "Desert Planet", "Frank Herbért", "Spice").await;
+
+        // When: Searching by word prefixes, without the accent
+        let found = search(&pool, "deser plan").await;
+        let by_author = search(&pool, "herbert").await;
+
+        // Then: The title match ranks first, with the matching words marked
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].book.id, titled.id);
+        assert_eq!(found[0].title, "<mark>Desert</mark> <mark>Planet</mark>");
+        assert_eq!(found[1].book.id, described.id);
+        assert_eq!(found[1].title, "Sand");
+        assert_eq!(
+            by_author[0].author.as_deref(),
+            Some("Frank <mark>Herbért</mark>")
+        );
+    }
+
+    #[tokio::test]
+    async fn should_follow_edits_and_deletes_and_rebuild() {
+        // Given: A book that gets renamed, and another that gets deleted
+        let (pool, _temp_dir) = setup_test_db().await;
+        let mut renamed = insert_book(&pool, "Old Name", "Anon", "").await;
+        let deleted = insert_book(&pool, "Old Times", "Anon", "").await;
+        renamed.title = "New Name".to_string();
+        book_repository::update_metadata(&pool, &renamed)
+            .await
+            .unwrap();
+        book_repository::delete(&pool, &deleted.id).await.unwrap();
+
+        // When: Searching, then searching again after a rebuild
+        let old = search(&pool, "old").await;
+        let new = search(&pool, "new").await;
+        let indexed = rebuild_fts(&pool).await.unwrap();
+
+        // Then: The index reflects the current rows, before and after the rebuild
+        assert!(old.is_empty());
+        assert_eq!(new.len(), 1);
+        assert_eq!(indexed, 1);
+        assert_eq!(search(&pool, "new").await.len(), 1);
+    }
+}
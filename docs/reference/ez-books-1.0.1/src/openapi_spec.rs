@@ -0,0 +1,1651 @@
+use serde_json::{json, Value};
+
+/// Handwritten OpenAPI 3 description of the HTTP API, served at `/api/openapi.json`.
+/// Keep it in sync with `route_filters`, `Book`, `UploadResponse` and `error_recovery`.
+pub fn openapi_document() -> Value {
+    let mut document = json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "EZ-Books API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Self-hosted EPUB library. Errors use the `Error` schema."
+        },
+        "paths": {
+            "/api/books": {
+                "get": {
+                    "summary": "List all books",
+                    "parameters": [
+                        {
+                            "name": "sort",
+                            "in": "query",
+                            "required": false,
+                            "schema": { "type": "string", "enum": ["created", "size", "completeness"], "default": "created" },
+                            "description": "`created`: newest first. `size`: largest EPUB first, unknown sizes last. `completeness`: least documented first. Omitted: the server's `DEFAULT_SORT`."
+                        },
+                        {
+                            "name": "limit",
+                            "in": "query",
+                            "required": false,
+                            "schema": { "type": "integer", "minimum": 1, "maximum": 1000, "default": 100 },
+                            "description": "Page size, `DEFAULT_PAGE_SIZE` when omitted. Giving `limit` or `cursor` returns a `BooksPage` instead of an array."
+                        },
+                        {
+                            "name": "cursor",
+                            "in": "query",
+                            "required": false,
+                            "schema": { "type": "string" },
+                            "description": "`next_cursor` from the previous page. Only with `sort=created`."
+                        },
+                        {
+                            "name": "added_from",
+                            "in": "query",
+                            "required": false,
+                            "schema": { "type": "integer", "format": "int64" },
+                            "description": "Only books added at or after this Unix timestamp (seconds), newest first. Not combinable with paging, `format` or a `sort` other than `created`."
+                        },
+                        {
+                            "name": "added_to",
+                            "in": "query",
+                            "required": false,
+                            "schema": { "type": "integer", "format": "int64" },
+                            "description": "Only books added at or before this Unix timestamp (seconds)"
+                        },
+                        {
+                            "name": "format",
+                            "in": "query",
+                            "required": false,
+                            "schema": { "type": "string", "example": "epub" },
+                            "description": "Only books of this format (case-insensitive). Not combinable with paging or `added_from`/`added_to`."
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Books in the library, or one page of them when paging",
+                            "content": { "application/json": { "schema": {
+                                "oneOf": [
+                                    { "type": "array", "items": { "$ref": "#/components/schemas/Book" } },
+                                    { "$ref": "#/components/schemas/BooksPage" }
+                                ]
+                            } } }
+                        },
+                        "400": error_response("Unknown sort value, malformed cursor, limit out of range, `added_from` after `added_to`, or a combination of parameters that cannot be used together"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/api/books/next": {
+                "get": {
+                    "summary": "Oldest-added book in a subject that has not been opened in the reader",
+                    "parameters": [
+                        {
+                            "name": "subject",
+                            "in": "query",
+                            "required": true,
+                            "schema": { "type": "string" },
+                            "description": "Subject to pick from, case-insensitive"
+                        },
+                        {
+                            "name": "lang",
+                            "in": "query",
+                            "required": false,
+                            "schema": { "type": "string" },
+                            "description": "Only consider books in this language"
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "The next unread book",
+                            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Book" } } }
+                        },
+                        "400": error_response("Missing `subject`"),
+                        "404": error_response("No unread books remain in the subject"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/api/books/recommended": {
+                "get": {
+                    "summary": "Unread books sharing subjects with the most recently read books",
+                    "description": "Ranked by the number of shared subjects, then newest first. Books already opened are excluded; the list is empty until something has been read.",
+                    "parameters": [{
+                        "name": "limit",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "integer", "minimum": 0, "maximum": 50, "default": 10 },
+                        "description": "Larger values are capped at 50"
+                    }],
+                    "responses": {
+                        "200": {
+                            "description": "Recommended books, possibly none",
+                            "content": { "application/json": { "schema": { "type": "array", "items": { "$ref": "#/components/schemas/Book" } } } }
+                        },
+                        "400": error_response("`limit` is not a number"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/api/books/{id}": {
+                "parameters": [book_id_parameter()],
+                "get": {
+                    "summary": "Get a single book",
+                    "description": "Responses carry `Last-Modified`, which moves when the book, its reading progress or its collections change.",
+                    "parameters": [{
+                        "name": "If-Modified-Since",
+                        "in": "header",
+                        "required": false,
+                        "description": "`Last-Modified` of the client's copy; answered with 304 while it is current",
+                        "schema": { "type": "string" }
+                    }],
+                    "responses": {
+                        "200": {
+                            "description": "The book",
+                            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/BookDetail" } } }
+                        },
+                        "304": { "description": "Unchanged since `If-Modified-Since`" },
+                        "404": error_response("Book not found"),
+                        "500": error_response("Internal server error")
+                    }
+                },
+                "put": {
+                    "summary": "Edit a book's metadata",
+                    "description": "Absent fields are left unchanged; an empty string clears an optional field.",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/BookUpdate" } } }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "The updated book",
+                            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Book" } } }
+                        },
+                        "400": error_response("Malformed JSON or unknown field"),
+                        "404": error_response("Book not found"),
+                        "422": {
+                            "description": "One or more fields are invalid",
+                            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ValidationError" } } }
+                        },
+                        "500": error_response("Internal server error")
+                    }
+                },
+                "delete": {
+                    "summary": "Delete a book and its stored files",
+                    "responses": {
+                        "200": {
+                            "description": "Book deleted",
+                            "content": { "application/json": { "schema": {
+                                "type": "object",
+                                "required": ["success"],
+                                "properties": { "success": { "type": "boolean" } }
+                            } } }
+                        },
+                        "404": error_response("Book not found"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/api/books/{id}/subjects": {
+                "parameters": [book_id_parameter()],
+                "post": {
+                    "summary": "Tag a book with a subject",
+                    "description": "Whitespace is trimmed and collapsed; a subject the book already has in any letter case is a conflict.",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": {
+                            "type": "object",
+                            "required": ["subject"],
+                            "properties": { "subject": { "type": "string" } }
+                        } } }
+                    },
+                    "responses": {
+                        "201": {
+                            "description": "All of the book's subjects",
+                            "content": { "application/json": { "schema": { "type": "array", "items": { "type": "string" } } } }
+                        },
+                        "400": error_response("Malformed JSON or unknown field"),
+                        "404": error_response("Book not found"),
+                        "409": error_response("The book already has this subject"),
+                        "422": {
+                            "description": "Empty or overlong subject",
+                            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ValidationError" } } }
+                        },
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/api/books/{id}/subjects/{subject}": {
+                "parameters": [book_id_parameter(), {
+                    "name": "subject",
+                    "in": "path",
+                    "required": true,
+                    "description": "Percent-encoded; matched ignoring letter case",
+                    "schema": { "type": "string" }
+                }],
+                "delete": {
+                    "summary": "Remove a subject from a book",
+                    "responses": {
+                        "204": { "description": "Subject removed" },
+                        "400": error_response("Subject is not valid UTF-8"),
+                        "404": error_response("Book not found, or the book doesn't have this subject"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/api/books/{id}/media-overlays": {
+                "parameters": [book_id_parameter()],
+                "get": {
+                    "summary": "List the media overlay documents and audio files of a narrated book",
+                    "description": "Resource discovery only; playback is up to the client. Empty for books without narration.",
+                    "responses": {
+                        "200": {
+                            "description": "Resources sorted by path",
+                            "content": { "application/json": { "schema": {
+                                "type": "array",
+                                "items": { "$ref": "#/components/schemas/MediaOverlayResource" }
+                            } } }
+                        },
+                        "404": error_response("Book not found"),
+                        "422": error_response("The stored EPUB could not be parsed"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/api/books/{id}/bundle": {
+                "parameters": [book_id_parameter()],
+                "get": {
+                    "summary": "Download one book as a ZIP of its EPUB, cover and `metadata.json`",
+                    "responses": {
+                        "200": {
+                            "description": "ZIP archive named after the title via `Content-Disposition`",
+                            "content": { "application/zip": { "schema": { "type": "string", "format": "binary" } } }
+                        },
+                        "404": error_response("Book or its stored EPUB not found"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/api/books/{id}/download": {
+                "parameters": [book_id_parameter()],
+                "get": {
+                    "summary": "Download the stored EPUB",
+                    "responses": {
+                        "200": {
+                            "description": "The EPUB, named via `Content-Disposition` after its `original_filename` (or the title when unknown), with an `ETag`",
+                            "content": { "application/epub+zip": { "schema": { "type": "string", "format": "binary" } } }
+                        },
+                        "404": error_response("Book or its stored EPUB not found"),
+                        "500": error_response("Internal server error")
+                    }
+                },
+                "head": {
+                    "summary": "Check that the EPUB exists; same headers as GET, sized from file metadata",
+                    "responses": {
+                        "200": { "description": "The EPUB exists" },
+                        "404": { "description": "Book or its stored EPUB not found" }
+                    }
+                }
+            },
+            "/api/stats": {
+                "get": {
+                    "summary": "Library statistics",
+                    "responses": {
+                        "200": {
+                            "description": "Aggregate statistics, cacheable for 30 seconds",
+                            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/LibraryStats" } } }
+                        },
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/api/admin/enrich-missing": {
+                "post": {
+                    "summary": "Look up every book missing an author, description or cover on OpenLibrary",
+                    "description": "Only books with an ISBN are looked up, 20 per request with a pause between requests; covers are not downloaded. Disabled unless `ADMIN_API_TOKEN` is set.",
+                    "security": [{ "adminToken": [] }],
+                    "responses": {
+                        "200": {
+                            "description": "Counts of the run",
+                            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/BulkEnrichmentReport" } } }
+                        },
+                        "401": error_response("Missing or invalid API token"),
+                        "429": error_response("Too many uploads or enrichment runs from this client; wait for `Retry-After` seconds. Only when `RATE_LIMIT_PER_MINUTE` is set"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/api/admin/integrity": {
+                "get": {
+                    "summary": "Report book rows and stored files that no longer match",
+                    "description": "Disabled unless `ADMIN_API_TOKEN` is set.",
+                    "security": [{ "adminToken": [] }],
+                    "parameters": [{
+                        "name": "fix",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "boolean", "default": false },
+                        "description": "Delete orphaned files and clear cover paths whose file is missing or was found corrupt"
+                    }],
+                    "responses": {
+                        "200": {
+                            "description": "Integrity report",
+                            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/IntegrityReport" } } }
+                        },
+                        "400": error_response("`fix` is not a boolean"),
+                        "401": error_response("Missing or invalid API token"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/api/admin/verify": {
+                "post": {
+                    "summary": "Open every stored EPUB and report the ones that are missing or unreadable",
+                    "description": "Read-only; catches corrupt files the integrity report cannot see. Files are opened a few at a time. Disabled unless `ADMIN_API_TOKEN` is set.",
+                    "security": [{ "adminToken": [] }],
+                    "responses": {
+                        "200": {
+                            "description": "Every book, newest first",
+                            "content": { "application/json": { "schema": {
+                                "type": "array",
+                                "items": { "$ref": "#/components/schemas/EpubVerification" }
+                            } } }
+                        },
+                        "401": error_response("Missing or invalid API token"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/api/openapi.json": {
+                "get": {
+                    "summary": "This document",
+                    "responses": {
+                        "200": {
+                            "description": "OpenAPI 3 description",
+                            "content": { "application/json": { "schema": { "type": "object" } } }
+                        }
+                    }
+                }
+            },
+            "/upload": {
+                "post": {
+                    "summary": "Upload an EPUB",
+                    "description": "Metadata is read from the EPUB, with any `title`, `author` or `isbn` fields taking precedence; OpenLibrary enrichment runs in the background.",
+                    "parameters": [{
+                        "name": "Idempotency-Key",
+                        "in": "header",
+                        "required": false,
+                        "description": "Retrying with the same key within `UPLOAD_IDEMPOTENCY_TTL_SECS` returns the original response instead of importing the book again",
+                        "schema": { "type": "string" }
+                    }],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "multipart/form-data": { "schema": {
+                            "type": "object",
+                            "required": ["file"],
+                            "properties": {
+                                "file": { "type": "string", "format": "binary" },
+                                "title": { "type": "string", "description": "Overrides the EPUB title" },
+                                "author": { "type": "string", "description": "Overrides the EPUB author" },
+                                "isbn": { "type": "string", "description": "ISBN-10 or ISBN-13 used instead of the EPUB's for enrichment; must have a valid check digit" }
+                            }
+                        } } }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Book imported",
+                            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/UploadResponse" } } }
+                        },
+                        "400": error_response("Missing `file` part, an extension not in `UPLOAD_ALLOWED_EXTENSIONS` or gzip data that is not an EPUB"),
+                        "408": error_response("Upload body not received within `UPLOAD_TIMEOUT_SECS`"),
+                        "409": error_response("ISBN already in the library; the message names the existing book id. Only when `REJECT_DUPLICATE_ISBN` is enabled; also returned while an upload with the same `Idempotency-Key` is still in progress"),
+                        "413": error_response("Upload larger than 50MB"),
+                        "422": error_response("The file could not be parsed as an EPUB, is DRM-protected, has no cover while `REQUIRE_COVER` is on, or an override field is invalid (see `errors`)"),
+                        "429": error_response("Too many uploads or enrichment runs from this client; wait for `Retry-After` seconds. Only when `RATE_LIMIT_PER_MINUTE` is set"),
+                        "500": error_response("Internal server error"),
+                        "503": error_response("Enrichment queue or database connections unavailable")
+                    }
+                }
+            },
+            "/reader/{id}/text": {
+                "parameters": [book_id_parameter()],
+                "get": {
+                    "summary": "Book content as plain text for screen readers and text-to-speech",
+                    "description": "Each chapter starts with a `=== Chapter N ===` line; paragraphs are separated by blank lines.",
+                    "parameters": [{
+                        "name": "chapter",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "integer", "minimum": 1 },
+                        "description": "Return only this 1-based chapter"
+                    }],
+                    "responses": {
+                        "200": {
+                            "description": "UTF-8 text; the `X-Chapter-Count` header gives the number of chapters",
+                            "content": { "text/plain": { "schema": { "type": "string" } } }
+                        },
+                        "400": error_response("`chapter` is not a number"),
+                        "404": error_response("Book or chapter not found"),
+                        "422": error_response("The stored EPUB could not be read"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/reader/{id}/chapters/{index}": {
+                "parameters": [book_id_parameter(), {
+                    "name": "index",
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "integer", "minimum": 0 },
+                    "description": "0-based spine position, as in the reader's `data-next-chapter`"
+                }],
+                "get": {
+                    "summary": "Sanitized HTML of one chapter, for loading chapters left out of a capped reader page",
+                    "responses": {
+                        "200": {
+                            "description": "HTML fragment; `X-Next-Chapter` gives the following index and is absent for the last chapter, `X-Chapter-Count` the spine length",
+                            "content": { "text/html": { "schema": { "type": "string" } } }
+                        },
+                        "404": error_response("Book or chapter not found"),
+                        "422": error_response("The stored EPUB could not be read"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/reader/{id}/stream": {
+                "parameters": [book_id_parameter()],
+                "get": {
+                    "summary": "Sanitized HTML of every chapter, streamed as each one is extracted",
+                    "responses": {
+                        "200": {
+                            "description": "One JSON object per line: `index` (0-based spine position), `label` (table of contents entry, null when not listed) and `html`",
+                            "content": { "application/x-ndjson": { "schema": {
+                                "type": "object",
+                                "properties": {
+                                    "index": { "type": "integer" },
+                                    "label": { "type": "string", "nullable": true },
+                                    "html": { "type": "string" }
+                                }
+                            } } }
+                        },
+                        "404": error_response("Book not found"),
+                        "422": error_response("The stored EPUB could not be read"),
+                        "500": error_response("Internal server error")
+                    }
+                }
+            },
+            "/covers/{id}": {
+                "parameters": [book_id_parameter()],
+                "get": {
+                    "summary": "Cover image of a book",
+                    "parameters": [{
+                        "name": "v",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "string" },
+                        "description": "The book's `cover_hash`. Versioned responses are cacheable for a year; unversioned ones must be revalidated."
+                    }, {
+                        "name": "w",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "integer", "enum": [64, 100, 150, 200, 300, 450] },
+                        "description": "Largest width of the cover, scaled down keeping its aspect ratio and served as JPEG; covers are never enlarged. Variants are cached on disk."
+                    }, {
+                        "name": "h",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "integer", "enum": [64, 100, 150, 200, 300, 450] },
+                        "description": "Largest height of the cover; combine with `w` to fit a box"
+                    }],
+                    "responses": {
+                        "200": {
+                            "description": "Cover image; `Content-Type` is the stored `cover_mime`, JPEG for older covers",
+                            "content": {
+                                "image/jpeg": { "schema": { "type": "string", "format": "binary" } },
+                                "image/png": { "schema": { "type": "string", "format": "binary" } },
+                                "image/webp": { "schema": { "type": "string", "format": "binary" } },
+                                "image/gif": { "schema": { "type": "string", "format": "binary" } }
+                            }
+                        },
+                        "400": error_response("`w` or `h` is not one of the allowed sizes"),
+                        "404": error_response("The book has no cover; the gallery uses `/covers/{id}/placeholder` instead"),
+                        "500": error_response("Cover could not be read")
+                    }
+                },
+                "head": {
+                    "summary": "Check that a cover exists; same headers as GET without the image",
+                    "responses": {
+                        "200": { "description": "The cover exists" },
+                        "404": { "description": "The book has no cover" }
+                    }
+                }
+            }
+        },
+        "components": {
+            "securitySchemes": {
+                "adminToken": { "type": "http", "scheme": "bearer", "description": "The configured `ADMIN_API_TOKEN`" }
+            },
+            "schemas": {
+                "Book": {
+                    "type": "object",
+                    "required": ["id", "title", "epub_file_path", "format", "enrichment_status", "created_at", "updated_at"],
+                    "properties": {
+                        "id": { "type": "string", "format": "uuid" },
+                        "title": { "type": "string" },
+                        "author": nullable("string"),
+                        "isbn_10": nullable("string"),
+                        "isbn_13": nullable("string"),
+                        "publisher": nullable("string"),
+                        "publish_date": nullable("string"),
+                        "description": nullable("string"),
+                        "description_from_content": {
+                            "type": "boolean",
+                            "description": "True when the description is the book's opening paragraph because the EPUB declared none; enrichment and edits replace it"
+                        },
+                        "notes": {
+                            "type": "string",
+                            "nullable": true,
+                            "description": "The reader's private plain-text notes, separate from the publisher description"
+                        },
+                        "cover_image_path": nullable("string"),
+                        "cover_hash": {
+                            "type": "string",
+                            "nullable": true,
+                            "description": "SHA-256 of the cover; pass it as `v` to `/covers/{id}` for an immutable URL"
+                        },
+                        "cover_mime": {
+                            "type": "string",
+                            "nullable": true,
+                            "description": "Content type of the stored cover; `null` for older covers, which are JPEG"
+                        },
+                        "cover_color": {
+                            "type": "string",
+                            "nullable": true,
+                            "description": "Average cover color as `#rrggbb`, e.g. for a card background while the cover loads"
+                        },
+                        "epub_file_path": { "type": "string" },
+                        "original_filename": {
+                            "type": "string",
+                            "nullable": true,
+                            "description": "Filename the EPUB was uploaded with, used to name downloads; null for books added before it was recorded"
+                        },
+                        "format": {
+                            "type": "string",
+                            "description": "Lowercase file format of the stored book; `epub` for every book imported so far"
+                        },
+                        "openlibrary_key": nullable("string"),
+                        "openlibrary_work_key": nullable("string"),
+                        "page_count": nullable("integer"),
+                        "language": nullable("string"),
+                        "language_detected": {
+                            "type": "boolean",
+                            "description": "True when the language was guessed from the text because the EPUB declared none"
+                        },
+                        "has_audio_narration": {
+                            "type": "boolean",
+                            "description": "True when the EPUB ships EPUB3 media overlays for read-aloud; list them with `/api/books/{id}/media-overlays`"
+                        },
+                        "enrichment_status": { "$ref": "#/components/schemas/EnrichmentStatus" },
+                        "file_size_bytes": nullable("integer"),
+                        "content_hash": {
+                            "type": "string",
+                            "nullable": true,
+                            "description": "SHA-256 of the EPUB bytes"
+                        },
+                        "created_at": { "type": "integer", "description": "Unix timestamp (seconds)" },
+                        "updated_at": { "type": "integer", "description": "Unix timestamp (seconds)" }
+                    }
+                },
+                "BookDetail": {
+                    "allOf": [
+                        { "$ref": "#/components/schemas/Book" },
+                        {
+                            "type": "object",
+                            "required": ["metadata_completeness", "subjects", "reading_progress", "collections"],
+                            "properties": {
+                                "metadata_completeness": {
+                                    "type": "integer",
+                                    "minimum": 0,
+                                    "maximum": 100,
+                                    "description": "How well the book is documented: title, author, cover, ISBN, description and publish year"
+                                },
+                                "subjects": { "type": "array", "items": { "type": "string" } },
+                                "reading_progress": {
+                                    "type": "object",
+                                    "nullable": true,
+                                    "description": "`null` until the book is opened in the reader",
+                                    "required": ["opened_at", "updated_at"],
+                                    "properties": {
+                                        "opened_at": { "type": "integer", "description": "Unix timestamp (seconds) of the first open" },
+                                        "updated_at": { "type": "integer", "description": "Unix timestamp (seconds) of the latest open" },
+                                        "finished_at": { "type": "integer", "nullable": true, "description": "Unix timestamp (seconds) of when the book was marked finished" }
+                                    }
+                                },
+                                "collections": {
+                                    "type": "array",
+                                    "description": "Collections the book is in, by name",
+                                    "items": { "$ref": "#/components/schemas/Collection" }
+                                }
+                            }
+                        }
+                    ]
+                },
+                "BooksPage": {
+                    "type": "object",
+                    "required": ["books", "next_cursor"],
+                    "properties": {
+                        "books": { "type": "array", "items": { "$ref": "#/components/schemas/Book" } },
+                        "next_cursor": {
+                            "type": "string",
+                            "nullable": true,
+                            "description": "Opaque; `null` on the last page"
+                        }
+                    }
+                },
+                "EnrichmentStatus": {
+                    "type": "string",
+                    "enum": ["pending", "done", "failed"]
+                },
+                "UploadResponse": {
+                    "type": "object",
+                    "required": ["id", "title", "enrichment_status"],
+                    "properties": {
+                        "id": { "type": "string", "format": "uuid" },
+                        "title": { "type": "string" },
+                        "author": nullable("string"),
+                        "enrichment_status": { "$ref": "#/components/schemas/EnrichmentStatus" }
+                    }
+                },
+                "Bookmark": {
+                    "type": "object",
+                    "required": ["id", "book_id", "chapter_index", "scroll_fraction", "label", "created_at"],
+                    "properties": {
+                        "id": { "type": "integer" },
+                        "book_id": { "type": "string" },
+                        "chapter_index": { "type": "integer", "description": "Spine index of the chapter" },
+                        "scroll_fraction": { "type": "number", "description": "0 at the start of the chapter, 1 at its end" },
+                        "label": { "type": "string" },
+                        "created_at": { "type": "integer", "description": "Unix timestamp (seconds)" }
+                    }
+                },
+                "HighlightColor": {
+                    "type": "string",
+                    "enum": ["yellow", "green", "blue", "pink"],
+                    "default": "yellow"
+                },
+                "Annotation": {
+                    "type": "object",
+                    "required": ["id", "book_id", "chapter_index", "quote", "prefix", "suffix", "color", "note", "created_at", "updated_at"],
+                    "properties": {
+                        "id": { "type": "integer" },
+                        "book_id": { "type": "string" },
+                        "chapter_index": { "type": "integer", "description": "Spine index of the chapter" },
+                        "quote": { "type": "string" },
+                        "prefix": { "type": "string" },
+                        "suffix": { "type": "string" },
+                        "color": { "$ref": "#/components/schemas/HighlightColor" },
+                        "note": { "type": "string", "nullable": true, "description": "Plain text, shown as the highlight's tooltip" },
+                        "created_at": { "type": "integer", "description": "Unix timestamp (seconds)" },
+                        "updated_at": { "type": "integer", "description": "Unix timestamp (seconds)" }
+                    }
+                },
+                "MediaOverlayResource": {
+                    "type": "object",
+                    "required": ["path", "media_type"],
+                    "properties": {
+                        "path": { "type": "string", "description": "Path inside the EPUB archive" },
+                        "media_type": { "type": "string", "description": "`application/smil+xml` for overlays, `audio/*` for narration" }
+                    }
+                },
+                "BulkEnrichmentReport": {
+                    "type": "object",
+                    "required": ["enriched", "failed", "skipped"],
+                    "properties": {
+                        "enriched": { "type": "integer", "description": "Books OpenLibrary had data for, now updated" },
+                        "failed": { "type": "integer", "description": "Books whose lookup or update failed" },
+                        "skipped": { "type": "integer", "description": "Books without an ISBN or unknown to OpenLibrary" }
+                    }
+                },
+                "EpubVerification": {
+                    "type": "object",
+                    "required": ["id", "title", "status"],
+                    "properties": {
+                        "id": { "type": "string" },
+                        "title": { "type": "string" },
+                        "status": { "type": "string", "enum": ["ok", "missing", "unreadable"] }
+                    }
+                },
+                "IntegrityReport": {
+                    "type": "object",
+                    "required": ["missing_epubs", "missing_covers", "corrupt_covers", "orphaned_files", "fixed"],
+                    "properties": {
+                        "missing_epubs": { "type": "array", "items": { "type": "string" }, "description": "Ids of books whose EPUB file is missing; never changed by `fix`" },
+                        "missing_covers": { "type": "array", "items": { "type": "string" }, "description": "Ids of books whose cover file is missing" },
+                        "corrupt_covers": { "type": "array", "items": { "type": "string" }, "description": "Ids of books whose cover failed to decode when served with `VALIDATE_COVERS_ON_READ`; these need regenerating" },
+                        "orphaned_files": { "type": "array", "items": { "type": "string" }, "description": "Stored files that belong to no book" },
+                        "fixed": { "type": "boolean", "description": "Whether `fix` was applied" }
+                    }
+                },
+                "LibraryStats": {
+                    "type": "object",
+                    "required": ["total_books", "total_storage_bytes", "books_by_language", "books_missing_cover"],
+                    "properties": {
+                        "total_books": { "type": "integer" },
+                        "total_storage_bytes": { "type": "integer" },
+                        "books_by_language": {
+                            "type": "object",
+                            "additionalProperties": { "type": "integer" },
+                            "description": "Books without a language are counted under `unknown`"
+                        },
+                        "books_missing_cover": { "type": "integer" },
+                        "average_page_count": nullable("number")
+                    }
+                },
+                "BookUpdate": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "properties": {
+                        "title": { "type": "string", "minLength": 1, "maxLength": 500 },
+                        "author": { "type": "string", "maxLength": 300 },
+                        "isbn_10": { "type": "string", "description": "Checksum-validated; stored without separators" },
+                        "isbn_13": { "type": "string", "description": "Checksum-validated; stored without separators" },
+                        "publisher": { "type": "string", "maxLength": 300 },
+                        "publish_date": { "type": "string", "maxLength": 50 },
+                        "description": { "type": "string", "maxLength": 10000 },
+                        "notes": { "type": "string", "maxLength": 20000, "description": "Private plain-text notes" },
+                        "page_count": { "type": "integer", "minimum": 0, "maximum": 2147483647 },
+                        "language": { "type": "string", "maxLength": 35 }
+                    }
+                },
+                "Error": {
+                    "type": "object",
+                    "required": ["error"],
+                    "properties": { "error": { "type": "string" } }
+                },
+                "ValidationError": {
+                    "type": "object",
+                    "required": ["error", "errors"],
+                    "properties": {
+                        "error": { "type": "string" },
+                        "errors": {
+                            "type": "object",
+                            "description": "Field name -> problem with its value",
+                            "additionalProperties": { "type": "string" }
+                        }
+                    }
+                }
+            }
+        }
+    });
+    // Kept apart so a single `json!` stays within the macro recursion limit
+    for group in [
+        reading_aid_paths(),
+        collection_paths(),
+        replace_file_paths(),
+        search_paths(),
+        cover_placeholder_paths(),
+        author_paths(),
+        cover_regeneration_paths(),
+        version_paths(),
+        progress_paths(),
+    ] {
+        if let (Some(paths), Value::Object(group)) = (document["paths"].as_object_mut(), group) {
+            paths.extend(group);
+        }
+    }
+    document["components"]["schemas"]["Collection"] = collection_schema();
+    document["components"]["schemas"]["CoverColor"] = cover_color_schema();
+    add_iso_timestamps(&mut document["components"]["schemas"]["Book"]);
+    document["components"]["schemas"]["Book"]["properties"]["epub_version"] = json!({
+        "type": "string",
+        "nullable": true,
+        "description": "Package version the OPF declares, e.g. `2.0` or `3.0`; null when it declares none"
+    });
+    document["components"]["schemas"]["Book"]["properties"]["raw_title"] = json!({
+        "type": "string",
+        "nullable": true,
+        "description": "Title the EPUB declared when it was a file name such as `my_book.epub` and `title` was tidied from it"
+    });
+    document["components"]["schemas"]["Book"]["properties"]["read_status"] = json!({
+        "type": "string",
+        "nullable": true,
+        "enum": ["full access", "lendable", "checked out"],
+        "description": "Availability of the book's Internet Archive scan per the OpenLibrary Read API"
+    });
+    document["components"]["schemas"]["Book"]["properties"]["read_url"] = json!({
+        "type": "string",
+        "nullable": true,
+        "description": "Internet Archive page of the scan; null when no readable scan was found"
+    });
+    document
+}
+
+/// The ISO-8601 copies `Book` serializes next to its unix timestamps
+fn add_iso_timestamps(schema: &mut Value) {
+    for field in ["created_at", "updated_at"] {
+        let iso = format!("{}_iso", field);
+        schema["properties"][&iso] = json!({
+            "type": "string",
+            "format": "date-time",
+            "description": format!("`{}` as ISO-8601 UTC, e.g. 2024-03-01T12:00:00Z", field)
+        });
+        if let Some(required) = schema["required"].as_array_mut() {
+            required.push(Value::String(iso));
+        }
+    }
+}
+
+/// Colors and generated covers for drawing a book before, or without, its cover
+fn cover_placeholder_paths() -> Value {
+    json!({
+        "/api/books/{id}/cover/color": {
+            "parameters": [book_id_parameter()],
+            "get": {
+                "summary": "Color to paint while the cover loads, with readable text color",
+                "description": "The dominant color of the cover, computed and stored on first request for covers imported before colors were. Books without a cover, or whose cover does not decode, get the two-tone gradient of their placeholder, picked from the title.",
+                "responses": {
+                    "200": {
+                        "description": "The cover color",
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/CoverColor" } } }
+                    },
+                    "404": error_response("Book not found"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/covers/{id}/placeholder": {
+            "parameters": [book_id_parameter()],
+            "get": {
+                "summary": "Generated cover for books without one",
+                "description": "A gradient in the colors `/api/books/{id}/cover/color` reports for the book; the gallery links it for books without a cover.",
+                "responses": {
+                    "200": {
+                        "description": "SVG placeholder",
+                        "content": { "image/svg+xml": { "schema": { "type": "string" } } }
+                    },
+                    "404": error_response("Book not found"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/gallery/montage": {
+            "get": {
+                "summary": "One image of the newest covers",
+                "description": "Covers of the newest books with a cover, left to right and top to bottom, each scaled into a 150x225 tile on a dark background. The montage is cached until a book is added or a cover changes.",
+                "parameters": [{
+                    "name": "cols",
+                    "in": "query",
+                    "required": false,
+                    "schema": { "type": "integer", "minimum": 1, "maximum": 10, "default": 6 }
+                }, {
+                    "name": "rows",
+                    "in": "query",
+                    "required": false,
+                    "schema": { "type": "integer", "minimum": 1, "maximum": 10, "default": 4 }
+                }],
+                "responses": {
+                    "200": {
+                        "description": "JPEG montage",
+                        "content": { "image/jpeg": { "schema": { "type": "string", "format": "binary" } } }
+                    },
+                    "400": error_response("Columns or rows out of range"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        }
+    })
+}
+
+fn cover_color_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["color", "text_color", "gradient"],
+        "properties": {
+            "color": { "type": "string", "description": "`#rrggbb`; the top gradient color for placeholders" },
+            "text_color": { "type": "string", "enum": ["#000000", "#ffffff"], "description": "Whichever contrasts more with `color`" },
+            "gradient": {
+                "type": "array",
+                "items": { "type": "string" },
+                "minItems": 2,
+                "maxItems": 2,
+                "nullable": true,
+                "description": "Top-left and bottom-right colors of the placeholder; null for books with a cover"
+            }
+        }
+    })
+}
+
+/// Replacing a book's EPUB in place
+fn replace_file_paths() -> Value {
+    json!({
+        "/api/books/{id}/file": {
+            "parameters": [book_id_parameter()],
+            "put": {
+                "summary": "Replace a book's EPUB, keeping its id and relations",
+                "description": "The new file is parsed before anything is stored; its EPUB and cover replace the old ones. Metadata the new EPUB declares replaces the book's, and its subjects are added. Notes, enrichment, subjects, progress, bookmarks, annotations and collections are kept; a new ISBN queues the book for enrichment again. Accepts the same form as `/upload`.",
+                "parameters": [{
+                    "name": "If-Unmodified-Since",
+                    "in": "header",
+                    "required": false,
+                    "description": "Refuse the replacement if the book changed after this HTTP date, e.g. the `Last-Modified` of a detail response",
+                    "schema": { "type": "string" }
+                }],
+                "requestBody": {
+                    "required": true,
+                    "content": { "multipart/form-data": { "schema": {
+                        "type": "object",
+                        "required": ["file"],
+                        "properties": {
+                            "file": { "type": "string", "format": "binary" },
+                            "title": { "type": "string", "description": "Overrides the EPUB title" },
+                            "author": { "type": "string", "description": "Overrides the EPUB author" },
+                            "isbn": { "type": "string", "description": "ISBN-10 or ISBN-13 used instead of the EPUB's" }
+                        }
+                    } } }
+                },
+                "responses": {
+                    "200": {
+                        "description": "The updated book",
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Book" } } }
+                    },
+                    "400": error_response("Missing `file` part, an extension not in `UPLOAD_ALLOWED_EXTENSIONS` or gzip data that is not an EPUB"),
+                    "404": error_response("Book not found"),
+                    "408": error_response("Upload body not received within `UPLOAD_TIMEOUT_SECS`"),
+                    "409": error_response("ISBN already held by another book; the message names its id. Only when `REJECT_DUPLICATE_ISBN` is enabled"),
+                    "412": error_response("The book changed after the `If-Unmodified-Since` date"),
+                    "413": error_response("Upload larger than 50MB"),
+                    "422": error_response("The file could not be parsed as an EPUB, is DRM-protected, has no cover while `REQUIRE_COVER` is on, or an override field is invalid (see `errors`)"),
+                    "429": error_response("Too many uploads or enrichment runs from this client; wait for `Retry-After` seconds. Only when `RATE_LIMIT_PER_MINUTE` is set"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/books/{id}/reset-metadata": {
+            "parameters": [book_id_parameter()],
+            "post": {
+                "summary": "Reset a book to the metadata of its stored EPUB",
+                "description": "Re-reads the stored EPUB and takes its title, author, ISBNs, publisher, description and language, clearing what it leaves out. The publish date, page count and OpenLibrary keys, which only enrichment or edits set, are cleared and the book is not enriched again. Notes, subjects, files, cover, progress and collections are kept.",
+                "responses": {
+                    "200": {
+                        "description": "The reset book",
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Book" } } }
+                    },
+                    "404": error_response("Book not found"),
+                    "422": error_response("The stored EPUB can no longer be parsed"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        }
+    })
+}
+
+/// Background re-processing of stored covers after the cover settings change
+fn cover_regeneration_paths() -> Value {
+    let progress = json!({
+        "type": "object",
+        "properties": {
+            "running": { "type": "boolean" },
+            "total": { "type": "integer" },
+            "regenerated": { "type": "integer" },
+            "skipped": { "type": "integer", "description": "Books whose EPUB is missing or has no cover" },
+            "failed": { "type": "integer" },
+            "started_at": { "type": "integer", "nullable": true },
+            "finished_at": { "type": "integer", "nullable": true }
+        }
+    });
+    json!({
+        "/api/admin/regenerate-covers": {
+            "post": {
+                "summary": "Extract every cover again with the current cover settings",
+                "description": "Starts a background run over all books, a few at a time, replacing each stored cover with one extracted from its EPUB. Books whose EPUB is missing or has no cover keep their cover.",
+                "security": [{ "adminToken": [] }],
+                "responses": {
+                    "202": {
+                        "description": "Run started",
+                        "content": { "application/json": { "schema": progress.clone() } }
+                    },
+                    "401": error_response("Missing or invalid API token"),
+                    "409": error_response("A run is already in progress")
+                }
+            },
+            "get": {
+                "summary": "Progress of the current or last cover regeneration",
+                "security": [{ "adminToken": [] }],
+                "responses": {
+                    "200": {
+                        "description": "Book counts so far",
+                        "content": { "application/json": { "schema": progress } }
+                    },
+                    "401": error_response("Missing or invalid API token")
+                }
+            }
+        }
+    })
+}
+
+fn version_paths() -> Value {
+    json!({
+        "/api/version": {
+            "get": {
+                "summary": "Which build is running",
+                "responses": {
+                    "200": {
+                        "description": "Crate version, with the commit and build time when the build recorded them",
+                        "content": { "application/json": { "schema": {
+                            "type": "object",
+                            "required": ["version"],
+                            "properties": {
+                                "version": { "type": "string" },
+                                "git_commit": { "type": "string", "nullable": true },
+                                "build_timestamp": { "type": "string", "nullable": true }
+                            }
+                        } } }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Marking books finished and starting them over
+fn progress_paths() -> Value {
+    json!({
+        "/api/books/{id}/progress/finish": {
+            "parameters": [book_id_parameter()],
+            "post": {
+                "summary": "Mark a book finished",
+                "description": "The book keeps its reading progress, so it can be reopened where it was; a book never opened counts as opened now. The gallery badges finished books.",
+                "responses": {
+                    "200": {
+                        "description": "The book's reading progress",
+                        "content": { "application/json": { "schema": {
+                            "type": "object",
+                            "required": ["opened_at", "updated_at", "finished_at"],
+                            "properties": {
+                                "opened_at": { "type": "integer" },
+                                "updated_at": { "type": "integer" },
+                                "finished_at": { "type": "integer" }
+                            }
+                        } } }
+                    },
+                    "404": error_response("Book not found"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/books/{id}/progress/reset": {
+            "parameters": [book_id_parameter()],
+            "post": {
+                "summary": "Forget a book's reading progress",
+                "description": "Clears when it was opened and finished, so it is unread again and can be suggested by `/api/books/next` and `/api/books/recommended`.",
+                "responses": {
+                    "204": { "description": "Progress cleared" },
+                    "404": error_response("Book not found"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        }
+    })
+}
+
+/// Authors for browsing; `/authors/{name}` shows the gallery of one
+fn author_paths() -> Value {
+    json!({
+        "/api/authors": {
+            "get": {
+                "summary": "Distinct authors with their number of books",
+                "description": "Alphabetical. Spellings differing only in case or surrounding space count as one author; books without an author are left out.",
+                "responses": {
+                    "200": {
+                        "description": "Authors",
+                        "content": { "application/json": { "schema": { "type": "array", "items": {
+                            "type": "object",
+                            "required": ["name", "book_count"],
+                            "properties": {
+                                "name": { "type": "string" },
+                                "book_count": { "type": "integer" }
+                            }
+                        } } } }
+                    },
+                    "500": error_response("Internal server error")
+                }
+            }
+        }
+    })
+}
+
+/// Ranked metadata search, and full-text search over book content with `CONTENT_SEARCH`
+fn search_paths() -> Value {
+    json!({
+        "/api/search": {
+            "get": {
+                "summary": "Find books by title, author or description",
+                "description": "Every word of `q` must start a word of the book's metadata, ignoring case and diacritics. Books are ranked by BM25, title words weighing most, and come with their title and author HTML-escaped with the matching words in `<mark>`.",
+                "parameters": [
+                    { "name": "q", "in": "query", "required": true, "schema": { "type": "string" } },
+                    { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "default": 20, "maximum": 100 } }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Matching books, best first",
+                        "content": { "application/json": { "schema": { "type": "array", "items": {
+                            "type": "object",
+                            "properties": {
+                                "book": { "$ref": "#/components/schemas/Book" },
+                                "title": { "type": "string", "description": "Title with the matches in `<mark>`" },
+                                "author": { "type": "string", "nullable": true, "description": "Author with the matches in `<mark>`" }
+                            }
+                        } } } }
+                    },
+                    "422": error_response("`q` contains no words"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/admin/rebuild-fts": {
+            "post": {
+                "summary": "Refill the metadata search index from the books table",
+                "description": "Triggers keep the index in sync; this repairs it after the database was changed outside ez-books.",
+                "security": [{ "adminToken": [] }],
+                "responses": {
+                    "200": {
+                        "description": "Number of books indexed",
+                        "content": { "application/json": { "schema": {
+                            "type": "object",
+                            "properties": { "indexed": { "type": "integer" } }
+                        } } }
+                    },
+                    "401": error_response("Missing or invalid API token"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/search/content": {
+            "post": {
+                "summary": "Find books containing a phrase",
+                "description": "Matches the words of `q` in order, ignoring case and diacritics. Books come best match first, each with up to three snippets whose matches are in `<mark>`. Answers 404 unless `CONTENT_SEARCH` is enabled.",
+                "parameters": [
+                    { "name": "q", "in": "query", "required": true, "schema": { "type": "string" } },
+                    { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "default": 20, "maximum": 100 } }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Matching books",
+                        "content": { "application/json": { "schema": { "type": "array", "items": {
+                            "type": "object",
+                            "properties": {
+                                "book": { "$ref": "#/components/schemas/Book" },
+                                "match_count": { "type": "integer", "description": "Matching chapters" },
+                                "snippets": { "type": "array", "items": {
+                                    "type": "object",
+                                    "properties": {
+                                        "chapter_index": { "type": "integer" },
+                                        "snippet": { "type": "string", "description": "HTML-escaped text with the match in `<mark>`" }
+                                    }
+                                } }
+                            }
+                        } } } }
+                    },
+                    "404": error_response("Content search is disabled"),
+                    "422": error_response("`q` is blank"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/admin/reindex-content": {
+            "post": {
+                "summary": "Rebuild the content search index from every stored EPUB",
+                "description": "Runs until every book is indexed again; books that cannot be read are counted as failed. Answers 404 unless `CONTENT_SEARCH` is enabled.",
+                "security": [{ "adminToken": [] }],
+                "responses": {
+                    "200": {
+                        "description": "Indexed and failed book counts",
+                        "content": { "application/json": { "schema": {
+                            "type": "object",
+                            "properties": {
+                                "indexed": { "type": "integer" },
+                                "failed": { "type": "integer" }
+                            }
+                        } } }
+                    },
+                    "401": error_response("Missing or invalid API token"),
+                    "404": error_response("Content search is disabled"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        }
+    })
+}
+
+/// Collection routes
+fn collection_paths() -> Value {
+    let collection_id = json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "schema": { "type": "integer" }
+    });
+    json!({
+        "/api/collections": {
+            "get": {
+                "summary": "List collections by name",
+                "responses": {
+                    "200": {
+                        "description": "All collections",
+                        "content": { "application/json": { "schema": { "type": "array", "items": { "$ref": "#/components/schemas/Collection" } } } }
+                    },
+                    "500": error_response("Internal server error")
+                }
+            },
+            "post": {
+                "summary": "Create a collection",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "required": ["name"],
+                        "properties": { "name": { "type": "string", "maxLength": 300 } }
+                    } } }
+                },
+                "responses": {
+                    "201": {
+                        "description": "The new, empty collection",
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Collection" } } }
+                    },
+                    "400": error_response("Malformed JSON or unknown field"),
+                    "409": error_response("A collection of that name exists, ignoring case"),
+                    "422": {
+                        "description": "Empty or overlong name",
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ValidationError" } } }
+                    },
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/collections/{id}": {
+            "parameters": [collection_id],
+            "get": {
+                "summary": "Get a collection with its books in reading order",
+                "responses": {
+                    "200": {
+                        "description": "The collection and a `books` array of Book",
+                        "content": { "application/json": { "schema": { "allOf": [
+                            { "$ref": "#/components/schemas/Collection" },
+                            { "type": "object", "properties": { "books": { "type": "array", "items": { "$ref": "#/components/schemas/Book" } } } }
+                        ] } } }
+                    },
+                    "404": error_response("Collection not found"),
+                    "500": error_response("Internal server error")
+                }
+            },
+            "delete": {
+                "summary": "Delete a collection; its books stay in the library",
+                "responses": {
+                    "204": { "description": "Collection deleted" },
+                    "404": error_response("Collection not found"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/collections/{id}/books": {
+            "parameters": [collection_id],
+            "post": {
+                "summary": "Append a book to a collection",
+                "description": "Adding a book the collection already holds leaves its position unchanged.",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "required": ["book_id"],
+                        "properties": { "book_id": { "type": "string" } }
+                    } } }
+                },
+                "responses": {
+                    "204": { "description": "The book is in the collection" },
+                    "400": error_response("Malformed JSON or unknown field"),
+                    "404": error_response("Collection or book not found"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/collections/{id}/books/{book_id}": {
+            "parameters": [collection_id, {
+                "name": "book_id",
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string" }
+            }],
+            "delete": {
+                "summary": "Remove a book from a collection",
+                "responses": {
+                    "204": { "description": "Book removed from the collection" },
+                    "404": error_response("The collection does not contain the book"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/collections/{id}/order": {
+            "parameters": [collection_id],
+            "put": {
+                "summary": "Rearrange the books of a collection",
+                "description": "`book_ids` lists every book of the collection exactly once, first to last. The change is all or nothing.",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "required": ["book_ids"],
+                        "properties": { "book_ids": { "type": "array", "items": { "type": "string" } } }
+                    } } }
+                },
+                "responses": {
+                    "200": { "description": "The collection in its new order, as from GET" },
+                    "400": error_response("Malformed JSON or unknown field"),
+                    "404": error_response("Collection not found"),
+                    "422": {
+                        "description": "`book_ids` repeats a book, names one outside the collection or leaves one out",
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ValidationError" } } }
+                    },
+                    "500": error_response("Internal server error")
+                }
+            }
+        }
+    })
+}
+
+fn collection_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["id", "name", "book_count", "created_at", "updated_at"],
+        "properties": {
+            "id": { "type": "integer" },
+            "name": { "type": "string" },
+            "book_count": { "type": "integer" },
+            "created_at": { "type": "integer", "description": "Unix timestamp (seconds)" },
+            "updated_at": { "type": "integer", "description": "Unix timestamp (seconds); changes when books are added, removed or reordered" }
+        }
+    })
+}
+
+/// Bookmark and annotation routes
+fn reading_aid_paths() -> Value {
+    json!({
+        "/api/books/{id}/bookmarks": {
+            "parameters": [book_id_parameter()],
+            "get": {
+                "summary": "List a book's bookmarks",
+                "responses": {
+                    "200": {
+                        "description": "Bookmarks by chapter and position",
+                        "content": { "application/json": { "schema": { "type": "array", "items": { "$ref": "#/components/schemas/Bookmark" } } } }
+                    },
+                    "404": error_response("Book not found"),
+                    "500": error_response("Internal server error")
+                }
+            },
+            "post": {
+                "summary": "Bookmark a position in a book",
+                "description": "Unlike reading progress a book can have any number of bookmarks. The reader lists them and links each to its chapter.",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "required": ["chapter_index", "label"],
+                        "properties": {
+                            "chapter_index": { "type": "integer", "minimum": 0, "description": "Spine index of the chapter" },
+                            "scroll_fraction": { "type": "number", "minimum": 0, "maximum": 1, "default": 0, "description": "How far down the chapter" },
+                            "label": { "type": "string" }
+                        }
+                    } } }
+                },
+                "responses": {
+                    "201": {
+                        "description": "The new bookmark",
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Bookmark" } } }
+                    },
+                    "400": error_response("Malformed JSON or unknown field"),
+                    "404": error_response("Book not found"),
+                    "422": {
+                        "description": "Empty or overlong label, or a position outside 0-1",
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ValidationError" } } }
+                    },
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/books/{id}/bookmarks/{bookmark_id}": {
+            "parameters": [book_id_parameter(), {
+                "name": "bookmark_id",
+                "in": "path",
+                "required": true,
+                "schema": { "type": "integer" }
+            }],
+            "delete": {
+                "summary": "Delete a bookmark",
+                "responses": {
+                    "204": { "description": "Bookmark deleted" },
+                    "404": error_response("The book has no such bookmark"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/books/{id}/annotations": {
+            "parameters": [book_id_parameter()],
+            "get": {
+                "summary": "List a book's highlights and notes",
+                "responses": {
+                    "200": {
+                        "description": "Annotations by chapter, oldest first",
+                        "content": { "application/json": { "schema": { "type": "array", "items": { "$ref": "#/components/schemas/Annotation" } } } }
+                    },
+                    "404": error_response("Book not found"),
+                    "500": error_response("Internal server error")
+                }
+            },
+            "post": {
+                "summary": "Highlight a passage, optionally with a note",
+                "description": "The passage is anchored by its text: the reader finds `quote` in the chapter ignoring whitespace, using `prefix` and `suffix` to pick between repeats. Quotes that can no longer be found are not highlighted.",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "required": ["chapter_index", "quote"],
+                        "properties": {
+                            "chapter_index": { "type": "integer", "minimum": 0, "description": "Spine index of the chapter" },
+                            "quote": { "type": "string", "maxLength": 5000 },
+                            "prefix": { "type": "string", "maxLength": 200, "default": "", "description": "Text just before the quote" },
+                            "suffix": { "type": "string", "maxLength": 200, "default": "", "description": "Text just after the quote" },
+                            "color": { "$ref": "#/components/schemas/HighlightColor" },
+                            "note": { "type": "string", "nullable": true }
+                        }
+                    } } }
+                },
+                "responses": {
+                    "201": {
+                        "description": "The new annotation",
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Annotation" } } }
+                    },
+                    "400": error_response("Malformed JSON, unknown field or color"),
+                    "404": error_response("Book not found"),
+                    "422": {
+                        "description": "Empty quote, or an overlong quote, context or note",
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ValidationError" } } }
+                    },
+                    "500": error_response("Internal server error")
+                }
+            }
+        },
+        "/api/books/{id}/annotations/{annotation_id}": {
+            "parameters": [book_id_parameter(), {
+                "name": "annotation_id",
+                "in": "path",
+                "required": true,
+                "schema": { "type": "integer" }
+            }],
+            "put": {
+                "summary": "Change an annotation's color or note",
+                "description": "Absent fields are left unchanged; an empty note clears it. The quote cannot be changed.",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "properties": {
+                            "color": { "$ref": "#/components/schemas/HighlightColor" },
+                            "note": { "type": "string" }
+                        }
+                    } } }
+                },
+                "responses": {
+                    "200": {
+                        "description": "The updated annotation",
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Annotation" } } }
+                    },
+                    "400": error_response("Malformed JSON, unknown field or color"),
+                    "404": error_response("The book has no such annotation"),
+                    "422": {
+                        "description": "Overlong note",
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ValidationError" } } }
+                    },
+                    "500": error_response("Internal server error")
+                }
+            },
+            "delete": {
+                "summary": "Delete an annotation",
+                "responses": {
+                    "204": { "description": "Annotation deleted" },
+                    "404": error_response("The book has no such annotation"),
+                    "500": error_response("Internal server error")
+                }
+            }
+        }
+    })
+}
+
+fn error_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } }
+    })
+}
+
+fn book_id_parameter() -> Value {
+    json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "schema": { "type": "string" }
+    })
+}
+
+fn nullable(schema_type: &str) -> Value {
+    json!({ "type": schema_type, "nullable": true })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book_model::{Book, EnrichmentStatus};
+    use crate::upload_handler::UploadResponse;
+
+    fn schema_properties(name: &str) -> Vec<String> {
+        let document = openapi_document();
+        let mut keys: Vec<String> = document["components"]["schemas"][name]["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    fn serialized_keys(value: serde_json::Value) -> Vec<String> {
+        let mut keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn should_describe_every_book_field() {
+        // Given: A serialized book
+        let book = Book::new("Title".to_string(), "/book.epub".to_string());
+
+        // When/Then: The schema should list exactly its fields
+        assert_eq!(
+            schema_properties("Book"),
+            serialized_keys(serde_json::to_value(&book).unwrap())
+        );
+    }
+
+    #[test]
+    fn should_describe_every_upload_response_field() {
+        // Given: A serialized upload response
+        let response = UploadResponse {
+            id: "id".to_string(),
+            title: "Title".to_string(),
+            author: None,
+            enrichment_status: EnrichmentStatus::Pending,
+        };
+
+        // When/Then: The schema should list exactly its fields
+        assert_eq!(
+            schema_properties("UploadResponse"),
+            serialized_keys(serde_json::to_value(&response).unwrap())
+        );
+    }
+
+    #[test]
+    fn should_document_routes_and_error_statuses() {
+        // Given/When: Building the document
+        let document = openapi_document();
+        let paths = &document["paths"];
+
+        // Then: Every route should be present with its error mappings
+        for path in [
+            "/api/books",
+            "/api/books/{id}",
+            "/api/books/recommended",
+            "/api/books/{id}/file",
+            "/api/books/{id}/reset-metadata",
+            "/api/books/{id}/subjects",
+            "/api/books/{id}/subjects/{subject}",
+            "/api/books/{id}/progress/finish",
+            "/api/books/{id}/progress/reset",
+            "/api/books/{id}/bookmarks",
+            "/api/books/{id}/bookmarks/{bookmark_id}",
+            "/api/books/{id}/annotations",
+            "/api/books/{id}/annotations/{annotation_id}",
+            "/api/collections",
+            "/api/collections/{id}",
+            "/api/collections/{id}/books",
+            "/api/collections/{id}/books/{book_id}",
+            "/api/collections/{id}/order",
+            "/api/stats",
+            "/api/admin/integrity",
+            "/api/admin/enrich-missing",
+            "/api/admin/verify",
+            "/api/admin/reindex-content",
+            "/api/admin/regenerate-covers",
+            "/api/admin/rebuild-fts",
+            "/api/search",
+            "/api/search/content",
+            "/api/openapi.json",
+            "/api/version",
+            "/upload",
+            "/reader/{id}/text",
+            "/reader/{id}/chapters/{index}",
+            "/reader/{id}/stream",
+            "/covers/{id}",
+            "/covers/{id}/placeholder",
+            "/api/books/{id}/cover/color",
+            "/api/gallery/montage",
+            "/api/authors",
+        ] {
+            assert!(paths.get(path).is_some(), "missing {}", path);
+        }
+        assert!(paths["/api/books/{id}"]["get"]["responses"]["404"].is_object());
+        assert!(paths["/api/books/{id}"]["delete"]["responses"]["404"].is_object());
+        assert!(paths["/api/books/{id}/subjects"]["post"]["responses"]["409"].is_object());
+        assert!(paths["/api/books/{id}/file"]["put"]["responses"]["412"].is_object());
+        assert!(paths["/api/books/{id}/file"]["put"]["responses"]["429"].is_object());
+        for status in ["400", "408", "409", "413", "422", "429", "503"] {
+            assert!(paths["/upload"]["post"]["responses"][status].is_object());
+        }
+    }
+}
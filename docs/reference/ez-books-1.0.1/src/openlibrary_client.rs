@@ -1,14 +1,38 @@
 use crate::error::{EzBooksError, Result};
-use crate::openlibrary_types::BooksApiResponse;
+use crate::openlibrary_types::{BookData, BooksApiResponse, ReadApiResponse, ReadAvailability};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT};
 use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{info, instrument, warn};
 
 const DEFAULT_BASE_URL: &str = "https://openlibrary.org";
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// OpenLibrary asks clients to identify themselves; operators should replace the contact
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    "ez-books/",
+    env!("CARGO_PKG_VERSION"),
+    " (contact: admin@example.com)"
+);
 
+/// Extra header sent with every request, e.g. the API key of a caching proxy or mirror
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiHeader {
+    pub name: String,
+    pub value: String,
+}
+
+/// Cheap to clone: every clone shares one connection pool and configuration, so it
+/// can be handed to each request
 #[derive(Clone, Debug)]
 pub struct OpenLibraryClient {
+    shared: Arc<SharedClient>,
+}
+
+/// State owned by all clones of a client
+#[derive(Debug)]
+struct SharedClient {
     http_client: Client,
     base_url: String,
 }
@@ -19,61 +43,109 @@ impl OpenLibraryClient {
     }
 
     pub fn with_base_url(base_url: &str) -> Result<Self> {
+        Self::with_user_agent(base_url, DEFAULT_USER_AGENT)
+    }
+
+    /// Client sending `user_agent`, which should name the app and a way to reach its operator
+    pub fn with_user_agent(base_url: &str, user_agent: &str) -> Result<Self> {
+        Self::with_api_header(base_url, user_agent, None)
+    }
+
+    /// Client that also sends `api_header` with every request; its value is never logged
+    pub fn with_api_header(
+        base_url: &str,
+        user_agent: &str,
+        api_header: Option<&ApiHeader>,
+    ) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        if let Some(api_header) = api_header {
+            let (name, value) = api_header_entry(api_header)?;
+            headers.insert(name, value);
+        }
+
         let http_client = Client::builder()
             .timeout(REQUEST_TIMEOUT)
-            .user_agent("ez-books/0.1.0")
+            .user_agent(user_agent)
+            .default_headers(headers)
             .build()
             .map_err(|e| {
                 EzBooksError::OpenLibraryApi(format!("Failed to create HTTP client: {}", e))
             })?;
 
         Ok(Self {
-            http_client,
-            base_url: base_url.to_string(),
+            shared: Arc::new(SharedClient {
+                http_client,
+                base_url: base_url.to_string(),
+            }),
         })
     }
 
     #[instrument(skip(self))]
     pub async fn lookup_by_isbn(&self, isbn: &str) -> Result<Option<BooksApiResponse>> {
         info!(isbn = %isbn, "Looking up book by ISBN on OpenLibrary");
+        self.fetch_books(&format!("ISBN:{}", isbn)).await
+    }
 
-        let url = format!(
-            "{}/api/books?bibkeys=ISBN:{}&format=json&jscmd=data",
-            self.base_url, isbn
+    /// Looks up several ISBNs in one request, keyed by ISBN.
+    ///
+    /// ISBNs OpenLibrary has no data for are simply absent from the map.
+    #[instrument(skip(self), fields(count = isbns.len()))]
+    pub async fn lookup_by_isbns(&self, isbns: &[&str]) -> Result<HashMap<String, BookData>> {
+        if isbns.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        info!(
+            count = isbns.len(),
+            "Looking up books by ISBN batch on OpenLibrary"
         );
 
-        let response = self.http_client.get(&url).send().await.map_err(|e| {
-            warn!(isbn = %isbn, error = %e, "Failed to send request to OpenLibrary");
-            EzBooksError::OpenLibraryApi(format!("Request failed: {}", e))
-        })?;
+        let bibkeys = isbns
+            .iter()
+            .map(|isbn| format!("ISBN:{}", isbn))
+            .collect::<Vec<_>>()
+            .join(",");
 
-        if !response.status().is_success() {
-            warn!(
-                isbn = %isbn,
-                status = %response.status(),
-                "OpenLibrary returned non-success status"
-            );
-            return Err(EzBooksError::OpenLibraryApi(format!(
-                "API returned status: {}",
-                response.status()
-            )));
-        }
+        let books: HashMap<String, BookData> = match self.fetch_books(&bibkeys).await? {
+            Some(response) => response
+                .books
+                .into_iter()
+                .map(|(key, data)| match key.strip_prefix("ISBN:") {
+                    Some(isbn) => (isbn.to_string(), data),
+                    None => (key, data),
+                })
+                .collect(),
+            None => HashMap::new(),
+        };
 
-        let response_text = response.text().await.map_err(|e| {
-            warn!(isbn = %isbn, error = %e, "Failed to read response body");
-            EzBooksError::OpenLibraryApi(format!("Failed to read response: {}", e))
-        })?;
+        info!(
+            requested = isbns.len(),
+            found = books.len(),
+            "Retrieved OpenLibrary batch data"
+        );
+        Ok(books)
+    }
+
+    /// Fetches the Books API for comma-separated `bibkeys`; `None` when nothing matched
+    async fn fetch_books(&self, bibkeys: &str) -> Result<Option<BooksApiResponse>> {
+        let url = format!(
+            "{}/api/books?bibkeys={}&format=json&jscmd=data",
+            self.shared.base_url, bibkeys
+        );
+
+        let response_text = self.get_text(&url).await?;
 
         // OpenLibrary returns empty object {} when no book is found
         if response_text.trim() == "{}" {
-            info!(isbn = %isbn, "No book found on OpenLibrary");
+            info!(bibkeys = %bibkeys, "No book found on OpenLibrary");
             return Ok(None);
         }
 
         let books_response: BooksApiResponse =
             serde_json::from_str(&response_text).map_err(|e| {
                 warn!(
-                    isbn = %isbn,
+                    bibkeys = %bibkeys,
                     error = %e,
                     response = %response_text,
                     "Failed to parse OpenLibrary response"
@@ -82,13 +154,68 @@ impl OpenLibraryClient {
             })?;
 
         if books_response.books.is_empty() {
-            info!(isbn = %isbn, "No book found in OpenLibrary response");
+            info!(bibkeys = %bibkeys, "No book found in OpenLibrary response");
             Ok(None)
         } else {
-            info!(isbn = %isbn, "Successfully retrieved book data from OpenLibrary");
+            info!(bibkeys = %bibkeys, "Successfully retrieved book data from OpenLibrary");
             Ok(Some(books_response))
         }
     }
+
+    /// How the edition with `isbn`, or failing that another edition of its work, can be
+    /// read on Internet Archive; `None` when there is no readable scan
+    #[instrument(skip(self))]
+    pub async fn read_availability(&self, isbn: &str) -> Result<Option<ReadAvailability>> {
+        let url = format!(
+            "{}/api/volumes/brief/isbn/{}.json",
+            self.shared.base_url, isbn
+        );
+        let response_text = self.get_text(&url).await?;
+
+        // Unknown ISBNs come back as an empty list rather than an object
+        if matches!(response_text.trim(), "[]" | "{}") {
+            info!(isbn = %isbn, "No Read API record on OpenLibrary");
+            return Ok(None);
+        }
+
+        let read_response: ReadApiResponse = serde_json::from_str(&response_text).map_err(|e| {
+            warn!(isbn = %isbn, error = %e, "Failed to parse Read API response");
+            EzBooksError::OpenLibraryApi(format!("Failed to parse response: {}", e))
+        })?;
+
+        let availability = read_response.best_availability();
+        info!(
+            isbn = %isbn,
+            status = availability.as_ref().map(|a| a.status.as_str()),
+            "Retrieved OpenLibrary read availability"
+        );
+        Ok(availability)
+    }
+
+    /// Body of a successful GET of `url`
+    async fn get_text(&self, url: &str) -> Result<String> {
+        let response = self.shared.http_client.get(url).send().await.map_err(|e| {
+            warn!(url = %url, error = %e, "Failed to send request to OpenLibrary");
+            EzBooksError::OpenLibraryApi(format!("Request failed: {}", e))
+        })?;
+
+        if !response.status().is_success() {
+            warn!(
+                url = %url,
+                status = %response.status(),
+                "OpenLibrary returned non-success status"
+            );
+            return Err(EzBooksError::OpenLibraryApi(format!(
+                "API returned status: {}",
+                response.status()
+            )));
+        }
+
+        response.text().await.map_err(|e| {
+            warn!(url = %url, error = %e, "Failed to read response body");
+            EzBooksError::OpenLibraryApi(format!("Failed to read response: {}", e))
+        })
+    }
 }
 
 impl Default for OpenLibraryClient {
@@ -97,6 +224,21 @@ impl Default for OpenLibraryClient {
     }
 }
 
+/// Validates the configured header, marking its value sensitive so it is redacted in `Debug`
+fn api_header_entry(api_header: &ApiHeader) -> Result<(HeaderName, HeaderValue)> {
+    let name = HeaderName::from_bytes(api_header.name.trim().as_bytes()).map_err(|_| {
+        EzBooksError::Config(format!(
+            "Invalid OpenLibrary API header name: {}",
+            api_header.name
+        ))
+    })?;
+    let mut value = HeaderValue::from_str(api_header.value.trim()).map_err(|_| {
+        EzBooksError::Config(format!("Invalid value for OpenLibrary API header {}", name))
+    })?;
+    value.set_sensitive(true);
+    Ok((name, value))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -109,7 +251,7 @@ mod tests {
         // Then: Should succeed
         assert!(result.is_ok());
         let client = result.unwrap();
-        assert_eq!(client.base_url, DEFAULT_BASE_URL);
+        assert_eq!(client.shared.base_url, DEFAULT_BASE_URL);
     }
 
     #[test]
@@ -123,7 +265,19 @@ mod tests {
         // Then: Should succeed with custom URL
         assert!(result.is_ok());
         let client = result.unwrap();
-        assert_eq!(client.base_url, custom_url);
+        assert_eq!(client.shared.base_url, custom_url);
+    }
+
+    #[test]
+    fn should_share_state_between_clones() {
+        // Given: A client
+        let client = OpenLibraryClient::new().unwrap();
+
+        // When: Cloning it, as every request does
+        let clone = client.clone();
+
+        // Then: Both point at the same state instead of copying it
+        assert!(Arc::ptr_eq(&client.shared, &clone.shared));
     }
 
     #[test]
@@ -135,7 +289,7 @@ mod tests {
         // When: Constructing the API URL
         let url = format!(
             "{}/api/books?bibkeys=ISBN:{}&format=json&jscmd=data",
-            client.base_url, isbn
+            client.shared.base_url, isbn
         );
 
         // Then: URL should be correctly formatted
@@ -145,6 +299,198 @@ mod tests {
         assert!(url.contains("jscmd=data"));
     }
 
+    #[tokio::test]
+    async fn should_identify_itself_and_ask_for_json() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Given: A local server that records the request and answers "no book"
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0; 4096];
+            let read = stream.read(&mut buffer).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buffer[..read]).to_lowercase()
+        });
+        let client =
+            OpenLibraryClient::with_user_agent(&base_url, "ez-books-test (ops@example.org)")
+                .unwrap();
+
+        // When: Looking up an ISBN
+        let result = client.lookup_by_isbn("9780140328721").await.unwrap();
+
+        // Then: The request should carry the configured user agent and JSON accept header
+        let request = server.await.unwrap();
+        assert!(result.is_none());
+        assert!(request.contains("user-agent: ez-books-test (ops@example.org)"));
+        assert!(request.contains("accept: application/json"));
+    }
+
+    #[tokio::test]
+    async fn should_send_configured_api_header() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Given: A local proxy that records the request and a client with an API key header
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0; 4096];
+            let read = stream.read(&mut buffer).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buffer[..read]).to_lowercase()
+        });
+        let api_header = ApiHeader {
+            name: "X-Api-Key".to_string(),
+            value: "s3cret".to_string(),
+        };
+        let client =
+            OpenLibraryClient::with_api_header(&base_url, DEFAULT_USER_AGENT, Some(&api_header))
+                .unwrap();
+
+        // When: Looking up an ISBN
+        client.lookup_by_isbn("9780140328721").await.unwrap();
+
+        // Then: The proxy receives the header, but the client's debug output hides the key
+        let request = server.await.unwrap();
+        assert!(request.contains("x-api-key: s3cret"));
+        assert!(!format!("{:?}", client).contains("s3cret"));
+    }
+
+    #[test]
+    fn should_reject_malformed_api_header() {
+        // Given: Headers with a space in the name and a newline in the value
+        let bad_name = ApiHeader {
+            name: "X Api Key".to_string(),
+            value: "s3cret".to_string(),
+        };
+        let bad_value = ApiHeader {
+            name: "X-Api-Key".to_string(),
+            value: "s3cret\nX-Injected: 1".to_string(),
+        };
+
+        // When/Then: Both are configuration errors
+        for api_header in [&bad_name, &bad_value] {
+            let result = OpenLibraryClient::with_api_header(
+                DEFAULT_BASE_URL,
+                DEFAULT_USER_AGENT,
+                Some(api_header),
+            );
+            assert!(matches!(result, Err(EzBooksError::Config(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_look_up_isbn_batch_in_one_request() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Given: A local server that only knows two of three requested ISBNs
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0; 4096];
+            let read = stream.read(&mut buffer).await.unwrap();
+            let body = r#"{"ISBN:9780140328721":{"title":"Fantastic Mr Fox"},"ISBN:0140328726":{"title":"Fantastic Mr Fox (10)"}}"#;
+            stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buffer[..read]).to_string()
+        });
+        let client = OpenLibraryClient::with_base_url(&base_url).unwrap();
+
+        // When: Looking up all three at once
+        let books = client
+            .lookup_by_isbns(&["9780140328721", "0140328726", "9780000000002"])
+            .await
+            .unwrap();
+
+        // Then: One request carries every bibkey and only known ISBNs are returned
+        let request = server.await.unwrap();
+        assert!(request.contains("bibkeys=ISBN:9780140328721,ISBN:0140328726,ISBN:9780000000002"));
+        assert_eq!(books.len(), 2);
+        assert_eq!(
+            books["9780140328721"].title.as_deref(),
+            Some("Fantastic Mr Fox")
+        );
+        assert!(books.contains_key("0140328726"));
+        assert!(!books.contains_key("9780000000002"));
+    }
+
+    #[tokio::test]
+    async fn should_pick_exact_readable_scan_from_read_api() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Given: A local server listing a restricted exact scan, a lendable exact scan
+        // and a fully readable scan of another edition
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0; 4096];
+            let read = stream.read(&mut buffer).await.unwrap();
+            let body = r#"{"records":{},"items":[
+                {"match":"exact","status":"restricted","itemURL":"https://archive.org/details/restricted"},
+                {"match":"similar","status":"full access","itemURL":"https://archive.org/details/other"},
+                {"match":"exact","status":"lendable","itemURL":"https://archive.org/details/fox00dahl"}
+            ]}"#;
+            stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buffer[..read]).to_string()
+        });
+        let client = OpenLibraryClient::with_base_url(&base_url).unwrap();
+
+        // When: Asking how the ISBN can be read
+        let availability = client.read_availability("9780140328721").await.unwrap();
+
+        // Then: The lendable scan of the requested edition is chosen
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /api/volumes/brief/isbn/9780140328721.json"));
+        assert_eq!(
+            availability,
+            Some(ReadAvailability {
+                status: "lendable".to_string(),
+                url: "https://archive.org/details/fox00dahl".to_string(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn should_skip_request_for_empty_isbn_batch() {
+        // Given: A client pointing at an unreachable server
+        let client = OpenLibraryClient::with_base_url("http://127.0.0.1:9").unwrap();
+
+        // When: Looking up no ISBNs
+        let books = client.lookup_by_isbns(&[]).await.unwrap();
+
+        // Then: Nothing is requested and the map is empty
+        assert!(books.is_empty());
+    }
+
     // Note: Integration tests that make actual API calls would go in
     // tests/openlibrary_client_test.rs and should be marked with #[ignore]
     // to avoid hitting the real API during normal test runs
@@ -92,6 +92,66 @@ pub struct Cover {
     pub large: Option<String>,
 }
 
+/// Response from OpenLibrary Read API
+/// https://openlibrary.org/dev/docs/api/read
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ReadApiResponse {
+    #[serde(default)]
+    pub items: Vec<ReadItem>,
+}
+
+/// A scan on Internet Archive of the requested edition or of a similar one
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReadItem {
+    /// "full access", "lendable", "checked out" or "restricted"
+    pub status: String,
+
+    /// "exact" for the requested edition, "similar" for another edition of the work
+    #[serde(default, rename = "match")]
+    pub match_kind: Option<String>,
+
+    #[serde(default, rename = "itemURL")]
+    pub item_url: Option<String>,
+}
+
+/// How the book can be read on Internet Archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadAvailability {
+    pub status: String,
+    pub url: String,
+}
+
+impl ReadApiResponse {
+    /// The most readable scan with a web link, preferring the exact edition; restricted
+    /// scans are left out since only print-disabled readers may open them
+    pub fn best_availability(self) -> Option<ReadAvailability> {
+        let status_rank = |status: &str| match status {
+            "full access" => Some(0),
+            "lendable" => Some(1),
+            "checked out" => Some(2),
+            _ => None,
+        };
+        self.items
+            .into_iter()
+            .filter_map(|item| {
+                let rank = status_rank(&item.status)?;
+                let url = item
+                    .item_url
+                    .filter(|url| url.starts_with("https://") || url.starts_with("http://"))?;
+                let similar = item.match_kind.as_deref() != Some("exact");
+                Some((
+                    (similar, rank),
+                    ReadAvailability {
+                        status: item.status,
+                        url,
+                    },
+                ))
+            })
+            .min_by_key(|(key, _)| *key)
+            .map(|(_, availability)| availability)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,124 @@
+use crate::error::{EzBooksError, Result};
+use crate::text_extraction::strip_tags;
+use regex::Regex;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use tracing::{info, instrument};
+use zip::ZipArchive;
+
+/// Core fields found in the OPF by pattern matching rather than XML parsing
+#[derive(Debug, Default, PartialEq)]
+pub struct SalvagedMetadata {
+    pub title: Option<String>,
+    pub creator: Option<String>,
+    /// Every `identifier` element, in document order
+    pub identifiers: Vec<String>,
+}
+
+#[instrument(skip_all, fields(path = %path.as_ref().display()))]
+pub fn salvage_metadata(path: impl AsRef<Path>) -> Result<SalvagedMetadata> {
+    let file = File::open(path.as_ref())?;
+    let mut archive = ZipArchive::new(BufReader::new(file))
+        .map_err(|e| EzBooksError::EpubParse(format!("Failed to open EPUB: {}", e)))?;
+
+    let Some(opf) = read_package_document(&mut archive)? else {
+        info!("No package document found to salvage metadata from");
+        return Ok(SalvagedMetadata::default());
+    };
+
+    Ok(SalvagedMetadata {
+        title: element_texts(&opf, "title").into_iter().next(),
+        creator: element_texts(&opf, "creator").into_iter().next(),
+        identifiers: element_texts(&opf, "identifier"),
+    })
+}
+
+/// The OPF named by `META-INF/container.xml`, otherwise the first `.opf` entry
+fn read_package_document(archive: &mut ZipArchive<BufReader<File>>) -> Result<Option<String>> {
+    let mut container = String::new();
+    let rootfile = match archive.by_name("META-INF/container.xml") {
+        Ok(mut entry) => {
+            entry.read_to_string(&mut container)?;
+            first_capture(r#"(?i)\bfull-path\s*=\s*["']([^"']+)["']"#, &container)
+        }
+        Err(_) => None,
+    };
+    let rootfile = rootfile.or_else(|| {
+        archive
+            .file_names()
+            .find(|name| name.to_lowercase().ends_with(".opf"))
+            .map(str::to_string)
+    });
+    let Some(rootfile) = rootfile else {
+        return Ok(None);
+    };
+
+    let mut opf = Vec::new();
+    match archive.by_name(&rootfile) {
+        Ok(mut entry) => {
+            entry.read_to_end(&mut opf)?;
+        }
+        Err(_) => return Ok(None),
+    }
+    Ok(Some(String::from_utf8_lossy(&opf).into_owned()))
+}
+
+/// Non-empty text of every `<name>`/`<prefix:name>` element, in any case
+fn element_texts(opf: &str, name: &str) -> Vec<String> {
+    let pattern = format!(
+        r"(?is)<(?:[\w.-]+:)?{name}(?:\s[^>]*)?>(.*?)</(?:[\w.-]+:)?{name}\s*>",
+        name = name
+    );
+    let Ok(element) = Regex::new(&pattern) else {
+        return Vec::new();
+    };
+    element
+        .captures_iter(opf)
+        .filter_map(|captures| captures.get(1))
+        .map(|inner| strip_tags(&unwrap_cdata(inner.as_str())))
+        .filter(|text| !text.is_empty())
+        .collect()
+}
+
+fn unwrap_cdata(text: &str) -> String {
+    match Regex::new(r"(?s)<!\[CDATA\[(.*?)\]\]>") {
+        Ok(cdata) => cdata.replace_all(text, "$1").into_owned(),
+        Err(_) => text.to_string(),
+    }
+}
+
+fn first_capture(pattern: &str, text: &str) -> Option<String> {
+    Regex::new(pattern)
+        .ok()?
+        .captures(text)?
+        .get(1)
+        .map(|value| value.as_str().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_find_elements_regardless_of_prefix_case_and_nesting() {
+        // Given: OEB-style metadata nested in dc-metadata, with odd casing and CDATA
+        let opf = r#"<package><metadata><dc-metadata>
+            <DC:Title>  The <![CDATA[Awkward]]> Book </DC:Title>
+            <dc:Creator opf:role="aut">Ann &amp; Bob</dc:Creator>
+            <dc:Identifier id="a">urn:uuid:1</dc:Identifier>
+            <dc:identifier>ISBN 978-0-306-40615-7</dc:identifier>
+            <dc:identifier/>
+        </dc-metadata></metadata></package>"#;
+
+        // When: Matching the core elements
+        let titles = element_texts(opf, "title");
+        let creators = element_texts(opf, "creator");
+        let identifiers = element_texts(opf, "identifier");
+
+        // Then: Their text is found, trimmed and decoded
+        assert_eq!(titles, vec!["The Awkward Book"]);
+        assert_eq!(creators, vec!["Ann & Bob"]);
+        assert_eq!(identifiers, vec!["urn:uuid:1", "ISBN 978-0-306-40615-7"]);
+    }
+}
@@ -0,0 +1,331 @@
+use crate::book_model::{current_timestamp, Book};
+use crate::book_repository;
+use crate::database_connection::DatabasePool;
+use crate::error::Result;
+use serde::Serialize;
+use sqlx::Row;
+use std::collections::HashSet;
+use tracing::{info, instrument};
+
+/// When the reader first and last opened a book, and when they marked it finished
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct ReadingProgress {
+    pub opened_at: i64,
+    pub updated_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+/// Records that the reader opened a book, keeping the first open time
+#[instrument(skip(pool))]
+pub async fn mark_opened(pool: &DatabasePool, book_id: &str) -> Result<()> {
+    let now = current_timestamp();
+
+    sqlx::query(
+        r#"
+        INSERT INTO reading_progress (book_id, opened_at, updated_at)
+        VALUES (?, ?, ?)
+        ON CONFLICT(book_id) DO UPDATE SET updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(book_id)
+    .bind(now)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Records that the reader finished a book. The progress is kept, so reopening the book
+/// does not start it over; a book never opened counts as opened now.
+#[instrument(skip(pool))]
+pub async fn mark_finished(pool: &DatabasePool, book_id: &str) -> Result<ReadingProgress> {
+    let now = current_timestamp();
+
+    sqlx::query(
+        r#"
+        INSERT INTO reading_progress (book_id, opened_at, updated_at, finished_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT(book_id) DO UPDATE SET
+            updated_at = excluded.updated_at,
+            finished_at = excluded.finished_at
+        "#,
+    )
+    .bind(book_id)
+    .bind(now)
+    .bind(now)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    let progress = sqlx::query_as::<_, ReadingProgress>(
+        "SELECT opened_at, updated_at, finished_at FROM reading_progress WHERE book_id = ?",
+    )
+    .bind(book_id)
+    .fetch_one(pool)
+    .await?;
+
+    info!(book_id = %book_id, "Marked book finished");
+    Ok(progress)
+}
+
+/// Forgets the reading progress of a book, which makes it unread again
+#[instrument(skip(pool))]
+pub async fn reset(pool: &DatabasePool, book_id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM reading_progress WHERE book_id = ?")
+        .bind(book_id)
+        .execute(pool)
+        .await?;
+
+    info!(book_id = %book_id, "Reset reading progress");
+    Ok(())
+}
+
+/// Reading progress of one book, `None` while it is unread
+#[instrument(skip(pool))]
+pub async fn find_by_book_id(
+    pool: &DatabasePool,
+    book_id: &str,
+) -> Result<Option<ReadingProgress>> {
+    let progress = sqlx::query_as::<_, ReadingProgress>(
+        "SELECT opened_at, updated_at, finished_at FROM reading_progress WHERE book_id = ?",
+    )
+    .bind(book_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(progress)
+}
+
+/// Ids of every book with reading progress
+#[instrument(skip(pool))]
+pub async fn find_started_book_ids(pool: &DatabasePool) -> Result<HashSet<String>> {
+    let ids = sqlx::query("SELECT book_id FROM reading_progress")
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| row.get("book_id"))
+        .collect();
+
+    Ok(ids)
+}
+
+/// Ids of every book marked finished
+#[instrument(skip(pool))]
+pub async fn find_finished_book_ids(pool: &DatabasePool) -> Result<HashSet<String>> {
+    let ids = sqlx::query("SELECT book_id FROM reading_progress WHERE finished_at IS NOT NULL")
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| row.get("book_id"))
+        .collect();
+
+    Ok(ids)
+}
+
+/// Recently read books whose subjects seed recommendations
+const RECOMMENDATION_SEED_BOOKS: i64 = 5;
+
+/// Unread books sharing subjects with the most recently read books, most shared subjects
+/// first and newest first among ties. Empty when nothing has been read yet.
+#[instrument(skip(pool))]
+pub async fn find_recommended(pool: &DatabasePool, limit: u32) -> Result<Vec<Book>> {
+    let books = sqlx::query_as::<_, Book>(
+        r#"
+        SELECT books.*, COUNT(DISTINCT lower(book_subjects.subject)) AS overlap FROM books
+        JOIN book_subjects ON book_subjects.book_id = books.id
+        WHERE lower(book_subjects.subject) IN (
+            SELECT lower(subject) FROM book_subjects WHERE book_id IN (
+                SELECT book_id FROM reading_progress ORDER BY updated_at DESC LIMIT ?
+            )
+        )
+        AND books.id NOT IN (SELECT book_id FROM reading_progress)
+        GROUP BY books.id
+        ORDER BY overlap DESC, books.created_at DESC, books.rowid DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(RECOMMENDATION_SEED_BOOKS)
+    .bind(i64::from(limit))
+    .fetch_all(pool)
+    .await?;
+
+    info!(count = books.len(), "Found recommended books");
+    Ok(books)
+}
+
+/// The oldest-added book in `subject` without reading progress, optionally limited to a language
+#[instrument(skip(pool))]
+pub async fn find_next_unread(
+    pool: &DatabasePool,
+    subject: &str,
+    language: Option<&str>,
+) -> Result<Option<Book>> {
+    let started = find_started_book_ids(pool).await?;
+    let next = book_repository::find_books_by_subject(pool, subject)
+        .await?
+        .into_iter()
+        .filter(|book| !started.contains(&book.id))
+        .find(|book| match language {
+            Some(language) => book
+                .language
+                .as_deref()
+                .map_or(false, |lang| lang.eq_ignore_ascii_case(language)),
+            None => true,
+        });
+
+    info!(subject = %subject, found = next.is_some(), "Looked up next unread book");
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::setup_test_db;
+
+    async fn insert_book(pool: &DatabasePool, title: &str, language: &str, subject: &str) -> Book {
+        let mut book = Book::new(title.to_string(), format!("/{}.epub", title));
+        book.language = Some(language.to_string());
+        book_repository::insert(pool, &book).await.unwrap();
+        book_repository::insert_subject(pool, &book.id, subject)
+            .await
+            .unwrap();
+        book
+    }
+
+    #[tokio::test]
+    async fn should_return_oldest_unread_book_in_subject() {
+        // Given: Three fantasy books, the oldest already opened
+        let (pool, _temp_dir) = setup_test_db().await;
+        let opened = insert_book(&pool, "Opened", "en", "Fantasy").await;
+        let next = insert_book(&pool, "Next", "en", "Fantasy").await;
+        insert_book(&pool, "Later", "en", "Fantasy").await;
+        insert_book(&pool, "Elsewhere", "en", "History").await;
+        mark_opened(&pool, &opened.id).await.unwrap();
+
+        // When: Asking for the next unread fantasy book
+        let found = find_next_unread(&pool, "Fantasy", None).await.unwrap();
+
+        // Then: The oldest book without progress is returned
+        assert_eq!(found.map(|book| book.id), Some(next.id));
+    }
+
+    #[tokio::test]
+    async fn should_filter_next_unread_by_language() {
+        // Given: An English and a French book in the same subject
+        let (pool, _temp_dir) = setup_test_db().await;
+        insert_book(&pool, "English", "en", "Poetry").await;
+        let french = insert_book(&pool, "French", "fr", "Poetry").await;
+
+        // When: Asking for the next unread French book
+        let found = find_next_unread(&pool, "Poetry", Some("FR")).await.unwrap();
+
+        // Then: The French book is returned
+        assert_eq!(found.map(|book| book.id), Some(french.id));
+    }
+
+    #[tokio::test]
+    async fn should_return_none_when_subject_is_fully_read() {
+        // Given: A subject whose only book has been opened twice
+        let (pool, _temp_dir) = setup_test_db().await;
+        let book = insert_book(&pool, "Only", "en", "Essays").await;
+        mark_opened(&pool, &book.id).await.unwrap();
+        mark_opened(&pool, &book.id).await.unwrap();
+
+        // When: Asking for the next unread book
+        let found = find_next_unread(&pool, "Essays", None).await.unwrap();
+
+        // Then: Nothing remains
+        assert!(found.is_none());
+        assert_eq!(find_started_book_ids(&pool).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_find_progress_only_for_opened_books() {
+        // Given: One opened and one unread book
+        let (pool, _temp_dir) = setup_test_db().await;
+        let opened = insert_book(&pool, "Opened", "en", "Fantasy").await;
+        let unread = insert_book(&pool, "Unread", "en", "Fantasy").await;
+        mark_opened(&pool, &opened.id).await.unwrap();
+
+        // When: Looking up their progress
+        let progress = find_by_book_id(&pool, &opened.id).await.unwrap();
+        let none = find_by_book_id(&pool, &unread.id).await.unwrap();
+
+        // Then: Only the opened book has timestamps
+        let progress = progress.unwrap();
+        assert!(progress.opened_at > 0);
+        assert!(progress.updated_at >= progress.opened_at);
+        assert!(none.is_none());
+    }
+
+    #[tokio::test]
+    async fn should_keep_progress_when_finishing_and_forget_it_on_reset() {
+        // Given: An opened book and one never opened
+        let (pool, _temp_dir) = setup_test_db().await;
+        let opened = insert_book(&pool, "Opened", "en", "Fantasy").await;
+        let unopened = insert_book(&pool, "Unopened", "en", "Fantasy").await;
+        mark_opened(&pool, &opened.id).await.unwrap();
+        let before = find_by_book_id(&pool, &opened.id).await.unwrap().unwrap();
+
+        // When: Finishing both, reopening the first, then resetting the second
+        let finished = mark_finished(&pool, &opened.id).await.unwrap();
+        mark_finished(&pool, &unopened.id).await.unwrap();
+        mark_opened(&pool, &opened.id).await.unwrap();
+        let finished_ids = find_finished_book_ids(&pool).await.unwrap();
+        reset(&pool, &unopened.id).await.unwrap();
+
+        // Then: Finishing keeps the first open and survives reopening; reset makes it unread
+        assert_eq!(finished.opened_at, before.opened_at);
+        assert!(finished.finished_at.is_some());
+        let reopened = find_by_book_id(&pool, &opened.id).await.unwrap().unwrap();
+        assert_eq!(reopened.finished_at, finished.finished_at);
+        assert_eq!(finished_ids.len(), 2);
+        assert!(find_by_book_id(&pool, &unopened.id)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            find_next_unread(&pool, "Fantasy", None)
+                .await
+                .unwrap()
+                .map(|book| book.id),
+            Some(unopened.id)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_recommend_unread_books_by_subject_overlap() {
+        // Given: A read book about fantasy and dragons, and unread books sharing some subjects
+        let (pool, _temp_dir) = setup_test_db().await;
+        let read = insert_book(&pool, "Read", "en", "Fantasy").await;
+        book_repository::insert_subject(&pool, &read.id, "Dragons")
+            .await
+            .unwrap();
+        mark_opened(&pool, &read.id).await.unwrap();
+        let one = insert_book(&pool, "One", "en", "fantasy").await;
+        let both = insert_book(&pool, "Both", "en", "Fantasy").await;
+        book_repository::insert_subject(&pool, &both.id, "Dragons")
+            .await
+            .unwrap();
+        insert_book(&pool, "Unrelated", "en", "Cooking").await;
+
+        // When: Asking for recommendations
+        let recommended = find_recommended(&pool, 10).await.unwrap();
+
+        // Then: Books sharing more subjects come first; read and unrelated books are left out
+        let titles: Vec<&str> = recommended.iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(titles, vec![both.title.as_str(), one.title.as_str()]);
+        assert_eq!(find_recommended(&pool, 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_recommend_nothing_before_anything_is_read() {
+        // Given: Unread books only
+        let (pool, _temp_dir) = setup_test_db().await;
+        insert_book(&pool, "Unread", "en", "Fantasy").await;
+
+        // When/Then: There is no signal to recommend from
+        assert!(find_recommended(&pool, 10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// How often buckets of clients that went quiet are dropped
+pub const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Requests allowed per client, from `RATE_LIMIT_PER_MINUTE` and `RATE_LIMIT_BURST`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_minute: u32,
+    /// Requests a client may make at once after being idle
+    pub burst: u32,
+}
+
+/// Shared limiter; clones use the same buckets
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: Arc<Mutex<HashMap<IpAddr, Bucket>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Takes a token for `client`, or returns how long until one is available
+    pub fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let burst = f64::from(self.limit.burst);
+        let mut buckets = self.lock();
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: burst,
+            refilled_at: now,
+        });
+
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.refilled_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let wait = (1.0 - bucket.tokens) / self.tokens_per_second();
+        debug!(client = %client, wait_secs = wait, "Rate limit exceeded");
+        Err(Duration::from_secs_f64(wait))
+    }
+
+    /// Drops the buckets that have refilled completely; they behave like new ones
+    pub fn remove_idle(&self) -> usize {
+        self.remove_idle_at(Instant::now())
+    }
+
+    fn remove_idle_at(&self, now: Instant) -> usize {
+        let burst = f64::from(self.limit.burst);
+        let mut buckets = self.lock();
+        let before = buckets.len();
+        buckets.retain(|_, bucket| self.refilled(bucket, now) < burst);
+        before - buckets.len()
+    }
+
+    /// Runs `remove_idle` every `CLEANUP_INTERVAL` for as long as the server runs
+    pub fn spawn_cleanup(&self) {
+        let limiter = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                let removed = limiter.remove_idle();
+                if removed > 0 {
+                    info!(removed, "Dropped idle rate limit buckets");
+                }
+            }
+        });
+    }
+
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        (bucket.tokens + elapsed.as_secs_f64() * self.tokens_per_second())
+            .min(f64::from(self.limit.burst))
+    }
+
+    fn tokens_per_second(&self) -> f64 {
+        f64::from(self.limit.per_minute) / 60.0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<IpAddr, Bucket>> {
+        // A panic while holding the lock cannot leave a bucket half-updated
+        self.buckets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 1));
+    const OTHER: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 2));
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimit {
+            per_minute: 6,
+            burst: 2,
+        })
+    }
+
+    #[test]
+    fn should_allow_burst_then_refuse_until_a_token_refills() {
+        // Given: A limit of 6 a minute with a burst of 2
+        let limiter = limiter();
+        let start = Instant::now();
+
+        // When: A client makes three requests at once, then one more 10 seconds later
+        let first = limiter.check_at(CLIENT, start);
+        let second = limiter.check_at(CLIENT, start);
+        let third = limiter.check_at(CLIENT, start);
+        let later = limiter.check_at(CLIENT, start + Duration::from_secs(10));
+
+        // Then: The burst passes, the third waits for the next token, other clients are unaffected
+        assert!(first.is_ok() && second.is_ok());
+        assert_eq!(third, Err(Duration::from_secs(10)));
+        assert!(later.is_ok());
+        assert!(limiter.check_at(OTHER, start).is_ok());
+    }
+
+    #[test]
+    fn should_drop_only_buckets_that_refilled() {
+        // Given: One client that used its burst long ago, and one that just did
+        let limiter = limiter();
+        let start = Instant::now();
+        limiter.check_at(CLIENT, start).unwrap();
+        let now = start + Duration::from_secs(60);
+        limiter.check_at(OTHER, now).unwrap();
+
+        // When: Cleaning up
+        let removed = limiter.remove_idle_at(now);
+
+        // Then: Only the idle client's bucket is gone
+        assert_eq!(removed, 1);
+        assert_eq!(limiter.lock().len(), 1);
+        assert!(limiter.lock().contains_key(&OTHER));
+    }
+}
@@ -1,74 +1,476 @@
-use crate::book_model::Book;
+use crate::annotation_anchor::highlight_annotations;
+use crate::annotation_repository::Annotation;
+use crate::book_model::{AdjacentBooks, Book};
+use crate::book_query::{BookSort, ReaderMode};
+use crate::bookmark_repository::Bookmark;
+use crate::chapter_encoding::current_chapter_str;
+use crate::config::Config;
 use crate::error::{EzBooksError, Result};
-use crate::html_templates::{escape_html, html_footer, html_header};
-use epub::doc::EpubDoc;
+use crate::html_templates::{escape_html, html_footer, html_header_with_body_attributes};
+use crate::text_extraction::clean_hyphenation;
+use crate::ui_text::{Lang, UiText};
+use epub::doc::{EpubDoc, NavPoint};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
 use std::path::Path;
 use tracing::{info, instrument, warn};
 
-pub fn render_reader(book: &Book, epub_content: String) -> String {
-    let mut html = html_header(&book.title, "reader.css");
+/// Reader limits from the configuration
+#[derive(Debug, Clone, Copy)]
+pub struct ReaderSettings {
+    /// Chapters stop being inlined once the page would grow past this many bytes;
+    /// the rest is loaded on demand. 0 inlines the whole book.
+    pub max_inline_bytes: usize,
+    /// Rejoin words hyphenated across line breaks; soft hyphens are always removed
+    pub rejoin_hyphenated_words: bool,
+}
+
+impl ReaderSettings {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_inline_bytes: config.reader_max_inline_bytes,
+            rejoin_hyphenated_words: config.reader_rejoin_hyphenated_words,
+        }
+    }
+}
 
-    html.push_str(&render_nav(&book.title));
-    html.push_str(&render_content(&epub_content));
-    html.push_str(&html_footer(None));
+/// Sanitized chapters inlined into the reader page, and where to resume when capped
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReaderContent {
+    pub html: String,
+    /// Spine index of the first chapter left out, when the size cap was reached
+    pub next_chapter: Option<usize>,
+    pub chapter_count: usize,
+    /// Spine index and byte offset in `html` of each inlined chapter
+    pub chapter_starts: Vec<(usize, usize)>,
+}
+
+impl ReaderContent {
+    /// Bytes of chapter HTML emitted into the page
+    pub fn bytes_emitted(&self) -> usize {
+        self.html.len()
+    }
+}
+
+/// The reader's own bookmarks and annotations of the book shown
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReaderMarks<'a> {
+    pub bookmarks: &'a [Bookmark],
+    pub annotations: &'a [Annotation],
+}
+
+/// Renders the reader page; paged mode lays the article out in CSS columns and adds
+/// page controls, and truncated content gets a control loading the remaining chapters.
+/// Both need the small `reader.js` script, as do the bookmarks of `marks`, listed in the
+/// nav. The nav also links to the `adjacent` books, keeping the view mode and sort. The
+/// annotations of `marks` are highlighted in the inlined chapters. Controls are labelled
+/// in `lang` and links start with `base_path`.
+pub fn render_reader(
+    book: &Book,
+    content: &ReaderContent,
+    mode: ReaderMode,
+    adjacent: &AdjacentBooks,
+    marks: ReaderMarks,
+    lang: Lang,
+    base_path: &str,
+) -> String {
+    let body_attributes = format!(r#" data-reader-mode="{}""#, mode.as_str());
+    let mut html = html_header_with_body_attributes(
+        &book.title,
+        "reader.css",
+        &body_attributes,
+        lang,
+        base_path,
+    );
+
+    html.push_str(&render_nav(book, mode, adjacent, lang, base_path));
+    html.push_str(&render_bookmarks(
+        book,
+        content,
+        marks.bookmarks,
+        lang,
+        base_path,
+    ));
+    html.push_str(&render_content(
+        book,
+        content,
+        mode,
+        marks.annotations,
+        lang,
+        base_path,
+    ));
+    let needs_script =
+        mode == ReaderMode::Paged || content.next_chapter.is_some() || !marks.bookmarks.is_empty();
+    html.push_str(&html_footer(needs_script.then_some("reader.js"), base_path));
 
     html
 }
 
-fn render_nav(title: &str) -> String {
+fn render_nav(
+    book: &Book,
+    mode: ReaderMode,
+    adjacent: &AdjacentBooks,
+    lang: Lang,
+    base_path: &str,
+) -> String {
+    let controls = match mode {
+        ReaderMode::Scroll => format!(
+            r#"<a class="mode-toggle" href="{}">{}</a>"#,
+            reader_url(&book.id, ReaderMode::Paged, adjacent.sort, base_path),
+            lang.text(UiText::PagedView)
+        ),
+        ReaderMode::Paged => format!(
+            r#"<div class="page-controls">
+        <button type="button" class="page-prev" aria-label="{}">&lsaquo;</button>
+        <span class="page-indicator" aria-live="polite"></span>
+        <button type="button" class="page-next" aria-label="{}">&rsaquo;</button>
+    </div>
+    <a class="mode-toggle" href="{}">{}</a>"#,
+            lang.text(UiText::PreviousPage),
+            lang.text(UiText::NextPage),
+            reader_url(&book.id, ReaderMode::Scroll, adjacent.sort, base_path),
+            lang.text(UiText::ScrollView)
+        ),
+    };
+    let previous = adjacent.previous.as_deref().map(|id| {
+        format!(
+            r#"<a class="book-prev" rel="prev" href="{}">&lsaquo; {}</a>"#,
+            reader_url(id, mode, adjacent.sort, base_path),
+            lang.text(UiText::PreviousBook)
+        )
+    });
+    let next = adjacent.next.as_deref().map(|id| {
+        format!(
+            r#"<a class="book-next" rel="next" href="{}">{} &rsaquo;</a>"#,
+            reader_url(id, mode, adjacent.sort, base_path),
+            lang.text(UiText::NextBook)
+        )
+    });
+    let books = match (previous, next) {
+        (None, None) => String::new(),
+        (previous, next) => format!(
+            r#"
+    <div class="book-nav">{}{}</div>"#,
+            previous.unwrap_or_default(),
+            next.unwrap_or_default()
+        ),
+    };
+
     format!(
         r#"<nav>
-    <a href="/">&larr; Back to Library</a>
+    <a href="{}/">&larr; {}</a>
     <h2>{}</h2>
+    <p class="author">{}</p>
+    {}{}
 </nav>"#,
-        escape_html(title)
+        base_path,
+        lang.text(UiText::BackToLibrary),
+        escape_html(&book.title),
+        escape_html(book.display_author(lang)),
+        controls,
+        books
+    )
+}
+
+/// Bookmarks in inlined chapters jump to the chapter anchor, scrolled by `reader.js`;
+/// the others open their chapter on its own
+fn render_bookmarks(
+    book: &Book,
+    content: &ReaderContent,
+    bookmarks: &[Bookmark],
+    lang: Lang,
+    base_path: &str,
+) -> String {
+    if bookmarks.is_empty() {
+        return String::new();
+    }
+    let inlined = content.next_chapter.unwrap_or(content.chapter_count);
+    let items: String = bookmarks
+        .iter()
+        .map(|bookmark| {
+            let chapter = bookmark.chapter_index as usize;
+            let href = if chapter < inlined {
+                format!("#{}", chapter_anchor_id(chapter))
+            } else {
+                format!(
+                    "{}/reader/{}/chapters/{}",
+                    base_path,
+                    escape_html(&book.id),
+                    chapter
+                )
+            };
+            format!(
+                r#"
+        <li><a href="{}" data-chapter="{}" data-scroll-fraction="{}">{}</a></li>"#,
+                href,
+                chapter,
+                bookmark.scroll_fraction,
+                escape_html(&bookmark.label)
+            )
+        })
+        .collect();
+
+    format!(
+        r#"
+<details class="bookmarks">
+    <summary>{}</summary>
+    <ol>{}
+    </ol>
+</details>"#,
+        lang.text(UiText::Bookmarks)
+            .replace("{count}", &bookmarks.len().to_string()),
+        items
     )
 }
 
-fn render_content(content: &str) -> String {
+/// Reader page URL; default mode and sort are left out of the query string
+fn reader_url(id: &str, mode: ReaderMode, sort: BookSort, base_path: &str) -> String {
+    let mut params = Vec::new();
+    if mode != ReaderMode::default() {
+        params.push(format!("mode={}", mode.as_str()));
+    }
+    if sort != BookSort::default() {
+        params.push(format!("sort={}", sort.as_str()));
+    }
+    let query = if params.is_empty() {
+        String::new()
+    } else {
+        format!("?{}", params.join("&amp;"))
+    };
+    format!("{}/reader/{}{}", base_path, escape_html(id), query)
+}
+
+fn render_content(
+    book: &Book,
+    content: &ReaderContent,
+    mode: ReaderMode,
+    annotations: &[Annotation],
+    lang: Lang,
+    base_path: &str,
+) -> String {
     format!(
-        r#"<main>
+        r#"<main class="reader-{}">
     <article>
 {}
     </article>
-</main>"#,
-        content
+{}</main>"#,
+        mode.as_str(),
+        annotated_html(content, annotations),
+        render_load_more(book, content, lang, base_path)
     )
 }
 
+/// Content with an empty anchor starting each chapter, for bookmark links, and the
+/// chapter's annotations highlighted
+fn annotated_html(content: &ReaderContent, annotations: &[Annotation]) -> String {
+    let html = &content.html;
+    let first = content
+        .chapter_starts
+        .first()
+        .map_or(html.len(), |(_, offset)| *offset);
+    let mut annotated = String::with_capacity(html.len());
+    annotated.push_str(html.get(..first).unwrap_or_default());
+
+    for (i, &(chapter, offset)) in content.chapter_starts.iter().enumerate() {
+        let end = content
+            .chapter_starts
+            .get(i + 1)
+            .map_or(html.len(), |(_, next)| *next);
+        let Some(chapter_html) = html.get(offset..end) else {
+            continue;
+        };
+        annotated.push_str(&format!(
+            r#"<a id="{}" class="chapter-anchor"></a>"#,
+            chapter_anchor_id(chapter)
+        ));
+        annotated.push_str(&highlight_annotations(chapter_html, chapter, annotations));
+    }
+    annotated
+}
+
+/// Without script the link opens the next chapter on its own
+fn render_load_more(book: &Book, content: &ReaderContent, lang: Lang, base_path: &str) -> String {
+    let Some(next_chapter) = content.next_chapter else {
+        return String::new();
+    };
+    let id = escape_html(&book.id);
+
+    format!(
+        r#"    <div class="load-more" data-book-id="{id}" data-next-chapter="{next}" data-chapter-count="{count}" data-bytes-emitted="{bytes}">
+        <a href="{base}/reader/{id}/chapters/{next}">{label}</a>
+    </div>
+"#,
+        base = base_path,
+        id = id,
+        next = next_chapter,
+        count = content.chapter_count,
+        bytes = content.bytes_emitted(),
+        label = lang.text(UiText::LoadRemainingChapters).replace(
+            "{count}",
+            &(content.chapter_count - next_chapter).to_string()
+        )
+    )
+}
+
+/// Sanitized chapters in reading order, stopping before the chapter that would take the
+/// content past `max_inline_bytes` (0 for no limit). The first chapter is always included.
+/// Hyphenation is cleaned up as in `clean_hyphenation`.
 #[instrument(skip_all, fields(path = %epub_path.as_ref().display()))]
-pub fn extract_and_sanitize_content(epub_path: impl AsRef<Path>) -> Result<String> {
+pub fn extract_and_sanitize_content(
+    epub_path: impl AsRef<Path>,
+    max_inline_bytes: usize,
+    rejoin_hyphenated_words: bool,
+) -> Result<ReaderContent> {
     let path = epub_path.as_ref();
     info!(path = %path.display(), "Extracting content from EPUB");
 
-    let mut doc = EpubDoc::new(path).map_err(|e| {
-        warn!(path = %path.display(), error = %e, "Failed to open EPUB for reading");
-        EzBooksError::EpubParse(format!("Failed to open EPUB: {}", e))
-    })?;
-
+    let mut doc = open_epub(path)?;
     let mut all_content = String::new();
     let spine_len = doc.spine.len();
+    let mut next_chapter = None;
+    let mut chapter_starts = Vec::new();
 
     info!(chapters = spine_len, "Extracting chapters");
 
     // Iterate through all chapters in the spine (reading order)
     for i in 0..spine_len {
-        doc.set_current_chapter(i);
-
-        match doc.get_current_str() {
-            Some((content, _mime)) => {
-                let sanitized = sanitize_html(&content);
-                all_content.push_str(&sanitized);
-                all_content.push_str("\n<hr>\n");
-            }
-            None => {
-                warn!(chapter = i, "Failed to read chapter");
-            }
+        let Some(sanitized) = sanitized_chapter(&mut doc, i, rejoin_hyphenated_words) else {
+            continue;
+        };
+
+        let size = sanitized.len() + CHAPTER_SEPARATOR.len();
+        if max_inline_bytes > 0
+            && !all_content.is_empty()
+            && all_content.len() + size > max_inline_bytes
+        {
+            next_chapter = Some(i);
+            break;
         }
+        chapter_starts.push((i, all_content.len()));
+        all_content.push_str(&sanitized);
+        all_content.push_str(CHAPTER_SEPARATOR);
     }
 
-    info!(size = all_content.len(), "Content extraction completed");
-    Ok(all_content)
+    info!(
+        size = all_content.len(),
+        next_chapter = ?next_chapter,
+        "Content extraction completed"
+    );
+    Ok(ReaderContent {
+        html: all_content,
+        next_chapter,
+        chapter_count: spine_len,
+        chapter_starts,
+    })
+}
+
+/// Sanitized HTML of the spine item at `index` and the spine length, or `None` past the end
+#[instrument(skip_all, fields(path = %epub_path.as_ref().display(), index))]
+pub fn extract_chapter_html(
+    epub_path: impl AsRef<Path>,
+    index: usize,
+    rejoin_hyphenated_words: bool,
+) -> Result<Option<(String, usize)>> {
+    let mut doc = open_epub(epub_path.as_ref())?;
+    let spine_len = doc.spine.len();
+    if index >= spine_len {
+        return Ok(None);
+    }
+
+    // An unreadable item is served empty so clients can still move past it
+    let html = sanitized_chapter(&mut doc, index, rejoin_hyphenated_words).unwrap_or_default();
+    Ok(Some((html, spine_len)))
+}
+
+/// One sanitized chapter, as streamed by `/reader/{id}/stream`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChapterHtml {
+    /// Spine index, as in `/reader/{id}/chapters/{index}`
+    pub index: usize,
+    /// Title of the chapter in the table of contents; `None` for chapters not listed there
+    pub label: Option<String>,
+    pub html: String,
+}
+
+/// Passes each readable chapter to `visit` in reading order as soon as it is sanitized,
+/// stopping early when `visit` returns `false`. Returns how many were visited.
+#[instrument(skip_all, fields(path = %epub_path.as_ref().display()))]
+pub fn for_each_chapter_html(
+    epub_path: impl AsRef<Path>,
+    rejoin_hyphenated_words: bool,
+    mut visit: impl FnMut(ChapterHtml) -> bool,
+) -> Result<usize> {
+    let mut doc = open_epub(epub_path.as_ref())?;
+    let labels = toc_labels(&doc.toc);
+
+    let mut visited = 0;
+    for index in 0..doc.spine.len() {
+        let Some(html) = sanitized_chapter(&mut doc, index, rejoin_hyphenated_words) else {
+            continue;
+        };
+        let label = doc
+            .get_current_path()
+            .and_then(|path| labels.get(&path.to_string_lossy().into_owned()).cloned());
+        visited += 1;
+        if !visit(ChapterHtml { index, label, html }) {
+            info!(chapters = visited, "Chapter extraction stopped early");
+            return Ok(visited);
+        }
+    }
+
+    info!(chapters = visited, "Chapter extraction completed");
+    Ok(visited)
+}
+
+/// Table of contents labels by chapter path; the first entry pointing into a chapter wins
+fn toc_labels(toc: &[NavPoint]) -> HashMap<String, String> {
+    let mut labels = HashMap::new();
+    let mut pending: Vec<&NavPoint> = toc.iter().rev().collect();
+    while let Some(point) = pending.pop() {
+        let content = point.content.to_string_lossy();
+        let path = content.split('#').next().unwrap_or_default().to_string();
+        let label = point.label.trim();
+        if !label.is_empty() {
+            labels.entry(path).or_insert_with(|| label.to_string());
+        }
+        pending.extend(point.children.iter().rev());
+    }
+    labels
+}
+
+const CHAPTER_SEPARATOR: &str = "\n<hr>\n";
+
+/// Id of the anchor starting an inlined chapter; `reader.js` adds the same anchors to
+/// chapters it loads
+fn chapter_anchor_id(index: usize) -> String {
+    format!("chapter-{}", index)
+}
+
+fn open_epub(path: &Path) -> Result<EpubDoc<BufReader<File>>> {
+    EpubDoc::new(path).map_err(|e| {
+        warn!(path = %path.display(), error = %e, "Failed to open EPUB for reading");
+        EzBooksError::EpubParse(format!("Failed to open EPUB: {}", e))
+    })
+}
+
+fn sanitized_chapter(
+    doc: &mut EpubDoc<BufReader<File>>,
+    index: usize,
+    rejoin_hyphenated_words: bool,
+) -> Option<String> {
+    doc.set_current_chapter(index);
+    match current_chapter_str(doc) {
+        Some(content) => Some(sanitize_html(&clean_hyphenation(
+            &content,
+            rejoin_hyphenated_words,
+        ))),
+        None => {
+            warn!(chapter = index, "Failed to read chapter");
+            None
+        }
+    }
 }
 
 fn sanitize_html(html: &str) -> String {
@@ -79,11 +481,21 @@ fn sanitize_html(html: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_epub::TestEpub;
 
     fn create_test_book() -> Book {
         Book::new("Test Book".to_string(), "/path/to/book.epub".to_string())
     }
 
+    fn inline(html: &str) -> ReaderContent {
+        ReaderContent {
+            html: html.to_string(),
+            next_chapter: None,
+            chapter_count: 1,
+            chapter_starts: Vec::new(),
+        }
+    }
+
     #[test]
     fn should_render_complete_reader_page() {
         // Given: A book and content
@@ -91,7 +503,15 @@ mod tests {
         let content = "<p>Test content</p>".to_string();
 
         // When: Rendering reader
-        let html = render_reader(&book, content);
+        let html = render_reader(
+            &book,
+            &inline(&content),
+            ReaderMode::Scroll,
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
 
         // Then: Should contain all necessary elements
         assert!(html.contains("<!DOCTYPE html>"));
@@ -110,12 +530,63 @@ mod tests {
         let content = String::new();
 
         // When: Rendering reader
-        let html = render_reader(&book, content);
+        let html = render_reader(
+            &book,
+            &inline(&content),
+            ReaderMode::Scroll,
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
 
         // Then: Should include back link
         assert!(html.contains(r#"<a href="/">&larr; Back to Library</a>"#));
     }
 
+    #[test]
+    fn should_link_adjacent_books_keeping_mode_and_sort() {
+        // Given: A paged book in the size order with a following book only
+        let book = create_test_book();
+        let adjacent = AdjacentBooks {
+            sort: BookSort::Size,
+            previous: None,
+            next: Some("next-id".to_string()),
+        };
+
+        // When: Rendering reader
+        let html = render_reader(
+            &book,
+            &inline(""),
+            ReaderMode::Paged,
+            &adjacent,
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
+
+        // Then: Only the next link shows, keeping the view and the sort
+        assert!(html.contains(r#"href="/reader/next-id?mode=paged&amp;sort=size">Next book"#));
+        assert!(!html.contains("Previous book"));
+    }
+
+    #[test]
+    fn should_omit_book_links_without_neighbours() {
+        // Given/When: The only book in the library
+        let html = render_reader(
+            &create_test_book(),
+            &inline(""),
+            ReaderMode::Scroll,
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
+
+        // Then: No book navigation is rendered
+        assert!(!html.contains("book-nav"));
+    }
+
     #[test]
     fn should_display_book_title_in_nav() {
         // Given: A book with specific title
@@ -123,12 +594,52 @@ mod tests {
         let content = String::new();
 
         // When: Rendering reader
-        let html = render_reader(&book, content);
+        let html = render_reader(
+            &book,
+            &inline(&content),
+            ReaderMode::Scroll,
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
 
         // Then: Should show title in navigation
         assert!(html.contains("<h2>Test Book</h2>"));
     }
 
+    #[test]
+    fn should_display_author_in_nav_with_fallback() {
+        // Given: A book without author and one with
+        let unknown = create_test_book();
+        let mut known = create_test_book();
+        known.author = Some("A & B".to_string());
+
+        // When: Rendering both
+        let unknown_html = render_reader(
+            &unknown,
+            &inline(""),
+            ReaderMode::Scroll,
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
+        let known_html = render_reader(
+            &known,
+            &inline(""),
+            ReaderMode::Scroll,
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
+
+        // Then: Should show the fallback or the escaped author
+        assert!(unknown_html.contains(r#"<p class="author">Unknown Author</p>"#));
+        assert!(known_html.contains(r#"<p class="author">A &amp; B</p>"#));
+    }
+
     #[test]
     fn should_escape_html_in_title() {
         // Given: A book with HTML characters in title
@@ -139,7 +650,15 @@ mod tests {
         let content = String::new();
 
         // When: Rendering reader
-        let html = render_reader(&book, content);
+        let html = render_reader(
+            &book,
+            &inline(&content),
+            ReaderMode::Scroll,
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
 
         // Then: Should escape HTML in title
         assert!(html.contains("&lt;script&gt;"));
@@ -153,7 +672,15 @@ mod tests {
         let content = "<p>Chapter 1</p><p>Chapter 2</p>".to_string();
 
         // When: Rendering reader
-        let html = render_reader(&book, content);
+        let html = render_reader(
+            &book,
+            &inline(&content),
+            ReaderMode::Scroll,
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
 
         // Then: Should wrap in article tags
         assert!(html.contains("<article>"));
@@ -169,7 +696,15 @@ mod tests {
         let content = String::new();
 
         // When: Rendering reader
-        let html = render_reader(&book, content);
+        let html = render_reader(
+            &book,
+            &inline(&content),
+            ReaderMode::Scroll,
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
 
         // Then: Should not include any script tags
         assert!(!html.contains("<script"));
@@ -215,4 +750,242 @@ mod tests {
         assert!(!sanitized.contains("onclick"));
         assert!(sanitized.contains("Click me"));
     }
+
+    #[test]
+    fn should_render_scroll_mode_by_default_without_page_controls() {
+        // Given/When: Rendering with the default mode
+        let html = render_reader(
+            &create_test_book(),
+            &inline(""),
+            ReaderMode::default(),
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
+
+        // Then: The body is marked as scrolling and links to the paged view
+        assert!(html.contains(r#"<body data-reader-mode="scroll">"#));
+        assert!(html.contains(r#"<main class="reader-scroll">"#));
+        assert!(html.contains("?mode=paged"));
+        assert!(!html.contains("page-controls"));
+    }
+
+    #[test]
+    fn should_render_paged_mode_with_page_controls() {
+        // Given/When: Rendering in paged mode
+        let book = create_test_book();
+        let html = render_reader(
+            &book,
+            &inline("<p>Text</p>"),
+            ReaderMode::Paged,
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
+
+        // Then: The markup targets the column layout and includes page controls
+        assert!(html.contains(r#"<body data-reader-mode="paged">"#));
+        assert!(html.contains(r#"<main class="reader-paged">"#));
+        assert!(html.contains(r#"class="page-prev""#));
+        assert!(html.contains(r#"class="page-next""#));
+        assert!(html.contains(&format!(r#"href="/reader/{}">Scroll view"#, book.id)));
+        assert!(html.contains("/static/js/reader.js"));
+    }
+
+    fn write_epub(temp_dir: &tempfile::TempDir, chapters: &[&str]) -> std::path::PathBuf {
+        let path = temp_dir.path().join("book.epub");
+        let epub = crate::test_epub::TestEpub::new("Long")
+            .chapters(chapters)
+            .build();
+        std::fs::write(&path, epub).unwrap();
+        path
+    }
+
+    #[test]
+    fn should_stop_inlining_chapters_at_size_cap() {
+        // Given: A book with three chapters of about 100 bytes each
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let paragraph = format!("<p>{}</p>", "x".repeat(100));
+        let path = write_epub(&temp_dir, &[&paragraph, &paragraph, &paragraph]);
+
+        // When: Extracting with room for about two chapters, and without a cap
+        let capped = extract_and_sanitize_content(&path, 250, false).unwrap();
+        let full = extract_and_sanitize_content(&path, 0, false).unwrap();
+
+        // Then: The capped content should say where to resume
+        assert_eq!(capped.next_chapter, Some(2));
+        assert_eq!(capped.chapter_count, 3);
+        assert!(capped.bytes_emitted() <= 250);
+        assert_eq!(capped.html.matches("<hr>").count(), 2);
+        assert_eq!(full.next_chapter, None);
+        assert_eq!(full.html.matches("<hr>").count(), 3);
+    }
+
+    #[test]
+    fn should_always_inline_first_chapter() {
+        // Given: A first chapter larger than the cap
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let big = format!("<p>{}</p>", "x".repeat(500));
+        let path = write_epub(&temp_dir, &[&big, "<p>Next</p>"]);
+
+        // When: Extracting with a tiny cap
+        let content = extract_and_sanitize_content(&path, 10, false).unwrap();
+
+        // Then: The reader still gets something to show
+        assert!(content.html.contains(&"x".repeat(500)));
+        assert_eq!(content.next_chapter, Some(1));
+    }
+
+    #[test]
+    fn should_extract_single_chapter_html() {
+        // Given: A two-chapter book
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = write_epub(&temp_dir, &["<p>First</p>", "<p>Second</p>"]);
+
+        // When: Fetching the second chapter and one past the end
+        let second = extract_chapter_html(&path, 1, false).unwrap();
+        let missing = extract_chapter_html(&path, 2, false).unwrap();
+
+        // Then: Only the requested chapter is returned with the spine length
+        let (html, chapter_count) = second.unwrap();
+        assert!(html.contains("Second"));
+        assert!(!html.contains("First"));
+        assert_eq!(chapter_count, 2);
+        assert!(missing.is_none());
+    }
+
+    #[test]
+    fn should_render_load_more_control_for_truncated_content() {
+        // Given: Content capped before the third of five chapters
+        let book = create_test_book();
+        let content = ReaderContent {
+            html: "<p>Start</p>".to_string(),
+            next_chapter: Some(2),
+            chapter_count: 5,
+            chapter_starts: Vec::new(),
+        };
+
+        // When: Rendering the reader
+        let html = render_reader(
+            &book,
+            &content,
+            ReaderMode::Scroll,
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "",
+        );
+
+        // Then: The control links to the next chapter and carries resume metadata
+        assert!(html.contains(&format!(r#"href="/reader/{}/chapters/2""#, book.id)));
+        assert!(html.contains(r#"data-next-chapter="2""#));
+        assert!(html.contains(r#"data-chapter-count="5""#));
+        assert!(html.contains(r#"data-bytes-emitted="12""#));
+        assert!(html.contains("3 more"));
+        assert!(html.contains("/static/js/reader.js"));
+    }
+
+    #[test]
+    fn should_link_bookmarks_to_inlined_chapters_or_chapter_pages() {
+        // Given: Two of three chapters inlined, and a bookmark in each of the first and last
+        let book = create_test_book();
+        let content = ReaderContent {
+            html: "<p>One</p><p>Two</p>".to_string(),
+            next_chapter: Some(2),
+            chapter_count: 3,
+            chapter_starts: vec![(0, 0), (1, 10)],
+        };
+        let bookmark = |id: i64, chapter_index: u32, label: &str| Bookmark {
+            id,
+            book_id: book.id.clone(),
+            chapter_index,
+            scroll_fraction: 0.25,
+            label: label.to_string(),
+            created_at: 0,
+        };
+        let bookmarks = [bookmark(1, 1, "Quote <1>"), bookmark(2, 2, "Ending")];
+
+        // When: Rendering the reader
+        let html = render_reader(
+            &book,
+            &content,
+            ReaderMode::Scroll,
+            &AdjacentBooks::default(),
+            ReaderMarks {
+                bookmarks: &bookmarks,
+                annotations: &[],
+            },
+            Lang::En,
+            "",
+        );
+
+        // Then: Chapters start with anchors, and each bookmark links to its chapter
+        assert!(html.contains(
+            r#"<a id="chapter-0" class="chapter-anchor"></a><p>One</p><a id="chapter-1" class="chapter-anchor"></a><p>Two</p>"#
+        ));
+        assert!(html.contains("Bookmarks (2)"));
+        assert!(html.contains(
+            r##"<a href="#chapter-1" data-chapter="1" data-scroll-fraction="0.25">Quote &lt;1&gt;</a>"##
+        ));
+        assert!(html.contains(&format!(
+            r#"<a href="/reader/{}/chapters/2" data-chapter="2""#,
+            book.id
+        )));
+    }
+
+    #[test]
+    fn should_prefix_reader_links_with_base_path() {
+        // Given: Truncated content served under a subpath
+        let book = create_test_book();
+        let content = ReaderContent {
+            html: "<p>Start</p>".to_string(),
+            next_chapter: Some(1),
+            chapter_count: 2,
+            chapter_starts: Vec::new(),
+        };
+
+        // When: Rendering the reader
+        let html = render_reader(
+            &book,
+            &content,
+            ReaderMode::Scroll,
+            &AdjacentBooks::default(),
+            ReaderMarks::default(),
+            Lang::En,
+            "/ezbooks",
+        );
+
+        // Then: Navigation, chapter and asset URLs carry the prefix
+        assert!(html.contains(r#"<a href="/ezbooks/">&larr; Back to Library</a>"#));
+        assert!(html.contains(&format!(r#"href="/ezbooks/reader/{}?mode=paged""#, book.id)));
+        assert!(html.contains(&format!(r#"href="/ezbooks/reader/{}/chapters/1""#, book.id)));
+        assert!(html.contains("/ezbooks/static/css/reader.css"));
+        assert!(html.contains("/ezbooks/static/js/reader.js"));
+    }
+
+    #[test]
+    fn should_stop_extracting_chapters_when_visitor_declines() {
+        // Given: A three-chapter EPUB
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        let epub = TestEpub::new("Three")
+            .chapters(&["<p>One</p>", "<p>Two</p>", "<p>Three</p>"])
+            .build();
+        std::fs::write(&path, epub).unwrap();
+
+        // When: Visiting chapters until the visitor stops after the second, as a
+        // disconnected client does
+        let mut seen = Vec::new();
+        let visited = for_each_chapter_html(&path, false, |chapter| {
+            seen.push(chapter.index);
+            seen.len() < 2
+        })
+        .unwrap();
+
+        // Then: The third chapter is never extracted
+        assert_eq!(visited, 2);
+        assert_eq!(seen, vec![0, 1]);
+    }
 }
@@ -0,0 +1,215 @@
+use crate::book_repository;
+use crate::content_hash::content_hash;
+use crate::database_connection::DatabasePool;
+use crate::epub_cover_extractor::dominant_color;
+use crate::error::Result;
+use crate::file_storage::FileStorage;
+use tracing::{info, instrument, warn};
+
+/// Summary of a reindex run
+#[derive(Debug, Default, PartialEq)]
+pub struct ReindexSummary {
+    pub file_sizes_backfilled: usize,
+    pub content_hashes_backfilled: usize,
+    pub cover_hashes_backfilled: usize,
+    pub cover_colors_backfilled: usize,
+    pub failures: usize,
+}
+
+/// Backfills derived per-book data for rows imported before it was tracked
+#[instrument(skip(pool, storage))]
+pub async fn run_reindex(pool: &DatabasePool, storage: &FileStorage) -> Result<ReindexSummary> {
+    info!("Starting reindex job");
+
+    let mut summary = ReindexSummary::default();
+
+    for book_id in book_repository::find_ids_missing_file_size(pool).await? {
+        match storage.epub_size(&book_id) {
+            Ok(size) => {
+                book_repository::update_file_size(pool, &book_id, size as i64).await?;
+                summary.file_sizes_backfilled += 1;
+            }
+            Err(e) => {
+                warn!(book_id = %book_id, error = %e, "Could not determine EPUB size");
+                summary.failures += 1;
+            }
+        }
+    }
+
+    for book_id in book_repository::find_ids_missing_content_hash(pool).await? {
+        match storage.read_epub(&book_id) {
+            Ok(data) => {
+                book_repository::update_content_hash(pool, &book_id, &content_hash(&data)).await?;
+                summary.content_hashes_backfilled += 1;
+            }
+            Err(e) => {
+                warn!(book_id = %book_id, error = %e, "Could not hash EPUB");
+                summary.failures += 1;
+            }
+        }
+    }
+
+    for book_id in book_repository::find_ids_missing_cover_hash(pool).await? {
+        match storage.read_cover(&book_id) {
+            Ok(data) => {
+                book_repository::update_cover_hash(pool, &book_id, &content_hash(&data)).await?;
+                summary.cover_hashes_backfilled += 1;
+            }
+            Err(e) => {
+                warn!(book_id = %book_id, error = %e, "Could not hash cover");
+                summary.failures += 1;
+            }
+        }
+    }
+
+    for book_id in book_repository::find_ids_missing_cover_color(pool).await? {
+        match storage
+            .read_cover(&book_id)
+            .and_then(|data| dominant_color(&data))
+        {
+            Ok(color) => {
+                book_repository::update_cover_color(pool, &book_id, &color).await?;
+                summary.cover_colors_backfilled += 1;
+            }
+            Err(e) => {
+                warn!(book_id = %book_id, error = %e, "Could not compute cover color");
+                summary.failures += 1;
+            }
+        }
+    }
+
+    info!(
+        file_sizes_backfilled = summary.file_sizes_backfilled,
+        content_hashes_backfilled = summary.content_hashes_backfilled,
+        cover_hashes_backfilled = summary.cover_hashes_backfilled,
+        cover_colors_backfilled = summary.cover_colors_backfilled,
+        failures = summary.failures,
+        "Reindex job completed"
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book_model::Book;
+    use crate::test_fixtures::setup_test_db;
+    use tempfile::TempDir;
+
+    async fn setup() -> (DatabasePool, FileStorage, TempDir) {
+        let (pool, temp_dir) = setup_test_db().await;
+        let storage = FileStorage::new(temp_dir.path().join("data")).unwrap();
+        (pool, storage, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn should_backfill_file_size_from_stored_epub() {
+        // Given: A legacy book whose EPUB is on disk but has no recorded size
+        let (pool, storage, _temp_dir) = setup().await;
+        let book = Book::new("Legacy".to_string(), "/legacy.epub".to_string());
+        book_repository::insert(&pool, &book).await.unwrap();
+        storage.save_epub(&book.id, b"epub-bytes").unwrap();
+
+        // When: Running the reindex job
+        let summary = run_reindex(&pool, &storage).await.unwrap();
+
+        // Then: The size should be stored from the file on disk
+        assert_eq!(summary.file_sizes_backfilled, 1);
+        let found = book_repository::find_by_id(&pool, &book.id).await.unwrap();
+        assert_eq!(found.file_size_bytes, Some(10));
+        assert_eq!(summary.content_hashes_backfilled, 1);
+        assert_eq!(found.content_hash, Some(content_hash(b"epub-bytes")));
+    }
+
+    #[tokio::test]
+    async fn should_count_failures_for_missing_epubs() {
+        // Given: A legacy book whose EPUB is missing
+        let (pool, storage, _temp_dir) = setup().await;
+        let book = Book::new("Missing".to_string(), "/missing.epub".to_string());
+        book_repository::insert(&pool, &book).await.unwrap();
+
+        // When: Running the reindex job
+        let summary = run_reindex(&pool, &storage).await.unwrap();
+
+        // Then: The failure should be counted and the size left unknown
+        assert_eq!(summary.failures, 2);
+        let found = book_repository::find_by_id(&pool, &book.id).await.unwrap();
+        assert!(found.file_size_bytes.is_none());
+    }
+
+    /// A small solid-color JPEG cover
+    fn jpeg_cover(rgb: [u8; 3]) -> Vec<u8> {
+        let mut data = Vec::new();
+        image::RgbImage::from_pixel(4, 6, image::Rgb(rgb))
+            .write_to(
+                &mut std::io::Cursor::new(&mut data),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        data
+    }
+
+    #[tokio::test]
+    async fn should_backfill_cover_hash_for_books_with_covers() {
+        // Given: A legacy book with a stored cover but no cover hash, and one without a cover
+        let (pool, storage, _temp_dir) = setup().await;
+        let cover = jpeg_cover([10, 20, 30]);
+        let mut with_cover = Book::new("Cover".to_string(), "/cover.epub".to_string());
+        with_cover.cover_image_path = Some(storage.save_cover(&with_cover.id, &cover).unwrap());
+        book_repository::insert(&pool, &with_cover).await.unwrap();
+        storage.save_epub(&with_cover.id, b"epub").unwrap();
+        let without_cover = Book::new("Bare".to_string(), "/bare.epub".to_string());
+        book_repository::insert(&pool, &without_cover)
+            .await
+            .unwrap();
+        storage.save_epub(&without_cover.id, b"epub").unwrap();
+
+        // When: Running the reindex job
+        let summary = run_reindex(&pool, &storage).await.unwrap();
+
+        // Then: Only the book with a cover gets a hash
+        assert_eq!(summary.cover_hashes_backfilled, 1);
+        assert_eq!(summary.failures, 0);
+        let found = book_repository::find_by_id(&pool, &with_cover.id)
+            .await
+            .unwrap();
+        assert_eq!(found.cover_hash, Some(content_hash(&cover)));
+    }
+
+    #[tokio::test]
+    async fn should_backfill_cover_color_from_stored_cover() {
+        // Given: A legacy book with a solid red cover and one whose cover cannot be decoded
+        let (pool, storage, _temp_dir) = setup().await;
+        let mut red = Book::new("Red".to_string(), "/red.epub".to_string());
+        red.cover_image_path = Some(
+            storage
+                .save_cover(&red.id, &jpeg_cover([200, 0, 0]))
+                .unwrap(),
+        );
+        book_repository::insert(&pool, &red).await.unwrap();
+        storage.save_epub(&red.id, b"epub").unwrap();
+        let mut broken = Book::new("Broken".to_string(), "/broken.epub".to_string());
+        broken.cover_image_path = Some(storage.save_cover(&broken.id, b"not an image").unwrap());
+        book_repository::insert(&pool, &broken).await.unwrap();
+        storage.save_epub(&broken.id, b"epub").unwrap();
+
+        // When: Running the reindex job
+        let summary = run_reindex(&pool, &storage).await.unwrap();
+
+        // Then: The decodable cover gets a reddish color, the other counts as a failure
+        assert_eq!(summary.cover_colors_backfilled, 1);
+        assert_eq!(summary.failures, 1);
+        let found = book_repository::find_by_id(&pool, &red.id).await.unwrap();
+        let color = found.cover_color.unwrap();
+        assert!(
+            color.starts_with("#c") || color.starts_with("#b"),
+            "{}",
+            color
+        );
+        assert!(book_repository::find_by_id(&pool, &broken.id)
+            .await
+            .unwrap()
+            .cover_color
+            .is_none());
+    }
+}
@@ -0,0 +1,87 @@
+use std::convert::Infallible;
+use tracing::{field, info_span, Span};
+use uuid::Uuid;
+use warp::http::header::{HeaderMap, HeaderValue};
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-supplied id that is kept; longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Wraps `filter` in a per-request span and adds the `X-Request-Id` header to its responses
+pub fn with_request_id<F>(
+    filter: F,
+) -> impl Filter<Extract = (Response,), Error = Infallible> + Clone
+where
+    F: Filter<Extract = (Response,), Error = Infallible> + Clone + Send + Sync + 'static,
+{
+    request_id()
+        .and(filter)
+        .map(|id: String, mut response: Response| {
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                response.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            response
+        })
+        .with(warp::trace(|info| {
+            info_span!(
+                "request",
+                method = %info.method(),
+                path = %info.path(),
+                request_id = field::Empty,
+            )
+        }))
+        .map(Reply::into_response)
+}
+
+/// Takes the client's `X-Request-Id` or generates one, and records it on the request span
+fn request_id() -> impl Filter<Extract = (String,), Error = Infallible> + Clone {
+    warp::header::headers_cloned().map(|headers: HeaderMap| {
+        let id = headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(client_request_id)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        Span::current().record("request_id", id.as_str());
+        id
+    })
+}
+
+/// Accepts short, printable ids so they are safe to log and echo back
+fn client_request_id(value: &str) -> Option<String> {
+    let value = value.trim();
+    let valid = !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LEN
+        && value.chars().all(|c| c.is_ascii_graphic());
+    valid.then(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_keep_printable_client_id() {
+        // Given
+        let value = " upload-42 ";
+
+        // When
+        let id = client_request_id(value);
+
+        // Then
+        assert_eq!(id.as_deref(), Some("upload-42"));
+    }
+
+    #[test]
+    fn should_reject_blank_long_or_spaced_client_ids() {
+        // Given
+        let long = "a".repeat(MAX_REQUEST_ID_LEN + 1);
+
+        // When / Then
+        assert_eq!(client_request_id("   "), None);
+        assert_eq!(client_request_id(&long), None);
+        assert_eq!(client_request_id("two words"), None);
+    }
+}
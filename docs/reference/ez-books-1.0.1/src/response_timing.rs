@@ -0,0 +1,125 @@
+use crate::config::Config;
+use std::convert::Infallible;
+use std::time::{Duration, Instant};
+use tracing::warn;
+use warp::http::header::HeaderValue;
+use warp::http::Method;
+use warp::path::FullPath;
+use warp::reply::Response;
+use warp::Filter;
+
+pub const RESPONSE_TIME_HEADER: &str = "x-response-time-ms";
+
+/// Timing behaviour from the configuration
+#[derive(Debug, Clone, Copy)]
+pub struct ResponseTiming {
+    /// Send `X-Response-Time-Ms` with every response
+    pub header: bool,
+    /// Requests taking at least this long are logged as warnings; `None` logs none
+    pub slow_request_threshold: Option<Duration>,
+}
+
+impl ResponseTiming {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            header: config.response_time_header,
+            slow_request_threshold: (config.slow_request_threshold_ms > 0)
+                .then(|| Duration::from_millis(config.slow_request_threshold_ms)),
+        }
+    }
+}
+
+/// Times every response of `filter`, adding the header and warning about slow requests
+/// as `timing` says
+pub fn with_response_timing<F>(
+    filter: F,
+    timing: ResponseTiming,
+) -> impl Filter<Extract = (Response,), Error = Infallible> + Clone
+where
+    F: Filter<Extract = (Response,), Error = Infallible> + Clone + Send + Sync + 'static,
+{
+    warp::any()
+        .map(Instant::now)
+        .and(warp::method())
+        .and(warp::path::full())
+        .and(filter)
+        .map(
+            move |started: Instant, method: Method, path: FullPath, mut response: Response| {
+                let elapsed = started.elapsed();
+                if timing.header {
+                    response.headers_mut().insert(
+                        RESPONSE_TIME_HEADER,
+                        HeaderValue::from(elapsed.as_millis() as u64),
+                    );
+                }
+                if timing
+                    .slow_request_threshold
+                    .map_or(false, |threshold| elapsed >= threshold)
+                {
+                    warn!(
+                        method = %method,
+                        route = %path.as_str(),
+                        status = response.status().as_u16(),
+                        elapsed_ms = elapsed.as_millis() as u64,
+                        "Slow request"
+                    );
+                }
+                response
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Reply;
+
+    fn slow_page() -> impl Filter<Extract = (Response,), Error = Infallible> + Clone {
+        warp::any().then(|| async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            warp::reply::html("<p>page</p>").into_response()
+        })
+    }
+
+    #[tokio::test]
+    async fn should_report_response_time_in_header() {
+        // Given: A slow route wrapped with the header on
+        let filter = with_response_timing(
+            slow_page(),
+            ResponseTiming {
+                header: true,
+                slow_request_threshold: Some(Duration::from_millis(10)),
+            },
+        );
+
+        // When: Requesting it
+        let response = warp::test::request().reply(&filter).await;
+
+        // Then: The header holds at least the time the route took
+        let millis: u64 = response.headers()[RESPONSE_TIME_HEADER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(millis >= 20, "{}", millis);
+        assert_eq!(response.body(), "<p>page</p>");
+    }
+
+    #[tokio::test]
+    async fn should_omit_header_when_turned_off() {
+        // Given: A route wrapped with the header off and no threshold
+        let filter = with_response_timing(
+            slow_page(),
+            ResponseTiming {
+                header: false,
+                slow_request_threshold: None,
+            },
+        );
+
+        // When: Requesting it
+        let response = warp::test::request().reply(&filter).await;
+
+        // Then: No timing header is sent
+        assert!(response.headers().get(RESPONSE_TIME_HEADER).is_none());
+    }
+}
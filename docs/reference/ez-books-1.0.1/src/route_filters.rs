@@ -1,32 +1,332 @@
+use crate::book_identifier::MetadataField;
+use crate::book_query::{
+    BooksQuery, CoverQuery, IntegrityQuery, LangQuery, ListingDefaults, MontageQuery,
+    NextBookQuery, ReaderQuery, RecommendedQuery, SearchQuery, TextQuery,
+};
+use crate::content_cache::ContentCache;
+use crate::cover_regeneration::CoverRegeneration;
 use crate::database_connection::DatabasePool;
+use crate::enrichment_queue::EnrichmentQueue;
+use crate::error::EzBooksError;
+use crate::error_recovery::with_error_recovery;
 use crate::file_storage::FileStorage;
+use crate::json_format::with_json_format;
 use crate::openlibrary_client::OpenLibraryClient;
+use crate::rate_limit::RateLimiter;
+use crate::reader_renderer::ReaderSettings;
+use crate::request_id::with_request_id;
+use crate::response_timing::{with_response_timing, ResponseTiming};
 use crate::route_handlers::*;
+use crate::security_headers::with_security_headers;
 use crate::static_assets::serve_static;
-use warp::{Filter, Rejection, Reply};
+use crate::ui_text::Lang;
+use crate::upload_handler::{UploadSettings, MAX_UPLOAD_BYTES};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tracing::info;
+use warp::filters::BoxedFilter;
+use warp::http::header::{HeaderValue, VARY};
+use warp::reply::Response;
+use warp::{reject, Filter, Rejection, Reply};
+
+/// Configuration shared by the route tree
+#[derive(Debug, Clone)]
+pub struct RouteSettings {
+    pub upload: UploadSettings,
+    pub reader: ReaderSettings,
+    /// Sort and page size for listings that do not ask for one
+    pub listing: ListingDefaults,
+    /// Bearer token for `/api/admin` endpoints; they answer 401 while unset
+    pub admin_token: Option<String>,
+    /// Prefix stripped from requests and added to generated URLs; empty at the root
+    pub base_path: String,
+    /// Decode covers before serving them; corrupt ones are replaced and flagged
+    pub validate_covers: bool,
+    /// Serve `/api/search/content` and the content reindex; they answer 404 while off
+    pub content_search: bool,
+    /// Limits uploads and enrichment per client IP; `None` leaves them unlimited
+    pub rate_limiter: Option<RateLimiter>,
+    /// Sent as `Content-Security-Policy` on every response; `None` sends no policy
+    pub content_security_policy: Option<String>,
+    /// Fields `/api/admin/enrich-missing` leaves as they are
+    pub locked_metadata_fields: Vec<MetadataField>,
+    /// `X-Response-Time-Ms` header and slow-request warnings
+    pub response_timing: ResponseTiming,
+    /// Indent JSON responses unless a request asks for `?pretty=0`
+    pub pretty_json: bool,
+}
 
 pub fn routes(
     pool: DatabasePool,
     storage: FileStorage,
-    ol_client: OpenLibraryClient,
-) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    gallery_route(pool.clone())
-        .or(static_route())
-        .or(api_books_route(pool.clone()))
+    enrichment_queue: EnrichmentQueue,
+    openlibrary: OpenLibraryClient,
+    content_cache: ContentCache,
+    settings: RouteSettings,
+) -> impl Filter<Extract = (Response,), Error = Infallible> + Clone {
+    let base_path = settings.base_path.clone();
+    let content_security_policy = settings.content_security_policy.clone();
+    let response_timing = settings.response_timing;
+    let pretty_json = settings.pretty_json;
+    with_request_id(with_response_timing(
+        with_security_headers(
+            with_json_format(
+                with_error_recovery(
+                    base_path_prefix(&base_path).and(app_routes(
+                        pool,
+                        storage,
+                        enrichment_queue,
+                        openlibrary,
+                        content_cache,
+                        settings,
+                    )),
+                    base_path,
+                ),
+                pretty_json,
+            ),
+            content_security_policy,
+        ),
+        response_timing,
+    ))
+}
+
+/// Consumes the `base_path` segments, so the routes below match paths from the app root
+fn base_path_prefix(base_path: &str) -> BoxedFilter<()> {
+    base_path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .fold(warp::any().boxed(), |prefix, segment| {
+            prefix.and(warp::path(segment.to_string())).boxed()
+        })
+}
+
+/// Route groups come boxed; unboxed, their nested filter types exhaust the compiler
+fn app_routes(
+    pool: DatabasePool,
+    storage: FileStorage,
+    enrichment_queue: EnrichmentQueue,
+    openlibrary: OpenLibraryClient,
+    content_cache: ContentCache,
+    settings: RouteSettings,
+) -> BoxedFilter<(Response,)> {
+    let humanize_filename_titles = settings.upload.humanize_filename_titles;
+    static_route()
+        .or(listing_routes(
+            pool.clone(),
+            settings.listing,
+            settings.base_path.clone(),
+        ))
+        .or(meta_routes())
+        .or(admin_routes(
+            pool.clone(),
+            storage.clone(),
+            openlibrary,
+            &settings,
+        ))
+        .or(content_search_route(pool.clone(), settings.content_search))
+        .or(metadata_search_route(pool.clone()))
         .or(api_book_detail_route(pool.clone()))
-        .or(cover_route(storage.clone()))
-        .or(reader_route(pool.clone(), storage.clone()))
-        .or(upload_route(pool.clone(), storage.clone(), ol_client))
-        .or(delete_route(pool, storage))
+        .or(file_routes(
+            pool.clone(),
+            storage.clone(),
+            settings.validate_covers,
+            settings.upload.cover_jpeg_quality,
+        ))
+        .or(reader_routes(
+            pool.clone(),
+            storage.clone(),
+            content_cache.clone(),
+            settings.reader,
+            settings.base_path,
+        ))
+        .or(upload_route(
+            pool.clone(),
+            storage.clone(),
+            enrichment_queue.clone(),
+            settings.upload.clone(),
+            settings.rate_limiter.clone(),
+        ))
+        .or(replace_file_route(
+            pool.clone(),
+            storage.clone(),
+            enrichment_queue,
+            content_cache.clone(),
+            settings.upload,
+            settings.rate_limiter,
+        ))
+        .or(update_route(pool.clone()))
+        .or(reset_metadata_route(
+            pool.clone(),
+            storage.clone(),
+            humanize_filename_titles,
+        ))
+        .or(add_subject_route(pool.clone()))
+        .or(delete_subject_route(pool.clone()))
+        .or(reading_aid_routes(pool.clone()))
+        .or(collection_routes(pool.clone()))
+        .or(delete_route(pool, storage, content_cache))
+        .map(Reply::into_response)
+        .boxed()
+}
+
+/// `/api/admin` endpoints, all behind the admin token
+fn admin_routes(
+    pool: DatabasePool,
+    storage: FileStorage,
+    openlibrary: OpenLibraryClient,
+    settings: &RouteSettings,
+) -> BoxedFilter<(Response,)> {
+    let admin_token = settings.admin_token.clone();
+    let regeneration = CoverRegeneration::default();
+    integrity_route(pool.clone(), storage.clone(), admin_token.clone())
+        .or(enrich_missing_route(
+            pool.clone(),
+            openlibrary,
+            admin_token.clone(),
+            settings.rate_limiter.clone(),
+            settings.locked_metadata_fields.clone(),
+        ))
+        .or(verify_route(pool.clone(), admin_token.clone()))
+        .or(reindex_content_route(
+            pool.clone(),
+            admin_token.clone(),
+            settings.content_search,
+        ))
+        .or(regenerate_covers_route(
+            pool.clone(),
+            storage,
+            admin_token.clone(),
+            regeneration.clone(),
+            settings.upload.clone(),
+        ))
+        .or(cover_regeneration_progress_route(
+            admin_token.clone(),
+            regeneration,
+        ))
+        .or(rebuild_fts_route(pool, admin_token))
+        .map(Reply::into_response)
+        .boxed()
+}
+
+/// Downloads, covers and other files of a book
+fn file_routes(
+    pool: DatabasePool,
+    storage: FileStorage,
+    validate_covers: bool,
+    cover_jpeg_quality: u8,
+) -> BoxedFilter<(Response,)> {
+    bundle_route(pool.clone(), storage.clone())
+        .or(media_overlays_route(pool.clone(), storage.clone()))
+        .or(download_route(pool.clone(), storage.clone()))
+        .or(download_head_route(pool.clone(), storage.clone()))
+        .or(cover_route(
+            pool.clone(),
+            storage.clone(),
+            validate_covers,
+            cover_jpeg_quality,
+        ))
+        .or(cover_head_route(
+            pool.clone(),
+            storage.clone(),
+            cover_jpeg_quality,
+        ))
+        .or(placeholder_cover_route(pool.clone()))
+        .or(cover_color_route(pool.clone(), storage.clone()))
+        .or(montage_route(pool, storage, cover_jpeg_quality))
+        .map(Reply::into_response)
+        .boxed()
+}
+
+fn reader_routes(
+    pool: DatabasePool,
+    storage: FileStorage,
+    content_cache: ContentCache,
+    reader: ReaderSettings,
+    base_path: String,
+) -> BoxedFilter<(Response,)> {
+    reader_text_route(pool.clone(), storage.clone(), reader)
+        .or(reader_chapter_route(pool.clone(), storage.clone(), reader))
+        .or(reader_stream_route(pool.clone(), storage.clone(), reader))
+        .or(reader_route(
+            pool,
+            storage,
+            content_cache,
+            reader,
+            base_path,
+        ))
+        .map(Reply::into_response)
+        .boxed()
+}
+
+/// The gallery, book pages and book listings, ahead of `/api/books/{id}` so their fixed
+/// paths win
+fn listing_routes(
+    pool: DatabasePool,
+    listing: ListingDefaults,
+    base_path: String,
+) -> BoxedFilter<(Response,)> {
+    gallery_route(pool.clone(), listing, base_path.clone())
+        .or(book_page_route(pool.clone(), base_path.clone()))
+        .or(author_gallery_route(pool.clone(), base_path))
+        .or(api_authors_route(pool.clone()))
+        .or(api_books_route(pool.clone(), listing))
+        .or(api_stats_route(pool.clone()))
+        .or(api_next_unread_route(pool.clone()))
+        .or(api_recommended_route(pool))
+        .map(Reply::into_response)
+        .boxed()
 }
 
 fn gallery_route(
     pool: DatabasePool,
+    listing: ListingDefaults,
+    base_path: String,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::path::end()
         .and(warp::get())
+        .and(warp::query::<BooksQuery>())
+        .and(with_lang())
+        .and(warp::header::optional::<String>("if-modified-since"))
         .and(with_db(pool))
+        .and(with_listing(listing))
+        .and(with_base_path(base_path))
         .and_then(handle_gallery)
+        .map(vary_by_language)
+}
+
+fn book_page_route(
+    pool: DatabasePool,
+    base_path: String,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("books" / String)
+        .and(warp::get())
+        .and(with_lang())
+        .and(with_db(pool))
+        .and(with_base_path(base_path))
+        .and_then(handle_book_page)
+        .map(vary_by_language)
+}
+
+fn author_gallery_route(
+    pool: DatabasePool,
+    base_path: String,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("authors" / String)
+        .and(warp::get())
+        .and(with_lang())
+        .and(with_db(pool))
+        .and(with_base_path(base_path))
+        .and_then(handle_author_gallery)
+        .map(vary_by_language)
+}
+
+fn api_authors_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "authors")
+        .and(warp::get())
+        .and(with_db(pool))
+        .and_then(handle_api_authors)
 }
 
 fn static_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
@@ -35,81 +335,3098 @@ fn static_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clon
 
 fn api_books_route(
     pool: DatabasePool,
+    listing: ListingDefaults,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::path!("api" / "books")
         .and(warp::get())
+        .and(warp::query::<BooksQuery>())
         .and(with_db(pool))
+        .and(with_listing(listing))
         .and_then(handle_api_books)
 }
 
+fn enrich_missing_route(
+    pool: DatabasePool,
+    openlibrary: OpenLibraryClient,
+    admin_token: Option<String>,
+    rate_limiter: Option<RateLimiter>,
+    locked_metadata_fields: Vec<MetadataField>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "admin" / "enrich-missing")
+        .and(warp::post())
+        .and(with_admin_token(admin_token))
+        .and(with_rate_limit(rate_limiter))
+        .and(with_db(pool))
+        .and(warp::any().map(move || openlibrary.clone()))
+        .and(warp::any().map(move || locked_metadata_fields.clone()))
+        .and_then(handle_enrich_missing)
+}
+
+fn verify_route(
+    pool: DatabasePool,
+    admin_token: Option<String>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "admin" / "verify")
+        .and(warp::post())
+        .and(with_admin_token(admin_token))
+        .and(with_db(pool))
+        .and_then(handle_verify_library)
+}
+
+fn reindex_content_route(
+    pool: DatabasePool,
+    admin_token: Option<String>,
+    content_search: bool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "admin" / "reindex-content")
+        .and(warp::post())
+        .and(with_admin_token(admin_token))
+        .and(with_db(pool))
+        .and(warp::any().map(move || content_search))
+        .and_then(handle_reindex_content)
+}
+
+fn regenerate_covers_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+    admin_token: Option<String>,
+    regeneration: CoverRegeneration,
+    upload_settings: UploadSettings,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "admin" / "regenerate-covers")
+        .and(warp::post())
+        .and(with_admin_token(admin_token))
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and(warp::any().map(move || regeneration.clone()))
+        .and(warp::any().map(move || upload_settings.clone()))
+        .and_then(handle_regenerate_covers)
+}
+
+fn cover_regeneration_progress_route(
+    admin_token: Option<String>,
+    regeneration: CoverRegeneration,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "admin" / "regenerate-covers")
+        .and(warp::get())
+        .and(with_admin_token(admin_token))
+        .and(warp::any().map(move || regeneration.clone()))
+        .and_then(handle_cover_regeneration_progress)
+}
+
+fn rebuild_fts_route(
+    pool: DatabasePool,
+    admin_token: Option<String>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "admin" / "rebuild-fts")
+        .and(warp::post())
+        .and(with_admin_token(admin_token))
+        .and(with_db(pool))
+        .and_then(handle_rebuild_fts)
+}
+
+fn content_search_route(
+    pool: DatabasePool,
+    content_search: bool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "search" / "content")
+        .and(warp::post())
+        .and(warp::query::<SearchQuery>())
+        .and(with_db(pool))
+        .and(warp::any().map(move || content_search))
+        .and_then(handle_content_search)
+}
+
+fn metadata_search_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "search")
+        .and(warp::get())
+        .and(warp::query::<SearchQuery>())
+        .and(with_db(pool))
+        .and_then(handle_metadata_search)
+}
+
+fn api_stats_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "stats")
+        .and(warp::get())
+        .and(with_db(pool))
+        .and_then(handle_stats)
+}
+
+fn integrity_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+    admin_token: Option<String>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "admin" / "integrity")
+        .and(warp::get())
+        .and(with_admin_token(admin_token))
+        .and(warp::query::<IntegrityQuery>())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and_then(handle_integrity)
+}
+
+/// Descriptions of the API and of the running build
+fn meta_routes() -> BoxedFilter<(Response,)> {
+    openapi_route()
+        .or(version_route())
+        .map(Reply::into_response)
+        .boxed()
+}
+
+fn openapi_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "openapi.json")
+        .and(warp::get())
+        .and_then(handle_openapi)
+}
+
+fn version_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "version")
+        .and(warp::get())
+        .and_then(handle_version)
+}
+
+fn api_next_unread_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / "next")
+        .and(warp::get())
+        .and(warp::query::<NextBookQuery>())
+        .and(with_db(pool))
+        .and_then(handle_next_unread)
+}
+
+fn api_recommended_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / "recommended")
+        .and(warp::get())
+        .and(warp::query::<RecommendedQuery>())
+        .and(with_db(pool))
+        .and_then(handle_recommended)
+}
+
 fn api_book_detail_route(
     pool: DatabasePool,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::path!("api" / "books" / String)
         .and(warp::get())
+        .and(warp::header::optional::<String>("if-modified-since"))
         .and(with_db(pool))
         .and_then(handle_api_book_detail)
 }
 
+fn bundle_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "bundle")
+        .and(warp::get())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and_then(handle_bundle)
+}
+
+fn media_overlays_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "media-overlays")
+        .and(warp::get())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and_then(handle_media_overlays)
+}
+
+fn download_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "download")
+        .and(warp::get())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and_then(handle_download)
+}
+
+fn download_head_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "download")
+        .and(warp::head())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and_then(handle_download_head)
+}
+
 fn cover_route(
+    pool: DatabasePool,
     storage: FileStorage,
+    validate_covers: bool,
+    jpeg_quality: u8,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::path!("covers" / String)
         .and(warp::get())
+        .and(warp::query::<CoverQuery>())
+        .and(with_db(pool))
         .and(with_storage(storage))
+        .and(warp::any().map(move || validate_covers))
+        .and(warp::any().map(move || jpeg_quality))
         .and_then(handle_cover)
 }
 
+fn cover_head_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+    jpeg_quality: u8,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("covers" / String)
+        .and(warp::head())
+        .and(warp::query::<CoverQuery>())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and(warp::any().map(move || jpeg_quality))
+        .and_then(handle_cover_head)
+}
+
+fn placeholder_cover_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("covers" / String / "placeholder")
+        .and(warp::get())
+        .and(with_db(pool))
+        .and_then(handle_placeholder_cover)
+}
+
+fn cover_color_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "cover" / "color")
+        .and(warp::get())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and_then(handle_cover_color)
+}
+
+fn montage_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+    jpeg_quality: u8,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "gallery" / "montage")
+        .and(warp::get())
+        .and(warp::query::<MontageQuery>())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and(warp::any().map(move || jpeg_quality))
+        .and_then(handle_montage)
+}
+
 fn reader_route(
     pool: DatabasePool,
     storage: FileStorage,
+    content_cache: ContentCache,
+    reader_settings: ReaderSettings,
+    base_path: String,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::path!("reader" / String)
         .and(warp::get())
+        .and(
+            warp::query::<ReaderQuery>()
+                .and(with_lang())
+                .map(|query: ReaderQuery, lang| ReaderQuery { lang, ..query }),
+        )
         .and(with_db(pool))
         .and(with_storage(storage))
+        .and(with_content_cache(content_cache))
+        .and(warp::any().map(move || reader_settings))
+        .and(with_base_path(base_path))
         .and_then(handle_reader)
+        .map(vary_by_language)
+}
+
+fn reader_chapter_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+    reader_settings: ReaderSettings,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("reader" / String / "chapters" / usize)
+        .and(warp::get())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and(warp::any().map(move || reader_settings))
+        .and_then(handle_reader_chapter)
+}
+
+fn reader_stream_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+    reader_settings: ReaderSettings,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("reader" / String / "stream")
+        .and(warp::get())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and(warp::any().map(move || reader_settings))
+        .and_then(handle_reader_stream)
+}
+
+fn reader_text_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+    reader_settings: ReaderSettings,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("reader" / String / "text")
+        .and(warp::get())
+        .and(warp::query::<TextQuery>())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and(warp::any().map(move || reader_settings))
+        .and_then(handle_reader_text)
 }
 
 fn upload_route(
     pool: DatabasePool,
     storage: FileStorage,
-    ol_client: OpenLibraryClient,
+    enrichment_queue: EnrichmentQueue,
+    upload_settings: UploadSettings,
+    rate_limiter: Option<RateLimiter>,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::path("upload")
         .and(warp::post())
-        .and(warp::multipart::form().max_length(52_428_800)) // 50MB max
+        .and(with_rate_limit(rate_limiter))
+        .and(warp::multipart::form().max_length(MAX_UPLOAD_BYTES))
+        .and(warp::header::optional::<String>("idempotency-key"))
         .and(with_db(pool))
         .and(with_storage(storage))
-        .and(with_ol_client(ol_client))
+        .and(with_enrichment_queue(enrichment_queue))
+        .and(warp::any().map(move || upload_settings.clone()))
         .and_then(handle_upload)
 }
 
-fn delete_route(
+fn replace_file_route(
     pool: DatabasePool,
     storage: FileStorage,
+    enrichment_queue: EnrichmentQueue,
+    content_cache: ContentCache,
+    upload_settings: UploadSettings,
+    rate_limiter: Option<RateLimiter>,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    warp::path!("api" / "books" / String)
-        .and(warp::delete())
+    warp::path!("api" / "books" / String / "file")
+        .and(warp::put())
+        .and(with_rate_limit(rate_limiter))
+        .and(warp::header::optional::<String>("if-unmodified-since"))
+        .and(with_db(pool.clone()))
+        .and_then(find_unmodified_book)
+        .and(warp::multipart::form().max_length(MAX_UPLOAD_BYTES))
         .and(with_db(pool))
         .and(with_storage(storage))
-        .and_then(handle_delete)
+        .and(with_enrichment_queue(enrichment_queue))
+        .and(with_content_cache(content_cache))
+        .and(warp::any().map(move || upload_settings.clone()))
+        .and_then(handle_replace_file)
 }
 
-fn with_db(
+fn update_route(
     pool: DatabasePool,
-) -> impl Filter<Extract = (DatabasePool,), Error = std::convert::Infallible> + Clone {
-    warp::any().map(move || pool.clone())
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String)
+        .and(warp::put())
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json())
+        .and(with_db(pool))
+        .and_then(handle_update)
 }
 
-fn with_storage(
+fn reset_metadata_route(
+    pool: DatabasePool,
     storage: FileStorage,
-) -> impl Filter<Extract = (FileStorage,), Error = std::convert::Infallible> + Clone {
-    warp::any().map(move || storage.clone())
+    humanize_filename_titles: bool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "reset-metadata")
+        .and(warp::post())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and(warp::any().map(move || humanize_filename_titles))
+        .and_then(handle_reset_metadata)
+}
+
+fn add_subject_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "subjects")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(16 * 1024))
+        .and(warp::body::json())
+        .and(with_db(pool))
+        .and_then(handle_add_subject)
+}
+
+fn delete_subject_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "subjects" / String)
+        .and(warp::delete())
+        .and(with_db(pool))
+        .and_then(handle_delete_subject)
+}
+
+/// Reading progress, bookmarks and annotations, grouped to keep the route tree shallow
+fn reading_aid_routes(pool: DatabasePool) -> BoxedFilter<(Response,)> {
+    finish_reading_route(pool.clone())
+        .or(reset_progress_route(pool.clone()))
+        .or(bookmarks_route(pool.clone()))
+        .or(add_bookmark_route(pool.clone()))
+        .or(delete_bookmark_route(pool.clone()))
+        .or(annotations_route(pool.clone()))
+        .or(add_annotation_route(pool.clone()))
+        .or(update_annotation_route(pool.clone()))
+        .or(delete_annotation_route(pool))
+        .map(Reply::into_response)
+        .boxed()
+}
+
+fn finish_reading_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "progress" / "finish")
+        .and(warp::post())
+        .and(with_db(pool))
+        .and_then(handle_finish_reading)
+}
+
+fn reset_progress_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "progress" / "reset")
+        .and(warp::post())
+        .and(with_db(pool))
+        .and_then(handle_reset_progress)
 }
 
-fn with_ol_client(
-    client: OpenLibraryClient,
-) -> impl Filter<Extract = (OpenLibraryClient,), Error = std::convert::Infallible> + Clone {
-    warp::any().map(move || client.clone())
+fn bookmarks_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "bookmarks")
+        .and(warp::get())
+        .and(with_db(pool))
+        .and_then(handle_list_bookmarks)
+}
+
+fn add_bookmark_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "bookmarks")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(16 * 1024))
+        .and(warp::body::json())
+        .and(with_db(pool))
+        .and_then(handle_add_bookmark)
+}
+
+fn delete_bookmark_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "bookmarks" / i64)
+        .and(warp::delete())
+        .and(with_db(pool))
+        .and_then(handle_delete_bookmark)
+}
+
+fn annotations_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "annotations")
+        .and(warp::get())
+        .and(with_db(pool))
+        .and_then(handle_list_annotations)
+}
+
+fn add_annotation_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "annotations")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json())
+        .and(with_db(pool))
+        .and_then(handle_add_annotation)
+}
+
+fn update_annotation_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "annotations" / i64)
+        .and(warp::put())
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json())
+        .and(with_db(pool))
+        .and_then(handle_update_annotation)
+}
+
+fn delete_annotation_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String / "annotations" / i64)
+        .and(warp::delete())
+        .and(with_db(pool))
+        .and_then(handle_delete_annotation)
+}
+
+/// `/api/collections` endpoints
+fn collection_routes(pool: DatabasePool) -> BoxedFilter<(Response,)> {
+    collections_route(pool.clone())
+        .or(create_collection_route(pool.clone()))
+        .or(collection_detail_route(pool.clone()))
+        .or(delete_collection_route(pool.clone()))
+        .or(add_collection_book_route(pool.clone()))
+        .or(remove_collection_book_route(pool.clone()))
+        .or(reorder_collection_route(pool))
+        .map(Reply::into_response)
+        .boxed()
+}
+
+fn collections_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "collections")
+        .and(warp::get())
+        .and(with_db(pool))
+        .and_then(handle_list_collections)
+}
+
+fn create_collection_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "collections")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(16 * 1024))
+        .and(warp::body::json())
+        .and(with_db(pool))
+        .and_then(handle_create_collection)
+}
+
+fn collection_detail_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "collections" / i64)
+        .and(warp::get())
+        .and(with_db(pool))
+        .and_then(handle_collection_detail)
+}
+
+fn delete_collection_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "collections" / i64)
+        .and(warp::delete())
+        .and(with_db(pool))
+        .and_then(handle_delete_collection)
+}
+
+fn add_collection_book_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "collections" / i64 / "books")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(16 * 1024))
+        .and(warp::body::json())
+        .and(with_db(pool))
+        .and_then(handle_add_collection_book)
+}
+
+fn remove_collection_book_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "collections" / i64 / "books" / String)
+        .and(warp::delete())
+        .and(with_db(pool))
+        .and_then(handle_remove_collection_book)
+}
+
+fn reorder_collection_route(
+    pool: DatabasePool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "collections" / i64 / "order")
+        .and(warp::put())
+        .and(warp::body::content_length_limit(256 * 1024))
+        .and(warp::body::json())
+        .and(with_db(pool))
+        .and_then(handle_reorder_collection)
+}
+
+fn delete_route(
+    pool: DatabasePool,
+    storage: FileStorage,
+    content_cache: ContentCache,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("api" / "books" / String)
+        .and(warp::delete())
+        .and(with_db(pool))
+        .and(with_storage(storage))
+        .and(with_content_cache(content_cache))
+        .and_then(handle_delete)
+}
+
+fn with_db(
+    pool: DatabasePool,
+) -> impl Filter<Extract = (DatabasePool,), Error = Infallible> + Clone {
+    warp::any().map(move || pool.clone())
+}
+
+fn with_storage(
+    storage: FileStorage,
+) -> impl Filter<Extract = (FileStorage,), Error = Infallible> + Clone {
+    warp::any().map(move || storage.clone())
+}
+
+fn with_content_cache(
+    cache: ContentCache,
+) -> impl Filter<Extract = (ContentCache,), Error = Infallible> + Clone {
+    warp::any().map(move || cache.clone())
+}
+
+fn with_listing(
+    listing: ListingDefaults,
+) -> impl Filter<Extract = (ListingDefaults,), Error = Infallible> + Clone {
+    warp::any().map(move || listing)
+}
+
+fn with_base_path(
+    base_path: String,
+) -> impl Filter<Extract = (String,), Error = Infallible> + Clone {
+    warp::any().map(move || base_path.clone())
+}
+
+/// Language of an HTML page, from `?lang=` or else `Accept-Language`
+fn with_lang() -> impl Filter<Extract = (Lang,), Error = Rejection> + Clone {
+    warp::query::<LangQuery>()
+        .and(warp::header::optional::<String>("accept-language"))
+        .map(|query: LangQuery, accept_language: Option<String>| {
+            Lang::negotiate(query.lang.as_deref(), accept_language.as_deref())
+        })
+}
+
+/// Marks a page rendered in the language `with_lang` chose, so caches keep one copy per language
+fn vary_by_language(reply: impl Reply) -> Response {
+    let mut response = reply.into_response();
+    response
+        .headers_mut()
+        .append(VARY, HeaderValue::from_static("accept-language"));
+    response
+}
+
+/// Requires `Authorization: Bearer <token>`; everything is refused when no token is configured
+fn with_admin_token(
+    admin_token: Option<String>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(move |header: Option<String>| {
+            let admin_token = admin_token.clone();
+            async move {
+                let presented = header
+                    .as_deref()
+                    .and_then(|value| value.strip_prefix("Bearer "))
+                    .map(str::trim);
+                match (admin_token.as_deref(), presented) {
+                    (Some(expected), Some(presented)) if tokens_match(expected, presented) => {
+                        Ok(())
+                    }
+                    _ => Err(reject::custom(EzBooksError::Unauthorized)),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+/// Takes a token from the client's bucket, refusing with 429 once it is empty.
+/// Requests without a peer address, as in tests, are not limited.
+fn with_rate_limit(
+    rate_limiter: Option<RateLimiter>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::addr::remote()
+        .and_then(move |remote: Option<SocketAddr>| {
+            let rate_limiter = rate_limiter.clone();
+            async move {
+                match (rate_limiter, remote) {
+                    (Some(limiter), Some(remote)) => {
+                        limiter.check(remote.ip()).map_err(|wait| {
+                            info!(client = %remote.ip(), "Rejected rate-limited request");
+                            // Rounded up, so a client retrying on time finds a token
+                            let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+                            reject::custom(EzBooksError::RateLimited(secs.max(1)))
+                        })
+                    }
+                    _ => Ok(()),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+/// Compares without stopping at the first difference, so timing does not reveal the token
+fn tokens_match(expected: &str, presented: &str) -> bool {
+    expected.len() == presented.len()
+        && expected
+            .bytes()
+            .zip(presented.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn with_enrichment_queue(
+    queue: EnrichmentQueue,
+) -> impl Filter<Extract = (EnrichmentQueue,), Error = Infallible> + Clone {
+    warp::any().map(move || queue.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book_model::{Book, EnrichmentStatus};
+    use crate::book_query::BookSort;
+    use crate::book_repository;
+    use crate::cover_placeholder::{placeholder_svg, CoverColor};
+    use crate::epub_cover_extractor::TRANSPARENT_PIXEL_PNG;
+    use crate::progress_repository;
+    use crate::rate_limit::RateLimit;
+    use crate::security_headers::DEFAULT_CONTENT_SECURITY_POLICY;
+    use crate::test_epub::TestEpub;
+    use crate::test_fixtures::setup_test_db;
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use warp::http::StatusCode;
+
+    const ADMIN_TOKEN: &str = "test-admin-token";
+
+    /// Handles to the state behind a test route tree
+    struct TestLibrary {
+        pool: DatabasePool,
+        storage: FileStorage,
+        temp_dir: TempDir,
+    }
+
+    async fn setup() -> (
+        impl Filter<Extract = (Response,), Error = Infallible> + Clone,
+        TestLibrary,
+    ) {
+        setup_with_upload_settings(UploadSettings::for_tests()).await
+    }
+
+    async fn setup_with_upload_settings(
+        upload_settings: UploadSettings,
+    ) -> (
+        impl Filter<Extract = (Response,), Error = Infallible> + Clone,
+        TestLibrary,
+    ) {
+        setup_with_settings(upload_settings, "").await
+    }
+
+    async fn setup_with_settings(
+        upload_settings: UploadSettings,
+        base_path: &str,
+    ) -> (
+        impl Filter<Extract = (Response,), Error = Infallible> + Clone,
+        TestLibrary,
+    ) {
+        setup_with_route_settings(RouteSettings {
+            upload: upload_settings,
+            reader: ReaderSettings {
+                max_inline_bytes: 0,
+                rejoin_hyphenated_words: false,
+            },
+            listing: ListingDefaults::default(),
+            admin_token: Some(ADMIN_TOKEN.to_string()),
+            base_path: base_path.to_string(),
+            validate_covers: false,
+            content_search: false,
+            rate_limiter: None,
+            content_security_policy: Some(DEFAULT_CONTENT_SECURITY_POLICY.to_string()),
+
+            locked_metadata_fields: Vec::new(),
+
+            response_timing: ResponseTiming {
+                header: true,
+
+                slow_request_threshold: None,
+            },
+            pretty_json: false,
+        })
+        .await
+    }
+
+    async fn setup_with_route_settings(
+        settings: RouteSettings,
+    ) -> (
+        impl Filter<Extract = (Response,), Error = Infallible> + Clone,
+        TestLibrary,
+    ) {
+        let (pool, temp_dir) = setup_test_db().await;
+        let storage = FileStorage::new(temp_dir.path().join("data")).unwrap();
+        let client = OpenLibraryClient::with_base_url("http://127.0.0.1:9").unwrap();
+        let queue = EnrichmentQueue::start(pool.clone(), client.clone(), 10, Vec::new());
+        let filter = routes(
+            pool.clone(),
+            storage.clone(),
+            queue,
+            client,
+            ContentCache::new(1024 * 1024),
+            settings,
+        );
+        (
+            filter,
+            TestLibrary {
+                pool,
+                storage,
+                temp_dir,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn should_filter_gallery_by_format() {
+        // Given: A library with one EPUB
+        let (filter, library) = setup().await;
+        let book = Book::new("Formatted".to_string(), "/formatted.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+
+        // When: Listing EPUBs, PDFs, and paging with a format
+        let epubs = warp::test::request()
+            .path("/?format=epub")
+            .reply(&filter)
+            .await;
+        let pdfs = warp::test::request()
+            .path("/?format=pdf")
+            .reply(&filter)
+            .await;
+        let paged = warp::test::request()
+            .path("/api/books?format=epub&limit=10")
+            .reply(&filter)
+            .await;
+
+        // Then: Only matching books are shown; paging rejects the filter
+        assert!(String::from_utf8_lossy(epubs.body()).contains("Formatted"));
+        assert!(!String::from_utf8_lossy(pdfs.body()).contains("Formatted"));
+        assert_eq!(paged.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_localize_pages_from_accept_language_or_lang_param() {
+        // Given: A library with one book
+        let (filter, library) = setup().await;
+        let book = Book::new("Localized".to_string(), "/localized.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+
+        // When: Browsing in Korean, overriding it with `?lang=en`, and in an unsupported language
+        let korean = warp::test::request()
+            .path("/")
+            .header("accept-language", "ko-KR,ko;q=0.9,en;q=0.8")
+            .reply(&filter)
+            .await;
+        let overridden = warp::test::request()
+            .path("/?lang=en")
+            .header("accept-language", "ko-KR")
+            .reply(&filter)
+            .await;
+        let book_page = warp::test::request()
+            .path(&format!("/books/{}?lang=ko", book.id))
+            .reply(&filter)
+            .await;
+        let unsupported = warp::test::request()
+            .path("/")
+            .header("accept-language", "fr-FR")
+            .reply(&filter)
+            .await;
+
+        // Then: Korean is used when asked for, English otherwise, and caches are told so
+        let korean_vary = korean.headers()["vary"].clone();
+        let book_page_vary = book_page.headers()["vary"].clone();
+        let korean = String::from_utf8_lossy(korean.body());
+        assert!(korean.contains(r#"<html lang="ko">"#));
+        assert!(korean.contains("EPUB 올리기"));
+        let overridden = String::from_utf8_lossy(overridden.body());
+        assert!(overridden.contains(r#"<html lang="en">"#));
+        assert!(overridden.contains("Upload EPUB"));
+        assert!(String::from_utf8_lossy(book_page.body()).contains("작자 미상"));
+        assert!(String::from_utf8_lossy(unsupported.body()).contains("Upload EPUB"));
+        assert_eq!(korean_vary, "accept-language");
+        assert_eq!(book_page_vary, "accept-language");
+    }
+
+    #[tokio::test]
+    async fn should_render_html_404_for_unknown_page() {
+        // Given: The full route tree
+        let (filter, _library) = setup().await;
+
+        // When: A browser requests an unknown page
+        let response = warp::test::request()
+            .path("/nonsense")
+            .header("accept", "text/html")
+            .reply(&filter)
+            .await;
+
+        // Then: Should get the styled 404 page
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = String::from_utf8(response.body().to_vec()).unwrap();
+        assert!(body.contains("<!DOCTYPE html>"));
+        assert!(body.contains("404 Not Found"));
+    }
+
+    #[tokio::test]
+    async fn should_return_json_404_for_unknown_api_path() {
+        // Given: The full route tree
+        let (filter, _library) = setup().await;
+
+        // When: Requesting an unknown API path
+        let response = warp::test::request()
+            .path("/api/nonsense")
+            .reply(&filter)
+            .await;
+
+        // Then: Should get a JSON error body
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body, serde_json::json!({"error": "not found"}));
+    }
+
+    #[tokio::test]
+    async fn should_serve_openapi_document() {
+        // Given: The full route tree
+        let (filter, _library) = setup().await;
+
+        // When: Requesting the API description
+        let response = warp::test::request()
+            .path("/api/openapi.json")
+            .reply(&filter)
+            .await;
+
+        // Then: Should be an OpenAPI 3 JSON document
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["openapi"], "3.0.3");
+    }
+
+    #[tokio::test]
+    async fn should_report_running_version() {
+        // Given: The full route tree
+        let (filter, _library) = setup().await;
+
+        // When: Asking which build is running
+        let response = warp::test::request()
+            .path("/api/version")
+            .reply(&filter)
+            .await;
+
+        // Then: The crate version comes back, with the optional fields present
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert!(body.get("git_commit").is_some());
+        assert!(body.get("build_timestamp").is_some());
+    }
+
+    #[tokio::test]
+    async fn should_indent_json_and_errors_on_request() {
+        // Given: The full route tree, compact by default
+        let (filter, _library) = setup().await;
+
+        // When: Asking for the version and a missing book with `?pretty=1`
+        let version = warp::test::request()
+            .path("/api/version?pretty=1")
+            .reply(&filter)
+            .await;
+        let missing = warp::test::request()
+            .path("/api/books/missing?pretty=1")
+            .reply(&filter)
+            .await;
+
+        // Then: Both bodies are indented JSON, the error keeping its status
+        let body = std::str::from_utf8(version.body()).unwrap();
+        assert!(body.starts_with("{\n  \"version\": "), "{}", body);
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+        assert!(missing.body().starts_with(b"{\n  "));
+    }
+
+    #[tokio::test]
+    async fn should_echo_client_request_id() {
+        // Given: The full route tree
+        let (filter, _library) = setup().await;
+
+        // When: Sending a request with an X-Request-Id header
+        let response = warp::test::request()
+            .path("/api/openapi.json")
+            .header("x-request-id", "upload-42")
+            .reply(&filter)
+            .await;
+
+        // Then: The same id should come back
+        assert_eq!(response.headers()["x-request-id"], "upload-42");
+    }
+
+    #[tokio::test]
+    async fn should_send_security_headers_on_pages_and_errors() {
+        // Given: The full route tree with the default policy
+        let (filter, _library) = setup().await;
+
+        // When: Requesting the gallery and a missing book
+        let gallery = warp::test::request().path("/").reply(&filter).await;
+        let missing = warp::test::request()
+            .path("/api/books/missing")
+            .reply(&filter)
+            .await;
+
+        // Then: Both carry the headers, including the policy
+        for response in [gallery, missing] {
+            assert_eq!(response.headers()["x-content-type-options"], "nosniff");
+            assert_eq!(response.headers()["x-frame-options"], "DENY");
+            assert_eq!(
+                response.headers()["content-security-policy"],
+                DEFAULT_CONTENT_SECURITY_POLICY
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn should_generate_request_id_when_absent() {
+        // Given: The full route tree
+        let (filter, _library) = setup().await;
+
+        // When: Sending a request without an id, including one that fails
+        let response = warp::test::request()
+            .path("/api/books/missing")
+            .reply(&filter)
+            .await;
+
+        // Then: A generated UUID should be returned
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let id = response.headers()["x-request-id"].to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(id).is_ok());
+    }
+
+    #[tokio::test]
+    async fn should_return_json_404_for_missing_book() {
+        // Given: An empty library
+        let (filter, _library) = setup().await;
+
+        // When: Requesting a book that does not exist
+        let response = warp::test::request()
+            .path("/api/books/missing")
+            .reply(&filter)
+            .await;
+
+        // Then: Should be a 404 rather than a 500
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert!(body["error"].as_str().unwrap().contains("missing"));
+    }
+
+    #[tokio::test]
+    async fn should_include_metadata_completeness_in_book_detail() {
+        // Given: A book with a title and author only
+        let (filter, library) = setup().await;
+        let mut book = Book::new("Sparse".to_string(), "/sparse.epub".to_string());
+        book.author = Some("Someone".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+
+        // When: Requesting its details
+        let response = warp::test::request()
+            .path(&format!("/api/books/{}", book.id))
+            .reply(&filter)
+            .await;
+
+        // Then: The derived score accompanies the stored fields
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["title"], "Sparse");
+        assert_eq!(body["metadata_completeness"], 35);
+    }
+
+    #[tokio::test]
+    async fn should_include_subjects_progress_and_collections_in_book_detail() {
+        // Given: A book with a subject, opened in the reader and kept in a collection, and an
+        // unopened one
+        let (filter, library) = setup().await;
+        let book = Book::new("Opened".to_string(), "/opened.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        book_repository::insert_subject(&library.pool, &book.id, "Fantasy")
+            .await
+            .unwrap();
+        progress_repository::mark_opened(&library.pool, &book.id)
+            .await
+            .unwrap();
+        let shelf = crate::collection_repository::create_collection(&library.pool, "Shelf")
+            .await
+            .unwrap();
+        crate::collection_repository::add_book(&library.pool, shelf.id, &book.id)
+            .await
+            .unwrap();
+        let unread = Book::new("Unread".to_string(), "/unread.epub".to_string());
+        book_repository::insert(&library.pool, &unread)
+            .await
+            .unwrap();
+
+        // When: Requesting both details
+        let opened = warp::test::request()
+            .path(&format!("/api/books/{}", book.id))
+            .reply(&filter)
+            .await;
+        let unread = warp::test::request()
+            .path(&format!("/api/books/{}", unread.id))
+            .reply(&filter)
+            .await;
+        let opened: serde_json::Value = serde_json::from_slice(opened.body()).unwrap();
+        let unread: serde_json::Value = serde_json::from_slice(unread.body()).unwrap();
+
+        // Then: Related records are joined in one payload
+        assert_eq!(opened["subjects"], serde_json::json!(["Fantasy"]));
+        assert!(opened["reading_progress"]["opened_at"].as_i64().unwrap() > 0);
+        assert_eq!(opened["collections"][0]["name"], "Shelf");
+        assert_eq!(opened["collections"][0]["book_count"], 1);
+        assert_eq!(unread["subjects"], serde_json::json!([]));
+        assert!(unread["reading_progress"].is_null());
+        assert_eq!(unread["collections"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn should_finish_and_reset_reading_progress() {
+        // Given: A book in the library
+        let (filter, library) = setup().await;
+        let book = Book::new("Finished".to_string(), "/finished.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        let progress_path = |action: &str| format!("/api/books/{}/progress/{}", book.id, action);
+
+        // When: Marking it finished, viewing the gallery, resetting it and finishing a missing book
+        let finished = warp::test::request()
+            .method("POST")
+            .path(&progress_path("finish"))
+            .reply(&filter)
+            .await;
+        let gallery = warp::test::request().path("/").reply(&filter).await;
+        let reset = warp::test::request()
+            .method("POST")
+            .path(&progress_path("reset"))
+            .reply(&filter)
+            .await;
+        let missing = warp::test::request()
+            .method("POST")
+            .path("/api/books/missing/progress/finish")
+            .reply(&filter)
+            .await;
+
+        // Then: The finish is recorded and badged, and the reset leaves the book unread
+        assert_eq!(finished.status(), StatusCode::OK);
+        let progress: serde_json::Value = serde_json::from_slice(finished.body()).unwrap();
+        assert!(progress["finished_at"].as_i64().unwrap() > 0);
+        assert!(std::str::from_utf8(gallery.body())
+            .unwrap()
+            .contains(r#"class="finished-badge""#));
+        assert_eq!(reset.status(), StatusCode::NO_CONTENT);
+        assert!(
+            progress_repository::find_by_book_id(&library.pool, &book.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_abort_slow_upload_with_408() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Given: A running server with a short upload timeout
+        let (filter, library) = setup_with_upload_settings(UploadSettings {
+            timeout: Duration::from_millis(200),
+            ..UploadSettings::for_tests()
+        })
+        .await;
+        let (addr, server) = warp::serve(filter).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        // When: A client sends only part of the promised body and then stalls
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let partial_body = "--XYZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"slow.epub\"\r\n\r\nPK";
+        let request = format!(
+            "POST /upload HTTP/1.1\r\nHost: localhost\r\nAccept: application/json\r\nContent-Type: multipart/form-data; boundary=XYZ\r\nContent-Length: 10000\r\n\r\n{}",
+            partial_body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut buffer = vec![0; 1024];
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buffer))
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Then: The server should give up with 408 and leave no stored files behind
+        let response = String::from_utf8_lossy(&buffer[..read]);
+        assert!(response.starts_with("HTTP/1.1 408"), "{}", response);
+        let books_dir = library.temp_dir.path().join("data").join("books");
+        assert_eq!(std::fs::read_dir(books_dir).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_serve_reader_content_from_cache_on_repeat_visits() {
+        // Given: A stored book
+        let (filter, library) = setup().await;
+        let book = Book::new("Cached".to_string(), "/cached.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        let epub = TestEpub::new("Cached").build();
+        library.storage.save_epub(&book.id, &epub).unwrap();
+
+        // When: Reading it, losing the EPUB file, and reading again
+        let path = format!("/reader/{}", book.id);
+        let first = warp::test::request().path(&path).reply(&filter).await;
+        library.storage.delete_epub(&book.id).unwrap();
+        let second = warp::test::request().path(&path).reply(&filter).await;
+
+        // Then: The second visit should be served from the cache
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(second.status(), StatusCode::OK);
+        assert_eq!(first.body(), second.body());
+        assert!(String::from_utf8_lossy(second.body()).contains("Once upon a time."));
+    }
+
+    #[tokio::test]
+    async fn should_drop_cached_content_when_book_is_deleted() {
+        // Given: A book that has been read once
+        let (filter, library) = setup().await;
+        let book = Book::new("Deleted".to_string(), "/deleted.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        library
+            .storage
+            .save_epub(&book.id, &TestEpub::new("Deleted").build())
+            .unwrap();
+        let path = format!("/reader/{}", book.id);
+        warp::test::request().path(&path).reply(&filter).await;
+
+        // When: Deleting it and re-adding the row without a file
+        let delete = warp::test::request()
+            .method("DELETE")
+            .path(&format!("/api/books/{}", book.id))
+            .reply(&filter)
+            .await;
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        let reread = warp::test::request().path(&path).reply(&filter).await;
+
+        // Then: The reader should not serve the old content
+        assert_eq!(delete.status(), StatusCode::OK);
+        assert_ne!(reread.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_answer_unchanged_gallery_with_304_until_a_book_is_deleted() {
+        // Given: A library whose books last changed at a known time
+        let (filter, library) = setup().await;
+        let mut kept = Book::new("Kept".to_string(), "/kept.epub".to_string());
+        kept.created_at = 1_000;
+        kept.updated_at = 1_000;
+        let mut removed = Book::new("Removed".to_string(), "/removed.epub".to_string());
+        removed.created_at = 500;
+        removed.updated_at = 500;
+        book_repository::insert(&library.pool, &kept).await.unwrap();
+        book_repository::insert(&library.pool, &removed)
+            .await
+            .unwrap();
+        let since = crate::last_modified::http_date(1_000);
+
+        // When: Polling the gallery before and after deleting the older book
+        let fresh = warp::test::request().path("/").reply(&filter).await;
+        let unchanged = warp::test::request()
+            .path("/")
+            .header("if-modified-since", &since)
+            .reply(&filter)
+            .await;
+        warp::test::request()
+            .method("DELETE")
+            .path(&format!("/api/books/{}", removed.id))
+            .reply(&filter)
+            .await;
+        let after_delete = warp::test::request()
+            .path("/")
+            .header("if-modified-since", &since)
+            .reply(&filter)
+            .await;
+
+        // Then: Only the poll between the two changes is a bodiless 304
+        assert_eq!(fresh.status(), StatusCode::OK);
+        assert_eq!(fresh.headers()["last-modified"], since.as_str());
+        assert_eq!(unchanged.status(), StatusCode::NOT_MODIFIED);
+        assert!(unchanged.body().is_empty());
+        assert_eq!(unchanged.headers()["vary"], "accept-language");
+        assert_eq!(after_delete.status(), StatusCode::OK);
+        assert!(String::from_utf8_lossy(after_delete.body()).contains("Kept"));
+    }
+
+    #[tokio::test]
+    async fn should_answer_unchanged_book_detail_with_304_until_it_is_read() {
+        // Given: A book last changed at a known time
+        let (filter, library) = setup().await;
+        let mut book = Book::new("Polled".to_string(), "/polled.epub".to_string());
+        book.created_at = 1_000;
+        book.updated_at = 1_000;
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        let path = format!("/api/books/{}", book.id);
+        let since = crate::last_modified::http_date(1_000);
+
+        // When: Polling it with the date of the client copy, before and after opening it
+        let unchanged = warp::test::request()
+            .path(&path)
+            .header("if-modified-since", &since)
+            .reply(&filter)
+            .await;
+        progress_repository::mark_opened(&library.pool, &book.id)
+            .await
+            .unwrap();
+        let opened = warp::test::request()
+            .path(&path)
+            .header("if-modified-since", &since)
+            .reply(&filter)
+            .await;
+
+        // Then: Opening the book changes its detail response
+        assert_eq!(unchanged.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(unchanged.headers()["last-modified"], since.as_str());
+        assert_eq!(opened.status(), StatusCode::OK);
+        assert_ne!(opened.headers()["last-modified"], since.as_str());
+    }
+
+    #[tokio::test]
+    async fn should_serve_routes_under_configured_base_path() {
+        // Given: A route tree served under a subpath
+        let (filter, library) = setup_with_settings(UploadSettings::for_tests(), "/ezbooks").await;
+        let book = Book::new("Proxied".to_string(), "/proxied.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+
+        // When: Requesting pages with and without the prefix
+        let gallery = warp::test::request().path("/ezbooks/").reply(&filter).await;
+        let detail = warp::test::request()
+            .path(&format!("/ezbooks/api/books/{}", book.id))
+            .reply(&filter)
+            .await;
+        let unprefixed = warp::test::request()
+            .path("/")
+            .header("accept", "text/html")
+            .reply(&filter)
+            .await;
+        let missing_api = warp::test::request()
+            .path("/ezbooks/api/books/missing")
+            .header("accept", "text/html")
+            .reply(&filter)
+            .await;
+
+        // Then: Prefixed routes work and generated URLs carry the prefix
+        assert_eq!(gallery.status(), StatusCode::OK);
+        let html = String::from_utf8_lossy(gallery.body());
+        assert!(html.contains(&format!(r#"href="/ezbooks/reader/{}""#, book.id)));
+        assert!(html.contains(r#"data-base-path="/ezbooks""#));
+        assert_eq!(detail.status(), StatusCode::OK);
+        assert_eq!(unprefixed.status(), StatusCode::NOT_FOUND);
+        assert!(String::from_utf8_lossy(unprefixed.body()).contains(r#"href="/ezbooks/""#));
+        assert_eq!(missing_api.status(), StatusCode::NOT_FOUND);
+        assert_eq!(missing_api.headers()["content-type"], "application/json");
+    }
+
+    #[tokio::test]
+    async fn should_serve_next_unread_book_until_subject_is_read() {
+        // Given: A stored book tagged with a subject
+        let (filter, library) = setup().await;
+        let book = Book::new("Queued".to_string(), "/queued.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        book_repository::insert_subject(&library.pool, &book.id, "Mystery")
+            .await
+            .unwrap();
+        library
+            .storage
+            .save_epub(&book.id, &TestEpub::new("Queued").build())
+            .unwrap();
+
+        // When: Asking for the next book before and after opening it
+        let before = warp::test::request()
+            .path("/api/books/next?subject=Mystery")
+            .reply(&filter)
+            .await;
+        warp::test::request()
+            .path(&format!("/reader/{}", book.id))
+            .reply(&filter)
+            .await;
+        let after = warp::test::request()
+            .path("/api/books/next?subject=Mystery")
+            .reply(&filter)
+            .await;
+
+        // Then: The book is offered once, then the subject is exhausted
+        assert_eq!(before.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(before.body()).unwrap();
+        assert_eq!(body["id"], book.id.as_str());
+        assert_eq!(after.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_reject_next_unread_without_subject() {
+        // Given: The routes
+        let (filter, _library) = setup().await;
+
+        // When: Omitting the subject
+        let response = warp::test::request()
+            .path("/api/books/next")
+            .reply(&filter)
+            .await;
+
+        // Then: Should be a bad request
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_return_field_errors_for_invalid_update() {
+        // Given: A stored book
+        let (filter, library) = setup().await;
+        let book = Book::new("Editable".to_string(), "/editable.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+
+        // When: Sending an update with invalid fields
+        let response = warp::test::request()
+            .method("PUT")
+            .path(&format!("/api/books/{}", book.id))
+            .json(&serde_json::json!({ "page_count": -5, "isbn_13": "9780306406158" }))
+            .reply(&filter)
+            .await;
+
+        // Then: Should be 422 with per-field errors and nothing stored
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["errors"]["page_count"], "must not be negative");
+        assert_eq!(body["errors"]["isbn_13"], "has an invalid check digit");
+        let stored = book_repository::find_by_id(&library.pool, &book.id)
+            .await
+            .unwrap();
+        assert_eq!(stored.page_count, None);
+    }
+
+    #[tokio::test]
+    async fn should_apply_valid_update() {
+        // Given: A stored book
+        let (filter, library) = setup().await;
+        let book = Book::new("Before".to_string(), "/before.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+
+        // When: Sending a valid partial update
+        let response = warp::test::request()
+            .method("PUT")
+            .path(&format!("/api/books/{}", book.id))
+            .json(&serde_json::json!({ "title": "After", "page_count": 210 }))
+            .reply(&filter)
+            .await;
+
+        // Then: The updated book is returned and stored
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["title"], "After");
+        let stored = book_repository::find_by_id(&library.pool, &book.id)
+            .await
+            .unwrap();
+        assert_eq!(stored.page_count, Some(210));
+    }
+
+    #[tokio::test]
+    async fn should_edit_notes_without_showing_them_in_gallery() {
+        // Given: A stored book
+        let (filter, library) = setup().await;
+        let book = Book::new("Annotated".to_string(), "/annotated.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+
+        // When: Saving notes and viewing the book and the gallery
+        warp::test::request()
+            .method("PUT")
+            .path(&format!("/api/books/{}", book.id))
+            .json(&serde_json::json!({ "notes": "Reread <ch. 3> & compare" }))
+            .reply(&filter)
+            .await;
+        let detail = warp::test::request()
+            .path(&format!("/api/books/{}", book.id))
+            .reply(&filter)
+            .await;
+        let gallery = warp::test::request().path("/").reply(&filter).await;
+
+        // Then: The detail API carries the notes as plain text; the gallery card does not
+        let body: serde_json::Value = serde_json::from_slice(detail.body()).unwrap();
+        assert_eq!(body["notes"], "Reread <ch. 3> & compare");
+        assert!(!String::from_utf8_lossy(gallery.body()).contains("Reread"));
+    }
+
+    #[tokio::test]
+    async fn should_download_book_bundle() {
+        // Given: A stored book with its EPUB
+        let (filter, library) = setup().await;
+        let book = Book::new("Bundled".to_string(), "/bundled.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        library
+            .storage
+            .save_epub(&book.id, &TestEpub::new("Bundled").build())
+            .unwrap();
+
+        // When: Requesting the bundle
+        let response = warp::test::request()
+            .path(&format!("/api/books/{}/bundle", book.id))
+            .reply(&filter)
+            .await;
+
+        // Then: A named ZIP download should be returned
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/zip");
+        assert!(response.headers()["content-disposition"]
+            .to_str()
+            .unwrap()
+            .contains("filename=\"Bundled.zip\""));
+        assert!(response.body().starts_with(b"PK"));
+    }
+
+    #[tokio::test]
+    async fn should_return_404_for_bundle_without_stored_epub() {
+        // Given: A book row whose EPUB file is missing
+        let (filter, library) = setup().await;
+        let book = Book::new("Lost".to_string(), "/lost.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+
+        // When: Requesting the bundle
+        let response = warp::test::request()
+            .path(&format!("/api/books/{}/bundle", book.id))
+            .reply(&filter)
+            .await;
+
+        // Then: Should be not found
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_serve_plain_text_by_chapter() {
+        // Given: A stored two-chapter book
+        let (filter, library) = setup().await;
+        let book = Book::new("Spoken".to_string(), "/spoken.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        let epub = TestEpub::new("Spoken")
+            .chapters(&[
+                "<h1>One</h1><p>First &amp; <b>bold</b>.</p>",
+                "<p>Second.</p>",
+            ])
+            .build();
+        library.storage.save_epub(&book.id, &epub).unwrap();
+
+        // When: Requesting the whole text, one chapter, and a missing chapter
+        let path = format!("/reader/{}/text", book.id);
+        let whole = warp::test::request().path(&path).reply(&filter).await;
+        let second = warp::test::request()
+            .path(&format!("{}?chapter=2", path))
+            .reply(&filter)
+            .await;
+        let missing = warp::test::request()
+            .path(&format!("{}?chapter=3", path))
+            .reply(&filter)
+            .await;
+
+        // Then: Plain text with chapter markers is returned, chunkable by chapter
+        assert_eq!(whole.status(), StatusCode::OK);
+        assert_eq!(whole.headers()["content-type"], "text/plain; charset=utf-8");
+        assert_eq!(whole.headers()["x-chapter-count"], "2");
+        assert_eq!(
+            String::from_utf8_lossy(whole.body()),
+            "=== Chapter 1 ===\n\nOne\n\nFirst & bold.\n\n=== Chapter 2 ===\n\nSecond.\n"
+        );
+        assert_eq!(second.body().as_ref(), b"=== Chapter 2 ===\n\nSecond.\n");
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_serve_chapter_html_with_resume_headers() {
+        // Given: A stored two-chapter book
+        let (filter, library) = setup().await;
+        let book = Book::new("Long".to_string(), "/long.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        let epub = TestEpub::new("Long")
+            .chapters(&["<p>First.</p>", "<p>Second.</p>"])
+            .build();
+        library.storage.save_epub(&book.id, &epub).unwrap();
+
+        // When: Requesting each chapter and one past the end
+        let path = |index: usize| format!("/reader/{}/chapters/{}", book.id, index);
+        let first = warp::test::request().path(&path(0)).reply(&filter).await;
+        let last = warp::test::request().path(&path(1)).reply(&filter).await;
+        let missing = warp::test::request().path(&path(2)).reply(&filter).await;
+
+        // Then: Each chapter says where the next one is until the last
+        assert_eq!(first.status(), StatusCode::OK);
+        assert!(String::from_utf8_lossy(first.body()).contains("First."));
+        assert_eq!(first.headers()["x-next-chapter"], "1");
+        assert_eq!(first.headers()["x-chapter-count"], "2");
+        assert!(String::from_utf8_lossy(last.body()).contains("Second."));
+        assert!(last.headers().get("x-next-chapter").is_none());
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_cache_versioned_cover_urls_for_a_year() {
+        // Given: A stored cover
+        let (filter, library) = setup().await;
+        library.storage.save_cover("covered", b"jpeg").unwrap();
+
+        // When: Requesting it with and without a version
+        let versioned = warp::test::request()
+            .path("/covers/covered?v=abc")
+            .reply(&filter)
+            .await;
+        let plain = warp::test::request()
+            .path("/covers/covered")
+            .reply(&filter)
+            .await;
+
+        // Then: Only the versioned URL is cacheable long-term
+        assert_eq!(versioned.status(), StatusCode::OK);
+        assert_eq!(
+            versioned.headers()["cache-control"],
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(plain.headers()["cache-control"], "no-cache");
+    }
+
+    #[tokio::test]
+    async fn should_describe_cover_color_and_serve_gradient_placeholder() {
+        // Given: A book without a cover, and one whose cover has no stored color yet
+        let (filter, library) = setup().await;
+        let bare = Book::new("Bare".to_string(), "/bare.epub".to_string());
+        book_repository::insert(&library.pool, &bare).await.unwrap();
+        let mut covered = Book::new("Covered".to_string(), "/covered.epub".to_string());
+        covered.cover_image_path = Some(
+            library
+                .storage
+                .save_cover(&covered.id, TRANSPARENT_PIXEL_PNG)
+                .unwrap(),
+        );
+        book_repository::insert(&library.pool, &covered)
+            .await
+            .unwrap();
+        let color = |id: &str| {
+            warp::test::request()
+                .path(&format!("/api/books/{}/cover/color", id))
+                .reply(&filter)
+        };
+
+        // When: Asking for both colors and the bare book's placeholder
+        let bare_color = color(&bare.id).await;
+        let covered_color = color(&covered.id).await;
+        let placeholder = warp::test::request()
+            .path(&format!("/covers/{}/placeholder", bare.id))
+            .reply(&filter)
+            .await;
+
+        // Then: The bare book gets its title gradient, drawn by the placeholder, and the
+        // covered one its dominant color, stored for next time
+        let bare_color: serde_json::Value = serde_json::from_slice(bare_color.body()).unwrap();
+        let placeholder_colors = CoverColor::placeholder("Bare");
+        assert_eq!(bare_color["color"], placeholder_colors.color.as_str());
+        assert_eq!(
+            bare_color["gradient"],
+            serde_json::json!(placeholder_colors.gradient)
+        );
+        assert_eq!(placeholder.headers()["content-type"], "image/svg+xml");
+        assert_eq!(placeholder.body(), placeholder_svg("Bare").as_bytes());
+        let covered_color: serde_json::Value =
+            serde_json::from_slice(covered_color.body()).unwrap();
+        let stored = book_repository::find_by_id(&library.pool, &covered.id)
+            .await
+            .unwrap();
+        assert_eq!(covered_color["color"], stored.cover_color.unwrap().as_str());
+        assert!(covered_color["gradient"].is_null());
+        assert_eq!(color("missing").await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_serve_cover_with_its_stored_content_type() {
+        // Given: A book with a PNG cover and a legacy cover without a recorded type
+        let (filter, library) = setup().await;
+        let png = b"\x89PNG\r\n\x1a\n-png-bytes".to_vec();
+        let mut book = Book::new("Png".to_string(), "/png.epub".to_string());
+        book.cover_image_path = Some(library.storage.save_cover(&book.id, &png).unwrap());
+        book.cover_mime = Some("image/png".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        library.storage.save_cover("legacy", b"jpeg").unwrap();
+
+        // When: Requesting and probing both covers
+        let path = format!("/covers/{}", book.id);
+        let get = warp::test::request().path(&path).reply(&filter).await;
+        let head = warp::test::request()
+            .method("HEAD")
+            .path(&path)
+            .reply(&filter)
+            .await;
+        let legacy = warp::test::request()
+            .path("/covers/legacy")
+            .reply(&filter)
+            .await;
+
+        // Then: The PNG is labelled as PNG and the legacy cover as JPEG
+        assert_eq!(get.status(), StatusCode::OK);
+        assert_eq!(get.headers()["content-type"], "image/png");
+        assert_eq!(get.body().as_ref(), png.as_slice());
+        assert_eq!(head.headers()["content-type"], "image/png");
+        assert_eq!(legacy.headers()["content-type"], "image/jpeg");
+    }
+
+    #[tokio::test]
+    async fn should_replace_corrupt_cover_when_validating_on_read() {
+        // Given: Cover validation enabled and a book whose stored cover is truncated
+        let (filter, library) = setup_with_route_settings(RouteSettings {
+            upload: UploadSettings::for_tests(),
+            reader: ReaderSettings {
+                max_inline_bytes: 0,
+                rejoin_hyphenated_words: false,
+            },
+            listing: ListingDefaults::default(),
+            admin_token: Some(ADMIN_TOKEN.to_string()),
+            base_path: String::new(),
+            validate_covers: true,
+            content_search: false,
+            rate_limiter: None,
+            content_security_policy: None,
+            locked_metadata_fields: Vec::new(),
+            response_timing: ResponseTiming {
+                header: true,
+                slow_request_threshold: None,
+            },
+            pretty_json: false,
+        })
+        .await;
+        let mut book = Book::new("Truncated".to_string(), "/truncated.epub".to_string());
+        book.cover_image_path = Some(
+            library
+                .storage
+                .save_cover(&book.id, b"\xff\xd8\xff\xe0trunc")
+                .unwrap(),
+        );
+        book_repository::insert(&library.pool, &book).await.unwrap();
+
+        // When: Requesting the cover
+        let response = warp::test::request()
+            .path(&format!("/covers/{}?v=abc", book.id))
+            .reply(&filter)
+            .await;
+
+        // Then: A transparent pixel is served uncached and the cover is flagged
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "image/png");
+        assert_eq!(response.headers()["cache-control"], "no-cache");
+        assert_eq!(response.body().as_ref(), TRANSPARENT_PIXEL_PNG);
+        assert_eq!(
+            book_repository::find_ids_with_corrupt_cover(&library.pool)
+                .await
+                .unwrap(),
+            vec![book.id]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_page_books_with_opaque_cursor() {
+        // Given: Three stored books
+        let (filter, library) = setup().await;
+        for title in ["A", "B", "C"] {
+            let book = Book::new(title.to_string(), format!("/{}.epub", title));
+            book_repository::insert(&library.pool, &book).await.unwrap();
+        }
+
+        // When: Fetching pages of two
+        let first = warp::test::request()
+            .path("/api/books?limit=2")
+            .reply(&filter)
+            .await;
+        let first: serde_json::Value = serde_json::from_slice(first.body()).unwrap();
+        let cursor = first["next_cursor"].as_str().unwrap();
+        let second = warp::test::request()
+            .path(&format!("/api/books?limit=2&cursor={}", cursor))
+            .reply(&filter)
+            .await;
+        let second: serde_json::Value = serde_json::from_slice(second.body()).unwrap();
+        let invalid = warp::test::request()
+            .path("/api/books?cursor=not-a-cursor")
+            .reply(&filter)
+            .await;
+
+        // Then: The second page ends the scan and a bad cursor is a client error
+        assert_eq!(first["books"].as_array().unwrap().len(), 2);
+        assert_eq!(second["books"].as_array().unwrap().len(), 1);
+        assert!(second["next_cursor"].is_null());
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_apply_configured_default_sort_when_request_has_none() {
+        // Given: Listings sorted by size by default, and a small book added after a large one
+        let (filter, library) = setup_with_route_settings(RouteSettings {
+            upload: UploadSettings::for_tests(),
+            reader: ReaderSettings {
+                max_inline_bytes: 0,
+                rejoin_hyphenated_words: false,
+            },
+            listing: ListingDefaults {
+                sort: BookSort::Size,
+                page_size: 1,
+            },
+            admin_token: Some(ADMIN_TOKEN.to_string()),
+            base_path: String::new(),
+            validate_covers: false,
+            content_search: false,
+            rate_limiter: None,
+            content_security_policy: None,
+            locked_metadata_fields: Vec::new(),
+            response_timing: ResponseTiming {
+                header: true,
+                slow_request_threshold: None,
+            },
+            pretty_json: false,
+        })
+        .await;
+        for (title, size, created_at) in [("Large", 9000, 1), ("Small", 10, 2)] {
+            let mut book = Book::new(title.to_string(), format!("/{}.epub", title));
+            book.file_size_bytes = Some(size);
+            book.created_at = created_at;
+            book_repository::insert(&library.pool, &book).await.unwrap();
+        }
+        let titles = |body: &[u8]| -> Vec<String> {
+            let books: serde_json::Value = serde_json::from_slice(body).unwrap();
+            books
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|book| book["title"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        // When: Listing without and with an explicit sort, and paging without a limit
+        let default = warp::test::request()
+            .path("/api/books")
+            .reply(&filter)
+            .await;
+        let explicit = warp::test::request()
+            .path("/api/books?sort=created")
+            .reply(&filter)
+            .await;
+        let page = warp::test::request()
+            .path("/api/books?limit=1")
+            .reply(&filter)
+            .await;
+        let page: serde_json::Value = serde_json::from_slice(page.body()).unwrap();
+        let cursor = page["next_cursor"].as_str().unwrap();
+        let next = warp::test::request()
+            .path(&format!("/api/books?cursor={}", cursor))
+            .reply(&filter)
+            .await;
+        let next: serde_json::Value = serde_json::from_slice(next.body()).unwrap();
+
+        // Then: The default applies only when asked for nothing; pages stay newest first
+        assert_eq!(titles(default.body()), vec!["Large", "Small"]);
+        assert_eq!(titles(explicit.body()), vec!["Small", "Large"]);
+        assert_eq!(page["books"][0]["title"], "Small");
+        assert_eq!(next["books"].as_array().unwrap().len(), 1);
+        assert_eq!(next["books"][0]["title"], "Large");
+    }
+
+    #[tokio::test]
+    async fn should_answer_head_for_downloads_and_covers_without_body() {
+        // Given: A stored book with an EPUB but no cover
+        let (filter, library) = setup().await;
+        let book = Book::new("Probe".to_string(), "/probe.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        let epub = TestEpub::new("Probe").build();
+        library.storage.save_epub(&book.id, &epub).unwrap();
+        let path = format!("/api/books/{}/download", book.id);
+
+        // When: Probing and downloading the EPUB, and probing the missing cover
+        let head = warp::test::request()
+            .method("HEAD")
+            .path(&path)
+            .reply(&filter)
+            .await;
+        let get = warp::test::request().path(&path).reply(&filter).await;
+        let cover = warp::test::request()
+            .method("HEAD")
+            .path(&format!("/covers/{}", book.id))
+            .reply(&filter)
+            .await;
+
+        // Then: HEAD repeats the GET headers without a body; the missing cover is a 404
+        assert_eq!(head.status(), StatusCode::OK);
+        assert!(head.body().is_empty());
+        assert_eq!(get.body().len(), epub.len());
+        for header in [
+            "content-type",
+            "content-length",
+            "etag",
+            "content-disposition",
+        ] {
+            assert_eq!(head.headers()[header], get.headers()[header], "{}", header);
+        }
+        assert_eq!(head.headers()["content-type"], "application/epub+zip");
+        assert_eq!(
+            head.headers()["content-length"],
+            epub.len().to_string().as_str()
+        );
+        assert_eq!(cover.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_filter_books_by_date_added() {
+        // Given: An old and a new book
+        let (filter, library) = setup().await;
+        let mut old = Book::new("Old".to_string(), "/old.epub".to_string());
+        old.created_at = 1_000;
+        book_repository::insert(&library.pool, &old).await.unwrap();
+        let new = Book::new("New".to_string(), "/new.epub".to_string());
+        book_repository::insert(&library.pool, &new).await.unwrap();
+
+        // When: Asking for an early window, an inverted one, a page of a window, and a
+        // window of one format or in another order
+        let window = warp::test::request()
+            .path("/api/books?added_from=500&added_to=1500")
+            .reply(&filter)
+            .await;
+        let inverted = warp::test::request()
+            .path("/api/books?added_from=1500&added_to=500")
+            .reply(&filter)
+            .await;
+        let paged = warp::test::request()
+            .path("/api/books?added_from=500&limit=10")
+            .reply(&filter)
+            .await;
+        let formatted = warp::test::request()
+            .path("/api/books?added_from=500&format=pdf")
+            .reply(&filter)
+            .await;
+        let sorted = warp::test::request()
+            .path("/api/books?added_from=500&sort=size")
+            .reply(&filter)
+            .await;
+
+        // Then: Only the old book is listed, and the others are a 400
+        let body: serde_json::Value = serde_json::from_slice(window.body()).unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["id"], old.id.as_str());
+        for rejected in [inverted, paged, formatted, sorted] {
+            assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    fn multipart_epub(epub: &[u8]) -> Vec<u8> {
+        let mut body = b"--XYZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"book.epub\"\r\nContent-Type: application/epub+zip\r\n\r\n".to_vec();
+        body.extend_from_slice(epub);
+        body.extend_from_slice(b"\r\n--XYZ--\r\n");
+        body
+    }
+
+    fn multipart_with_fields(epub: &[u8], fields: &[(&str, &str)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (name, value) in fields {
+            body.extend_from_slice(
+                format!(
+                    "--XYZ\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                    name, value
+                )
+                .as_bytes(),
+            );
+        }
+        body.extend_from_slice(&multipart_epub(epub));
+        body
+    }
+
+    #[tokio::test]
+    async fn should_apply_upload_overrides_and_reject_bad_isbn() {
+        // Given: Uploads carrying title, author and ISBN fields, one with a bad check digit
+        let (filter, library) = setup().await;
+        let epub = TestEpub::new("Scanned Tilte").build();
+        let upload = |fields: &[(&str, &str)]| {
+            warp::test::request()
+                .method("POST")
+                .path("/upload")
+                .header("accept", "application/json")
+                .header("content-type", "multipart/form-data; boundary=XYZ")
+                .body(multipart_with_fields(&epub, fields))
+        };
+
+        // When: Uploading with each set of overrides
+        let valid = upload(&[
+            ("title", "Scanned Title"),
+            ("author", "Real Author"),
+            ("isbn", "978-0-306-40615-7"),
+        ])
+        .reply(&filter)
+        .await;
+        let invalid = upload(&[("isbn", "978-0-306-40615-8")])
+            .reply(&filter)
+            .await;
+
+        // Then: The overrides win over the EPUB and the bad ISBN is a field error
+        assert_eq!(valid.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(valid.body()).unwrap();
+        let book = book_repository::find_by_id(&library.pool, body["id"].as_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(book.title, "Scanned Title");
+        assert_eq!(book.author, Some("Real Author".to_string()));
+        assert_eq!(book.isbn_13, Some("9780306406157".to_string()));
+        assert_eq!(invalid.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: serde_json::Value = serde_json::from_slice(invalid.body()).unwrap();
+        assert_eq!(body["errors"]["isbn"], "has an invalid check digit");
+        assert_eq!(
+            book_repository::count_books(&library.pool).await.unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn should_add_and_remove_subjects_by_hand() {
+        // Given: A stored book without subjects
+        let (filter, library) = setup().await;
+        let book = Book::new("Tagged".to_string(), "/tagged.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        let subjects_path = format!("/api/books/{}/subjects", book.id);
+        let add = |path: &str, subject: &str| {
+            warp::test::request()
+                .method("POST")
+                .path(path)
+                .json(&serde_json::json!({ "subject": subject }))
+        };
+
+        // When: Adding a subject, repeating it, tagging an unknown book and removing it twice
+        let added = add(&subjects_path, " Science  Fiction")
+            .reply(&filter)
+            .await;
+        let duplicate = add(&subjects_path, "science fiction").reply(&filter).await;
+        let unknown = add("/api/books/missing/subjects", "Fantasy")
+            .reply(&filter)
+            .await;
+        let remove = || {
+            warp::test::request()
+                .method("DELETE")
+                .path(&format!("{}/Science%20Fiction", subjects_path))
+        };
+        let removed = remove().reply(&filter).await;
+        let removed_again = remove().reply(&filter).await;
+
+        // Then: The normalized subject is listed, duplicates conflict and removal is 204 once
+        assert_eq!(added.status(), StatusCode::CREATED);
+        let body: serde_json::Value = serde_json::from_slice(added.body()).unwrap();
+        assert_eq!(body, serde_json::json!(["Science Fiction"]));
+        assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+        assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+        assert_eq!(removed.status(), StatusCode::NO_CONTENT);
+        assert_eq!(removed_again.status(), StatusCode::NOT_FOUND);
+        assert!(
+            book_repository::find_subjects_by_book_id(&library.pool, &book.id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_store_tidied_title_when_epub_title_is_file_name() {
+        // Given: An EPUB whose title is the name of the uploaded file
+        let (filter, library) = setup().await;
+        let epub = TestEpub::new("book.epub").build();
+
+        // When: Uploading it, then resetting its metadata from the stored EPUB
+        let uploaded = warp::test::request()
+            .method("POST")
+            .path("/upload")
+            .header("content-type", "multipart/form-data; boundary=XYZ")
+            .body(multipart_epub(&epub))
+            .reply(&filter)
+            .await;
+        let uploaded: serde_json::Value = serde_json::from_slice(uploaded.body()).unwrap();
+        let id = uploaded["id"].as_str().unwrap();
+        let reset = warp::test::request()
+            .method("POST")
+            .path(&format!("/api/books/{}/reset-metadata", id))
+            .reply(&filter)
+            .await;
+
+        // Then: The extension is gone and the declared title is kept beside it
+        let book = book_repository::find_by_id(&library.pool, id)
+            .await
+            .unwrap();
+        assert_eq!(book.title, "book");
+        assert_eq!(book.raw_title.as_deref(), Some("book.epub"));
+        let reset: serde_json::Value = serde_json::from_slice(reset.body()).unwrap();
+        assert_eq!(reset["title"], "book");
+        assert_eq!(reset["raw_title"], "book.epub");
+    }
+
+    #[tokio::test]
+    async fn should_save_list_show_and_delete_bookmarks() {
+        // Given: An uploaded two-chapter book
+        let (filter, _library) = setup().await;
+        let epub = TestEpub::new("Marked")
+            .chapters(&["<p>First</p>", "<p>Second</p>"])
+            .build();
+        let uploaded = warp::test::request()
+            .method("POST")
+            .path("/upload")
+            .header("content-type", "multipart/form-data; boundary=XYZ")
+            .body(multipart_epub(&epub))
+            .reply(&filter)
+            .await;
+        let uploaded: serde_json::Value = serde_json::from_slice(uploaded.body()).unwrap();
+        let bookmarks_path = format!("/api/books/{}/bookmarks", uploaded["id"].as_str().unwrap());
+        let add = |body: serde_json::Value| {
+            warp::test::request()
+                .method("POST")
+                .path(&bookmarks_path)
+                .json(&body)
+        };
+
+        // When: Adding a valid and an invalid bookmark, listing, reading and deleting twice
+        let added = add(serde_json::json!({
+            "chapter_index": 1,
+            "scroll_fraction": 0.5,
+            "label": "Favourite passage"
+        }))
+        .reply(&filter)
+        .await;
+        let invalid = add(serde_json::json!({ "chapter_index": 0, "label": "" }))
+            .reply(&filter)
+            .await;
+        let listed = warp::test::request()
+            .path(&bookmarks_path)
+            .reply(&filter)
+            .await;
+        let reader = warp::test::request()
+            .path(&format!("/reader/{}", uploaded["id"].as_str().unwrap()))
+            .reply(&filter)
+            .await;
+        let added: serde_json::Value = serde_json::from_slice(added.body()).unwrap();
+        let delete = || {
+            warp::test::request()
+                .method("DELETE")
+                .path(&format!("{}/{}", bookmarks_path, added["id"]))
+        };
+        let deleted = delete().reply(&filter).await;
+        let deleted_again = delete().reply(&filter).await;
+
+        // Then: The bookmark is listed, linked from the reader, and deleted once
+        assert_eq!(added["label"], "Favourite passage");
+        assert_eq!(invalid.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let listed: serde_json::Value = serde_json::from_slice(listed.body()).unwrap();
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        assert_eq!(listed[0]["scroll_fraction"], 0.5);
+        let reader = String::from_utf8_lossy(reader.body()).to_string();
+        assert!(reader.contains(r#"<a id="chapter-1" class="chapter-anchor"></a>"#));
+        assert!(reader.contains(
+            r##"href="#chapter-1" data-chapter="1" data-scroll-fraction="0.5">Favourite passage"##
+        ));
+        assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+        assert_eq!(deleted_again.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_highlight_annotations_in_reader_and_loaded_chapters() {
+        // Given: An uploaded book with a repeated phrase in its second chapter
+        let (filter, _library) = setup().await;
+        let epub = TestEpub::new("Highlighted")
+            .chapters(&[
+                "<p>Opening words.</p>",
+                "<p>Once more. Then, <em>once</em>\n more with feeling.</p>",
+            ])
+            .build();
+        let uploaded = warp::test::request()
+            .method("POST")
+            .path("/upload")
+            .header("content-type", "multipart/form-data; boundary=XYZ")
+            .body(multipart_epub(&epub))
+            .reply(&filter)
+            .await;
+        let uploaded: serde_json::Value = serde_json::from_slice(uploaded.body()).unwrap();
+        let id = uploaded["id"].as_str().unwrap();
+        let annotations_path = format!("/api/books/{}/annotations", id);
+
+        // When: Highlighting the second "once more", recoloring it and reading the book
+        let added = warp::test::request()
+            .method("POST")
+            .path(&annotations_path)
+            .json(&serde_json::json!({
+                "chapter_index": 1,
+                "quote": "once more",
+                "prefix": "Then, ",
+                "suffix": " with",
+                "note": "Refrain"
+            }))
+            .reply(&filter)
+            .await;
+        assert_eq!(added.status(), StatusCode::CREATED);
+        let added: serde_json::Value = serde_json::from_slice(added.body()).unwrap();
+        let annotation_path = format!("{}/{}", annotations_path, added["id"]);
+        let updated = warp::test::request()
+            .method("PUT")
+            .path(&annotation_path)
+            .json(&serde_json::json!({ "color": "pink" }))
+            .reply(&filter)
+            .await;
+        let reader = warp::test::request()
+            .path(&format!("/reader/{}", id))
+            .reply(&filter)
+            .await;
+        let chapter = warp::test::request()
+            .path(&format!("/reader/{}/chapters/1", id))
+            .reply(&filter)
+            .await;
+        let listed = warp::test::request()
+            .path(&annotations_path)
+            .reply(&filter)
+            .await;
+
+        // Then: The update sticks and both pages mark only the anchored occurrence
+        let updated: serde_json::Value = serde_json::from_slice(updated.body()).unwrap();
+        assert_eq!(updated["color"], "pink");
+        assert_eq!(updated["note"], "Refrain");
+        let mark = format!(
+            r#"<mark class="highlight highlight-pink" data-annotation-id="{}" title="Refrain">"#,
+            added["id"]
+        );
+        for page in [reader.body(), chapter.body()] {
+            let page = String::from_utf8_lossy(page).to_string();
+            assert!(page.contains("<p>Once more. Then, <em>"));
+            assert!(page.contains(&format!("<em>{}once</mark></em>", mark)));
+            assert_eq!(page.matches("</mark>").count(), 2);
+        }
+        let listed: serde_json::Value = serde_json::from_slice(listed.body()).unwrap();
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_stream_chapters_as_ndjson() {
+        // Given: A stored book with a table of contents naming its first chapter
+        let (filter, library) = setup().await;
+        let book = Book::new("Streamed".to_string(), "/streamed.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        let epub = TestEpub::new("Streamed")
+            .chapters(&["<p>First</p><script>x()</script>", "<p>Second</p>"])
+            .toc(&["Opening"])
+            .build();
+        library.storage.save_epub(&book.id, &epub).unwrap();
+
+        // When: Streaming the book, and a book whose EPUB is gone
+        let response = warp::test::request()
+            .path(&format!("/reader/{}/stream", book.id))
+            .reply(&filter)
+            .await;
+        let missing = Book::new("Missing".to_string(), "/missing.epub".to_string());
+        book_repository::insert(&library.pool, &missing)
+            .await
+            .unwrap();
+        let broken = warp::test::request()
+            .path(&format!("/reader/{}/stream", missing.id))
+            .reply(&filter)
+            .await;
+
+        // Then: Each chapter is one sanitized JSON line, and the missing EPUB is an error
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+        let body = String::from_utf8_lossy(response.body()).to_string();
+        let lines: Vec<serde_json::Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["index"], 0);
+        assert_eq!(lines[0]["label"], "Opening");
+        assert!(lines[0]["html"].as_str().unwrap().contains("<p>First</p>"));
+        assert!(!body.contains("<script>"));
+        assert_eq!(lines[1]["index"], 1);
+        assert!(lines[1]["label"].is_null());
+        assert!(broken.status().is_client_error());
+    }
+
+    #[tokio::test]
+    async fn should_expose_audio_narration_of_uploaded_book() {
+        // Given: An uploaded EPUB3 with a media overlay
+        let (filter, _library) = setup().await;
+        let epub = TestEpub::new("Narrated")
+            .package_version("3.0")
+            .resource("audio/chapter1.mp3", "audio/mpeg", b"ID3".to_vec())
+            .resource("chapter1.smil", "application/smil+xml", b"<smil/>".to_vec())
+            .build();
+        let uploaded = warp::test::request()
+            .method("POST")
+            .path("/upload")
+            .header("content-type", "multipart/form-data; boundary=XYZ")
+            .body(multipart_epub(&epub))
+            .reply(&filter)
+            .await;
+        let uploaded: serde_json::Value = serde_json::from_slice(uploaded.body()).unwrap();
+        let id = uploaded["id"].as_str().unwrap();
+
+        // When: Fetching its details and overlay resources
+        let detail = warp::test::request()
+            .path(&format!("/api/books/{}", id))
+            .reply(&filter)
+            .await;
+        let overlays = warp::test::request()
+            .path(&format!("/api/books/{}/media-overlays", id))
+            .reply(&filter)
+            .await;
+
+        // Then: The book is flagged with its version and both resources are listed
+        let detail: serde_json::Value = serde_json::from_slice(detail.body()).unwrap();
+        assert_eq!(detail["has_audio_narration"], true);
+        assert_eq!(detail["epub_version"], "3.0");
+        assert_eq!(overlays.status(), StatusCode::OK);
+        let overlays: serde_json::Value = serde_json::from_slice(overlays.body()).unwrap();
+        assert_eq!(overlays[0]["media_type"], "audio/mpeg");
+        assert_eq!(overlays[1]["path"], "OEBPS/chapter1.smil");
+    }
+
+    #[tokio::test]
+    async fn should_replay_upload_for_repeated_idempotency_key() {
+        // Given: An EPUB upload sent with an Idempotency-Key
+        let (filter, library) = setup().await;
+        let body = multipart_epub(&TestEpub::new("Retried").build());
+        let upload = || {
+            warp::test::request()
+                .method("POST")
+                .path("/upload")
+                .header("content-type", "multipart/form-data; boundary=XYZ")
+                .header("idempotency-key", "retry-1")
+                .body(body.clone())
+        };
+
+        // When: The client retries the same upload
+        let first = upload().reply(&filter).await;
+        let second = upload().reply(&filter).await;
+
+        // Then: The retry should get the original response without a second book
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(second.status(), StatusCode::OK);
+        let first: serde_json::Value = serde_json::from_slice(first.body()).unwrap();
+        let second: serde_json::Value = serde_json::from_slice(second.body()).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(
+            book_repository::count_books(&library.pool).await.unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn should_require_admin_token_for_bulk_enrichment() {
+        // Given: A book missing metadata, with an ISBN OpenLibrary can't be reached for
+        let (filter, library) = setup().await;
+        let mut book = Book::new("Sparse".to_string(), "/sparse.epub".to_string());
+        book.isbn_13 = Some("9780140328721".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        let enrich = || {
+            warp::test::request()
+                .method("POST")
+                .path("/api/admin/enrich-missing")
+        };
+
+        // When: Requesting bulk enrichment without and with the token
+        let anonymous = enrich().reply(&filter).await;
+        let authorized = enrich()
+            .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+            .reply(&filter)
+            .await;
+
+        // Then: Only the authorized request runs and reports the failed lookup
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(authorized.status(), StatusCode::OK);
+        let report: serde_json::Value = serde_json::from_slice(authorized.body()).unwrap();
+        assert_eq!(
+            report,
+            serde_json::json!({ "enriched": 0, "failed": 1, "skipped": 0 })
+        );
+    }
+
+    #[tokio::test]
+    async fn should_require_admin_token_for_integrity_report() {
+        // Given: A book whose EPUB is missing from storage
+        let (filter, library) = setup().await;
+        let book = Book::new("Lost".to_string(), "/nowhere/lost.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+
+        // When: Requesting the report without, with a wrong, and with the right token
+        let path = "/api/admin/integrity";
+        let anonymous = warp::test::request().path(path).reply(&filter).await;
+        let wrong = warp::test::request()
+            .path(path)
+            .header("authorization", "Bearer nope")
+            .reply(&filter)
+            .await;
+        let authorized = warp::test::request()
+            .path(path)
+            .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+            .reply(&filter)
+            .await;
+
+        // Then: Only the token holder gets the report
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(authorized.status(), StatusCode::OK);
+        let report: serde_json::Value = serde_json::from_slice(authorized.body()).unwrap();
+        assert_eq!(report["missing_epubs"], serde_json::json!([book.id]));
+        assert_eq!(report["fixed"], false);
+    }
+
+    #[tokio::test]
+    async fn should_render_book_page_with_subjects() {
+        // Given: A tagged book
+        let (filter, library) = setup().await;
+        let book = Book::new("Tagged".to_string(), "/tagged.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        book_repository::insert_subject(&library.pool, &book.id, "Poetry")
+            .await
+            .unwrap();
+
+        // When: Requesting its page and one of a book that does not exist
+        let page = warp::test::request()
+            .path(&format!("/books/{}", book.id))
+            .reply(&filter)
+            .await;
+        let missing = warp::test::request()
+            .path("/books/no-such-book")
+            .reply(&filter)
+            .await;
+
+        // Then: The page lists the subject and carries structured data
+        assert_eq!(page.status(), StatusCode::OK);
+        let html = String::from_utf8_lossy(page.body());
+        assert!(html.contains("<li>Poetry</li>"));
+        assert!(html.contains(r#"<script type="application/ld+json">"#));
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_browse_books_by_percent_encoded_author() {
+        // Given: Two books by an author with special characters and one by another
+        let (filter, library) = setup().await;
+        for (title, author) in [
+            ("First", "O'Brien & Sons/Co"),
+            ("Second", "O'Brien & Sons/Co"),
+            ("Other", "Someone Else"),
+        ] {
+            let mut book = Book::new(title.to_string(), format!("/{}.epub", title));
+            book.author = Some(author.to_string());
+            book_repository::insert(&library.pool, &book).await.unwrap();
+        }
+
+        // When: Opening the author's gallery through its encoded name and listing authors
+        let gallery = warp::test::request()
+            .path("/authors/O%27Brien%20%26%20Sons%2FCo")
+            .reply(&filter)
+            .await;
+        let authors = warp::test::request()
+            .path("/api/authors")
+            .reply(&filter)
+            .await;
+        let invalid = warp::test::request()
+            .path("/authors/%FF")
+            .reply(&filter)
+            .await;
+
+        // Then: Only the author's books are shown and every author is counted
+        assert_eq!(gallery.status(), StatusCode::OK);
+        let html = String::from_utf8_lossy(gallery.body());
+        assert!(html.contains("First") && html.contains("Second"));
+        assert!(!html.contains("Other"));
+        let authors: serde_json::Value = serde_json::from_slice(authors.body()).unwrap();
+        assert_eq!(
+            authors,
+            serde_json::json!([
+                { "name": "O'Brien & Sons/Co", "book_count": 2 },
+                { "name": "Someone Else", "book_count": 1 }
+            ])
+        );
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_verify_stored_epubs_for_admins() {
+        // Given: A book whose stored EPUB was overwritten with garbage
+        let (filter, library) = setup().await;
+        let mut book = Book::new("Garbled".to_string(), String::new());
+        book.epub_file_path = library.storage.save_epub(&book.id, b"not a zip").unwrap();
+        book_repository::insert(&library.pool, &book).await.unwrap();
+
+        // When: Verifying without and with the admin token
+        let anonymous = warp::test::request()
+            .method("POST")
+            .path("/api/admin/verify")
+            .reply(&filter)
+            .await;
+        let authorized = warp::test::request()
+            .method("POST")
+            .path("/api/admin/verify")
+            .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+            .reply(&filter)
+            .await;
+
+        // Then: Only the token holder learns the EPUB is unreadable
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(authorized.status(), StatusCode::OK);
+        let results: serde_json::Value = serde_json::from_slice(authorized.body()).unwrap();
+        assert_eq!(
+            results,
+            serde_json::json!([{ "id": book.id, "title": "Garbled", "status": "unreadable" }])
+        );
+    }
+
+    #[tokio::test]
+    async fn should_regenerate_covers_for_admins_and_report_progress() {
+        // Given: A book whose EPUB is missing
+        let (filter, library) = setup().await;
+        let book = Book::new("Gone".to_string(), "/gone.epub".to_string());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+
+        // When: Starting a regeneration without and with the token, then polling it
+        let anonymous = warp::test::request()
+            .method("POST")
+            .path("/api/admin/regenerate-covers")
+            .reply(&filter)
+            .await;
+        let started = warp::test::request()
+            .method("POST")
+            .path("/api/admin/regenerate-covers")
+            .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+            .reply(&filter)
+            .await;
+        let mut progress = serde_json::Value::Null;
+        for _ in 0..200 {
+            let polled = warp::test::request()
+                .path("/api/admin/regenerate-covers")
+                .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+                .reply(&filter)
+                .await;
+            progress = serde_json::from_slice(polled.body()).unwrap();
+            if progress["running"] == false {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+
+        // Then: The run is accepted for the token holder and skips the book
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(started.status(), StatusCode::ACCEPTED);
+        assert_eq!(progress["total"], 1);
+        assert_eq!(progress["skipped"], 1);
+        assert_eq!(progress["regenerated"], 0);
+    }
+
+    #[test]
+    fn should_compare_tokens_exactly() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secret", "secreT"));
+        assert!(!tokens_match("secret", "secret2"));
+    }
+
+    #[tokio::test]
+    async fn should_keep_original_filename_for_detail_and_download() {
+        // Given: An EPUB uploaded as "book.epub" under a different title
+        let (filter, _library) = setup().await;
+        let epub = TestEpub::new("Stored Under Id").build();
+        let uploaded = warp::test::request()
+            .method("POST")
+            .path("/upload")
+            .header("content-type", "multipart/form-data; boundary=XYZ")
+            .body(multipart_epub(&epub))
+            .reply(&filter)
+            .await;
+        let uploaded: serde_json::Value = serde_json::from_slice(uploaded.body()).unwrap();
+        let id = uploaded["id"].as_str().unwrap();
+
+        // When: Fetching its details and downloading it
+        let detail = warp::test::request()
+            .path(&format!("/api/books/{}", id))
+            .reply(&filter)
+            .await;
+        let download = warp::test::request()
+            .path(&format!("/api/books/{}/download", id))
+            .reply(&filter)
+            .await;
+
+        // Then: Both use the uploaded filename rather than the title
+        let detail: serde_json::Value = serde_json::from_slice(detail.body()).unwrap();
+        assert_eq!(detail["original_filename"], "book.epub");
+        assert!(download.headers()["content-disposition"]
+            .to_str()
+            .unwrap()
+            .contains("filename=\"book.epub\""));
+    }
+
+    #[tokio::test]
+    async fn should_reorder_books_within_a_collection() {
+        // Given: A collection holding two books in the order they were added
+        let (filter, library) = setup().await;
+        let first = Book::new("First".to_string(), "/first.epub".to_string());
+        let second = Book::new("Second".to_string(), "/second.epub".to_string());
+        for book in [&first, &second] {
+            book_repository::insert(&library.pool, book).await.unwrap();
+        }
+        let created = warp::test::request()
+            .method("POST")
+            .path("/api/collections")
+            .json(&serde_json::json!({ "name": "Series" }))
+            .reply(&filter)
+            .await;
+        let created: serde_json::Value = serde_json::from_slice(created.body()).unwrap();
+        let collection_path = format!("/api/collections/{}", created["id"]);
+        for book in [&first, &second] {
+            let added = warp::test::request()
+                .method("POST")
+                .path(&format!("{}/books", collection_path))
+                .json(&serde_json::json!({ "book_id": book.id }))
+                .reply(&filter)
+                .await;
+            assert_eq!(added.status(), StatusCode::NO_CONTENT);
+        }
+        let reorder = |book_ids: Vec<&str>| {
+            warp::test::request()
+                .method("PUT")
+                .path(&format!("{}/order", collection_path))
+                .json(&serde_json::json!({ "book_ids": book_ids }))
+        };
+
+        // When: Swapping them, then sending an order that leaves one out
+        let reordered = reorder(vec![&second.id, &first.id]).reply(&filter).await;
+        let incomplete = reorder(vec![&first.id]).reply(&filter).await;
+        let detail = warp::test::request()
+            .path(&collection_path)
+            .reply(&filter)
+            .await;
+
+        // Then: The swap is kept and the incomplete order is refused
+        assert_eq!(reordered.status(), StatusCode::OK);
+        assert_eq!(incomplete.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let detail: serde_json::Value = serde_json::from_slice(detail.body()).unwrap();
+        assert_eq!(detail["name"], "Series");
+        assert_eq!(detail["book_count"], 2);
+        assert_eq!(detail["books"][0]["title"], "Second");
+        assert_eq!(detail["books"][1]["title"], "First");
+    }
+
+    #[tokio::test]
+    async fn should_replace_book_file_keeping_id_and_relations() {
+        // Given: An uploaded book with a subject, in a collection
+        let (filter, library) = setup().await;
+        let uploaded = warp::test::request()
+            .method("POST")
+            .path("/upload")
+            .header("content-type", "multipart/form-data; boundary=XYZ")
+            .body(multipart_epub(&TestEpub::new("Draft").build()))
+            .reply(&filter)
+            .await;
+        let uploaded: serde_json::Value = serde_json::from_slice(uploaded.body()).unwrap();
+        let id = uploaded["id"].as_str().unwrap().to_string();
+        book_repository::insert_subject(&library.pool, &id, "Keep me")
+            .await
+            .unwrap();
+        let collection = crate::collection_repository::create_collection(&library.pool, "Shelf")
+            .await
+            .unwrap();
+        crate::collection_repository::add_book(&library.pool, collection.id, &id)
+            .await
+            .unwrap();
+        let replacement = TestEpub::new("Final Edition")
+            .author("Careful Editor")
+            .build();
+        let replace = |body: Vec<u8>| {
+            warp::test::request()
+                .method("PUT")
+                .path(&format!("/api/books/{}/file", id))
+                .header("content-type", "multipart/form-data; boundary=XYZ")
+                .body(body)
+        };
+
+        // When: Sending a broken file, a stale conditional one, then a good one
+        let broken = replace(multipart_epub(b"PK not really an epub"))
+            .reply(&filter)
+            .await;
+        let stale = replace(multipart_epub(&replacement))
+            .header("if-unmodified-since", "Sun, 06 Nov 1994 08:49:37 GMT")
+            .reply(&filter)
+            .await;
+        let replaced = replace(multipart_epub(&replacement)).reply(&filter).await;
+
+        // Then: Only the good file is stored, under the same id and with its relations
+        assert!(broken.status().is_client_error());
+        assert_eq!(stale.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(replaced.status(), StatusCode::OK);
+        let replaced: serde_json::Value = serde_json::from_slice(replaced.body()).unwrap();
+        assert_eq!(replaced["id"], id.as_str());
+        assert_eq!(replaced["title"], "Final Edition");
+        assert_eq!(replaced["author"], "Careful Editor");
+        assert_eq!(library.storage.read_epub(&id).unwrap(), replacement);
+        assert_eq!(
+            book_repository::find_subjects_by_book_id(&library.pool, &id)
+                .await
+                .unwrap(),
+            vec!["Keep me".to_string()]
+        );
+        let shelf =
+            crate::collection_repository::find_books_in_collection(&library.pool, collection.id)
+                .await
+                .unwrap();
+        assert_eq!(shelf[0].title, "Final Edition");
+    }
+
+    #[tokio::test]
+    async fn should_check_and_enrich_new_isbn_of_replaced_file() {
+        // Given: Duplicate ISBNs rejected, a book holding one, and an enriched book without
+        let (filter, library) = setup_with_upload_settings(UploadSettings {
+            reject_duplicate_isbn: true,
+            ..UploadSettings::for_tests()
+        })
+        .await;
+        let mut taken = Book::new("Taken".to_string(), "/taken.epub".to_string());
+        taken.isbn_13 = Some("9780306406157".to_string());
+        book_repository::insert(&library.pool, &taken)
+            .await
+            .unwrap();
+        let uploaded = warp::test::request()
+            .method("POST")
+            .path("/upload")
+            .header("content-type", "multipart/form-data; boundary=XYZ")
+            .body(multipart_epub(&TestEpub::new("Draft").build()))
+            .reply(&filter)
+            .await;
+        let uploaded: serde_json::Value = serde_json::from_slice(uploaded.body()).unwrap();
+        let id = uploaded["id"].as_str().unwrap().to_string();
+        book_repository::update_enrichment_status(&library.pool, &id, EnrichmentStatus::Done)
+            .await
+            .unwrap();
+        let replace = |isbn: &str| {
+            warp::test::request()
+                .method("PUT")
+                .path(&format!("/api/books/{}/file", id))
+                .header("content-type", "multipart/form-data; boundary=XYZ")
+                .body(multipart_epub(&TestEpub::new("Final").isbn(isbn).build()))
+        };
+
+        // When: Replacing its file with one carrying the taken ISBN, then a new one
+        let duplicate = replace("9780306406157").reply(&filter).await;
+        let replaced = replace("9780131103627").reply(&filter).await;
+
+        // Then: The duplicate is refused, and the new ISBN is queued for enrichment
+        assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+        assert_eq!(replaced.status(), StatusCode::OK);
+        let replaced: serde_json::Value = serde_json::from_slice(replaced.body()).unwrap();
+        assert_eq!(replaced["isbn_13"], "9780131103627");
+        assert_eq!(replaced["enrichment_status"], "pending");
+        let stored = book_repository::find_by_id(&library.pool, &id)
+            .await
+            .unwrap();
+        assert_ne!(stored.enrichment_status, EnrichmentStatus::Done);
+    }
+
+    #[tokio::test]
+    async fn should_reset_metadata_to_the_epub_keeping_notes_and_subjects() {
+        // Given: An uploaded book that enrichment got wrong and the reader annotated
+        let (filter, library) = setup().await;
+        let uploaded = warp::test::request()
+            .method("POST")
+            .path("/upload")
+            .header("content-type", "multipart/form-data; boundary=XYZ")
+            .body(multipart_epub(
+                &TestEpub::new("Real Title").author("Real Author").build(),
+            ))
+            .reply(&filter)
+            .await;
+        let uploaded: serde_json::Value = serde_json::from_slice(uploaded.body()).unwrap();
+        let id = uploaded["id"].as_str().unwrap().to_string();
+        let mut book = book_repository::find_by_id(&library.pool, &id)
+            .await
+            .unwrap();
+        let loaded = book.clone();
+        book.title = "Merged Wrong Title".to_string();
+        book.publish_date = Some("1901".to_string());
+        book.page_count = Some(999);
+        book.openlibrary_key = Some("/books/OL1M".to_string());
+        book_repository::update_enrichment(&library.pool, &loaded, &book)
+            .await
+            .unwrap();
+        book.notes = Some("Lent to Sam".to_string());
+        book_repository::update_metadata(&library.pool, &book)
+            .await
+            .unwrap();
+        book_repository::insert_subject(&library.pool, &id, "Keep me")
+            .await
+            .unwrap();
+
+        // When: Resetting its metadata, and that of an unknown book
+        let reset = warp::test::request()
+            .method("POST")
+            .path(&format!("/api/books/{}/reset-metadata", id))
+            .reply(&filter)
+            .await;
+        let missing = warp::test::request()
+            .method("POST")
+            .path("/api/books/missing/reset-metadata")
+            .reply(&filter)
+            .await;
+
+        // Then: The EPUB's metadata is back, OpenLibrary's is gone, the reader's stays
+        assert_eq!(reset.status(), StatusCode::OK);
+        let reset: serde_json::Value = serde_json::from_slice(reset.body()).unwrap();
+        assert_eq!(reset["title"], "Real Title");
+        assert_eq!(reset["author"], "Real Author");
+        assert!(reset["publish_date"].is_null());
+        assert!(reset["page_count"].is_null());
+        assert!(reset["openlibrary_key"].is_null());
+        assert_eq!(reset["notes"], "Lent to Sam");
+        let stored = book_repository::find_by_id(&library.pool, &id)
+            .await
+            .unwrap();
+        assert_eq!(stored.title, "Real Title");
+        assert!(stored.openlibrary_key.is_none());
+        assert_eq!(
+            book_repository::find_subjects_by_book_id(&library.pool, &id)
+                .await
+                .unwrap(),
+            vec!["Keep me".to_string()]
+        );
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_search_book_content_after_reindex_only_when_enabled() {
+        // Given: Content search enabled, and a book stored before it was indexed
+        let (filter, library) = setup_with_route_settings(RouteSettings {
+            upload: UploadSettings::for_tests(),
+            reader: ReaderSettings {
+                max_inline_bytes: 0,
+                rejoin_hyphenated_words: false,
+            },
+            listing: ListingDefaults::default(),
+            admin_token: Some(ADMIN_TOKEN.to_string()),
+            base_path: String::new(),
+            validate_covers: false,
+            content_search: true,
+            rate_limiter: None,
+            content_security_policy: None,
+            locked_metadata_fields: Vec::new(),
+            response_timing: ResponseTiming {
+                header: true,
+                slow_request_threshold: None,
+            },
+            pretty_json: false,
+        })
+        .await;
+        let (disabled, _disabled_library) = setup().await;
+        let epub = TestEpub::new("Lighthouse")
+            .chapters(&["<p>Prologue</p>", "<p>The lamp burned all night.</p>"])
+            .build();
+        let mut book = Book::new("Lighthouse".to_string(), String::new());
+        book.epub_file_path = library.storage.save_epub(&book.id, &epub).unwrap();
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        let search = |q: &str| {
+            warp::test::request()
+                .method("POST")
+                .path(&format!("/api/search/content?q={}", q))
+        };
+
+        // When: Searching before and after a reindex, with a blank phrase, and while disabled
+        let before = search("lamp%20burned").reply(&filter).await;
+        let reindexed = warp::test::request()
+            .method("POST")
+            .path("/api/admin/reindex-content")
+            .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+            .reply(&filter)
+            .await;
+        let after = search("lamp%20burned").reply(&filter).await;
+        let blank = search("%20").reply(&filter).await;
+        let off = search("lamp").reply(&disabled).await;
+
+        // Then: The phrase is found once indexed, with its chapter and marked snippet
+        let before: serde_json::Value = serde_json::from_slice(before.body()).unwrap();
+        assert_eq!(before, serde_json::json!([]));
+        let reindexed: serde_json::Value = serde_json::from_slice(reindexed.body()).unwrap();
+        assert_eq!(reindexed["indexed"], 1);
+        let after: serde_json::Value = serde_json::from_slice(after.body()).unwrap();
+        assert_eq!(after[0]["book"]["id"], book.id.as_str());
+        assert_eq!(after[0]["match_count"], 1);
+        assert_eq!(after[0]["snippets"][0]["chapter_index"], 1);
+        assert_eq!(
+            after[0]["snippets"][0]["snippet"],
+            "The <mark>lamp burned</mark> all night."
+        );
+        assert_eq!(blank.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(off.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_search_metadata_by_word_prefixes_with_highlights() {
+        // Given: A library with two books
+        let (filter, library) = setup().await;
+        let mut dune = Book::new("Dune Messiah".to_string(), "/dune.epub".to_string());
+        dune.author = Some("Frank Herbert".to_string());
+        book_repository::insert(&library.pool, &dune).await.unwrap();
+        let other = Book::new("Neuromancer".to_string(), "/neuro.epub".to_string());
+        book_repository::insert(&library.pool, &other)
+            .await
+            .unwrap();
+
+        // When: Searching by the start of a title word and an author word, and by punctuation
+        let found = warp::test::request()
+            .path("/api/search?q=dun%20herb")
+            .reply(&filter)
+            .await;
+        let punctuation = warp::test::request()
+            .path("/api/search?q=%2A")
+            .reply(&filter)
+            .await;
+
+        // Then: Only the matching book comes back, its matching words marked
+        assert_eq!(found.status(), StatusCode::OK);
+        let found: serde_json::Value = serde_json::from_slice(found.body()).unwrap();
+        assert_eq!(found.as_array().unwrap().len(), 1);
+        assert_eq!(found[0]["book"]["id"], dune.id.as_str());
+        assert_eq!(found[0]["title"], "<mark>Dune</mark> Messiah");
+        assert_eq!(found[0]["author"], "Frank <mark>Herbert</mark>");
+        assert_eq!(punctuation.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn should_rate_limit_uploads_per_client_but_not_reads() {
+        // Given: A limit of one upload a minute
+        let (filter, _library) = setup_with_route_settings(RouteSettings {
+            upload: UploadSettings::for_tests(),
+            reader: ReaderSettings {
+                max_inline_bytes: 0,
+                rejoin_hyphenated_words: false,
+            },
+            listing: ListingDefaults::default(),
+            admin_token: Some(ADMIN_TOKEN.to_string()),
+            base_path: String::new(),
+            validate_covers: false,
+            content_search: false,
+            rate_limiter: Some(RateLimiter::new(RateLimit {
+                per_minute: 1,
+                burst: 1,
+            })),
+            content_security_policy: None,
+
+            locked_metadata_fields: Vec::new(),
+
+            response_timing: ResponseTiming {
+                header: true,
+
+                slow_request_threshold: None,
+            },
+            pretty_json: false,
+        })
+        .await;
+        let epub = TestEpub::new("Limited").build();
+        let client: SocketAddr = "192.0.2.7:4000".parse().unwrap();
+        let other: SocketAddr = "192.0.2.8:4000".parse().unwrap();
+        let upload = |from: SocketAddr| {
+            warp::test::request()
+                .method("POST")
+                .path("/upload")
+                .remote_addr(from)
+                .header("content-type", "multipart/form-data; boundary=XYZ")
+                .body(multipart_epub(&epub))
+        };
+
+        // When: One client uploads twice and lists books, another uploads once
+        let first = upload(client).reply(&filter).await;
+        let second = upload(client).reply(&filter).await;
+        let listing = warp::test::request()
+            .path("/api/books")
+            .remote_addr(client)
+            .reply(&filter)
+            .await;
+        let elsewhere = upload(other).reply(&filter).await;
+
+        // Then: Only the second upload is refused, with the wait in Retry-After
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(second.headers()["retry-after"], "60");
+        assert_eq!(listing.status(), StatusCode::OK);
+        assert_eq!(elsewhere.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_serve_resized_cover_variants_from_an_allowed_set() {
+        // Given: A book with a 300x450 cover
+        let (filter, library) = setup().await;
+        let mut book = Book::new("Sized".to_string(), "/sized.epub".to_string());
+        let mut cover = Vec::new();
+        image::RgbImage::from_pixel(300, 450, image::Rgb([200, 100, 50]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut cover),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        book.cover_image_path = Some(library.storage.save_cover(&book.id, &cover).unwrap());
+        book_repository::insert(&library.pool, &book).await.unwrap();
+        let request =
+            |query: &str| warp::test::request().path(&format!("/covers/{}?{}", book.id, query));
+
+        // When: Asking for an allowed width twice, and for a size outside the set
+        let first = request("w=100&v=1").reply(&filter).await;
+        let second = request("w=100").reply(&filter).await;
+        let disallowed = request("w=101").reply(&filter).await;
+
+        // Then: The variant is scaled and cached, and other sizes are refused
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(first.headers()["content-type"], "image/jpeg");
+        assert_eq!(
+            first.headers()["cache-control"],
+            "public, max-age=31536000, immutable"
+        );
+        let resized = image::load_from_memory(first.body()).unwrap();
+        assert_eq!((resized.width(), resized.height()), (100, 150));
+        assert!(library
+            .storage
+            .cover_variant_path(&book.id, "w100")
+            .exists());
+        assert_eq!(second.body(), first.body());
+        assert_eq!(second.headers()["etag"], first.headers()["etag"]);
+        assert_eq!(disallowed.status(), StatusCode::BAD_REQUEST);
+    }
+    #[tokio::test]
+    async fn should_compose_montage_of_newest_covers_and_reuse_it() {
+        // Given: Two books with covers and one without
+        let (filter, library) = setup().await;
+        let mut cover = Vec::new();
+        image::RgbImage::from_pixel(300, 450, image::Rgb([200, 100, 50]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut cover),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        for title in ["First", "Second"] {
+            let mut book = Book::new(title.to_string(), format!("/{}.epub", title));
+            book.cover_image_path = Some(library.storage.save_cover(&book.id, &cover).unwrap());
+            book_repository::insert(&library.pool, &book).await.unwrap();
+        }
+        let bare = Book::new("Bare".to_string(), "/bare.epub".to_string());
+        book_repository::insert(&library.pool, &bare).await.unwrap();
+        let montage = |query: &str| {
+            warp::test::request()
+                .path(&format!("/api/gallery/montage?{}", query))
+                .reply(&filter)
+        };
+
+        // When: Asking for a 3x1 montage twice, and for a grid over the limit
+        let first = montage("cols=3&rows=1").await;
+        let second = montage("cols=3&rows=1").await;
+        let too_wide = montage("cols=11").await;
+
+        // Then: Both covers are on one row of three tiles, served again from the cache
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(first.headers()["content-type"], "image/jpeg");
+        let image = image::load_from_memory(first.body()).unwrap();
+        assert_eq!((image.width(), image.height()), (466, 233));
+        assert_eq!(second.body(), first.body());
+        assert_eq!(too_wide.status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -1,37 +1,218 @@
+use crate::annotation_anchor::highlight_annotations;
+use crate::annotation_repository::{self, Annotation};
+use crate::book_bundle::{build_bundle, bundle_content_disposition, epub_content_disposition};
+use crate::book_identifier::{reset_to_epub_metadata, MetadataField};
+use crate::book_model::{current_timestamp, AdjacentBooks, Book, BookDetail};
+use crate::book_page_renderer::render_book_page;
+use crate::book_query::{
+    BookCursor, BookSort, BooksPage, BooksQuery, CoverQuery, CoverSize, IntegrityQuery,
+    ListingDefaults, MontageQuery, NextBookQuery, ReaderQuery, RecommendedQuery, SearchQuery,
+    TextQuery,
+};
 use crate::book_repository;
+use crate::book_update::{
+    AnnotationUpdate, BookUpdate, CollectionBook, CollectionOrder, NewAnnotation, NewBookmark,
+    NewCollection, NewSubject,
+};
+use crate::bookmark_repository;
+use crate::build_info::BuildInfo;
+use crate::bulk_enrichment::{enrich_missing, OPENLIBRARY_REQUEST_INTERVAL};
+use crate::collection_repository::{self, CollectionDetail};
+use crate::content_cache::ContentCache;
+use crate::content_index::{rebuild_index, search_content};
+use crate::cover_montage::{compose_montage, montage_key, TILE_HEIGHT, TILE_WIDTH};
+use crate::cover_placeholder::{placeholder_svg, CoverColor};
+use crate::cover_regeneration::CoverRegeneration;
 use crate::database_connection::DatabasePool;
-use crate::error::EzBooksError;
-use crate::file_storage::FileStorage;
-use crate::gallery_renderer::render_gallery;
+use crate::enrichment_queue::EnrichmentQueue;
+use crate::epub_cover_extractor::{
+    cover_content_type, dominant_color, is_decodable_cover, resize_cover, sniff_cover_mime,
+    TRANSPARENT_PIXEL_PNG,
+};
+use crate::epub_parser::{media_overlay_resources, parse_epub};
+use crate::epub_verification::{verify_epubs, VERIFY_CONCURRENCY};
+use crate::error::{EzBooksError, FieldErrors};
+use crate::file_storage::{FileStorage, StoredFile};
+use crate::fts_query::prefix_terms;
+use crate::gallery_renderer::{render_author_gallery, render_gallery};
+use crate::idempotency_repository::{self, KeyClaim};
+use crate::integrity_check::check_integrity;
+use crate::last_modified::{is_modified_since, is_not_modified, not_modified, with_last_modified};
+use crate::library_stats::collect_library_stats;
+use crate::metadata_search::{rebuild_fts, search_books};
+use crate::openapi_spec::openapi_document;
 use crate::openlibrary_client::OpenLibraryClient;
-use crate::reader_renderer::{extract_and_sanitize_content, render_reader};
-use crate::upload_handler::process_upload;
+use crate::progress_repository;
+use crate::reader_renderer::{
+    extract_and_sanitize_content, extract_chapter_html, for_each_chapter_html, render_reader,
+    ReaderContent, ReaderMarks, ReaderSettings,
+};
+use crate::text_extraction::{chapter_marker, extract_chapter_texts, format_plain_text};
+use crate::ui_text::Lang;
+use crate::upload_handler::{
+    process_replacement, process_upload, schedule_enrichment, validate_extension, TempUpload,
+    UploadOverrides, UploadResponse, UploadSettings,
+};
 use bytes::BufMut;
-use futures::TryStreamExt;
+use futures::{StreamExt, TryStreamExt};
+use percent_encoding::percent_decode_str;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
 use tracing::{info, instrument, warn};
-use warp::http::StatusCode;
+use warp::http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE, ETAG};
+use warp::http::{HeaderValue, StatusCode};
+use warp::hyper::Body;
 use warp::multipart::{FormData, Part};
+use warp::reply::Response;
 use warp::{reject, Rejection, Reply};
 
 #[instrument(skip(pool))]
-pub async fn handle_gallery(pool: DatabasePool) -> Result<impl Reply, Rejection> {
-    info!("Handling gallery request");
+pub async fn handle_gallery(
+    query: BooksQuery,
+    lang: Lang,
+    if_modified_since: Option<String>,
+    pool: DatabasePool,
+    listing: ListingDefaults,
+    base_path: String,
+) -> Result<Response, Rejection> {
+    let sort = query.sort_or(listing.sort);
+    info!(sort = ?sort, "Handling gallery request");
+
+    let last_modified = book_repository::max_updated_at(&pool).await.map_err(|e| {
+        warn!(error = %e, "Failed to fetch library modification time");
+        reject::custom(e)
+    })?;
+    if let Some(timestamp) = last_modified {
+        if is_not_modified(if_modified_since.as_deref(), timestamp) {
+            info!("Gallery unchanged since client copy");
+            return Ok(not_modified(timestamp));
+        }
+    }
 
-    let books = book_repository::find_all(&pool).await.map_err(|e| {
+    let books = match (&query.format, sort) {
+        (Some(format), sort) => book_repository::find_by_format(&pool, format, sort).await,
+        (None, BookSort::Created) => book_repository::find_all(&pool).await,
+        (None, sort) => book_repository::find_all_sorted(&pool, sort).await,
+    }
+    .map_err(|e| {
         warn!(error = %e, "Failed to fetch books");
         reject::custom(e)
     })?;
+    let finished = finished_book_ids(&pool).await;
 
-    let html = render_gallery(books);
+    let html = render_gallery(books, &finished, lang, &base_path);
 
-    Ok(warp::reply::html(html))
+    Ok(with_last_modified(warp::reply::html(html), last_modified))
+}
+
+/// Gallery of the books by one author, given percent-encoded in the path
+#[instrument(skip(pool))]
+pub async fn handle_author_gallery(
+    author: String,
+    lang: Lang,
+    pool: DatabasePool,
+    base_path: String,
+) -> Result<impl Reply, Rejection> {
+    let author = decode_author(&author)?;
+    info!(author = %author, "Handling author gallery request");
+
+    let books = book_repository::find_by_author(&pool, &author)
+        .await
+        .map_err(|e| {
+            warn!(error = %e, "Failed to fetch books by author");
+            reject::custom(e)
+        })?;
+
+    let finished = finished_book_ids(&pool).await;
+
+    Ok(warp::reply::html(render_author_gallery(
+        author.trim(),
+        books,
+        &finished,
+        lang,
+        &base_path,
+    )))
+}
+
+/// Books to badge as finished; the gallery is shown without badges when they cannot be loaded
+async fn finished_book_ids(pool: &DatabasePool) -> HashSet<String> {
+    progress_repository::find_finished_book_ids(pool)
+        .await
+        .unwrap_or_else(|e| {
+            warn!(error = %e, "Failed to fetch finished books");
+            HashSet::new()
+        })
+}
+
+/// Every author with the number of their books, alphabetically
+#[instrument(skip(pool))]
+pub async fn handle_api_authors(pool: DatabasePool) -> Result<impl Reply, Rejection> {
+    info!("Handling authors list request");
+
+    let authors = book_repository::find_authors(&pool).await.map_err(|e| {
+        warn!(error = %e, "Failed to fetch authors");
+        reject::custom(e)
+    })?;
+
+    Ok(warp::reply::json(&authors))
+}
+
+fn decode_author(author: &str) -> Result<String, Rejection> {
+    percent_decode_str(author)
+        .decode_utf8()
+        .map(|author| author.into_owned())
+        .map_err(|_| {
+            info!("Rejected author that is not valid UTF-8");
+            reject::custom(EzBooksError::InvalidFormat(
+                "author is not valid UTF-8".to_string(),
+            ))
+        })
 }
 
 #[instrument(skip(pool))]
-pub async fn handle_api_books(pool: DatabasePool) -> Result<impl Reply, Rejection> {
+pub async fn handle_api_books(
+    query: BooksQuery,
+    pool: DatabasePool,
+    listing: ListingDefaults,
+) -> Result<impl Reply, Rejection> {
     info!("Handling API books list request");
 
-    let books = book_repository::find_all(&pool).await.map_err(|e| {
+    let added_range = query.added_range().map_err(|e| {
+        warn!(error = %e, "Rejected date range");
+        reject::custom(e)
+    })?;
+    if let Some((from, to)) = added_range {
+        if query.is_paged() {
+            return Err(reject::custom(EzBooksError::InvalidPagination(
+                "cursor paging cannot be combined with added_from or added_to".to_string(),
+            )));
+        }
+        // Ranges are always newest first and span every format
+        if query.has_custom_sort() || query.format.is_some() {
+            return Err(reject::custom(EzBooksError::InvalidDateRange(
+                "added_from and added_to only support sort=created and no format".to_string(),
+            )));
+        }
+        let books = book_repository::find_created_between(&pool, from, to)
+            .await
+            .map_err(|e| {
+                warn!(error = %e, "Failed to fetch books in date range");
+                reject::custom(e)
+            })?;
+        return Ok(warp::reply::json(&books));
+    }
+
+    if query.is_paged() {
+        return handle_api_books_page(query, pool, listing.page_size).await;
+    }
+
+    let sort = query.sort_or(listing.sort);
+    let books = match &query.format {
+        Some(format) => book_repository::find_by_format(&pool, format, sort).await,
+        None => book_repository::find_all_sorted(&pool, sort).await,
+    }
+    .map_err(|e| {
         warn!(error = %e, "Failed to fetch books");
         reject::custom(e)
     })?;
@@ -39,131 +220,1810 @@ pub async fn handle_api_books(pool: DatabasePool) -> Result<impl Reply, Rejectio
     Ok(warp::reply::json(&books))
 }
 
+/// Keyset-paged variant of `/api/books`, for scanning large libraries; pages are always
+/// newest first, whatever the configured default sort
+async fn handle_api_books_page(
+    query: BooksQuery,
+    pool: DatabasePool,
+    default_limit: u32,
+) -> Result<warp::reply::Json, Rejection> {
+    if query.has_custom_sort() {
+        return Err(reject::custom(EzBooksError::InvalidPagination(
+            "cursor paging only supports sort=created".to_string(),
+        )));
+    }
+    if query.format.is_some() {
+        return Err(reject::custom(EzBooksError::InvalidPagination(
+            "cursor paging cannot be combined with format".to_string(),
+        )));
+    }
+    let limit = query.page_limit(default_limit).map_err(reject::custom)?;
+    let cursor = match &query.cursor {
+        Some(cursor) => BookCursor::decode(cursor).map_err(|e| {
+            warn!(error = %e, "Rejected books cursor");
+            reject::custom(e)
+        })?,
+        None => BookCursor::start(),
+    };
+
+    // One extra row tells whether another page follows
+    let mut books = book_repository::find_after(&pool, cursor.created_at, &cursor.id, limit + 1)
+        .await
+        .map_err(|e| {
+            warn!(error = %e, "Failed to fetch books page");
+            reject::custom(e)
+        })?;
+    let has_more = books.len() > limit as usize;
+    books.truncate(limit as usize);
+    let next_cursor = books
+        .last()
+        .filter(|_| has_more)
+        .map(|book| BookCursor::after(book).encode());
+
+    Ok(warp::reply::json(&BooksPage { books, next_cursor }))
+}
+
 #[instrument(skip(pool))]
 pub async fn handle_api_book_detail(
     id: String,
+    if_modified_since: Option<String>,
     pool: DatabasePool,
-) -> Result<impl Reply, Rejection> {
+) -> Result<Response, Rejection> {
     info!(book_id = %id, "Handling API book detail request");
 
     let book = book_repository::find_by_id(&pool, &id).await.map_err(|e| {
         warn!(book_id = %id, error = %e, "Failed to fetch book");
         reject::custom(e)
     })?;
+    let reading_progress = progress_repository::find_by_book_id(&pool, &id)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to fetch reading progress");
+            reject::custom(e)
+        })?;
+
+    let collections = collection_repository::find_collections_for_book(&pool, &id)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to fetch collections");
+            reject::custom(e)
+        })?;
+
+    // Reading progress and collections are part of the response, so their changes move it too
+    let last_modified = book
+        .updated_at
+        .max(book.created_at)
+        .max(reading_progress.as_ref().map_or(0, |p| p.updated_at))
+        .max(collections.iter().map(|c| c.updated_at).max().unwrap_or(0));
+    if is_not_modified(if_modified_since.as_deref(), last_modified) {
+        info!(book_id = %id, "Book detail unchanged since client copy");
+        return Ok(not_modified(last_modified));
+    }
+
+    let subjects = book_repository::find_subjects_by_book_id(&pool, &id)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to fetch subjects");
+            reject::custom(e)
+        })?;
+
+    Ok(with_last_modified(
+        warp::reply::json(&BookDetail::new(
+            &book,
+            subjects,
+            reading_progress,
+            collections,
+        )),
+        Some(last_modified),
+    ))
+}
+
+/// Server-rendered page of one book, with structured data for search engines
+#[instrument(skip(pool))]
+pub async fn handle_book_page(
+    id: String,
+    lang: Lang,
+    pool: DatabasePool,
+    base_path: String,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling book page request");
+
+    let book = book_repository::find_by_id(&pool, &id).await.map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to fetch book");
+        reject::custom(e)
+    })?;
+    let subjects = book_repository::find_subjects_by_book_id(&pool, &id)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to fetch subjects");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::html(render_book_page(
+        &book, &subjects, lang, &base_path,
+    )))
+}
+
+/// Reports drift between book rows and stored files, repairing it when asked
+#[instrument(skip(pool, storage))]
+pub async fn handle_integrity(
+    query: IntegrityQuery,
+    pool: DatabasePool,
+    storage: FileStorage,
+) -> Result<impl Reply, Rejection> {
+    info!(fix = query.fix, "Handling integrity check request");
+
+    let report = check_integrity(&pool, &storage, query.fix)
+        .await
+        .map_err(|e| {
+            warn!(error = %e, "Integrity check failed");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::json(&report))
+}
+
+/// Opens every stored EPUB to catch files that exist but no longer open
+#[instrument(skip(pool))]
+pub async fn handle_verify_library(pool: DatabasePool) -> Result<impl Reply, Rejection> {
+    info!("Handling EPUB verification request");
+
+    let results = verify_epubs(&pool, VERIFY_CONCURRENCY).await.map_err(|e| {
+        warn!(error = %e, "EPUB verification failed");
+        reject::custom(e)
+    })?;
+
+    Ok(warp::reply::json(&results))
+}
+
+/// Runs OpenLibrary enrichment for every under-enriched book with an ISBN; slow on large
+/// libraries since requests are spaced out
+#[instrument(skip(pool, openlibrary))]
+pub async fn handle_enrich_missing(
+    pool: DatabasePool,
+    openlibrary: OpenLibraryClient,
+    locked_metadata_fields: Vec<MetadataField>,
+) -> Result<impl Reply, Rejection> {
+    info!("Handling bulk enrichment request");
+
+    let report = enrich_missing(
+        &pool,
+        &openlibrary,
+        &locked_metadata_fields,
+        OPENLIBRARY_REQUEST_INTERVAL,
+    )
+    .await
+    .map_err(|e| {
+        warn!(error = %e, "Bulk enrichment failed");
+        reject::custom(e)
+    })?;
+
+    Ok(warp::reply::json(&report))
+}
+
+/// Re-extracts and indexes the text of every book
+#[instrument(skip(pool))]
+pub async fn handle_reindex_content(
+    pool: DatabasePool,
+    content_search: bool,
+) -> Result<impl Reply, Rejection> {
+    info!("Handling content reindex request");
+
+    if !content_search {
+        return Err(reject::custom(EzBooksError::ContentSearchDisabled));
+    }
+    let summary = rebuild_index(&pool).await.map_err(|e| {
+        warn!(error = %e, "Content reindex failed");
+        reject::custom(e)
+    })?;
+
+    Ok(warp::reply::json(&summary))
+}
+
+/// Starts extracting every cover again with the current cover settings; the returned
+/// progress is polled with `GET /api/admin/regenerate-covers`
+#[instrument(skip(pool, storage, regeneration, settings))]
+pub async fn handle_regenerate_covers(
+    pool: DatabasePool,
+    storage: FileStorage,
+    regeneration: CoverRegeneration,
+    settings: UploadSettings,
+) -> Result<impl Reply, Rejection> {
+    info!("Handling cover regeneration request");
+
+    let progress = regeneration.start(pool, storage, settings).map_err(|e| {
+        info!("Rejected cover regeneration, a run is in progress");
+        reject::custom(e)
+    })?;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&progress),
+        StatusCode::ACCEPTED,
+    ))
+}
+
+/// Progress of the current or last cover regeneration
+#[instrument(skip(regeneration))]
+pub async fn handle_cover_regeneration_progress(
+    regeneration: CoverRegeneration,
+) -> Result<impl Reply, Rejection> {
+    Ok(warp::reply::json(&regeneration.progress()))
+}
+
+/// Books whose text contains the `q` phrase, with highlighted snippets
+#[instrument(skip(pool))]
+pub async fn handle_content_search(
+    query: SearchQuery,
+    pool: DatabasePool,
+    content_search: bool,
+) -> Result<impl Reply, Rejection> {
+    info!(q = %query.q, "Handling content search request");
+
+    if !content_search {
+        return Err(reject::custom(EzBooksError::ContentSearchDisabled));
+    }
+    if query.q.trim().is_empty() {
+        let mut errors = FieldErrors::new();
+        errors.insert("q".to_string(), "must not be empty".to_string());
+        info!("Rejected empty content search");
+        return Err(reject::custom(EzBooksError::Validation(errors)));
+    }
+
+    let matches = search_content(&pool, query.q.trim(), query.limit())
+        .await
+        .map_err(|e| {
+            warn!(q = %query.q, error = %e, "Content search failed");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::json(&matches))
+}
+
+/// Books whose title, author or description contain every word of `q`, best first
+#[instrument(skip(pool))]
+pub async fn handle_metadata_search(
+    query: SearchQuery,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(q = %query.q, "Handling metadata search request");
+
+    let Some(terms) = prefix_terms(&query.q) else {
+        let mut errors = FieldErrors::new();
+        errors.insert("q".to_string(), "must contain a word".to_string());
+        info!("Rejected metadata search without words");
+        return Err(reject::custom(EzBooksError::Validation(errors)));
+    };
+
+    let matches = search_books(&pool, &terms, query.limit())
+        .await
+        .map_err(|e| {
+            warn!(q = %query.q, error = %e, "Metadata search failed");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::json(&matches))
+}
+
+/// Refills the metadata search index from the books table
+#[instrument(skip(pool))]
+pub async fn handle_rebuild_fts(pool: DatabasePool) -> Result<impl Reply, Rejection> {
+    info!("Handling metadata search rebuild request");
+
+    let indexed = rebuild_fts(&pool).await.map_err(|e| {
+        warn!(error = %e, "Metadata search rebuild failed");
+        reject::custom(e)
+    })?;
+
+    Ok(warp::reply::json(
+        &serde_json::json!({ "indexed": indexed }),
+    ))
+}
+
+#[instrument(skip(pool))]
+pub async fn handle_next_unread(
+    query: NextBookQuery,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(subject = %query.subject, lang = ?query.lang, "Handling next unread book request");
+
+    let book = progress_repository::find_next_unread(&pool, &query.subject, query.lang.as_deref())
+        .await
+        .map_err(|e| {
+            warn!(subject = %query.subject, error = %e, "Failed to find next unread book");
+            reject::custom(e)
+        })?
+        .ok_or_else(|| {
+            reject::custom(EzBooksError::BookNotFound(format!(
+                "no unread books in subject {}",
+                query.subject
+            )))
+        })?;
 
     Ok(warp::reply::json(&book))
 }
 
-#[instrument(skip(storage))]
-pub async fn handle_cover(id: String, storage: FileStorage) -> Result<impl Reply, Rejection> {
-    info!(book_id = %id, "Handling cover image request");
+/// Unread books related to recent reading; an empty list when nothing has been read
+#[instrument(skip(pool))]
+pub async fn handle_recommended(
+    query: RecommendedQuery,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(limit = query.limit(), "Handling recommended books request");
 
-    let cover_data = storage.read_cover(&id).map_err(|e| {
-        warn!(book_id = %id, error = %e, "Failed to read cover");
+    let books = progress_repository::find_recommended(&pool, query.limit())
+        .await
+        .map_err(|e| {
+            warn!(error = %e, "Failed to find recommended books");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::json(&books))
+}
+
+#[instrument(skip(pool))]
+pub async fn handle_stats(pool: DatabasePool) -> Result<impl Reply, Rejection> {
+    info!("Handling library stats request");
+
+    let stats = collect_library_stats(&pool).await.map_err(|e| {
+        warn!(error = %e, "Failed to collect library stats");
         reject::custom(e)
     })?;
 
+    // Aggregates change slowly, so let clients reuse them for a short while
     Ok(warp::reply::with_header(
-        cover_data,
-        "content-type",
-        "image/jpeg",
+        warp::reply::json(&stats),
+        "cache-control",
+        "private, max-age=30",
     ))
 }
 
+pub async fn handle_openapi() -> Result<impl Reply, Rejection> {
+    Ok(warp::reply::json(&openapi_document()))
+}
+
+pub async fn handle_version() -> Result<impl Reply, Rejection> {
+    Ok(warp::reply::json(&BuildInfo::current()))
+}
+
 #[instrument(skip(pool, storage))]
-pub async fn handle_reader(
+pub async fn handle_bundle(
     id: String,
     pool: DatabasePool,
     storage: FileStorage,
 ) -> Result<impl Reply, Rejection> {
-    info!(book_id = %id, "Handling reader request");
+    info!(book_id = %id, "Handling book bundle request");
 
     let book = book_repository::find_by_id(&pool, &id).await.map_err(|e| {
         warn!(book_id = %id, error = %e, "Failed to fetch book");
         reject::custom(e)
     })?;
 
-    let epub_data = storage.read_epub(&id).map_err(|e| {
-        warn!(book_id = %id, error = %e, "Failed to read EPUB");
+    if !storage.epub_path(&id).exists() {
+        warn!(book_id = %id, "Stored EPUB missing for bundle");
+        return Err(reject::custom(EzBooksError::BookNotFound(id)));
+    }
+
+    let subjects = book_repository::find_subjects_by_book_id(&pool, &id)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to fetch subjects");
+            reject::custom(e)
+        })?;
+    let epub = storage.read_epub(&id).map_err(reject::custom)?;
+    let cover = storage
+        .cover_path(&id)
+        .exists()
+        .then(|| storage.read_cover(&id).ok())
+        .flatten();
+
+    let bundle = build_bundle(&book, &subjects, &epub, cover.as_deref()).map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to build bundle");
         reject::custom(e)
     })?;
 
-    // Save to temp file for reading
-    let temp_path = std::env::temp_dir().join(format!("{}.epub", id));
-    std::fs::write(&temp_path, epub_data).map_err(|e| {
-        warn!(error = %e, "Failed to write temp file");
-        reject::custom(EzBooksError::Io(e))
-    })?;
+    info!(book_id = %id, size = bundle.len(), "Book bundle built");
+    Ok(warp::reply::with_header(
+        warp::reply::with_header(bundle, "content-type", "application/zip"),
+        "content-disposition",
+        bundle_content_disposition(&book.title),
+    ))
+}
 
-    let content = extract_and_sanitize_content(&temp_path).map_err(|e| {
-        warn!(book_id = %id, error = %e, "Failed to extract content");
+#[instrument(skip(storage))]
+pub async fn handle_cover(
+    id: String,
+    query: CoverQuery,
+    pool: DatabasePool,
+    storage: FileStorage,
+    validate: bool,
+    jpeg_quality: u8,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling cover image request");
+    cover_response(&id, &query, &pool, &storage, true, validate, jpeg_quality).await
+}
+
+/// Cover headers without the image, for clients checking whether a cover exists
+#[instrument(skip(storage))]
+pub async fn handle_cover_head(
+    id: String,
+    query: CoverQuery,
+    pool: DatabasePool,
+    storage: FileStorage,
+    jpeg_quality: u8,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling cover HEAD request");
+    cover_response(&id, &query, &pool, &storage, false, false, jpeg_quality).await
+}
+
+async fn cover_response(
+    id: &str,
+    query: &CoverQuery,
+    pool: &DatabasePool,
+    storage: &FileStorage,
+    with_body: bool,
+    validate: bool,
+    jpeg_quality: u8,
+) -> Result<Response, Rejection> {
+    let size = query.size().map_err(|e| {
+        info!(book_id = %id, error = %e, "Rejected cover size");
         reject::custom(e)
     })?;
+    let stored = storage.stat_cover(id).ok_or_else(|| {
+        warn!(book_id = %id, "Cover not found");
+        reject::custom(EzBooksError::CoverNotFound(id.to_string()))
+    })?;
+    if let Some(size) = size {
+        let Some(data) = cover_variant(id, size, pool, storage, jpeg_quality).await? else {
+            return Ok(placeholder_cover_response());
+        };
+        // Derived from the full cover's ETag, so it changes whenever the cover does
+        let variant = StoredFile {
+            len: data.len() as u64,
+            etag: format!(
+                "{}-{}\"",
+                stored.etag.trim_end_matches('"'),
+                size.variant_key()
+            ),
+        };
+        let content_type = sniff_cover_mime(&data);
+        let mut response = file_response(&variant, content_type, with_body.then_some(data));
+        response
+            .headers_mut()
+            .insert(CACHE_CONTROL, cover_cache_control(query));
+        return Ok(response);
+    }
+    let body = if with_body {
+        Some(storage.read_cover(id).map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to read cover");
+            reject::custom(e)
+        })?)
+    } else {
+        None
+    };
 
-    // Clean up temp file
-    let _ = std::fs::remove_file(&temp_path);
+    if validate
+        && body
+            .as_deref()
+            .map_or(false, |data| !is_decodable_cover(data))
+    {
+        warn!(book_id = %id, "Stored cover is corrupt, serving a placeholder");
+        if let Err(e) = book_repository::mark_cover_corrupt(pool, id).await {
+            warn!(book_id = %id, error = %e, "Failed to flag corrupt cover");
+        }
+        // Not cached, so the real cover shows up once it is regenerated
+        return Ok(placeholder_cover_response());
+    }
 
-    let html = render_reader(&book, content);
+    let cover_mime = book_repository::find_cover_mime(pool, id)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to fetch cover type");
+            reject::custom(e)
+        })?;
 
-    Ok(warp::reply::html(html))
+    let mut response = file_response(&stored, cover_content_type(cover_mime.as_deref()), body);
+    response
+        .headers_mut()
+        .insert(CACHE_CONTROL, cover_cache_control(query));
+    Ok(response)
 }
 
-#[instrument(skip(form, pool, storage, ol_client))]
-pub async fn handle_upload(
-    form: FormData,
+/// The cover scaled to `size`, resized on first request and then read from the variant
+/// cache; `None` when the stored cover does not decode
+async fn cover_variant(
+    id: &str,
+    size: CoverSize,
+    pool: &DatabasePool,
+    storage: &FileStorage,
+    jpeg_quality: u8,
+) -> Result<Option<Vec<u8>>, Rejection> {
+    let key = size.variant_key();
+    if let Some(data) = storage.read_cover_variant(id, &key) {
+        return Ok(Some(data));
+    }
+
+    let cover = storage.read_cover(id).map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to read cover");
+        reject::custom(e)
+    })?;
+    let data = match resize_cover(&cover, size.width, size.height, jpeg_quality) {
+        Ok(data) => data,
+        Err(e) => {
+            warn!(book_id = %id, error = %e, "Stored cover does not decode");
+            if let Err(e) = book_repository::mark_cover_corrupt(pool, id).await {
+                warn!(book_id = %id, error = %e, "Failed to flag corrupt cover");
+            }
+            return Ok(None);
+        }
+    };
+    if let Err(e) = storage.save_cover_variant(id, &key, &data) {
+        warn!(book_id = %id, error = %e, "Failed to cache cover variant");
+    }
+    Ok(Some(data))
+}
+
+/// A new cover gets a new hash and therefore a new URL, so versioned URLs are immutable
+fn cover_cache_control(query: &CoverQuery) -> HeaderValue {
+    if query.v.is_some() {
+        HeaderValue::from_static("public, max-age=31536000, immutable")
+    } else {
+        HeaderValue::from_static("no-cache")
+    }
+}
+
+fn placeholder_cover_response() -> Response {
+    let mut response = Response::new(Body::from(TRANSPARENT_PIXEL_PNG));
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("image/png"));
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
+
+/// The cover's dominant color, or the placeholder gradient of books without one
+#[instrument(skip(pool, storage))]
+pub async fn handle_cover_color(
+    id: String,
     pool: DatabasePool,
     storage: FileStorage,
-    ol_client: OpenLibraryClient,
 ) -> Result<impl Reply, Rejection> {
-    info!("Handling upload request");
+    info!(book_id = %id, "Handling cover color request");
 
-    let parts: Vec<Part> = form.try_collect().await.map_err(|e| {
-        warn!(error = %e, "Failed to collect form parts");
-        reject::reject()
+    let book = book_repository::find_by_id(&pool, &id).await.map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to fetch book");
+        reject::custom(e)
     })?;
+    let color = match (&book.cover_image_path, book.cover_color) {
+        (None, _) => None,
+        (Some(_), Some(color)) => Some(color),
+        // Stored before colors were, and not backfilled by a reindex yet
+        (Some(_), None) => match storage
+            .read_cover(&id)
+            .and_then(|data| dominant_color(&data))
+        {
+            Ok(color) => {
+                if let Err(e) = book_repository::update_cover_color(&pool, &id, &color).await {
+                    warn!(book_id = %id, error = %e, "Failed to store cover color");
+                }
+                Some(color)
+            }
+            Err(e) => {
+                warn!(book_id = %id, error = %e, "Failed to compute cover color");
+                None
+            }
+        },
+    };
 
-    for part in parts {
-        if part.name() == "file" {
-            let filename = part.filename().unwrap_or("unknown.epub").to_string();
+    let color = color
+        .map(CoverColor::from_cover)
+        .unwrap_or_else(|| CoverColor::placeholder(&book.title));
+    Ok(warp::reply::json(&color))
+}
 
-            if !filename.to_lowercase().ends_with(".epub") {
-                return Err(reject::custom(EzBooksError::InvalidFormat));
-            }
+/// Gradient cover generated from the title, linked by the gallery for books without a cover
+#[instrument(skip(pool))]
+pub async fn handle_placeholder_cover(
+    id: String,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling placeholder cover request");
 
-            let data = part
-                .stream()
-                .try_fold(Vec::new(), |mut vec, data| {
-                    vec.put(data);
-                    async move { Ok(vec) }
-                })
-                .await
-                .map_err(|e| {
-                    warn!(error = %e, "Failed to read file data");
-                    reject::reject()
-                })?;
-
-            let response = process_upload(filename, data, pool, storage, ol_client)
-                .await
-                .map_err(|e| {
-                    warn!(error = %e, "Failed to process upload");
-                    reject::custom(e)
-                })?;
-
-            return Ok(warp::reply::with_status(
-                warp::reply::json(&response),
-                StatusCode::OK,
-            ));
+    let book = book_repository::find_by_id(&pool, &id).await.map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to fetch book");
+        reject::custom(e)
+    })?;
+    let mut response = Response::new(Body::from(placeholder_svg(&book.title)));
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("image/svg+xml"));
+    // Changes with the title, which is rare but possible
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    Ok(response)
+}
+
+/// One JPEG of the newest covers, `cols` by `rows`, cached until the books or covers change
+#[instrument(skip(pool, storage))]
+pub async fn handle_montage(
+    query: MontageQuery,
+    pool: DatabasePool,
+    storage: FileStorage,
+    jpeg_quality: u8,
+) -> Result<impl Reply, Rejection> {
+    info!(cols = ?query.cols, rows = ?query.rows, "Handling cover montage request");
+
+    let (columns, rows) = query.grid().map_err(|e| {
+        info!(error = %e, "Rejected montage grid");
+        reject::custom(e)
+    })?;
+    let covers = book_repository::find_newest_covers(&pool, columns * rows)
+        .await
+        .map_err(|e| {
+            warn!(error = %e, "Failed to fetch covers");
+            reject::custom(e)
+        })?;
+    let grid = format!("{}x{}", columns, rows);
+    let key = montage_key(&grid, &covers);
+
+    let data = match storage.read_montage(&grid, &key) {
+        Some(data) => data,
+        None => {
+            let size = CoverSize {
+                width: Some(TILE_WIDTH),
+                height: Some(TILE_HEIGHT),
+            };
+            let mut tiles = Vec::with_capacity(covers.len());
+            for (id, _) in &covers {
+                // A cover that fails to load leaves a gap rather than failing the montage
+                if let Ok(Some(tile)) = cover_variant(id, size, &pool, &storage, jpeg_quality).await
+                {
+                    tiles.push(tile);
+                }
+            }
+            let data = compose_montage(&tiles, columns, jpeg_quality).map_err(|e| {
+                warn!(error = %e, "Failed to compose montage");
+                reject::custom(e)
+            })?;
+            if let Err(e) = storage.save_montage(&grid, &key, &data) {
+                warn!(grid = %grid, error = %e, "Failed to cache montage");
+            }
+            data
         }
-    }
+    };
+
+    let mut response = Response::new(Body::from(data));
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("image/jpeg"));
+    // Changes whenever a book is added or a cover replaced
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    Ok(response)
+}
 
-    Err(reject::custom(EzBooksError::InvalidFormat))
+/// The stored EPUB as an attachment named after the book's title
+#[instrument(skip(pool, storage))]
+pub async fn handle_download(
+    id: String,
+    pool: DatabasePool,
+    storage: FileStorage,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling EPUB download request");
+    download_response(&id, &pool, &storage, true).await
 }
 
+/// Download headers without the EPUB, sized from file metadata
 #[instrument(skip(pool, storage))]
+pub async fn handle_download_head(
+    id: String,
+    pool: DatabasePool,
+    storage: FileStorage,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling EPUB download HEAD request");
+    download_response(&id, &pool, &storage, false).await
+}
+
+async fn download_response(
+    id: &str,
+    pool: &DatabasePool,
+    storage: &FileStorage,
+    with_body: bool,
+) -> Result<Response, Rejection> {
+    let book = book_repository::find_by_id(pool, id).await.map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to fetch book");
+        reject::custom(e)
+    })?;
+    let stored = storage.stat_epub(id).ok_or_else(|| {
+        warn!(book_id = %id, "Stored EPUB missing for download");
+        reject::custom(EzBooksError::BookNotFound(id.to_string()))
+    })?;
+    let body = if with_body {
+        Some(storage.read_epub(id).map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to read EPUB");
+            reject::custom(e)
+        })?)
+    } else {
+        None
+    };
+
+    let mut response = file_response(&stored, "application/epub+zip", body);
+    if let Ok(disposition) = HeaderValue::from_str(&epub_content_disposition(&book)) {
+        response
+            .headers_mut()
+            .insert(CONTENT_DISPOSITION, disposition);
+    }
+    Ok(response)
+}
+
+/// Response with the file's content headers; HEAD requests pass no body but keep the length
+fn file_response(
+    stored: &StoredFile,
+    content_type: &'static str,
+    body: Option<Vec<u8>>,
+) -> Response {
+    let len = body.as_ref().map_or(stored.len, |body| body.len() as u64);
+    let mut response = Response::new(body.map_or_else(Body::empty, Body::from));
+
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+    if let Ok(etag) = HeaderValue::from_str(&stored.etag) {
+        headers.insert(ETAG, etag);
+    }
+    response
+}
+
+#[instrument(skip(pool, storage, content_cache))]
+pub async fn handle_reader(
+    id: String,
+    query: ReaderQuery,
+    pool: DatabasePool,
+    storage: FileStorage,
+    content_cache: ContentCache,
+    settings: ReaderSettings,
+    base_path: String,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling reader request");
+
+    let book = book_repository::find_by_id(&pool, &id).await.map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to fetch book");
+        reject::custom(e)
+    })?;
+
+    let content = match content_cache.get(&id, book.updated_at) {
+        Some(content) => {
+            info!(book_id = %id, "Serving reader content from cache");
+            content
+        }
+        None => {
+            let content = Arc::new(load_reader_content(&id, &storage, settings)?);
+            content_cache.insert(&id, book.updated_at, Arc::clone(&content));
+            content
+        }
+    };
+
+    // Progress is a convenience; failing to record it should not block reading
+    if let Err(e) = progress_repository::mark_opened(&pool, &id).await {
+        warn!(book_id = %id, error = %e, "Failed to record reading progress");
+    }
+
+    // Missing neighbour links are better than no reader page
+    let adjacent = book_repository::find_adjacent(&pool, &book, query.sort)
+        .await
+        .unwrap_or_else(|e| {
+            warn!(book_id = %id, error = %e, "Failed to find adjacent books");
+            AdjacentBooks {
+                sort: query.sort,
+                ..AdjacentBooks::default()
+            }
+        });
+
+    // Bookmarks and highlights are conveniences; the book can be read without them
+    let bookmarks = bookmark_repository::list_bookmarks(&pool, &id)
+        .await
+        .unwrap_or_else(|e| {
+            warn!(book_id = %id, error = %e, "Failed to fetch bookmarks");
+            Vec::new()
+        });
+    let annotations = book_annotations(&pool, &id).await;
+
+    let html = render_reader(
+        &book,
+        &content,
+        query.mode,
+        &adjacent,
+        ReaderMarks {
+            bookmarks: &bookmarks,
+            annotations: &annotations,
+        },
+        query.lang,
+        &base_path,
+    );
+
+    Ok(warp::reply::html(html))
+}
+
+/// Rejects with 404 unless the book exists, for handlers that do not need its row
+async fn ensure_book_exists(pool: &DatabasePool, id: &str) -> Result<(), Rejection> {
+    let exists = book_repository::exists(pool, id).await.map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to check book");
+        reject::custom(e)
+    })?;
+    if exists {
+        Ok(())
+    } else {
+        info!(book_id = %id, "Book not found");
+        Err(reject::custom(EzBooksError::BookNotFound(id.to_string())))
+    }
+}
+
+/// A book's annotations, or none when they cannot be loaded
+async fn book_annotations(pool: &DatabasePool, id: &str) -> Vec<Annotation> {
+    annotation_repository::list_annotations(pool, id)
+        .await
+        .unwrap_or_else(|e| {
+            warn!(book_id = %id, error = %e, "Failed to fetch annotations");
+            Vec::new()
+        })
+}
+
+fn load_reader_content(
+    id: &str,
+    storage: &FileStorage,
+    settings: ReaderSettings,
+) -> Result<ReaderContent, Rejection> {
+    let epub_data = storage.read_epub(id).map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to read EPUB");
+        reject::custom(e)
+    })?;
+
+    // Save to temp file for reading
+    let temp_path = std::env::temp_dir().join(format!("{}.epub", id));
+    std::fs::write(&temp_path, epub_data).map_err(|e| {
+        warn!(error = %e, "Failed to write temp file");
+        reject::custom(EzBooksError::Io(e))
+    })?;
+
+    let content = extract_and_sanitize_content(
+        &temp_path,
+        settings.max_inline_bytes,
+        settings.rejoin_hyphenated_words,
+    );
+
+    // Clean up temp file
+    let _ = std::fs::remove_file(&temp_path);
+
+    content.map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to extract content");
+        reject::custom(e)
+    })
+}
+
+/// Sanitized HTML of one spine item, for loading chapters left out of a capped reader page
+#[instrument(skip(pool, storage))]
+pub async fn handle_reader_chapter(
+    id: String,
+    index: usize,
+    pool: DatabasePool,
+    storage: FileStorage,
+    settings: ReaderSettings,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, index, "Handling reader chapter request");
+
+    ensure_book_exists(&pool, &id).await?;
+
+    let chapter = extract_chapter_html(
+        storage.epub_path(&id),
+        index,
+        settings.rejoin_hyphenated_words,
+    )
+    .map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to extract chapter");
+        reject::custom(e)
+    })?;
+    let Some((html, chapter_count)) = chapter else {
+        warn!(book_id = %id, index, "Requested chapter out of range");
+        return Err(reject::custom(EzBooksError::ChapterNotFound {
+            book_id: id,
+            chapter: index,
+        }));
+    };
+    let html = highlight_annotations(&html, index, &book_annotations(&pool, &id).await);
+
+    let mut response = warp::reply::html(html).into_response();
+    let headers = response.headers_mut();
+    headers.insert("x-chapter-count", HeaderValue::from(chapter_count));
+    if index + 1 < chapter_count {
+        headers.insert("x-next-chapter", HeaderValue::from(index + 1));
+    }
+    Ok(response)
+}
+
+/// Chapters sanitized ahead of what a streaming client has read
+const STREAM_CHAPTER_BUFFER: usize = 2;
+
+/// Streams the sanitized chapters of a book as NDJSON, one `ChapterHtml` per line, each
+/// sent as soon as it is ready so clients can show the first chapter while later ones are
+/// still being extracted. Extraction stops when the client goes away.
+#[instrument(skip(pool, storage))]
+pub async fn handle_reader_stream(
+    id: String,
+    pool: DatabasePool,
+    storage: FileStorage,
+    settings: ReaderSettings,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling reader stream request");
+
+    ensure_book_exists(&pool, &id).await?;
+    let annotations = book_annotations(&pool, &id).await;
+
+    let (sender, mut receiver) = tokio::sync::mpsc::channel::<Vec<u8>>(STREAM_CHAPTER_BUFFER);
+    let epub_path = storage.epub_path(&id);
+    let rejoin_hyphenated_words = settings.rejoin_hyphenated_words;
+    let extraction = tokio::task::spawn_blocking(move || {
+        for_each_chapter_html(epub_path, rejoin_hyphenated_words, |mut chapter| {
+            chapter.html = highlight_annotations(&chapter.html, chapter.index, &annotations);
+            let mut line = match serde_json::to_vec(&chapter) {
+                Ok(line) => line,
+                Err(e) => {
+                    warn!(chapter = chapter.index, error = %e, "Failed to encode chapter");
+                    return false;
+                }
+            };
+            line.push(b'\n');
+            // Sending fails once the response body is dropped, i.e. the client went away
+            sender.blocking_send(line).is_ok()
+        })
+    });
+
+    // Failing to open the EPUB happens before the first chapter, so it still gets a status
+    let first = receiver.recv().await;
+    if first.is_none() {
+        extraction
+            .await
+            .map_err(|e| EzBooksError::EpubParse(format!("chapter extraction failed: {}", e)))
+            .and_then(|result| result)
+            .map_err(|e| {
+                warn!(book_id = %id, error = %e, "Failed to stream chapters");
+                reject::custom(e)
+            })?;
+    }
+
+    let rest = futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|line| (line, receiver))
+    });
+    let lines = futures::stream::iter(first)
+        .chain(rest)
+        .map(Ok::<_, std::convert::Infallible>);
+    let mut response = Response::new(Body::wrap_stream(lines));
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/x-ndjson"),
+    );
+    Ok(response)
+}
+
+/// Plain UTF-8 text of a book for screen readers and text-to-speech, optionally one chapter
+#[instrument(skip(pool, storage))]
+pub async fn handle_reader_text(
+    id: String,
+    query: TextQuery,
+    pool: DatabasePool,
+    storage: FileStorage,
+    settings: ReaderSettings,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, chapter = ?query.chapter, "Handling plain text reader request");
+
+    ensure_book_exists(&pool, &id).await?;
+
+    let chapters = extract_chapter_texts(storage.epub_path(&id), settings.rejoin_hyphenated_words)
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to extract text");
+            reject::custom(e)
+        })?;
+    let chapter_count = chapters.len();
+
+    let text = match query.chapter {
+        None => format_plain_text(&chapters),
+        Some(chapter) => match chapter.checked_sub(1).and_then(|i| chapters.get(i)) {
+            Some(text) => format!("{}\n\n{}\n", chapter_marker(chapter), text),
+            None => {
+                warn!(book_id = %id, chapter, chapter_count, "Requested chapter out of range");
+                return Err(reject::custom(EzBooksError::ChapterNotFound {
+                    book_id: id,
+                    chapter,
+                }));
+            }
+        },
+    };
+
+    Ok(warp::reply::with_header(
+        warp::reply::with_header(text, "content-type", "text/plain; charset=utf-8"),
+        "x-chapter-count",
+        chapter_count.to_string(),
+    ))
+}
+
+/// Media overlay documents and audio files of a narrated book, for read-aloud clients
+#[instrument(skip(pool, storage))]
+pub async fn handle_media_overlays(
+    id: String,
+    pool: DatabasePool,
+    storage: FileStorage,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling media overlays request");
+
+    ensure_book_exists(&pool, &id).await?;
+
+    let resources = media_overlay_resources(storage.epub_path(&id)).map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to list media overlays");
+        reject::custom(e)
+    })?;
+
+    Ok(warp::reply::json(&resources))
+}
+
+#[instrument(skip(form, idempotency_key, pool, storage, enrichment_queue))]
+pub async fn handle_upload(
+    form: FormData,
+    idempotency_key: Option<String>,
+    pool: DatabasePool,
+    storage: FileStorage,
+    enrichment_queue: EnrichmentQueue,
+    settings: UploadSettings,
+) -> Result<impl Reply, Rejection> {
+    info!(
+        has_idempotency_key = idempotency_key.is_some(),
+        "Handling upload request"
+    );
+
+    let idempotency_key = idempotency_key.filter(|key| !key.trim().is_empty());
+    if let Some(key) = &idempotency_key {
+        match idempotency_repository::claim_key(&pool, key, settings.idempotency_ttl)
+            .await
+            .map_err(reject::custom)?
+        {
+            KeyClaim::New => {}
+            KeyClaim::InProgress => {
+                warn!("Upload retried while the original is still in progress");
+                return Err(reject::custom(EzBooksError::IdempotencyKeyInUse));
+            }
+            KeyClaim::Completed { status, response } => {
+                let status = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&response),
+                    status,
+                ));
+            }
+        }
+    }
+
+    let result =
+        receive_and_process_upload(form, pool.clone(), storage, enrichment_queue, settings).await;
+
+    let Some(key) = idempotency_key else {
+        return result.map(|response| {
+            warp::reply::with_status(warp::reply::json(&response), StatusCode::OK)
+        });
+    };
+    match result {
+        Ok(response) => {
+            let body = serde_json::to_value(&response)
+                .map_err(|e| reject::custom(EzBooksError::from(e)))?;
+            // The book exists either way; a lost key only means a retry could duplicate it
+            if let Err(e) = idempotency_repository::complete_key(
+                &pool,
+                &key,
+                &response.id,
+                StatusCode::OK.as_u16(),
+                &body,
+            )
+            .await
+            {
+                warn!(book_id = %response.id, error = %e, "Failed to store idempotency key");
+            }
+            Ok(warp::reply::with_status(
+                warp::reply::json(&body),
+                StatusCode::OK,
+            ))
+        }
+        Err(rejection) => {
+            if let Err(e) = idempotency_repository::release_key(&pool, &key).await {
+                warn!(error = %e, "Failed to release idempotency key");
+            }
+            Err(rejection)
+        }
+    }
+}
+
+async fn receive_and_process_upload(
+    form: FormData,
+    pool: DatabasePool,
+    storage: FileStorage,
+    enrichment_queue: EnrichmentQueue,
+    settings: UploadSettings,
+) -> Result<UploadResponse, Rejection> {
+    // Bound the whole body read so slow clients can't hold the connection open;
+    // on timeout the partially received temp file is removed before it is parsed
+    let (filename, upload, overrides) = tokio::time::timeout(
+        settings.timeout,
+        read_upload_form(form, &settings.allowed_extensions),
+    )
+    .await
+    .map_err(|_| {
+        warn!(
+            timeout_secs = settings.timeout.as_secs(),
+            "Upload body not received in time"
+        );
+        reject::custom(EzBooksError::UploadTimeout(settings.timeout.as_secs()))
+    })??;
+
+    let response = process_upload(
+        filename,
+        upload.path(),
+        overrides,
+        pool,
+        storage,
+        enrichment_queue,
+        settings,
+    )
+    .await
+    .map_err(|e| {
+        warn!(error = %e, "Failed to process upload");
+        reject::custom(e)
+    })?;
+
+    Ok(response)
+}
+
+/// The book a file replacement targets. With `If-Unmodified-Since`, a book changed
+/// after that date is left alone (412).
+pub async fn find_unmodified_book(
+    id: String,
+    if_unmodified_since: Option<String>,
+    pool: DatabasePool,
+) -> Result<Book, Rejection> {
+    let book = book_repository::find_by_id(&pool, &id).await.map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to fetch book");
+        reject::custom(e)
+    })?;
+    if is_modified_since(if_unmodified_since.as_deref(), book.updated_at) {
+        info!(book_id = %id, "Rejected replacement of a book changed since the client's copy");
+        return Err(reject::custom(EzBooksError::BookModified(id)));
+    }
+    Ok(book)
+}
+
+/// Swaps a book's EPUB for a new one and answers with the updated book; a new ISBN
+/// queues it for enrichment again
+#[instrument(skip(book, form, pool, storage, enrichment_queue, content_cache, settings), fields(book_id = %book.id))]
+pub async fn handle_replace_file(
+    book: Book,
+    form: FormData,
+    pool: DatabasePool,
+    storage: FileStorage,
+    enrichment_queue: EnrichmentQueue,
+    content_cache: ContentCache,
+    settings: UploadSettings,
+) -> Result<impl Reply, Rejection> {
+    info!("Handling replace file request");
+    let id = book.id.clone();
+
+    let (filename, upload, overrides) = tokio::time::timeout(
+        settings.timeout,
+        read_upload_form(form, &settings.allowed_extensions),
+    )
+    .await
+    .map_err(|_| {
+        warn!(
+            timeout_secs = settings.timeout.as_secs(),
+            "Replacement body not received in time"
+        );
+        reject::custom(EzBooksError::UploadTimeout(settings.timeout.as_secs()))
+    })??;
+
+    let mut book = process_replacement(
+        book,
+        filename,
+        upload.path(),
+        overrides,
+        &pool,
+        &storage,
+        &settings,
+    )
+    .await
+    .map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to replace EPUB");
+        reject::custom(e)
+    })?;
+    content_cache.invalidate(&id);
+    schedule_enrichment(&pool, &enrichment_queue, &mut book)
+        .await
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&book))
+}
+
+/// Streams the `file` part of an upload form to a temp file, and reads the optional
+/// `title`, `author` and `isbn` override fields; other parts are ignored
+async fn read_upload_form(
+    mut form: FormData,
+    allowed_extensions: &[String],
+) -> Result<(String, TempUpload, UploadOverrides), Rejection> {
+    let mut file = None;
+    let mut overrides = UploadOverrides::default();
+
+    // Parts are read one at a time, since the next part can't be parsed while an
+    // earlier one is still waiting for its body
+    while let Some(part) = form.try_next().await.map_err(|e| {
+        warn!(error = %e, "Failed to read form part");
+        reject::reject()
+    })? {
+        match part.name() {
+            "file" => {
+                let filename = part.filename().unwrap_or("unknown.epub").to_string();
+
+                // Checked before reading the body so rejected files are never written
+                validate_extension(&filename, allowed_extensions).map_err(reject::custom)?;
+
+                file = Some((filename, receive_part_to_file(part).await?));
+            }
+            "title" => overrides.title = Some(read_text_part(part).await?),
+            "author" => overrides.author = Some(read_text_part(part).await?),
+            "isbn" => overrides.isbn = Some(read_text_part(part).await?),
+            _ => {}
+        }
+    }
+
+    match file {
+        Some((filename, upload)) => Ok((filename, upload, overrides)),
+        None => Err(reject::custom(EzBooksError::InvalidFormat(
+            "missing `file` part".to_string(),
+        ))),
+    }
+}
+
+/// Writes a part chunk by chunk, so an upload never sits in memory as a whole
+async fn receive_part_to_file(part: Part) -> Result<TempUpload, Rejection> {
+    let (upload, file) = TempUpload::create().map_err(|e| {
+        warn!(error = %e, "Failed to create temp file for upload");
+        reject::custom(e)
+    })?;
+    let mut file = tokio::fs::File::from_std(file);
+    let mut stream = Box::pin(part.stream());
+    while let Some(mut chunk) = stream.try_next().await.map_err(|e| {
+        warn!(error = %e, "Failed to read form part data");
+        reject::reject()
+    })? {
+        file.write_all_buf(&mut chunk).await.map_err(|e| {
+            warn!(error = %e, "Failed to write upload to temp file");
+            reject::custom(EzBooksError::from(e))
+        })?;
+    }
+    file.flush().await.map_err(|e| {
+        warn!(error = %e, "Failed to write upload to temp file");
+        reject::custom(EzBooksError::from(e))
+    })?;
+    Ok(upload)
+}
+
+async fn read_part(part: Part) -> Result<Vec<u8>, Rejection> {
+    part.stream()
+        .try_fold(Vec::new(), |mut vec, data| {
+            vec.put(data);
+            async move { Ok(vec) }
+        })
+        .await
+        .map_err(|e| {
+            warn!(error = %e, "Failed to read form part data");
+            reject::reject()
+        })
+}
+
+async fn read_text_part(part: Part) -> Result<String, Rejection> {
+    let name = part.name().to_string();
+    String::from_utf8(read_part(part).await?).map_err(|_| {
+        reject::custom(EzBooksError::InvalidFormat(format!(
+            "`{}` is not valid UTF-8",
+            name
+        )))
+    })
+}
+
+#[instrument(skip(update, pool))]
+pub async fn handle_update(
+    id: String,
+    update: BookUpdate,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling book update request");
+
+    update.validate().map_err(|e| {
+        info!(book_id = %id, error = %e, "Rejected invalid book update");
+        reject::custom(e)
+    })?;
+
+    let mut book = book_repository::find_by_id(&pool, &id).await.map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to fetch book");
+        reject::custom(e)
+    })?;
+
+    update.apply_to(&mut book);
+    book.updated_at = current_timestamp();
+
+    book_repository::update_metadata(&pool, &book)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to update book");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::json(&book))
+}
+
+/// Re-reads the stored EPUB and answers with the book reset to the metadata it declares,
+/// dropping OpenLibrary data and edits; see `reset_to_epub_metadata`
+#[instrument(skip(pool, storage))]
+pub async fn handle_reset_metadata(
+    id: String,
+    pool: DatabasePool,
+    storage: FileStorage,
+    humanize_filename_titles: bool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling reset metadata request");
+
+    let mut book = book_repository::find_by_id(&pool, &id).await.map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to fetch book");
+        reject::custom(e)
+    })?;
+    let mut epub_metadata = parse_epub(storage.epub_path(&id)).map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to parse stored EPUB");
+        reject::custom(e)
+    })?;
+    epub_metadata.tidy_filename_title(book.original_filename.as_deref(), humanize_filename_titles);
+
+    reset_to_epub_metadata(&mut book, epub_metadata);
+    book.updated_at = current_timestamp();
+
+    book_repository::update_reset_metadata(&pool, &book)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to reset book metadata");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::json(&book))
+}
+
+/// Tags a book with a subject and answers with all of its subjects
+#[instrument(skip(pool))]
+pub async fn handle_add_subject(
+    id: String,
+    new_subject: NewSubject,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling add subject request");
+
+    new_subject.validate().map_err(|e| {
+        info!(book_id = %id, error = %e, "Rejected invalid subject");
+        reject::custom(e)
+    })?;
+    ensure_book_exists(&pool, &id).await?;
+
+    book_repository::insert_subject(&pool, &id, &new_subject.subject)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to add subject");
+            reject::custom(e)
+        })?;
+    let subjects = touch_and_list_subjects(&pool, &id).await?;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&subjects),
+        StatusCode::CREATED,
+    ))
+}
+
+/// Removes a subject given percent-encoded in the path
+#[instrument(skip(pool))]
+pub async fn handle_delete_subject(
+    id: String,
+    subject: String,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling delete subject request");
+
+    let subject = percent_decode_str(&subject).decode_utf8().map_err(|_| {
+        reject::custom(EzBooksError::InvalidFormat(
+            "subject is not valid UTF-8".to_string(),
+        ))
+    })?;
+    ensure_book_exists(&pool, &id).await?;
+
+    book_repository::delete_subject(&pool, &id, &subject)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to delete subject");
+            reject::custom(e)
+        })?;
+    touch_and_list_subjects(&pool, &id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Subjects live outside the books row, so the book is touched for `Last-Modified`
+async fn touch_and_list_subjects(pool: &DatabasePool, id: &str) -> Result<Vec<String>, Rejection> {
+    book_repository::touch(pool, id).await.map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to touch book");
+        reject::custom(e)
+    })?;
+    book_repository::find_subjects_by_book_id(pool, id)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to fetch subjects");
+            reject::custom(e)
+        })
+}
+
+/// Marks a book finished and answers with its reading progress
+#[instrument(skip(pool))]
+pub async fn handle_finish_reading(
+    id: String,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling finish reading request");
+
+    ensure_book_exists(&pool, &id).await?;
+
+    let progress = progress_repository::mark_finished(&pool, &id)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to mark book finished");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::json(&progress))
+}
+
+/// Clears a book's reading progress, finished or not, so it counts as unread again
+#[instrument(skip(pool))]
+pub async fn handle_reset_progress(
+    id: String,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling reset progress request");
+
+    ensure_book_exists(&pool, &id).await?;
+
+    progress_repository::reset(&pool, &id).await.map_err(|e| {
+        warn!(book_id = %id, error = %e, "Failed to reset reading progress");
+        reject::custom(e)
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Bookmarks of a book in reading order
+#[instrument(skip(pool))]
+pub async fn handle_list_bookmarks(
+    id: String,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling list bookmarks request");
+
+    ensure_book_exists(&pool, &id).await?;
+
+    let bookmarks = bookmark_repository::list_bookmarks(&pool, &id)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to fetch bookmarks");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::json(&bookmarks))
+}
+
+/// Saves a named position in a book and answers with the new bookmark
+#[instrument(skip(pool))]
+pub async fn handle_add_bookmark(
+    id: String,
+    new_bookmark: NewBookmark,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling add bookmark request");
+
+    new_bookmark.validate().map_err(|e| {
+        info!(book_id = %id, error = %e, "Rejected invalid bookmark");
+        reject::custom(e)
+    })?;
+    ensure_book_exists(&pool, &id).await?;
+
+    let bookmark = bookmark_repository::add_bookmark(&pool, &id, &new_bookmark)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to add bookmark");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&bookmark),
+        StatusCode::CREATED,
+    ))
+}
+
+#[instrument(skip(pool))]
+pub async fn handle_delete_bookmark(
+    id: String,
+    bookmark_id: i64,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, bookmark_id, "Handling delete bookmark request");
+
+    bookmark_repository::delete_bookmark(&pool, &id, bookmark_id)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, bookmark_id, error = %e, "Failed to delete bookmark");
+            reject::custom(e)
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Annotations of a book in reading order
+#[instrument(skip(pool))]
+pub async fn handle_list_annotations(
+    id: String,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling list annotations request");
+
+    ensure_book_exists(&pool, &id).await?;
+
+    let annotations = annotation_repository::list_annotations(&pool, &id)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to fetch annotations");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::json(&annotations))
+}
+
+/// Highlights a passage and answers with the new annotation
+#[instrument(skip(pool, new_annotation))]
+pub async fn handle_add_annotation(
+    id: String,
+    new_annotation: NewAnnotation,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, "Handling add annotation request");
+
+    new_annotation.validate().map_err(|e| {
+        info!(book_id = %id, error = %e, "Rejected invalid annotation");
+        reject::custom(e)
+    })?;
+    ensure_book_exists(&pool, &id).await?;
+
+    let annotation = annotation_repository::add_annotation(&pool, &id, &new_annotation)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, error = %e, "Failed to add annotation");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&annotation),
+        StatusCode::CREATED,
+    ))
+}
+
+/// Changes an annotation's color or note
+#[instrument(skip(pool, update))]
+pub async fn handle_update_annotation(
+    id: String,
+    annotation_id: i64,
+    update: AnnotationUpdate,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, annotation_id, "Handling update annotation request");
+
+    update.validate().map_err(|e| {
+        info!(book_id = %id, error = %e, "Rejected invalid annotation update");
+        reject::custom(e)
+    })?;
+
+    let annotation = annotation_repository::update_annotation(&pool, &id, annotation_id, update)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, annotation_id, error = %e, "Failed to update annotation");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::json(&annotation))
+}
+
+#[instrument(skip(pool))]
+pub async fn handle_delete_annotation(
+    id: String,
+    annotation_id: i64,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(book_id = %id, annotation_id, "Handling delete annotation request");
+
+    annotation_repository::delete_annotation(&pool, &id, annotation_id)
+        .await
+        .map_err(|e| {
+            warn!(book_id = %id, annotation_id, error = %e, "Failed to delete annotation");
+            reject::custom(e)
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[instrument(skip(pool))]
+pub async fn handle_list_collections(pool: DatabasePool) -> Result<impl Reply, Rejection> {
+    info!("Handling list collections request");
+
+    let collections = collection_repository::list_collections(&pool)
+        .await
+        .map_err(|e| {
+            warn!(error = %e, "Failed to fetch collections");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::json(&collections))
+}
+
+#[instrument(skip(pool))]
+pub async fn handle_create_collection(
+    new_collection: NewCollection,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!("Handling create collection request");
+
+    new_collection.validate().map_err(|e| {
+        info!(error = %e, "Rejected invalid collection");
+        reject::custom(e)
+    })?;
+
+    let collection = collection_repository::create_collection(&pool, &new_collection.name)
+        .await
+        .map_err(|e| {
+            warn!(error = %e, "Failed to create collection");
+            reject::custom(e)
+        })?;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&collection),
+        StatusCode::CREATED,
+    ))
+}
+
+/// The collection and its books in reading order
+#[instrument(skip(pool))]
+pub async fn handle_collection_detail(
+    collection_id: i64,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(collection_id, "Handling collection detail request");
+
+    let detail = collection_detail(&pool, collection_id).await?;
+    Ok(warp::reply::json(&detail))
+}
+
+#[instrument(skip(pool))]
+pub async fn handle_delete_collection(
+    collection_id: i64,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(collection_id, "Handling delete collection request");
+
+    collection_repository::delete_collection(&pool, collection_id)
+        .await
+        .map_err(|e| {
+            warn!(collection_id, error = %e, "Failed to delete collection");
+            reject::custom(e)
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Appends a book to a collection; adding one it already holds is a no-op
+#[instrument(skip(pool))]
+pub async fn handle_add_collection_book(
+    collection_id: i64,
+    entry: CollectionBook,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(collection_id, book_id = %entry.book_id, "Handling add collection book request");
+
+    collection_repository::find_collection(&pool, collection_id)
+        .await
+        .map_err(|e| {
+            warn!(collection_id, error = %e, "Failed to fetch collection");
+            reject::custom(e)
+        })?;
+    ensure_book_exists(&pool, &entry.book_id).await?;
+
+    collection_repository::add_book(&pool, collection_id, &entry.book_id)
+        .await
+        .map_err(|e| {
+            warn!(collection_id, book_id = %entry.book_id, error = %e, "Failed to add book to collection");
+            reject::custom(e)
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[instrument(skip(pool))]
+pub async fn handle_remove_collection_book(
+    collection_id: i64,
+    book_id: String,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(collection_id, book_id = %book_id, "Handling remove collection book request");
+
+    collection_repository::remove_book(&pool, collection_id, &book_id)
+        .await
+        .map_err(|e| {
+            warn!(collection_id, book_id = %book_id, error = %e, "Failed to remove book from collection");
+            reject::custom(e)
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Rearranges a collection's books and answers with the collection in its new order
+#[instrument(skip(pool, order))]
+pub async fn handle_reorder_collection(
+    collection_id: i64,
+    order: CollectionOrder,
+    pool: DatabasePool,
+) -> Result<impl Reply, Rejection> {
+    info!(
+        collection_id,
+        count = order.book_ids.len(),
+        "Handling reorder collection request"
+    );
+
+    collection_repository::find_collection(&pool, collection_id)
+        .await
+        .map_err(|e| {
+            warn!(collection_id, error = %e, "Failed to fetch collection");
+            reject::custom(e)
+        })?;
+    collection_repository::reorder_books(&pool, collection_id, &order.book_ids)
+        .await
+        .map_err(|e| {
+            info!(collection_id, error = %e, "Rejected collection order");
+            reject::custom(e)
+        })?;
+
+    let detail = collection_detail(&pool, collection_id).await?;
+    Ok(warp::reply::json(&detail))
+}
+
+async fn collection_detail(
+    pool: &DatabasePool,
+    collection_id: i64,
+) -> Result<CollectionDetail, Rejection> {
+    let collection = collection_repository::find_collection(pool, collection_id)
+        .await
+        .map_err(|e| {
+            warn!(collection_id, error = %e, "Failed to fetch collection");
+            reject::custom(e)
+        })?;
+    let books = collection_repository::find_books_in_collection(pool, collection_id)
+        .await
+        .map_err(|e| {
+            warn!(collection_id, error = %e, "Failed to fetch collection books");
+            reject::custom(e)
+        })?;
+
+    Ok(CollectionDetail { collection, books })
+}
+
+#[instrument(skip(pool, storage, content_cache))]
 pub async fn handle_delete(
     id: String,
     pool: DatabasePool,
     storage: FileStorage,
+    content_cache: ContentCache,
 ) -> Result<impl Reply, Rejection> {
     info!(book_id = %id, "Handling delete request");
 
@@ -176,6 +2036,7 @@ pub async fn handle_delete(
     // Delete files from storage
     let _ = storage.delete_epub(&id);
     let _ = storage.delete_cover(&id);
+    content_cache.invalidate(&id);
 
     Ok(warp::reply::with_status(
         warp::reply::json(&serde_json::json!({"success": true})),
@@ -0,0 +1,89 @@
+use std::convert::Infallible;
+use warp::http::header::{
+    HeaderName, HeaderValue, CONTENT_SECURITY_POLICY, REFERRER_POLICY, X_CONTENT_TYPE_OPTIONS,
+    X_FRAME_OPTIONS,
+};
+use warp::reply::Response;
+use warp::Filter;
+
+/// Scripts and styles come from `/static`; the reader inlines small images as data URIs
+/// and the gallery colours placeholders with `style` attributes
+pub const DEFAULT_CONTENT_SECURITY_POLICY: &str = "default-src 'self'; img-src 'self' data:; style-src 'self' 'unsafe-inline'; script-src 'self'; object-src 'none'; base-uri 'self'; frame-ancestors 'none'";
+
+/// Adds the security headers to every response of `filter`, keeping any a handler set
+/// itself. No `Content-Security-Policy` is sent when `content_security_policy` is `None`.
+pub fn with_security_headers<F>(
+    filter: F,
+    content_security_policy: Option<String>,
+) -> impl Filter<Extract = (Response,), Error = Infallible> + Clone
+where
+    F: Filter<Extract = (Response,), Error = Infallible> + Clone + Send + Sync + 'static,
+{
+    // Config rejects policies that are not valid header values
+    let policy = content_security_policy.and_then(|policy| HeaderValue::from_str(&policy).ok());
+    filter.map(move |mut response: Response| {
+        let mut headers = vec![
+            (X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+            (X_FRAME_OPTIONS, HeaderValue::from_static("DENY")),
+            (REFERRER_POLICY, HeaderValue::from_static("same-origin")),
+        ];
+        if let Some(policy) = &policy {
+            headers.push((CONTENT_SECURITY_POLICY, policy.clone()));
+        }
+        insert_missing(&mut response, headers);
+        response
+    })
+}
+
+fn insert_missing(response: &mut Response, headers: Vec<(HeaderName, HeaderValue)>) {
+    for (name, value) in headers {
+        response.headers_mut().entry(name).or_insert(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Reply;
+
+    fn page() -> impl Filter<Extract = (Response,), Error = Infallible> + Clone {
+        warp::any().map(|| warp::reply::html("<p>page</p>").into_response())
+    }
+
+    #[tokio::test]
+    async fn should_add_security_headers_with_configured_policy() {
+        // Given: A route wrapped with a custom policy
+        let filter = with_security_headers(page(), Some("default-src 'none'".to_string()));
+
+        // When: Requesting it
+        let response = warp::test::request().reply(&filter).await;
+
+        // Then: Every header is attached, with the configured policy
+        let headers = response.headers();
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+        assert_eq!(headers["x-frame-options"], "DENY");
+        assert_eq!(headers["referrer-policy"], "same-origin");
+        assert_eq!(headers["content-security-policy"], "default-src 'none'");
+    }
+
+    #[tokio::test]
+    async fn should_omit_policy_when_disabled_and_keep_handler_headers() {
+        // Given: A route that sets its own frame option, wrapped without a policy
+        let own = warp::any().map(|| {
+            let mut response = warp::reply::html("<p>embed</p>").into_response();
+            response
+                .headers_mut()
+                .insert(X_FRAME_OPTIONS, HeaderValue::from_static("SAMEORIGIN"));
+            response
+        });
+        let filter = with_security_headers(own, None);
+
+        // When: Requesting it
+        let response = warp::test::request().reply(&filter).await;
+
+        // Then: The handler's value wins and no policy is sent
+        assert_eq!(response.headers()["x-frame-options"], "SAMEORIGIN");
+        assert!(response.headers().get("content-security-policy").is_none());
+        assert_eq!(response.headers()["x-content-type-options"], "nosniff");
+    }
+}
@@ -0,0 +1,60 @@
+use crate::book_repository;
+use crate::database_connection::DatabasePool;
+use crate::error::Result;
+use crate::file_storage::FileStorage;
+use tracing::{info, instrument, warn};
+
+/// Moves files from the flat layout into shard folders and updates the stored paths
+#[instrument(skip(pool, storage))]
+pub async fn migrate_to_sharded_layout(
+    pool: &DatabasePool,
+    storage: &FileStorage,
+) -> Result<usize> {
+    let moved = storage.shard_flat_files()?;
+
+    for book_id in &moved {
+        let epub_path = storage.epub_path(book_id).to_string_lossy().to_string();
+        let cover_path = storage.cover_path(book_id).to_string_lossy().to_string();
+
+        if !book_repository::update_storage_paths(pool, book_id, &epub_path, &cover_path).await? {
+            warn!(book_id = %book_id, "Moved files for a book that is not in the database");
+        }
+    }
+
+    info!(count = moved.len(), "Storage layout migration completed");
+    Ok(moved.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book_model::Book;
+    use crate::test_fixtures::setup_test_db;
+
+    #[tokio::test]
+    async fn should_move_files_and_update_book_paths() {
+        // Given: A book stored with the flat layout
+        let (pool, temp_dir) = setup_test_db().await;
+        let flat = FileStorage::new(temp_dir.path().join("data")).unwrap();
+
+        let mut book = Book::new("Flat".to_string(), String::new());
+        book.epub_file_path = flat.save_epub(&book.id, b"epub").unwrap();
+        book.cover_image_path = Some(flat.save_cover(&book.id, b"cover").unwrap());
+        book_repository::insert(&pool, &book).await.unwrap();
+
+        // When: Migrating to the sharded layout
+        let sharded = flat.with_sharding(true);
+        let moved = migrate_to_sharded_layout(&pool, &sharded).await.unwrap();
+
+        // Then: The files and the stored paths should point at the shard
+        assert_eq!(moved, 1);
+        let found = book_repository::find_by_id(&pool, &book.id).await.unwrap();
+        let shard = &book.id[..2];
+        assert!(found.epub_file_path.contains(&format!("books/{}/", shard)));
+        assert!(found
+            .cover_image_path
+            .unwrap()
+            .contains(&format!("covers/{}/", shard)));
+        assert_eq!(sharded.read_epub(&book.id).unwrap(), b"epub");
+    }
+}
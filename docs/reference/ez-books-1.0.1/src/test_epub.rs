@@ -0,0 +1,269 @@
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+pub struct TestEpub {
+    title: String,
+    author: Option<String>,
+    identifier: Option<String>,
+    language: Option<String>,
+    /// `version` attribute of the generated package document
+    package_version: String,
+    chapters: Vec<String>,
+    /// NCX table of contents labels of the first chapters, in order
+    toc: Vec<String>,
+    /// (path relative to OEBPS/, media type, bytes); images are not declared as the cover
+    resources: Vec<(String, String, Vec<u8>)>,
+    /// (algorithm URI, encrypted path) entries for `META-INF/encryption.xml`
+    encrypted: Vec<(String, String)>,
+    /// Replaces the generated `content.opf`, for malformed package documents
+    raw_package: Option<String>,
+}
+
+impl TestEpub {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            author: None,
+            identifier: None,
+            language: Some("en".to_string()),
+            package_version: "2.0".to_string(),
+            chapters: vec!["<p>Once upon a time.</p>".to_string()],
+            toc: Vec::new(),
+            resources: Vec::new(),
+            encrypted: Vec::new(),
+            raw_package: None,
+        }
+    }
+
+    pub fn author(mut self, author: &str) -> Self {
+        self.author = Some(author.to_string());
+        self
+    }
+
+    pub fn isbn(mut self, isbn: &str) -> Self {
+        self.identifier = Some(isbn.to_string());
+        self
+    }
+
+    pub fn without_language(mut self) -> Self {
+        self.language = None;
+        self
+    }
+
+    pub fn package_version(mut self, version: &str) -> Self {
+        self.package_version = version.to_string();
+        self
+    }
+
+    pub fn chapters(mut self, bodies: &[&str]) -> Self {
+        self.chapters = bodies.iter().map(|body| body.to_string()).collect();
+        self
+    }
+
+    /// Lists the first chapters in an NCX table of contents under `labels`
+    pub fn toc(mut self, labels: &[&str]) -> Self {
+        self.toc = labels.iter().map(|label| label.to_string()).collect();
+        self
+    }
+
+    pub fn image(self, href: &str, data: Vec<u8>) -> Self {
+        let media_type = mime_guess::from_path(href)
+            .first_or_octet_stream()
+            .to_string();
+        self.resource(href, &media_type, data)
+    }
+
+    /// Adds a manifest item outside the spine, e.g. a media overlay
+    pub fn resource(mut self, href: &str, media_type: &str, data: Vec<u8>) -> Self {
+        self.resources
+            .push((href.to_string(), media_type.to_string(), data));
+        self
+    }
+
+    /// Declares `path` as encrypted with `algorithm`; the content itself is left as is
+    pub fn encryption(mut self, algorithm: &str, path: &str) -> Self {
+        self.encrypted
+            .push((algorithm.to_string(), path.to_string()));
+        self
+    }
+
+    /// Uses `opf` verbatim as the package document; chapters are still written
+    pub fn raw_package_document(mut self, opf: &str) -> Self {
+        self.raw_package = Some(opf.to_string());
+        self
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+        zip.start_file("mimetype", stored).unwrap();
+        zip.write_all(b"application/epub+zip").unwrap();
+
+        zip.start_file("META-INF/container.xml", stored).unwrap();
+        zip.write_all(
+            br#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#,
+        )
+        .unwrap();
+
+        if !self.encrypted.is_empty() {
+            zip.start_file("META-INF/encryption.xml", stored).unwrap();
+            zip.write_all(self.encryption_document().as_bytes())
+                .unwrap();
+        }
+
+        zip.start_file("OEBPS/content.opf", stored).unwrap();
+        let package = match &self.raw_package {
+            Some(opf) => opf.clone(),
+            None => self.package_document(),
+        };
+        zip.write_all(package.as_bytes()).unwrap();
+
+        for (index, body) in self.chapters.iter().enumerate() {
+            zip.start_file(format!("OEBPS/chapter{}.xhtml", index + 1), stored)
+                .unwrap();
+            zip.write_all(
+                format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml"><head><title>Chapter {}</title></head><body>{}</body></html>"#,
+                    index + 1,
+                    body
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+        }
+
+        for (href, _, data) in &self.resources {
+            zip.start_file(format!("OEBPS/{}", href), stored).unwrap();
+            zip.write_all(data).unwrap();
+        }
+
+        if !self.toc.is_empty() {
+            zip.start_file("OEBPS/toc.ncx", stored).unwrap();
+            zip.write_all(self.ncx_document().as_bytes()).unwrap();
+        }
+
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn encryption_document(&self) -> String {
+        let entries: String = self
+            .encrypted
+            .iter()
+            .map(|(algorithm, path)| {
+                format!(
+                    r#"<enc:EncryptedData><enc:EncryptionMethod Algorithm="{}"/><enc:CipherData><enc:CipherReference URI="{}"/></enc:CipherData></enc:EncryptedData>"#,
+                    algorithm, path
+                )
+            })
+            .collect();
+        format!(
+            r#"<?xml version="1.0"?>
+<encryption xmlns="urn:oasis:names:tc:opendocument:xmlns:container" xmlns:enc="http://www.w3.org/2001/04/xmlenc#">{}</encryption>"#,
+            entries
+        )
+    }
+
+    fn ncx_document(&self) -> String {
+        let points: String = self
+            .toc
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                format!(
+                    r#"<navPoint id="nav{0}" playOrder="{0}"><navLabel><text>{1}</text></navLabel><content src="chapter{0}.xhtml"/></navPoint>"#,
+                    i + 1,
+                    label
+                )
+            })
+            .collect();
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1"><docTitle><text>{}</text></docTitle><navMap>{}</navMap></ncx>"#,
+            self.title, points
+        )
+    }
+
+    fn package_document(&self) -> String {
+        let author = self
+            .author
+            .as_ref()
+            .map(|a| format!("<dc:creator>{}</dc:creator>", a))
+            .unwrap_or_default();
+        let identifier = self
+            .identifier
+            .as_ref()
+            .map(|i| format!(r#"<dc:identifier id="bookid">{}</dc:identifier>"#, i))
+            .unwrap_or_else(|| {
+                r#"<dc:identifier id="bookid">urn:uuid:test-book</dc:identifier>"#.to_string()
+            });
+        let language = self
+            .language
+            .as_ref()
+            .map(|l| format!("<dc:language>{}</dc:language>", l))
+            .unwrap_or_default();
+        let manifest: String = (1..=self.chapters.len())
+            .map(|i| {
+                format!(
+                    r#"<item id="chapter{0}" href="chapter{0}.xhtml" media-type="application/xhtml+xml"/>"#,
+                    i
+                )
+            })
+            .chain(
+                self.resources
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (href, media_type, _))| {
+                        format!(
+                            r#"<item id="resource{}" href="{}" media-type="{}"/>"#,
+                            i + 1,
+                            href,
+                            media_type
+                        )
+                    }),
+            )
+            .collect();
+        let manifest = if self.toc.is_empty() {
+            manifest
+        } else {
+            manifest + r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>"#
+        };
+        let spine: String = (1..=self.chapters.len())
+            .map(|i| format!(r#"<itemref idref="chapter{}"/>"#, i))
+            .collect();
+        let spine_attributes = if self.toc.is_empty() {
+            ""
+        } else {
+            r#" toc="ncx""#
+        };
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="{}" unique-identifier="bookid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>{}</dc:title>
+    {}
+    {}
+    {}
+  </metadata>
+  <manifest>{}</manifest>
+  <spine{}>{}</spine>
+</package>"#,
+            self.package_version,
+            self.title,
+            author,
+            identifier,
+            language,
+            manifest,
+            spine_attributes,
+            spine
+        )
+    }
+}
@@ -0,0 +1,36 @@
+use crate::database_connection::{create_pool, run_migrations, DatabasePool, PoolSettings};
+use crate::enrichment_queue::EnrichmentQueue;
+use crate::file_storage::FileStorage;
+use crate::folder_import::FolderImporter;
+use crate::openlibrary_client::OpenLibraryClient;
+use crate::upload_handler::UploadSettings;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// An empty, migrated database in a temporary directory that must outlive the pool
+pub async fn setup_test_db() -> (DatabasePool, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let database_url = format!("sqlite://{}", temp_dir.path().join("test.db").display());
+    let pool = create_pool(&database_url, PoolSettings::default())
+        .await
+        .unwrap();
+    run_migrations(&pool).await.unwrap();
+    (pool, temp_dir)
+}
+
+/// An importer into an empty library with its files under `data`, whose queue never
+/// reaches OpenLibrary
+pub async fn setup_folder_importer() -> (FolderImporter, DatabasePool, TempDir) {
+    let (pool, temp_dir) = setup_test_db().await;
+    let storage = FileStorage::new(temp_dir.path().join("data")).unwrap();
+    let client = OpenLibraryClient::with_base_url("http://127.0.0.1:9").unwrap();
+    let queue = EnrichmentQueue::start(pool.clone(), client, 10, Vec::new());
+    let importer = FolderImporter::new(
+        pool.clone(),
+        storage,
+        queue,
+        UploadSettings::for_tests(),
+        Duration::from_millis(10),
+    );
+    (importer, pool, temp_dir)
+}
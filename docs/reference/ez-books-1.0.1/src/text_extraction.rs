@@ -0,0 +1,278 @@
+use crate::chapter_encoding::current_chapter_str;
+use crate::error::{EzBooksError, Result};
+use epub::doc::EpubDoc;
+use regex::Regex;
+use std::path::Path;
+use tracing::{info, instrument, warn};
+
+/// Converts XHTML chapter content to plain text.
+///
+/// Block elements become paragraph breaks (a blank line), `<br>` a single newline,
+/// and `<head>`, `<script>` and `<style>` are dropped entirely. Common entities are decoded.
+pub fn strip_tags(html: &str) -> String {
+    let text = replace_all(
+        r"(?is)<head\b.*?</head>|<script\b.*?</script>|<style\b.*?</style>",
+        html,
+        "",
+    );
+    // Source line breaks are just whitespace; only markup decides where lines end
+    let text = replace_all(r"\s+", &text, " ");
+    let text = replace_all(r"(?i)<br\b[^>]*>", &text, "\n");
+    let text = replace_all(
+        r"(?i)</?(?:p|div|h[1-6]|li|ul|ol|blockquote|section|article|pre|tr|table|hr)\b[^>]*>",
+        &text,
+        "\n\n",
+    );
+    let text = replace_all(r"(?s)<[^>]*>", &text, "");
+    let text = decode_entities(&text);
+
+    text.split("\n\n")
+        .map(|paragraph| {
+            paragraph
+                .lines()
+                .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Removes soft hyphens, which show up as stray hyphens once chapters are concatenated.
+///
+/// With `rejoin_split_words`, words hyphenated across a line break (a newline or `<br>`)
+/// are joined again, but only when the continuation starts lowercase: "exam-\nple" becomes
+/// "example" while "Anglo-\nSaxon" is left alone. Works on XHTML and plain text.
+pub fn clean_hyphenation(text: &str, rejoin_split_words: bool) -> String {
+    let text = replace_all(r"\u{AD}|(?i)&(?:shy|#173|#x0*ad);", text, "");
+    if !rejoin_split_words {
+        return text;
+    }
+    replace_all(
+        r"(\p{L})-[^\S\n]*(?:(?i)<br\b[^>]*>|\n)\s*(\p{Ll})",
+        &text,
+        "$1$2",
+    )
+}
+
+/// Plain text of every spine item in reading order, skipping items without text
+pub fn extract_chapter_texts(
+    epub_path: impl AsRef<Path>,
+    rejoin_hyphenated_words: bool,
+) -> Result<Vec<String>> {
+    let mut chapters = Vec::new();
+    for_each_chapter_text(epub_path, rejoin_hyphenated_words, |_, text| {
+        chapters.push(text)
+    })?;
+    Ok(chapters)
+}
+
+/// Passes the spine index and plain text of each spine item with text to `visit`, in
+/// reading order, so only one chapter is held at a time. Returns how many were visited.
+#[instrument(skip_all, fields(path = %epub_path.as_ref().display()))]
+pub fn for_each_chapter_text(
+    epub_path: impl AsRef<Path>,
+    rejoin_hyphenated_words: bool,
+    mut visit: impl FnMut(usize, String),
+) -> Result<usize> {
+    let path = epub_path.as_ref();
+    let mut doc = EpubDoc::new(path).map_err(|e| {
+        warn!(path = %path.display(), error = %e, "Failed to open EPUB for text extraction");
+        EzBooksError::EpubParse(format!("Failed to open EPUB: {}", e))
+    })?;
+
+    let mut visited = 0;
+    for i in 0..doc.spine.len() {
+        doc.set_current_chapter(i);
+        match current_chapter_str(&mut doc) {
+            Some(content) => {
+                let text = strip_tags(&clean_hyphenation(&content, rejoin_hyphenated_words));
+                if !text.is_empty() {
+                    visit(i, text);
+                    visited += 1;
+                }
+            }
+            None => warn!(chapter = i, "Failed to read chapter"),
+        }
+    }
+
+    info!(chapters = visited, "Text extraction completed");
+    Ok(visited)
+}
+
+/// Joins chapters into one document, each introduced by a `=== Chapter N ===` line
+pub fn format_plain_text(chapters: &[String]) -> String {
+    chapters
+        .iter()
+        .enumerate()
+        .map(|(i, text)| format!("{}\n\n{}\n", chapter_marker(i + 1), text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Boundary line for the 1-based chapter `number`
+pub fn chapter_marker(number: usize) -> String {
+    format!("=== Chapter {} ===", number)
+}
+
+fn replace_all(pattern: &str, text: &str, replacement: &str) -> String {
+    match Regex::new(pattern) {
+        Ok(regex) => regex.replace_all(text, replacement).into_owned(),
+        Err(_) => text.to_string(),
+    }
+}
+
+/// Decodes named XML entities plus `&nbsp;` and numeric references
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end]).map(|c| (c, end)));
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Character of an entity given without `&` and `;`, e.g. `amp` or `#x263A`
+pub fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_keep_paragraph_breaks_and_drop_markup() {
+        // Given: A chapter with head, headings, paragraphs and inline markup
+        let html = r#"<html><head><title>Skip me</title><style>p{}</style></head>
+<body><h1>Chapter 1</h1><p>It was a <em>dark</em>
+   and stormy night.</p><p>Line one<br/>line two</p><script>alert(1)</script></body></html>"#;
+
+        // When: Stripping tags
+        let text = strip_tags(html);
+
+        // Then: Only readable text remains, with paragraphs separated by blank lines
+        assert_eq!(
+            text,
+            "Chapter 1\n\nIt was a dark and stormy night.\n\nLine one\nline two"
+        );
+    }
+
+    #[test]
+    fn should_decode_entities() {
+        // Given/When/Then: Named and numeric entities are decoded, stray ampersands kept
+        assert_eq!(
+            strip_tags("<p>Tom &amp; Jerry &#8212; &#x263A; &lt;3 &nbsp;R&D</p>"),
+            "Tom & Jerry \u{2014} \u{263A} <3 R&D"
+        );
+    }
+
+    #[test]
+    fn should_remove_soft_hyphens() {
+        // Given: Text with literal and escaped soft hyphens
+        let text = "Ex\u{AD}tra\u{AD}or\u{AD}di&shy;nary &#173;and &#xAD;long";
+
+        // When: Cleaning without rejoining
+        let cleaned = clean_hyphenation(text, false);
+
+        // Then: Every soft hyphen is gone
+        assert_eq!(cleaned, "Extraordinary and long");
+    }
+
+    #[test]
+    fn should_rejoin_words_split_before_lowercase_only() {
+        // Given: Line-break hyphenation in markup and text, next to real compounds
+        let html = "<p>An exam-\n  ple, a sen-<br/>tence and Anglo-\nSaxon well-known prose-</p>";
+
+        // When: Cleaning with and without rejoining
+        let rejoined = clean_hyphenation(html, true);
+        let kept = clean_hyphenation(html, false);
+
+        // Then: Only lowercase continuations are joined; in-line hyphens stay
+        assert_eq!(
+            rejoined,
+            "<p>An example, a sentence and Anglo-\nSaxon well-known prose-</p>"
+        );
+        assert_eq!(kept, html);
+    }
+
+    #[test]
+    fn should_clean_hyphenation_in_extracted_text() {
+        // Given: An EPUB whose chapter was hyphenated for print
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        let epub = crate::test_epub::TestEpub::new("Hyphenated")
+            .chapters(&["<p>Un\u{AD}der the moun-\ntain</p>"])
+            .build();
+        std::fs::write(&path, epub).unwrap();
+
+        // When: Extracting with and without rejoining split words
+        let rejoined = extract_chapter_texts(&path, true).unwrap();
+        let kept = extract_chapter_texts(&path, false).unwrap();
+
+        // Then: Soft hyphens are always dropped; the split word only when asked
+        assert_eq!(rejoined, vec!["Under the mountain"]);
+        assert_eq!(kept, vec!["Under the moun- tain"]);
+    }
+
+    #[test]
+    fn should_extract_chapters_and_mark_boundaries() {
+        // Given: An EPUB with an empty cover page and two text chapters
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        let epub = crate::test_epub::TestEpub::new("Chapters")
+            .chapters(&[
+                "<div><img src=\"c.jpg\"/></div>",
+                "<p>One.</p><p>Two.</p>",
+                "<p>Three.</p>",
+            ])
+            .build();
+        std::fs::write(&path, epub).unwrap();
+
+        // When: Extracting and formatting the text
+        let chapters = extract_chapter_texts(&path, false).unwrap();
+        let text = format_plain_text(&chapters);
+
+        // Then: Only text chapters remain, numbered in reading order
+        assert_eq!(chapters, vec!["One.\n\nTwo.", "Three."]);
+        assert_eq!(
+            text,
+            "=== Chapter 1 ===\n\nOne.\n\nTwo.\n\n=== Chapter 2 ===\n\nThree.\n"
+        );
+    }
+}
@@ -0,0 +1,179 @@
+/// Languages the pages are available in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    En,
+    Ko,
+}
+
+/// A translatable piece of page text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiText {
+    LibraryTitle,
+    Library,
+    BackToLibrary,
+    UploadEpub,
+    NoBooksYet,
+    UploadFirstEpub,
+    /// `{author}` is replaced by the author's name
+    NoBooksBy,
+    UnknownAuthor,
+    Read,
+    Delete,
+    Download,
+    PagedView,
+    ScrollView,
+    PreviousPage,
+    NextPage,
+    PreviousBook,
+    NextBook,
+    /// `{count}` is replaced by the number of bookmarks
+    Bookmarks,
+    /// `{count}` is replaced by the number of chapters left
+    LoadRemainingChapters,
+    Publisher,
+    Published,
+    Language,
+    Pages,
+    Format,
+    ReadOnArchive,
+    Finished,
+}
+
+impl Lang {
+    /// Language tag for the `lang` attribute of `<html>`
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::Ko => "ko",
+        }
+    }
+
+    /// The language of a tag such as `ko` or `ko-KR`, by its primary subtag
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next().unwrap_or_default();
+        [Lang::En, Lang::Ko]
+            .into_iter()
+            .find(|lang| lang.code().eq_ignore_ascii_case(primary))
+    }
+
+    /// `param` when it names a supported language, else the `Accept-Language` entry with
+    /// the highest weight that does, else English
+    pub fn negotiate(param: Option<&str>, accept_language: Option<&str>) -> Self {
+        if let Some(lang) = param.and_then(Lang::from_tag) {
+            return lang;
+        }
+        let mut accepted: Vec<(f32, Lang)> = accept_language
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let lang = Lang::from_tag(parts.next()?)?;
+                let weight = parts
+                    .find_map(|part| part.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse().ok())?;
+                (weight > 0.0).then_some((weight, lang))
+            })
+            .collect();
+        // Stable, so equally weighted languages keep the header's order
+        accepted.sort_by(|a, b| b.0.total_cmp(&a.0));
+        accepted.first().map_or(Lang::default(), |(_, lang)| *lang)
+    }
+
+    pub fn text(self, key: UiText) -> &'static str {
+        match self {
+            Lang::En => english(key),
+            Lang::Ko => korean(key),
+        }
+    }
+}
+
+fn english(key: UiText) -> &'static str {
+    match key {
+        UiText::LibraryTitle => "EZ-Books Library",
+        UiText::Library => "Library",
+        UiText::BackToLibrary => "Back to Library",
+        UiText::UploadEpub => "Upload EPUB",
+        UiText::NoBooksYet => "No books yet",
+        UiText::UploadFirstEpub => "Upload your first EPUB to get started!",
+        UiText::NoBooksBy => "No books by {author}",
+        UiText::UnknownAuthor => "Unknown Author",
+        UiText::Read => "Read",
+        UiText::Delete => "Delete",
+        UiText::Download => "Download",
+        UiText::PagedView => "Paged view",
+        UiText::ScrollView => "Scroll view",
+        UiText::PreviousPage => "Previous page",
+        UiText::NextPage => "Next page",
+        UiText::PreviousBook => "Previous book",
+        UiText::NextBook => "Next book",
+        UiText::Bookmarks => "Bookmarks ({count})",
+        UiText::LoadRemainingChapters => "Load remaining chapters ({count} more)",
+        UiText::Publisher => "Publisher",
+        UiText::Published => "Published",
+        UiText::Language => "Language",
+        UiText::Pages => "Pages",
+        UiText::Format => "Format",
+        UiText::ReadOnArchive => "Available to read on Internet Archive",
+        UiText::Finished => "Finished",
+    }
+}
+
+fn korean(key: UiText) -> &'static str {
+    match key {
+        UiText::LibraryTitle => "EZ-Books 서재",
+        UiText::Library => "서재",
+        UiText::BackToLibrary => "서재로 돌아가기",
+        UiText::UploadEpub => "EPUB 올리기",
+        UiText::NoBooksYet => "아직 책이 없습니다",
+        UiText::UploadFirstEpub => "첫 EPUB을 올려 시작해 보세요!",
+        UiText::NoBooksBy => "{author}의 책이 없습니다",
+        UiText::UnknownAuthor => "작자 미상",
+        UiText::Read => "읽기",
+        UiText::Delete => "삭제",
+        UiText::Download => "내려받기",
+        UiText::PagedView => "페이지 보기",
+        UiText::ScrollView => "스크롤 보기",
+        UiText::PreviousPage => "이전 페이지",
+        UiText::NextPage => "다음 페이지",
+        UiText::PreviousBook => "이전 책",
+        UiText::NextBook => "다음 책",
+        UiText::Bookmarks => "책갈피 ({count})",
+        UiText::LoadRemainingChapters => "남은 장 불러오기 ({count}개 더)",
+        UiText::Publisher => "출판사",
+        UiText::Published => "출간일",
+        UiText::Language => "언어",
+        UiText::Pages => "쪽수",
+        UiText::Format => "형식",
+        UiText::ReadOnArchive => "인터넷 아카이브에서 읽기",
+        UiText::Finished => "완독",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_prefer_param_then_weighted_accept_language() {
+        // Given/When/Then: The parameter wins, then the heaviest supported header entry
+        assert_eq!(Lang::negotiate(Some("ko"), Some("en-US")), Lang::Ko);
+        assert_eq!(
+            Lang::negotiate(None, Some("fr-FR, en;q=0.5, ko-KR;q=0.8")),
+            Lang::Ko
+        );
+        assert_eq!(Lang::negotiate(Some("xx"), Some("ko")), Lang::Ko);
+        assert_eq!(Lang::negotiate(None, Some("en, ko")), Lang::En);
+        assert_eq!(Lang::negotiate(None, Some("ko;q=0")), Lang::En);
+    }
+
+    #[test]
+    fn should_fall_back_to_english_for_unknown_locales() {
+        // Given/When/Then: Unsupported, malformed or missing languages give English
+        assert_eq!(Lang::negotiate(None, Some("de-DE, fr;q=0.9")), Lang::En);
+        assert_eq!(Lang::negotiate(None, Some("ko;q=high")), Lang::En);
+        assert_eq!(Lang::negotiate(None, None), Lang::En);
+        assert_eq!(Lang::Ko.text(UiText::Read), "읽기");
+        assert_eq!(Lang::En.text(UiText::Read), "Read");
+    }
+}
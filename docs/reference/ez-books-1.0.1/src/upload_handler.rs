@@ -1,68 +1,335 @@
-use crate::book_identifier::identify_and_enrich;
+use crate::book_identifier::book_from_epub_metadata;
+use crate::book_model::{current_timestamp, Book, EnrichmentStatus};
 use crate::book_repository;
+use crate::book_update::{MAX_NAME_CHARS, MAX_TITLE_CHARS};
+use crate::config::Config;
+use crate::content_hash::{content_hash, content_hash_file};
+use crate::content_index::index_in_background;
 use crate::database_connection::DatabasePool;
-use crate::epub_cover_extractor::extract_cover;
+use crate::enrichment_queue::EnrichmentQueue;
+use crate::epub_cover_extractor::{extract_cover, sniff_cover_mime, CoverFit, ExtractedCover};
 use crate::epub_parser::parse_epub;
-use crate::error::Result;
+use crate::error::{EzBooksError, FieldErrors, Result};
 use crate::file_storage::FileStorage;
-use crate::openlibrary_client::OpenLibraryClient;
+use crate::isbn::{has_valid_checksum, isbn10_to_isbn13, normalize_isbn};
+use crate::metadata_completeness::has_real_title;
+use flate2::read::GzDecoder;
 use serde::Serialize;
-use std::path::PathBuf;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tracing::{info, instrument, warn};
+use uuid::Uuid;
 
 #[derive(Debug, Serialize)]
 pub struct UploadResponse {
     pub id: String,
     pub title: String,
     pub author: Option<String>,
+    pub enrichment_status: EnrichmentStatus,
 }
 
-#[instrument(skip(file_data, pool, storage, ol_client))]
+/// Largest accepted upload body, and the largest a gzipped upload may decompress to
+pub const MAX_UPLOAD_BYTES: u64 = 52_428_800; // 50MB
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// EPUBs are ZIP archives, which start with a local file header
+const ZIP_MAGIC: [u8; 4] = *b"PK\x03\x04";
+
+/// A file an upload is received into, removed when dropped so failed, rejected and
+/// abandoned uploads leave nothing behind
+#[derive(Debug)]
+pub struct TempUpload {
+    path: PathBuf,
+}
+
+impl TempUpload {
+    /// A new empty file under a unique name in the system temp directory
+    pub fn create() -> Result<(Self, File)> {
+        let path = std::env::temp_dir().join(format!("ez-books-upload-{}.epub", Uuid::new_v4()));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok((Self { path }, file))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempUpload {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                warn!(path = %self.path.display(), error = %e, "Failed to clean up temp file");
+            }
+        }
+    }
+}
+
+/// Extensions accepted until parsers for other formats exist
+pub const DEFAULT_UPLOAD_EXTENSIONS: &[&str] = &["epub"];
+
+/// Upload behaviour taken from the configuration
+#[derive(Debug, Clone)]
+pub struct UploadSettings {
+    /// How long a client may take to send the whole upload body
+    pub timeout: Duration,
+    /// Refuse uploads whose ISBN is already in the library
+    pub reject_duplicate_isbn: bool,
+    /// How long a repeated `Idempotency-Key` replays the original response
+    pub idempotency_ttl: Duration,
+    /// Lowercase file extensions without the dot, checked by `validate_extension`
+    pub allowed_extensions: Vec<String>,
+    /// JPEG quality (1-100) of stored covers
+    pub cover_jpeg_quality: u8,
+    /// Covers larger than this on either side are not stored
+    pub cover_max_dimension: u32,
+    /// Covers with more pixels than this are not stored
+    pub cover_max_pixels: u64,
+    /// How stored covers are brought to the gallery's cover size
+    pub cover_fit: CoverFit,
+    /// Store covers with transparent pixels as PNG instead of JPEG
+    pub cover_keep_transparency: bool,
+    /// Add stored books to the full-text content index
+    pub index_content: bool,
+    /// Turn underscores and dashes into spaces in titles tidied from file names
+    pub humanize_filename_titles: bool,
+    /// Refuse uploads whose EPUB yields no cover instead of storing them coverless
+    pub require_cover: bool,
+}
+
+impl UploadSettings {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            timeout: Duration::from_secs(config.upload_timeout_secs),
+            reject_duplicate_isbn: config.reject_duplicate_isbn,
+            idempotency_ttl: Duration::from_secs(config.upload_idempotency_ttl_secs),
+            allowed_extensions: config.upload_allowed_extensions.clone(),
+            cover_jpeg_quality: config.cover_jpeg_quality,
+            cover_max_dimension: config.cover_max_dimension,
+            cover_max_pixels: config.cover_max_pixels,
+            cover_fit: config.cover_fit,
+            cover_keep_transparency: config.cover_keep_transparency,
+            index_content: config.content_search,
+            humanize_filename_titles: config.humanize_filename_titles,
+            require_cover: config.require_cover,
+        }
+    }
+
+    /// Defaults with every optional behaviour off, for tests to override
+    #[cfg(test)]
+    pub fn for_tests() -> Self {
+        use crate::epub_cover_extractor::{
+            DEFAULT_COVER_JPEG_QUALITY, DEFAULT_MAX_COVER_DIMENSION, DEFAULT_MAX_COVER_PIXELS,
+        };
+
+        Self {
+            timeout: Duration::from_secs(30),
+            reject_duplicate_isbn: false,
+            idempotency_ttl: Duration::from_secs(3600),
+            allowed_extensions: vec!["epub".to_string()],
+            cover_jpeg_quality: DEFAULT_COVER_JPEG_QUALITY,
+            cover_max_dimension: DEFAULT_MAX_COVER_DIMENSION,
+            cover_max_pixels: DEFAULT_MAX_COVER_PIXELS,
+            cover_fit: CoverFit::Contain,
+            cover_keep_transparency: false,
+            index_content: false,
+            humanize_filename_titles: false,
+            require_cover: false,
+        }
+    }
+}
+
+/// Metadata supplied alongside an upload, winning over what the EPUB says.
+///
+/// Blank values count as absent, since forms send empty fields.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UploadOverrides {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// ISBN-10 or ISBN-13 used for the OpenLibrary lookup instead of the EPUB's
+    pub isbn: Option<String>,
+}
+
+impl UploadOverrides {
+    /// Checks every supplied field, collecting all problems instead of stopping at the first
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        check_override_length(&mut errors, "title", &self.title, MAX_TITLE_CHARS);
+        check_override_length(&mut errors, "author", &self.author, MAX_NAME_CHARS);
+        if let Some(isbn) = non_blank(&self.isbn).map(normalize_isbn) {
+            if isbn.len() != 10 && isbn.len() != 13 {
+                errors.insert("isbn".to_string(), "must have 10 or 13 digits".to_string());
+            } else if !has_valid_checksum(&isbn) {
+                errors.insert("isbn".to_string(), "has an invalid check digit".to_string());
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(EzBooksError::Validation(errors))
+        }
+    }
+
+    /// Applies the supplied fields to `book`; call `validate` first.
+    /// A supplied ISBN replaces both EPUB ISBNs and schedules enrichment with it.
+    pub fn apply_to(&self, book: &mut Book) {
+        if let Some(title) = non_blank(&self.title) {
+            book.title = title.to_string();
+        }
+        if let Some(author) = non_blank(&self.author) {
+            book.author = Some(author.to_string());
+        }
+        if let Some(isbn) = non_blank(&self.isbn).map(normalize_isbn) {
+            if isbn.len() == 10 {
+                book.isbn_13 = isbn10_to_isbn13(&isbn);
+                book.isbn_10 = Some(isbn);
+            } else {
+                book.isbn_13 = Some(isbn);
+                book.isbn_10 = None;
+            }
+            book.enrichment_status = EnrichmentStatus::Pending;
+        }
+    }
+}
+
+fn non_blank(value: &Option<String>) -> Option<&str> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn check_override_length(
+    errors: &mut FieldErrors,
+    field: &str,
+    value: &Option<String>,
+    max_chars: usize,
+) {
+    if non_blank(value).map_or(false, |value| value.chars().count() > max_chars) {
+        errors.insert(
+            field.to_string(),
+            format!("must be at most {} characters", max_chars),
+        );
+    }
+}
+
+/// The single place deciding which uploaded file names are accepted
+pub fn validate_extension(filename: &str, allowed: &[String]) -> Result<()> {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+
+    match extension {
+        Some(extension) if allowed.contains(&extension) => Ok(()),
+        Some(extension) => {
+            warn!(filename = %filename, extension = %extension, "Rejected upload extension");
+            Err(EzBooksError::InvalidFormat(format!(
+                ".{} files are not accepted (allowed: {})",
+                extension,
+                describe_extensions(allowed)
+            )))
+        }
+        None => Err(EzBooksError::InvalidFormat(format!(
+            "file has no extension (allowed: {})",
+            describe_extensions(allowed)
+        ))),
+    }
+}
+
+fn describe_extensions(allowed: &[String]) -> String {
+    allowed
+        .iter()
+        .map(|extension| format!(".{}", extension))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Imports the EPUB at `upload`, which is left in place; the library stores a copy
+#[instrument(skip(pool, storage, enrichment_queue))]
 pub async fn process_upload(
     filename: String,
-    file_data: Vec<u8>,
+    upload: &Path,
+    overrides: UploadOverrides,
     pool: DatabasePool,
     storage: FileStorage,
-    ol_client: OpenLibraryClient,
+    enrichment_queue: EnrichmentQueue,
+    settings: UploadSettings,
 ) -> Result<UploadResponse> {
-    info!(filename = %filename, size = file_data.len(), "Processing EPUB upload");
+    info!(filename = %filename, size = fs::metadata(upload)?.len(), "Processing EPUB upload");
+    overrides.validate()?;
 
-    // Step 1: Save the EPUB file temporarily for processing
-    let temp_path = save_temp_file(&filename, &file_data)?;
+    // Step 1: Some sync tools store EPUBs gzipped; the library keeps the usable EPUB
+    let decompressed = decompress_if_gzipped(upload)?;
+    let epub_path = decompressed.as_ref().map_or(upload, TempUpload::path);
 
     // Step 2: Parse EPUB metadata
     info!("Parsing EPUB metadata");
-    let epub_metadata = parse_epub(&temp_path)?;
-    info!(title = %epub_metadata.title, "EPUB metadata parsed");
+    let mut epub_metadata = parse_epub(epub_path)?;
+    epub_metadata.tidy_filename_title(
+        original_filename(&filename).as_deref(),
+        settings.humanize_filename_titles,
+    );
+    info!(
+        title = %epub_metadata.title,
+        complete = epub_metadata.is_complete(),
+        "EPUB metadata parsed"
+    );
 
     // Step 3: Extract cover image
     info!("Extracting cover image");
-    let cover_data = extract_cover(&temp_path)?;
-
-    // Step 4: Identify and enrich with OpenLibrary
-    info!("Identifying and enriching book metadata");
-    let mut book = identify_and_enrich(&ol_client, epub_metadata, String::new()).await?;
+    let cover = extract_cover(
+        epub_path,
+        settings.cover_jpeg_quality,
+        settings.cover_max_dimension,
+        settings.cover_max_pixels,
+        settings.cover_fit,
+        settings.cover_keep_transparency,
+    )?;
+    // Enrichment only runs once the book is stored, and placeholders are drawn on
+    // request, so the EPUB is the only cover known at this point
+    if cover.is_none() && settings.require_cover {
+        warn!(filename = %filename, "Rejected upload without a cover");
+        return Err(EzBooksError::MissingCover);
+    }
 
-    // Step 5: Save EPUB and cover to permanent storage
-    let epub_path = storage.save_epub(&book.id, &file_data)?;
-    book.epub_file_path = epub_path;
+    // Step 4: Build the book from EPUB metadata and the uploader's overrides;
+    // OpenLibrary enrichment happens later
+    let subjects = epub_metadata.subjects.clone();
+    let mut book = book_from_epub_metadata(epub_metadata, String::new());
+    book.original_filename = original_filename(&filename);
+    overrides.apply_to(&mut book);
 
-    if let Some(cover_bytes) = cover_data {
-        let cover_path = storage.save_cover(&book.id, &cover_bytes)?;
-        book.cover_image_path = Some(cover_path);
+    if settings.reject_duplicate_isbn {
+        ensure_isbn_is_new(&pool, &book).await?;
     }
 
-    // Step 6: Save book to database
-    book_repository::insert(&pool, &book).await?;
+    // Step 5: Save EPUB and cover to permanent storage, then the book and its subjects
+    // in one transaction; files written for a failed upload are removed again
+    let stored = match save_book_files(&storage, &mut book, epub_path, cover.as_ref()) {
+        Ok(()) => insert_book_with_subjects(&pool, &book, &subjects).await,
+        Err(e) => Err(e),
+    };
+    drop(decompressed);
 
-    // Step 7: Save subjects if any
-    for subject in book_repository::find_subjects_by_book_id(&pool, &book.id).await? {
-        book_repository::insert_subject(&pool, &book.id, &subject).await?;
+    if let Err(e) = stored {
+        warn!(book_id = %book.id, error = %e, "Upload failed, removing stored files");
+        remove_book_files(&storage, &book.id);
+        return Err(e);
     }
 
-    // Clean up temp file
-    if let Err(e) = std::fs::remove_file(&temp_path) {
-        warn!(error = %e, "Failed to clean up temp file");
+    // Step 6: Hand off enrichment to the background queue
+    schedule_enrichment(&pool, &enrichment_queue, &mut book).await?;
+
+    if settings.index_content {
+        index_in_background(pool.clone(), book.id.clone(), book.epub_file_path.clone());
     }
 
     info!(book_id = %book.id, title = %book.title, "Upload processed successfully");
@@ -71,24 +338,308 @@ pub async fn process_upload(
         id: book.id,
         title: book.title,
         author: book.author,
+        enrichment_status: book.enrichment_status,
     })
 }
 
-fn save_temp_file(filename: &str, data: &[u8]) -> Result<PathBuf> {
-    use std::io::Write;
+/// Queues a pending book for enrichment; when the queue cannot take it, the book is
+/// marked failed instead of waiting for a job that will never run
+pub async fn schedule_enrichment(
+    pool: &DatabasePool,
+    enrichment_queue: &EnrichmentQueue,
+    book: &mut Book,
+) -> Result<()> {
+    if book.enrichment_status != EnrichmentStatus::Pending {
+        return Ok(());
+    }
+    if let Err(e) = enrichment_queue.enqueue(&book.id) {
+        warn!(book_id = %book.id, error = %e, "Could not schedule enrichment");
+        book.enrichment_status = EnrichmentStatus::Failed;
+        book_repository::update_enrichment_status(pool, &book.id, book.enrichment_status).await?;
+    }
+    Ok(())
+}
+
+/// Replaces the stored EPUB and cover of `book` with those of `upload`, keeping its id,
+/// notes, enrichment, subjects, progress and collections.
+///
+/// The new file must parse as an EPUB before anything is written. Metadata it declares
+/// replaces the book's, other fields keep their values, and its subjects are added to
+/// the book's. If the database update fails the previous files are put back. A new
+/// ISBN leaves the book pending, for the caller to hand to `schedule_enrichment`.
+#[instrument(skip(book, overrides, pool, storage, settings), fields(book_id = %book.id))]
+pub async fn process_replacement(
+    mut book: Book,
+    filename: String,
+    upload: &Path,
+    overrides: UploadOverrides,
+    pool: &DatabasePool,
+    storage: &FileStorage,
+    settings: &UploadSettings,
+) -> Result<Book> {
+    info!(filename = %filename, size = fs::metadata(upload)?.len(), "Processing EPUB replacement");
+    overrides.validate()?;
+    let decompressed = decompress_if_gzipped(upload)?;
+    let epub_path = decompressed.as_ref().map_or(upload, TempUpload::path);
+
+    let mut epub_metadata = parse_epub(epub_path)?;
+    epub_metadata.tidy_filename_title(
+        original_filename(&filename).as_deref(),
+        settings.humanize_filename_titles,
+    );
+    let cover = extract_cover(
+        epub_path,
+        settings.cover_jpeg_quality,
+        settings.cover_max_dimension,
+        settings.cover_max_pixels,
+        settings.cover_fit,
+        settings.cover_keep_transparency,
+    )?;
+    // Checked before the files are swapped, as a coverless file drops the stored cover
+    if cover.is_none() && settings.require_cover {
+        warn!(filename = %filename, "Rejected replacement without a cover");
+        return Err(EzBooksError::MissingCover);
+    }
+
+    let subjects = epub_metadata.subjects.clone();
+    let previous_isbns = (book.isbn_10.clone(), book.isbn_13.clone());
+    apply_epub_metadata(
+        &mut book,
+        book_from_epub_metadata(epub_metadata, String::new()),
+    );
+    book.original_filename = original_filename(&filename);
+    overrides.apply_to(&mut book);
+    book.updated_at = current_timestamp();
+
+    if settings.reject_duplicate_isbn {
+        ensure_isbn_is_new(pool, &book).await?;
+    }
+    // What OpenLibrary said about the old ISBN may not hold for the new one
+    if book.isbn_10 != previous_isbns.0 || book.isbn_13 != previous_isbns.1 {
+        book.enrichment_status = EnrichmentStatus::Pending;
+    }
+
+    let stored = match storage
+        .back_up_files(&book.id)
+        .and_then(|()| replace_book_files(storage, &mut book, epub_path, cover.as_ref()))
+    {
+        Ok(()) => update_book_with_subjects(pool, &book, &subjects).await,
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = stored {
+        warn!(error = %e, "Replacement failed, restoring previous files");
+        storage.restore_backup(&book.id);
+        return Err(e);
+    }
+    if let Err(e) = storage.discard_backup(&book.id) {
+        warn!(error = %e, "Failed to delete backup of replaced files");
+    }
+
+    if settings.index_content {
+        index_in_background(pool.clone(), book.id.clone(), book.epub_file_path.clone());
+    }
+
+    info!(title = %book.title, "EPUB replaced successfully");
+    Ok(book)
+}
+
+/// Takes what the new EPUB declares; fields it leaves out keep their current, possibly
+/// enriched or edited, values
+fn apply_epub_metadata(book: &mut Book, parsed: Book) {
+    if has_real_title(&parsed.title) {
+        book.title = parsed.title;
+        book.raw_title = parsed.raw_title;
+    }
+    for (field, value) in [
+        (&mut book.author, parsed.author),
+        (&mut book.isbn_10, parsed.isbn_10),
+        (&mut book.isbn_13, parsed.isbn_13),
+        (&mut book.publisher, parsed.publisher),
+    ] {
+        if value.is_some() {
+            *field = value;
+        }
+    }
+    // An opening paragraph only stands in for a description nobody declared
+    if parsed.description.is_some()
+        && (!parsed.description_from_content
+            || book.description.is_none()
+            || book.description_from_content)
+    {
+        book.description = parsed.description;
+        book.description_from_content = parsed.description_from_content;
+    }
+    if parsed.language.is_some() {
+        book.language = parsed.language;
+        book.language_detected = parsed.language_detected;
+    }
+    book.has_audio_narration = parsed.has_audio_narration;
+    book.epub_version = parsed.epub_version;
+    book.format = parsed.format;
+}
+
+fn replace_book_files(
+    storage: &FileStorage,
+    book: &mut Book,
+    epub_path: &Path,
+    cover: Option<&ExtractedCover>,
+) -> Result<()> {
+    save_book_files(storage, book, epub_path, cover)?;
+    if cover.is_none() {
+        storage.delete_cover(&book.id)?;
+        book.cover_image_path = None;
+        book.cover_hash = None;
+        book.cover_mime = None;
+        book.cover_color = None;
+    }
+    Ok(())
+}
+
+async fn update_book_with_subjects(
+    pool: &DatabasePool,
+    book: &Book,
+    subjects: &[String],
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    book_repository::update_replaced_file(&mut *tx, book).await?;
+    book_repository::insert_subjects(&mut *tx, &book.id, subjects).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Decompresses a gzipped file into a new temp file; `None` for anything else, which is
+/// used as it is.
+///
+/// Decompressed data must still look like an EPUB and fit within `MAX_UPLOAD_BYTES`.
+fn decompress_if_gzipped(path: &Path) -> Result<Option<TempUpload>> {
+    if !starts_with(path, &GZIP_MAGIC)? {
+        return Ok(None);
+    }
+
+    let compressed = File::open(path)?;
+    let (decompressed, mut output) = TempUpload::create()?;
+    let size = io::copy(
+        &mut GzDecoder::new(compressed).take(MAX_UPLOAD_BYTES + 1),
+        &mut output,
+    )
+    .map_err(|e| {
+        warn!(error = %e, "Failed to decompress gzipped upload");
+        EzBooksError::InvalidFormat(format!("gzip data could not be decompressed: {}", e))
+    })?;
+
+    if size > MAX_UPLOAD_BYTES {
+        return Err(EzBooksError::InvalidFormat(format!(
+            "gzipped file decompresses to more than {} bytes",
+            MAX_UPLOAD_BYTES
+        )));
+    }
+    if !starts_with(decompressed.path(), &ZIP_MAGIC)? {
+        return Err(EzBooksError::InvalidFormat(
+            "decompressed gzip data is not an EPUB".to_string(),
+        ));
+    }
 
-    let temp_dir = std::env::temp_dir();
-    let temp_path = temp_dir.join(filename);
+    info!(
+        compressed = fs::metadata(path)?.len(),
+        decompressed = size,
+        "Decompressed gzipped upload"
+    );
+    Ok(Some(decompressed))
+}
+
+fn starts_with(path: &Path, magic: &[u8]) -> Result<bool> {
+    let mut head = Vec::with_capacity(magic.len());
+    File::open(path)?
+        .take(magic.len() as u64)
+        .read_to_end(&mut head)?;
+    Ok(head == magic)
+}
+
+fn save_book_files(
+    storage: &FileStorage,
+    book: &mut Book,
+    epub_path: &Path,
+    cover: Option<&ExtractedCover>,
+) -> Result<()> {
+    book.epub_file_path = storage.save_epub_file(&book.id, epub_path)?;
+    book.file_size_bytes = Some(fs::metadata(epub_path)?.len() as i64);
+    book.content_hash = Some(content_hash_file(epub_path)?);
+
+    if let Some(cover) = cover {
+        book.cover_image_path = Some(storage.save_cover(&book.id, &cover.data)?);
+        book.cover_hash = Some(content_hash(&cover.data));
+        book.cover_mime = Some(sniff_cover_mime(&cover.data).to_string());
+        book.cover_color = cover.color.clone();
+    }
+    Ok(())
+}
+
+async fn insert_book_with_subjects(
+    pool: &DatabasePool,
+    book: &Book,
+    subjects: &[String],
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    book_repository::insert(&mut *tx, book).await?;
+    // EPUBs often repeat a subject in different letter cases; one copy is enough
+    book_repository::insert_subjects(&mut *tx, &book.id, subjects).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Best effort: the upload already failed, so cleanup errors are only logged
+fn remove_book_files(storage: &FileStorage, book_id: &str) {
+    if let Err(e) = storage.delete_epub(book_id) {
+        warn!(book_id = %book_id, error = %e, "Failed to remove EPUB of failed upload");
+    }
+    if let Err(e) = storage.delete_cover(book_id) {
+        warn!(book_id = %book_id, error = %e, "Failed to remove cover of failed upload");
+    }
+}
 
-    let mut file = std::fs::File::create(&temp_path)?;
-    file.write_all(data)?;
+/// Fails with `DuplicateIsbn` if another book already has one of this book's ISBNs
+async fn ensure_isbn_is_new(pool: &DatabasePool, book: &Book) -> Result<()> {
+    for isbn in [&book.isbn_13, &book.isbn_10].into_iter().flatten() {
+        if let Some(existing) = book_repository::find_by_isbn(pool, isbn, Some(&book.id)).await? {
+            warn!(isbn = %isbn, existing_id = %existing.id, "Rejecting duplicate ISBN upload");
+            return Err(EzBooksError::DuplicateIsbn {
+                isbn: isbn.clone(),
+                existing_id: existing.id,
+            });
+        }
+    }
+    Ok(())
+}
 
-    Ok(temp_path)
+/// The uploaded name without client-side directories or control characters
+fn original_filename(filename: &str) -> Option<String> {
+    let name: String = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect();
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::openlibrary_client::OpenLibraryClient;
+    use crate::test_fixtures::setup_test_db;
+    use tempfile::TempDir;
+
+    /// An empty library with its files under `data`, and a queue that never reaches OpenLibrary
+    async fn setup() -> (DatabasePool, FileStorage, EnrichmentQueue, TempDir) {
+        let (pool, temp_dir) = setup_test_db().await;
+        let storage = FileStorage::new(temp_dir.path().join("data")).unwrap();
+        let client = OpenLibraryClient::with_base_url("http://127.0.0.1:9").unwrap();
+        let queue = EnrichmentQueue::start(pool.clone(), client, 10, Vec::new());
+        (pool, storage, queue, temp_dir)
+    }
 
     #[test]
     fn should_create_upload_response() {
@@ -102,6 +653,7 @@ mod tests {
             id: id.clone(),
             title: title.clone(),
             author: author.clone(),
+            enrichment_status: EnrichmentStatus::Pending,
         };
 
         // Then: Should have correct fields
@@ -111,21 +663,36 @@ mod tests {
     }
 
     #[test]
-    fn should_save_temp_file() {
-        // Given: File data
-        let filename = "test.epub";
-        let data = b"test data";
+    fn should_keep_only_the_file_part_of_uploaded_names() {
+        // Given/When/Then: Client paths and control characters are dropped
+        assert_eq!(
+            original_filename("C:\\Books\\Dune.epub").as_deref(),
+            Some("Dune.epub")
+        );
+        assert_eq!(
+            original_filename("shelf/Dune\tMessiah.epub").as_deref(),
+            Some("DuneMessiah.epub")
+        );
+        assert_eq!(original_filename("shelf/ "), None);
+    }
 
-        // When: Saving temp file
-        let result = save_temp_file(filename, data);
+    #[test]
+    fn should_remove_temp_upload_when_dropped() {
+        // Given: Two temp uploads, one written to
+        let (first, mut file) = TempUpload::create().unwrap();
+        let (second, _) = TempUpload::create().unwrap();
+        std::io::Write::write_all(&mut file, b"test data").unwrap();
+        let path = first.path().to_path_buf();
 
-        // Then: Should succeed and file should exist
-        assert!(result.is_ok());
-        let path = result.unwrap();
-        assert!(path.exists());
+        // When: Dropping the first
+        let exists_before = path.exists();
+        drop(first);
 
-        // Cleanup
-        let _ = std::fs::remove_file(path);
+        // Then: Each has its own file, removed with its guard
+        assert_ne!(path, second.path());
+        assert!(exists_before);
+        assert!(!path.exists());
+        assert!(second.path().exists());
     }
 
     #[test]
@@ -135,6 +702,7 @@ mod tests {
             id: "123".to_string(),
             title: "Test".to_string(),
             author: Some("Author".to_string()),
+            enrichment_status: EnrichmentStatus::Pending,
         };
 
         // When: Serializing to JSON
@@ -146,5 +714,301 @@ mod tests {
         assert!(json_str.contains("\"id\":\"123\""));
         assert!(json_str.contains("\"title\":\"Test\""));
         assert!(json_str.contains("\"author\":\"Author\""));
+        assert!(json_str.contains("\"enrichment_status\":\"pending\""));
+    }
+
+    #[tokio::test]
+    async fn should_reject_isbn_already_in_library() {
+        // Given: A library containing a book with a hyphen-free ISBN
+        let (pool, _storage, _queue, _temp_dir) = setup().await;
+        let mut existing = Book::new("Existing".to_string(), "/a.epub".to_string());
+        existing.isbn_13 = Some("9780306406157".to_string());
+        book_repository::insert(&pool, &existing).await.unwrap();
+
+        // When: Checking a new book with the same ISBN written differently
+        let mut upload = Book::new("Upload".to_string(), "/b.epub".to_string());
+        upload.isbn_13 = Some("978-0-306-40615-7".to_string());
+        let result = ensure_isbn_is_new(&pool, &upload).await;
+
+        // Then: Should report the existing book
+        assert!(matches!(
+            result,
+            Err(EzBooksError::DuplicateIsbn { existing_id, .. }) if existing_id == existing.id
+        ));
+
+        // And: A book with another ISBN passes
+        upload.isbn_13 = Some("9781234567897".to_string());
+        assert!(ensure_isbn_is_new(&pool, &upload).await.is_ok());
+    }
+
+    #[test]
+    fn should_accept_only_allowed_extensions() {
+        // Given: The default allowed extensions
+        let allowed = vec!["epub".to_string()];
+
+        // When/Then: Matching is case-insensitive and the message names the offender
+        assert!(validate_extension("Book.EPUB", &allowed).is_ok());
+        let rejected = validate_extension("scan.pdf", &allowed).unwrap_err();
+        assert_eq!(
+            rejected.to_string(),
+            "Invalid file format: .pdf files are not accepted (allowed: .epub)"
+        );
+        assert!(validate_extension("README", &allowed).is_err());
+    }
+
+    #[tokio::test]
+    async fn should_remove_stored_files_when_database_insert_fails() {
+        // Given: A library whose book inserts always fail
+        let (pool, storage, queue, temp_dir) = setup().await;
+        sqlx::query(
+            "CREATE TRIGGER fail_book_insert BEFORE INSERT ON books BEGIN SELECT RAISE(ABORT, 'forced failure'); END",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let epub_path = temp_dir.path().join("orphan-test.epub");
+        let epub = crate::test_epub::TestEpub::new("Orphan")
+            .image("cover.jpg", vec![0xFF, 0xD8, 0xFF, 0xD9])
+            .build();
+        std::fs::write(&epub_path, epub).unwrap();
+
+        // When: Uploading a book
+        let result = process_upload(
+            "orphan-test.epub".to_string(),
+            &epub_path,
+            UploadOverrides::default(),
+            pool.clone(),
+            storage,
+            queue,
+            UploadSettings::for_tests(),
+        )
+        .await;
+
+        // Then: The upload fails without leaving files or rows behind
+        assert!(matches!(result, Err(EzBooksError::Database(_))));
+        assert_eq!(book_repository::count_books(&pool).await.unwrap(), 0);
+        assert!(stored_files(&temp_dir.path().join("data")).is_empty());
+    }
+
+    fn stored_files(dir: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.extend(stored_files(&path));
+            } else {
+                files.push(path);
+            }
+        }
+        files
+    }
+
+    #[tokio::test]
+    async fn should_import_gzipped_epub_like_uncompressed_one() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        // Given: The same EPUB as it is and gzipped, uploaded to separate libraries
+        let epub = crate::test_epub::TestEpub::new("Squeezed")
+            .author("Packer")
+            .build();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&epub).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let mut imported = Vec::new();
+        for (name, data) in [("plain.epub", epub.clone()), ("gzipped.epub", gzipped)] {
+            let (pool, storage, queue, temp_dir) = setup().await;
+            let upload = temp_dir.path().join(name);
+            std::fs::write(&upload, data).unwrap();
+
+            // When: Processing each upload
+            let response = process_upload(
+                name.to_string(),
+                &upload,
+                UploadOverrides::default(),
+                pool.clone(),
+                storage.clone(),
+                queue,
+                UploadSettings::for_tests(),
+            )
+            .await
+            .unwrap();
+            let book = book_repository::find_by_id(&pool, &response.id)
+                .await
+                .unwrap();
+            imported.push((book, storage.read_epub(&response.id).unwrap()));
+        }
+
+        // Then: Both store the uncompressed EPUB with the same metadata
+        let (plain, plain_bytes) = &imported[0];
+        let (gzipped, gzipped_bytes) = &imported[1];
+        assert_eq!(gzipped_bytes, &epub);
+        assert_eq!(gzipped_bytes, plain_bytes);
+        assert_eq!(gzipped.title, plain.title);
+        assert_eq!(gzipped.author, plain.author);
+        assert_eq!(gzipped.content_hash, plain.content_hash);
+        assert_eq!(gzipped.file_size_bytes, Some(epub.len() as i64));
+    }
+
+    #[tokio::test]
+    async fn should_reject_coverless_upload_only_when_covers_are_required() {
+        // Given: An EPUB without any image, and a library that requires covers
+        let (pool, storage, queue, temp_dir) = setup().await;
+        let strict = UploadSettings {
+            require_cover: true,
+            ..UploadSettings::for_tests()
+        };
+        let upload = temp_dir.path().join("plain.epub");
+        std::fs::write(&upload, crate::test_epub::TestEpub::new("Plain").build()).unwrap();
+
+        // When: Uploading it to the strict library, then with the policy off
+        let rejected = process_upload(
+            "plain.epub".to_string(),
+            &upload,
+            UploadOverrides::default(),
+            pool.clone(),
+            storage.clone(),
+            queue.clone(),
+            strict,
+        )
+        .await;
+        let rejected_count = book_repository::count_books(&pool).await.unwrap();
+        let accepted = process_upload(
+            "plain.epub".to_string(),
+            &upload,
+            UploadOverrides::default(),
+            pool.clone(),
+            storage,
+            queue,
+            UploadSettings::for_tests(),
+        )
+        .await;
+
+        // Then: Only the strict upload is refused, before anything is stored
+        assert!(matches!(rejected, Err(EzBooksError::MissingCover)));
+        assert_eq!(rejected_count, 0);
+        assert!(accepted.is_ok());
+        assert_eq!(book_repository::count_books(&pool).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_keep_stored_cover_when_required_cover_is_missing_from_replacement() {
+        // Given: A book with a stored cover, in a library that requires covers
+        let (pool, storage, _queue, temp_dir) = setup().await;
+        let mut book = Book::new("Covered".to_string(), "/covered.epub".to_string());
+        book.cover_image_path = Some(storage.save_cover(&book.id, b"cover").unwrap());
+        book_repository::insert(&pool, &book).await.unwrap();
+        let settings = UploadSettings {
+            require_cover: true,
+            ..UploadSettings::for_tests()
+        };
+        let upload = temp_dir.path().join("plain.epub");
+        std::fs::write(&upload, crate::test_epub::TestEpub::new("Plain").build()).unwrap();
+
+        // When: Replacing its file with an EPUB that has no cover
+        let result = process_replacement(
+            book.clone(),
+            "plain.epub".to_string(),
+            &upload,
+            UploadOverrides::default(),
+            &pool,
+            &storage,
+            &settings,
+        )
+        .await;
+
+        // Then: The replacement is refused and the book keeps its cover
+        assert!(matches!(result, Err(EzBooksError::MissingCover)));
+        assert_eq!(storage.read_cover(&book.id).unwrap(), b"cover");
+        let stored = book_repository::find_by_id(&pool, &book.id).await.unwrap();
+        assert_eq!(stored.title, "Covered");
+        assert_eq!(stored.cover_image_path, book.cover_image_path);
+    }
+
+    #[test]
+    fn should_reject_gzip_that_does_not_hold_an_epub() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        // Given: Gzipped plain text and a truncated gzip stream
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"just some text").unwrap();
+        let text = encoder.finish().unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let write = |name: &str, data: &[u8]| {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, data).unwrap();
+            path
+        };
+        let truncated = write("truncated.gz", &text[..text.len() / 2]);
+        let text = write("text.gz", &text);
+        let epub = write("book.epub", b"PK\x03\x04");
+
+        // When/Then: Both are invalid formats; other files are used as they are
+        assert!(matches!(
+            decompress_if_gzipped(&text),
+            Err(EzBooksError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            decompress_if_gzipped(&truncated),
+            Err(EzBooksError::InvalidFormat(_))
+        ));
+        assert!(decompress_if_gzipped(&epub).unwrap().is_none());
+    }
+
+    #[test]
+    fn should_apply_overrides_over_epub_metadata() {
+        // Given: A book parsed from an EPUB with its own title, author and ISBN
+        let mut book = Book::new("EPUB Title".to_string(), "/book.epub".to_string());
+        book.author = Some("EPUB Author".to_string());
+        book.isbn_13 = Some("9780306406157".to_string());
+        book.enrichment_status = EnrichmentStatus::Done;
+        let overrides = UploadOverrides {
+            title: Some("  Real Title ".to_string()),
+            author: Some(String::new()),
+            isbn: Some("0-306-40615-2".to_string()),
+        };
+
+        // When: Applying the uploader's overrides
+        overrides.validate().unwrap();
+        overrides.apply_to(&mut book);
+
+        // Then: Supplied values win, blank ones are ignored and the ISBN drives enrichment
+        assert_eq!(book.title, "Real Title");
+        assert_eq!(book.author, Some("EPUB Author".to_string()));
+        assert_eq!(book.isbn_10, Some("0306406152".to_string()));
+        assert_eq!(book.isbn_13, Some("9780306406157".to_string()));
+        assert_eq!(book.enrichment_status, EnrichmentStatus::Pending);
+    }
+
+    #[test]
+    fn should_reject_override_isbn_with_bad_checksum() {
+        // Given: Overrides with a mistyped ISBN and a too long title
+        let overrides = UploadOverrides {
+            title: Some("x".repeat(MAX_TITLE_CHARS + 1)),
+            author: None,
+            isbn: Some("978-0-306-40615-8".to_string()),
+        };
+
+        // When: Validating them
+        let result = overrides.validate();
+
+        // Then: Every problem is reported by field
+        match result {
+            Err(EzBooksError::Validation(errors)) => {
+                assert_eq!(errors["isbn"], "has an invalid check digit");
+                assert!(errors.contains_key("title"));
+            }
+            other => panic!("expected validation error, got {:?}", other),
+        }
+        assert!(UploadOverrides {
+            isbn: Some("12345".to_string()),
+            ..UploadOverrides::default()
+        }
+        .validate()
+        .is_err());
     }
 }
@@ -0,0 +1,123 @@
+// EZ-Books Reader: paged mode controls, loading chapters left out of capped pages and
+// jumping to bookmarks
+
+// Prefix the app is served under, set by the server when behind a reverse-proxy subpath
+const basePath = document.body.dataset.basePath || '';
+
+document.addEventListener('DOMContentLoaded', () => {
+    setupLoadMore();
+    setupPaging();
+    setupBookmarks();
+});
+
+function setupLoadMore() {
+    const control = document.querySelector('.load-more');
+    const article = document.querySelector('main article');
+    if (!control || !article) {
+        return;
+    }
+    const link = control.querySelector('a');
+    const bookId = control.dataset.bookId;
+
+    link.addEventListener('click', async (e) => {
+        e.preventDefault();
+        link.textContent = 'Loading…';
+
+        let next = control.dataset.nextChapter;
+        try {
+            while (next !== null && next !== undefined) {
+                const response = await fetch(`${basePath}/reader/${encodeURIComponent(bookId)}/chapters/${next}`);
+                if (!response.ok) {
+                    throw new Error(`HTTP ${response.status}`);
+                }
+                const anchor = `<a id="chapter-${next}" class="chapter-anchor"></a>`;
+                article.insertAdjacentHTML('beforeend', anchor + (await response.text()) + '\n<hr>\n');
+                next = response.headers.get('x-next-chapter');
+                control.dataset.nextChapter = next || '';
+            }
+            control.remove();
+        } catch (error) {
+            link.textContent = 'Loading failed, try again';
+        }
+        window.dispatchEvent(new Event('resize'));
+    });
+}
+
+function setupPaging() {
+    const main = document.querySelector('main.reader-paged');
+    const prevButton = document.querySelector('.page-prev');
+    const nextButton = document.querySelector('.page-next');
+    const indicator = document.querySelector('.page-indicator');
+    if (!main || !prevButton || !nextButton) {
+        return;
+    }
+
+    function pageWidth() {
+        return main.clientWidth;
+    }
+
+    function pageCount() {
+        return Math.max(1, Math.round(main.scrollWidth / pageWidth()));
+    }
+
+    function currentPage() {
+        return Math.round(main.scrollLeft / pageWidth()) + 1;
+    }
+
+    function updateControls() {
+        const page = currentPage();
+        const total = pageCount();
+        prevButton.disabled = page <= 1;
+        nextButton.disabled = page >= total;
+        if (indicator) {
+            indicator.textContent = `${page} / ${total}`;
+        }
+    }
+
+    function turn(direction) {
+        main.scrollBy({ left: direction * pageWidth(), behavior: 'smooth' });
+    }
+
+    prevButton.addEventListener('click', () => turn(-1));
+    nextButton.addEventListener('click', () => turn(1));
+
+    document.addEventListener('keydown', (e) => {
+        if (e.key === 'ArrowRight' || e.key === 'PageDown') {
+            e.preventDefault();
+            turn(1);
+        } else if (e.key === 'ArrowLeft' || e.key === 'PageUp') {
+            e.preventDefault();
+            turn(-1);
+        }
+    });
+
+    main.addEventListener('scroll', updateControls);
+    window.addEventListener('resize', updateControls);
+    updateControls();
+}
+
+// Scrolls to a bookmark's position within its chapter, measured up to the next chapter
+function setupBookmarks() {
+    document.querySelectorAll('.bookmarks a[data-chapter]').forEach((link) => {
+        link.addEventListener('click', (e) => {
+            const anchor = document.getElementById(`chapter-${link.dataset.chapter}`);
+            if (!anchor) {
+                return; // not on the page: follow the link to the chapter itself
+            }
+            e.preventDefault();
+            link.closest('details').open = false;
+            if (document.querySelector('main.reader-paged')) {
+                anchor.scrollIntoView();
+                return;
+            }
+
+            const anchors = Array.from(document.querySelectorAll('.chapter-anchor'));
+            const next = anchors[anchors.indexOf(anchor) + 1];
+            const article = document.querySelector('main article');
+            const top = anchor.getBoundingClientRect().top + window.scrollY;
+            const end = (next || article).getBoundingClientRect()[next ? 'top' : 'bottom'] + window.scrollY;
+            const fraction = parseFloat(link.dataset.scrollFraction) || 0;
+            window.scrollTo({ top: top + fraction * (end - top) });
+        });
+    });
+}
@@ -1,6 +1,8 @@
 // EZ-Books Upload Handler
 
 document.addEventListener('DOMContentLoaded', () => {
+    // Prefix the app is served under, set by the server when behind a reverse-proxy subpath
+    const basePath = document.body.dataset.basePath || '';
     const uploadForm = document.getElementById('upload-form');
     const fileInput = uploadForm.querySelector('input[type="file"]');
     const submitButton = uploadForm.querySelector('button[type="submit"]');
@@ -33,14 +35,13 @@ document.addEventListener('DOMContentLoaded', () => {
         showStatus('Uploading... Please wait', 'info');
 
         try {
-            const response = await fetch('/upload', {
+            const response = await fetch(`${basePath}/upload`, {
                 method: 'POST',
                 body: formData
             });
 
             if (!response.ok) {
-                const errorText = await response.text();
-                throw new Error(errorText || `Upload failed with status ${response.status}`);
+                throw new Error(await readErrorMessage(response));
             }
 
             const result = await response.json();
@@ -64,6 +65,16 @@ document.addEventListener('DOMContentLoaded', () => {
         }
     }
 
+    async function readErrorMessage(response) {
+        const fallback = `Upload failed with status ${response.status}`;
+        try {
+            const body = await response.json();
+            return body.error || fallback;
+        } catch (_) {
+            return fallback;
+        }
+    }
+
     function showStatus(message, type) {
         if (!uploadStatus) return;
 
@@ -96,7 +107,7 @@ document.addEventListener('DOMContentLoaded', () => {
             }
 
             try {
-                const response = await fetch(`/api/books/${bookId}`, {
+                const response = await fetch(`${basePath}/api/books/${bookId}`, {
                     method: 'DELETE'
                 });
 